@@ -1,9 +1,9 @@
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use ghostfs_core::{FileSystemType, XfsRecoveryConfig};
+use anyhow::{Context, Result};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use ghostfs_core::{FileSystemType, RecoveryStrategy, XfsRecoveryConfig};
 
 /// Parse user input for scan limit (e.g., "50%", "10GB", "all")
 fn parse_scan_limit(input: &str, total_blocks: u64, block_size: u32) -> Option<u64> {
@@ -44,6 +44,45 @@ fn parse_scan_limit(input: &str, total_blocks: u64, block_size: u32) -> Option<u
     None
 }
 
+/// Parse a byte count with an optional unit (e.g., "4096", "500KB", "1.5GB")
+fn parse_size(input: &str) -> Result<u64, String> {
+    let lower = input.trim().to_lowercase();
+    let (number, unit) = [
+        ("tb", 1024u64 * 1024 * 1024 * 1024),
+        ("gb", 1024u64 * 1024 * 1024),
+        ("mb", 1024u64 * 1024),
+        ("kb", 1024u64),
+        ("b", 1u64),
+    ]
+    .iter()
+    .find_map(|&(suffix, unit)| lower.strip_suffix(suffix).map(|n| (n, unit)))
+    .unwrap_or((lower.as_str(), 1));
+
+    match number.trim().parse::<f64>() {
+        Ok(size) if size >= 0.0 => Ok((size * unit as f64) as u64),
+        _ => Err(format!("invalid size '{}'", input)),
+    }
+}
+
+/// Parse an RFC 3339 timestamp (e.g., "2024-03-01T09:00:00Z") as UTC
+fn parse_rfc3339(input: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    chrono::DateTime::parse_from_rfc3339(input.trim())
+        .map(|time| time.with_timezone(&chrono::Utc))
+        .map_err(|e| format!("invalid RFC 3339 time '{}': {}", input, e))
+}
+
+/// Summary of the unreadable ranges of a partially recovered file
+fn describe_damage(damaged: &[ghostfs_core::DamagedRange]) -> String {
+    let bytes: u64 = damaged.iter().map(|range| range.length).sum();
+    match damaged.first() {
+        Some(first) => format!(
+            "partial: {} bytes unreadable from offset {}",
+            bytes, first.offset
+        ),
+        None => "partial".to_string(),
+    }
+}
+
 /// Prompt user for scan limit on large filesystems
 fn prompt_scan_limit(total_blocks: u64, block_size: u32) -> Result<Option<u64>> {
     let total_size_gb = (total_blocks * block_size as u64) as f64 / (1024.0 * 1024.0 * 1024.0);
@@ -95,18 +134,85 @@ fn prompt_scan_limit(total_blocks: u64, block_size: u32) -> Result<Option<u64>>
     about = "GhostFS CLI - Professional Data Recovery Tool"
 )]
 struct Cli {
+    /// Allow scanning a block device that is currently mounted read-write
+    #[arg(long, global = true)]
+    force: bool,
+    /// JSON file of extra file signatures to detect while scanning
+    #[arg(long, global = true, value_name = "FILE")]
+    signatures: Option<PathBuf>,
+    /// Log more detail to stderr (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+    /// Only log errors and hide the progress bar
+    #[arg(short, long, global = true)]
+    quiet: bool,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Deletion-time window shared by the commands that list deleted files
+#[derive(Args, Debug, Clone)]
+struct DeletionWindowArgs {
+    /// Only keep files deleted at or after this RFC 3339 time, e.g. 2024-03-01T00:00:00Z
+    #[arg(long, value_parser = parse_rfc3339)]
+    deleted_after: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only keep files deleted before this RFC 3339 time
+    #[arg(long, value_parser = parse_rfc3339)]
+    deleted_before: Option<chrono::DateTime<chrono::Utc>>,
+    /// Also drop files whose deletion time is unknown
+    #[arg(long)]
+    exclude_unknown_time: bool,
+}
+
+impl DeletionWindowArgs {
+    fn window(&self) -> Result<ghostfs_core::DeletionWindow> {
+        if let (Some(after), Some(before)) = (self.deleted_after, self.deleted_before) {
+            if after >= before {
+                anyhow::bail!("--deleted-after must be earlier than --deleted-before");
+            }
+        }
+        Ok(ghostfs_core::DeletionWindow {
+            after: self.deleted_after,
+            before: self.deleted_before,
+            exclude_unknown: self.exclude_unknown_time,
+        })
+    }
+}
+
+/// Case identity recorded with a session and in its audit trail
+#[derive(Args, Debug, Clone)]
+struct CaseArgs {
+    /// Name of the examiner handling the evidence
+    #[arg(long)]
+    examiner: Option<String>,
+    /// Case number the recovery belongs to
+    #[arg(long = "case", value_name = "CASE_NUMBER")]
+    case_number: Option<String>,
+    /// ID of the item of evidence the image was acquired from
+    #[arg(long)]
+    evidence_id: Option<String>,
+}
+
+impl CaseArgs {
+    fn metadata(&self) -> Option<ghostfs_core::CaseMetadata> {
+        ghostfs_core::CaseMetadata {
+            examiner: self.examiner.clone(),
+            case_number: self.case_number.clone(),
+            evidence_id: self.evidence_id.clone(),
+            acquisition_hash: None,
+        }
+        .non_empty()
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Scan an image file for recoverable entries
     Scan {
-        /// Path to image file (use image files for safety; raw devices later)
+        /// Path to image file or block device (always opened read-only)
         image: PathBuf,
-        /// Filesystem type
-        #[arg(long, value_parser = ["xfs", "btrfs", "exfat"], default_value = "xfs")]
+        /// Filesystem type ("auto" detects it from the superblock)
+        #[arg(long, value_parser = ["auto", "xfs", "btrfs", "exfat"], default_value = "auto")]
         fs: String,
         /// Show detailed filesystem information
         #[arg(long)]
@@ -114,6 +220,59 @@ enum Commands {
         /// Disable interactive prompts (for CI/automation)
         #[arg(long)]
         no_interactive: bool,
+        /// Continue an interrupted scan of this image from its last checkpoint
+        /// (written by scans run with --save or --resume)
+        #[arg(long)]
+        resume: bool,
+        /// Output format ("json" prints the session to stdout for scripts)
+        #[arg(long, value_parser = ["text", "json"], default_value = "text")]
+        format: String,
+        /// Recovery strategy to run (repeatable; replaces the default set):
+        /// directory, inode, signature, journal, trailer, or the not yet
+        /// implemented metadata and fragmented
+        #[arg(long = "strategy", value_name = "STRATEGY")]
+        strategies: Vec<RecoveryStrategy>,
+        /// Skip content-based signature scanning and trailer carving
+        #[arg(long, alias = "exclude-signature-scan")]
+        no_signature_scan: bool,
+        /// Print how confidence scores are distributed across the found files
+        #[arg(long)]
+        confidence_histogram: bool,
+        /// Also search the older Btrfs trees kept as superblock backup roots
+        #[arg(long)]
+        btrfs_backup_roots: bool,
+        /// Save the finished scan to the session database for later extraction
+        #[arg(long)]
+        save: bool,
+        /// Session database path (defaults to the user data directory)
+        #[arg(long)]
+        db: Option<PathBuf>,
+        /// JSON file of confidence factor weights; factors it leaves out
+        /// keep their default weight
+        #[arg(long, value_name = "FILE")]
+        confidence_weights: Option<PathBuf>,
+        #[command(flatten)]
+        window: DeletionWindowArgs,
+        #[command(flatten)]
+        case: CaseArgs,
+        /// SHA-256 recorded when the image was acquired; the scan is refused
+        /// if the image no longer matches it
+        #[arg(long, value_name = "SHA256")]
+        verify_hash: Option<String>,
+        /// Print the SHA-256 of the image and exit without scanning
+        #[arg(long, conflicts_with = "verify_hash")]
+        compute_hash: bool,
+        /// Write an audit trail of the scan to this directory
+        #[arg(long, value_name = "DIR")]
+        audit_dir: Option<PathBuf>,
+        /// Only list deleted file names and sizes from the filesystem
+        /// metadata, skipping carving, extent resolution and confidence
+        /// scoring; much faster, for triage
+        #[arg(long, conflicts_with_all = ["resume", "save", "confidence_histogram"])]
+        list_only: bool,
+        /// Column to sort the --list-only table by
+        #[arg(long, value_parser = ["id", "name", "size", "deleted"], default_value = "id", requires = "list_only")]
+        sort: String,
     },
     /// Detect filesystem type
     Detect {
@@ -124,12 +283,15 @@ enum Commands {
     Recover {
         /// Path to image file
         image: PathBuf,
-        /// Filesystem type
-        #[arg(long, value_parser = ["xfs", "btrfs", "exfat"], default_value = "xfs")]
+        /// Filesystem type ("auto" detects it from the superblock)
+        #[arg(long, value_parser = ["auto", "xfs", "btrfs", "exfat"], default_value = "auto")]
         fs: String,
         /// Output directory for recovered files
         #[arg(long)]
         out: PathBuf,
+        /// How to arrange recovered files: flat, by-type, by-date or original
+        #[arg(long, default_value = "original")]
+        output_layout: ghostfs_core::OutputLayout,
         /// File IDs to recover (if not specified, recovers all recoverable files)
         #[arg(long)]
         ids: Option<Vec<String>>,
@@ -145,22 +307,68 @@ enum Commands {
         /// Enable hash verification (creates hash_manifest.json)
         #[arg(long)]
         verify_hash: bool,
-        /// Hash algorithm for verification (sha256, sha512, sha1, md5)
-        #[arg(long, default_value = "sha256")]
-        hash_algorithm: String,
+        /// Hash algorithm for verification (sha256, sha512, sha1, md5, blake3)
+        #[arg(long = "hash-algo", alias = "hash-algorithm", default_value = "sha256")]
+        hash_algorithm: ghostfs_core::HashAlgorithm,
         /// Enable partial file recovery
         #[arg(long)]
         partial: bool,
         /// Enable smart extent reconstruction
         #[arg(long)]
         reconstruct: bool,
+        /// Check that recovered files decode and adjust their confidence
+        #[arg(long)]
+        verify_structure: bool,
+        /// Show what would be written, and whether it fits, without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        case: CaseArgs,
+    },
+    /// Recover the files of a saved session that match filters
+    Extract {
+        /// Session ID or unique ID prefix
+        #[arg(long)]
+        session: String,
+        /// Session database path (defaults to the user data directory)
+        #[arg(long)]
+        db: Option<PathBuf>,
+        /// Image to read from (defaults to the image the session was scanned from)
+        #[arg(long)]
+        image: Option<PathBuf>,
+        /// Output directory for extracted files
+        #[arg(long)]
+        out: PathBuf,
+        /// How to arrange extracted files: flat, by-type, by-date or original
+        #[arg(long, default_value = "original")]
+        output_layout: ghostfs_core::OutputLayout,
+        /// MIME type or prefix to match, e.g. "image/jpeg" or "image/"
+        #[arg(long)]
+        mime: Option<String>,
+        /// Minimum confidence score (0.0-1.0)
+        #[arg(long)]
+        min_confidence: Option<f32>,
+        /// Maximum confidence score (0.0-1.0)
+        #[arg(long)]
+        max_confidence: Option<f32>,
+        /// Minimum file size, e.g. "4096", "500KB" or "1MB"
+        #[arg(long, value_parser = parse_size)]
+        min_size: Option<u64>,
+        /// Maximum file size, e.g. "10MB"
+        #[arg(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+        #[command(flatten)]
+        window: DeletionWindowArgs,
+        /// Show what would be written, and whether it fits, without writing anything
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Show a timeline of file deletion activity
     Timeline {
         /// Path to image file (required to generate timeline)
         image: PathBuf,
-        /// Filesystem type
-        #[arg(long, value_parser = ["xfs", "btrfs", "exfat"], default_value = "xfs")]
+        /// Filesystem type ("auto" detects it from the superblock)
+        #[arg(long, value_parser = ["auto", "xfs", "btrfs", "exfat"], default_value = "auto")]
         fs: String,
         /// Export timeline to JSON file
         #[arg(long)]
@@ -168,7 +376,228 @@ enum Commands {
         /// Export timeline to CSV file
         #[arg(long)]
         csv: Option<PathBuf>,
+        /// Export timeline to a standalone HTML report
+        #[arg(long)]
+        html: Option<PathBuf>,
+        #[command(flatten)]
+        window: DeletionWindowArgs,
     },
+    /// Carve files by their signatures alone, ignoring file system metadata
+    Carve {
+        /// Path to image file or block device (always opened read-only)
+        image: PathBuf,
+        /// Output directory for carved files
+        #[arg(long)]
+        out: PathBuf,
+        /// How to arrange carved files: flat or by-type
+        #[arg(long, default_value = "flat")]
+        output_layout: ghostfs_core::OutputLayout,
+        /// Only accept files starting on multiples of this many bytes
+        /// (defaults to the detected file system's block or cluster size,
+        /// or 512; 1 accepts any offset)
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        alignment: Option<u64>,
+    },
+    /// Re-hash a recovery directory and check it against its manifest
+    Verify {
+        /// Directory holding the recovered files
+        dir: PathBuf,
+        /// Manifest to check against (defaults to manifest.json in the directory)
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+    },
+    /// Manage saved recovery sessions
+    Session {
+        /// Session database path (defaults to the user data directory)
+        #[arg(long, global = true)]
+        db: Option<PathBuf>,
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SessionCommands {
+    /// List saved sessions, newest first
+    List {
+        /// Only sessions of this filesystem type
+        #[arg(long, value_parser = ["xfs", "btrfs", "exfat"])]
+        fs: Option<String>,
+        /// Only sessions scanned from this image or device
+        #[arg(long)]
+        device: Option<PathBuf>,
+    },
+    /// Show a session's details and the files it found
+    Show {
+        /// Session ID or unique ID prefix
+        id: String,
+    },
+    /// Delete a session
+    Rm {
+        /// Session ID or unique ID prefix
+        id: String,
+    },
+    /// Delete sessions older than a number of days
+    Cleanup {
+        /// Remove sessions created more than this many days ago
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        days: u32,
+    },
+    /// Export a session to a portable JSON file (use a .gz extension to compress)
+    Export {
+        /// Session ID or unique ID prefix
+        id: String,
+        /// Output file
+        file: PathBuf,
+    },
+    /// Import a session exported on another machine
+    Import {
+        /// Session file (.json or .json.gz)
+        file: PathBuf,
+    },
+    /// Merge a second scan of the same device into a session
+    Merge {
+        /// Session to merge into (ID or unique ID prefix)
+        target: String,
+        /// Session whose files are merged in; it is kept unchanged
+        source: String,
+        /// Merge even if the sessions scanned different devices
+        #[arg(long)]
+        force: bool,
+    },
+    /// Compare two scans of the same device to see what was lost in between
+    Diff {
+        /// Earlier session (ID or unique ID prefix)
+        older: String,
+        /// Later session (ID or unique ID prefix)
+        newer: String,
+        /// Output format ("json" prints the diff to stdout for scripts)
+        #[arg(long, value_parser = ["text", "json"], default_value = "text")]
+        format: String,
+    },
+}
+
+impl Commands {
+    /// Image or device the command reads from, if any
+    fn image(&self) -> Option<&PathBuf> {
+        match self {
+            Commands::Scan { image, .. }
+            | Commands::Detect { image }
+            | Commands::Recover { image, .. }
+            | Commands::Timeline { image, .. }
+            | Commands::Carve { image, .. } => Some(image),
+            // Extract resolves its image from the session and checks it itself
+            Commands::Verify { .. } | Commands::Session { .. } | Commands::Extract { .. } => None,
+        }
+    }
+}
+
+impl Cli {
+    /// Most detailed log level to print for `--verbose` and `--quiet`
+    fn log_level(&self) -> tracing_subscriber::filter::LevelFilter {
+        use tracing_subscriber::filter::LevelFilter;
+
+        if self.quiet {
+            return LevelFilter::ERROR;
+        }
+        match self.verbose {
+            0 => LevelFilter::WARN,
+            1 => LevelFilter::INFO,
+            2 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Progress bar on stderr that follows the engine's scan progress
+///
+/// Hidden when stderr is not a terminal, so redirected output stays clean.
+fn scan_progress_bar() -> indicatif::ProgressBar {
+    let bar = indicatif::ProgressBar::new(100);
+    bar.set_style(
+        indicatif::ProgressStyle::with_template(
+            "{spinner} [{elapsed_precise}] [{bar:40}] {pos:>3}% {msg}",
+        )
+        .expect("valid progress template")
+        .progress_chars("=> "),
+    );
+    bar
+}
+
+/// Open the session manager for `--db`, or the default database
+fn open_session_manager(db: Option<&PathBuf>) -> Result<ghostfs_core::session::SessionManager> {
+    use ghostfs_core::session::SessionManager;
+
+    match db {
+        Some(path) => SessionManager::new(path),
+        None => SessionManager::open_default(),
+    }
+}
+
+/// File system type named by an `--fs` value; `None` for "auto"
+fn parse_fs_arg(fs: &str) -> Option<FileSystemType> {
+    match fs {
+        "auto" => None,
+        "xfs" => Some(FileSystemType::Xfs),
+        "btrfs" => Some(FileSystemType::Btrfs),
+        "exfat" => Some(FileSystemType::ExFat),
+        _ => unreachable!(),
+    }
+}
+
+/// Resolve `--fs` to a file system type, detecting it for "auto"
+fn resolve_fs_type(image: &Path, fs: &str) -> Result<FileSystemType> {
+    ghostfs_core::fs::resolve_filesystem(image, parse_fs_arg(fs)).map_err(|e| {
+        // Nothing recognisable left: raw carving is the only way in
+        if fs == "auto" && matches!(ghostfs_core::fs::identify_filesystem(image), Ok(None)) {
+            anyhow::anyhow!(
+                "{}, or carve files from it without metadata with `ghostfs carve {} --out <dir>`",
+                e,
+                image.display()
+            )
+        } else {
+            e
+        }
+    })
+}
+
+/// Build the strategy list from `--strategy` and `--no-signature-scan`
+///
+/// Returns `None` to keep the core defaults when neither flag was given.
+fn select_strategies(
+    requested: Vec<RecoveryStrategy>,
+    no_signature_scan: bool,
+) -> Result<Option<Vec<RecoveryStrategy>>> {
+    if requested.is_empty() && !no_signature_scan {
+        return Ok(None);
+    }
+
+    let mut strategies = if requested.is_empty() {
+        ghostfs_core::DEFAULT_SCAN_STRATEGIES.to_vec()
+    } else {
+        requested
+    };
+    if no_signature_scan {
+        strategies.retain(|strategy| {
+            !matches!(
+                strategy,
+                RecoveryStrategy::FileSignatureScan | RecoveryStrategy::TrailerCarving
+            )
+        });
+    }
+    if strategies.is_empty() {
+        anyhow::bail!("No recovery strategies left to run");
+    }
+
+    for strategy in &strategies {
+        if !strategy.is_implemented() {
+            eprintln!(
+                "⚠️  The '{}' strategy is not implemented yet and will have no effect",
+                strategy.name()
+            );
+        }
+    }
+    Ok(Some(strategies))
 }
 
 /// Get XFS recovery config with optional user prompts for large filesystems
@@ -206,28 +635,379 @@ fn get_xfs_config_for_scan(
     Ok(None) // Use adaptive defaults
 }
 
-fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+/// Print a dry-run recovery plan
+/// Print a bar chart of confidence scores in 10% buckets, with counts either side of `threshold`
+fn print_confidence_histogram(
+    out: &mut impl Write,
+    files: &[ghostfs_core::DeletedFile],
+    threshold: f32,
+) -> io::Result<()> {
+    const BAR_WIDTH: usize = 40;
+
+    let histogram = ghostfs_core::recovery::confidence_histogram(files);
+    let largest = histogram.iter().copied().max().unwrap_or(0).max(1);
+
+    writeln!(out, "Confidence Distribution:")?;
+    for (bucket, &count) in histogram.iter().enumerate() {
+        let bar = "█".repeat((count * BAR_WIDTH).div_ceil(largest));
+        writeln!(
+            out,
+            "  {:>3}-{:<3}% | {:<width$} | {}",
+            bucket * 10,
+            (bucket + 1) * 10,
+            bar,
+            count,
+            width = BAR_WIDTH
+        )?;
+    }
+
+    let above = files
+        .iter()
+        .filter(|file| file.confidence_score >= threshold)
+        .count();
+    writeln!(
+        out,
+        "  At or above threshold ({:.0}%): {}",
+        threshold * 100.0,
+        above
+    )?;
+    writeln!(
+        out,
+        "  Below threshold ({:.0}%): {}",
+        threshold * 100.0,
+        files.len() - above
+    )
+}
+
+/// Print session summaries as a table with aligned columns
+fn print_session_table(
+    out: &mut impl Write,
+    sessions: &[ghostfs_core::session::SessionSummary],
+) -> io::Result<()> {
+    let header = ["ID", "FS", "CREATED", "FILES", "RECOVERABLE", "DEVICE"];
+    let rows: Vec<[String; 6]> = sessions
+        .iter()
+        .map(|session| {
+            [
+                session.id.to_string()[..8].to_string(),
+                session.fs_type.to_string(),
+                session.created_at.format("%Y-%m-%d %H:%M").to_string(),
+                session.files_found.to_string(),
+                session.recoverable_files.to_string(),
+                session.device_path.display().to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = header.map(String::from);
+    for row in std::iter::once(&header).chain(&rows) {
+        writeln!(
+            out,
+            "{:<w0$}  {:<w1$}  {:<w2$}  {:>w3$}  {:>w4$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            row[5],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+        )?;
+    }
+    Ok(())
+}
+
+/// Print files found by a --list-only scan as a table sorted by `sort`
+fn print_file_listing(
+    out: &mut impl Write,
+    files: &mut [ghostfs_core::DeletedFile],
+    sort: &str,
+) -> io::Result<()> {
+    fn path(file: &ghostfs_core::DeletedFile) -> String {
+        file.original_path
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| format!("inode_{}", file.inode_or_cluster))
+    }
+
+    match sort {
+        "name" => files.sort_by_cached_key(path),
+        "size" => files.sort_by(|a, b| b.size.cmp(&a.size).then(a.id.cmp(&b.id))),
+        // Most recently deleted first, files without a time last
+        "deleted" => {
+            files.sort_by(|a, b| b.deletion_time.cmp(&a.deletion_time).then(a.id.cmp(&b.id)))
+        }
+        _ => files.sort_by_key(|file| file.id),
+    }
+
+    let header = ["ID", "SIZE", "DELETED", "PATH"];
+    let rows: Vec<[String; 4]> = files
+        .iter()
+        .map(|file| {
+            [
+                file.id.to_string(),
+                file.size.to_string(),
+                file.deletion_time.map_or_else(
+                    || "-".to_string(),
+                    |t| t.format("%Y-%m-%d %H:%M").to_string(),
+                ),
+                path(file),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = header.map(String::from);
+    for row in std::iter::once(&header).chain(&rows) {
+        writeln!(
+            out,
+            "{:>w0$}  {:>w1$}  {:<w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        )?;
+    }
+    writeln!(out, "{} deleted files", files.len())
+}
+
+/// Print a session's metadata followed by the files it found
+fn print_session_details(session: &ghostfs_core::RecoverySession) {
+    println!("Session ID: {}", session.id);
+    println!("File System: {}", session.fs_type);
+    println!("Device: {}", session.device_path.display());
+    println!(
+        "Created: {}",
+        session.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    if let Some(case) = &session.case_metadata {
+        let fields = [
+            ("Case", &case.case_number),
+            ("Evidence ID", &case.evidence_id),
+            ("Examiner", &case.examiner),
+            ("Acquisition Hash", &case.acquisition_hash),
+        ];
+        for (label, value) in fields {
+            if let Some(value) = value {
+                println!("{}: {}", label, value);
+            }
+        }
+    }
+    println!(
+        "Device Size: {} MB",
+        session.metadata.device_size / (1024 * 1024)
+    );
+    println!("Block Size: {} bytes", session.metadata.block_size);
+    println!("Scan Duration: {} ms", session.metadata.scan_duration_ms);
+    println!(
+        "Confidence Threshold: {:.0}%",
+        session.confidence_threshold * 100.0
+    );
+    println!("Files Found: {}", session.metadata.files_found);
+    println!("Recoverable Files: {}", session.metadata.recoverable_files);
+    if let Some(health) = &session.metadata.filesystem_health {
+        println!();
+        println!("{}", health);
+    }
+
+    if session.scan_results.is_empty() {
+        return;
+    }
+    println!("\nFiles:");
+    println!(
+        "  {:>6}  {:>12}  {:>6}  {:<24}  PATH",
+        "ID", "SIZE", "CONF", "TYPE"
+    );
+    for file in &session.scan_results {
+        let path = file
+            .original_path
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| format!("inode_{}", file.inode_or_cluster));
+        println!(
+            "  {:>6}  {:>12}  {:>5.1}%  {:<24}  {}",
+            file.id,
+            file.size,
+            file.confidence_score * 100.0,
+            file.metadata.mime_type.as_deref().unwrap_or("-"),
+            path
+        );
+    }
+}
+
+fn print_session_diff(diff: &ghostfs_core::session::SessionDiff) {
+    fn describe(change: &ghostfs_core::session::FileChange) -> String {
+        change
+            .original_path
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| format!("inode_{}", change.inode_or_cluster))
+    }
+    fn percent(confidence: Option<f32>) -> String {
+        confidence.map_or_else(|| "-".to_string(), |c| format!("{:.1}%", c * 100.0))
+    }
+
+    println!("🆕 Newly deleted: {}", diff.newly_deleted.len());
+    for change in &diff.newly_deleted {
+        println!(
+            "  {}  ({})",
+            describe(change),
+            percent(change.new_confidence)
+        );
+    }
+    println!(
+        "💀 Became unrecoverable: {}",
+        diff.became_unrecoverable.len()
+    );
+    for change in &diff.became_unrecoverable {
+        let now = match change.new_id {
+            Some(_) => percent(change.new_confidence),
+            None => "no longer found".to_string(),
+        };
+        println!(
+            "  {}  ({} -> {})",
+            describe(change),
+            percent(change.old_confidence),
+            now
+        );
+    }
+    println!("📉 Confidence changed: {}", diff.confidence_changed.len());
+    for change in &diff.confidence_changed {
+        println!(
+            "  {}  ({} -> {})",
+            describe(change),
+            percent(change.old_confidence),
+            percent(change.new_confidence)
+        );
+    }
+    println!("Unchanged: {}", diff.unchanged);
+}
+
+fn print_recovery_plan(plan: &ghostfs_core::RecoveryPlan) {
+    println!("\n📋 Dry run: nothing will be written");
+    for entry in &plan.entries {
+        println!(
+            "  {} {} -> {} ({} bytes)",
+            if entry.collides { "⚠️ " } else { "  " },
+            entry.file_id,
+            entry.output_path.display(),
+            entry.bytes
+        );
+    }
+    println!(
+        "\nWould write {} files, {} bytes",
+        plan.entries.len(),
+        plan.total_bytes
+    );
+    if plan.collisions > 0 {
+        println!(
+            "⚠️  {} files have conflicting output names",
+            plan.collisions
+        );
+    }
+    match plan.available_bytes {
+        Some(available) if !plan.space_ok => {
+            println!("❌ Not enough space: {} bytes free", available)
+        }
+        Some(available) => println!("✅ {} bytes free", available),
+        None => println!("Free space could not be determined"),
+    }
+}
 
+fn main() -> Result<()> {
     let cli = Cli::parse();
+
+    // Initialize tracing (on stderr, so stdout stays parseable with --format json)
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_max_level(cli.log_level())
+        .init();
+
+    // Raw devices are only ever read, but a live read-write filesystem is still off limits
+    if let Some(image) = cli.command.image() {
+        ghostfs_core::fs::common::ensure_not_mounted_writable(image, cli.force)?;
+    }
+
+    let signatures = match &cli.signatures {
+        Some(path) => {
+            let custom = ghostfs_core::recovery::load_definitions(path)?;
+            eprintln!(
+                "📑 Loaded {} custom signatures from {}",
+                custom.len(),
+                path.display()
+            );
+            ghostfs_core::recovery::SignatureSet::new(custom)
+        }
+        None => ghostfs_core::recovery::SignatureSet::default(),
+    };
+
     match cli.command {
         Commands::Scan {
             image,
             fs,
             info,
             no_interactive,
+            resume,
+            format,
+            strategies,
+            no_signature_scan,
+            confidence_histogram,
+            btrfs_backup_roots,
+            save,
+            db,
+            confidence_weights,
+            window,
+            case,
+            verify_hash,
+            compute_hash,
+            audit_dir,
+            list_only,
+            sort,
         } => {
-            let fs_type = match fs.as_str() {
-                "xfs" => FileSystemType::Xfs,
-                "btrfs" => FileSystemType::Btrfs,
-                "exfat" => FileSystemType::ExFat,
-                _ => unreachable!(),
-            };
+            if compute_hash {
+                let hash = ghostfs_core::calculate_image_hash(
+                    &image,
+                    ghostfs_core::HashAlgorithm::SHA256,
+                )?;
+                println!("{}  {}", hash.hash, image.display());
+                return Ok(());
+            }
+
+            let fs_type = resolve_fs_type(&image, &fs)?;
+            let strategies = select_strategies(strategies, no_signature_scan)?;
+            let window = window.window()?;
+            let json = format == "json";
+            let confidence_weights = confidence_weights
+                .map(|path| {
+                    ghostfs_core::ConfidenceWeights::from_json_file(&path).with_context(|| {
+                        format!("Failed to load confidence weights from {}", path.display())
+                    })
+                })
+                .transpose()?;
 
             if info {
-                // Show filesystem information
+                // Show filesystem information (on stderr when stdout carries JSON)
                 match ghostfs_core::fs::get_filesystem_info(&image, fs_type) {
+                    Ok(info_str) if json => eprintln!("{}", info_str),
                     Ok(info_str) => {
                         println!("📋 File System Information:");
                         println!("{}", info_str);
@@ -241,19 +1021,95 @@ fn main() -> Result<()> {
             }
 
             // Get XFS config - only prompt interactively if stdin is a TTY and not --no-interactive
-            let interactive = !no_interactive && atty::is(atty::Stream::Stdin);
+            let interactive = !no_interactive && !json && atty::is(atty::Stream::Stdin);
             let xfs_config = if fs_type == FileSystemType::Xfs {
                 get_xfs_config_for_scan(&image, interactive)?
             } else {
                 None
             };
 
+            // Only scans that use the session database checkpoint to it
+            let options = ghostfs_core::ScanOptions {
+                xfs_config,
+                strategies,
+                checkpoint_db: (save || resume).then(|| {
+                    db.clone()
+                        .unwrap_or_else(ghostfs_core::session::SessionDatabase::default_path)
+                }),
+                resume,
+                btrfs_backup_roots,
+                expected_image_hash: verify_hash,
+                audit_dir,
+                case_metadata: case.metadata(),
+                list_only,
+                confidence_weights,
+                signatures,
+                ..Default::default()
+            };
+
             // Perform scan (no threshold - software auto-calculates confidence)
-            let session = ghostfs_core::scan_and_analyze_with_config(&image, fs_type, xfs_config)?;
+            let mut session = if cli.quiet {
+                ghostfs_core::scan_and_analyze_with_options(&image, fs_type, options)?
+            } else {
+                let bar = scan_progress_bar();
+                let updates = bar.clone();
+                let session = ghostfs_core::scan_and_analyze_with_options_and_progress(
+                    &image,
+                    fs_type,
+                    options,
+                    move |update| {
+                        updates.set_position(update.progress_percent.clamp(0.0, 100.0) as u64);
+                        updates.set_message(update.current_operation);
+                    },
+                );
+                bar.finish_and_clear();
+                session?
+            };
+            window.apply(&mut session);
+
+            if list_only && !json {
+                print_file_listing(&mut io::stdout(), &mut session.scan_results, &sort)?;
+                return Ok(());
+            }
+
+            if save {
+                open_session_manager(db.as_ref())?.save_session(&session)?;
+                let saved = format!(
+                    "💾 Session {} saved; extract from it with --session {}",
+                    session.id, session.id
+                );
+                if json {
+                    eprintln!("{}", saved);
+                } else {
+                    println!("{}", saved);
+                }
+            }
+
+            if json {
+                if confidence_histogram {
+                    print_confidence_histogram(
+                        &mut io::stderr(),
+                        &session.scan_results,
+                        session.confidence_threshold,
+                    )?;
+                }
+                let stdout = io::stdout();
+                let mut out = stdout.lock();
+                serde_json::to_writer_pretty(&mut out, &session)?;
+                writeln!(out)?;
+                return Ok(());
+            }
 
             println!("Scan completed successfully!");
             println!("Session ID: {}", session.id);
             println!("File System: {}", session.fs_type);
+            if let Some(hash) = session
+                .case_metadata
+                .as_ref()
+                .and_then(|case| case.acquisition_hash.as_ref())
+            {
+                println!("Acquisition Hash: {} (verified)", hash);
+            }
             println!(
                 "Device Size: {} MB",
                 session.metadata.device_size / (1024 * 1024)
@@ -263,6 +1119,20 @@ fn main() -> Result<()> {
                 "Recoverable Files: {} (confidence >= 40%)",
                 session.metadata.recoverable_files
             );
+            if info {
+                if let Some(health) = &session.metadata.filesystem_health {
+                    println!();
+                    println!("{}", health);
+                }
+            }
+            if confidence_histogram {
+                println!();
+                print_confidence_histogram(
+                    &mut io::stdout(),
+                    &session.scan_results,
+                    session.confidence_threshold,
+                )?;
+            }
 
             // Show detailed file list with auto-calculated confidence
             if !session.scan_results.is_empty() {
@@ -300,14 +1170,16 @@ fn main() -> Result<()> {
         Commands::Detect { image } => {
             println!("Detecting file system type for: {}", image.display());
 
-            match ghostfs_core::fs::detect_filesystem(&image)? {
-                Some(fs_type) => {
-                    println!("Detected: {}", fs_type);
+            match ghostfs_core::fs::identify_filesystem(&image)? {
+                Some(detected) => {
+                    println!("Detected: {}", detected);
 
                     // Show basic info
-                    if let Ok(info) = ghostfs_core::fs::get_filesystem_info(&image, fs_type) {
-                        println!();
-                        println!("{}", info);
+                    if let Some(fs_type) = detected.fs_type {
+                        if let Ok(info) = ghostfs_core::fs::get_filesystem_info(&image, fs_type) {
+                            println!();
+                            println!("{}", info);
+                        }
                     }
                 }
                 None => {
@@ -319,6 +1191,7 @@ fn main() -> Result<()> {
             image,
             fs,
             out,
+            output_layout,
             ids,
             no_interactive,
             forensics,
@@ -327,23 +1200,15 @@ fn main() -> Result<()> {
             hash_algorithm,
             partial,
             reconstruct,
+            verify_structure,
+            dry_run,
+            case,
         } => {
             println!("Starting recovery process for: {}", image.display());
             println!("Output directory: {}", out.display());
 
             // Parse filesystem type
-            let fs_type = match fs.as_str() {
-                "xfs" => ghostfs_core::FileSystemType::Xfs,
-                "btrfs" => ghostfs_core::FileSystemType::Btrfs,
-                "exfat" => ghostfs_core::FileSystemType::ExFat,
-                _ => {
-                    eprintln!("Unsupported filesystem type: {}", fs);
-                    std::process::exit(1);
-                }
-            };
-
-            // Create output directory if it doesn't exist
-            std::fs::create_dir_all(&out)?;
+            let fs_type = resolve_fs_type(&image, &fs)?;
 
             // Get XFS config - only prompt interactively if stdin is a TTY and not --no-interactive
             let interactive = !no_interactive && atty::is(atty::Stream::Stdin);
@@ -355,7 +1220,14 @@ fn main() -> Result<()> {
 
             // Perform scan to identify recoverable files (auto-confidence)
             println!("Scanning for recoverable files...");
-            let session = ghostfs_core::scan_and_analyze_with_config(&image, fs_type, xfs_config)?;
+            let options = ghostfs_core::ScanOptions {
+                xfs_config,
+                signatures,
+                ..Default::default()
+            };
+            let mut session =
+                ghostfs_core::scan_and_analyze_with_options(&image, fs_type, options)?;
+            session.case_metadata = case.metadata();
 
             if session.metadata.recoverable_files == 0 {
                 println!("No recoverable files found (confidence >= 40%)");
@@ -367,8 +1239,23 @@ fn main() -> Result<()> {
                 session.metadata.recoverable_files
             );
 
+            let file_ids_u64: Option<Vec<u64>> = ids
+                .as_ref()
+                .map(|ids_vec| ids_vec.iter().filter_map(|id| id.parse().ok()).collect());
+
+            if dry_run {
+                let plan =
+                    ghostfs_core::plan_recovery(&session, &out, file_ids_u64, output_layout)?;
+                print_recovery_plan(&plan);
+                return Ok(());
+            }
+
+            // Create output directory if it doesn't exist
+            std::fs::create_dir_all(&out)?;
+
             // Determine if forensics mode is enabled
-            let use_forensics = forensics || audit || verify_hash || partial || reconstruct;
+            let use_forensics =
+                forensics || audit || verify_hash || partial || reconstruct || verify_structure;
 
             if use_forensics {
                 println!("\n🔒 Forensics mode enabled:");
@@ -376,17 +1263,7 @@ fn main() -> Result<()> {
                     println!("   • Audit trail logging");
                 }
                 if forensics || verify_hash {
-                    let algo = match hash_algorithm.as_str() {
-                        "sha256" => ghostfs_core::HashAlgorithm::SHA256,
-                        "sha512" => ghostfs_core::HashAlgorithm::SHA512,
-                        "sha1" => ghostfs_core::HashAlgorithm::SHA1,
-                        "md5" => ghostfs_core::HashAlgorithm::MD5,
-                        _ => {
-                            eprintln!("Invalid hash algorithm: {}", hash_algorithm);
-                            std::process::exit(1);
-                        }
-                    };
-                    println!("   • Hash verification ({})", hash_algorithm.to_uppercase());
+                    println!("   • Hash verification ({})", hash_algorithm.name());
                 }
                 if partial {
                     println!("   • Partial file recovery");
@@ -394,16 +1271,15 @@ fn main() -> Result<()> {
                 if reconstruct {
                     println!("   • Smart extent reconstruction");
                 }
+                if forensics || verify_structure {
+                    println!("   • File structure verification");
+                }
                 println!();
             }
 
             // Perform recovery with or without forensics
             println!("Starting file recovery...");
 
-            let file_ids_u64: Option<Vec<u64>> = ids.as_ref().map(|ids_vec| {
-                ids_vec.iter().filter_map(|id| id.parse().ok()).collect()
-            });
-
             if use_forensics {
                 // Build forensics config
                 let mut config = if forensics {
@@ -420,13 +1296,6 @@ fn main() -> Result<()> {
                     }
                     if verify_hash {
                         config.enable_hash_verification = true;
-                        config.hash_algorithm = match hash_algorithm.as_str() {
-                            "sha256" => ghostfs_core::HashAlgorithm::SHA256,
-                            "sha512" => ghostfs_core::HashAlgorithm::SHA512,
-                            "sha1" => ghostfs_core::HashAlgorithm::SHA1,
-                            "md5" => ghostfs_core::HashAlgorithm::MD5,
-                            _ => ghostfs_core::HashAlgorithm::SHA256,
-                        };
                         config.manifest_path = Some(out.join("hash_manifest.json"));
                     }
                     if partial {
@@ -435,8 +1304,15 @@ fn main() -> Result<()> {
                     if reconstruct {
                         config.enable_extent_reconstruction = true;
                     }
+                    if verify_structure {
+                        config.enable_structure_verification = true;
+                    }
                 }
 
+                // Recorded in the manifests so they can be verified later
+                config.hash_algorithm = hash_algorithm;
+                config.output_layout = output_layout;
+
                 // Forensics recovery
                 let forensics_report = ghostfs_core::recover_files_with_forensics(
                     &image,
@@ -446,6 +1322,9 @@ fn main() -> Result<()> {
                     config,
                 )?;
 
+                let report_path = out.join(ghostfs_core::forensics::FORENSICS_REPORT_FILE);
+                forensics_report.export_json(&report_path)?;
+
                 let recovery_report = forensics_report.report;
 
                 // Display recovery results
@@ -471,12 +1350,39 @@ fn main() -> Result<()> {
                     );
                 }
 
+                if !forensics_report.structural_validity.is_empty() {
+                    let damaged = forensics_report
+                        .structural_validity
+                        .values()
+                        .filter(|validity| validity.is_damaged())
+                        .count();
+                    println!(
+                        "Structure checks: {} files, {} truncated or corrupt",
+                        forensics_report.structural_validity.len(),
+                        damaged
+                    );
+                }
+
+                println!("\n📄 Forensics report: {}", report_path.display());
+
                 if let Some(ref audit_path) = forensics_report.audit_log_path {
-                    println!("\n📝 Audit trail: {}", audit_path.display());
+                    println!("📝 Audit trail: {}", audit_path.display());
                 }
 
                 if let Some(ref manifest_path) = forensics_report.manifest_path {
                     println!("🔐 Hash manifest: {}", manifest_path.display());
+
+                    ghostfs_core::generate_session_manifest(
+                        &out,
+                        &session,
+                        hash_algorithm,
+                        output_layout,
+                    )?;
+                    println!(
+                        "🔐 Session manifest: {}",
+                        out.join(ghostfs_core::forensics::SESSION_MANIFEST_FILE)
+                            .display()
+                    );
                 }
 
                 if !recovery_report.recovery_details.is_empty() {
@@ -490,6 +1396,14 @@ fn main() -> Result<()> {
                                     result.recovered_path.display()
                                 );
                             }
+                            ghostfs_core::RecoveryStatus::Partial(damaged) => {
+                                println!(
+                                    "  ⚠️ {} -> {} ({})",
+                                    result.file_id,
+                                    result.recovered_path.display(),
+                                    describe_damage(damaged)
+                                );
+                            }
                             ghostfs_core::RecoveryStatus::Failed(error) => {
                                 println!("  ❌ {} -> Failed: {}", result.file_id, error);
                             }
@@ -507,6 +1421,7 @@ fn main() -> Result<()> {
                     &session,
                     &out,
                     file_ids_u64,
+                    output_layout,
                 )?;
 
                 // Display recovery results
@@ -515,6 +1430,19 @@ fn main() -> Result<()> {
                     "Successfully recovered: {}",
                     recovery_report.recovered_files
                 );
+                if recovery_report.partial_files > 0 {
+                    println!(
+                        "Partially recovered (unreadable data zero-filled): {}",
+                        recovery_report.partial_files
+                    );
+                }
+                if !recovery_report.read_errors.bad_sectors.is_empty() {
+                    println!(
+                        "Bad sectors on source: {} ({} reads recovered by retrying)",
+                        recovery_report.read_errors.bad_sectors.len(),
+                        recovery_report.read_errors.recovered_reads
+                    );
+                }
                 println!("Failed recoveries: {}", recovery_report.failed_files);
                 println!("Total files processed: {}", recovery_report.total_files);
 
@@ -529,6 +1457,14 @@ fn main() -> Result<()> {
                                     result.recovered_path.display()
                                 );
                             }
+                            ghostfs_core::RecoveryStatus::Partial(damaged) => {
+                                println!(
+                                    "  {} -> {} ({})",
+                                    result.file_id,
+                                    result.recovered_path.display(),
+                                    describe_damage(damaged)
+                                );
+                            }
                             ghostfs_core::RecoveryStatus::Failed(error) => {
                                 println!("  {} -> Failed: {}", result.file_id, error);
                             }
@@ -546,25 +1482,28 @@ fn main() -> Result<()> {
             fs,
             json,
             csv,
+            html,
+            window,
         } => {
             println!("📅 Generating Recovery Timeline...\n");
 
-            let fs_type = match fs.as_str() {
-                "xfs" => FileSystemType::Xfs,
-                "btrfs" => FileSystemType::Btrfs,
-                "exfat" => FileSystemType::ExFat,
-                _ => unreachable!(),
-            };
+            let fs_type = resolve_fs_type(&image, &fs)?;
+            let window = window.window()?;
 
             // Perform scan to get recovery session
             println!("🔍 Scanning {} filesystem...", fs_type);
-            let session = match ghostfs_core::scan_and_analyze(&image, fs_type) {
-                Ok(s) => s,
-                Err(e) => {
-                    eprintln!("❌ Failed to scan image: {}", e);
-                    return Err(e);
-                }
+            let options = ghostfs_core::ScanOptions {
+                signatures,
+                ..Default::default()
             };
+            let session =
+                match ghostfs_core::scan_and_analyze_with_options(&image, fs_type, options) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        eprintln!("❌ Failed to scan image: {}", e);
+                        return Err(e);
+                    }
+                };
 
             println!(
                 "✅ Scan complete: {} files found\n",
@@ -573,7 +1512,11 @@ fn main() -> Result<()> {
 
             // Generate timeline
             use ghostfs_core::RecoveryTimeline;
-            let timeline = RecoveryTimeline::from_session(&session);
+            let timeline = RecoveryTimeline::from_session_in_window(
+                &session,
+                &ghostfs_core::PatternConfig::default(),
+                &window,
+            );
 
             // Display text report
             println!("{}", timeline.to_text_report());
@@ -604,6 +1547,15 @@ fn main() -> Result<()> {
                 }
             }
 
+            // Export to HTML if requested
+            if let Some(ref html_path) = html {
+                if let Err(e) = std::fs::write(html_path, timeline.to_html()) {
+                    eprintln!("⚠️  Failed to write HTML file: {}", e);
+                } else {
+                    println!("💾 Timeline report saved to: {}", html_path.display());
+                }
+            }
+
             // Provide helpful next steps
             if !timeline.events.is_empty() {
                 println!("\n💡 Next Steps:");
@@ -611,9 +1563,264 @@ fn main() -> Result<()> {
                 if !timeline.patterns.is_empty() {
                     println!("   • Review suspicious patterns above for forensic analysis");
                 }
-                if json.is_none() && csv.is_none() {
-                    println!("   • Add --json or --csv flags to export timeline data");
+                if json.is_none() && csv.is_none() && html.is_none() {
+                    println!("   • Add --json, --csv or --html flags to export timeline data");
+                }
+            }
+        }
+        Commands::Extract {
+            session,
+            db,
+            image,
+            out,
+            output_layout,
+            mime,
+            min_confidence,
+            max_confidence,
+            min_size,
+            max_size,
+            window,
+            dry_run,
+        } => {
+            let window = window.window()?;
+            let manager = open_session_manager(db.as_ref())?;
+            let session = manager.database().load_session(&session)?;
+            let image = image.unwrap_or_else(|| session.device_path.clone());
+            ghostfs_core::fs::common::ensure_not_mounted_writable(&image, cli.force)?;
+
+            let filter = ghostfs_core::session::FileFilter {
+                mime_prefix: mime,
+                min_confidence,
+                max_confidence,
+                min_size,
+                max_size,
+                ..Default::default()
+            };
+            let matching: Vec<u64> = session
+                .scan_results
+                .iter()
+                .filter(|file| filter.matches(file) && window.contains(file))
+                .map(|file| file.id)
+                .collect();
+
+            println!(
+                "🔎 {} of {} files in session {} match",
+                matching.len(),
+                session.scan_results.len(),
+                session.id
+            );
+            if matching.is_empty() {
+                return Ok(());
+            }
+
+            if dry_run {
+                let plan =
+                    ghostfs_core::plan_recovery(&session, &out, Some(matching), output_layout)?;
+                print_recovery_plan(&plan);
+                return Ok(());
+            }
+
+            let report =
+                ghostfs_core::recover_files(&image, &session, &out, Some(matching), output_layout)?;
+            println!(
+                "✅ Wrote {} files ({} bytes) to {}",
+                report.recovered_files,
+                report.total_bytes_recovered,
+                out.display()
+            );
+            if report.partial_files > 0 {
+                println!(
+                    "⚠️ {} files had unreadable data, written as zeros",
+                    report.partial_files
+                );
+            }
+            if report.failed_files > 0 {
+                println!("❌ {} files could not be extracted", report.failed_files);
+            }
+        }
+        Commands::Session { db, command } => {
+            let manager = open_session_manager(db.as_ref())?;
+
+            match command {
+                SessionCommands::List { fs, device } => {
+                    let fs_type = fs.as_deref().and_then(parse_fs_arg);
+                    let sessions: Vec<_> = manager
+                        .database()
+                        .list_sessions()?
+                        .into_iter()
+                        .filter(|session| fs_type.is_none_or(|fs| session.fs_type == fs))
+                        .filter(|session| {
+                            device
+                                .as_ref()
+                                .is_none_or(|device| &session.device_path == device)
+                        })
+                        .collect();
+
+                    if sessions.is_empty() {
+                        println!("No saved sessions");
+                    } else {
+                        print_session_table(&mut io::stdout(), &sessions)?;
+                    }
+                }
+                SessionCommands::Show { id } => {
+                    let session = manager.database().load_session(&id)?;
+                    print_session_details(&session);
                 }
+                SessionCommands::Rm { id } => {
+                    let id = manager.database().delete_session(&id)?;
+                    println!("🗑️ Deleted session {}", id);
+                }
+                SessionCommands::Cleanup { days } => {
+                    let removed = manager.database().cleanup_old_sessions(days)?;
+                    println!("🧹 Removed {} sessions older than {} days", removed, days);
+                }
+                SessionCommands::Export { id, file } => {
+                    manager.export_session(&id, &file)?;
+                    println!("💾 Session exported to: {}", file.display());
+                }
+                SessionCommands::Import { file } => {
+                    let id = manager.import_session(&file)?;
+                    println!("✅ Imported session {}", id);
+                }
+                SessionCommands::Merge {
+                    target,
+                    source,
+                    force,
+                } => {
+                    let report = manager.merge_sessions(&target, &source, force)?;
+                    println!(
+                        "✅ Merged into {}: {} new files, {} duplicates ({} replaced by higher-confidence copies)",
+                        target, report.added, report.duplicates, report.upgraded
+                    );
+                }
+                SessionCommands::Diff {
+                    older,
+                    newer,
+                    format,
+                } => {
+                    let older = manager.database().load_session(&older)?;
+                    let newer = manager.database().load_session(&newer)?;
+                    if older.device_path != newer.device_path || older.fs_type != newer.fs_type {
+                        eprintln!(
+                            "⚠️ Session {} scanned {} ({}) but session {} scanned {} ({})",
+                            older.id,
+                            older.device_path.display(),
+                            older.fs_type,
+                            newer.id,
+                            newer.device_path.display(),
+                            newer.fs_type
+                        );
+                    }
+
+                    let diff = older.diff(&newer);
+                    if format == "json" {
+                        let stdout = io::stdout();
+                        let mut out = stdout.lock();
+                        serde_json::to_writer_pretty(&mut out, &diff)?;
+                        writeln!(out)?;
+                    } else {
+                        println!(
+                            "Comparing {} ({}) with {} ({})\n",
+                            older.id,
+                            older.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                            newer.id,
+                            newer.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+                        );
+                        print_session_diff(&diff);
+                    }
+                }
+            }
+        }
+        Commands::Carve {
+            image,
+            out,
+            output_layout,
+            alignment,
+        } => {
+            let device = ghostfs_core::fs::common::BlockDevice::open(&image)?;
+            let config = match alignment {
+                Some(alignment) => ghostfs_core::CarveConfig {
+                    alignment,
+                    signatures,
+                    ..Default::default()
+                },
+                None => ghostfs_core::CarveConfig {
+                    signatures,
+                    ..ghostfs_core::CarveConfig::for_device(&device)
+                },
+            };
+            println!(
+                "📐 Accepting files that start every {} bytes from offset {}",
+                config.alignment, config.alignment_origin
+            );
+            let files = ghostfs_core::carve_device(&device, &config);
+            println!("🔪 Carved {} files from {}", files.len(), image.display());
+
+            let paths = ghostfs_core::assign_recovery_paths(&out, &files, output_layout);
+            let mut total_bytes = 0;
+            for file in &files {
+                let path = &paths[&file.id];
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let output = io::BufWriter::new(std::fs::File::create(path)?);
+                let written = ghostfs_core::recovery::write_carved_file(&device, file, output)?;
+                total_bytes += written;
+                println!(
+                    "  {} ({}, {} bytes at offset {})",
+                    path.display(),
+                    file.metadata.mime_type.as_deref().unwrap_or("unknown"),
+                    written,
+                    file.data_blocks
+                        .first()
+                        .map_or(0, |range| range.start_block)
+                );
+            }
+            println!(
+                "✅ Wrote {} files ({} bytes) to {}",
+                files.len(),
+                total_bytes,
+                out.display()
+            );
+        }
+        Commands::Verify { dir, manifest } => {
+            let manifest_path = manifest
+                .unwrap_or_else(|| dir.join(ghostfs_core::forensics::SESSION_MANIFEST_FILE));
+            let manifest = ghostfs_core::HashManifest::import_json(&manifest_path)?;
+
+            println!(
+                "🔐 Verifying {} files in {} ({})\n",
+                manifest.files.len(),
+                dir.display(),
+                manifest.algorithm.name()
+            );
+
+            let result = manifest.verify_all(&dir)?;
+            let mut verifications = result.verifications;
+            verifications.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+
+            for verification in &verifications {
+                match verification.status {
+                    ghostfs_core::VerificationStatus::Verified => {
+                        println!("  ✅ {}", verification.file_path);
+                    }
+                    _ => {
+                        println!(
+                            "  ❌ {} -> {}",
+                            verification.file_path,
+                            verification.notes.as_deref().unwrap_or("hash mismatch")
+                        );
+                    }
+                }
+            }
+
+            println!(
+                "\nVerified: {}, mismatched: {}",
+                result.summary.verified, result.summary.corrupted
+            );
+
+            if result.summary.corrupted > 0 {
+                std::process::exit(1);
             }
         }
     }
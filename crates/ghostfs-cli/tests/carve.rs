@@ -0,0 +1,63 @@
+//! Integration tests for `ghostfs carve`
+
+use std::io::Write;
+use std::process::Command;
+
+/// A JPEG from SOI to EOI with some scan data in between
+fn jpeg() -> Vec<u8> {
+    let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+    data.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+    data.extend((0..5000u32).map(|i| (i % 251) as u8 + 1));
+    data.extend_from_slice(&[0xFF, 0xD9]);
+    data
+}
+
+/// An image with no file system, holding a JPEG at 8KiB
+fn write_unformatted_image(jpeg: &[u8]) -> tempfile::NamedTempFile {
+    let mut image = vec![0u8; 256 * 1024];
+    image[8192..8192 + jpeg.len()].copy_from_slice(jpeg);
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&image).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+#[test]
+fn test_carve_writes_files_without_file_system() {
+    let jpeg = jpeg();
+    let image = write_unformatted_image(&jpeg);
+    let out = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .arg("carve")
+        .arg(image.path())
+        .arg("--out")
+        .arg(out.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "carve failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Carved 1 files"));
+
+    let carved = std::fs::read(out.path().join("recovered_file_1.jpg")).unwrap();
+    assert_eq!(carved, jpeg);
+}
+
+#[test]
+fn test_scan_of_unformatted_image_suggests_carving() {
+    let image = write_unformatted_image(&jpeg());
+    let data_home = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["scan", "--no-interactive"])
+        .arg(image.path())
+        .env("XDG_DATA_HOME", data_home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("ghostfs carve"));
+}
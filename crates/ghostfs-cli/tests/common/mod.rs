@@ -0,0 +1,75 @@
+//! Sessions saved for the CLI to work on
+
+use chrono::Utc;
+use ghostfs_core::{
+    BlockRange, DeletedFile, FileMetadata, FileSystemType, FileType, RecoverySession,
+    SessionMetadata,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Block size of the sessions built here
+pub const BLOCK_SIZE: u64 = 4096;
+
+/// A recoverable file `/home/user/<name>`, deleted now, filling `blocks`
+/// blocks from `start_block`
+pub fn deleted_file(id: u64, name: &str, mime: &str, start_block: u64, blocks: u64) -> DeletedFile {
+    DeletedFile {
+        id,
+        inode_or_cluster: 100 + id,
+        original_path: Some(PathBuf::from(format!("/home/user/{}", name))),
+        size: blocks * BLOCK_SIZE,
+        deletion_time: Some(Utc::now()),
+        confidence_score: 0.9,
+        file_type: FileType::RegularFile,
+        data_blocks: vec![BlockRange {
+            start_block,
+            block_count: blocks,
+            is_allocated: false,
+        }],
+        is_recoverable: true,
+        metadata: FileMetadata {
+            mime_type: Some(mime.to_string()),
+            file_extension: None,
+            permissions: None,
+            owner_uid: None,
+            owner_gid: None,
+            owner_name: None,
+            group_name: None,
+            created_time: None,
+            modified_time: None,
+            accessed_time: None,
+            extended_attributes: HashMap::new(),
+        },
+        fs_metadata: None,
+        skip_reason: None,
+    }
+}
+
+/// A session of `scan_results`, created now, on a device of `device_size` bytes
+pub fn session(
+    fs_type: FileSystemType,
+    device_path: impl Into<PathBuf>,
+    device_size: u64,
+    scan_results: Vec<DeletedFile>,
+) -> RecoverySession {
+    RecoverySession {
+        id: uuid::Uuid::new_v4(),
+        fs_type,
+        device_path: device_path.into(),
+        created_at: Utc::now(),
+        total_scanned: scan_results.len() as u64,
+        confidence_threshold: 0.4,
+        metadata: SessionMetadata {
+            device_size,
+            filesystem_size: device_size,
+            block_size: BLOCK_SIZE as u32,
+            scan_duration_ms: 10,
+            files_found: scan_results.len() as u32,
+            recoverable_files: scan_results.len() as u32,
+            filesystem_health: None,
+        },
+        scan_results,
+        case_metadata: None,
+    }
+}
@@ -0,0 +1,257 @@
+//! Integration tests for `ghostfs extract`
+
+mod common;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use ghostfs_core::session::SessionDatabase;
+use ghostfs_core::{DeletedFile, FileSystemType, RecoverySession};
+
+const BLOCK_SIZE: usize = common::BLOCK_SIZE as usize;
+
+/// A deleted file occupying `blocks` blocks from `start_block`
+fn deleted_file(
+    id: u64,
+    name: &str,
+    mime: &str,
+    confidence: f32,
+    start_block: u64,
+    blocks: u64,
+) -> DeletedFile {
+    DeletedFile {
+        confidence_score: confidence,
+        is_recoverable: confidence >= 0.4,
+        ..common::deleted_file(id, name, mime, start_block, blocks)
+    }
+}
+
+/// Save a session with mixed file types over a 16-block image, returning the image
+fn write_mixed_session(db_path: &Path) -> (tempfile::NamedTempFile, RecoverySession) {
+    let mut image = tempfile::NamedTempFile::new().unwrap();
+    image.write_all(&[0xAB; 16 * BLOCK_SIZE]).unwrap();
+    image.flush().unwrap();
+
+    let scan_results = vec![
+        deleted_file(1, "small.jpg", "image/jpeg", 0.9, 0, 1),
+        deleted_file(2, "large.jpg", "image/jpeg", 0.9, 1, 4),
+        deleted_file(3, "doubtful.jpg", "image/jpeg", 0.5, 5, 4),
+        deleted_file(4, "diagram.png", "image/png", 0.8, 9, 2),
+        deleted_file(5, "report.pdf", "application/pdf", 0.95, 11, 4),
+    ];
+    let session = common::session(
+        FileSystemType::Xfs,
+        image.path(),
+        16 * BLOCK_SIZE as u64,
+        scan_results,
+    );
+    SessionDatabase::open(db_path)
+        .unwrap()
+        .save_session(&session)
+        .unwrap();
+
+    (image, session)
+}
+
+/// Run `ghostfs extract` and return the sorted names of the files written
+fn extract(db_path: &Path, session: &RecoverySession, filters: &[&str]) -> Vec<String> {
+    let out = tempfile::tempdir().unwrap();
+    let id = session.id.to_string();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["extract", "--session", &id[..8]])
+        .arg("--db")
+        .arg(db_path)
+        .arg("--out")
+        .arg(out.path())
+        .args(filters)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "extract failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Files keep the directory of their original path
+    let mut names: Vec<String> = std::fs::read_dir(out.path().join("home/user"))
+        .map(|entries| {
+            entries
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_extract_filters_by_mime_confidence_and_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let (_image, session) = write_mixed_session(&db_path);
+
+    assert_eq!(
+        extract(&db_path, &session, &["--mime", "image/"]),
+        vec!["diagram.png", "doubtful.jpg", "large.jpg", "small.jpg"]
+    );
+    assert_eq!(
+        extract(
+            &db_path,
+            &session,
+            &[
+                "--mime",
+                "image/jpeg",
+                "--min-confidence",
+                "0.7",
+                "--min-size",
+                "8KB"
+            ]
+        ),
+        vec!["large.jpg"]
+    );
+    assert_eq!(
+        extract(
+            &db_path,
+            &session,
+            &["--max-size", "8kb", "--max-confidence", "0.85"]
+        ),
+        vec!["diagram.png"]
+    );
+    assert!(extract(&db_path, &session, &["--mime", "video/"]).is_empty());
+}
+
+#[test]
+fn test_extracted_file_holds_image_data() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let (_image, session) = write_mixed_session(&db_path);
+    let out = tempfile::tempdir().unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["extract", "--session", &session.id.to_string()])
+        .arg("--db")
+        .arg(&db_path)
+        .arg("--out")
+        .arg(out.path())
+        .args(["--mime", "application/pdf"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let data = std::fs::read(out.path().join("home/user/report.pdf")).unwrap();
+    assert_eq!(data.len(), 4 * BLOCK_SIZE);
+    assert!(data.iter().all(|&b| b == 0xAB));
+}
+
+#[test]
+fn test_extract_dry_run_writes_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let (_image, session) = write_mixed_session(&db_path);
+    let out = dir.path().join("out");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["extract", "--session", &session.id.to_string()])
+        .arg("--db")
+        .arg(&db_path)
+        .arg("--out")
+        .arg(&out)
+        .args(["--mime", "image/jpeg", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Would write 3 files, 36864 bytes"),
+        "{}",
+        stdout
+    );
+    assert!(!out.exists());
+}
+
+#[test]
+fn test_extract_by_type_layout() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let (_image, session) = write_mixed_session(&db_path);
+    let out = tempfile::tempdir().unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["extract", "--session", &session.id.to_string()])
+        .arg("--db")
+        .arg(&db_path)
+        .arg("--out")
+        .arg(out.path())
+        .args(["--min-confidence", "0.8", "--output-layout", "by-type"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    for path in [
+        "image/small.jpg",
+        "image/large.jpg",
+        "image/diagram.png",
+        "application/report.pdf",
+    ] {
+        assert!(out.path().join(path).is_file(), "{} missing", path);
+    }
+    assert!(!out.path().join("home").exists());
+}
+
+#[test]
+fn test_extract_filters_by_deletion_window() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let (_image, mut session) = write_mixed_session(&db_path);
+
+    // One deletion a day from March 1st, except the PNG whose time is unknown
+    for file in &mut session.scan_results {
+        file.deletion_time = format!("2024-03-0{}T12:00:00Z", file.id)
+            .parse()
+            .ok()
+            .filter(|_| file.id != 4);
+    }
+    SessionDatabase::open(&db_path)
+        .unwrap()
+        .save_session(&session)
+        .unwrap();
+
+    let window = [
+        "--deleted-after",
+        "2024-03-02T00:00:00Z",
+        "--deleted-before",
+        "2024-03-04T00:00:00Z",
+    ];
+    assert_eq!(
+        extract(&db_path, &session, &window),
+        vec!["diagram.png", "doubtful.jpg", "large.jpg"]
+    );
+    assert_eq!(
+        extract(
+            &db_path,
+            &session,
+            &[&window[..], &["--exclude-unknown-time"]].concat()
+        ),
+        vec!["doubtful.jpg", "large.jpg"]
+    );
+
+    let id = session.id.to_string();
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["extract", "--session", &id, "--out"])
+        .arg(dir.path().join("out"))
+        .arg("--db")
+        .arg(&db_path)
+        .args([
+            "--deleted-after",
+            "2024-03-04T00:00:00Z",
+            "--deleted-before",
+            "2024-03-02T00:00:00Z",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--deleted-after"));
+}
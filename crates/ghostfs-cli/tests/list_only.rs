@@ -0,0 +1,109 @@
+//! Integration tests for `ghostfs scan --list-only`
+
+use std::process::Command;
+
+/// Small exFAT volume with a JPEG header at 1MB and the deleted entries of
+/// `files` (name, first cluster, size) in its root directory
+fn write_fixture_image(files: &[(&str, u32, u64)]) -> tempfile::NamedTempFile {
+    let size = 4 * 1024 * 1024;
+    let mut image = vec![0u8; size];
+    image[3..11].copy_from_slice(b"EXFAT   ");
+    image[72..80].copy_from_slice(&(size as u64 / 512).to_le_bytes()); // volume length
+    image[80..84].copy_from_slice(&8u32.to_le_bytes()); // FAT offset (sectors)
+    image[84..88].copy_from_slice(&32u32.to_le_bytes()); // FAT length (sectors)
+    image[88..92].copy_from_slice(&64u32.to_le_bytes()); // cluster heap offset (sectors)
+    image[92..96].copy_from_slice(&(size as u32 / 4096 - 1).to_le_bytes());
+    image[96..100].copy_from_slice(&2u32.to_le_bytes()); // root directory
+    image[108] = 9; // 512-byte sectors
+    image[109] = 3; // 4KiB clusters
+    image[110] = 1; // one FAT
+    image[510..512].copy_from_slice(&[0x55, 0xAA]);
+    image[8 * 512 + 8..8 * 512 + 12].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes()); // root: one cluster
+
+    image[1024 * 1024..1024 * 1024 + 10]
+        .copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F']);
+
+    for (i, (name, cluster, file_size)) in files.iter().enumerate() {
+        let start = 64 * 512 + i * 96;
+        let set = &mut image[start..start + 96];
+        set[0] = 0x05; // deleted file entry
+        set[1] = 2; // secondary entries
+        set[32] = 0x40; // deleted stream extension
+        set[35] = name.len() as u8;
+        set[52..56].copy_from_slice(&cluster.to_le_bytes());
+        set[56..64].copy_from_slice(&file_size.to_le_bytes());
+        set[64] = 0x41; // deleted file name
+        for (j, unit) in name.encode_utf16().enumerate() {
+            set[66 + j * 2..68 + j * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), &image).unwrap();
+    file
+}
+
+fn list(image: &std::path::Path, args: &[&str]) -> String {
+    let data_home = tempfile::tempdir().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["--quiet", "scan", "--no-interactive", "--list-only"])
+        .args(args)
+        .arg(image)
+        .env("XDG_DATA_HOME", data_home.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "scan failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_list_only_prints_sorted_table_of_deleted_entries() {
+    let image = write_fixture_image(&[("notes.txt", 20, 1200), ("archive.zip", 30, 880_000)]);
+
+    let by_id = list(image.path(), &[]);
+    let lines: Vec<&str> = by_id.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", by_id);
+    assert!(lines[0].contains("SIZE") && lines[0].ends_with("PATH"));
+    assert!(lines[1].ends_with("notes.txt") && lines[1].contains(" 1200 "));
+    assert!(lines[2].ends_with("archive.zip") && lines[2].contains(" 880000 "));
+    assert_eq!(lines[3], "2 deleted files");
+    // Nothing is carved from the JPEG header
+    assert!(!by_id.contains("jpg"));
+
+    let by_size = list(image.path(), &["--sort", "size"]);
+    let paths: Vec<&str> = by_size
+        .lines()
+        .skip(1)
+        .take(2)
+        .filter_map(|line| line.split_whitespace().last())
+        .collect();
+    assert_eq!(paths, vec!["archive.zip", "notes.txt"]);
+}
+
+#[test]
+fn test_list_only_json_has_no_extents() {
+    let image = write_fixture_image(&[("notes.txt", 20, 1200)]);
+
+    let output = list(image.path(), &["--format", "json"]);
+    let session: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let files = session["scan_results"].as_array().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0]["original_path"], "notes.txt");
+    assert_eq!(files[0]["data_blocks"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_sort_requires_list_only() {
+    let image = write_fixture_image(&[]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["scan", "--no-interactive", "--sort", "size"])
+        .arg(image.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,382 @@
+//! Integration tests for `ghostfs scan --format json`
+
+use std::io::Write;
+use std::process::Command;
+
+/// Build a small exFAT volume with a JPEG header at the start of every 1MB chunk after the first
+fn write_fixture_image(megabytes: usize) -> tempfile::NamedTempFile {
+    let size = megabytes * 1024 * 1024;
+    let mut image = vec![0u8; size];
+    image[3..11].copy_from_slice(b"EXFAT   ");
+    image[72..80].copy_from_slice(&(size as u64 / 512).to_le_bytes()); // volume length
+    image[80..84].copy_from_slice(&8u32.to_le_bytes()); // FAT offset (sectors)
+    image[84..88].copy_from_slice(&32u32.to_le_bytes()); // FAT length (sectors)
+    image[88..92].copy_from_slice(&64u32.to_le_bytes()); // cluster heap offset (sectors)
+    image[92..96].copy_from_slice(&(size as u32 / 4096 - 1).to_le_bytes());
+    image[96..100].copy_from_slice(&2u32.to_le_bytes()); // root directory
+    image[108] = 9; // 512-byte sectors
+    image[109] = 3; // 4KiB clusters
+    image[110] = 1; // one FAT
+    image[112] = 10; // percent in use
+    image[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    for chunk in image.chunks_mut(1024 * 1024).skip(1) {
+        chunk[..10].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F']);
+    }
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&image).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// Run `ghostfs scan --format json` with extra arguments and parse its output
+fn scan_json(image: &std::path::Path, args: &[&str]) -> serde_json::Value {
+    let data_home = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["scan", "--no-interactive", "--format", "json"])
+        .args(args)
+        .arg(image)
+        .env("XDG_DATA_HOME", data_home.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "scan failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_scan_json_output_parses() {
+    let image = write_fixture_image(4);
+    let session = scan_json(image.path(), &["--fs", "exfat"]);
+
+    assert_eq!(session["fs_type"], "ExFat");
+    assert!(session["id"].is_string());
+
+    let files = session["scan_results"].as_array().unwrap();
+    assert!(!files.is_empty());
+    assert_eq!(
+        session["metadata"]["files_found"].as_u64(),
+        Some(files.len() as u64)
+    );
+    for file in files {
+        assert!(file["confidence_score"].is_f64());
+        assert!(file["is_recoverable"].is_boolean());
+    }
+}
+
+#[test]
+fn test_no_signature_scan_finds_no_carved_files() {
+    let image = write_fixture_image(4);
+    let session = scan_json(image.path(), &["--no-signature-scan"]);
+
+    assert_eq!(session["scan_results"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_strategy_flags_must_leave_a_strategy() {
+    let image = write_fixture_image(1);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["scan", "--no-interactive", "--strategy", "signature"])
+        .arg("--no-signature-scan")
+        .arg(image.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_confidence_weights_file_changes_scores() {
+    let image = write_fixture_image(4);
+    let dir = tempfile::tempdir().unwrap();
+    let scores = |session: serde_json::Value| -> Vec<f64> {
+        session["scan_results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["confidence_score"].as_f64().unwrap())
+            .collect()
+    };
+    let defaults = scores(scan_json(image.path(), &["--fs", "exfat"]));
+    assert!(!defaults.is_empty());
+
+    // All weight on the signature match, which every carved JPEG has
+    let weights = dir.path().join("weights.json");
+    std::fs::write(
+        &weights,
+        r#"{"time_recency": 0, "metadata_completeness": 0, "data_block_integrity": 0,
+            "file_signature_match": 1, "size_consistency": 0, "fs_specific": 0}"#,
+    )
+    .unwrap();
+    let weighted = scores(scan_json(
+        image.path(),
+        &[
+            "--fs",
+            "exfat",
+            "--confidence-weights",
+            weights.to_str().unwrap(),
+        ],
+    ));
+    assert_eq!(weighted.len(), defaults.len());
+    assert_ne!(weighted, defaults);
+
+    std::fs::write(&weights, "not json").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args([
+            "scan",
+            "--no-interactive",
+            "--fs",
+            "exfat",
+            "--confidence-weights",
+        ])
+        .arg(&weights)
+        .arg(image.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to load confidence weights"));
+}
+
+#[test]
+fn test_confidence_histogram_counts_every_file() {
+    let image = write_fixture_image(4);
+    let data_home = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["scan", "--no-interactive", "--format", "json"])
+        .arg("--confidence-histogram")
+        .arg(image.path())
+        .env("XDG_DATA_HOME", data_home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // The histogram goes to stderr so stdout stays valid JSON
+    let session: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let files = session["scan_results"].as_array().unwrap().len();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Confidence Distribution:"));
+
+    let bucket_counts: Vec<usize> = stderr
+        .lines()
+        .filter(|line| line.contains("% |"))
+        .map(|line| line.rsplit('|').next().unwrap().trim().parse().unwrap())
+        .collect();
+    assert_eq!(bucket_counts.len(), 10);
+    assert_eq!(bucket_counts.iter().sum::<usize>(), files);
+}
+
+#[test]
+fn test_saved_scan_loads_back_by_id() {
+    let image = write_fixture_image(4);
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let session = scan_json(
+        image.path(),
+        &["--fs", "exfat", "--save", "--db", db_path.to_str().unwrap()],
+    );
+
+    let id = session["id"].as_str().unwrap();
+    let saved = ghostfs_core::session::SessionDatabase::open(&db_path)
+        .unwrap()
+        .load_session(id)
+        .unwrap();
+    assert_eq!(saved.id.to_string(), id);
+    assert_eq!(
+        saved.scan_results.len(),
+        session["scan_results"].as_array().unwrap().len()
+    );
+}
+
+#[test]
+fn test_unsaved_scan_leaves_the_database_alone() {
+    let image = write_fixture_image(4);
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    scan_json(
+        image.path(),
+        &["--fs", "exfat", "--db", db_path.to_str().unwrap()],
+    );
+    assert!(!db_path.exists());
+}
+
+#[test]
+fn test_unopenable_checkpoint_database_does_not_stop_the_scan() {
+    let image = write_fixture_image(4);
+    // A database path under a regular file cannot be opened
+    let db_path = image.path().join("sessions.db");
+    let session = scan_json(
+        image.path(),
+        &[
+            "--fs",
+            "exfat",
+            "--resume",
+            "--db",
+            db_path.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(session["fs_type"], "ExFat");
+}
+
+#[test]
+fn test_saved_scan_keeps_case_metadata() {
+    let image = write_fixture_image(4);
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let session = scan_json(
+        image.path(),
+        &[
+            "--fs",
+            "exfat",
+            "--save",
+            "--db",
+            db_path.to_str().unwrap(),
+            "--examiner",
+            "J. Doe",
+            "--case",
+            "2024-0117",
+            "--evidence-id",
+            "EV-3",
+        ],
+    );
+    assert_eq!(session["case_metadata"]["case_number"], "2024-0117");
+
+    let saved = ghostfs_core::session::SessionDatabase::open(&db_path)
+        .unwrap()
+        .load_session(session["id"].as_str().unwrap())
+        .unwrap();
+    let case = saved.case_metadata.unwrap();
+    assert_eq!(case.examiner.as_deref(), Some("J. Doe"));
+    assert_eq!(case.case_number.as_deref(), Some("2024-0117"));
+    assert_eq!(case.evidence_id.as_deref(), Some("EV-3"));
+}
+
+/// Contents of the one audit log written to `dir`
+fn audit_log_in(dir: &std::path::Path) -> String {
+    let path = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .unwrap();
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_matching_acquisition_hash_proceeds() {
+    let image = write_fixture_image(4);
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["scan", "--compute-hash"])
+        .arg(image.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let hash = stdout.split_whitespace().next().unwrap().to_string();
+    assert_eq!(
+        hash,
+        ghostfs_core::calculate_file_hash(image.path(), ghostfs_core::HashAlgorithm::SHA256)
+            .unwrap()
+            .hash
+    );
+
+    let audit_dir = tempfile::tempdir().unwrap();
+    let session = scan_json(
+        image.path(),
+        &[
+            "--fs",
+            "exfat",
+            "--verify-hash",
+            &hash.to_uppercase(),
+            "--audit-dir",
+            audit_dir.path().to_str().unwrap(),
+        ],
+    );
+    assert_eq!(session["case_metadata"]["acquisition_hash"], hash.as_str());
+
+    let log = audit_log_in(audit_dir.path());
+    assert!(log.contains("Acquisition hash verified"));
+    assert!(log.contains("DISK_SCAN_COMPLETE"));
+}
+
+#[test]
+fn test_mismatched_acquisition_hash_aborts_scan() {
+    let image = write_fixture_image(4);
+    let data_home = tempfile::tempdir().unwrap();
+    let audit_dir = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args([
+            "scan",
+            "--no-interactive",
+            "--fs",
+            "exfat",
+            "--format",
+            "json",
+        ])
+        .args(["--verify-hash", &"0".repeat(64)])
+        .arg("--audit-dir")
+        .arg(audit_dir.path())
+        .arg(image.path())
+        .env("XDG_DATA_HOME", data_home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("not the expected acquisition hash"),
+        "{}",
+        stderr
+    );
+
+    let log = audit_log_in(audit_dir.path());
+    assert!(log.contains("Acquisition hash mismatch"));
+    assert!(!log.contains("DISK_SCAN_START"));
+}
+
+#[test]
+fn test_verbose_logs_stay_off_json_stdout() {
+    let image = write_fixture_image(4);
+    let data_home = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["-vv", "scan", "--no-interactive", "--format", "json"])
+        .arg(image.path())
+        .env("XDG_DATA_HOME", data_home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("INFO"),
+        "expected logs on stderr: {}",
+        stderr
+    );
+    for level in ["TRACE", "DEBUG", "INFO", "WARN", "ERROR"] {
+        assert!(!stdout.contains(level), "{} log line on stdout", level);
+    }
+    serde_json::from_str::<serde_json::Value>(&stdout).unwrap();
+}
+
+#[test]
+fn test_quiet_scan_logs_nothing() {
+    let image = write_fixture_image(4);
+    let data_home = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .args(["scan", "--quiet", "--no-interactive", "--format", "json"])
+        .arg(image.path())
+        .env("XDG_DATA_HOME", data_home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+}
@@ -0,0 +1,214 @@
+//! Integration tests for `ghostfs session`
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use chrono::{Duration, Utc};
+use ghostfs_core::session::SessionDatabase;
+use ghostfs_core::{DeletedFile, FileSystemType, RecoverySession};
+
+/// A recoverable deleted JPEG
+fn deleted_file(id: u64, name: &str) -> DeletedFile {
+    common::deleted_file(id, name, "image/jpeg", id, 1)
+}
+
+/// Save a session of `fs_type` created `age_days` ago and return it
+fn save_session(
+    db_path: &Path,
+    fs_type: FileSystemType,
+    device: &str,
+    age_days: i64,
+    files: &[&str],
+) -> RecoverySession {
+    let scan_results: Vec<DeletedFile> = files
+        .iter()
+        .enumerate()
+        .map(|(i, name)| deleted_file(i as u64 + 1, name))
+        .collect();
+    let session = RecoverySession {
+        created_at: Utc::now() - Duration::days(age_days),
+        ..common::session(fs_type, device, 1024 * 1024, scan_results)
+    };
+    SessionDatabase::open(db_path)
+        .unwrap()
+        .save_session(&session)
+        .unwrap();
+    session
+}
+
+/// Run `ghostfs session --db <db_path>` with `args` and return its stdout
+fn session_command(db_path: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+        .arg("session")
+        .arg("--db")
+        .arg(db_path)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "session {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn short_id(session: &RecoverySession) -> String {
+    session.id.to_string()[..8].to_string()
+}
+
+#[test]
+fn test_session_list_filters_and_aligns() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let xfs = save_session(&db_path, FileSystemType::Xfs, "/dev/sdb1", 1, &["a.jpg"]);
+    let btrfs = save_session(
+        &db_path,
+        FileSystemType::Btrfs,
+        "/images/disk.img",
+        2,
+        &["b.jpg", "c.jpg"],
+    );
+
+    let listing = session_command(&db_path, &["list"]);
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("ID"));
+    // Newest first
+    assert!(lines[1].starts_with(&short_id(&xfs)));
+    assert!(lines[2].starts_with(&short_id(&btrfs)));
+    // Every row puts the device column at the same place
+    let device_column = lines[0].find("DEVICE").unwrap();
+    assert_eq!(lines[1].find("/dev/sdb1"), Some(device_column));
+    assert_eq!(lines[2].find("/images/disk.img"), Some(device_column));
+
+    let listing = session_command(&db_path, &["list", "--fs", "btrfs"]);
+    assert!(!listing.contains(&short_id(&xfs)));
+    assert!(listing.contains(&short_id(&btrfs)));
+
+    let listing = session_command(&db_path, &["list", "--device", "/dev/sdb1"]);
+    assert!(listing.contains(&short_id(&xfs)));
+    assert!(!listing.contains(&short_id(&btrfs)));
+}
+
+#[test]
+fn test_session_show_prints_details_and_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let session = save_session(
+        &db_path,
+        FileSystemType::ExFat,
+        "/dev/sdc1",
+        0,
+        &["holiday.jpg", "party.jpg"],
+    );
+
+    let details = session_command(&db_path, &["show", &short_id(&session)]);
+    assert!(details.contains(&format!("Session ID: {}", session.id)));
+    assert!(details.contains("File System: exFAT"));
+    assert!(details.contains("Device: /dev/sdc1"));
+    assert!(details.contains("Files Found: 2"));
+    assert!(details.contains("/home/user/holiday.jpg"));
+    assert!(details.contains("/home/user/party.jpg"));
+}
+
+#[test]
+fn test_session_rm_deletes_by_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let removed = save_session(&db_path, FileSystemType::Xfs, "/dev/sdb1", 0, &["a.jpg"]);
+    let kept = save_session(&db_path, FileSystemType::Xfs, "/dev/sdb1", 0, &["b.jpg"]);
+
+    let output = session_command(&db_path, &["rm", &short_id(&removed)]);
+    assert!(output.contains(&removed.id.to_string()));
+
+    let db = SessionDatabase::open(&db_path).unwrap();
+    assert!(!db.session_exists(&removed.id).unwrap());
+    assert!(db.session_exists(&kept.id).unwrap());
+}
+
+#[test]
+fn test_session_cleanup_removes_old_sessions() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let old = save_session(&db_path, FileSystemType::Xfs, "/dev/sdb1", 45, &["a.jpg"]);
+    let recent = save_session(&db_path, FileSystemType::Xfs, "/dev/sdb1", 3, &["b.jpg"]);
+
+    let output = session_command(&db_path, &["cleanup", "--days", "30"]);
+    assert!(output.contains("Removed 1 sessions"));
+
+    let db = SessionDatabase::open(&db_path).unwrap();
+    assert!(!db.session_exists(&old.id).unwrap());
+    assert!(db.session_exists(&recent.id).unwrap());
+}
+
+#[test]
+fn test_session_cleanup_rejects_out_of_range_days() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    for days in ["0", "-1", "9223372036854775807"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ghostfs-cli"))
+            .args(["session", "--db"])
+            .arg(&db_path)
+            .args(["cleanup", "--days", days])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "--days {} was accepted", days);
+    }
+}
+
+#[test]
+fn test_session_diff_reports_each_change() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("sessions.db");
+    let older = save_session(
+        &db_path,
+        FileSystemType::Xfs,
+        "/dev/sdb1",
+        7,
+        &["a.jpg", "b.jpg", "c.jpg"],
+    );
+
+    // A week later: a.jpg was overwritten, b.jpg partly, and d.jpg deleted
+    let mut newer = older.clone();
+    newer.id = uuid::Uuid::new_v4();
+    newer.created_at = Utc::now();
+    newer.scan_results[0].is_recoverable = false;
+    newer.scan_results[1].confidence_score = 0.6;
+    newer.scan_results.push(deleted_file(4, "d.jpg"));
+    SessionDatabase::open(&db_path)
+        .unwrap()
+        .save_session(&newer)
+        .unwrap();
+
+    let json = session_command(
+        &db_path,
+        &[
+            "diff",
+            &short_id(&older),
+            &short_id(&newer),
+            "--format",
+            "json",
+        ],
+    );
+    let diff: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let paths = |category: &str| -> Vec<String> {
+        diff[category]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|change| change["original_path"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(paths("newly_deleted"), vec!["/home/user/d.jpg"]);
+    assert_eq!(paths("became_unrecoverable"), vec!["/home/user/a.jpg"]);
+    assert_eq!(paths("confidence_changed"), vec!["/home/user/b.jpg"]);
+    assert_eq!(diff["unchanged"], 1);
+
+    let text = session_command(&db_path, &["diff", &short_id(&older), &short_id(&newer)]);
+    assert!(text.contains("Newly deleted: 1"));
+    assert!(text.contains("/home/user/b.jpg  (90.0% -> 60.0%)"));
+}
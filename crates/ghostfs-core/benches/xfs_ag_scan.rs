@@ -0,0 +1,90 @@
+//! Inode table scan of a multi-AG XFS image, one AG at a time and in parallel
+//!
+//! Run with `cargo bench -p ghostfs-core --bench xfs_ag_scan`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ghostfs_core::fs::common::BlockDevice;
+use ghostfs_core::fs::xfs::{XfsRecoveryConfig, XfsRecoveryEngine};
+
+const BLOCK_SIZE: usize = 4096;
+const INODE_SIZE: usize = 512;
+/// AG size as a power of two (512 blocks), so 64 inode table blocks are scanned per AG
+const AG_BLOCK_LOG: u32 = 9;
+const AG_COUNT: usize = 16;
+/// Where the scanner expects each AG's inode table
+const INODE_TABLE_BLOCK: usize = 4;
+const INODE_TABLE_BLOCKS: usize = 64;
+
+/// `AG_COUNT` AGs whose inode tables are full of deleted regular files
+fn multi_ag_image() -> Vec<u8> {
+    let ag_blocks = 1usize << AG_BLOCK_LOG;
+    let inodes_per_block = BLOCK_SIZE / INODE_SIZE;
+    let mut image = vec![0u8; AG_COUNT * ag_blocks * BLOCK_SIZE];
+
+    image[0..4].copy_from_slice(b"XFSB");
+    image[4..8].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes());
+    image[8..16].copy_from_slice(&((AG_COUNT * ag_blocks) as u64).to_be_bytes());
+    image[56..64].copy_from_slice(&32u64.to_be_bytes()); // root inode
+    image[84..88].copy_from_slice(&(ag_blocks as u32).to_be_bytes());
+    image[88..92].copy_from_slice(&(AG_COUNT as u32).to_be_bytes());
+    image[100..102].copy_from_slice(&0xB4A5u16.to_be_bytes()); // v5
+    image[102..104].copy_from_slice(&512u16.to_be_bytes()); // sector size
+    image[104..106].copy_from_slice(&(INODE_SIZE as u16).to_be_bytes());
+    image[123] = inodes_per_block.trailing_zeros() as u8;
+    image[124] = AG_BLOCK_LOG as u8;
+
+    for ag in 0..AG_COUNT {
+        for agbno in INODE_TABLE_BLOCK..INODE_TABLE_BLOCK + INODE_TABLE_BLOCKS {
+            for slot in 0..inodes_per_block {
+                let ino = (ag << (AG_BLOCK_LOG + 3)) | (agbno << 3) | slot;
+                let start = (ag * ag_blocks + agbno) * BLOCK_SIZE + slot * INODE_SIZE;
+                let inode = &mut image[start..start + INODE_SIZE];
+                inode[0..2].copy_from_slice(b"IN");
+                inode[2..4].copy_from_slice(&0x81A4u16.to_be_bytes()); // regular file
+                inode[4] = 3; // v3 inode
+                inode[5] = 3; // inline data, as in the XFS unit tests
+                inode[8..12].copy_from_slice(&1u32.to_be_bytes());
+                inode[56..64].copy_from_slice(&100u64.to_be_bytes());
+                inode[64..72].copy_from_slice(&1u64.to_be_bytes());
+                inode[80] = 0x60; // mtime
+                inode[152..160].copy_from_slice(&(ino as u64).to_be_bytes());
+            }
+        }
+    }
+    image
+}
+
+fn bench_ag_scan(c: &mut Criterion) {
+    let image = multi_ag_image();
+    let mut group = c.benchmark_group("xfs_ag_scan");
+    group.sample_size(20);
+
+    for parallel_ags in [false, true] {
+        let config = XfsRecoveryConfig {
+            parallel_ags,
+            ..Default::default()
+        };
+        let engine =
+            XfsRecoveryEngine::new_with_config(BlockDevice::from_bytes(image.clone()), config)
+                .unwrap();
+        let found = engine.scan_deleted_files().unwrap().len();
+        assert!(
+            found >= AG_COUNT * INODE_TABLE_BLOCKS,
+            "found {} files",
+            found
+        );
+
+        let name = if parallel_ags {
+            "parallel"
+        } else {
+            "sequential"
+        };
+        group.bench_function(BenchmarkId::new(name, AG_COUNT), |b| {
+            b.iter(|| engine.scan_deleted_files().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_ag_scan);
+criterion_main!(benches);
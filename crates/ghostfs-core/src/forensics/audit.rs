@@ -2,14 +2,24 @@
 ///
 /// This module provides comprehensive logging of all recovery operations
 /// for legal compliance, chain of custody, and investigative transparency.
+///
+/// Entries form a hash chain: each one stores the SHA-256 of its predecessor
+/// and of its own content, so editing, inserting or removing an entry breaks
+/// the chain. Sealing a log records the final hash (the chain tip) next to it.
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
-use std::io::{self, Write};
+use std::io::{self, BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+use crate::forensics::{CaseMetadata, HashVerification, VerificationStatus};
+
+/// `previous_hash` of the first entry in a chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
 /// Types of auditable events
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -22,6 +32,8 @@ pub enum AuditEventType {
     DiskScanStart,
     /// Disk scan completed
     DiskScanComplete,
+    /// Recovery session saved to the session database
+    SessionSaved,
     /// File signature detected
     FileDetected,
     /// File successfully recovered
@@ -61,6 +73,10 @@ pub struct AuditEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
 
+    /// Case the session belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case: Option<CaseMetadata>,
+
     /// Event description
     pub message: String,
 
@@ -70,6 +86,68 @@ pub struct AuditEntry {
 
     /// Severity level
     pub severity: AuditSeverity,
+
+    /// Hash of the previous entry, or `GENESIS_HASH` for the first one
+    #[serde(default)]
+    pub previous_hash: String,
+
+    /// SHA-256 over this entry's content, including `previous_hash`
+    #[serde(default)]
+    pub entry_hash: String,
+}
+
+impl AuditEntry {
+    /// Compute the hash this entry should carry in `entry_hash`
+    ///
+    /// The entry is hashed as JSON with sorted keys and an empty `entry_hash`,
+    /// so the result does not depend on metadata map ordering.
+    pub fn compute_hash(&self) -> String {
+        let mut unhashed = self.clone();
+        unhashed.entry_hash.clear();
+        let canonical = serde_json::to_value(&unhashed)
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+        format!("{:x}", Sha256::digest(canonical.as_bytes()))
+    }
+}
+
+/// Final hash of a sealed audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainTip {
+    pub session_id: String,
+    pub entry_count: u64,
+    pub tip_hash: String,
+    pub sealed_at: DateTime<Utc>,
+}
+
+/// Check the hash chain of a sequence of entries and return its tip
+///
+/// Fails on the first entry whose ID, link to its predecessor or own hash
+/// does not match.
+pub fn verify_chain(entries: &[AuditEntry]) -> io::Result<String> {
+    let mut previous = GENESIS_HASH.to_string();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let broken = |reason: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Audit chain broken at entry {}: {}", entry.id, reason),
+            )
+        };
+
+        if entry.id != index as u64 + 1 {
+            return Err(broken("entry out of sequence"));
+        }
+        if entry.previous_hash != previous {
+            return Err(broken("previous hash does not match"));
+        }
+        if entry.entry_hash != entry.compute_hash() {
+            return Err(broken("content was modified"));
+        }
+        previous = entry.entry_hash.clone();
+    }
+
+    Ok(previous)
 }
 
 /// Severity levels for audit events
@@ -146,6 +224,9 @@ pub struct AuditLog {
 
     /// Log file handle
     log_file: Arc<Mutex<File>>,
+
+    /// Case recorded in every entry
+    case: Option<CaseMetadata>,
 }
 
 impl AuditLog {
@@ -172,26 +253,43 @@ impl AuditLog {
             entries: Arc::new(Mutex::new(Vec::new())),
             next_id: Arc::new(Mutex::new(1)),
             log_file: Arc::new(Mutex::new(log_file)),
+            case: None,
         })
     }
 
+    /// Record `case` in every entry logged from now on
+    pub fn with_case_metadata(mut self, case: Option<CaseMetadata>) -> Self {
+        self.case = case;
+        self
+    }
+
     /// Log an audit event
     pub fn log(&self, event: AuditEvent) -> io::Result<u64> {
+        // Held for the whole append so concurrent entries chain in ID order
+        let mut entries = self.entries.lock().unwrap();
+
         let mut next_id = self.next_id.lock().unwrap();
         let id = *next_id;
         *next_id += 1;
         drop(next_id);
 
-        let entry = AuditEntry {
+        let mut entry = AuditEntry {
             id,
             timestamp: Utc::now(),
             event_type: event.event_type,
             session_id: self.session_id.clone(),
             user: event.user,
+            case: self.case.clone(),
             message: event.message,
             metadata: event.metadata,
             severity: event.severity,
+            previous_hash: entries
+                .last()
+                .map(|last| last.entry_hash.clone())
+                .unwrap_or_else(|| GENESIS_HASH.to_string()),
+            entry_hash: String::new(),
         };
+        entry.entry_hash = entry.compute_hash();
 
         // Write to file (JSONL format - one JSON object per line)
         let json = serde_json::to_string(&entry)
@@ -203,7 +301,6 @@ impl AuditLog {
         drop(file);
 
         // Store in memory
-        let mut entries = self.entries.lock().unwrap();
         entries.push(entry);
 
         Ok(id)
@@ -241,11 +338,106 @@ impl AuditLog {
         &self.log_path
     }
 
+    /// Path of the chain tip written by [`AuditLog::seal`]
+    pub fn tip_path(&self) -> PathBuf {
+        self.log_path.with_extension("tip")
+    }
+
+    /// Hash of the latest entry
+    pub fn chain_tip(&self) -> String {
+        self.entries
+            .lock()
+            .unwrap()
+            .last()
+            .map(|entry| entry.entry_hash.clone())
+            .unwrap_or_else(|| GENESIS_HASH.to_string())
+    }
+
+    /// Record the current chain tip next to the log file
+    pub fn seal(&self) -> io::Result<ChainTip> {
+        let entries = self.entries.lock().unwrap();
+        let tip = ChainTip {
+            session_id: self.session_id.clone(),
+            entry_count: entries.len() as u64,
+            tip_hash: entries
+                .last()
+                .map(|entry| entry.entry_hash.clone())
+                .unwrap_or_else(|| GENESIS_HASH.to_string()),
+            sealed_at: Utc::now(),
+        };
+
+        let json = serde_json::to_string_pretty(&tip)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        std::fs::write(self.tip_path(), json)?;
+        Ok(tip)
+    }
+
+    /// Verify that the log on disk is intact and matches what was logged
+    pub fn verify_chain(&self) -> io::Result<()> {
+        let tip = Self::verify_file(&self.log_path)?;
+        if tip != self.chain_tip() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Audit log on disk does not end at the current chain tip",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Verify an audit log file and return its chain tip
+    ///
+    /// If the log was sealed, the entry count and tip must also match the
+    /// sealed values, which catches entries removed from the end.
+    pub fn verify_file(log_path: impl AsRef<Path>) -> io::Result<String> {
+        let log_path = log_path.as_ref();
+        let mut entries = Vec::new();
+        for line in BufReader::new(File::open(log_path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: AuditEntry = serde_json::from_str(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            entries.push(entry);
+        }
+
+        let tip_hash = verify_chain(&entries)?;
+
+        let tip_path = log_path.with_extension("tip");
+        if tip_path.exists() {
+            let sealed: ChainTip = serde_json::from_str(&std::fs::read_to_string(&tip_path)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            // Entries may legitimately follow a seal, but the sealed ones must be intact
+            let sealed_hash = match sealed.entry_count {
+                0 => Some(GENESIS_HASH),
+                n => entries
+                    .get(n as usize - 1)
+                    .map(|entry| entry.entry_hash.as_str()),
+            };
+            if sealed_hash != Some(sealed.tip_hash.as_str()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Audit log does not match its sealed tip ({} entries)",
+                        sealed.entry_count
+                    ),
+                ));
+            }
+        }
+
+        Ok(tip_hash)
+    }
+
     /// Get session ID
     pub fn session_id(&self) -> &str {
         &self.session_id
     }
 
+    /// Case recorded in every entry, if any
+    pub fn case_metadata(&self) -> Option<&CaseMetadata> {
+        self.case.as_ref()
+    }
+
     /// Export to JSON
     pub fn export_json(&self, output_path: impl AsRef<Path>) -> io::Result<()> {
         let entries = self.get_entries();
@@ -297,7 +489,7 @@ impl AuditLog {
         AuditStatistics {
             total_entries: entries.len(),
             event_type_counts: event_counts,
-            severity_counts: severity_counts,
+            severity_counts,
             first_entry_time: entries.first().map(|e| e.timestamp),
             last_entry_time: entries.last().map(|e| e.timestamp),
         }
@@ -315,6 +507,7 @@ pub struct AuditStatistics {
 }
 
 /// Convenience wrapper for audit logging
+#[derive(Clone)]
 pub struct AuditLogger {
     log: Arc<AuditLog>,
 }
@@ -334,15 +527,53 @@ impl AuditLogger {
         Ok(())
     }
 
-    /// Log session end
+    /// Log session end and seal the log
     pub fn session_end(&self, status: &str) -> io::Result<()> {
         self.log.log(
             AuditEvent::new(AuditEventType::SessionEnd, "Recovery session ended")
                 .with_metadata("status", status),
         )?;
+        self.log.seal()?;
+        Ok(())
+    }
+
+    /// Log scan start
+    pub fn scan_started(&self, fs_type: &str, device_size: u64) -> io::Result<()> {
+        self.log.log(
+            AuditEvent::new(AuditEventType::DiskScanStart, "Disk scan started")
+                .with_metadata("filesystem", fs_type)
+                .with_metadata("device_size", device_size.to_string()),
+        )?;
+        Ok(())
+    }
+
+    /// Log scan completion
+    pub fn scan_completed(&self, files_found: usize, duration_ms: u64) -> io::Result<()> {
+        self.log.log(
+            AuditEvent::new(AuditEventType::DiskScanComplete, "Disk scan completed")
+                .with_metadata("files_found", files_found.to_string())
+                .with_metadata("duration_ms", duration_ms.to_string()),
+        )?;
+        Ok(())
+    }
+
+    /// Log session saved to the database
+    pub fn session_saved(&self, session_id: &str, files: usize) -> io::Result<()> {
+        self.log.log(
+            AuditEvent::new(
+                AuditEventType::SessionSaved,
+                format!("Session saved: {}", session_id),
+            )
+            .with_metadata("files", files.to_string()),
+        )?;
         Ok(())
     }
 
+    /// Underlying audit log
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.log
+    }
+
     /// Log file detected
     pub fn file_detected(&self, path: &str, signature: &str, confidence: f32) -> io::Result<()> {
         self.log.log(
@@ -356,8 +587,8 @@ impl AuditLogger {
         Ok(())
     }
 
-    /// Log file recovered
-    pub fn file_recovered(&self, path: &str, size: u64, inode: u64) -> io::Result<()> {
+    /// Log file recovered, returning the ID of the entry
+    pub fn file_recovered(&self, path: &str, size: u64, inode: u64) -> io::Result<u64> {
         self.log.log(
             AuditEvent::new(
                 AuditEventType::FileRecovered,
@@ -365,8 +596,7 @@ impl AuditLogger {
             )
             .with_metadata("size_bytes", size.to_string())
             .with_metadata("inode", inode.to_string()),
-        )?;
-        Ok(())
+        )
     }
 
     /// Log file exported
@@ -396,6 +626,33 @@ impl AuditLogger {
         Ok(())
     }
 
+    /// Log a check of the source image against its acquisition hash
+    pub fn acquisition_verified(&self, verification: &HashVerification) -> io::Result<()> {
+        let matched = verification.status == VerificationStatus::Verified;
+        self.log.log(
+            AuditEvent::new(
+                AuditEventType::VerificationPerformed,
+                if matched {
+                    format!("Acquisition hash verified: {}", verification.file_path)
+                } else {
+                    format!("Acquisition hash mismatch: {}", verification.file_path)
+                },
+            )
+            .with_metadata("algorithm", verification.algorithm.name())
+            .with_metadata(
+                "expected_hash",
+                verification.expected_hash.clone().unwrap_or_default(),
+            )
+            .with_metadata("actual_hash", verification.actual_hash.clone())
+            .with_severity(if matched {
+                AuditSeverity::Info
+            } else {
+                AuditSeverity::Critical
+            }),
+        )?;
+        Ok(())
+    }
+
     /// Log error
     pub fn error(&self, message: &str, details: &str) -> io::Result<()> {
         self.log.log(
@@ -460,6 +717,96 @@ mod tests {
         assert_eq!(entries.len(), 3);
     }
 
+    #[test]
+    fn test_modified_middle_entry_breaks_chain() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = Arc::new(AuditLog::new("test-session", temp_dir.path()).unwrap());
+        let logger = AuditLogger::new(log.clone());
+
+        logger.session_start("/dev/sda1").unwrap();
+        logger.file_recovered("a.jpg", 1024, 131).unwrap();
+        logger.file_recovered("b.jpg", 2048, 132).unwrap();
+        logger.session_end("2 recovered").unwrap();
+
+        log.verify_chain().unwrap();
+        assert!(log.tip_path().exists());
+        assert_eq!(
+            AuditLog::verify_file(log.log_path()).unwrap(),
+            log.chain_tip()
+        );
+
+        // Change the size of the second entry without touching its hashes
+        let contents = std::fs::read_to_string(log.log_path()).unwrap();
+        let tampered = contents.replacen("\"1024\"", "\"4096\"", 1);
+        assert_ne!(contents, tampered);
+        std::fs::write(log.log_path(), tampered).unwrap();
+
+        let err = log.verify_chain().unwrap_err();
+        assert!(err.to_string().contains("entry 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_removed_entries_break_chain() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new("test-session", temp_dir.path()).unwrap();
+        for i in 0..4 {
+            log.log(AuditEvent::new(
+                AuditEventType::FileDetected,
+                format!("File {}", i),
+            ))
+            .unwrap();
+        }
+        log.seal().unwrap();
+
+        let contents = std::fs::read_to_string(log.log_path()).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+
+        // Dropping a middle entry breaks the link of its successor
+        let without_middle = [lines[0], lines[2], lines[3]].join("\n");
+        std::fs::write(log.log_path(), without_middle).unwrap();
+        assert!(AuditLog::verify_file(log.log_path()).is_err());
+
+        // Dropping the tail leaves a valid chain, but not the sealed one
+        std::fs::write(log.log_path(), lines[..3].join("\n")).unwrap();
+        assert!(AuditLog::verify_file(log.log_path()).is_err());
+    }
+
+    #[test]
+    fn test_case_metadata_recorded_in_every_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let case = CaseMetadata {
+            examiner: Some("J. Doe".to_string()),
+            case_number: Some("2024-0117".to_string()),
+            evidence_id: Some("EV-3".to_string()),
+            acquisition_hash: None,
+        };
+        let log = Arc::new(
+            AuditLog::new("test-session", temp_dir.path())
+                .unwrap()
+                .with_case_metadata(Some(case.clone())),
+        );
+        let logger = AuditLogger::new(log.clone());
+
+        logger.session_start("/dev/sda1").unwrap();
+        logger.file_recovered("a.jpg", 1024, 131).unwrap();
+        logger.session_end("1 recovered").unwrap();
+
+        let contents = std::fs::read_to_string(log.log_path()).unwrap();
+        let entries: Vec<AuditEntry> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 3);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.case.as_ref() == Some(&case)));
+        log.verify_chain().unwrap();
+
+        // The case is covered by the entry hashes like any other content
+        std::fs::write(log.log_path(), contents.replacen("EV-3", "EV-4", 1)).unwrap();
+        assert!(log.verify_chain().is_err());
+    }
+
     #[test]
     fn test_audit_export_json() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,37 @@
+/// Case identity attached to sessions, audit logs and recovery reports
+///
+/// Evidence is only admissible if every artifact can be tied back to the
+/// case, the item of evidence and the examiner who handled it.
+use serde::{Deserialize, Serialize};
+
+/// Identity of the case a recovery belongs to; every field is optional
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaseMetadata {
+    /// Name of the examiner handling the evidence
+    pub examiner: Option<String>,
+
+    /// Case number assigned by the investigating organisation
+    pub case_number: Option<String>,
+
+    /// ID of the item of evidence the image was acquired from
+    pub evidence_id: Option<String>,
+
+    /// Hex-encoded SHA-256 of the image, recorded at acquisition
+    pub acquisition_hash: Option<String>,
+}
+
+impl CaseMetadata {
+    /// Whether no field is set
+    pub fn is_empty(&self) -> bool {
+        self.examiner.is_none()
+            && self.case_number.is_none()
+            && self.evidence_id.is_none()
+            && self.acquisition_hash.is_none()
+    }
+
+    /// `Some(self)` unless no field is set
+    pub fn non_empty(self) -> Option<Self> {
+        (!self.is_empty()).then_some(self)
+    }
+}
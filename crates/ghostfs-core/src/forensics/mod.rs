@@ -1,13 +1,21 @@
 /// Forensic features for legal and investigative use cases
 pub mod audit;
+pub mod case;
 pub mod recovery;
 pub mod verification;
 
-pub use audit::{AuditEntry, AuditEvent, AuditEventType, AuditLog, AuditLogger};
+pub use audit::{AuditEntry, AuditEvent, AuditEventType, AuditLog, AuditLogger, ChainTip};
+
+pub use case::CaseMetadata;
 
 pub use verification::{
-    calculate_file_hash, calculate_hash, verify_file_integrity, FileHash, HashAlgorithm,
-    HashManifest, HashVerification, VerificationResult, VerificationStatus,
+    calculate_device_hash, calculate_file_hash, calculate_hash, calculate_image_hash,
+    verify_device_integrity, verify_file_integrity, verify_image_integrity, verify_structure,
+    verify_structure_bytes, FileHash, HashAlgorithm, HashManifest, HashVerification,
+    StructuralValidity, VerificationResult, VerificationStatus,
 };
 
-pub use recovery::{recover_files_with_forensics, ForensicsConfig, ForensicsRecoveryReport};
+pub use recovery::{
+    generate_session_manifest, recover_files_with_forensics, ForensicsConfig, ForensicsFileRecord,
+    ForensicsRecoveryReport, FORENSICS_REPORT_FILE, SESSION_MANIFEST_FILE,
+};
@@ -3,18 +3,27 @@
 /// This module provides forensics-enabled recovery operations that integrate
 /// audit trail logging and hash verification for legal/forensic use cases.
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::forensics::{
-    AuditEvent, AuditEventType, AuditLog, AuditLogger, HashAlgorithm, HashManifest,
+    calculate_file_hash, verify_structure, AuditEvent, AuditEventType, AuditLog, AuditLogger,
+    CaseMetadata, FileHash, HashAlgorithm, HashManifest, StructuralValidity, VerificationStatus,
 };
 use crate::{
-    DeletedFile, FileRecoveryResult, FileSystemType, RecoveryReport, RecoverySession,
-    RecoveryStatus,
+    assign_recovery_paths, BlockRange, DeletedFile, FileRecoveryResult, OutputLayout,
+    RecoveryReport, RecoverySession, RecoveryStatus,
 };
 
+/// Name of the manifest written by [`generate_session_manifest`]
+pub const SESSION_MANIFEST_FILE: &str = "manifest.json";
+
+/// Name the CLI gives the JSON export of a [`ForensicsRecoveryReport`]
+pub const FORENSICS_REPORT_FILE: &str = "forensics_report.json";
+
 /// Configuration for forensics-enabled recovery
 #[derive(Debug, Clone)]
 pub struct ForensicsConfig {
@@ -38,6 +47,16 @@ pub struct ForensicsConfig {
 
     /// Enable smart extent reconstruction
     pub enable_extent_reconstruction: bool,
+
+    /// Check recovered files decode and adjust their reported confidence
+    pub enable_structure_verification: bool,
+
+    /// How recovered files are arranged under the output directory
+    pub output_layout: OutputLayout,
+
+    /// Case the recovery belongs to, recorded in the audit log and report;
+    /// defaults to the session's
+    pub case_metadata: Option<CaseMetadata>,
 }
 
 impl Default for ForensicsConfig {
@@ -50,6 +69,9 @@ impl Default for ForensicsConfig {
             manifest_path: None,
             enable_partial_recovery: false,
             enable_extent_reconstruction: false,
+            enable_structure_verification: false,
+            output_layout: OutputLayout::default(),
+            case_metadata: None,
         }
     }
 }
@@ -65,6 +87,9 @@ impl ForensicsConfig {
             manifest_path: Some(output_dir.join("hash_manifest.json")),
             enable_partial_recovery: true,
             enable_extent_reconstruction: true,
+            enable_structure_verification: true,
+            output_layout: OutputLayout::default(),
+            case_metadata: None,
         }
     }
 
@@ -89,11 +114,32 @@ impl ForensicsConfig {
 }
 
 /// Enhanced recovery report with forensics data
-#[derive(Debug, Clone)]
+///
+/// This is the record of a recovery an analyst attaches to a case: who ran
+/// it, what was recovered from where, and how far each file can be trusted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForensicsRecoveryReport {
+    /// Recovery session the files came from
+    pub session_id: String,
+
+    /// Image or device the files were read from
+    pub source_path: PathBuf,
+
+    /// When the recovery finished
+    pub generated_at: DateTime<Utc>,
+
+    /// Case the recovery belongs to (examiner, case number, evidence ID)
+    pub case_metadata: Option<CaseMetadata>,
+
+    /// Algorithm of the per-file hashes (if hash verification was enabled)
+    pub hash_algorithm: Option<HashAlgorithm>,
+
     /// Standard recovery report
     pub report: RecoveryReport,
 
+    /// Forensic record of every file recovery was attempted on
+    pub files: Vec<ForensicsFileRecord>,
+
     /// Path to audit log (if enabled)
     pub audit_log_path: Option<PathBuf>,
 
@@ -105,6 +151,69 @@ pub struct ForensicsRecoveryReport {
 
     /// Number of extent reconstructions performed
     pub extent_reconstructions: usize,
+
+    /// Structure check of each recovered file with a known type (if enabled)
+    pub structural_validity: HashMap<u64, StructuralValidity>,
+}
+
+impl ForensicsRecoveryReport {
+    /// Forensic record of the file with the given ID
+    pub fn file(&self, file_id: u64) -> Option<&ForensicsFileRecord> {
+        self.files.iter().find(|record| record.file_id == file_id)
+    }
+
+    /// Serialize the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Write the report as JSON to `path`
+    pub fn export_json(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+/// Forensic record of one recovered file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForensicsFileRecord {
+    /// ID of the file in the recovery session
+    pub file_id: u64,
+
+    /// Where the file was written
+    pub recovered_path: PathBuf,
+
+    /// Source ranges the contents were read from, in the units of the
+    /// session's file system
+    pub source_blocks: Vec<BlockRange>,
+
+    /// Confidence in the recovered contents, after any structure check
+    pub confidence_score: f32,
+
+    /// Hash of the recovered file (if hash verification was enabled)
+    pub hash: Option<FileHash>,
+
+    /// Whether the hash was calculated; recovery has no reference to check
+    /// it against, which is what the manifest is for
+    pub verification_status: VerificationStatus,
+
+    /// Structure check of the recovered file (if enabled and its type known)
+    pub structural_validity: Option<StructuralValidity>,
+
+    /// ID of the audit log entry recording the recovery (if audit was enabled)
+    pub audit_entry_id: Option<u64>,
+
+    /// Outcome of the recovery
+    pub status: RecoveryStatus,
+}
+
+/// What [`recover_single_file_forensics`] did with one file
+struct SingleFileRecovery {
+    bytes_written: u64,
+    was_partial: bool,
+    was_reconstructed: bool,
+    hash: Option<FileHash>,
+    audit_entry_id: Option<u64>,
 }
 
 /// Recover files with forensics features enabled
@@ -121,6 +230,11 @@ pub fn recover_files_with_forensics(
     // Create output directory
     create_dir_all(output_dir)?;
 
+    let case_metadata = config
+        .case_metadata
+        .clone()
+        .or_else(|| session.case_metadata.clone());
+
     // Initialize audit logger if enabled
     let mut audit_logger: Option<AuditLogger> = if config.enable_audit {
         let log_path_dir = config
@@ -128,7 +242,8 @@ pub fn recover_files_with_forensics(
             .clone()
             .and_then(|p| p.parent().map(|p| p.to_path_buf()))
             .unwrap_or_else(|| output_dir.to_path_buf());
-        let audit_log = AuditLog::new(&session.id.to_string(), log_path_dir)?;
+        let audit_log = AuditLog::new(session.id.to_string(), log_path_dir)?
+            .with_case_metadata(case_metadata.clone());
         Some(AuditLogger::new(Arc::new(audit_log)))
     } else {
         None
@@ -137,7 +252,7 @@ pub fn recover_files_with_forensics(
     // Initialize hash manifest if enabled
     let mut hash_manifest = if config.enable_hash_verification {
         Some(HashManifest::new(
-            &session.id.to_string(),
+            session.id.to_string(),
             config.hash_algorithm,
         ))
     } else {
@@ -159,6 +274,8 @@ pub fn recover_files_with_forensics(
     let mut recovery_details = Vec::new();
     let mut partial_recoveries = 0;
     let mut extent_reconstructions = 0;
+    let mut structural_validity = HashMap::new();
+    let mut file_records = Vec::new();
 
     // Filter files to recover
     let files_to_recover: Vec<&DeletedFile> = if let Some(ids) = file_ids {
@@ -175,6 +292,9 @@ pub fn recover_files_with_forensics(
             .collect()
     };
 
+    let output_paths =
+        assign_recovery_paths(output_dir, &session.scan_results, config.output_layout);
+
     tracing::info!(
         "Starting forensics recovery of {} files",
         files_to_recover.len()
@@ -182,6 +302,8 @@ pub fn recover_files_with_forensics(
 
     // Recover each file
     for deleted_file in &files_to_recover {
+        let output_path = &output_paths[&deleted_file.id];
+
         // Log file detection
         if let Some(ref mut logger) = audit_logger {
             let path = deleted_file
@@ -201,41 +323,80 @@ pub fn recover_files_with_forensics(
         match recover_single_file_forensics(
             &mmap,
             deleted_file,
-            output_dir,
-            session.fs_type,
+            output_path,
+            crate::block_offset_multiplier(session.fs_type, session.metadata.block_size),
             &mut audit_logger,
             &mut hash_manifest,
             &config,
         ) {
-            Ok((bytes_recovered, was_partial, was_reconstructed)) => {
+            Ok(recovered) => {
+                let bytes_recovered = recovered.bytes_written;
                 recovered_count += 1;
                 total_bytes_recovered += bytes_recovered;
 
-                if was_partial {
+                if recovered.was_partial {
                     partial_recoveries += 1;
                 }
-                if was_reconstructed {
+                if recovered.was_reconstructed {
                     extent_reconstructions += 1;
                 }
 
-                let recovered_path = generate_recovery_path(output_dir, deleted_file);
+                let mut confidence_score = deleted_file.confidence_score;
+                let mime = deleted_file.metadata.mime_type.as_deref();
+                if let Some(mime) = mime.filter(|_| config.enable_structure_verification) {
+                    let validity = verify_structure(output_path, mime);
+                    confidence_score = validity.adjust_confidence(confidence_score);
+
+                    if validity.is_damaged() {
+                        if let Some(ref mut logger) = audit_logger {
+                            logger.audit_log().log(
+                                AuditEvent::new(
+                                    AuditEventType::Warning,
+                                    format!("Recovered file {} is {}", deleted_file.id, validity),
+                                )
+                                .with_metadata("mime_type", mime.to_string()),
+                            )?;
+                        }
+                    }
+                    structural_validity.insert(deleted_file.id, validity);
+                }
+
+                file_records.push(ForensicsFileRecord {
+                    file_id: deleted_file.id,
+                    recovered_path: output_path.clone(),
+                    source_blocks: deleted_file.data_blocks.clone(),
+                    confidence_score,
+                    verification_status: if recovered.hash.is_some() {
+                        VerificationStatus::Calculated
+                    } else {
+                        VerificationStatus::NoReference
+                    },
+                    hash: recovered.hash,
+                    structural_validity: structural_validity.get(&deleted_file.id).cloned(),
+                    audit_entry_id: recovered.audit_entry_id,
+                    status: RecoveryStatus::Success,
+                });
 
                 recovery_details.push(FileRecoveryResult {
                     file_id: deleted_file.id,
                     original_path: deleted_file.original_path.clone(),
-                    recovered_path: recovered_path.clone(),
+                    recovered_path: output_path.clone(),
                     size: deleted_file.size,
                     bytes_recovered,
                     status: RecoveryStatus::Success,
-                    confidence_score: deleted_file.confidence_score,
+                    confidence_score,
                 });
 
                 tracing::info!(
                     "✅ Recovered file ID {} ({} bytes){}{}",
                     deleted_file.id,
                     bytes_recovered,
-                    if was_partial { " [PARTIAL]" } else { "" },
-                    if was_reconstructed {
+                    if recovered.was_partial {
+                        " [PARTIAL]"
+                    } else {
+                        ""
+                    },
+                    if recovered.was_reconstructed {
                         " [RECONSTRUCTED]"
                     } else {
                         ""
@@ -253,10 +414,22 @@ pub fn recover_files_with_forensics(
                     );
                 }
 
+                file_records.push(ForensicsFileRecord {
+                    file_id: deleted_file.id,
+                    recovered_path: output_path.clone(),
+                    source_blocks: deleted_file.data_blocks.clone(),
+                    confidence_score: deleted_file.confidence_score,
+                    hash: None,
+                    verification_status: VerificationStatus::NoReference,
+                    structural_validity: None,
+                    audit_entry_id: None,
+                    status: RecoveryStatus::Failed(e.to_string()),
+                });
+
                 recovery_details.push(FileRecoveryResult {
                     file_id: deleted_file.id,
                     original_path: deleted_file.original_path.clone(),
-                    recovered_path: generate_recovery_path(output_dir, deleted_file),
+                    recovered_path: output_path.clone(),
                     size: deleted_file.size,
                     bytes_recovered: 0,
                     status: RecoveryStatus::Failed(e.to_string()),
@@ -291,69 +464,132 @@ pub fn recover_files_with_forensics(
         None
     };
 
-    // Log session end
+    // Log session end, which seals the audit log
+    let mut audit_log_path = None;
     if let Some(ref mut logger) = audit_logger {
         logger.session_end(&format!(
             "{} recovered, {} failed",
             recovered_count, failed_count
         ))?;
+        audit_log_path = Some(logger.audit_log().log_path().to_path_buf());
     }
 
     let report = RecoveryReport {
         total_files: files_to_recover.len(),
         recovered_files: recovered_count,
+        partial_files: 0,
         failed_files: failed_count,
         total_bytes_recovered,
         output_directory: output_dir.to_path_buf(),
         recovery_details,
+        read_errors: Default::default(),
     };
 
     Ok(ForensicsRecoveryReport {
+        session_id: session.id.to_string(),
+        source_path: image_path.to_path_buf(),
+        generated_at: Utc::now(),
+        case_metadata,
+        hash_algorithm: hash_manifest.as_ref().map(|manifest| manifest.algorithm),
         report,
-        audit_log_path: config.audit_log_path,
+        files: file_records,
+        audit_log_path,
         manifest_path,
         partial_recoveries,
         extent_reconstructions,
+        structural_validity,
     })
 }
 
+/// Hash every recovered file of a session and write `manifest.json`
+///
+/// Files are looked up where recovery put them in `output_dir` and keyed by
+/// their path relative to it, so the manifest stays valid if the directory is
+/// moved. Files of the session that are not on disk are skipped. `layout`
+/// must be the one the files were recovered with.
+pub fn generate_session_manifest(
+    output_dir: &Path,
+    session: &RecoverySession,
+    algo: HashAlgorithm,
+    layout: OutputLayout,
+) -> Result<HashManifest> {
+    let mut manifest = HashManifest::new(session.id.to_string(), algo);
+    let mut output_paths = assign_recovery_paths(output_dir, &session.scan_results, layout);
+
+    for deleted_file in &session.scan_results {
+        let path = output_paths.remove(&deleted_file.id).unwrap();
+        if !path.is_file() {
+            continue;
+        }
+
+        let key = path
+            .strip_prefix(output_dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .into_owned();
+        manifest.add_file(key.clone(), calculate_file_hash(&path, algo)?);
+        manifest.file_ids.insert(deleted_file.id, key);
+    }
+
+    manifest.export_json(output_dir.join(SESSION_MANIFEST_FILE))?;
+    tracing::info!(
+        "Wrote {} manifest for {} files",
+        algo.name(),
+        manifest.files.len()
+    );
+
+    Ok(manifest)
+}
+
 /// Recover a single file with forensics support
+///
+/// `offset_multiplier` converts the file's data blocks to device bytes.
 fn recover_single_file_forensics(
     mmap: &memmap2::Mmap,
     deleted_file: &DeletedFile,
-    output_dir: &Path,
-    fs_type: FileSystemType,
+    output_path: &Path,
+    offset_multiplier: u64,
     audit_logger: &mut Option<AuditLogger>,
     hash_manifest: &mut Option<HashManifest>,
     config: &ForensicsConfig,
-) -> Result<(u64, bool, bool)> {
+) -> Result<SingleFileRecovery> {
     use std::fs::File;
     use std::io::Write;
 
-    let output_path = generate_recovery_path(output_dir, deleted_file);
-    let mut output_file = File::create(&output_path)?;
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut output_file = File::create(output_path)?;
     let mut bytes_written = 0u64;
 
-    let offset_multiplier = match fs_type {
-        FileSystemType::Xfs => 4096,
-        FileSystemType::Btrfs => 4096,
-        FileSystemType::ExFat => 1,
-    };
-
     let mut was_partial = false;
     let mut was_reconstructed = false;
 
-    // Check if extent reconstruction is needed
-    if config.enable_extent_reconstruction && deleted_file.data_blocks.is_empty() {
+    if let Some(inline) = deleted_file.inline_data() {
+        let len = std::cmp::min(inline.len() as u64, deleted_file.size);
+        output_file.write_all(&inline[..len as usize])?;
+        bytes_written = len;
+    } else if config.enable_extent_reconstruction && deleted_file.data_blocks.is_empty() {
+        // Check if extent reconstruction is needed
         was_reconstructed = true;
         // TODO: Use ExtentReconstructor here
         // For now, just mark as reconstructed
     }
 
-    // Recover data from block ranges
+    // Recover data from block ranges, with the zeros of any holes before each
     for block_range in &deleted_file.data_blocks {
-        let start_offset = block_range.start_block * offset_multiplier as u64;
-        let total_bytes = block_range.block_count * offset_multiplier as u64;
+        bytes_written += crate::write_sparse_ranges(
+            deleted_file,
+            bytes_written,
+            deleted_file.size,
+            &mut output_file,
+        )?;
+        if bytes_written >= deleted_file.size {
+            break;
+        }
+
+        let start_offset = block_range.start_block * offset_multiplier;
+        let total_bytes = block_range.block_count * offset_multiplier;
         let end_offset = start_offset + total_bytes;
 
         if start_offset >= mmap.len() as u64 {
@@ -376,6 +612,12 @@ fn recover_single_file_forensics(
             break;
         }
     }
+    bytes_written += crate::write_sparse_ranges(
+        deleted_file,
+        bytes_written,
+        deleted_file.size,
+        &mut output_file,
+    )?;
 
     output_file.flush()?;
 
@@ -385,15 +627,14 @@ fn recover_single_file_forensics(
         {
             use std::os::unix::fs::PermissionsExt;
             let perms = std::fs::Permissions::from_mode(permissions);
-            std::fs::set_permissions(&output_path, perms)?;
+            std::fs::set_permissions(output_path, perms)?;
         }
     }
 
     // Calculate hash if enabled
+    let mut hash = None;
     if let Some(ref mut manifest) = hash_manifest {
-        use crate::forensics::calculate_file_hash;
-
-        let file_hash = calculate_file_hash(&output_path, config.hash_algorithm)?;
+        let file_hash = calculate_file_hash(output_path, config.hash_algorithm)?;
 
         // Log hash calculation
         if let Some(ref mut logger) = audit_logger {
@@ -404,7 +645,17 @@ fn recover_single_file_forensics(
             )?;
         }
 
-        manifest.add_file(output_path.display().to_string(), file_hash);
+        manifest.add_file(output_path.display().to_string(), file_hash.clone());
+        hash = Some(file_hash);
+    }
+
+    let mut audit_entry_id = None;
+    if let Some(ref mut logger) = audit_logger {
+        audit_entry_id = Some(logger.file_recovered(
+            &output_path.display().to_string(),
+            bytes_written,
+            deleted_file.inode_or_cluster,
+        )?);
     }
 
     // Check if partial recovery
@@ -412,39 +663,191 @@ fn recover_single_file_forensics(
         was_partial = true;
 
         if let Some(ref mut logger) = audit_logger {
-            logger.file_recovered(
-                &format!("partial_{}", deleted_file.id),
-                bytes_written,
-                deleted_file.inode_or_cluster,
+            logger.audit_log().log(
+                AuditEvent::new(
+                    AuditEventType::Warning,
+                    format!("Partial recovery of file {}", deleted_file.id),
+                )
+                .with_metadata("bytes_recovered", bytes_written.to_string())
+                .with_metadata("expected_size", deleted_file.size.to_string()),
             )?;
         }
     }
 
-    Ok((bytes_written, was_partial, was_reconstructed))
+    Ok(SingleFileRecovery {
+        bytes_written,
+        was_partial,
+        was_reconstructed,
+        hash,
+        audit_entry_id,
+    })
 }
 
-/// Generate recovery path for a file
-fn generate_recovery_path(output_dir: &Path, deleted_file: &DeletedFile) -> PathBuf {
-    let filename = if let Some(ref original_path) = deleted_file.original_path {
-        original_path
-            .file_name()
-            .and_then(|name| name.to_str())
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| format!("recovered_file_{}", deleted_file.id))
-    } else {
-        let extension = deleted_file
-            .metadata
-            .file_extension
-            .as_ref()
-            .map(|ext| format!(".{}", ext))
-            .unwrap_or_else(|| match deleted_file.file_type {
-                crate::FileType::RegularFile => ".dat".to_string(),
-                crate::FileType::Directory => "".to_string(),
-                _ => ".unknown".to_string(),
-            });
-
-        format!("recovered_file_{}{}", deleted_file.id, extension)
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forensics::VerificationStatus;
+    use crate::session::database::tests::sample_session;
+    use crate::FileSystemType;
+
+    #[test]
+    fn test_session_manifest_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = sample_session(3);
+        let paths =
+            assign_recovery_paths(dir.path(), &session.scan_results, OutputLayout::default());
+        for file in &session.scan_results {
+            let path = &paths[&file.id];
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, vec![file.id as u8; 1000]).unwrap();
+        }
+
+        let manifest = generate_session_manifest(
+            dir.path(),
+            &session,
+            HashAlgorithm::SHA256,
+            OutputLayout::default(),
+        )
+        .unwrap();
+        assert_eq!(manifest.files.len(), 3);
+        assert_eq!(manifest.file_ids.len(), 3);
+        assert_eq!(manifest.file_ids[&1], "home/user/photo_0.jpg");
+        assert_eq!(manifest.get_by_file_id(2).unwrap().file_size, 1000);
+
+        let imported = HashManifest::import_json(dir.path().join(SESSION_MANIFEST_FILE)).unwrap();
+        assert_eq!(imported.file_ids, manifest.file_ids);
+        assert_eq!(imported.verify_all(dir.path()).unwrap().summary.verified, 3);
+
+        std::fs::write(dir.path().join("home/user/photo_1.jpg"), b"tampered").unwrap();
+        let result = imported.verify_all(dir.path()).unwrap();
+        assert_eq!(result.summary.verified, 2);
+        assert_eq!(result.summary.corrupted, 1);
+        let corrupted = result
+            .verifications
+            .iter()
+            .find(|v| v.status == VerificationStatus::Corrupted)
+            .unwrap();
+        assert!(corrupted.file_path.ends_with("photo_1.jpg"));
+    }
+
+    #[test]
+    fn test_structure_verification_adjusts_confidence() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F'];
+        jpeg.resize(62, 0x55);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+
+        // A whole photo at 0 and one cut off before its end marker at 4096
+        let mut image = vec![0u8; 8192];
+        image[..64].copy_from_slice(&jpeg);
+        image[4096..4096 + 60].copy_from_slice(&jpeg[..60]);
+        let image_path = dir.path().join("card.img");
+        std::fs::write(&image_path, &image).unwrap();
+
+        let mut session = sample_session(2);
+        for (file, start) in session.scan_results.iter_mut().zip([0, 4096]) {
+            file.size = 64;
+            file.data_blocks = vec![crate::BlockRange {
+                start_block: start,
+                block_count: 64,
+                is_allocated: false,
+            }];
+        }
+        let config = ForensicsConfig {
+            enable_structure_verification: true,
+            ..Default::default()
+        };
+
+        let out = dir.path().join("out");
+        let result =
+            recover_files_with_forensics(&image_path, &session, &out, None, config).unwrap();
+
+        assert_eq!(result.structural_validity[&1], StructuralValidity::Valid);
+        assert!(result.structural_validity[&2].is_damaged());
+        let confidence: Vec<f32> = result
+            .report
+            .recovery_details
+            .iter()
+            .map(|detail| detail.confidence_score)
+            .collect();
+        assert!(confidence[0] > 0.85, "{:?}", confidence);
+        assert!(confidence[1] < 0.85, "{:?}", confidence);
+    }
 
-    output_dir.join(filename)
+    #[test]
+    fn test_report_records_each_recovered_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut image = vec![0u8; 4 * 4096];
+        image[4096..8192].fill(0xAB);
+        image[8192..12288].fill(0xCD);
+        let image_path = dir.path().join("disk.img");
+        std::fs::write(&image_path, &image).unwrap();
+
+        let mut session = sample_session(3);
+        session.fs_type = FileSystemType::Xfs;
+        for (file, block) in session.scan_results.iter_mut().zip([1, 2, 64]) {
+            file.size = 4096;
+            file.data_blocks = vec![crate::BlockRange {
+                start_block: block,
+                block_count: 1,
+                is_allocated: false,
+            }];
+        }
+        let out = dir.path().join("out");
+        session.case_metadata = Some(CaseMetadata {
+            examiner: Some("J. Doe".to_string()),
+            case_number: Some("2024-0117".to_string()),
+            ..Default::default()
+        });
+        let config = ForensicsConfig::full_forensics(&out);
+
+        let report =
+            recover_files_with_forensics(&image_path, &session, &out, Some(vec![1, 2, 3]), config)
+                .unwrap();
+
+        assert_eq!(report.session_id, session.id.to_string());
+        assert_eq!(report.case_metadata, session.case_metadata);
+        assert_eq!(report.hash_algorithm, Some(HashAlgorithm::SHA256));
+        assert_eq!(report.files.len(), 3);
+
+        let first = report.file(1).unwrap();
+        assert_eq!(first.source_blocks[0].start_block, 1);
+        assert_eq!(first.verification_status, VerificationStatus::Calculated);
+        assert_eq!(
+            first.hash.as_ref().unwrap().hash,
+            crate::forensics::calculate_hash(&[0xAB; 4096], HashAlgorithm::SHA256)
+        );
+        assert!(matches!(first.status, RecoveryStatus::Success));
+
+        // Each recovery points at its own entry in the sealed audit log
+        let entries: Vec<crate::forensics::AuditEntry> =
+            std::fs::read_to_string(report.audit_log_path.as_ref().unwrap())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+        let second = report.file(2).unwrap();
+        let entry = entries
+            .iter()
+            .find(|entry| Some(entry.id) == second.audit_entry_id)
+            .unwrap();
+        assert_eq!(entry.event_type, AuditEventType::FileRecovered);
+        assert_eq!(entry.case, session.case_metadata);
+        assert!(entry
+            .message
+            .contains(&second.recovered_path.display().to_string()));
+
+        // Past the end of the image, so nothing is written and nothing hashed
+        let missing = report.file(3).unwrap();
+        assert_eq!(missing.hash.as_ref().unwrap().file_size, 0);
+
+        let json = report.to_json().unwrap();
+        let parsed: ForensicsRecoveryReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.files.len(), 3);
+        assert_eq!(parsed.case_metadata, session.case_metadata);
+        assert_eq!(
+            parsed.file(2).unwrap().audit_entry_id,
+            second.audit_entry_id
+        );
+    }
 }
@@ -2,7 +2,6 @@
 ///
 /// This module provides cryptographic hash calculation and verification
 /// to ensure recovered files are authentic and haven't been corrupted.
-
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
@@ -10,6 +9,11 @@ use std::io::{self, Read};
 use std::path::Path;
 use sha2::{Sha256, Sha512, Digest};
 
+use crate::fs::common::BlockDevice;
+
+/// Bytes hashed per read
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
 /// Supported hash algorithms
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum HashAlgorithm {
@@ -17,12 +21,13 @@ pub enum HashAlgorithm {
     SHA1,
     SHA256,
     SHA512,
+    BLAKE3,
 }
 
 impl HashAlgorithm {
     /// Get all algorithms
     pub fn all() -> Vec<Self> {
-        vec![Self::MD5, Self::SHA256, Self::SHA512]
+        vec![Self::MD5, Self::SHA256, Self::SHA512, Self::BLAKE3]
     }
     
     /// Get algorithm name
@@ -32,6 +37,23 @@ impl HashAlgorithm {
             Self::SHA1 => "SHA1",
             Self::SHA256 => "SHA256",
             Self::SHA512 => "SHA512",
+            Self::BLAKE3 => "BLAKE3",
+        }
+    }
+}
+
+impl std::str::FromStr for HashAlgorithm {
+    type Err = String;
+    
+    /// Parse an algorithm name, case-insensitively and with or without dashes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "md5" => Ok(Self::MD5),
+            "sha1" => Ok(Self::SHA1),
+            "sha256" => Ok(Self::SHA256),
+            "sha512" => Ok(Self::SHA512),
+            "blake3" => Ok(Self::BLAKE3),
+            _ => Err(format!("unknown hash algorithm '{}'", s)),
         }
     }
 }
@@ -100,13 +122,85 @@ pub fn calculate_file_hash(
     let mut file = File::open(path.as_ref())?;
     let metadata = file.metadata()?;
     let file_size = metadata.len();
+    let hash = calculate_reader_hash(&mut file, algorithm)?;
+    
+    Ok(FileHash {
+        algorithm,
+        hash,
+        file_size,
+        calculated_at: Some(chrono::Utc::now()),
+    })
+}
+
+/// Calculate hash of the media in an image file or block device
+///
+/// A split image (`image.001`, ...) or an EWF image is hashed as the media
+/// it holds rather than as the named file, matching how acquisition hashes
+/// are recorded. An EWF image is hashed chunk by chunk as it is
+/// decompressed, without a temporary copy of the media.
+pub fn calculate_image_hash(
+    path: impl AsRef<Path>,
+    algorithm: HashAlgorithm,
+) -> anyhow::Result<FileHash> {
+    #[cfg(feature = "ewf")]
+    if crate::fs::common::is_ewf_path(path.as_ref()) {
+        let image = crate::fs::common::EwfImage::open(path.as_ref())?;
+        let hash = calculate_reader_hash(&mut image.media_reader(), algorithm)?;
+        return Ok(FileHash {
+            algorithm,
+            hash,
+            file_size: image.size(),
+            calculated_at: Some(chrono::Utc::now()),
+        });
+    }
     
+    calculate_device_hash(&BlockDevice::open(path.as_ref())?, algorithm)
+}
+
+/// Calculate hash of the whole of an opened device
+///
+/// Lets a scan hash the device it has already mapped instead of opening
+/// (and, for an EWF image, decompressing) the image again.
+pub fn calculate_device_hash(
+    device: &BlockDevice,
+    algorithm: HashAlgorithm,
+) -> anyhow::Result<FileHash> {
+    let mut reader = DeviceReader { device, position: 0 };
+    let hash = calculate_reader_hash(&mut reader, algorithm)?;
+    
+    Ok(FileHash {
+        algorithm,
+        hash,
+        file_size: device.size(),
+        calculated_at: Some(chrono::Utc::now()),
+    })
+}
+
+/// Reads a device from start to end
+struct DeviceReader<'a> {
+    device: &'a BlockDevice,
+    position: u64,
+}
+
+impl Read for DeviceReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.device.size() - self.position;
+        let length = (buf.len() as u64).min(remaining) as usize;
+        let data = self.device.read_at(self.position, length).map_err(io::Error::other)?;
+        buf[..length].copy_from_slice(data);
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+/// Calculate hash of everything `reader` yields
+fn calculate_reader_hash(reader: &mut impl Read, algorithm: HashAlgorithm) -> io::Result<String> {
     let hash = match algorithm {
         HashAlgorithm::MD5 => {
             let mut hasher = md5::Context::new();
-            let mut buffer = vec![0; 8192];
+            let mut buffer = vec![0; HASH_BUFFER_SIZE];
             loop {
-                let n = file.read(&mut buffer)?;
+                let n = reader.read(&mut buffer)?;
                 if n == 0 {
                     break;
                 }
@@ -116,9 +210,9 @@ pub fn calculate_file_hash(
         }
         HashAlgorithm::SHA256 => {
             let mut hasher = Sha256::new();
-            let mut buffer = vec![0; 8192];
+            let mut buffer = vec![0; HASH_BUFFER_SIZE];
             loop {
-                let n = file.read(&mut buffer)?;
+                let n = reader.read(&mut buffer)?;
                 if n == 0 {
                     break;
                 }
@@ -128,9 +222,9 @@ pub fn calculate_file_hash(
         }
         HashAlgorithm::SHA1 => {
             let mut hasher = sha1::Sha1::new();
-            let mut buffer = vec![0; 8192];
+            let mut buffer = vec![0; HASH_BUFFER_SIZE];
             loop {
-                let n = file.read(&mut buffer)?;
+                let n = reader.read(&mut buffer)?;
                 if n == 0 {
                     break;
                 }
@@ -140,9 +234,9 @@ pub fn calculate_file_hash(
         }
         HashAlgorithm::SHA512 => {
             let mut hasher = Sha512::new();
-            let mut buffer = vec![0; 8192];
+            let mut buffer = vec![0; HASH_BUFFER_SIZE];
             loop {
-                let n = file.read(&mut buffer)?;
+                let n = reader.read(&mut buffer)?;
                 if n == 0 {
                     break;
                 }
@@ -150,14 +244,13 @@ pub fn calculate_file_hash(
             }
             format!("{:x}", hasher.finalize())
         }
+        HashAlgorithm::BLAKE3 => {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update_reader(reader)?;
+            hasher.finalize().to_hex().to_string()
+        }
     };
-    
-    Ok(FileHash {
-        algorithm,
-        hash,
-        file_size,
-        calculated_at: Some(chrono::Utc::now()),
-    })
+    Ok(hash)
 }
 
 /// Calculate hash from byte slice
@@ -181,6 +274,7 @@ pub fn calculate_hash(data: &[u8], algorithm: HashAlgorithm) -> String {
             hasher.update(data);
             format!("{:x}", hasher.finalize())
         }
+        HashAlgorithm::BLAKE3 => blake3::hash(data).to_hex().to_string(),
     }
 }
 
@@ -191,8 +285,38 @@ pub fn verify_file_integrity(
     algorithm: HashAlgorithm,
 ) -> io::Result<HashVerification> {
     let file_hash = calculate_file_hash(&path, algorithm)?;
-    let actual_hash = file_hash.hash;
-    
+    Ok(hash_verification(path.as_ref(), file_hash.hash, expected_hash, algorithm))
+}
+
+/// Verify the media of an image file or block device against expected hash
+///
+/// See [`calculate_image_hash`] for how split and EWF images are hashed.
+pub fn verify_image_integrity(
+    path: impl AsRef<Path>,
+    expected_hash: Option<&str>,
+    algorithm: HashAlgorithm,
+) -> anyhow::Result<HashVerification> {
+    let image_hash = calculate_image_hash(&path, algorithm)?;
+    Ok(hash_verification(path.as_ref(), image_hash.hash, expected_hash, algorithm))
+}
+
+/// Verify an opened device, the media of the image at `path`, against expected hash
+pub fn verify_device_integrity(
+    device: &BlockDevice,
+    path: impl AsRef<Path>,
+    expected_hash: Option<&str>,
+    algorithm: HashAlgorithm,
+) -> anyhow::Result<HashVerification> {
+    let device_hash = calculate_device_hash(device, algorithm)?;
+    Ok(hash_verification(path.as_ref(), device_hash.hash, expected_hash, algorithm))
+}
+
+fn hash_verification(
+    path: &Path,
+    actual_hash: String,
+    expected_hash: Option<&str>,
+    algorithm: HashAlgorithm,
+) -> HashVerification {
     let (status, notes) = if let Some(expected) = expected_hash {
         if actual_hash.eq_ignore_ascii_case(expected) {
             (VerificationStatus::Verified, Some("Hash matches - File is authentic".to_string()))
@@ -203,14 +327,14 @@ pub fn verify_file_integrity(
         (VerificationStatus::NoReference, Some("No reference hash available".to_string()))
     };
     
-    Ok(HashVerification {
-        file_path: path.as_ref().display().to_string(),
+    HashVerification {
+        file_path: path.display().to_string(),
         expected_hash: expected_hash.map(String::from),
         actual_hash,
         algorithm,
         status,
         notes,
-    })
+    }
 }
 
 /// Verification result with detailed analysis
@@ -274,6 +398,10 @@ pub struct HashManifest {
     
     /// File hashes
     pub files: HashMap<String, FileHash>,
+    
+    /// Recovered file ID -> key in `files`, for manifests built from a session
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub file_ids: HashMap<u64, String>,
 }
 
 impl HashManifest {
@@ -284,6 +412,7 @@ impl HashManifest {
             created_at: chrono::Utc::now(),
             algorithm,
             files: HashMap::new(),
+            file_ids: HashMap::new(),
         }
     }
     
@@ -297,6 +426,11 @@ impl HashManifest {
         self.files.get(path)
     }
     
+    /// Get the hash recorded for a recovered file ID
+    pub fn get_by_file_id(&self, file_id: u64) -> Option<&FileHash> {
+        self.file_ids.get(&file_id).and_then(|path| self.files.get(path))
+    }
+    
     /// Verify all files in manifest
     pub fn verify_all(&self, base_path: impl AsRef<Path>) -> io::Result<VerificationResult> {
         let mut verifications = Vec::new();
@@ -343,6 +477,243 @@ impl HashManifest {
     }
 }
 
+/// Outcome of checking a recovered file's internal structure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StructuralValidity {
+    /// Every structure checked is present and consistent
+    Valid,
+
+    /// The file starts correctly but ends before its structure does
+    Truncated(String),
+
+    /// The structure is damaged or is not the claimed format
+    Corrupt(String),
+
+    /// No structural check exists for the format
+    Unchecked,
+}
+
+impl StructuralValidity {
+    /// Whether the check found the file truncated or corrupt
+    pub fn is_damaged(&self) -> bool {
+        matches!(self, Self::Truncated(_) | Self::Corrupt(_))
+    }
+
+    /// Confidence after taking the check into account
+    ///
+    /// A file that decodes is worth more than its scan-time score, one that
+    /// ends early is worth less, and a damaged one much less.
+    pub fn adjust_confidence(&self, confidence: f32) -> f32 {
+        let adjusted = match self {
+            Self::Valid => confidence + 0.1,
+            Self::Truncated(_) => confidence - 0.2,
+            Self::Corrupt(_) => confidence - 0.4,
+            Self::Unchecked => confidence,
+        };
+        adjusted.clamp(0.0, 1.0)
+    }
+}
+
+impl std::fmt::Display for StructuralValidity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Valid => write!(f, "valid"),
+            Self::Truncated(detail) => write!(f, "truncated: {}", detail),
+            Self::Corrupt(detail) => write!(f, "corrupt: {}", detail),
+            Self::Unchecked => write!(f, "unchecked"),
+        }
+    }
+}
+
+/// Check that a recovered file is structurally sound for its MIME type
+///
+/// Lightweight checks only: JPEG start and end markers, PNG chunk CRCs, the
+/// PDF `startxref` and `%%EOF` trailer and the ZIP central directory. Zero
+/// padding after the end of the data, which recovery of whole blocks leaves
+/// behind, is ignored.
+pub fn verify_structure(path: &Path, mime: &str) -> StructuralValidity {
+    match std::fs::read(path) {
+        Ok(data) => verify_structure_bytes(&data, mime),
+        Err(e) => StructuralValidity::Corrupt(format!("unreadable: {}", e)),
+    }
+}
+
+/// Check that `data` is structurally sound for its MIME type
+pub fn verify_structure_bytes(data: &[u8], mime: &str) -> StructuralValidity {
+    match mime {
+        "image/jpeg" => verify_jpeg(data),
+        "image/png" => verify_png(data),
+        "application/pdf" => verify_pdf(data),
+        _ if is_zip_based(mime) => verify_zip(data),
+        _ => StructuralValidity::Unchecked,
+    }
+}
+
+fn is_zip_based(mime: &str) -> bool {
+    mime == "application/zip"
+        || mime.starts_with("application/vnd.openxmlformats")
+        || mime.ends_with("+zip")
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+fn verify_jpeg(data: &[u8]) -> StructuralValidity {
+    if !data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return StructuralValidity::Corrupt("missing JPEG start of image marker".to_string());
+    }
+    if rfind(&data[2..], &[0xFF, 0xD9]).is_none() {
+        return StructuralValidity::Truncated("no end of image marker".to_string());
+    }
+    StructuralValidity::Valid
+}
+
+fn verify_png(data: &[u8]) -> StructuralValidity {
+    const SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    if !data.starts_with(&SIGNATURE) {
+        return StructuralValidity::Corrupt("missing PNG signature".to_string());
+    }
+
+    let mut pos = SIGNATURE.len();
+    let mut first = true;
+    loop {
+        let Some(header) = data.get(pos..pos + 8) else {
+            return StructuralValidity::Truncated(format!("no IEND chunk before offset {}", pos));
+        };
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type = &header[4..8];
+        let name = String::from_utf8_lossy(chunk_type);
+        if !chunk_type.iter().all(u8::is_ascii_alphabetic) {
+            return StructuralValidity::Corrupt(format!("invalid chunk type at offset {}", pos));
+        }
+        if first && chunk_type != b"IHDR" {
+            return StructuralValidity::Corrupt(format!("first chunk is {}, not IHDR", name));
+        }
+        first = false;
+
+        let crc_start = pos + 8 + length;
+        let Some(stored) = data.get(crc_start..crc_start + 4) else {
+            return StructuralValidity::Truncated(format!(
+                "{} chunk at offset {} is cut off",
+                name, pos
+            ));
+        };
+        let stored = u32::from_be_bytes([stored[0], stored[1], stored[2], stored[3]]);
+        if crc32fast::hash(&data[pos + 4..crc_start]) != stored {
+            return StructuralValidity::Corrupt(format!(
+                "CRC mismatch in {} chunk at offset {}",
+                name, pos
+            ));
+        }
+
+        if chunk_type == b"IEND" {
+            return StructuralValidity::Valid;
+        }
+        pos = crc_start + 4;
+    }
+}
+
+fn verify_pdf(data: &[u8]) -> StructuralValidity {
+    if !data.starts_with(b"%PDF-") {
+        return StructuralValidity::Corrupt("missing %PDF- header".to_string());
+    }
+    let Some(eof) = rfind(data, b"%%EOF") else {
+        return StructuralValidity::Truncated("no %%EOF marker".to_string());
+    };
+    let Some(startxref) = rfind(&data[..eof], b"startxref") else {
+        return StructuralValidity::Corrupt("no startxref before %%EOF".to_string());
+    };
+
+    let offset = std::str::from_utf8(&data[startxref + b"startxref".len()..eof])
+        .ok()
+        .and_then(|text| text.trim().parse::<usize>().ok());
+    let Some(offset) = offset else {
+        return StructuralValidity::Corrupt("startxref offset is not a number".to_string());
+    };
+
+    // Either a classic xref table or an xref stream object ("12 0 obj")
+    let target = data.get(offset..startxref).unwrap_or_default();
+    let line_end = target
+        .iter()
+        .position(|&b| b == b'\n' || b == b'\r')
+        .unwrap_or(target.len());
+    let first_line = &target[..line_end];
+    if first_line.starts_with(b"xref") || find(first_line, b" obj").is_some() {
+        StructuralValidity::Valid
+    } else {
+        StructuralValidity::Corrupt(format!(
+            "startxref offset {} is not an xref section",
+            offset
+        ))
+    }
+}
+
+fn verify_zip(data: &[u8]) -> StructuralValidity {
+    const LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+    const CENTRAL_HEADER: &[u8] = b"PK\x01\x02";
+    const END_OF_CENTRAL_DIRECTORY: &[u8] = b"PK\x05\x06";
+    const EOCD_LEN: usize = 22;
+    const CENTRAL_HEADER_LEN: usize = 46;
+
+    let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+    let u32_at = |pos: usize| {
+        u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize
+    };
+
+    if !data.starts_with(LOCAL_HEADER) {
+        return StructuralValidity::Corrupt("missing ZIP local file header".to_string());
+    }
+    let eocd = match rfind(data, END_OF_CENTRAL_DIRECTORY) {
+        Some(eocd) if eocd + EOCD_LEN <= data.len() => eocd,
+        _ => {
+            return StructuralValidity::Truncated("no end of central directory record".to_string())
+        }
+    };
+
+    let entries = u16_at(eocd + 10);
+    let directory_size = u32_at(eocd + 12);
+    let directory_offset = u32_at(eocd + 16);
+    if entries == 0xFFFF || directory_offset == 0xFFFF_FFFF {
+        // ZIP64 keeps the real values in records this check does not read
+        return StructuralValidity::Unchecked;
+    }
+    if directory_offset + directory_size > eocd {
+        return StructuralValidity::Corrupt(
+            "central directory overlaps its end record".to_string(),
+        );
+    }
+
+    let mut pos = directory_offset;
+    for index in 0..entries {
+        if data.get(pos..pos + 4) != Some(CENTRAL_HEADER) || pos + CENTRAL_HEADER_LEN > eocd {
+            return StructuralValidity::Corrupt(format!(
+                "central directory entry {} is missing",
+                index
+            ));
+        }
+        let local = u32_at(pos + 42);
+        if data.get(local..local + 4) != Some(LOCAL_HEADER) {
+            return StructuralValidity::Corrupt(format!(
+                "central directory entry {} points at no local header",
+                index
+            ));
+        }
+        pos += CENTRAL_HEADER_LEN + u16_at(pos + 28) + u16_at(pos + 30) + u16_at(pos + 32);
+    }
+    if pos != directory_offset + directory_size {
+        return StructuralValidity::Corrupt(
+            "central directory size does not match its entries".to_string(),
+        );
+    }
+
+    StructuralValidity::Valid
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -360,6 +731,28 @@ mod tests {
         assert_eq!(sha256.len(), 64); // SHA256 = 256 bits = 64 hex chars
     }
     
+    #[test]
+    fn test_known_answers() {
+        let known = [
+            (HashAlgorithm::MD5, "900150983cd24fb0d6963f7d28e17f72"),
+            (HashAlgorithm::SHA1, "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (HashAlgorithm::SHA256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (HashAlgorithm::SHA512, "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"),
+            (HashAlgorithm::BLAKE3, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"),
+        ];
+        
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("abc.txt");
+        std::fs::write(&file_path, b"abc").unwrap();
+        
+        for (algorithm, expected) in known {
+            assert_eq!(calculate_hash(b"abc", algorithm), expected, "{}", algorithm.name());
+            let file_hash = calculate_file_hash(&file_path, algorithm).unwrap();
+            assert_eq!(file_hash.hash, expected, "{}", algorithm.name());
+            assert_eq!(algorithm.name().parse::<HashAlgorithm>(), Ok(algorithm));
+        }
+    }
+    
     #[test]
     fn test_calculate_file_hash() {
         let temp_dir = TempDir::new().unwrap();
@@ -398,6 +791,45 @@ mod tests {
         assert_eq!(verification.status, VerificationStatus::Verified);
     }
     
+    #[test]
+    fn test_image_hash_covers_every_segment() {
+        let temp_dir = TempDir::new().unwrap();
+        let media: Vec<u8> = (0..3 * 4096u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(temp_dir.path().join("image.001"), &media[..8192]).unwrap();
+        std::fs::write(temp_dir.path().join("image.002"), &media[8192..]).unwrap();
+        let first = temp_dir.path().join("image.001");
+        
+        let expected = calculate_hash(&media, HashAlgorithm::SHA256);
+        let hash = calculate_image_hash(&first, HashAlgorithm::SHA256).unwrap();
+        assert_eq!(hash.hash, expected);
+        assert_eq!(hash.file_size, media.len() as u64);
+        
+        let verification = verify_image_integrity(
+            &first,
+            Some(&expected),
+            HashAlgorithm::SHA256,
+        ).unwrap();
+        assert_eq!(verification.status, VerificationStatus::Verified);
+        // The first segment alone is not the media
+        assert_ne!(calculate_file_hash(&first, HashAlgorithm::SHA256).unwrap().hash, expected);
+    }
+    
+    #[cfg(feature = "ewf")]
+    #[test]
+    fn test_ewf_image_hash_is_the_hash_of_the_media() {
+        let temp_dir = TempDir::new().unwrap();
+        // Ten and a half 4 KiB chunks over several segments, the last chunk cut short
+        let media: Vec<u8> = (0..42 * 1024u32).map(|i| (i % 251) as u8).collect();
+        let first = crate::fs::common::ewf::tests::write_ewf(temp_dir.path(), &media, 4);
+        
+        let hash = calculate_image_hash(&first, HashAlgorithm::SHA256).unwrap();
+        assert_eq!(hash.hash, calculate_hash(&media, HashAlgorithm::SHA256));
+        assert_eq!(hash.file_size, media.len() as u64);
+        
+        let device = BlockDevice::open(&first).unwrap();
+        assert_eq!(calculate_device_hash(&device, HashAlgorithm::SHA256).unwrap().hash, hash.hash);
+    }
+    
     #[test]
     fn test_verify_integrity_corruption() {
         let temp_dir = TempDir::new().unwrap();
@@ -468,4 +900,167 @@ mod tests {
         assert_eq!(imported.manifest_id, "test-manifest");
         assert_eq!(imported.files.len(), 1);
     }
+
+    /// PNG chunk with its CRC
+    fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&crc32fast::hash(&chunk[4..]).to_be_bytes());
+        chunk
+    }
+
+    fn sample_png() -> Vec<u8> {
+        let mut png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        png.extend(png_chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]));
+        png.extend(png_chunk(
+            b"IDAT",
+            &[0x78, 0x9C, 0x63, 0x60, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01],
+        ));
+        png.extend(png_chunk(b"IEND", &[]));
+        png
+    }
+
+    fn sample_pdf() -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n<< /Type /Catalog >>\nendobj\n".to_vec();
+        let xref = pdf.len();
+        pdf.extend_from_slice(b"xref\n0 2\n0000000000 65535 f \n0000000009 00000 n \n");
+        pdf.extend_from_slice(b"trailer\n<< /Size 2 /Root 1 0 R >>\n");
+        pdf.extend_from_slice(format!("startxref\n{}\n%%EOF\n", xref).as_bytes());
+        pdf
+    }
+
+    /// A ZIP archive holding one stored file
+    fn sample_zip() -> Vec<u8> {
+        let name = b"a.txt";
+        let contents = b"hello";
+
+        let mut zip = b"PK\x03\x04".to_vec();
+        zip.extend_from_slice(&[0u8; 22]); // version, flags, method, times, crc, sizes
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip.extend_from_slice(name);
+        zip.extend_from_slice(contents);
+
+        let directory_offset = zip.len();
+        zip.extend_from_slice(b"PK\x01\x02");
+        zip.extend_from_slice(&[0u8; 24]); // versions, flags, method, times, crc, sizes
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0u8; 12]); // extra and comment lengths, disk, attributes
+        zip.extend_from_slice(&0u32.to_le_bytes()); // local header offset
+        zip.extend_from_slice(name);
+        let directory_size = zip.len() - directory_offset;
+
+        zip.extend_from_slice(b"PK\x05\x06");
+        zip.extend_from_slice(&[0u8; 4]); // disk numbers
+        zip.extend_from_slice(&1u16.to_le_bytes());
+        zip.extend_from_slice(&1u16.to_le_bytes());
+        zip.extend_from_slice(&(directory_size as u32).to_le_bytes());
+        zip.extend_from_slice(&(directory_offset as u32).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip
+    }
+
+    #[test]
+    fn test_verify_structure_of_valid_files() {
+        let mut jpeg = vec![
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0, 0xFF, 0xD9,
+        ];
+        // Whole-block recovery pads files with zeros
+        jpeg.extend_from_slice(&[0u8; 100]);
+        let mut pdf = sample_pdf();
+        pdf.extend_from_slice(&[0u8; 100]);
+
+        assert_eq!(
+            verify_structure_bytes(&jpeg, "image/jpeg"),
+            StructuralValidity::Valid
+        );
+        assert_eq!(
+            verify_structure_bytes(&sample_png(), "image/png"),
+            StructuralValidity::Valid
+        );
+        assert_eq!(
+            verify_structure_bytes(&pdf, "application/pdf"),
+            StructuralValidity::Valid
+        );
+        assert_eq!(
+            verify_structure_bytes(&sample_zip(), "application/zip"),
+            StructuralValidity::Valid
+        );
+        assert_eq!(
+            verify_structure_bytes(&jpeg, "video/mp4"),
+            StructuralValidity::Unchecked
+        );
+    }
+
+    #[test]
+    fn test_verify_structure_of_truncated_files() {
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00,
+        ];
+        let png = sample_png();
+        let pdf = sample_pdf();
+        let zip = sample_zip();
+
+        let truncated = |validity| matches!(validity, StructuralValidity::Truncated(_));
+        assert!(truncated(verify_structure_bytes(&jpeg, "image/jpeg")));
+        assert!(truncated(verify_structure_bytes(
+            &png[..png.len() - 20],
+            "image/png"
+        )));
+        assert!(truncated(verify_structure_bytes(
+            &pdf[..pdf.len() - 10],
+            "application/pdf"
+        )));
+        assert!(truncated(verify_structure_bytes(
+            &zip[..zip.len() - 30],
+            "application/zip"
+        )));
+    }
+
+    #[test]
+    fn test_verify_structure_of_corrupt_files() {
+        let corrupt = |validity| matches!(validity, StructuralValidity::Corrupt(_));
+
+        let mut png = sample_png();
+        png[45] ^= 0xFF; // inside the IDAT data
+        let validity = verify_structure_bytes(&png, "image/png");
+        assert_eq!(
+            validity,
+            StructuralValidity::Corrupt("CRC mismatch in IDAT chunk at offset 33".to_string())
+        );
+
+        let pdf = String::from_utf8(sample_pdf())
+            .unwrap()
+            .replace("startxref\n45", "startxref\n3");
+        assert!(corrupt(verify_structure_bytes(
+            pdf.as_bytes(),
+            "application/pdf"
+        )));
+
+        let mut zip = sample_zip();
+        zip[0] = b'X';
+        assert!(corrupt(verify_structure_bytes(&zip, "application/zip")));
+        assert!(corrupt(verify_structure_bytes(
+            b"not a photo",
+            "image/jpeg"
+        )));
+    }
+
+    #[test]
+    fn test_verify_structure_reads_file_and_adjusts_confidence() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("diagram.png");
+        std::fs::write(&path, sample_png()).unwrap();
+
+        let validity = verify_structure(&path, "image/png");
+        assert_eq!(validity, StructuralValidity::Valid);
+        assert!((validity.adjust_confidence(0.6) - 0.7).abs() < 1e-6);
+        assert_eq!(validity.adjust_confidence(0.95), 1.0);
+
+        let missing = verify_structure(&temp_dir.path().join("missing.png"), "image/png");
+        assert!(matches!(missing, StructuralValidity::Corrupt(_)));
+        assert!(missing.adjust_confidence(0.6) < 0.6);
+        assert_eq!(StructuralValidity::Unchecked.adjust_confidence(0.6), 0.6);
+    }
 }
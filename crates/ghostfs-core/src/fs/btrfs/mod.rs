@@ -7,10 +7,65 @@ pub mod recovery;
 pub mod tree;
 
 use super::common::BlockDevice;
+use crate::recovery::RecoveryError;
+
+/// Device offset of the primary superblock
+pub const BTRFS_SUPER_INFO_OFFSET: u64 = 65536;
 
 /// Btrfs magic number
 const BTRFS_MAGIC: &[u8; 8] = b"_BHRfS_M";
 
+/// Offset of the magic within the superblock
+const BTRFS_MAGIC_OFFSET: u64 = 64;
+
+/// Offset of the backup root array (`super_roots`) in the superblock
+const BTRFS_SUPER_ROOTS_OFFSET: usize = 0xB2B;
+
+/// Entries in the backup root array
+const BTRFS_NUM_BACKUP_ROOTS: usize = 4;
+
+/// Size of one `btrfs_root_backup`
+const BTRFS_ROOT_BACKUP_SIZE: usize = 168;
+
+/// Tree roots of an earlier generation, kept in the superblock
+///
+/// The superblock rotates through four of these on every commit, so they
+/// point at trees from the last few transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BtrfsRootBackup {
+    pub tree_root: u64,
+    pub tree_root_gen: u64,
+    pub chunk_root: u64,
+    pub extent_root: u64,
+    pub fs_root: u64,
+    pub fs_root_gen: u64,
+}
+
+impl BtrfsRootBackup {
+    fn parse(data: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor::new(data);
+        let tree_root = cursor.read_u64::<LittleEndian>()?;
+        let tree_root_gen = cursor.read_u64::<LittleEndian>()?;
+        let chunk_root = cursor.read_u64::<LittleEndian>()?;
+        // Skip chunk_root_gen
+        cursor.set_position(cursor.position() + 8);
+        let extent_root = cursor.read_u64::<LittleEndian>()?;
+        // Skip extent_root_gen
+        cursor.set_position(cursor.position() + 8);
+        let fs_root = cursor.read_u64::<LittleEndian>()?;
+        let fs_root_gen = cursor.read_u64::<LittleEndian>()?;
+
+        Ok(Self {
+            tree_root,
+            tree_root_gen,
+            chunk_root,
+            extent_root,
+            fs_root,
+            fs_root_gen,
+        })
+    }
+}
+
 /// Btrfs superblock structure (simplified)
 #[derive(Debug)]
 pub struct BtrfsSuperblock {
@@ -31,10 +86,12 @@ pub struct BtrfsSuperblock {
     pub nodesize: u32,
     pub stripesize: u32,
     pub chunk_root_generation: u64,
+    /// Backup roots in use, in array order; empty if the data was too short
+    pub backup_roots: Vec<BtrfsRootBackup>,
 }
 
 impl BtrfsSuperblock {
-    /// Parse Btrfs superblock from raw bytes
+    /// Parse the primary Btrfs superblock from raw bytes
     pub fn parse(data: &[u8]) -> Result<Self> {
         if data.len() < 1024 {
             anyhow::bail!("Insufficient data for Btrfs superblock");
@@ -55,7 +112,11 @@ impl BtrfsSuperblock {
         std::io::Read::read_exact(&mut cursor, &mut magic)?;
 
         if &magic != BTRFS_MAGIC {
-            anyhow::bail!("Invalid Btrfs magic");
+            return Err(RecoveryError::corrupted(
+                BTRFS_SUPER_INFO_OFFSET + BTRFS_MAGIC_OFFSET,
+                "Invalid Btrfs magic",
+            )
+            .into());
         }
 
         let generation = cursor.read_u64::<LittleEndian>()?;
@@ -82,6 +143,19 @@ impl BtrfsSuperblock {
         cursor.set_position(176);
         let chunk_root_generation = cursor.read_u64::<LittleEndian>()?;
 
+        // Unused slots are zeroed
+        let roots_end = BTRFS_SUPER_ROOTS_OFFSET + BTRFS_NUM_BACKUP_ROOTS * BTRFS_ROOT_BACKUP_SIZE;
+        let backup_roots = match data.get(BTRFS_SUPER_ROOTS_OFFSET..roots_end) {
+            Some(roots) => roots
+                .chunks_exact(BTRFS_ROOT_BACKUP_SIZE)
+                .map(BtrfsRootBackup::parse)
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .filter(|backup| backup.tree_root != 0)
+                .collect(),
+            None => Vec::new(),
+        };
+
         let magic2 = magic;
 
         Ok(BtrfsSuperblock {
@@ -102,8 +176,23 @@ impl BtrfsSuperblock {
             nodesize,
             stripesize,
             chunk_root_generation,
+            backup_roots,
         })
     }
+
+    /// Fraction of the filesystem's bytes in use (0.0 - 1.0)
+    pub fn used_fraction(&self) -> Option<f32> {
+        if self.total_bytes == 0 {
+            return None;
+        }
+        Some(self.bytes_used.min(self.total_bytes) as f32 / self.total_bytes as f32)
+    }
+
+    /// Whether a tree log is present, i.e. fsync'd writes had not been
+    /// committed when the filesystem was last written
+    pub fn has_pending_log(&self) -> bool {
+        self.log_root != 0
+    }
 }
 
 /// Check if data contains Btrfs superblock signature
@@ -119,7 +208,7 @@ pub fn is_btrfs_superblock(data: &[u8]) -> bool {
 /// Get Btrfs file system information
 pub fn get_filesystem_info(device: &BlockDevice) -> Result<String> {
     // Btrfs superblock is at 64KB
-    let sb_data = device.read_bytes(65536, 4096)?;
+    let sb_data = device.read_at(BTRFS_SUPER_INFO_OFFSET, 4096)?;
     let superblock = BtrfsSuperblock::parse(sb_data)?;
 
     let fs_size_mb = superblock.total_bytes / (1024 * 1024);
@@ -154,10 +243,38 @@ pub fn get_filesystem_info(device: &BlockDevice) -> Result<String> {
     ))
 }
 
+/// What [`scan_for_deleted_files`] searches
+#[derive(Debug, Clone)]
+pub struct BtrfsScanOptions {
+    /// Also search the trees of earlier generations recorded in the superblock
+    pub backup_roots: bool,
+    /// Only list the files: their extents are not mapped and no signature
+    /// scan runs
+    pub list_only: bool,
+    /// Carve files from their content as well
+    pub signature_scan: bool,
+    /// Signatures the signature scan identifies files by
+    pub signatures: crate::recovery::SignatureSet,
+}
+
+impl Default for BtrfsScanOptions {
+    fn default() -> Self {
+        Self {
+            backup_roots: false,
+            list_only: false,
+            signature_scan: true,
+            signatures: crate::recovery::SignatureSet::default(),
+        }
+    }
+}
+
 /// Scan for deleted files in Btrfs
-pub fn scan_for_deleted_files(device: &BlockDevice) -> Result<Vec<crate::DeletedFile>> {
+pub fn scan_for_deleted_files(
+    device: &BlockDevice,
+    options: BtrfsScanOptions,
+) -> Result<Vec<crate::DeletedFile>> {
     // Parse superblock
-    let sb_data = device.read_bytes(65536, 4096)?;
+    let sb_data = device.read_at(BTRFS_SUPER_INFO_OFFSET, 4096)?;
     let superblock = BtrfsSuperblock::parse(sb_data)?;
 
     tracing::info!("Btrfs scan: Starting tree analysis");
@@ -166,7 +283,11 @@ pub fn scan_for_deleted_files(device: &BlockDevice) -> Result<Vec<crate::Deleted
     tracing::info!("  Node size: {} bytes", superblock.nodesize);
 
     // Create and use the recovery engine
-    let recovery_engine = recovery::BtrfsRecoveryEngine::new(device, superblock)?;
+    let recovery_engine = recovery::BtrfsRecoveryEngine::new(device, superblock)?
+        .with_backup_roots(options.backup_roots)
+        .with_list_only(options.list_only)
+        .with_signature_scan(options.signature_scan)
+        .with_signatures(options.signatures);
     let deleted_files = recovery_engine.scan_deleted_files()?;
 
     tracing::info!("Btrfs scan complete: {} files found", deleted_files.len());
@@ -187,4 +308,41 @@ mod tests {
         let wrong_magic = vec![0u8; 72];
         assert!(!is_btrfs_superblock(&wrong_magic));
     }
+
+    #[test]
+    fn test_parse_backup_roots() {
+        let mut data = vec![0u8; 4096];
+        data[64..72].copy_from_slice(BTRFS_MAGIC);
+        data[72..80].copy_from_slice(&12u64.to_le_bytes()); // generation
+
+        // Generations 10 and 11 in slots 2 and 3; slots 0 and 1 never used
+        for (slot, generation) in [(2usize, 10u64), (3, 11)] {
+            let start = BTRFS_SUPER_ROOTS_OFFSET + slot * BTRFS_ROOT_BACKUP_SIZE;
+            let backup = &mut data[start..start + BTRFS_ROOT_BACKUP_SIZE];
+            backup[0..8].copy_from_slice(&(generation << 20).to_le_bytes()); // tree_root
+            backup[8..16].copy_from_slice(&generation.to_le_bytes());
+            backup[32..40].copy_from_slice(&0x40_0000u64.to_le_bytes()); // extent_root
+            backup[48..56].copy_from_slice(&(generation << 16).to_le_bytes()); // fs_root
+            backup[56..64].copy_from_slice(&generation.to_le_bytes());
+        }
+
+        let superblock = BtrfsSuperblock::parse(&data).unwrap();
+        assert_eq!(superblock.generation, 12);
+        assert_eq!(superblock.backup_roots.len(), 2);
+        assert_eq!(
+            superblock.backup_roots[1],
+            BtrfsRootBackup {
+                tree_root: 11 << 20,
+                tree_root_gen: 11,
+                chunk_root: 0,
+                extent_root: 0x40_0000,
+                fs_root: 11 << 16,
+                fs_root_gen: 11,
+            }
+        );
+
+        // Too short to hold the array
+        let superblock = BtrfsSuperblock::parse(&data[..2048]).unwrap();
+        assert!(superblock.backup_roots.is_empty());
+    }
 }
@@ -5,15 +5,32 @@
 /// 2. Looking for orphan items (deleted but not yet cleaned)
 /// 3. Scanning for unlinked files
 /// 4. Scanning directory entries for filename recovery
+/// 5. Optionally, searching the trees of the superblock's backup roots
 use anyhow::{bail, Result};
 use byteorder::{LittleEndian, ReadBytesExt};
 use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
 use std::path::PathBuf;
 
 use super::tree::*;
 use super::{BlockDevice, BtrfsSuperblock};
-use crate::{BlockRange, DeletedFile, FileMetadata, FileType};
+use crate::fs::common::RangeSet;
+use crate::recovery::{signatures, RecoveryError};
+use crate::{BlockRange, BtrfsCompressedExtent, DeletedFile, FileMetadata, FileType};
+
+/// Btrfs extent compression types
+pub const BTRFS_COMPRESS_NONE: u8 = 0;
+pub const BTRFS_COMPRESS_ZLIB: u8 = 1;
+pub const BTRFS_COMPRESS_LZ4: u8 = 2;
+pub const BTRFS_COMPRESS_ZSTD: u8 = 3;
+
+/// Largest uncompressed size of a compressed extent (128 KiB)
+pub const BTRFS_MAX_UNCOMPRESSED: u64 = 128 * 1024;
+
+/// Offset of `bytenr` (the tree root) inside a `btrfs_root_item`
+const ROOT_ITEM_BYTENR_OFFSET: usize = 176;
 
 // ============================================================================
 // Inode Structures
@@ -117,12 +134,17 @@ impl BtrfsInodeItem {
 
     /// Check if this is a regular file (not directory/symlink/etc)
     pub fn is_regular_file(&self) -> bool {
-        (self.mode & 0o170000) == 0o100000
+        self.file_type() == FileType::RegularFile
     }
 
     /// Check if this is a directory
     pub fn is_directory(&self) -> bool {
-        (self.mode & 0o170000) == 0o040000
+        self.file_type() == FileType::Directory
+    }
+
+    /// File type from the mode; the type bits fit in the low 16 bits
+    pub fn file_type(&self) -> FileType {
+        FileType::from_mode(self.mode as u16)
     }
 
     /// Check if this inode appears deleted (nlink == 0)
@@ -203,6 +225,107 @@ impl BtrfsFileExtentItem {
     }
 }
 
+// ============================================================================
+// Extent Decompression
+// ============================================================================
+
+/// Decompress the on-disk bytes of a compressed Btrfs extent
+///
+/// The output is expected to be `ram_bytes` long; a mismatch is logged as a
+/// warning but the decompressed data is still returned so recovery can proceed.
+/// `ram_bytes` comes from the image, so a compressed extent claiming more than
+/// [`BTRFS_MAX_UNCOMPRESSED`], or expanding past `ram_bytes`, is an error.
+pub fn decompress_extent(data: &[u8], compression: u8, ram_bytes: u64) -> Result<Vec<u8>> {
+    if compression != BTRFS_COMPRESS_NONE && ram_bytes > BTRFS_MAX_UNCOMPRESSED {
+        return Err(RecoveryError::ParseError(format!(
+            "Compressed extent claims {} uncompressed bytes, more than the {} byte limit",
+            ram_bytes, BTRFS_MAX_UNCOMPRESSED
+        ))
+        .into());
+    }
+
+    let output = match compression {
+        BTRFS_COMPRESS_NONE => data.to_vec(),
+        BTRFS_COMPRESS_ZLIB => decompress_zlib(data, ram_bytes)?,
+        BTRFS_COMPRESS_LZ4 => decompress_lz4(data, ram_bytes)?,
+        BTRFS_COMPRESS_ZSTD => decompress_zstd(data, ram_bytes)?,
+        other => {
+            return Err(RecoveryError::UnsupportedFeature(format!(
+                "Btrfs compression type {}",
+                other
+            ))
+            .into())
+        }
+    };
+
+    if output.len() as u64 != ram_bytes {
+        tracing::warn!(
+            "Decompressed extent size mismatch: expected {} bytes, got {}",
+            ram_bytes,
+            output.len()
+        );
+    }
+
+    Ok(output)
+}
+
+/// Read a decompressing stream, failing if it yields more than `ram_bytes`
+#[cfg(any(feature = "btrfs-zlib", feature = "btrfs-zstd"))]
+fn read_bounded(decoder: impl std::io::Read, ram_bytes: u64) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut output = Vec::with_capacity(ram_bytes as usize);
+    decoder.take(ram_bytes + 1).read_to_end(&mut output)?;
+    if output.len() as u64 > ram_bytes {
+        return Err(RecoveryError::ParseError(format!(
+            "Compressed extent expands past its {} uncompressed bytes",
+            ram_bytes
+        ))
+        .into());
+    }
+    Ok(output)
+}
+
+#[cfg(feature = "btrfs-zlib")]
+fn decompress_zlib(data: &[u8], ram_bytes: u64) -> Result<Vec<u8>> {
+    read_bounded(flate2::read::ZlibDecoder::new(data), ram_bytes)
+}
+
+#[cfg(not(feature = "btrfs-zlib"))]
+fn decompress_zlib(_data: &[u8], _ram_bytes: u64) -> Result<Vec<u8>> {
+    Err(RecoveryError::UnsupportedFeature(
+        "zlib extent support not compiled in (enable the `btrfs-zlib` feature)".to_string(),
+    )
+    .into())
+}
+
+#[cfg(feature = "btrfs-lz4")]
+fn decompress_lz4(data: &[u8], ram_bytes: u64) -> Result<Vec<u8>> {
+    lz4_flex::block::decompress(data, ram_bytes as usize)
+        .map_err(|e| anyhow::anyhow!("lz4 decompression failed: {}", e))
+}
+
+#[cfg(not(feature = "btrfs-lz4"))]
+fn decompress_lz4(_data: &[u8], _ram_bytes: u64) -> Result<Vec<u8>> {
+    Err(RecoveryError::UnsupportedFeature(
+        "lz4 extent support not compiled in (enable the `btrfs-lz4` feature)".to_string(),
+    )
+    .into())
+}
+
+#[cfg(feature = "btrfs-zstd")]
+fn decompress_zstd(data: &[u8], ram_bytes: u64) -> Result<Vec<u8>> {
+    read_bounded(zstd::stream::read::Decoder::new(data)?, ram_bytes)
+}
+
+#[cfg(not(feature = "btrfs-zstd"))]
+fn decompress_zstd(_data: &[u8], _ram_bytes: u64) -> Result<Vec<u8>> {
+    Err(RecoveryError::UnsupportedFeature(
+        "zstd extent support not compiled in (enable the `btrfs-zstd` feature)".to_string(),
+    )
+    .into())
+}
+
 /// Btrfs inode reference - links inode to directory
 #[derive(Debug, Clone)]
 pub struct BtrfsInodeRef {
@@ -230,6 +353,104 @@ impl BtrfsInodeRef {
     }
 }
 
+/// Size of a `btrfs_dir_item` header: location key, transid, lengths and type
+const BTRFS_DIR_ITEM_HEADER_SIZE: usize = 30;
+
+/// Name/value pairs of an xattr item
+///
+/// Xattrs are stored as directory items keyed by the name hash, with the
+/// value following the name; names whose hashes collide share one item.
+pub fn parse_xattr_items(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut xattrs = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..offset + BTRFS_DIR_ITEM_HEADER_SIZE) {
+        let data_len = u16::from_le_bytes([header[25], header[26]]) as usize;
+        let name_len = u16::from_le_bytes([header[27], header[28]]) as usize;
+        let name_start = offset + BTRFS_DIR_ITEM_HEADER_SIZE;
+        let value_start = name_start + name_len;
+        let Some(value) = data.get(value_start..value_start + data_len) else {
+            break;
+        };
+        xattrs.push((
+            String::from_utf8_lossy(&data[name_start..value_start]).to_string(),
+            value.to_vec(),
+        ));
+        offset = value_start + data_len;
+    }
+    xattrs
+}
+
+/// Backreferences recorded for one data extent in the extent tree
+#[derive(Debug, Clone, Default)]
+struct ExtentBackrefs {
+    /// Reference count from the extent item
+    refs: u64,
+    /// Subvolume trees referencing the extent by root ID
+    roots: Vec<u64>,
+    /// Referenced through a tree leaf shared between subvolumes, which
+    /// happens once a snapshot shares the file's leaf
+    shared: bool,
+}
+
+impl ExtentBackrefs {
+    /// Parse an extent item: refs, generation and flags, then inline backrefs
+    fn parse(data: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor::new(data);
+        let refs = cursor.read_u64::<LittleEndian>()?;
+        let _generation = cursor.read_u64::<LittleEndian>()?;
+        let flags = cursor.read_u64::<LittleEndian>()?;
+
+        let mut backrefs = Self {
+            refs,
+            ..Default::default()
+        };
+        if flags & BTRFS_EXTENT_FLAG_DATA == 0 {
+            return Ok(backrefs);
+        }
+
+        while let Ok(ref_type) = cursor.read_u8() {
+            match ref_type {
+                // root, objectid, offset, count
+                BTRFS_EXTENT_DATA_REF_KEY => {
+                    backrefs.roots.push(cursor.read_u64::<LittleEndian>()?);
+                    cursor.set_position(cursor.position() + 20);
+                }
+                // parent, count
+                BTRFS_SHARED_DATA_REF_KEY => {
+                    backrefs.shared = true;
+                    cursor.set_position(cursor.position() + 12);
+                }
+                // Anything else means we have lost our place
+                _ => break,
+            }
+        }
+
+        Ok(backrefs)
+    }
+}
+
+/// Extent tree backreferences and the snapshots they may point into
+#[derive(Debug, Default)]
+struct ExtentRefIndex {
+    /// Data extent backrefs keyed by disk bytenr
+    extents: HashMap<u64, ExtentBackrefs>,
+    /// Subvolume IDs of snapshots
+    snapshot_roots: HashSet<u64>,
+}
+
+impl ExtentRefIndex {
+    /// Whether a snapshot still references the extent at `bytenr`
+    fn in_snapshot(&self, bytenr: u64) -> bool {
+        self.extents.get(&bytenr).is_some_and(|backrefs| {
+            backrefs.shared
+                || backrefs
+                    .roots
+                    .iter()
+                    .any(|root| self.snapshot_roots.contains(root))
+        })
+    }
+}
+
 // ============================================================================
 // Recovery Engine
 // ============================================================================
@@ -239,6 +460,11 @@ pub struct BtrfsRecoveryEngine<'a> {
     device: &'a BlockDevice,
     superblock: BtrfsSuperblock,
     tree_reader: BtrfsTreeReader<'a>,
+    backup_roots: bool,
+    list_only: bool,
+    signature_scan: bool,
+    signatures: signatures::SignatureSet,
+    end_markers: signatures::EndMarkerScanner,
 }
 
 impl<'a> BtrfsRecoveryEngine<'a> {
@@ -249,9 +475,41 @@ impl<'a> BtrfsRecoveryEngine<'a> {
             device,
             superblock,
             tree_reader,
+            backup_roots: false,
+            list_only: false,
+            signature_scan: true,
+            signatures: signatures::SignatureSet::default(),
+            end_markers: signatures::EndMarkerScanner::default(),
         })
     }
 
+    /// Also search the trees of the superblock's backup roots
+    pub fn with_backup_roots(mut self, enabled: bool) -> Self {
+        self.backup_roots = enabled;
+        self
+    }
+
+    /// Only list the deleted files, without mapping their extents or
+    /// scanning for signatures
+    pub fn with_list_only(mut self, enabled: bool) -> Self {
+        self.list_only = enabled;
+        self
+    }
+
+    /// Carve files from their content as well; on by default
+    pub fn with_signature_scan(mut self, enabled: bool) -> Self {
+        self.signature_scan = enabled;
+        self
+    }
+
+    /// Identify carved files by `signatures`
+    pub fn with_signatures(mut self, signatures: signatures::SignatureSet) -> Self {
+        self.end_markers =
+            signatures::EndMarkerScanner::default().with_signatures(signatures.clone());
+        self.signatures = signatures;
+        self
+    }
+
     /// Scan for deleted files in the filesystem
     pub fn scan_deleted_files(&self) -> Result<Vec<DeletedFile>> {
         let mut deleted_files = Vec::new();
@@ -283,17 +541,48 @@ impl<'a> BtrfsRecoveryEngine<'a> {
             }
         }
 
-        // Method 3: Signature-based scan for file content
-        match self.scan_file_signatures(&mut file_id_counter) {
-            Ok(mut sig_files) => {
-                tracing::info!("Found {} files via signature scan", sig_files.len());
-                deleted_files.append(&mut sig_files);
+        // Method 3: Files still in the trees of earlier generations
+        if self.backup_roots {
+            let known: HashSet<u64> = deleted_files.iter().map(|f| f.inode_or_cluster).collect();
+            match self.scan_backup_roots(&known, &mut file_id_counter) {
+                Ok(mut historical) => {
+                    tracing::info!("Found {} files in backup roots", historical.len());
+                    deleted_files.append(&mut historical);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to scan backup roots: {}", e);
+                }
             }
-            Err(e) => {
-                tracing::warn!("Signature scan failed: {}", e);
+        }
+
+        if self.list_only {
+            tracing::info!(
+                "Btrfs listing complete: {} deleted files found",
+                deleted_files.len()
+            );
+            return Ok(deleted_files);
+        }
+
+        // Method 4: Signature-based scan for file content
+        if self.signature_scan {
+            match self.scan_file_signatures(&mut file_id_counter) {
+                Ok(mut sig_files) => {
+                    tracing::info!("Found {} files via signature scan", sig_files.len());
+                    deleted_files.append(&mut sig_files);
+                }
+                Err(e) => {
+                    tracing::warn!("Signature scan failed: {}", e);
+                }
             }
         }
 
+        match self.read_extent_allocations() {
+            Ok(allocated) => {
+                mark_reallocated_blocks(&mut deleted_files, &allocated, self.sector_size())
+            }
+            Err(e) => tracing::warn!("Could not read extent tree: {}", e),
+        }
+
         tracing::info!(
             "Btrfs scan complete: {} total deleted files found",
             deleted_files.len()
@@ -322,6 +611,8 @@ impl<'a> BtrfsRecoveryEngine<'a> {
                         inode_num,
                         &inode_info.0,
                         inode_info.1,
+                        &[],
+                        None,
                         0.7, // Good confidence for orphan items
                     );
                     *file_id_counter += 1;
@@ -337,36 +628,173 @@ impl<'a> BtrfsRecoveryEngine<'a> {
 
     /// Scan for inodes with nlink == 0 (deleted files)
     fn scan_unlinked_inodes(&self, file_id_counter: &mut u64) -> Result<Vec<DeletedFile>> {
+        let fs_tree_root = self.find_fs_tree_root()?;
+        self.scan_inodes(
+            fs_tree_root,
+            file_id_counter,
+            0.6, // Medium confidence for unlinked inodes
+            |_, inode| inode.is_deleted(),
+        )
+    }
+
+    /// Deleted files that the trees of earlier generations still hold
+    ///
+    /// A file deleted in the last few transactions is gone from the current
+    /// FS tree, but a backup root may still point at a tree with its inode
+    /// and extents. Inodes the current tree still links, or that `known`
+    /// already holds, are left out. When the current tree is unreadable,
+    /// which is when the backup roots matter most, every inode they hold is
+    /// taken.
+    fn scan_backup_roots(
+        &self,
+        known: &HashSet<u64>,
+        file_id_counter: &mut u64,
+    ) -> Result<Vec<DeletedFile>> {
+        let live = match self
+            .find_fs_tree_root()
+            .and_then(|root| self.live_inodes(root))
+        {
+            Ok(live) => live,
+            Err(e) => {
+                tracing::warn!(
+                    "Current FS tree is unreadable, taking every file of the backup roots: {}",
+                    e
+                );
+                HashSet::new()
+            }
+        };
+        let mut seen = known.clone();
         let mut deleted_files = Vec::new();
 
-        let fs_tree_root = self.find_fs_tree_root()?;
+        // Newest first, so each file comes from the latest tree that has it
+        let mut backups: Vec<_> = self
+            .superblock
+            .backup_roots
+            .iter()
+            .filter(|b| b.fs_root != 0 && b.tree_root_gen < self.superblock.generation)
+            .collect();
+        backups.sort_by_key(|b| Reverse(b.tree_root_gen));
+
+        for backup in backups {
+            tracing::info!(
+                "Scanning backup root of generation {} (FS tree at 0x{:x})",
+                backup.tree_root_gen,
+                backup.fs_root
+            );
+            let found = self.scan_inodes(
+                backup.fs_root,
+                file_id_counter,
+                0.5, // Older trees point at data more likely to be overwritten
+                |inode_num, inode| inode.is_deleted() || !live.contains(&inode_num),
+            );
+            let found = match found {
+                Ok(found) => found,
+                Err(e) => {
+                    tracing::warn!(
+                        "Backup root of generation {} is unreadable: {}",
+                        backup.tree_root_gen,
+                        e
+                    );
+                    continue;
+                }
+            };
+            for mut file in found {
+                if !seen.insert(file.inode_or_cluster) {
+                    continue;
+                }
+                if let Some(crate::FsSpecificMetadata::Btrfs(meta)) = &mut file.fs_metadata {
+                    meta.backup_generation = Some(backup.tree_root_gen);
+                }
+                deleted_files.push(file);
+            }
+        }
+
+        Ok(deleted_files)
+    }
 
-        // Collect all inode items first
+    /// Inode numbers the tree at `fs_tree_root` still links
+    fn live_inodes(&self, fs_tree_root: u64) -> Result<HashSet<u64>> {
+        let mut live = HashSet::new();
+        self.tree_reader.iterate_tree(fs_tree_root, |node, item| {
+            if item.key.item_type == BTRFS_INODE_ITEM_KEY {
+                let inode = node.get_item_data(item).map(BtrfsInodeItem::parse);
+                if let Some(Ok(inode)) = inode {
+                    if !inode.is_deleted() {
+                        live.insert(item.key.objectid);
+                    }
+                }
+            }
+            Ok(true)
+        })?;
+        Ok(live)
+    }
+
+    /// Regular files in the tree at `fs_tree_root` that `is_deleted` picks out
+    fn scan_inodes(
+        &self,
+        fs_tree_root: u64,
+        file_id_counter: &mut u64,
+        base_confidence: f32,
+        is_deleted: impl Fn(u64, &BtrfsInodeItem) -> bool,
+    ) -> Result<Vec<DeletedFile>> {
+        let mut deleted_files = Vec::new();
+
+        // Collect all inode items first, along with their file extents
         let mut inode_items: Vec<(u64, BtrfsInodeItem, Option<String>)> = Vec::new();
+        let mut extents: HashMap<u64, Vec<BtrfsFileExtentItem>> = HashMap::new();
+        let mut xattrs: HashMap<u64, HashMap<String, Vec<u8>>> = HashMap::new();
 
         self.tree_reader.iterate_tree(fs_tree_root, |node, item| {
             if item.key.item_type == BTRFS_INODE_ITEM_KEY {
                 if let Some(data) = node.get_item_data(item) {
                     if let Ok(inode) = BtrfsInodeItem::parse(data) {
                         // Check if this looks like a deleted file
-                        if inode.nlink == 0 && inode.is_regular_file() && inode.size > 0 {
+                        if is_deleted(item.key.objectid, &inode)
+                            && inode.is_regular_file()
+                            && inode.size > 0
+                        {
                             inode_items.push((item.key.objectid, inode, None));
                         }
                     }
                 }
+            } else if item.key.item_type == BTRFS_EXTENT_DATA_KEY {
+                if let Some(data) = node.get_item_data(item) {
+                    if let Ok(extent) = BtrfsFileExtentItem::parse(data) {
+                        extents.entry(item.key.objectid).or_default().push(extent);
+                    }
+                }
+            } else if item.key.item_type == BTRFS_XATTR_ITEM_KEY {
+                if let Some(data) = node.get_item_data(item) {
+                    xattrs
+                        .entry(item.key.objectid)
+                        .or_default()
+                        .extend(parse_xattr_items(data));
+                }
             }
             Ok(true)
         })?;
 
+        let ref_index = match self.read_extent_ref_index() {
+            Ok(index) => Some(index),
+            Err(e) => {
+                tracing::warn!("Could not read extent backrefs: {}", e);
+                None
+            }
+        };
+
         // Convert to DeletedFile
         for (inode_num, inode, name) in inode_items {
-            let file = self.inode_to_deleted_file(
+            let file_extents = extents.remove(&inode_num).unwrap_or_default();
+            let mut file = self.inode_to_deleted_file(
                 *file_id_counter,
                 inode_num,
                 &inode,
                 name,
-                0.6, // Medium confidence for unlinked inodes
+                &file_extents,
+                ref_index.as_ref(),
+                base_confidence,
             );
+            file.metadata.extended_attributes = xattrs.remove(&inode_num).unwrap_or_default();
             *file_id_counter += 1;
             deleted_files.push(file);
         }
@@ -380,13 +808,13 @@ impl<'a> BtrfsRecoveryEngine<'a> {
 
         // Scan blocks looking for file signatures
         let total_bytes = self.superblock.total_bytes;
-        let block_size = self.superblock.sectorsize as u64;
+        let block_size = self.sector_size();
         let max_blocks = std::cmp::min(total_bytes / block_size, 100_000);
 
         for block_num in 0..max_blocks {
             let offset = block_num * block_size;
 
-            if let Ok(data) = self.device.read_bytes(offset, block_size as usize) {
+            if let Ok(data) = self.device.read_at(offset, block_size as usize) {
                 if let Some((mime, ext, file_size)) = self.detect_file_with_size(data, offset) {
                     // Calculate block count from file size
                     let block_count = if file_size > 0 {
@@ -418,12 +846,15 @@ impl<'a> BtrfsRecoveryEngine<'a> {
                             permissions: None,
                             owner_uid: None,
                             owner_gid: None,
+                            owner_name: None,
+                            group_name: None,
                             created_time: None,
                             modified_time: None,
                             accessed_time: None,
                             extended_attributes: std::collections::HashMap::new(),
                         },
                         fs_metadata: None, // TODO: Populate Btrfs metadata for confidence scoring
+                        skip_reason: None,
                     };
                     *file_id_counter += 1;
                     deleted_files.push(file);
@@ -434,6 +865,99 @@ impl<'a> BtrfsRecoveryEngine<'a> {
         Ok(deleted_files)
     }
 
+    /// Location of the extent tree, from its root item in the root tree
+    fn find_extent_tree_root(&self) -> Result<u64> {
+        let extent_root = self
+            .tree_reader
+            .find_items_by_type(
+                self.superblock.root,
+                BTRFS_EXTENT_TREE_OBJECTID,
+                BTRFS_ROOT_ITEM_KEY,
+            )?
+            .into_iter()
+            .find_map(|(_, data)| {
+                data.get(ROOT_ITEM_BYTENR_OFFSET..ROOT_ITEM_BYTENR_OFFSET + 8)
+                    .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            });
+        let Some(extent_root) = extent_root else {
+            bail!("No extent tree root item");
+        };
+        Ok(extent_root)
+    }
+
+    /// Byte ranges referenced by the extent tree, i.e. space that is in use
+    fn read_extent_allocations(&self) -> Result<RangeSet> {
+        let extent_root = self.find_extent_tree_root()?;
+
+        let nodesize = self.superblock.nodesize as u64;
+        let mut extents = Vec::new();
+        self.tree_reader.iterate_tree(extent_root, |_, item| {
+            match item.key.item_type {
+                // Data and tree blocks: key = (bytenr, type, length)
+                BTRFS_EXTENT_ITEM_KEY => extents.push((item.key.objectid, item.key.offset)),
+                // Skinny metadata: key = (bytenr, type, level)
+                BTRFS_METADATA_ITEM_KEY => extents.push((item.key.objectid, nodesize)),
+                _ => {}
+            }
+            Ok(true)
+        })?;
+
+        Ok(RangeSet::from_ranges(extents))
+    }
+
+    /// Collect data extent backrefs from the extent tree and the snapshot
+    /// subvolumes from the root tree
+    fn read_extent_ref_index(&self) -> Result<ExtentRefIndex> {
+        let extent_root = self.find_extent_tree_root()?;
+        let mut index = ExtentRefIndex::default();
+
+        self.tree_reader.iterate_tree(extent_root, |node, item| {
+            let Some(data) = node.get_item_data(item) else {
+                return Ok(true);
+            };
+            let bytenr = item.key.objectid;
+            match item.key.item_type {
+                BTRFS_EXTENT_ITEM_KEY => {
+                    if let Ok(parsed) = ExtentBackrefs::parse(data) {
+                        let backrefs = index.extents.entry(bytenr).or_default();
+                        backrefs.refs = parsed.refs;
+                        backrefs.roots.extend(parsed.roots);
+                        backrefs.shared |= parsed.shared;
+                    }
+                }
+                // Backrefs that did not fit inline get items of their own
+                BTRFS_EXTENT_DATA_REF_KEY => {
+                    if let Some(root) = data.get(0..8) {
+                        let root = u64::from_le_bytes(root.try_into().unwrap());
+                        index.extents.entry(bytenr).or_default().roots.push(root);
+                    }
+                }
+                BTRFS_SHARED_DATA_REF_KEY => {
+                    index.extents.entry(bytenr).or_default().shared = true;
+                }
+                _ => {}
+            }
+            Ok(true)
+        })?;
+
+        // A snapshot's root item is keyed by the transid it was taken at;
+        // plain subvolumes have offset 0
+        self.tree_reader
+            .iterate_tree(self.superblock.root, |_, item| {
+                let key = item.key;
+                if key.item_type == BTRFS_ROOT_ITEM_KEY
+                    && (BTRFS_FIRST_FREE_OBJECTID..=BTRFS_LAST_FREE_OBJECTID)
+                        .contains(&key.objectid)
+                    && key.offset != 0
+                {
+                    index.snapshot_roots.insert(key.objectid);
+                }
+                Ok(true)
+            })?;
+
+        Ok(index)
+    }
+
     /// Find the FS tree root by looking it up in the root tree
     fn find_fs_tree_root(&self) -> Result<u64> {
         // For simplicity, we'll use the root from superblock
@@ -464,34 +988,42 @@ impl<'a> BtrfsRecoveryEngine<'a> {
     }
 
     /// Extract Btrfs-specific metadata for confidence scoring
+    ///
+    /// Reference counts and snapshot membership come from `ref_index` when the
+    /// extent tree could be read, and are estimated from the inode otherwise.
     fn extract_btrfs_metadata(
         &self,
         inode: &BtrfsInodeItem,
-        _inode_num: u64,
+        extents: &[BtrfsFileExtentItem],
+        ref_index: Option<&ExtentRefIndex>,
+        compressed_extents: Vec<BtrfsCompressedExtent>,
     ) -> crate::BtrfsFileMetadata {
-        // Calculate extent reference counts from extent tree (simplified)
-        let extent_refs = if inode.nbytes > 0 {
-            // Estimate refcount based on COW behavior
-            // In real implementation, would traverse extent tree
-            vec![1u64] // Most files have refcount = 1
-        } else {
-            vec![]
+        let (extent_refs, in_snapshot, cow_extent_count) = match ref_index {
+            Some(index) => {
+                // Inline extents and holes have no extent item
+                let bytenrs: Vec<u64> = extents
+                    .iter()
+                    .filter(|e| !e.is_inline() && e.disk_bytenr != 0)
+                    .map(|e| e.disk_bytenr)
+                    .collect();
+                // An extent missing from the tree has been freed
+                let extent_refs = bytenrs
+                    .iter()
+                    .map(|bytenr| index.extents.get(bytenr).map_or(0, |b| b.refs))
+                    .collect();
+                let in_snapshot = bytenrs.iter().any(|&bytenr| index.in_snapshot(bytenr));
+                (extent_refs, in_snapshot, bytenrs.len() as u32)
+            }
+            None if inode.nbytes > 0 => {
+                // Most files have refcount = 1
+                (vec![1u64], false, inode.nbytes.div_ceil(4096) as u32)
+            }
+            None => (vec![], false, 0),
         };
 
-        // Check if file is in snapshot (simplified)
-        // Would need to check subvolume tree in full implementation
-        let in_snapshot = false;
-
         // Validate checksum (simplified - assume valid if inode is parseable)
         let checksum_valid = inode.generation > 0 && inode.transid > 0;
 
-        // COW extent count estimation
-        let cow_extent_count = if inode.nbytes > 0 {
-            inode.nbytes.div_ceil(4096) as u32 // Estimate from blocks
-        } else {
-            0
-        };
-
         crate::BtrfsFileMetadata {
             generation: inode.generation,
             transid: inode.transid,
@@ -500,22 +1032,96 @@ impl<'a> BtrfsRecoveryEngine<'a> {
             in_snapshot,
             cow_extent_count,
             tree_level: 0, // Leaf level for regular files
+            compressed_extents,
+            backup_generation: None,
+            inline_data: None,
         }
     }
 
+    /// Contents of the file's inline extent, decompressed, if it has one
+    fn inline_contents(&self, inode_num: u64, extents: &[BtrfsFileExtentItem]) -> Option<Vec<u8>> {
+        let extent = extents.iter().find(|e| e.is_inline())?;
+        match decompress_extent(&extent.inline_data, extent.compression, extent.ram_bytes) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                tracing::warn!("Inline extent of inode {} is unreadable: {}", inode_num, e);
+                None
+            }
+        }
+    }
+
+    /// Bytes per data block of the files found, the file system's sector size
+    fn sector_size(&self) -> u64 {
+        self.superblock.sectorsize as u64
+    }
+
+    /// Map file extents onto data block ranges, recording compressed extents
+    fn map_extents(
+        &self,
+        extents: &[BtrfsFileExtentItem],
+    ) -> (Vec<BlockRange>, Vec<BtrfsCompressedExtent>) {
+        let mut data_blocks = Vec::new();
+        let mut compressed_extents = Vec::new();
+        let sector_size = self.sector_size();
+
+        for extent in extents {
+            // Inline extents have no disk location; holes have disk_bytenr == 0
+            if extent.is_inline() || extent.disk_bytenr == 0 {
+                continue;
+            }
+
+            if extent.is_compressed() {
+                // Compressed extents must be read whole and decompressed
+                compressed_extents.push(BtrfsCompressedExtent {
+                    block_index: data_blocks.len(),
+                    compression: extent.compression,
+                    disk_num_bytes: extent.disk_num_bytes,
+                    ram_bytes: extent.ram_bytes,
+                    extent_offset: extent.offset,
+                    num_bytes: extent.num_bytes,
+                });
+                data_blocks.push(BlockRange {
+                    start_block: extent.disk_bytenr / sector_size,
+                    block_count: extent.disk_num_bytes.div_ceil(sector_size),
+                    is_allocated: false,
+                });
+            } else {
+                data_blocks.push(BlockRange {
+                    start_block: (extent.disk_bytenr + extent.offset) / sector_size,
+                    block_count: extent.num_bytes.div_ceil(sector_size),
+                    is_allocated: false,
+                });
+            }
+        }
+
+        (data_blocks, compressed_extents)
+    }
+
     /// Convert an inode to a DeletedFile
+    #[allow(clippy::too_many_arguments)]
     fn inode_to_deleted_file(
         &self,
         id: u64,
         inode_num: u64,
         inode: &BtrfsInodeItem,
         name: Option<String>,
+        extents: &[BtrfsFileExtentItem],
+        ref_index: Option<&ExtentRefIndex>,
         base_confidence: f32,
     ) -> DeletedFile {
         let path = name.map(PathBuf::from);
+        let (data_blocks, compressed_extents) = if self.list_only {
+            (Vec::new(), Vec::new())
+        } else {
+            self.map_extents(extents)
+        };
 
         // Extract Btrfs-specific metadata
-        let btrfs_meta = self.extract_btrfs_metadata(inode, inode_num);
+        let mut btrfs_meta =
+            self.extract_btrfs_metadata(inode, extents, ref_index, compressed_extents);
+        if !self.list_only {
+            btrfs_meta.inline_data = self.inline_contents(inode_num, extents);
+        }
 
         DeletedFile {
             id,
@@ -524,12 +1130,8 @@ impl<'a> BtrfsRecoveryEngine<'a> {
             size: inode.size,
             deletion_time: inode.ctime.to_datetime(),
             confidence_score: base_confidence,
-            file_type: if inode.is_directory() {
-                FileType::Directory
-            } else {
-                FileType::RegularFile
-            },
-            data_blocks: Vec::new(), // Would need extent parsing
+            file_type: inode.file_type(),
+            data_blocks,
             is_recoverable: inode.size > 0 && inode.is_regular_file(),
             metadata: FileMetadata {
                 mime_type: None,
@@ -537,12 +1139,15 @@ impl<'a> BtrfsRecoveryEngine<'a> {
                 permissions: Some(inode.mode),
                 owner_uid: Some(inode.uid),
                 owner_gid: Some(inode.gid),
+                owner_name: None,
+                group_name: None,
                 created_time: inode.otime.to_datetime(),
                 modified_time: inode.mtime.to_datetime(),
                 accessed_time: inode.atime.to_datetime(),
                 extended_attributes: std::collections::HashMap::new(),
             },
             fs_metadata: Some(crate::FsSpecificMetadata::Btrfs(btrfs_meta)),
+            skip_reason: None,
         }
     }
 
@@ -552,114 +1157,64 @@ impl<'a> BtrfsRecoveryEngine<'a> {
         header: &[u8],
         start_offset: u64,
     ) -> Option<(String, String, u64)> {
-        if header.len() < 8 {
-            return None;
-        }
-
-        // JPEG: starts with FF D8 FF, ends with FF D9
-        if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
-            let size = self.find_jpeg_end(start_offset);
-            return Some(("image/jpeg".to_string(), "jpg".to_string(), size));
-        }
-
-        // PNG: starts with 89 50 4E 47 0D 0A 1A 0A, ends with IEND chunk
-        if header.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
-            let size = self.find_png_end(start_offset);
-            return Some(("image/png".to_string(), "png".to_string(), size));
-        }
-
-        // PDF: starts with %PDF, ends with %%EOF
-        if header.starts_with(b"%PDF") {
-            let size = self.find_pdf_end(start_offset);
-            return Some(("application/pdf".to_string(), "pdf".to_string(), size));
-        }
-
-        // ZIP: has a more complex structure, use conservative estimate
-        if header.starts_with(&[0x50, 0x4B, 0x03, 0x04]) {
-            // For ZIP files, read a reasonable chunk (1MB max)
-            let size = std::cmp::min(self.superblock.total_bytes - start_offset, 1024 * 1024);
-            return Some(("application/zip".to_string(), "zip".to_string(), size));
-        }
-
-        None
+        let (mime, ext) = self.signatures.detect_file_type(header)?;
+        let size = self.find_file_end(start_offset, &mime);
+        Some((mime, ext, size))
     }
 
-    /// Find JPEG end marker (FF D9)
-    fn find_jpeg_end(&self, start_offset: u64) -> u64 {
-        let max_size = std::cmp::min(
-            self.superblock.total_bytes - start_offset,
-            10 * 1024 * 1024, // Max 10MB for JPEG
-        );
-
-        // Read in chunks to find end marker
-        let chunk_size = 64 * 1024; // 64KB chunks
-        let mut offset = start_offset;
-
-        while offset < start_offset + max_size {
-            let read_size = std::cmp::min(chunk_size, (start_offset + max_size - offset) as usize);
-            if let Ok(data) = self.device.read_bytes(offset, read_size) {
-                // Look for JPEG end marker FF D9
-                for i in 0..data.len().saturating_sub(1) {
-                    if data[i] == 0xFF && data[i + 1] == 0xD9 {
-                        return offset - start_offset + i as u64 + 2;
-                    }
-                }
-            }
-            offset += chunk_size as u64 - 1; // Overlap by 1 byte
-        }
-
-        // If we can't find end marker, return a reasonable estimate
-        max_size
+    /// Size of the file at `start_offset`, from its end marker if one is found
+    ///
+    /// Types without an end marker get a conservative 1 MiB.
+    fn find_file_end(&self, start_offset: u64, mime: &str) -> u64 {
+        let limit = self
+            .signatures
+            .end_marker_search_limit(mime)
+            .unwrap_or(1024 * 1024);
+        let available = self.device.size().saturating_sub(start_offset).min(limit);
+        self.device
+            .read_at(start_offset, available as usize)
+            .ok()
+            .and_then(|data| self.end_markers.find_end(data, start_offset, mime))
+            .map_or(available, |end| end.size)
     }
+}
 
-    /// Find PNG end (IEND chunk)
-    fn find_png_end(&self, start_offset: u64) -> u64 {
-        let max_size = std::cmp::min(
-            self.superblock.total_bytes - start_offset,
-            10 * 1024 * 1024, // Max 10MB for PNG
-        );
-
-        // Read in chunks to find IEND marker
-        let chunk_size = 64 * 1024;
-        let mut offset = start_offset;
-
-        while offset < start_offset + max_size {
-            let read_size = std::cmp::min(chunk_size, (start_offset + max_size - offset) as usize);
-            if let Ok(data) = self.device.read_bytes(offset, read_size) {
-                // Look for IEND + CRC (total 12 bytes: length + IEND + CRC)
-                if let Some(pos) = data.windows(4).position(|w| w == b"IEND") {
-                    // IEND chunk is: 4 bytes length (0) + IEND + 4 bytes CRC
-                    return offset - start_offset + pos as u64 + 8;
-                }
+/// Flag the parts of each file's extents that the extent tree says are in use
+///
+/// A compressed extent only decompresses whole, so it stays one range and is
+/// flagged if any of its blocks is in use. The ranges after a split move, and
+/// the compressed extents are pointed at their new index. Blocks are
+/// `sector_size` bytes.
+fn mark_reallocated_blocks(files: &mut [DeletedFile], allocated: &RangeSet, sector_size: u64) {
+    let is_allocated = |block: u64| allocated.contains(block * sector_size);
+
+    for file in files {
+        let compressed_extents = match file.fs_metadata {
+            Some(crate::FsSpecificMetadata::Btrfs(ref mut meta)) => {
+                &mut meta.compressed_extents[..]
             }
-            offset += chunk_size as u64 - 4; // Overlap by 4 bytes
-        }
-
-        max_size
-    }
-
-    /// Find PDF end (%%EOF marker)
-    fn find_pdf_end(&self, start_offset: u64) -> u64 {
-        let max_size = std::cmp::min(
-            self.superblock.total_bytes - start_offset,
-            50 * 1024 * 1024, // Max 50MB for PDF
-        );
-
-        // Read in chunks to find %%EOF
-        let chunk_size = 64 * 1024;
-        let mut offset = start_offset;
+            _ => &mut [],
+        };
 
-        while offset < start_offset + max_size {
-            let read_size = std::cmp::min(chunk_size, (start_offset + max_size - offset) as usize);
-            if let Ok(data) = self.device.read_bytes(offset, read_size) {
-                if let Some(pos) = data.windows(5).position(|w| w == b"%%EOF") {
-                    return offset - start_offset + pos as u64 + 5;
+        // Looked up by the original indices, as the extents are moved one by one
+        let compressed_indices: Vec<usize> =
+            compressed_extents.iter().map(|e| e.block_index).collect();
+
+        let mut data_blocks = Vec::with_capacity(file.data_blocks.len());
+        for (index, range) in file.data_blocks.iter().enumerate() {
+            match compressed_indices.iter().position(|&i| i == index) {
+                Some(extent) => {
+                    compressed_extents[extent].block_index = data_blocks.len();
+                    data_blocks.push(BlockRange {
+                        is_allocated: (range.start_block..range.start_block + range.block_count)
+                            .any(is_allocated),
+                        ..range.clone()
+                    });
                 }
+                None => data_blocks.extend(range.split_by_allocation(1, is_allocated)),
             }
-            offset += chunk_size as u64 - 5;
         }
-
-        max_size
+        file.data_blocks = data_blocks;
     }
 }
 
@@ -672,6 +1227,9 @@ mod tests {
     use super::*;
     use chrono::Datelike;
 
+    /// Sector size of [`test_superblock`]
+    const SECTOR_SIZE: u64 = 4096;
+
     #[test]
     fn test_btrfs_inode_mode() {
         let mut inode = BtrfsInodeItem {
@@ -718,4 +1276,762 @@ mod tests {
         let dt = ts.to_datetime().unwrap();
         assert_eq!(dt.year(), 2024);
     }
+
+    #[cfg(any(feature = "btrfs-zlib", feature = "btrfs-lz4", feature = "btrfs-zstd"))]
+    fn sample_extent_data() -> Vec<u8> {
+        b"GhostFS compressed extent payload. ".repeat(64)
+    }
+
+    #[cfg(feature = "btrfs-zlib")]
+    #[test]
+    fn test_decompress_zlib_round_trip() {
+        use std::io::Write;
+
+        let original = sample_extent_data();
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&original).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let decompressed =
+            decompress_extent(&compressed, BTRFS_COMPRESS_ZLIB, original.len() as u64).unwrap();
+        assert_eq!(decompressed, original);
+    }
+
+    #[cfg(feature = "btrfs-lz4")]
+    #[test]
+    fn test_decompress_lz4_round_trip() {
+        let original = sample_extent_data();
+        let compressed = lz4_flex::block::compress(&original);
+
+        let decompressed =
+            decompress_extent(&compressed, BTRFS_COMPRESS_LZ4, original.len() as u64).unwrap();
+        assert_eq!(decompressed, original);
+    }
+
+    #[cfg(feature = "btrfs-zstd")]
+    #[test]
+    fn test_decompress_zstd_round_trip() {
+        let original = sample_extent_data();
+        let compressed = zstd::stream::encode_all(original.as_slice(), 3).unwrap();
+
+        let decompressed =
+            decompress_extent(&compressed, BTRFS_COMPRESS_ZSTD, original.len() as u64).unwrap();
+        assert_eq!(decompressed, original);
+    }
+
+    #[test]
+    fn test_decompress_rejects_oversized_ram_bytes() {
+        for compression in [BTRFS_COMPRESS_ZLIB, BTRFS_COMPRESS_LZ4, BTRFS_COMPRESS_ZSTD] {
+            let err = decompress_extent(b"data", compression, 1 << 40).unwrap_err();
+            assert!(err.to_string().contains("uncompressed bytes"), "{}", err);
+        }
+    }
+
+    #[cfg(feature = "btrfs-zlib")]
+    #[test]
+    fn test_decompress_zlib_stops_at_ram_bytes() {
+        use std::io::Write;
+
+        let original = sample_extent_data();
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&original).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let short = original.len() as u64 / 2;
+        assert!(decompress_extent(&compressed, BTRFS_COMPRESS_ZLIB, short).is_err());
+    }
+
+    #[cfg(feature = "btrfs-zstd")]
+    #[test]
+    fn test_decompress_zstd_stops_at_ram_bytes() {
+        let original = sample_extent_data();
+        let compressed = zstd::stream::encode_all(original.as_slice(), 3).unwrap();
+
+        let short = original.len() as u64 / 2;
+        assert!(decompress_extent(&compressed, BTRFS_COMPRESS_ZSTD, short).is_err());
+    }
+
+    #[test]
+    fn test_decompress_unknown_compression() {
+        assert!(decompress_extent(b"data", 9, 4).is_err());
+    }
+
+    /// Build a leaf node holding `(objectid, type, offset, data)` items
+    fn build_leaf(items: &[(u64, u8, u64, Vec<u8>)], nodesize: usize) -> Vec<u8> {
+        let mut node = vec![0u8; nodesize];
+        node[96..100].copy_from_slice(&(items.len() as u32).to_le_bytes());
+        node[100] = 0; // Leaf
+
+        let mut data_end = nodesize - BtrfsHeader::SIZE;
+        for (i, (objectid, item_type, offset, data)) in items.iter().enumerate() {
+            data_end -= data.len();
+            let header = BtrfsHeader::SIZE + i * BtrfsItem::SIZE;
+            node[header..header + 8].copy_from_slice(&objectid.to_le_bytes());
+            node[header + 8] = *item_type;
+            node[header + 9..header + 17].copy_from_slice(&offset.to_le_bytes());
+            node[header + 17..header + 21].copy_from_slice(&(data_end as u32).to_le_bytes());
+            node[header + 21..header + 25].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            let start = BtrfsHeader::SIZE + data_end;
+            node[start..start + data.len()].copy_from_slice(data);
+        }
+        node
+    }
+
+    /// A 4MB filesystem whose root tree is at `root`
+    fn test_superblock(root: u64) -> BtrfsSuperblock {
+        BtrfsSuperblock {
+            magic: *b"_BHRfS_M",
+            uuid: [0; 16],
+            physical_address: 0x10000,
+            flags: 0,
+            magic2: *b"_BHRfS_M",
+            generation: 10,
+            root,
+            chunk_root: 0,
+            log_root: 0,
+            total_bytes: 4 * 1024 * 1024,
+            bytes_used: 0,
+            root_dir_objectid: 6,
+            num_devices: 1,
+            sectorsize: SECTOR_SIZE as u32,
+            nodesize: 16384,
+            stripesize: 4096,
+            chunk_root_generation: 0,
+            backup_roots: Vec::new(),
+        }
+    }
+
+    fn confidence_context() -> crate::recovery::ConfidenceContext {
+        crate::recovery::ConfidenceContext {
+            fs_type: crate::FileSystemType::Btrfs,
+            scan_time: Utc::now(),
+            filesystem_integrity: 1.0,
+            total_files_found: 1,
+            device_activity_level: crate::recovery::ActivityLevel::Low,
+            weights: Default::default(),
+            block_size: 4096,
+        }
+    }
+
+    /// Root item pointing at a tree at `bytenr`
+    fn root_item(bytenr: u64) -> Vec<u8> {
+        let mut item = vec![0u8; 439];
+        item[ROOT_ITEM_BYTENR_OFFSET..ROOT_ITEM_BYTENR_OFFSET + 8]
+            .copy_from_slice(&bytenr.to_le_bytes());
+        item
+    }
+
+    #[test]
+    fn test_extent_tree_marks_reused_blocks() {
+        const NODESIZE: usize = 16384;
+        const ROOT_TREE: u64 = 0x10_0000;
+        const EXTENT_TREE: u64 = 0x11_0000;
+
+        let mut image = vec![0u8; 4 * 1024 * 1024];
+
+        let root_leaf = build_leaf(
+            &[(
+                BTRFS_EXTENT_TREE_OBJECTID,
+                BTRFS_ROOT_ITEM_KEY,
+                0,
+                root_item(EXTENT_TREE),
+            )],
+            NODESIZE,
+        );
+        image[ROOT_TREE as usize..ROOT_TREE as usize + NODESIZE].copy_from_slice(&root_leaf);
+
+        // A live file now owns 8KB at 0x20_2000, and the tree node itself is in use
+        let extent_leaf = build_leaf(
+            &[
+                (0x20_2000, BTRFS_EXTENT_ITEM_KEY, 0x2000, vec![0u8; 24]),
+                (EXTENT_TREE, BTRFS_METADATA_ITEM_KEY, 0, vec![0u8; 24]),
+            ],
+            NODESIZE,
+        );
+        image[EXTENT_TREE as usize..EXTENT_TREE as usize + NODESIZE].copy_from_slice(&extent_leaf);
+
+        let device = BlockDevice::from_vec(image);
+        let engine = BtrfsRecoveryEngine::new(&device, test_superblock(ROOT_TREE)).unwrap();
+
+        let allocated = engine.read_extent_allocations().unwrap();
+        assert!(allocated.contains(0x20_2000));
+        assert!(!allocated.contains(0x20_4000));
+        assert!(allocated.contains(EXTENT_TREE + NODESIZE as u64 - 1));
+
+        // Deleted file at blocks 0x200..0x206; blocks 0x202 and 0x203 were reused
+        let mut files = vec![DeletedFile {
+            id: 1,
+            inode_or_cluster: 257,
+            original_path: None,
+            size: 6 * SECTOR_SIZE,
+            deletion_time: None,
+            confidence_score: 0.5,
+            file_type: FileType::RegularFile,
+            data_blocks: vec![BlockRange {
+                start_block: 0x200,
+                block_count: 6,
+                is_allocated: false,
+            }],
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        }];
+        let context = confidence_context();
+        let intact = crate::recovery::calculate_confidence_score(&files[0], &context);
+
+        mark_reallocated_blocks(&mut files, &allocated, SECTOR_SIZE);
+        let reused = crate::recovery::calculate_confidence_score(&files[0], &context);
+        assert!(reused < intact);
+
+        let runs: Vec<(u64, u64, bool)> = files[0]
+            .data_blocks
+            .iter()
+            .map(|r| (r.start_block, r.block_count, r.is_allocated))
+            .collect();
+        assert_eq!(
+            runs,
+            vec![(0x200, 2, false), (0x202, 2, true), (0x204, 2, false)]
+        );
+    }
+
+    #[test]
+    fn test_reused_compressed_extent_stays_whole() {
+        // An uncompressed extent at 0x200..0x206 with 0x202 reused, then a
+        // compressed one at 0x300..0x304 with 0x301 reused
+        let allocated = RangeSet::from_ranges([
+            (0x202 * SECTOR_SIZE, SECTOR_SIZE),
+            (0x301 * SECTOR_SIZE, SECTOR_SIZE),
+        ]);
+        let compressed = BtrfsCompressedExtent {
+            block_index: 1,
+            compression: BTRFS_COMPRESS_ZLIB,
+            disk_num_bytes: 4 * SECTOR_SIZE,
+            ram_bytes: 16 * SECTOR_SIZE,
+            extent_offset: 0,
+            num_bytes: 16 * SECTOR_SIZE,
+        };
+        let mut files = vec![DeletedFile {
+            id: 1,
+            inode_or_cluster: 257,
+            original_path: None,
+            size: 22 * SECTOR_SIZE,
+            deletion_time: None,
+            confidence_score: 0.5,
+            file_type: FileType::RegularFile,
+            data_blocks: vec![
+                BlockRange {
+                    start_block: 0x200,
+                    block_count: 6,
+                    is_allocated: false,
+                },
+                BlockRange {
+                    start_block: 0x300,
+                    block_count: 4,
+                    is_allocated: false,
+                },
+            ],
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: Some(crate::FsSpecificMetadata::Btrfs(crate::BtrfsFileMetadata {
+                generation: 7,
+                transid: 7,
+                checksum_valid: true,
+                extent_refs: vec![0, 0],
+                in_snapshot: false,
+                cow_extent_count: 0,
+                tree_level: 0,
+                compressed_extents: vec![compressed],
+                backup_generation: None,
+                inline_data: None,
+            })),
+            skip_reason: None,
+        }];
+
+        mark_reallocated_blocks(&mut files, &allocated, SECTOR_SIZE);
+
+        let runs: Vec<(u64, u64, bool)> = files[0]
+            .data_blocks
+            .iter()
+            .map(|r| (r.start_block, r.block_count, r.is_allocated))
+            .collect();
+        assert_eq!(
+            runs,
+            vec![
+                (0x200, 2, false),
+                (0x202, 1, true),
+                (0x203, 3, false),
+                (0x300, 4, true),
+            ]
+        );
+        let Some(crate::FsSpecificMetadata::Btrfs(ref meta)) = files[0].fs_metadata else {
+            panic!("Btrfs metadata was dropped");
+        };
+        assert_eq!(meta.compressed_extents[0].block_index, 3);
+    }
+
+    /// Extent item for a data extent with inline backrefs from `roots`
+    fn data_extent_item(roots: &[u64]) -> Vec<u8> {
+        let mut item = Vec::new();
+        item.extend_from_slice(&(roots.len() as u64).to_le_bytes());
+        item.extend_from_slice(&7u64.to_le_bytes()); // generation
+        item.extend_from_slice(&BTRFS_EXTENT_FLAG_DATA.to_le_bytes());
+        for &root in roots {
+            item.push(BTRFS_EXTENT_DATA_REF_KEY);
+            item.extend_from_slice(&root.to_le_bytes());
+            item.extend_from_slice(&[0u8; 20]); // objectid, offset, count
+        }
+        item
+    }
+
+    /// Inode item of a deleted 8KB regular file
+    fn deleted_inode_item() -> Vec<u8> {
+        let mut item = vec![0u8; 160];
+        item[0..8].copy_from_slice(&7u64.to_le_bytes()); // generation
+        item[8..16].copy_from_slice(&7u64.to_le_bytes()); // transid
+        item[16..24].copy_from_slice(&0x2000u64.to_le_bytes()); // size
+        item[24..32].copy_from_slice(&0x2000u64.to_le_bytes()); // nbytes
+        item[52..56].copy_from_slice(&0o100644u32.to_le_bytes()); // mode
+        item
+    }
+
+    /// Inode item of an 8KB regular file that is still linked
+    fn live_inode_item() -> Vec<u8> {
+        let mut item = deleted_inode_item();
+        item[40..44].copy_from_slice(&1u32.to_le_bytes()); // nlink
+        item
+    }
+
+    /// Regular file extent covering 8KB at `disk_bytenr`
+    fn file_extent_item(disk_bytenr: u64) -> Vec<u8> {
+        let mut item = vec![0u8; 53];
+        item[8..16].copy_from_slice(&0x2000u64.to_le_bytes()); // ram_bytes
+        item[20] = 1; // Regular extent
+        item[21..29].copy_from_slice(&disk_bytenr.to_le_bytes());
+        item[29..37].copy_from_slice(&0x2000u64.to_le_bytes()); // disk_num_bytes
+        item[45..53].copy_from_slice(&0x2000u64.to_le_bytes()); // num_bytes
+        item
+    }
+
+    /// `btrfs_dir_item` holding an xattr name and value
+    fn xattr_item(name: &str, value: &[u8]) -> Vec<u8> {
+        let mut item = vec![0u8; BTRFS_DIR_ITEM_HEADER_SIZE];
+        item[25..27].copy_from_slice(&(value.len() as u16).to_le_bytes());
+        item[27..29].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        item.extend_from_slice(name.as_bytes());
+        item.extend_from_slice(value);
+        item
+    }
+
+    #[test]
+    fn test_parse_xattr_items() {
+        // Two names whose hashes collided, packed into one item
+        let mut data = xattr_item("security.selinux", b"system_u:object_r:user_home_t:s0\0");
+        data.extend(xattr_item("user.mime_type", b"image/jpeg"));
+
+        let xattrs = parse_xattr_items(&data);
+        assert_eq!(
+            xattrs,
+            vec![
+                (
+                    "security.selinux".to_string(),
+                    b"system_u:object_r:user_home_t:s0\0".to_vec()
+                ),
+                ("user.mime_type".to_string(), b"image/jpeg".to_vec()),
+            ]
+        );
+
+        // A truncated trailing item is dropped
+        assert_eq!(parse_xattr_items(&data[..data.len() - 3]).len(), 1);
+    }
+
+    #[test]
+    fn test_unlinked_inode_keeps_xattrs() {
+        const NODESIZE: usize = 16384;
+        const ROOT_TREE: u64 = 0x10_0000;
+
+        let mut image = vec![0u8; 4 * 1024 * 1024];
+        let root_leaf = build_leaf(
+            &[
+                (257, BTRFS_INODE_ITEM_KEY, 0, deleted_inode_item()),
+                (
+                    257,
+                    BTRFS_XATTR_ITEM_KEY,
+                    0x1234,
+                    xattr_item("user.origin", b"https://example.com/a.jpg"),
+                ),
+                (257, BTRFS_EXTENT_DATA_KEY, 0, file_extent_item(0x20_0000)),
+            ],
+            NODESIZE,
+        );
+        image[ROOT_TREE as usize..ROOT_TREE as usize + NODESIZE].copy_from_slice(&root_leaf);
+
+        let device = BlockDevice::from_vec(image);
+        let engine = BtrfsRecoveryEngine::new(&device, test_superblock(ROOT_TREE)).unwrap();
+
+        let mut next_id = 1;
+        let files = engine.scan_unlinked_inodes(&mut next_id).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            files[0].metadata.extended_attributes["user.origin"],
+            b"https://example.com/a.jpg"
+        );
+    }
+
+    #[test]
+    fn test_inline_file_recovered_byte_for_byte() {
+        const NODESIZE: usize = 16384;
+        const ROOT_TREE: u64 = 0x10_0000;
+        let content = b"#!/bin/sh\necho small files live in the tree\n";
+
+        let mut inode = deleted_inode_item();
+        inode[16..24].copy_from_slice(&(content.len() as u64).to_le_bytes()); // size
+        inode[24..32].copy_from_slice(&0u64.to_le_bytes()); // nbytes
+        let mut extent = vec![0u8; 21];
+        extent[8..16].copy_from_slice(&(content.len() as u64).to_le_bytes()); // ram_bytes
+        extent.extend_from_slice(content); // Inline extent, type 0
+
+        let mut image = vec![0u8; 4 * 1024 * 1024];
+        let root_leaf = build_leaf(
+            &[
+                (257, BTRFS_INODE_ITEM_KEY, 0, inode),
+                (257, BTRFS_EXTENT_DATA_KEY, 0, extent),
+            ],
+            NODESIZE,
+        );
+        image[ROOT_TREE as usize..ROOT_TREE as usize + NODESIZE].copy_from_slice(&root_leaf);
+
+        let device = BlockDevice::from_vec(image);
+        let engine = BtrfsRecoveryEngine::new(&device, test_superblock(ROOT_TREE)).unwrap();
+
+        let mut next_id = 1;
+        let files = engine.scan_unlinked_inodes(&mut next_id).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].data_blocks.is_empty());
+        assert_eq!(files[0].inline_data(), Some(&content[..]));
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("inline.sh");
+        let (written, damaged) = crate::recover_single_file(
+            &device,
+            &files[0],
+            &output,
+            crate::FileSystemType::Btrfs,
+            4096,
+        )
+        .unwrap();
+        assert_eq!(written, content.len() as u64);
+        assert!(damaged.is_empty());
+        assert_eq!(std::fs::read(&output).unwrap(), content);
+    }
+
+    #[test]
+    fn test_extents_are_in_sectors() {
+        const SECTOR: u64 = 16384;
+        let mut superblock = test_superblock(0x10_0000);
+        superblock.sectorsize = SECTOR as u32;
+        let mut image = vec![0u8; 4 * 1024 * 1024];
+        image[0x20_0000 + SECTOR as usize..0x20_0000 + 2 * SECTOR as usize].fill(0xAB);
+        let device = BlockDevice::from_vec(image);
+        let engine = BtrfsRecoveryEngine::new(&device, superblock).unwrap();
+
+        // The second sector of an extent at 2 MiB
+        let extent = BtrfsFileExtentItem {
+            generation: 1,
+            ram_bytes: 2 * SECTOR,
+            compression: BTRFS_COMPRESS_NONE,
+            encryption: 0,
+            other_encoding: 0,
+            extent_type: 1,
+            disk_bytenr: 0x20_0000,
+            disk_num_bytes: 2 * SECTOR,
+            offset: SECTOR,
+            num_bytes: SECTOR,
+            inline_data: Vec::new(),
+        };
+        let (data_blocks, _) = engine.map_extents(&[extent]);
+        assert_eq!(
+            (data_blocks[0].start_block, data_blocks[0].block_count),
+            (0x20_0000 / SECTOR + 1, 1)
+        );
+
+        let file = DeletedFile {
+            id: 1,
+            inode_or_cluster: 257,
+            original_path: None,
+            size: SECTOR,
+            deletion_time: None,
+            confidence_score: 0.5,
+            file_type: FileType::RegularFile,
+            data_blocks,
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        };
+        let preview = crate::preview_file(
+            &file,
+            &device,
+            crate::FileSystemType::Btrfs,
+            SECTOR as u32,
+            64,
+        )
+        .unwrap();
+        assert_eq!(preview, vec![0xAB; 64]);
+    }
+
+    #[test]
+    fn test_signature_scan_can_be_skipped() {
+        const NODESIZE: usize = 16384;
+        const ROOT_TREE: u64 = 0x10_0000;
+
+        let mut image = vec![0u8; 4 * 1024 * 1024];
+        let root_leaf = build_leaf(
+            &[(257, BTRFS_INODE_ITEM_KEY, 0, deleted_inode_item())],
+            NODESIZE,
+        );
+        image[ROOT_TREE as usize..ROOT_TREE as usize + NODESIZE].copy_from_slice(&root_leaf);
+        // A JPEG no tree references
+        image[0x30_0000..0x30_0004].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+
+        let device = BlockDevice::from_vec(image);
+        let inodes = |signature_scan: bool| {
+            let engine = BtrfsRecoveryEngine::new(&device, test_superblock(ROOT_TREE))
+                .unwrap()
+                .with_signature_scan(signature_scan);
+            let mut inodes: Vec<u64> = engine
+                .scan_deleted_files()
+                .unwrap()
+                .iter()
+                .map(|file| file.inode_or_cluster)
+                .collect();
+            inodes.sort();
+            inodes
+        };
+
+        // Carved files are numbered by the block they start at
+        assert_eq!(inodes(true), vec![257, 0x300]);
+        assert_eq!(inodes(false), vec![257]);
+    }
+
+    #[test]
+    fn test_files_recovered_from_backup_roots() {
+        const NODESIZE: usize = 16384;
+        const ROOT_TREE: u64 = 0x10_0000;
+
+        let mut image = vec![0u8; 4 * 1024 * 1024];
+        let mut put_leaf = |at: u64, items: &[(u64, u8, u64, Vec<u8>)]| {
+            let leaf = build_leaf(items, NODESIZE);
+            image[at as usize..at as usize + NODESIZE].copy_from_slice(&leaf);
+        };
+
+        // Generation 12: 258 is live, 259 was just unlinked
+        put_leaf(
+            ROOT_TREE,
+            &[
+                (258, BTRFS_INODE_ITEM_KEY, 0, live_inode_item()),
+                (259, BTRFS_INODE_ITEM_KEY, 0, deleted_inode_item()),
+            ],
+        );
+        // Generation 11 still had 257, which was deleted since
+        put_leaf(
+            0x12_0000,
+            &[
+                (257, BTRFS_INODE_ITEM_KEY, 0, live_inode_item()),
+                (257, BTRFS_EXTENT_DATA_KEY, 0, file_extent_item(0x20_0000)),
+                (258, BTRFS_INODE_ITEM_KEY, 0, live_inode_item()),
+            ],
+        );
+        // Generation 10 also had 257, and 256, deleted one transaction earlier
+        put_leaf(
+            0x13_0000,
+            &[
+                (256, BTRFS_INODE_ITEM_KEY, 0, live_inode_item()),
+                (257, BTRFS_INODE_ITEM_KEY, 0, live_inode_item()),
+                (259, BTRFS_INODE_ITEM_KEY, 0, live_inode_item()),
+            ],
+        );
+        // The slot for the current generation points at the live tree
+        put_leaf(
+            0x14_0000,
+            &[(300, BTRFS_INODE_ITEM_KEY, 0, deleted_inode_item())],
+        );
+
+        let superblock = &mut image[0x10000..0x11000];
+        superblock[64..72].copy_from_slice(b"_BHRfS_M");
+        superblock[72..80].copy_from_slice(&12u64.to_le_bytes()); // generation
+        superblock[80..88].copy_from_slice(&ROOT_TREE.to_le_bytes());
+        superblock[112..120].copy_from_slice(&(4u64 << 20).to_le_bytes()); // total_bytes
+        superblock[144..148].copy_from_slice(&4096u32.to_le_bytes()); // sectorsize
+        superblock[148..152].copy_from_slice(&(NODESIZE as u32).to_le_bytes());
+        for (slot, (generation, fs_root)) in
+            [(12u64, 0x14_0000u64), (10, 0x13_0000), (11, 0x12_0000)]
+                .into_iter()
+                .enumerate()
+        {
+            let start = 0xB2B + slot * 168;
+            let backup = &mut superblock[start..start + 168];
+            backup[0..8].copy_from_slice(&ROOT_TREE.to_le_bytes()); // tree_root
+            backup[8..16].copy_from_slice(&generation.to_le_bytes());
+            backup[48..56].copy_from_slice(&fs_root.to_le_bytes());
+            backup[56..64].copy_from_slice(&generation.to_le_bytes());
+        }
+
+        let found = |image: &[u8], backup_roots: bool| {
+            let device = BlockDevice::from_vec(image.to_vec());
+            let mut files: Vec<(u64, Option<u64>)> = super::super::scan_for_deleted_files(
+                &device,
+                super::super::BtrfsScanOptions {
+                    backup_roots,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .iter()
+            .map(|file| match &file.fs_metadata {
+                Some(crate::FsSpecificMetadata::Btrfs(meta)) => {
+                    (file.inode_or_cluster, meta.backup_generation)
+                }
+                other => panic!("Expected Btrfs metadata, got {:?}", other),
+            })
+            .collect();
+            files.sort();
+            files
+        };
+
+        assert_eq!(found(&image, false), vec![(259, None)]);
+        // 257 comes from the newest tree that has it; 259 only once
+        assert_eq!(
+            found(&image, true),
+            vec![(256, Some(10)), (257, Some(11)), (259, None)]
+        );
+
+        // With the root tree past the end of the image, every file of the
+        // backup roots is taken
+        image[0x10000 + 80..0x10000 + 88].copy_from_slice(&(8u64 << 20).to_le_bytes());
+        assert_eq!(
+            found(&image, true),
+            vec![
+                (256, Some(10)),
+                (257, Some(11)),
+                (258, Some(11)),
+                (259, Some(10))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshotted_file_scores_higher() {
+        const NODESIZE: usize = 16384;
+        const ROOT_TREE: u64 = 0x10_0000;
+        const EXTENT_TREE: u64 = 0x11_0000;
+        const SNAPSHOT: u64 = 260;
+
+        let mut image = vec![0u8; 4 * 1024 * 1024];
+
+        // Inodes 257 and 258 were deleted; a snapshot taken at transid 9 still has 257
+        let root_leaf = build_leaf(
+            &[
+                (
+                    BTRFS_EXTENT_TREE_OBJECTID,
+                    BTRFS_ROOT_ITEM_KEY,
+                    0,
+                    root_item(EXTENT_TREE),
+                ),
+                (257, BTRFS_INODE_ITEM_KEY, 0, deleted_inode_item()),
+                (257, BTRFS_EXTENT_DATA_KEY, 0, file_extent_item(0x20_0000)),
+                (258, BTRFS_INODE_ITEM_KEY, 0, deleted_inode_item()),
+                (258, BTRFS_EXTENT_DATA_KEY, 0, file_extent_item(0x21_0000)),
+                (SNAPSHOT, BTRFS_ROOT_ITEM_KEY, 9, root_item(0x12_0000)),
+            ],
+            NODESIZE,
+        );
+        image[ROOT_TREE as usize..ROOT_TREE as usize + NODESIZE].copy_from_slice(&root_leaf);
+
+        let extent_leaf = build_leaf(
+            &[
+                (
+                    0x20_0000,
+                    BTRFS_EXTENT_ITEM_KEY,
+                    0x2000,
+                    data_extent_item(&[BTRFS_FS_TREE_OBJECTID, SNAPSHOT]),
+                ),
+                (
+                    0x21_0000,
+                    BTRFS_EXTENT_ITEM_KEY,
+                    0x2000,
+                    data_extent_item(&[BTRFS_FS_TREE_OBJECTID]),
+                ),
+            ],
+            NODESIZE,
+        );
+        image[EXTENT_TREE as usize..EXTENT_TREE as usize + NODESIZE].copy_from_slice(&extent_leaf);
+
+        let device = BlockDevice::from_vec(image);
+        let engine = BtrfsRecoveryEngine::new(&device, test_superblock(ROOT_TREE)).unwrap();
+
+        let mut next_id = 1;
+        let files = engine.scan_unlinked_inodes(&mut next_id).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let btrfs_meta = |inode: u64| {
+            let file = files.iter().find(|f| f.inode_or_cluster == inode).unwrap();
+            match &file.fs_metadata {
+                Some(crate::FsSpecificMetadata::Btrfs(meta)) => meta.clone(),
+                other => panic!("Expected Btrfs metadata, got {:?}", other),
+            }
+        };
+        let snapshotted = btrfs_meta(257);
+        assert!(snapshotted.in_snapshot);
+        assert_eq!(snapshotted.extent_refs, vec![2]);
+        assert_eq!(snapshotted.cow_extent_count, 1);
+        let unique = btrfs_meta(258);
+        assert!(!unique.in_snapshot);
+        assert_eq!(unique.extent_refs, vec![1]);
+
+        let context = confidence_context();
+        let score = |inode: u64| {
+            let file = files.iter().find(|f| f.inode_or_cluster == inode).unwrap();
+            crate::recovery::calculate_confidence_score(file, &context)
+        };
+        assert!(score(257) > score(258));
+    }
 }
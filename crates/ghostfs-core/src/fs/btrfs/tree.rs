@@ -16,10 +16,17 @@ use super::BlockDevice;
 pub const BTRFS_INODE_ITEM_KEY: u8 = 1;
 pub const BTRFS_INODE_REF_KEY: u8 = 12;
 pub const BTRFS_INODE_EXTREF_KEY: u8 = 13;
+pub const BTRFS_XATTR_ITEM_KEY: u8 = 24;
 pub const BTRFS_DIR_ITEM_KEY: u8 = 84;
 pub const BTRFS_DIR_INDEX_KEY: u8 = 96;
 pub const BTRFS_EXTENT_DATA_KEY: u8 = 108;
 pub const BTRFS_ORPHAN_ITEM_KEY: u8 = 48;
+pub const BTRFS_EXTENT_ITEM_KEY: u8 = 168;
+pub const BTRFS_METADATA_ITEM_KEY: u8 = 169;
+pub const BTRFS_TREE_BLOCK_REF_KEY: u8 = 176;
+pub const BTRFS_EXTENT_DATA_REF_KEY: u8 = 178;
+pub const BTRFS_SHARED_BLOCK_REF_KEY: u8 = 182;
+pub const BTRFS_SHARED_DATA_REF_KEY: u8 = 184;
 pub const BTRFS_ROOT_ITEM_KEY: u8 = 132;
 pub const BTRFS_ROOT_REF_KEY: u8 = 156;
 pub const BTRFS_CHUNK_ITEM_KEY: u8 = 228;
@@ -32,6 +39,10 @@ pub const BTRFS_DEV_TREE_OBJECTID: u64 = 4;
 pub const BTRFS_FS_TREE_OBJECTID: u64 = 5;
 pub const BTRFS_ORPHAN_OBJECTID: u64 = u64::MAX - 5; // -6
 pub const BTRFS_FIRST_FREE_OBJECTID: u64 = 256;
+pub const BTRFS_LAST_FREE_OBJECTID: u64 = u64::MAX - 255; // -256
+
+/// Extent item flags
+pub const BTRFS_EXTENT_FLAG_DATA: u64 = 0x1;
 
 // ============================================================================
 // Structures
@@ -93,9 +104,9 @@ impl BtrfsHeader {
         let flags = cursor.read_u64::<LittleEndian>()?;
 
         let mut chunk_tree_uuid = [0u8; 16];
-        chunk_tree_uuid.copy_from_slice(&data[72..88]);
+        chunk_tree_uuid.copy_from_slice(&data[64..80]);
 
-        let mut cursor = Cursor::new(&data[88..]);
+        let mut cursor = Cursor::new(&data[80..]);
         let generation = cursor.read_u64::<LittleEndian>()?;
         let owner = cursor.read_u64::<LittleEndian>()?;
         let nritems = cursor.read_u32::<LittleEndian>()?;
@@ -215,7 +226,7 @@ impl<'a> BtrfsTreeReader<'a> {
 
     /// Read and parse a tree node at the given logical address
     pub fn read_node(&self, bytenr: u64) -> Result<BtrfsNode> {
-        let data = self.device.read_bytes(bytenr, self.nodesize as usize)?;
+        let data = self.device.read_at(bytenr, self.nodesize as usize)?;
         self.parse_node(data)
     }
 
@@ -0,0 +1,434 @@
+/// Read-only access to image files and raw block devices
+///
+/// Image files and block devices are opened through the same path so every
+/// scanner can work on either. Block devices report a zero length through
+/// `metadata()`, so their size comes from the kernel instead, and they are
+/// never opened with write access.
+///
+/// Both are read through a memory mapping, and so through the page cache,
+/// rather than with `O_DIRECT`. The scanners borrow their bytes straight
+/// from the mapping; `O_DIRECT` reads would have to be copied into aligned
+/// buffers first, and would lose the readahead sequential scans rely on.
+/// Nothing is ever written, so the cache cannot hold anything but the
+/// device's own bytes.
+use anyhow::{bail, Context, Result};
+use memmap2::{Mmap, MmapOptions};
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// A mount of the device (or one of its partitions) found in the mount table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountEntry {
+    pub source: PathBuf,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub read_only: bool,
+}
+
+/// Whether `path` refers to a block device rather than a regular file
+pub fn is_block_device(path: &Path) -> Result<bool> {
+    let metadata =
+        std::fs::metadata(path).with_context(|| format!("Failed to stat {}", path.display()))?;
+    Ok(file_type_is_block_device(&metadata.file_type()))
+}
+
+#[cfg(unix)]
+fn file_type_is_block_device(file_type: &std::fs::FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    file_type.is_block_device()
+}
+
+#[cfg(not(unix))]
+fn file_type_is_block_device(_file_type: &std::fs::FileType) -> bool {
+    false
+}
+
+/// Open an image file or block device strictly read-only
+///
+/// Devices are read through a memory mapping, and so through the page
+/// cache; `O_DIRECT` would have no effect on that and is not used.
+pub fn open_read_only(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(false)
+        .open(path)
+        .with_context(|| format!("Failed to open {} read-only", path.display()))
+}
+
+/// Size in bytes of an opened image file or block device
+pub fn device_size(file: &File) -> Result<u64> {
+    let metadata = file.metadata()?;
+    if file_type_is_block_device(&metadata.file_type()) {
+        block_device_size(file)
+    } else {
+        Ok(metadata.len())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn block_device_size(file: &File) -> Result<u64> {
+    use std::os::unix::io::AsRawFd;
+
+    // BLKGETSIZE64 from <linux/fs.h>
+    const BLKGETSIZE64: libc::Ioctl = libc::_IOR::<libc::size_t>(0x12, 114);
+
+    let mut size: u64 = 0;
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), BLKGETSIZE64, &mut size as *mut u64) };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error()).context("BLKGETSIZE64 ioctl failed");
+    }
+    Ok(size)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn block_device_size(file: &File) -> Result<u64> {
+    use std::io::{Seek, SeekFrom};
+
+    // Seeking to the end reports the device size on most Unix systems
+    let mut file = file;
+    Ok(file.seek(SeekFrom::End(0))?)
+}
+
+/// Free bytes available to unprivileged users on the file system holding `path`
+///
+/// `path` need not exist yet; its nearest existing ancestor is checked.
+/// Returns `None` on platforms where this is not supported.
+#[cfg(target_os = "linux")]
+pub fn available_space(path: &Path) -> Result<Option<u64>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Path::new("."));
+    let c_path = std::ffi::CString::new(existing.as_os_str().as_bytes())?;
+
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Failed to stat file system of {}", existing.display()));
+    }
+    Ok(Some(stat.f_bavail as u64 * stat.f_frsize as u64))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn available_space(_path: &Path) -> Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Open an image file or block device read-only and map it into memory
+///
+/// A segment of a split image (`image.001`, ...) maps the whole set, and an
+/// EWF image is decompressed into a file in the system temporary directory;
+/// the returned file is then the first segment.
+pub fn map_read_only(path: &Path) -> Result<(File, Mmap, u64)> {
+    map_read_only_in(path, None)
+}
+
+/// Like [`map_read_only`], with temporary files in `temp_dir` if given
+pub fn map_read_only_in(path: &Path, temp_dir: Option<&Path>) -> Result<(File, Mmap, u64)> {
+    if is_ewf_path(path) {
+        return map_ewf(path, temp_dir);
+    }
+    if let Some(paths) = super::split::detect_segments(path)? {
+        let (segments, mmap) = super::split::map_segments(&paths)?;
+        let size = mmap.len() as u64;
+        return Ok((open_read_only(&segments[0].path)?, mmap, size));
+    }
+
+    let file = open_read_only(path)?;
+    let size = device_size(&file)?;
+    if size == 0 {
+        bail!("{} is empty", path.display());
+    }
+
+    let mmap = unsafe { MmapOptions::new().len(size as usize).map(&file)? };
+    Ok((file, mmap, size))
+}
+
+/// Largest single `pread` made by [`FileReads`]
+const FILE_READ_CHUNK: u64 = 1024 * 1024;
+
+thread_local! {
+    /// Buffer the reads of [`FileReads`] land in, reused by each thread
+    static READ_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Reads of a mapped block device, made through the device first
+///
+/// A media error on a mapped device arrives as SIGBUS when the page is
+/// touched, which no retry can catch. Reading the bytes with `pread` first
+/// turns the error into a failed read that [`BlockDevice`] retries and
+/// records, and leaves the bytes in the page cache the mapping reads from.
+///
+/// This doubles the cost of every read, so it is only used for block
+/// devices; image files are read straight from their mapping.
+///
+/// [`BlockDevice`]: super::BlockDevice
+pub struct FileReads {
+    /// Backing files with the device offset and length of each, in order
+    files: Vec<(u64, u64, File)>,
+}
+
+impl FileReads {
+    /// Read through the device at `path`
+    ///
+    /// Returns `None` unless `path` is a block device (or a split set
+    /// holding one); EWF images are decompressed before reading.
+    pub fn open(path: &Path) -> Result<Option<Self>> {
+        if is_ewf_path(path) {
+            return Ok(None);
+        }
+        let paths =
+            super::split::detect_segments(path)?.unwrap_or_else(|| vec![path.to_path_buf()]);
+        Self::for_block_devices(&paths)
+    }
+
+    /// Read through `paths` if any of them is a block device
+    pub fn for_block_devices(paths: &[PathBuf]) -> Result<Option<Self>> {
+        for path in paths {
+            if is_block_device(path)? {
+                return Self::from_paths(paths).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read through `paths`, mapped back to back in this order
+    pub fn from_paths(paths: &[PathBuf]) -> Result<Self> {
+        let mut files = Vec::with_capacity(paths.len());
+        let mut offset = 0;
+        for path in paths {
+            let file = open_read_only(path)?;
+            let length = device_size(&file)?;
+            files.push((offset, length, file));
+            offset += length;
+        }
+        Ok(Self { files })
+    }
+}
+
+impl super::FaultSource for FileReads {
+    fn fails(&self, offset: u64, length: u64) -> bool {
+        let end = offset + length;
+        READ_BUFFER.with_borrow_mut(|buffer| {
+            buffer.resize(length.min(FILE_READ_CHUNK) as usize, 0);
+            for (start, file_length, file) in &self.files {
+                let mut position = offset.max(*start);
+                let until = end.min(start + file_length);
+                while position < until {
+                    let piece = (until - position).min(FILE_READ_CHUNK) as usize;
+                    if let Err(e) = read_exact_at(file, &mut buffer[..piece], position - start) {
+                        tracing::debug!("Read error at device offset {}: {}", position, e);
+                        return true;
+                    }
+                    position += piece as u64;
+                }
+            }
+            false
+        })
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buffer, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buffer: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buffer.is_empty() {
+        match file.seek_read(buffer, offset)? {
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            read => {
+                buffer = &mut buffer[read..];
+                offset += read as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` names the first segment of an EWF image (`.E01` or `.Ex01`)
+pub fn is_ewf_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("e01") || e.eq_ignore_ascii_case("ex01"))
+}
+
+#[cfg(feature = "ewf")]
+fn map_ewf(path: &Path, temp_dir: Option<&Path>) -> Result<(File, Mmap, u64)> {
+    let image = super::ewf::EwfImage::open(path)?;
+    let mmap = match temp_dir {
+        Some(dir) => image.to_mmap_in(dir)?,
+        None => image.to_mmap()?,
+    };
+    let size = mmap.len() as u64;
+    Ok((open_read_only(path)?, mmap, size))
+}
+
+#[cfg(not(feature = "ewf"))]
+fn map_ewf(path: &Path, _temp_dir: Option<&Path>) -> Result<(File, Mmap, u64)> {
+    bail!(
+        "{} is an EWF image, which requires the ewf feature",
+        path.display()
+    )
+}
+
+/// Find a writable mount of the device at `path` or of any of its partitions
+pub fn find_writable_mount(path: &Path) -> Result<Option<MountEntry>> {
+    if !is_block_device(path)? {
+        return Ok(None);
+    }
+
+    let mounts = match std::fs::read_to_string("/proc/self/mounts") {
+        Ok(contents) => parse_mounts(&contents),
+        // No mount table to consult (non-Linux); nothing to check against
+        Err(_) => return Ok(None),
+    };
+
+    let device = std::fs::canonicalize(path)?;
+    Ok(mounts.into_iter().find(|mount| {
+        let source = std::fs::canonicalize(&mount.source).unwrap_or_else(|_| mount.source.clone());
+        !mount.read_only && is_same_or_partition(&device, &source)
+    }))
+}
+
+/// Refuse to work on a block device that is mounted writable, unless forced
+///
+/// Scanning a filesystem that is live and being written to gives inconsistent
+/// results, and any mistake could destroy the data being recovered.
+pub fn ensure_not_mounted_writable(path: &Path, force: bool) -> Result<()> {
+    if let Some(mount) = find_writable_mount(path)? {
+        if force {
+            tracing::warn!(
+                "{} is mounted read-write at {}; continuing because of --force",
+                mount.source.display(),
+                mount.mount_point.display()
+            );
+        } else {
+            bail!(
+                "{} is mounted read-write at {}. Unmount it or remount read-only first \
+                 (or pass --force to scan it anyway)",
+                mount.source.display(),
+                mount.mount_point.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Parse `/proc/self/mounts` formatted text
+fn parse_mounts(contents: &str) -> Vec<MountEntry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            let options = fields.next()?;
+
+            Some(MountEntry {
+                source: PathBuf::from(unescape_mount_field(source)),
+                mount_point: PathBuf::from(unescape_mount_field(mount_point)),
+                fs_type: fs_type.to_string(),
+                read_only: options.split(',').any(|opt| opt == "ro"),
+            })
+        })
+        .collect()
+}
+
+/// Undo the octal escaping (`\040` for space, etc.) used in the mount table
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let escaped = std::str::from_utf8(&bytes[i + 1..i + 4]).ok();
+            if let Some(value) = escaped.and_then(|digits| u8::from_str_radix(digits, 8).ok()) {
+                out.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `/dev/sdb` matches `/dev/sdb`, `/dev/sdb1`; `/dev/nvme0n1` matches `/dev/nvme0n1p2`
+fn is_same_or_partition(device: &Path, source: &Path) -> bool {
+    if device == source {
+        return true;
+    }
+
+    let device = device.to_string_lossy();
+    let source = source.to_string_lossy();
+    match source.strip_prefix(device.as_ref()) {
+        Some(rest) => {
+            // Devices whose name ends in a digit separate partitions with 'p'
+            let digits = if device.ends_with(|c: char| c.is_ascii_digit()) {
+                match rest.strip_prefix('p') {
+                    Some(digits) => digits,
+                    None => return false,
+                }
+            } else {
+                rest
+            };
+            !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mounts() {
+        let table = "/dev/sda1 / ext4 rw,relatime 0 0\n\
+                     /dev/sdb1 /mnt/usb\\040stick exfat ro,nosuid 0 0\n\
+                     proc /proc proc rw,nosuid,nodev,noexec 0 0\n";
+        let mounts = parse_mounts(table);
+
+        assert_eq!(mounts.len(), 3);
+        assert!(!mounts[0].read_only);
+        assert!(mounts[1].read_only);
+        assert_eq!(mounts[1].mount_point, PathBuf::from("/mnt/usb stick"));
+        assert_eq!(mounts[1].fs_type, "exfat");
+    }
+
+    #[test]
+    fn test_partition_matching() {
+        let sdb = Path::new("/dev/sdb");
+        assert!(is_same_or_partition(sdb, Path::new("/dev/sdb")));
+        assert!(is_same_or_partition(sdb, Path::new("/dev/sdb2")));
+        assert!(!is_same_or_partition(sdb, Path::new("/dev/sdba")));
+        assert!(!is_same_or_partition(sdb, Path::new("/dev/sda1")));
+
+        let nvme = Path::new("/dev/nvme0n1");
+        assert!(is_same_or_partition(nvme, Path::new("/dev/nvme0n1p3")));
+        assert!(!is_same_or_partition(nvme, Path::new("/dev/nvme0n10")));
+    }
+
+    #[test]
+    fn test_regular_file_is_not_a_device() {
+        let image = tempfile::NamedTempFile::new().unwrap();
+        image.as_file().set_len(8192).unwrap();
+
+        assert!(!is_block_device(image.path()).unwrap());
+        assert!(find_writable_mount(image.path()).unwrap().is_none());
+        ensure_not_mounted_writable(image.path(), false).unwrap();
+
+        let (_file, mmap, size) = map_read_only(image.path()).unwrap();
+        assert_eq!(size, 8192);
+        assert_eq!(mmap.len(), 8192);
+    }
+}
@@ -0,0 +1,842 @@
+/// Expert Witness Format (EnCase `.E01`) images
+///
+/// An EWF image stores the acquired media as fixed-size chunks, each either
+/// zlib-compressed or stored raw with an Adler-32 checksum, spread over
+/// segment files `image.E01`, `image.E02`, ... Per-segment `table` sections
+/// give the location of every chunk. Opening an image decompresses it into
+/// a temporary file, chunks of zeros left as holes, which is mapped so it
+/// reads as an ordinary [`BlockDevice`].
+///
+/// Only the original EWF format is supported; EWF2 (`.Ex01`) images are
+/// recognised and rejected.
+use anyhow::{bail, ensure, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use memmap2::{Mmap, MmapMut};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::device::{available_space, open_read_only};
+use super::temp::TempImage;
+use super::types::BlockDevice;
+use crate::recovery::RecoveryError;
+
+/// Signature at the start of every EWF segment file
+const EWF_SIGNATURE: &[u8; 8] = b"EVF\x09\x0d\x0a\xff\x00";
+
+/// Signature of EWF2 (`.Ex01`) segment files
+const EWF2_SIGNATURE: &[u8; 8] = b"EVF2\x0d\x0a\x81\x00";
+
+/// Signature, fields start marker, segment number and fields end marker
+const FILE_HEADER_SIZE: u64 = 13;
+
+/// Type, next offset, size, padding and checksum of a section
+const SECTION_DESCRIPTOR_SIZE: usize = 76;
+
+/// Entry count, padding, base offset, padding and checksum of a table
+const TABLE_HEADER_SIZE: usize = 24;
+
+/// Set in a table entry when the chunk is zlib-compressed
+const COMPRESSED_FLAG: u32 = 0x8000_0000;
+
+/// Chunk counts above this are taken as a corrupt volume section
+const MAX_CHUNK_COUNT: u32 = 1 << 28;
+
+/// Sector sizes accepted in the volume section
+const SECTOR_SIZES: std::ops::RangeInclusive<u32> = 512..=4096;
+
+/// Chunks larger than this are taken as a corrupt volume section; EnCase
+/// writes 32 KiB chunks
+const MAX_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Where one chunk of the image is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct EwfChunk {
+    /// Index into the segment files
+    segment: usize,
+    offset: u64,
+    /// Bytes stored in the segment, including the checksum of raw chunks
+    stored_size: u64,
+    compressed: bool,
+}
+
+/// Geometry from the `volume` (or `disk`) section
+#[derive(Debug, Clone, Copy)]
+struct Volume {
+    chunk_count: u32,
+    sectors_per_chunk: u32,
+    bytes_per_sector: u32,
+    sector_count: u64,
+}
+
+/// An opened EWF image, across all of its segment files
+pub struct EwfImage {
+    segments: Vec<PathBuf>,
+    files: Vec<Mutex<File>>,
+    chunks: Vec<EwfChunk>,
+    volume: Volume,
+}
+
+impl EwfImage {
+    /// Open the image whose first segment is `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut files = Vec::new();
+        let mut chunks = Vec::new();
+        let mut volume = None;
+
+        let mut segment_path = path.to_path_buf();
+        loop {
+            let number = segments.len() + 1;
+            let mut file = open_read_only(&segment_path)?;
+            let done = read_segment(&mut file, number, &mut volume, &mut chunks)
+                .with_context(|| format!("Invalid EWF segment {}", segment_path.display()))?;
+            segments.push(segment_path.clone());
+            files.push(Mutex::new(file));
+            if done {
+                break;
+            }
+
+            segment_path = segment_path.with_extension(segment_extension(path, number + 1)?);
+            ensure!(
+                segment_path.is_file(),
+                "EWF segment {} is missing",
+                segment_path.display()
+            );
+        }
+
+        let Some(volume) = volume else {
+            bail!("{} has no volume section", path.display());
+        };
+        ensure!(
+            volume.bytes_per_sector.is_power_of_two()
+                && SECTOR_SIZES.contains(&volume.bytes_per_sector),
+            "{} has {}-byte sectors",
+            path.display(),
+            volume.bytes_per_sector
+        );
+        let chunk_size = volume.sectors_per_chunk as u64 * volume.bytes_per_sector as u64;
+        ensure!(
+            chunk_size > 0 && chunk_size <= MAX_CHUNK_SIZE,
+            "{} has an invalid chunk size of {} sectors",
+            path.display(),
+            volume.sectors_per_chunk
+        );
+        ensure!(
+            volume
+                .sector_count
+                .checked_mul(volume.bytes_per_sector as u64)
+                .is_some(),
+            "{} lists {} sectors of {} bytes",
+            path.display(),
+            volume.sector_count,
+            volume.bytes_per_sector
+        );
+        let image = Self {
+            segments,
+            files,
+            chunks,
+            volume,
+        };
+        let needed = image.size().div_ceil(image.chunk_size() as u64);
+        ensure!(
+            image.chunks.len() as u64 >= needed,
+            "{} has {} chunks in its tables, {} needed for {} bytes",
+            path.display(),
+            image.chunks.len(),
+            needed,
+            image.size()
+        );
+
+        tracing::debug!(
+            "Opened EWF image {}: {} segments, {} chunks of {} bytes",
+            path.display(),
+            image.segments.len(),
+            image.chunks.len(),
+            image.chunk_size()
+        );
+        Ok(image)
+    }
+
+    /// Size of the acquired media in bytes
+    pub fn size(&self) -> u64 {
+        self.volume.sector_count * self.volume.bytes_per_sector as u64
+    }
+
+    /// Bytes of media per chunk
+    pub fn chunk_size(&self) -> usize {
+        self.volume.sectors_per_chunk as usize * self.volume.bytes_per_sector as usize
+    }
+
+    /// Number of chunks listed in the segment tables
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// The segment files, in order
+    pub fn segments(&self) -> &[PathBuf] {
+        &self.segments
+    }
+
+    /// Media bytes of chunk `index`, decompressed
+    pub fn read_chunk(&self, index: usize) -> Result<Vec<u8>> {
+        let Some(chunk) = self.chunks.get(index) else {
+            bail!("Chunk {} is beyond the end of the image", index);
+        };
+
+        let mut stored = vec![0u8; chunk.stored_size as usize];
+        {
+            let mut file = self.files[chunk.segment].lock().unwrap();
+            file.seek(SeekFrom::Start(chunk.offset))?;
+            file.read_exact(&mut stored).with_context(|| {
+                format!(
+                    "Chunk {} is truncated in {}",
+                    index,
+                    self.segments[chunk.segment].display()
+                )
+            })?;
+        }
+
+        let chunk_size = self.chunk_size();
+        if chunk.compressed {
+            let mut data = Vec::with_capacity(chunk_size);
+            flate2::read::ZlibDecoder::new(&stored[..])
+                .take(chunk_size as u64)
+                .read_to_end(&mut data)
+                .with_context(|| format!("Failed to decompress chunk {}", index))?;
+            return Ok(data);
+        }
+
+        // Raw chunks end in the Adler-32 checksum of their data
+        ensure!(stored.len() > 4, "Chunk {} is too short", index);
+        let (data, checksum) = stored.split_at(stored.len() - 4);
+        if adler32(data) != LittleEndian::read_u32(checksum) {
+            tracing::warn!("Checksum mismatch in EWF chunk {}", index);
+        }
+        stored.truncate(stored.len() - 4);
+        Ok(stored)
+    }
+
+    /// Read the media from start to end, one chunk at a time
+    ///
+    /// Unlike [`Self::to_mmap`], this needs no temporary space, for a single
+    /// pass such as hashing the media.
+    pub fn media_reader(&self) -> EwfReader<'_> {
+        EwfReader {
+            image: self,
+            next_chunk: 0,
+            remaining: self.size(),
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Decompress the whole image into a read-only mapping, see [`Self::to_mmap_in`]
+    ///
+    /// The temporary file goes to the system temporary directory (`TMPDIR`).
+    pub fn to_mmap(&self) -> Result<Mmap> {
+        self.to_mmap_in(&std::env::temp_dir())
+    }
+
+    /// Decompress the whole image into a read-only mapping of a file in `dir`
+    ///
+    /// The file is removed once mapped, or when decompressing fails, and its
+    /// space freed with the mapping. Being file-backed, decompressed pages
+    /// can be written out and evicted, so images larger than memory can be
+    /// opened; `dir` must have room for the media instead.
+    pub fn to_mmap_in(&self, dir: &Path) -> Result<Mmap> {
+        let size = self.size();
+        ensure!(size > 0, "{} is empty", self.segments[0].display());
+        // Running out of space while writing through the mapping is a SIGBUS
+        if let Some(available) = available_space(dir)? {
+            ensure!(
+                available >= size,
+                "{} needs {} bytes of temporary space, {} has {} available",
+                self.segments[0].display(),
+                size,
+                dir.display(),
+                available
+            );
+        }
+
+        let temp = TempImage::create(Some(dir), "ewf")?;
+        temp.file().set_len(size)?;
+        let mut map = unsafe { MmapMut::map_mut(temp.file())? };
+        map.par_chunks_mut(self.chunk_size())
+            .enumerate()
+            .try_for_each(|(index, target)| -> Result<()> {
+                let data = self.read_chunk(index)?;
+                ensure!(
+                    data.len() >= target.len(),
+                    "Chunk {} holds {} bytes, {} expected",
+                    index,
+                    data.len(),
+                    target.len()
+                );
+                // The file is sparse and reads as zeros; leave zero chunks as holes
+                let data = &data[..target.len()];
+                if data.iter().any(|&b| b != 0) {
+                    target.copy_from_slice(data);
+                }
+                Ok(())
+            })?;
+        Ok(map.make_read_only()?)
+    }
+
+    /// Decompress the image into a device
+    pub fn into_block_device(self) -> Result<BlockDevice> {
+        Ok(BlockDevice::from_mmap(self.to_mmap()?))
+    }
+}
+
+/// Sequential reader of the media of an [`EwfImage`]
+pub struct EwfReader<'a> {
+    image: &'a EwfImage,
+    next_chunk: usize,
+    /// Media bytes not yet loaded from a chunk
+    remaining: u64,
+    chunk: Vec<u8>,
+    /// Bytes of `chunk` already read
+    position: usize,
+}
+
+impl Read for EwfReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.chunk.len() {
+            if self.remaining == 0 {
+                return Ok(0);
+            }
+            let mut chunk = self
+                .image
+                .read_chunk(self.next_chunk)
+                .map_err(std::io::Error::other)?;
+            let length = (self.image.chunk_size() as u64).min(self.remaining) as usize;
+            if chunk.len() < length {
+                return Err(std::io::Error::other(format!(
+                    "Chunk {} holds {} bytes, {} expected",
+                    self.next_chunk,
+                    chunk.len(),
+                    length
+                )));
+            }
+            chunk.truncate(length);
+            self.chunk = chunk;
+            self.position = 0;
+            self.next_chunk += 1;
+            self.remaining -= length as u64;
+        }
+
+        let length = buf.len().min(self.chunk.len() - self.position);
+        buf[..length].copy_from_slice(&self.chunk[self.position..self.position + length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+/// Read the sections of one segment file; true if it is the last segment
+fn read_segment(
+    file: &mut File,
+    number: usize,
+    volume: &mut Option<Volume>,
+    chunks: &mut Vec<EwfChunk>,
+) -> Result<bool> {
+    let mut header = [0u8; FILE_HEADER_SIZE as usize];
+    file.read_exact(&mut header)?;
+    if &header[..8] == EWF2_SIGNATURE {
+        return Err(RecoveryError::UnsupportedFeature("EWF2 (Ex01) images".to_string()).into());
+    }
+    ensure!(&header[..8] == EWF_SIGNATURE, "Not an EWF segment file");
+    let segment_number = LittleEndian::read_u16(&header[9..11]) as usize;
+    ensure!(
+        segment_number == number,
+        "Segment number {} found where {} was expected",
+        segment_number,
+        number
+    );
+
+    let file_size = file.metadata()?.len();
+    let segment = number - 1;
+    let mut offset = FILE_HEADER_SIZE;
+    // End of the chunk data, which bounds the last chunk of the next table
+    let mut sectors_end = None;
+    loop {
+        let data_offset = offset
+            .checked_add(SECTION_DESCRIPTOR_SIZE as u64)
+            .filter(|&end| end <= file_size);
+        let Some(data_offset) = data_offset else {
+            bail!("Section at offset {} is past the end of the file", offset);
+        };
+        let mut descriptor = [0u8; SECTION_DESCRIPTOR_SIZE];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut descriptor)?;
+        ensure!(
+            adler32(&descriptor[..72]) == LittleEndian::read_u32(&descriptor[72..]),
+            "Section descriptor at offset {} is corrupt",
+            offset
+        );
+
+        let section_type = section_type(&descriptor);
+        let next = LittleEndian::read_u64(&descriptor[16..24]);
+        let size = LittleEndian::read_u64(&descriptor[24..32]);
+
+        match section_type {
+            "volume" | "disk" => *volume = Some(read_volume(file, data_offset)?),
+            "sectors" => {
+                let end = offset.checked_add(size).filter(|&end| end <= file_size);
+                ensure!(
+                    end.is_some(),
+                    "Sectors section at offset {} runs past the end of the file",
+                    offset
+                );
+                sectors_end = end;
+            }
+            "table" => {
+                let end = sectors_end.unwrap_or(offset);
+                read_table(file, data_offset, file_size, segment, end, chunks)?;
+            }
+            "next" => return Ok(false),
+            "done" => return Ok(true),
+            // header, table2 (a copy of table), hash, digest, error2, ...
+            _ => {}
+        }
+
+        ensure!(
+            next > offset,
+            "Section at offset {} does not point forward",
+            offset
+        );
+        offset = next;
+    }
+}
+
+fn section_type(descriptor: &[u8]) -> &str {
+    let name = &descriptor[..16];
+    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    std::str::from_utf8(&name[..len]).unwrap_or("")
+}
+
+fn read_volume(file: &mut File, offset: u64) -> Result<Volume> {
+    let mut data = [0u8; 24];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut data)?;
+    let volume = Volume {
+        chunk_count: LittleEndian::read_u32(&data[4..8]),
+        sectors_per_chunk: LittleEndian::read_u32(&data[8..12]),
+        bytes_per_sector: LittleEndian::read_u32(&data[12..16]),
+        sector_count: LittleEndian::read_u64(&data[16..24]),
+    };
+    ensure!(
+        volume.chunk_count <= MAX_CHUNK_COUNT,
+        "Volume section lists {} chunks",
+        volume.chunk_count
+    );
+    Ok(volume)
+}
+
+/// Append the chunks listed in a table section
+///
+/// Entries hold chunk offsets relative to the table's base offset; a chunk
+/// ends where the next one starts, and the last where the chunk data ends.
+fn read_table(
+    file: &mut File,
+    offset: u64,
+    file_size: u64,
+    segment: usize,
+    data_end: u64,
+    chunks: &mut Vec<EwfChunk>,
+) -> Result<()> {
+    let mut header = [0u8; TABLE_HEADER_SIZE];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut header)?;
+    let entry_count = LittleEndian::read_u32(&header[0..4]);
+    let base_offset = LittleEndian::read_u64(&header[8..16]);
+    ensure!(
+        entry_count <= MAX_CHUNK_COUNT,
+        "Table lists {} chunks",
+        entry_count
+    );
+    let entries_size = entry_count as u64 * 4;
+    ensure!(
+        offset + (TABLE_HEADER_SIZE as u64) + entries_size <= file_size,
+        "Table at offset {} runs past the end of the file",
+        offset
+    );
+
+    let mut entries = vec![0u8; entries_size as usize];
+    file.read_exact(&mut entries)?;
+    let locations = entries
+        .chunks_exact(4)
+        .map(LittleEndian::read_u32)
+        .map(|entry| {
+            let start = base_offset.checked_add((entry & !COMPRESSED_FLAG) as u64);
+            let Some(start) = start else {
+                bail!("Table base offset {} is out of range", base_offset);
+            };
+            Ok((start, entry & COMPRESSED_FLAG != 0))
+        })
+        .collect::<Result<Vec<(u64, bool)>>>()?;
+
+    for (i, &(start, compressed)) in locations.iter().enumerate() {
+        let end = locations.get(i + 1).map_or(data_end, |&(next, _)| next);
+        ensure!(
+            end > start && end <= data_end,
+            "Chunk at offset {} has no data in the segment",
+            start
+        );
+        chunks.push(EwfChunk {
+            segment,
+            offset: start,
+            stored_size: end - start,
+            compressed,
+        });
+    }
+    Ok(())
+}
+
+/// Extension of segment `number` of the image whose first segment is `first`
+///
+/// Segments run `E01`-`E99`, then `EAA`-`EZZ`, `FAA`, ... in the case of
+/// the first segment's extension.
+fn segment_extension(first: &Path, number: usize) -> Result<String> {
+    let lowercase = first
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.starts_with('e'));
+
+    let extension = if number < 100 {
+        format!("E{:02}", number)
+    } else {
+        let index = number - 100;
+        let first_letter = b'E' as usize + index / (26 * 26);
+        ensure!(first_letter <= b'Z' as usize, "Too many EWF segments");
+        [
+            first_letter,
+            b'A' as usize + index / 26 % 26,
+            b'A' as usize + index % 26,
+        ]
+        .iter()
+        .map(|&c| c as u8 as char)
+        .collect()
+    };
+    Ok(if lowercase {
+        extension.to_lowercase()
+    } else {
+        extension
+    })
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // Sums stay below u32::MAX for 5552 bytes between reductions
+    for block in data.chunks(5552) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const SECTORS_PER_CHUNK: u32 = 8;
+    const CHUNK_SIZE: usize = SECTORS_PER_CHUNK as usize * 512;
+
+    fn section(name: &str, offset: u64, next: u64, size: u64) -> Vec<u8> {
+        let mut descriptor = vec![0u8; SECTION_DESCRIPTOR_SIZE];
+        descriptor[..name.len()].copy_from_slice(name.as_bytes());
+        LittleEndian::write_u64(&mut descriptor[16..24], next.max(offset));
+        LittleEndian::write_u64(&mut descriptor[24..32], size);
+        let checksum = adler32(&descriptor[..72]);
+        LittleEndian::write_u32(&mut descriptor[72..], checksum);
+        descriptor
+    }
+
+    /// Write `image` as an EWF image with `chunks_per_segment` chunks per file
+    ///
+    /// Chunks that compress are stored compressed, the rest raw.
+    pub(crate) fn write_ewf(dir: &Path, image: &[u8], chunks_per_segment: usize) -> PathBuf {
+        assert_eq!(image.len() % 512, 0);
+        let chunks: Vec<&[u8]> = image.chunks(CHUNK_SIZE).collect();
+        let segment_count = chunks.len().div_ceil(chunks_per_segment);
+        let first = dir.join("image.E01");
+
+        for (index, segment_chunks) in chunks.chunks(chunks_per_segment).enumerate() {
+            let mut out = EWF_SIGNATURE.to_vec();
+            out.push(1);
+            out.extend_from_slice(&(index as u16 + 1).to_le_bytes());
+            out.extend_from_slice(&[0, 0]);
+
+            if index == 0 {
+                let mut volume = vec![0u8; 1052];
+                LittleEndian::write_u32(&mut volume[4..8], chunks.len() as u32);
+                LittleEndian::write_u32(&mut volume[8..12], SECTORS_PER_CHUNK);
+                LittleEndian::write_u32(&mut volume[12..16], 512);
+                LittleEndian::write_u64(&mut volume[16..24], image.len() as u64 / 512);
+                let offset = out.len() as u64;
+                let size = (SECTION_DESCRIPTOR_SIZE + volume.len()) as u64;
+                out.extend(section("volume", offset, offset + size, size));
+                out.extend(volume);
+            }
+
+            // Chunk data, with table entries relative to the sectors section
+            let sectors_offset = out.len() as u64;
+            let mut data = Vec::new();
+            let mut entries = Vec::new();
+            for chunk in segment_chunks {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(chunk).unwrap();
+                let compressed = encoder.finish().unwrap();
+                let relative = (SECTION_DESCRIPTOR_SIZE + data.len()) as u32;
+                if compressed.len() < chunk.len() {
+                    entries.push(relative | COMPRESSED_FLAG);
+                    data.extend(compressed);
+                } else {
+                    entries.push(relative);
+                    data.extend_from_slice(chunk);
+                    data.extend_from_slice(&adler32(chunk).to_le_bytes());
+                }
+            }
+            let size = (SECTION_DESCRIPTOR_SIZE + data.len()) as u64;
+            out.extend(section(
+                "sectors",
+                sectors_offset,
+                sectors_offset + size,
+                size,
+            ));
+            out.extend(data);
+
+            let mut table = vec![0u8; TABLE_HEADER_SIZE];
+            LittleEndian::write_u32(&mut table[0..4], entries.len() as u32);
+            LittleEndian::write_u64(&mut table[8..16], sectors_offset);
+            for entry in entries {
+                table.extend_from_slice(&entry.to_le_bytes());
+            }
+            table.extend_from_slice(&[0; 4]);
+            let offset = out.len() as u64;
+            let size = (SECTION_DESCRIPTOR_SIZE + table.len()) as u64;
+            out.extend(section("table", offset, offset + size, size));
+            out.extend(table);
+
+            let last = if index + 1 == segment_count {
+                "done"
+            } else {
+                "next"
+            };
+            let offset = out.len() as u64;
+            out.extend(section(
+                last,
+                offset,
+                offset,
+                SECTION_DESCRIPTOR_SIZE as u64,
+            ));
+
+            let path = first.with_extension(segment_extension(&first, index + 1).unwrap());
+            std::fs::write(path, out).unwrap();
+        }
+        first
+    }
+
+    /// Zeros, text and incompressible bytes, in chunks and sectors that do not line up
+    fn sample_image() -> Vec<u8> {
+        let mut image = vec![0u8; CHUNK_SIZE * 2];
+        let text = b"GhostFS EWF test pattern ";
+        for (i, byte) in image[CHUNK_SIZE..].iter_mut().enumerate() {
+            *byte = text[i % text.len()];
+        }
+        let mut state: u32 = 0x1234_5678;
+        for _ in 0..CHUNK_SIZE * 2 + 3 * 512 {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            image.push((state >> 24) as u8);
+        }
+        image
+    }
+
+    #[test]
+    fn test_reads_known_bytes_from_e01() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = sample_image();
+        let first = write_ewf(dir.path(), &image, 3);
+
+        let ewf = EwfImage::open(&first).unwrap();
+        assert_eq!(ewf.size(), image.len() as u64);
+        assert_eq!(ewf.segments().len(), 2);
+        assert_eq!(ewf.chunk_count(), 5);
+        // Both storage forms are exercised
+        assert!(ewf.chunks[1].compressed);
+        assert!(!ewf.chunks[2].compressed);
+        assert_eq!(ewf.chunks[3].segment, 1);
+
+        assert_eq!(
+            ewf.read_chunk(2).unwrap(),
+            &image[2 * CHUNK_SIZE..3 * CHUNK_SIZE]
+        );
+        // The last chunk holds three sectors
+        assert_eq!(ewf.read_chunk(4).unwrap(), &image[4 * CHUNK_SIZE..]);
+
+        let device = BlockDevice::open(&first).unwrap();
+        assert_eq!(device.size(), image.len() as u64);
+        // Across the compressed/raw chunk boundary and the segment boundary
+        let boundary = 3 * CHUNK_SIZE;
+        assert_eq!(
+            device.read_at(CHUNK_SIZE as u64 * 2 - 8, 16).unwrap(),
+            &image[CHUNK_SIZE * 2 - 8..CHUNK_SIZE * 2 + 8]
+        );
+        assert_eq!(
+            device.read_at(boundary as u64 - 100, 200).unwrap(),
+            &image[boundary - 100..boundary + 100]
+        );
+        assert_eq!(device.read_at(0, image.len()).unwrap(), &image[..]);
+    }
+
+    #[test]
+    fn test_decompresses_into_the_given_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill = tempfile::tempdir().unwrap();
+        let image = sample_image();
+        let first = write_ewf(dir.path(), &image, 3);
+
+        let map = EwfImage::open(&first)
+            .unwrap()
+            .to_mmap_in(spill.path())
+            .unwrap();
+        assert_eq!(&map[..], &image[..]);
+        // The spill file is removed once mapped
+        assert_eq!(std::fs::read_dir(spill.path()).unwrap().count(), 0);
+
+        let missing = spill.path().join("missing");
+        assert!(EwfImage::open(&first)
+            .unwrap()
+            .to_mmap_in(&missing)
+            .is_err());
+
+        // A chunk that fails to decompress leaves nothing behind either
+        let ewf = EwfImage::open(&first).unwrap();
+        let chunk = &ewf.chunks[1];
+        assert!(chunk.compressed);
+        let mut data = std::fs::read(&first).unwrap();
+        let start = chunk.offset as usize;
+        data[start..start + chunk.stored_size as usize].fill(0xFF);
+        std::fs::write(&first, data).unwrap();
+        assert!(EwfImage::open(&first)
+            .unwrap()
+            .to_mmap_in(spill.path())
+            .is_err());
+        assert_eq!(std::fs::read_dir(spill.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_missing_segment_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = write_ewf(dir.path(), &sample_image(), 2);
+        std::fs::remove_file(dir.path().join("image.E02")).unwrap();
+
+        let error = EwfImage::open(&first).err().unwrap();
+        assert!(error.to_string().contains("image.E02 is missing"));
+    }
+
+    /// Offset of the descriptor of the first `name` section in `data`
+    fn find_section(data: &[u8], name: &str) -> usize {
+        let mut pattern = name.as_bytes().to_vec();
+        pattern.push(0);
+        data.windows(pattern.len())
+            .position(|window| window == pattern)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_out_of_range_offsets_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = write_ewf(dir.path(), &sample_image(), 5);
+        let original = std::fs::read(&first).unwrap();
+
+        // A sectors section reaching past the end of the file, or of u64
+        for size in [original.len() as u64, u64::MAX - 8] {
+            let mut data = original.clone();
+            let at = find_section(&data, "sectors");
+            LittleEndian::write_u64(&mut data[at + 24..at + 32], size);
+            let checksum = adler32(&data[at..at + 72]);
+            LittleEndian::write_u32(&mut data[at + 72..at + 76], checksum);
+            std::fs::write(&first, data).unwrap();
+            let error = EwfImage::open(&first).err().unwrap();
+            assert!(format!("{:#}", error).contains("runs past the end of the file"));
+        }
+
+        // A table whose base offset overflows once an entry is added
+        let mut data = original.clone();
+        let at = find_section(&data, "table") + SECTION_DESCRIPTOR_SIZE;
+        LittleEndian::write_u64(&mut data[at + 8..at + 16], u64::MAX - 8);
+        std::fs::write(&first, data).unwrap();
+        let error = EwfImage::open(&first).err().unwrap();
+        assert!(format!("{:#}", error).contains("is out of range"));
+    }
+
+    #[test]
+    fn test_rejects_implausible_chunk_geometry() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = write_ewf(dir.path(), &sample_image(), 5);
+        let original = std::fs::read(&first).unwrap();
+        let at = find_section(&original, "volume") + SECTION_DESCRIPTOR_SIZE;
+
+        // (sectors per chunk, bytes per sector, expected error)
+        for (sectors_per_chunk, bytes_per_sector, message) in [
+            (1 << 31, 512, "invalid chunk size"),
+            (0, 512, "invalid chunk size"),
+            (64, 1 << 31, "-byte sectors"),
+            (64, 520, "-byte sectors"),
+            (64, 256, "-byte sectors"),
+        ] {
+            let mut data = original.clone();
+            LittleEndian::write_u32(&mut data[at + 8..at + 12], sectors_per_chunk);
+            LittleEndian::write_u32(&mut data[at + 12..at + 16], bytes_per_sector);
+            std::fs::write(&first, data).unwrap();
+            let error = EwfImage::open(&first).err().unwrap();
+            assert!(format!("{:#}", error).contains(message), "{:#}", error);
+        }
+    }
+
+    #[test]
+    fn test_rejects_ewf2_and_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let ex01 = dir.path().join("image.Ex01");
+        let mut data = EWF2_SIGNATURE.to_vec();
+        data.resize(512, 0);
+        std::fs::write(&ex01, data).unwrap();
+        let error = EwfImage::open(&ex01).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<RecoveryError>(),
+            Some(RecoveryError::UnsupportedFeature(_))
+        ));
+        assert!(format!("{:#}", error).contains("Unsupported feature: EWF2 (Ex01) images"));
+
+        let raw = dir.path().join("raw.E01");
+        std::fs::write(&raw, vec![0u8; 512]).unwrap();
+        assert!(EwfImage::open(&raw).is_err());
+    }
+
+    #[test]
+    fn test_segment_extensions() {
+        let first = Path::new("disk.E01");
+        assert_eq!(segment_extension(first, 2).unwrap(), "E02");
+        assert_eq!(segment_extension(first, 99).unwrap(), "E99");
+        assert_eq!(segment_extension(first, 100).unwrap(), "EAA");
+        assert_eq!(segment_extension(first, 127).unwrap(), "EBB");
+        assert_eq!(segment_extension(first, 776).unwrap(), "FAA");
+        assert_eq!(segment_extension(Path::new("disk.e01"), 3).unwrap(), "e03");
+    }
+
+    #[test]
+    fn test_adler32() {
+        // Reference value from the zlib documentation
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+}
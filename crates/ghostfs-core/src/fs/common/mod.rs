@@ -1,3 +1,13 @@
+pub mod device;
+#[cfg(feature = "ewf")]
+pub mod ewf;
+pub mod split;
+pub mod temp;
 pub mod types;
 
+pub use device::*;
+#[cfg(feature = "ewf")]
+pub use ewf::EwfImage;
+pub use split::*;
+pub use temp::TempImage;
 pub use types::*;
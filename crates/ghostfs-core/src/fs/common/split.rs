@@ -0,0 +1,277 @@
+/// Images split into numbered segments (`image.001`, `image.002`, ...)
+///
+/// Acquisition tools often split raw images to fit file size limits. The
+/// segments are mapped back to back into one contiguous mapping, so a split
+/// image opens as an ordinary [`BlockDevice`] and every scanner reads it
+/// without knowing it was split.
+use anyhow::{bail, Context, Result};
+use memmap2::{Mmap, MmapMut};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::device::{device_size, open_read_only, FileReads};
+use super::types::{BlockDevice, RangeSet};
+
+/// Digits a segment extension needs, so `photo.1` is not mistaken for a segment
+const MIN_SEGMENT_DIGITS: usize = 3;
+
+/// One file of a split image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageSegment {
+    pub path: PathBuf,
+    /// Offset of the segment's first byte in the whole image
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// A split image read as one device
+pub struct SplitBlockDevice {
+    segments: Vec<ImageSegment>,
+    device: BlockDevice,
+}
+
+impl SplitBlockDevice {
+    /// Open the split set that `path` (any of its segments) belongs to
+    pub fn open(path: &Path) -> Result<Self> {
+        let Some(paths) = detect_segments(path)? else {
+            bail!("{} is not part of a split image", path.display());
+        };
+        let (segments, map) = map_segments(&paths)?;
+        let mut device = BlockDevice::from_mmap(map);
+        if let Some(reads) = FileReads::for_block_devices(&paths)? {
+            device = device.with_fault_source(reads);
+        }
+        Ok(Self { segments, device })
+    }
+
+    /// The segments, in address order
+    pub fn segments(&self) -> &[ImageSegment] {
+        &self.segments
+    }
+
+    /// Index of the segment holding image byte `offset`, and the offset within it
+    pub fn segment_at(&self, offset: u64) -> Option<(usize, u64)> {
+        let index = self
+            .segments
+            .partition_point(|segment| segment.offset + segment.length <= offset);
+        let segment = self.segments.get(index)?;
+        Some((index, offset - segment.offset))
+    }
+
+    /// Size of the whole image in bytes
+    pub fn size(&self) -> u64 {
+        self.device.size()
+    }
+
+    /// Read a slice of bytes, which may span segments
+    pub fn read_at(&self, offset: u64, length: usize) -> Result<&[u8]> {
+        self.device.read_at(offset, length)
+    }
+
+    /// Read a single sector (512 bytes)
+    pub fn read_sector(&self, sector: u64) -> Result<&[u8]> {
+        self.device.read_sector(sector)
+    }
+
+    /// Read data at a specific block offset
+    pub fn read_block(&self, block_number: u64, block_size: u32) -> Result<&[u8]> {
+        self.device.read_block(block_number, block_size)
+    }
+
+    /// Fail reads that touch any of the given byte ranges
+    pub fn with_unreadable_regions(mut self, regions: RangeSet) -> Self {
+        self.device = self.device.with_unreadable_regions(regions);
+        self
+    }
+
+    /// The image as a plain device, for code that takes a [`BlockDevice`]
+    pub fn into_block_device(self) -> BlockDevice {
+        self.device
+    }
+}
+
+/// Every segment of the split set `path` belongs to, in order
+///
+/// A set is named `<base>.<n>` with a fixed-width number of at least three
+/// digits, numbered consecutively from 000 or 001. Returns `None` when `path`
+/// does not look like a segment or has no siblings.
+pub fn detect_segments(path: &Path) -> Result<Option<Vec<PathBuf>>> {
+    let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+        return Ok(None);
+    };
+    if extension.len() < MIN_SEGMENT_DIGITS || !extension.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(None);
+    }
+    let width = extension.len();
+    let segment = |number: usize| path.with_extension(format!("{:0width$}", number));
+
+    let first = if segment(0).is_file() { 0 } else { 1 };
+    let paths: Vec<PathBuf> = (first..)
+        .map(segment)
+        .take_while(|candidate| candidate.is_file())
+        .collect();
+
+    if paths.len() < 2 || !paths.iter().any(|p| p == path) {
+        return Ok(None);
+    }
+    Ok(Some(paths))
+}
+
+/// Map the segments back to back into a single read-only mapping
+pub(super) fn map_segments(paths: &[PathBuf]) -> Result<(Vec<ImageSegment>, Mmap)> {
+    let mut segments = Vec::with_capacity(paths.len());
+    let mut files = Vec::with_capacity(paths.len());
+    let mut offset = 0;
+    for path in paths {
+        let file = open_read_only(path)?;
+        let length = device_size(&file)?;
+        segments.push(ImageSegment {
+            path: path.clone(),
+            offset,
+            length,
+        });
+        files.push(file);
+        offset += length;
+    }
+    if offset == 0 {
+        bail!("Split image {} is empty", paths[0].display());
+    }
+
+    let mut map = MmapMut::map_anon(offset as usize)?;
+    if !map_in_place(&mut map, &segments, &files)? {
+        tracing::info!(
+            "Segments of {} are not page aligned, reading them into memory",
+            paths[0].display()
+        );
+        for (segment, mut file) in segments.iter().zip(&files) {
+            let start = segment.offset as usize;
+            file.read_exact(&mut map[start..start + segment.length as usize])
+                .with_context(|| format!("Failed to read {}", segment.path.display()))?;
+        }
+    }
+
+    tracing::debug!("Mapped {} segments, {} bytes", segments.len(), offset);
+    Ok((segments, map.make_read_only()?))
+}
+
+/// Map each segment's file over its part of `map`, without copying
+///
+/// Only possible when every segment but the last is a whole number of
+/// pages; returns false without touching `map` otherwise.
+#[cfg(target_os = "linux")]
+fn map_in_place(map: &mut MmapMut, segments: &[ImageSegment], files: &[File]) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    if segments[..segments.len() - 1]
+        .iter()
+        .any(|segment| segment.length % page_size != 0)
+    {
+        return Ok(false);
+    }
+
+    for (segment, file) in segments.iter().zip(files) {
+        if segment.length == 0 {
+            continue;
+        }
+        // MAP_FIXED replaces the anonymous pages; dropping `map` unmaps it all
+        let mapped = unsafe {
+            libc::mmap(
+                map.as_mut_ptr().add(segment.offset as usize).cast(),
+                segment.length as usize,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE | libc::MAP_FIXED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if mapped == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("Failed to map {}", segment.path.display()));
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn map_in_place(_map: &mut MmapMut, _segments: &[ImageSegment], _files: &[File]) -> Result<bool> {
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Image bytes, distinct enough that a misplaced segment shows
+    fn image_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Write `image` as segments of `segment_len` bytes named `image.<first>`, ...
+    fn split_image(image: &[u8], segment_len: usize, first: usize) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (i, chunk) in image.chunks(segment_len).enumerate() {
+            let path = dir.path().join(format!("image.{:03}", first + i));
+            std::fs::write(path, chunk).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_detects_numbered_segments() {
+        let dir = split_image(&image_bytes(3000), 1000, 1);
+        let second = dir.path().join("image.002");
+
+        let paths = detect_segments(&second).unwrap().unwrap();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0], dir.path().join("image.001"));
+        assert_eq!(paths[2], dir.path().join("image.003"));
+
+        let zero_based = split_image(&image_bytes(2000), 1000, 0);
+        let paths = detect_segments(&zero_based.path().join("image.001")).unwrap();
+        assert_eq!(paths.unwrap()[0], zero_based.path().join("image.000"));
+
+        // A lone numbered file or an ordinary image is not a split set
+        let lone = tempfile::tempdir().unwrap();
+        std::fs::write(lone.path().join("image.001"), b"data").unwrap();
+        assert!(detect_segments(&lone.path().join("image.001"))
+            .unwrap()
+            .is_none());
+        assert!(detect_segments(Path::new("disk.img")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reads_across_segment_boundary() {
+        // Unaligned segments are read into memory, page-sized ones mapped in place
+        for segment_len in [1000, 8192] {
+            let image = image_bytes(segment_len * 3 - 100);
+            let dir = split_image(&image, segment_len, 1);
+            let device = SplitBlockDevice::open(&dir.path().join("image.001")).unwrap();
+
+            assert_eq!(device.size(), image.len() as u64);
+            assert_eq!(device.segments().len(), 3);
+            assert_eq!(device.segments()[2].offset, 2 * segment_len as u64);
+
+            let boundary = segment_len as u64;
+            let read = device.read_at(boundary - 10, 20).unwrap();
+            assert_eq!(read, &image[segment_len - 10..segment_len + 10]);
+            assert_eq!(device.segment_at(boundary - 1), Some((0, boundary - 1)));
+            assert_eq!(device.segment_at(boundary), Some((1, 0)));
+            assert_eq!(device.segment_at(image.len() as u64), None);
+
+            // The whole image reads back contiguously
+            assert_eq!(device.read_at(0, image.len()).unwrap(), &image[..]);
+        }
+    }
+
+    #[test]
+    fn test_block_device_opens_split_sets() {
+        let image = image_bytes(4096 * 4);
+        let dir = split_image(&image, 4096, 1);
+
+        let device = BlockDevice::open(dir.path().join("image.003")).unwrap();
+        assert_eq!(device.size(), image.len() as u64);
+        assert_eq!(device.read_at(4090, 12).unwrap(), &image[4090..4102]);
+    }
+}
@@ -0,0 +1,81 @@
+/// Temporary image files
+///
+/// Images that cannot be mapped as they are, such as compressed EWF images,
+/// are expanded into a temporary file first. [`TempImage`] owns such a file
+/// and deletes it when dropped, so it does not outlive the scan on an early
+/// return or a panic.
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// A temporary file deleted when dropped
+pub struct TempImage {
+    file: File,
+    path: PathBuf,
+}
+
+impl TempImage {
+    /// Create an empty temporary file in `dir`, or in the system temporary
+    /// directory (`TMPDIR`) if `None`
+    ///
+    /// `label` goes into the file name to tell what the file is for.
+    pub fn create(dir: Option<&Path>, label: &str) -> Result<Self> {
+        let dir = dir.map_or_else(std::env::temp_dir, Path::to_path_buf);
+        let path = dir.join(format!("ghostfs-{}-{}.img", label, uuid::Uuid::new_v4()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create temporary file {}", path.display()))?;
+        Ok(Self { file, path })
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempImage {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!(
+                "Failed to remove temporary file {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removed_on_drop_and_unwind() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = TempImage::create(Some(dir.path()), "test").unwrap();
+        assert!(image.path().starts_with(dir.path()));
+        assert!(image.path().is_file());
+        drop(image);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let result = std::panic::catch_unwind(|| {
+            let _image = TempImage::create(Some(dir.path()), "test").unwrap();
+            panic!("scan failed");
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_defaults_to_the_system_temporary_directory() {
+        let image = TempImage::create(None, "default").unwrap();
+        assert!(image.path().starts_with(std::env::temp_dir()));
+    }
+}
@@ -1,90 +1,334 @@
 /// Common types and utilities for file system access
 use anyhow::Result;
-use memmap2::MmapOptions;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs::File;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Bytes per sector, the unit bad areas are tracked in
+pub const SECTOR_SIZE: u64 = 512;
 
 /// A memory-mapped file for efficient large file access
 pub struct BlockDevice {
-    _file: File,
-    mmap: memmap2::Mmap,
+    _file: Option<File>,
+    data: DeviceData,
     size: u64,
+    /// Where read failures come from, if the device can have any
+    faults: Option<Box<dyn FaultSource>>,
+    retry: RetryPolicy,
+    /// Sectors that failed every retry; reads touching them fail at once
+    bad_sectors: Mutex<HashSet<u64>>,
+    /// Whether `bad_sectors` has any, so reads skip its lock until then
+    has_bad_sectors: AtomicBool,
+    /// Read attempts repeated after a failure
+    retries: AtomicU64,
+    /// Reads that failed at first but succeeded on a retry
+    recovered_reads: AtomicU64,
+}
+
+/// Decides which reads of a device fail, standing in for damaged media
+pub trait FaultSource: Send + Sync {
+    /// Whether this attempt at reading `length` bytes at `offset` fails
+    fn fails(&self, offset: u64, length: u64) -> bool;
+}
+
+/// A region set fails every read that touches it
+impl FaultSource for RangeSet {
+    fn fails(&self, offset: u64, length: u64) -> bool {
+        self.overlaps(offset, offset + length)
+    }
+}
+
+/// Fails the reads that either source fails
+struct EitherFaults(Box<dyn FaultSource>, Box<dyn FaultSource>);
+
+impl FaultSource for EitherFaults {
+    fn fails(&self, offset: u64, length: u64) -> bool {
+        self.0.fails(offset, length) || self.1.fails(offset, length)
+    }
+}
+
+/// How often a failed read is attempted before giving up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts per read, including the first
+    pub attempts: u32,
+    /// Wait before the first retry; doubled for each further one
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Read errors seen on a device, see [`BlockDevice::bad_sector_report`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BadSectorReport {
+    /// Sectors ([`SECTOR_SIZE`] bytes) that could not be read, in order
+    pub bad_sectors: Vec<u64>,
+    /// Read attempts repeated after a failure
+    pub retries: u64,
+    /// Reads that succeeded on a retry
+    pub recovered_reads: u64,
+}
+
+/// Bytes backing a BlockDevice
+enum DeviceData {
+    /// A mapping that may be shared with other readers
+    Mapped(Arc<memmap2::Mmap>),
+    Owned(Vec<u8>),
+}
+
+impl DeviceData {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            DeviceData::Mapped(mmap) => mmap,
+            DeviceData::Owned(bytes) => bytes,
+        }
+    }
 }
 
 impl BlockDevice {
-    /// Open a block device or image file
+    /// Open a block device or image file read-only
+    ///
+    /// Reads of a block device go through the device before the mapping is
+    /// touched, so media errors fail the read instead of crashing the
+    /// process, see [`FileReads`](super::device::FileReads).
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = File::open(path.as_ref())?;
-        let size = file.metadata()?.len();
+        Self::open_in(path, None)
+    }
 
-        let mmap = unsafe { MmapOptions::new().map(&file)? };
+    /// Like [`Self::open`], with temporary files (such as a decompressed EWF
+    /// image) in `temp_dir` if given
+    pub fn open_in<P: AsRef<Path>>(path: P, temp_dir: Option<&Path>) -> Result<Self> {
+        let (file, mmap, size) = super::device::map_read_only_in(path.as_ref(), temp_dir)?;
+        let faults = super::device::FileReads::open(path.as_ref())?;
 
         Ok(BlockDevice {
-            _file: file,
-            mmap,
+            _file: Some(file),
+            data: DeviceData::Mapped(Arc::new(mmap)),
             size,
+            faults: faults.map(|f| Box::new(f) as Box<dyn FaultSource>),
+            retry: RetryPolicy::default(),
+            bad_sectors: Mutex::new(HashSet::new()),
+            has_bad_sectors: AtomicBool::new(false),
+            retries: AtomicU64::new(0),
+            recovered_reads: AtomicU64::new(0),
         })
     }
 
+    /// Wrap an existing mapping without copying it
+    ///
+    /// Pass an `Arc` to share one mapping between several devices.
+    pub fn from_mmap(mmap: impl Into<Arc<memmap2::Mmap>>) -> Self {
+        let mmap = mmap.into();
+        BlockDevice {
+            _file: None,
+            size: mmap.len() as u64,
+            data: DeviceData::Mapped(mmap),
+            faults: None,
+            retry: RetryPolicy::default(),
+            bad_sectors: Mutex::new(HashSet::new()),
+            has_bad_sectors: AtomicBool::new(false),
+            retries: AtomicU64::new(0),
+            recovered_reads: AtomicU64::new(0),
+        }
+    }
+
+    /// Use in-memory data as a device
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        BlockDevice {
+            _file: None,
+            size: data.len() as u64,
+            data: DeviceData::Owned(data),
+            faults: None,
+            retry: RetryPolicy::default(),
+            bad_sectors: Mutex::new(HashSet::new()),
+            has_bad_sectors: AtomicBool::new(false),
+            retries: AtomicU64::new(0),
+            recovered_reads: AtomicU64::new(0),
+        }
+    }
+
+    /// Fail reads that touch any of the given byte ranges
+    ///
+    /// Marks regions known to be damaged (e.g. from an imaging tool's bad
+    /// sector map) and lets tests simulate media errors. Reads elsewhere
+    /// still fail where the device itself does.
+    pub fn with_unreadable_regions(mut self, regions: RangeSet) -> Self {
+        self.faults = Some(match self.faults.take() {
+            Some(faults) => Box::new(EitherFaults(Box::new(regions), faults)),
+            None => Box::new(regions),
+        });
+        self
+    }
+
+    /// Let `faults` decide which reads fail
+    pub fn with_fault_source(mut self, faults: impl FaultSource + 'static) -> Self {
+        self.faults = Some(Box::new(faults));
+        self
+    }
+
+    /// Set how often failed reads are retried
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sectors that could not be read so far, and how retries fared
+    pub fn bad_sector_report(&self) -> BadSectorReport {
+        let mut bad_sectors: Vec<u64> = self.bad_sectors.lock().unwrap().iter().copied().collect();
+        bad_sectors.sort_unstable();
+        BadSectorReport {
+            bad_sectors,
+            retries: self.retries.load(Ordering::Relaxed),
+            recovered_reads: self.recovered_reads.load(Ordering::Relaxed),
+        }
+    }
+
     /// Get the size of the device in bytes
     pub fn size(&self) -> u64 {
         self.size
     }
 
-    /// Read a slice of bytes from the device
-    pub fn read_bytes(&self, offset: u64, length: usize) -> Result<&[u8]> {
-        let start = offset as usize;
-        let end = start + length;
+    /// Borrow `length` bytes at `offset` straight from the mapping, without copying
+    ///
+    /// Fails if the range does not lie entirely within the device. A failing
+    /// read is retried according to the [`RetryPolicy`]. If it keeps failing,
+    /// the sectors it covers are probed one by one and those that fail are
+    /// remembered, so later reads touching them fail at once.
+    pub fn read_at(&self, offset: u64, length: usize) -> Result<&[u8]> {
+        let data = self.data.as_slice();
+        let end = offset
+            .checked_add(length as u64)
+            .filter(|&end| end <= data.len() as u64);
+        let Some(end) = end else {
+            anyhow::bail!(
+                "Read beyond end of device: {} bytes at {} > {}",
+                length,
+                offset,
+                data.len()
+            );
+        };
+        let (start, end) = (offset as usize, end as usize);
+
+        if let Some(ref faults) = self.faults {
+            let sectors = sector_span(offset, length as u64);
+            if let Some(sector) = self.first_bad_sector(sectors.clone()) {
+                anyhow::bail!("Bad sector {} in {}..{}", sector, offset, end);
+            }
+
+            if !self.read_with_retries(faults.as_ref(), offset, length as u64) {
+                let mut bad = Vec::new();
+                if sectors.end - sectors.start > 1 {
+                    for sector in sectors {
+                        let (sector_start, sector_len) =
+                            clamp_sector(sector, offset, length as u64);
+                        if !self.read_with_retries(faults.as_ref(), sector_start, sector_len) {
+                            bad.push(sector);
+                        }
+                    }
+                } else {
+                    bad.push(sectors.start);
+                }
+
+                // If every sector reads fine on its own, none is marked
+                if !bad.is_empty() {
+                    self.bad_sectors.lock().unwrap().extend(&bad);
+                    self.has_bad_sectors.store(true, Ordering::Release);
+                }
+                anyhow::bail!("Unreadable data in {}..{}", offset, end);
+            }
+        }
+
+        Ok(&data[start..end])
+    }
+
+    /// Copy `length` bytes at `offset` into an owned buffer
+    ///
+    /// For data that must outlive the device; prefer [`read_at`](Self::read_at)
+    /// for anything parsed in place.
+    pub fn read_to_vec(&self, offset: u64, length: usize) -> Result<Vec<u8>> {
+        self.read_at(offset, length).map(<[u8]>::to_vec)
+    }
 
-        if end > self.mmap.len() {
-            anyhow::bail!("Read beyond end of device: {} > {}", end, self.mmap.len());
+    /// Try a read up to the policy's number of attempts, backing off between them
+    fn read_with_retries(&self, faults: &dyn FaultSource, offset: u64, length: u64) -> bool {
+        let mut backoff = self.retry.backoff;
+        for attempt in 0..self.retry.attempts.max(1) {
+            if attempt > 0 {
+                self.retries.fetch_add(1, Ordering::Relaxed);
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+            if !faults.fails(offset, length) {
+                if attempt > 0 {
+                    self.recovered_reads.fetch_add(1, Ordering::Relaxed);
+                }
+                return true;
+            }
         }
+        false
+    }
 
-        Ok(&self.mmap[start..end])
+    /// First sector of `sectors` already known to be bad
+    fn first_bad_sector(&self, sectors: std::ops::Range<u64>) -> Option<u64> {
+        if !self.has_bad_sectors.load(Ordering::Acquire) {
+            return None;
+        }
+        let bad_sectors = self.bad_sectors.lock().unwrap();
+        sectors
+            .into_iter()
+            .find(|sector| bad_sectors.contains(sector))
     }
 
     /// Read a single sector (512 bytes)
     pub fn read_sector(&self, sector: u64) -> Result<&[u8]> {
-        self.read_bytes(sector * 512, 512)
+        self.read_at(sector * SECTOR_SIZE, SECTOR_SIZE as usize)
     }
 
     /// Read multiple sectors
     pub fn read_sectors(&self, start_sector: u64, count: u32) -> Result<&[u8]> {
-        let offset = start_sector * 512;
-        let length = (count as u64 * 512) as usize;
-        self.read_bytes(offset, length)
+        let offset = start_sector * SECTOR_SIZE;
+        let length = (count as u64 * SECTOR_SIZE) as usize;
+        self.read_at(offset, length)
     }
 
     /// Read data at a specific block offset
     pub fn read_block(&self, block_number: u64, block_size: u32) -> Result<&[u8]> {
         let offset = block_number * block_size as u64;
-        self.read_bytes(offset, block_size as usize)
+        self.read_at(offset, block_size as usize)
     }
 
     /// Create a test BlockDevice from in-memory data (for testing only)
     #[cfg(test)]
     pub fn from_vec(data: Vec<u8>) -> Self {
-        use std::io::Write;
-        let temp_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
-        let mut file = temp_file.as_file();
-        file.write_all(&data).expect("Failed to write test data");
-        file.sync_all().expect("Failed to sync");
-
-        let size = data.len() as u64;
-        let mmap = unsafe {
-            MmapOptions::new()
-                .map(file)
-                .expect("Failed to mmap test data")
-        };
-
-        BlockDevice {
-            _file: temp_file.into_file(),
-            mmap,
-            size,
-        }
+        Self::from_bytes(data)
     }
 }
 
+/// Sectors touched by `length` bytes at `offset`
+fn sector_span(offset: u64, length: u64) -> std::ops::Range<u64> {
+    let first = offset / SECTOR_SIZE;
+    let last = (offset + length.max(1)).div_ceil(SECTOR_SIZE);
+    first..last
+}
+
+/// The part of a `length`-byte read at `offset` that falls in `sector`
+fn clamp_sector(sector: u64, offset: u64, length: u64) -> (u64, u64) {
+    let start = (sector * SECTOR_SIZE).max(offset);
+    let end = ((sector + 1) * SECTOR_SIZE).min(offset + length);
+    (start, end - start)
+}
+
 /// Common block range representation
 #[derive(Debug, Clone)]
 pub struct BlockRange {
@@ -106,6 +350,67 @@ impl BlockRange {
     }
 }
 
+/// Sorted set of non-overlapping half-open `[start, end)` ranges
+///
+/// Used to answer "is this block in use" questions against allocation
+/// structures read from the filesystem (free-space btrees, extent trees, ...).
+#[derive(Debug, Clone, Default)]
+pub struct RangeSet {
+    ranges: Vec<(u64, u64)>,
+}
+
+impl RangeSet {
+    /// Build from `(start, length)` pairs in any order; overlapping ranges are merged
+    pub fn from_ranges<I: IntoIterator<Item = (u64, u64)>>(ranges: I) -> Self {
+        let mut sorted: Vec<(u64, u64)> = ranges
+            .into_iter()
+            .filter(|&(_, length)| length > 0)
+            .map(|(start, length)| (start, start.saturating_add(length)))
+            .collect();
+        sorted.sort_unstable();
+
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(sorted.len());
+        for (start, end) in sorted {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        Self { ranges: merged }
+    }
+
+    /// Whether `value` falls inside one of the ranges
+    pub fn contains(&self, value: u64) -> bool {
+        let index = self.ranges.partition_point(|&(start, _)| start <= value);
+        index > 0 && value < self.ranges[index - 1].1
+    }
+
+    /// Whether any value in `[start, end)` falls inside one of the ranges
+    pub fn overlaps(&self, start: u64, end: u64) -> bool {
+        let index = self
+            .ranges
+            .partition_point(|&(_, range_end)| range_end <= start);
+        self.ranges
+            .get(index)
+            .is_some_and(|&(range_start, _)| range_start < end)
+    }
+
+    /// Number of merged ranges
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Total number of values covered by the ranges
+    pub fn total_len(&self) -> u64 {
+        self.ranges.iter().map(|&(start, end)| end - start).sum()
+    }
+}
+
 /// Magic number detection for file types
 pub struct MagicDetector;
 
@@ -203,6 +508,187 @@ mod tests {
         assert!(!MagicDetector::is_text(&binary_data));
     }
 
+    #[test]
+    fn test_read_at_borrows_and_checks_bounds() {
+        let device = BlockDevice::from_bytes((0..=255).collect());
+
+        let data = device.read_at(250, 6).unwrap();
+        assert_eq!(data, &[250, 251, 252, 253, 254, 255]);
+        // Borrowed from the device's own buffer
+        assert!(std::ptr::eq(
+            data.as_ptr(),
+            device.read_at(0, 256).unwrap()[250..].as_ptr()
+        ));
+        assert_eq!(device.read_at(256, 0).unwrap(), &[] as &[u8]);
+
+        let err = device.read_at(250, 7).unwrap_err();
+        assert!(err.to_string().contains("beyond end of device"), "{}", err);
+        assert!(device.read_at(257, 0).is_err());
+        assert!(device.read_at(u64::MAX, 2).is_err());
+        assert!(device.read_to_vec(200, 100).is_err());
+        assert!(device.read_block(1, 256).is_err());
+    }
+
+    #[test]
+    fn test_read_to_vec_copies() {
+        let device = BlockDevice::from_bytes(vec![7; 64]);
+        let mut copy = device.read_to_vec(8, 8).unwrap();
+        copy[0] = 0;
+        assert_eq!(copy.len(), 8);
+        assert_eq!(device.read_at(8, 1).unwrap(), &[7]);
+    }
+
+    #[test]
+    fn test_range_set() {
+        let set = RangeSet::from_ranges([(100, 10), (0, 5), (105, 20), (200, 0)]);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(0));
+        assert!(set.contains(4));
+        assert!(!set.contains(5));
+        assert!(set.contains(124));
+        assert!(!set.contains(125));
+        assert!(!set.contains(200));
+
+        // Merged ranges are [0, 5) and [100, 125)
+        assert!(set.overlaps(4, 6));
+        assert!(!set.overlaps(5, 100));
+        assert!(set.overlaps(50, 101));
+        assert!(set.overlaps(110, 111));
+        assert!(!set.overlaps(125, 300));
+    }
+
+    #[test]
+    fn test_unreadable_regions_fail_reads() {
+        let device = BlockDevice::from_bytes(vec![7; 4096])
+            .with_unreadable_regions(RangeSet::from_ranges([(1024, 512)]))
+            .with_retry_policy(RetryPolicy {
+                attempts: 1,
+                backoff: Duration::ZERO,
+            });
+
+        assert_eq!(device.read_at(0, 1024).unwrap(), &[7; 1024][..]);
+        assert!(device.read_at(1000, 100).is_err());
+        assert!(device.read_sector(2).is_err());
+        assert!(device.read_sector(3).is_ok());
+        assert_eq!(device.bad_sector_report().bad_sectors, vec![2]);
+    }
+
+    /// Fails the first `failures` reads, or every read of `bad_sector`
+    struct FlakyMedia {
+        failures: AtomicU64,
+        bad_sector: Option<u64>,
+        reads: AtomicU64,
+    }
+
+    impl FlakyMedia {
+        fn new(failures: u64, bad_sector: Option<u64>) -> Arc<Self> {
+            Arc::new(Self {
+                failures: AtomicU64::new(failures),
+                bad_sector,
+                reads: AtomicU64::new(0),
+            })
+        }
+    }
+
+    impl FaultSource for Arc<FlakyMedia> {
+        fn fails(&self, offset: u64, length: u64) -> bool {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            if let Some(sector) = self.bad_sector {
+                if sector_span(offset, length).contains(&sector) {
+                    return true;
+                }
+            }
+            self.failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .is_ok()
+        }
+    }
+
+    fn quick_retries(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn test_read_succeeds_after_retries() {
+        let media = FlakyMedia::new(2, None);
+        let device = BlockDevice::from_bytes(vec![9; 4096])
+            .with_fault_source(media.clone())
+            .with_retry_policy(quick_retries(3));
+
+        assert_eq!(device.read_sector(1).unwrap(), &[9; 512][..]);
+        assert_eq!(media.reads.load(Ordering::SeqCst), 3);
+
+        let report = device.bad_sector_report();
+        assert!(report.bad_sectors.is_empty());
+        assert_eq!(report.retries, 2);
+        assert_eq!(report.recovered_reads, 1);
+
+        // Too few attempts to outlast the failures
+        let media = FlakyMedia::new(2, None);
+        let device = BlockDevice::from_bytes(vec![9; 4096])
+            .with_fault_source(media)
+            .with_retry_policy(quick_retries(2));
+        assert!(device.read_sector(1).is_err());
+        assert_eq!(device.bad_sector_report().bad_sectors, vec![1]);
+    }
+
+    #[test]
+    fn test_persistent_failures_build_bad_sector_map() {
+        let media = FlakyMedia::new(0, Some(5));
+        let device = BlockDevice::from_bytes(vec![3; 8 * 512])
+            .with_fault_source(media.clone())
+            .with_retry_policy(quick_retries(3));
+
+        // A read over sectors 4..8 fails, and probing pins it on sector 5
+        assert!(device.read_at(4 * 512, 4 * 512).is_err());
+        assert_eq!(device.bad_sector_report().bad_sectors, vec![5]);
+
+        // Known bad sectors fail without touching the media again
+        let reads = media.reads.load(Ordering::SeqCst);
+        assert!(device.read_sector(5).is_err());
+        assert!(device.read_at(5 * 512 + 100, 10).is_err());
+        assert_eq!(media.reads.load(Ordering::SeqCst), reads);
+
+        // Neighbouring sectors still read fine
+        assert_eq!(device.read_sectors(6, 2).unwrap(), &[3; 1024][..]);
+        assert_eq!(device.read_sector(4).unwrap(), &[3; 512][..]);
+    }
+
+    #[test]
+    fn test_read_errors_of_the_backing_file_fail_reads() {
+        use super::super::device::{map_read_only, FileReads};
+
+        let image = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(image.path(), vec![5u8; 16384]).unwrap();
+        // Image files are read straight from the mapping
+        assert!(FileReads::open(image.path()).unwrap().is_none());
+
+        let (_file, mmap, _) = map_read_only(image.path()).unwrap();
+        let reads = FileReads::from_paths(&[image.path().to_path_buf()]).unwrap();
+        let device = BlockDevice::from_mmap(mmap)
+            .with_fault_source(reads)
+            .with_retry_policy(quick_retries(2));
+
+        // Cut the file short under the mapping. Touching the lost pages would
+        // raise SIGBUS, the way a media error does, but reading them through
+        // the file fails cleanly first.
+        image.as_file().set_len(8192).unwrap();
+
+        assert_eq!(device.read_at(4096, 4096).unwrap(), &[5; 4096][..]);
+        assert!(device.read_at(4096, 8192).is_err());
+        assert!(device.read_sector(16).is_err());
+
+        let report = device.bad_sector_report();
+        assert_eq!(report.bad_sectors, (16..24).collect::<Vec<u64>>());
+        assert!(report.retries > 0);
+        assert_eq!(report.recovered_reads, 0);
+    }
+
     #[test]
     fn test_block_range() {
         let range = BlockRange::new(10, 5);
@@ -0,0 +1,110 @@
+//! exFAT allocation bitmap
+//!
+//! The allocation bitmap is the authoritative record of which clusters are in
+//! use: files stored contiguously (the NoFatChain flag) have no FAT entries at
+//! all, so the FAT alone cannot tell whether a cluster was re-used.
+//!
+//! Bit N of the bitmap describes cluster N + 2.
+
+use anyhow::{bail, Result};
+
+use super::directory::{BitmapEntry, ENTRY_SIZE, ENTRY_TYPE_BITMAP};
+use super::fat::FatTable;
+use super::ExFatBootSector;
+use crate::fs::common::BlockDevice;
+
+/// First cluster number described by the bitmap
+const FIRST_DATA_CLUSTER: u32 = 2;
+
+/// In-memory copy of the exFAT allocation bitmap
+#[derive(Debug, Clone)]
+pub struct AllocationBitmap {
+    bits: Vec<u8>,
+    cluster_count: u32,
+}
+
+impl AllocationBitmap {
+    /// Build a bitmap from raw bytes covering `cluster_count` clusters
+    pub fn from_bytes(bits: Vec<u8>, cluster_count: u32) -> Self {
+        Self {
+            bits,
+            cluster_count,
+        }
+    }
+
+    /// Locate the bitmap through its root directory entry and read it
+    pub fn from_device(
+        device: &BlockDevice,
+        boot: &ExFatBootSector,
+        fat: &FatTable,
+    ) -> Result<Self> {
+        let cluster_size = fat.cluster_size() as usize;
+        let entry = fat
+            .get_chain(boot.first_cluster_of_root_directory)
+            .into_iter()
+            .filter_map(|cluster| {
+                device
+                    .read_at(fat.cluster_offset(cluster), cluster_size)
+                    .ok()
+            })
+            .flat_map(|data| data.chunks_exact(ENTRY_SIZE))
+            .find(|raw| raw[0] == ENTRY_TYPE_BITMAP)
+            .map(BitmapEntry::parse)
+            .transpose()?;
+
+        let Some(entry) = entry else {
+            bail!("No allocation bitmap entry in root directory");
+        };
+        if entry.first_cluster < FIRST_DATA_CLUSTER {
+            bail!("Invalid allocation bitmap cluster {}", entry.first_cluster);
+        }
+
+        // The bitmap is written contiguously by every mainstream implementation
+        let length =
+            std::cmp::min(entry.data_length, (boot.cluster_count as u64).div_ceil(8)) as usize;
+        let bits = device.read_to_vec(fat.cluster_offset(entry.first_cluster), length)?;
+
+        Ok(Self::from_bytes(bits, boot.cluster_count))
+    }
+
+    /// Whether `cluster` is marked in use
+    pub fn is_allocated(&self, cluster: u32) -> bool {
+        if cluster < FIRST_DATA_CLUSTER || cluster - FIRST_DATA_CLUSTER >= self.cluster_count {
+            return false;
+        }
+
+        let index = (cluster - FIRST_DATA_CLUSTER) as usize;
+        self.bits
+            .get(index / 8)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    /// Number of clusters marked in use
+    pub fn allocated_count(&self) -> usize {
+        (FIRST_DATA_CLUSTER..FIRST_DATA_CLUSTER + self.cluster_count)
+            .filter(|&cluster| self.is_allocated(cluster))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitmap_cluster_numbering() {
+        // Clusters 2 and 5 in use, cluster 10 in use in the second byte
+        let bitmap = AllocationBitmap::from_bytes(vec![0b0000_1001, 0b0000_0001], 12);
+
+        assert!(bitmap.is_allocated(2));
+        assert!(!bitmap.is_allocated(3));
+        assert!(bitmap.is_allocated(5));
+        assert!(bitmap.is_allocated(10));
+        assert!(!bitmap.is_allocated(11));
+        assert!(!bitmap.is_allocated(0));
+        assert!(!bitmap.is_allocated(1));
+        // Beyond the volume
+        assert!(!bitmap.is_allocated(14));
+        assert_eq!(bitmap.allocated_count(), 3);
+    }
+}
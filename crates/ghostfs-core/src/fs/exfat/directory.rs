@@ -10,6 +10,7 @@
 
 use anyhow::Result;
 use byteorder::{LittleEndian, ReadBytesExt};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use std::io::Cursor;
 
 /// Directory entry size in bytes
@@ -69,6 +70,16 @@ pub struct FileEntry {
     pub modify_timestamp: u32,
     /// Last accessed timestamp
     pub access_timestamp: u32,
+    /// Creation time 10ms increment (0-199)
+    pub create_10ms_increment: u8,
+    /// Last modified time 10ms increment (0-199)
+    pub modify_10ms_increment: u8,
+    /// Creation time UTC offset
+    pub create_utc_offset: u8,
+    /// Last modified time UTC offset
+    pub modify_utc_offset: u8,
+    /// Last accessed time UTC offset
+    pub access_utc_offset: u8,
     /// Is this entry deleted?
     pub is_deleted: bool,
 }
@@ -205,6 +216,11 @@ impl FileEntry {
         let create_timestamp = cursor.read_u32::<LittleEndian>()?;
         let modify_timestamp = cursor.read_u32::<LittleEndian>()?;
         let access_timestamp = cursor.read_u32::<LittleEndian>()?;
+        let create_10ms_increment = cursor.read_u8()?;
+        let modify_10ms_increment = cursor.read_u8()?;
+        let create_utc_offset = cursor.read_u8()?;
+        let modify_utc_offset = cursor.read_u8()?;
+        let access_utc_offset = cursor.read_u8()?;
 
         Ok(FileEntry {
             entry_type,
@@ -214,6 +230,11 @@ impl FileEntry {
             create_timestamp,
             modify_timestamp,
             access_timestamp,
+            create_10ms_increment,
+            modify_10ms_increment,
+            create_utc_offset,
+            modify_utc_offset,
+            access_utc_offset,
             is_deleted,
         })
     }
@@ -222,6 +243,66 @@ impl FileEntry {
     pub fn is_directory(&self) -> bool {
         self.file_attributes & ATTR_DIRECTORY != 0
     }
+
+    /// Decoded creation time
+    pub fn created_time(&self) -> Option<DateTime<Utc>> {
+        decode_timestamp(
+            self.create_timestamp,
+            self.create_10ms_increment,
+            self.create_utc_offset,
+        )
+    }
+
+    /// Decoded last modified time
+    pub fn modified_time(&self) -> Option<DateTime<Utc>> {
+        decode_timestamp(
+            self.modify_timestamp,
+            self.modify_10ms_increment,
+            self.modify_utc_offset,
+        )
+    }
+
+    /// Decoded last accessed time (no 10ms field; accessed times have 2s resolution)
+    pub fn accessed_time(&self) -> Option<DateTime<Utc>> {
+        decode_timestamp(self.access_timestamp, 0, self.access_utc_offset)
+    }
+}
+
+/// Decode an exFAT timestamp into UTC
+///
+/// The timestamp uses the DOS layout (2-second resolution, years since 1980),
+/// refined by the 10ms increment (0-199). The UTC offset byte holds a signed
+/// 7-bit count of 15-minute intervals and is only applied when bit 7 is set;
+/// otherwise the local time is assumed to be UTC.
+pub fn decode_timestamp(
+    timestamp: u32,
+    increment_10ms: u8,
+    utc_offset: u8,
+) -> Option<DateTime<Utc>> {
+    if timestamp == 0 {
+        return None;
+    }
+
+    let double_seconds = timestamp & 0x1F;
+    let minute = (timestamp >> 5) & 0x3F;
+    let hour = (timestamp >> 11) & 0x1F;
+    let day = (timestamp >> 16) & 0x1F;
+    let month = (timestamp >> 21) & 0x0F;
+    let year = 1980 + ((timestamp >> 25) & 0x7F) as i32;
+
+    let local =
+        NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, double_seconds * 2)?;
+    let local = local + Duration::milliseconds(increment_10ms.min(199) as i64 * 10);
+
+    let offset_minutes = if utc_offset & 0x80 != 0 {
+        // Sign-extend the 7-bit offset
+        let quarters = ((utc_offset << 1) as i8) >> 1;
+        quarters as i64 * 15
+    } else {
+        0
+    };
+
+    Some((local - Duration::minutes(offset_minutes)).and_utc())
 }
 
 impl StreamExtensionEntry {
@@ -442,9 +523,50 @@ mod tests {
             create_timestamp: 0,
             modify_timestamp: 0,
             access_timestamp: 0,
+            create_10ms_increment: 0,
+            modify_10ms_increment: 0,
+            create_utc_offset: 0,
+            modify_utc_offset: 0,
+            access_utc_offset: 0,
             is_deleted: false,
         };
 
         assert!(entry.is_directory());
     }
+
+    #[test]
+    fn test_timestamp_decoding() {
+        // 2024-03-15 14:30:22 local time
+        let timestamp: u32 = 11 | (30 << 5) | (14 << 11) | (15 << 16) | (3 << 21) | (44 << 25);
+
+        let mut data = [0u8; ENTRY_SIZE];
+        data[0] = ENTRY_TYPE_FILE;
+        data[1] = 2;
+        data[8..12].copy_from_slice(&timestamp.to_le_bytes()); // create
+        data[12..16].copy_from_slice(&timestamp.to_le_bytes()); // modify
+        data[16..20].copy_from_slice(&timestamp.to_le_bytes()); // access
+        data[20] = 150; // create +1.5s
+        data[21] = 0;
+        data[22] = 0x80 | 4; // UTC+01:00
+        data[23] = 0x80 | 0x7C; // UTC-01:00
+        data[24] = 0x00; // offset not recorded
+
+        let entry = FileEntry::parse(&data, true).unwrap();
+
+        let created = entry.created_time().unwrap();
+        assert_eq!(created.to_rfc3339(), "2024-03-15T13:30:23.500+00:00");
+
+        let modified = entry.modified_time().unwrap();
+        assert_eq!(modified.to_rfc3339(), "2024-03-15T15:30:22+00:00");
+
+        let accessed = entry.accessed_time().unwrap();
+        assert_eq!(accessed.to_rfc3339(), "2024-03-15T14:30:22+00:00");
+    }
+
+    #[test]
+    fn test_timestamp_invalid() {
+        assert!(decode_timestamp(0, 0, 0).is_none());
+        // Month 0 is not a valid date
+        assert!(decode_timestamp(1 << 16, 0, 0).is_none());
+    }
 }
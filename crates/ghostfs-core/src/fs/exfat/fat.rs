@@ -22,6 +22,36 @@ pub const FAT_ENTRY_BAD: u32 = 0xFFFFFFF7;
 pub const FAT_ENTRY_EOC_MIN: u32 = 0xFFFFFFF8; // End of chain minimum
 pub const FAT_ENTRY_EOC_MAX: u32 = 0xFFFFFFFF; // End of chain maximum
 
+/// How a cluster chain ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainHealth {
+    /// Ends with an end-of-chain marker
+    Terminated,
+    /// Ends at a free or out-of-range entry, as the chains of deleted and
+    /// contiguous (NoFatChain) files do
+    Unterminated,
+    /// Reaches a cluster marked bad
+    BadCluster(u32),
+    /// Links back to this cluster, which is already in the chain
+    Loop(u32),
+}
+
+impl ChainHealth {
+    /// Whether the chain can be trusted to describe a file's clusters
+    pub fn is_valid(&self) -> bool {
+        matches!(self, ChainHealth::Terminated | ChainHealth::Unterminated)
+    }
+}
+
+/// A cluster chain as followed through the FAT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterChain {
+    pub clusters: Vec<u32>,
+    pub health: ChainHealth,
+    /// Following stopped early because the chain looped
+    pub truncated: bool,
+}
+
 /// exFAT File Allocation Table
 #[derive(Debug)]
 pub struct FatTable {
@@ -48,7 +78,7 @@ impl FatTable {
             fat_size_bytes
         );
 
-        let fat_data = device.read_bytes(fat_offset_bytes, fat_size_bytes as usize)?;
+        let fat_data = device.read_at(fat_offset_bytes, fat_size_bytes as usize)?;
 
         // Parse FAT entries (4 bytes each)
         let mut entries = Vec::with_capacity(fat_data.len() / 4);
@@ -110,28 +140,53 @@ impl FatTable {
 
     /// Get entire cluster chain starting from a cluster
     pub fn get_chain(&self, start_cluster: u32) -> Vec<u32> {
-        let mut chain = Vec::new();
+        self.follow_chain(start_cluster).clusters
+    }
+
+    /// How the chain starting at `start_cluster` ends
+    pub fn validate_chain(&self, start_cluster: u32) -> ChainHealth {
+        self.follow_chain(start_cluster).health
+    }
+
+    /// Follow a cluster chain, stopping at loops and cross-links back into itself
+    ///
+    /// The visited set holds distinct in-range clusters, so it is capped at
+    /// the size of the FAT and a corrupted chain cannot grow without bound.
+    pub fn follow_chain(&self, start_cluster: u32) -> ClusterChain {
+        let mut clusters = Vec::new();
         let mut current = start_cluster;
         let mut visited = std::collections::HashSet::new();
 
-        // Follow chain with loop detection
-        while current >= 2 && (current as usize) < self.entries.len() {
+        let health = loop {
+            if current < 2 || current as usize >= self.entries.len() {
+                break ChainHealth::Unterminated;
+            }
             if !visited.insert(current) {
                 tracing::warn!("FAT chain loop detected at cluster {}", current);
-                break;
+                break ChainHealth::Loop(current);
             }
 
-            chain.push(current);
+            clusters.push(current);
 
             let next = self.entries[current as usize];
-            if self.is_end_of_chain(next) || next == FAT_ENTRY_FREE || next == FAT_ENTRY_BAD {
-                break;
+            if self.is_end_of_chain(next) {
+                break ChainHealth::Terminated;
+            }
+            if next == FAT_ENTRY_BAD {
+                break ChainHealth::BadCluster(current);
+            }
+            if next == FAT_ENTRY_FREE {
+                break ChainHealth::Unterminated;
             }
 
             current = next;
-        }
+        };
 
-        chain
+        ClusterChain {
+            clusters,
+            truncated: matches!(health, ChainHealth::Loop(_)),
+            health,
+        }
     }
 
     /// Get cluster size in bytes
@@ -147,6 +202,14 @@ impl FatTable {
         cluster_heap_offset_bytes + ((cluster - 2) as u64 * self.cluster_size as u64)
     }
 
+    /// Cluster containing a byte offset inside the cluster heap
+    pub fn offset_to_cluster(&self, offset: u64) -> Option<u32> {
+        let cluster_heap_offset_bytes =
+            self.cluster_heap_offset as u64 * self.bytes_per_sector as u64;
+        let relative = offset.checked_sub(cluster_heap_offset_bytes)?;
+        u32::try_from(relative / self.cluster_size as u64 + 2).ok()
+    }
+
     /// Find orphaned cluster chains (allocated but not referenced by any directory entry)
     /// Returns list of (start_cluster, chain) for potential deleted files
     pub fn find_orphaned_chains(
@@ -259,4 +322,49 @@ mod tests {
         let chain = fat.get_chain(2);
         assert_eq!(chain, vec![2, 3, 4]);
     }
+
+    fn fat_with(links: &[(usize, u32)]) -> FatTable {
+        let mut entries = vec![0u32; 16];
+        for &(cluster, next) in links {
+            entries[cluster] = next;
+        }
+        FatTable {
+            entries,
+            cluster_size: 4096,
+            cluster_heap_offset: 0,
+            bytes_per_sector: 512,
+        }
+    }
+
+    #[test]
+    fn test_validate_chain() {
+        let fat = fat_with(&[
+            (2, 3),
+            (3, FAT_ENTRY_EOC_MAX),
+            // 5 -> 6 -> 7 -> 5
+            (5, 6),
+            (6, 7),
+            (7, 5),
+            // 9 -> 10, which is marked bad
+            (9, 10),
+            (10, FAT_ENTRY_BAD),
+            (12, 12),
+        ]);
+
+        assert_eq!(fat.validate_chain(2), ChainHealth::Terminated);
+        assert_eq!(fat.validate_chain(4), ChainHealth::Unterminated);
+        assert_eq!(fat.validate_chain(9), ChainHealth::BadCluster(10));
+        assert!(!fat.validate_chain(9).is_valid());
+
+        let looping = fat.follow_chain(5);
+        assert_eq!(looping.clusters, vec![5, 6, 7]);
+        assert_eq!(looping.health, ChainHealth::Loop(5));
+        assert!(looping.truncated);
+
+        // A cluster linked to itself
+        let self_linked = fat.follow_chain(12);
+        assert_eq!(self_linked.clusters, vec![12]);
+        assert_eq!(self_linked.health, ChainHealth::Loop(12));
+        assert!(!fat.follow_chain(2).truncated);
+    }
 }
@@ -5,14 +5,18 @@ use encoding_rs::UTF_16LE;
 use std::io::Cursor;
 
 use super::common::BlockDevice;
+use crate::recovery::RecoveryError;
 
 // Sub-modules
+pub mod bitmap;
 pub mod directory;
 pub mod fat;
 pub mod recovery;
 
 /// exFAT file system signature
 const EXFAT_SIGNATURE: &[u8; 8] = b"EXFAT   ";
+/// VolumeFlags bit set while the volume is mounted and cleared on a clean unmount
+const VOLUME_FLAG_DIRTY: u16 = 0x0002;
 
 /// exFAT boot sector structure (simplified)
 #[derive(Debug)]
@@ -52,7 +56,7 @@ impl ExFatBootSector {
         std::io::Read::read_exact(&mut cursor, &mut file_system_name)?;
 
         if &file_system_name != EXFAT_SIGNATURE {
-            anyhow::bail!("Invalid exFAT signature");
+            return Err(RecoveryError::corrupted(3, "Invalid exFAT signature").into());
         }
 
         // Skip reserved area (53 bytes)
@@ -109,6 +113,17 @@ impl ExFatBootSector {
     pub fn bytes_per_cluster(&self) -> u32 {
         self.bytes_per_sector() * self.sectors_per_cluster()
     }
+
+    /// Fraction of clusters in use (0.0 - 1.0), if the volume records it
+    pub fn used_fraction(&self) -> Option<f32> {
+        // 0xFF means the percentage is not available
+        (self.percent_in_use <= 100).then(|| self.percent_in_use as f32 / 100.0)
+    }
+
+    /// Whether the volume was not cleanly unmounted
+    pub fn is_dirty(&self) -> bool {
+        self.volume_flags & VOLUME_FLAG_DIRTY != 0
+    }
 }
 
 /// Check if data contains exFAT boot sector signature
@@ -180,6 +195,14 @@ pub fn decode_utf16_filename(utf16_data: &[u8]) -> Result<String> {
 
 /// Scan for deleted files in exFAT
 pub fn scan_for_deleted_files(device: &BlockDevice) -> Result<Vec<crate::DeletedFile>> {
+    scan_for_deleted_files_with_options(device, recovery::ExFatScanOptions::default())
+}
+
+/// Scan for deleted files in exFAT, limiting or reporting on the signature scan
+pub fn scan_for_deleted_files_with_options(
+    device: &BlockDevice,
+    options: recovery::ExFatScanOptions,
+) -> Result<Vec<crate::DeletedFile>> {
     // Parse boot sector
     let sector0 = device.read_sector(0)?;
     let boot_sector = ExFatBootSector::parse(sector0)?;
@@ -196,7 +219,8 @@ pub fn scan_for_deleted_files(device: &BlockDevice) -> Result<Vec<crate::Deleted
     );
 
     // Create and use the recovery engine
-    let recovery_engine = recovery::ExFatRecoveryEngine::new(device, boot_sector)?;
+    let recovery_engine =
+        recovery::ExFatRecoveryEngine::new(device, boot_sector)?.with_options(options);
     let deleted_files = recovery_engine.scan_deleted_files()?;
 
     tracing::info!("exFAT scan complete: {} files found", deleted_files.len());
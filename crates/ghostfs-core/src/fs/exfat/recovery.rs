@@ -6,20 +6,76 @@
 //! 3. Signature-based scanning (file magic bytes)
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use std::collections::HashSet;
 use std::path::PathBuf;
 
+use super::bitmap::AllocationBitmap;
 use super::directory::{DirectoryEntry, FileEntrySet, ENTRY_SIZE};
-use super::fat::FatTable;
+use super::fat::{ChainHealth, FatTable};
 use super::ExFatBootSector;
 use crate::fs::common::BlockDevice;
+use crate::recovery::signatures;
 use crate::{BlockRange, DeletedFile, FileMetadata, FileType};
 
+/// Directories nested deeper than this are not scanned
+const MAX_DIRECTORY_DEPTH: usize = 32;
+
+/// Parts of the directory tree the scan could not walk
+///
+/// Anything non-zero means deleted entries in those directories may have
+/// been missed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExFatScanStats {
+    /// Subdirectories skipped for being nested deeper than the depth limit
+    pub truncated_subtrees: usize,
+    /// Subdirectories whose clusters had already been scanned
+    pub directory_loops: usize,
+    /// Directory cluster chains that link back into themselves in the FAT
+    pub fat_chain_loops: usize,
+}
+
+impl ExFatScanStats {
+    /// Whether the whole directory tree was walked
+    pub fn is_complete(&self) -> bool {
+        self.truncated_subtrees == 0 && self.directory_loops == 0 && self.fat_chain_loops == 0
+    }
+}
+
+/// Clusters between signature scan progress reports
+const PROGRESS_INTERVAL: u32 = 4096;
+
+/// Settings for an exFAT scan
+#[derive(Default)]
+pub struct ExFatScanOptions<'a> {
+    /// Clusters the signature scan examines; `None` examines the whole heap
+    pub max_signature_clusters: Option<u32>,
+    /// Called with (clusters examined, clusters to examine) as the signature scan runs
+    pub progress: Option<Box<dyn Fn(u32, u32) + 'a>>,
+    /// Only list the deleted directory entries: their cluster chains are not
+    /// followed, and neither orphaned chains nor signatures are scanned for
+    pub list_only: bool,
+    /// Signatures orphaned chains and the signature scan identify files by
+    pub signatures: signatures::SignatureSet,
+}
+
+/// State shared across the recursive directory walk
+#[derive(Default)]
+struct DirectoryWalk {
+    /// Directory clusters already scanned
+    visited: HashSet<u32>,
+    stats: ExFatScanStats,
+}
+
 /// exFAT Recovery Engine
 pub struct ExFatRecoveryEngine<'a> {
     device: &'a BlockDevice,
     boot_sector: ExFatBootSector,
     fat_table: FatTable,
+    /// Allocation bitmap, if it could be read; the FAT is used otherwise
+    bitmap: Option<AllocationBitmap>,
+    options: ExFatScanOptions<'a>,
+    end_markers: signatures::EndMarkerScanner,
 }
 
 impl<'a> ExFatRecoveryEngine<'a> {
@@ -34,23 +90,55 @@ impl<'a> ExFatRecoveryEngine<'a> {
             fat_table.allocated_cluster_count()
         );
 
+        let bitmap = match AllocationBitmap::from_device(device, &boot_sector, &fat_table) {
+            Ok(bitmap) => {
+                tracing::debug!(
+                    "exFAT allocation bitmap: {} clusters in use",
+                    bitmap.allocated_count()
+                );
+                Some(bitmap)
+            }
+            Err(e) => {
+                tracing::warn!("Could not read exFAT allocation bitmap, using FAT: {}", e);
+                None
+            }
+        };
+
         Ok(Self {
             device,
             boot_sector,
             fat_table,
+            bitmap,
+            options: ExFatScanOptions::default(),
+            end_markers: signatures::EndMarkerScanner::default(),
         })
     }
 
+    /// Use `options` for the scan
+    pub fn with_options(mut self, options: ExFatScanOptions<'a>) -> Self {
+        self.end_markers =
+            signatures::EndMarkerScanner::default().with_signatures(options.signatures.clone());
+        self.options = options;
+        self
+    }
+
     /// Scan for all deleted files
     pub fn scan_deleted_files(&self) -> Result<Vec<DeletedFile>> {
+        Ok(self.scan_deleted_files_with_stats()?.0)
+    }
+
+    /// Scan for all deleted files, reporting which parts of the directory tree were skipped
+    pub fn scan_deleted_files_with_stats(&self) -> Result<(Vec<DeletedFile>, ExFatScanStats)> {
         let mut deleted_files = Vec::new();
         let mut file_id = 1u64;
         let mut referenced_clusters = HashSet::new();
+        let mut walk = DirectoryWalk::default();
 
         tracing::info!("exFAT Recovery: Starting scan");
 
         // Method 1: Scan root directory and subdirectories for deleted entries
-        match self.scan_deleted_directory_entries(&mut file_id, &mut referenced_clusters) {
+        match self.scan_deleted_directory_entries(&mut file_id, &mut referenced_clusters, &mut walk)
+        {
             Ok(mut files) => {
                 tracing::info!("Found {} deleted directory entries", files.len());
                 deleted_files.append(&mut files);
@@ -60,6 +148,25 @@ impl<'a> ExFatRecoveryEngine<'a> {
             }
         }
 
+        let stats = walk.stats;
+        if !stats.is_complete() {
+            tracing::warn!(
+                "exFAT directory scan incomplete, recovery may miss files: \
+                 {} subtrees too deep, {} directory loops, {} FAT chain loops",
+                stats.truncated_subtrees,
+                stats.directory_loops,
+                stats.fat_chain_loops
+            );
+        }
+
+        if self.options.list_only {
+            tracing::info!(
+                "exFAT listing complete: {} deleted entries found",
+                deleted_files.len()
+            );
+            return Ok((deleted_files, stats));
+        }
+
         // Method 2: Find orphaned cluster chains
         match self.find_orphaned_clusters(&mut file_id, &referenced_clusters) {
             Ok(mut files) => {
@@ -86,7 +193,7 @@ impl<'a> ExFatRecoveryEngine<'a> {
             "exFAT Recovery complete: {} total files found",
             deleted_files.len()
         );
-        Ok(deleted_files)
+        Ok((deleted_files, stats))
     }
 
     /// Scan directories for deleted entries
@@ -94,6 +201,7 @@ impl<'a> ExFatRecoveryEngine<'a> {
         &self,
         file_id: &mut u64,
         referenced_clusters: &mut HashSet<u32>,
+        walk: &mut DirectoryWalk,
     ) -> Result<Vec<DeletedFile>> {
         let mut deleted_files = Vec::new();
 
@@ -108,6 +216,7 @@ impl<'a> ExFatRecoveryEngine<'a> {
             file_id,
             &mut deleted_files,
             referenced_clusters,
+            walk,
             0, // depth
         )?;
 
@@ -121,15 +230,38 @@ impl<'a> ExFatRecoveryEngine<'a> {
         file_id: &mut u64,
         deleted_files: &mut Vec<DeletedFile>,
         referenced_clusters: &mut HashSet<u32>,
+        walk: &mut DirectoryWalk,
         depth: usize,
     ) -> Result<()> {
-        if depth > 32 {
-            // Prevent infinite recursion
+        if depth > MAX_DIRECTORY_DEPTH {
+            tracing::warn!(
+                "Directory at cluster {} is nested deeper than {} levels, skipping",
+                start_cluster,
+                MAX_DIRECTORY_DEPTH
+            );
+            walk.stats.truncated_subtrees += 1;
             return Ok(());
         }
 
         // Get cluster chain for this directory
-        let chain = self.fat_table.get_chain(start_cluster);
+        let followed = self.fat_table.follow_chain(start_cluster);
+        let chain = followed.clusters;
+
+        // A directory reached twice means the tree links back on itself
+        if chain.iter().any(|cluster| walk.visited.contains(cluster)) {
+            tracing::warn!(
+                "Directory at cluster {} was already scanned, not following the loop",
+                start_cluster
+            );
+            walk.stats.directory_loops += 1;
+            return Ok(());
+        }
+        walk.visited.extend(&chain);
+
+        if let ChainHealth::Loop(_) = followed.health {
+            tracing::warn!("FAT chain of directory at cluster {} loops", start_cluster);
+            walk.stats.fat_chain_loops += 1;
+        }
 
         // Mark these clusters as referenced
         for cluster in &chain {
@@ -142,7 +274,7 @@ impl<'a> ExFatRecoveryEngine<'a> {
 
         for cluster in &chain {
             let offset = self.fat_table.cluster_offset(*cluster);
-            if let Ok(data) = self.device.read_bytes(offset, cluster_size) {
+            if let Ok(data) = self.device.read_at(offset, cluster_size) {
                 dir_data.extend_from_slice(data);
             }
         }
@@ -184,6 +316,7 @@ impl<'a> ExFatRecoveryEngine<'a> {
                                     file_id,
                                     deleted_files,
                                     referenced_clusters,
+                                    walk,
                                     depth + 1,
                                 )?;
                             }
@@ -281,21 +414,26 @@ impl<'a> ExFatRecoveryEngine<'a> {
         for (start_cluster, chain) in orphans {
             // Try to detect file type from first cluster
             let offset = self.fat_table.cluster_offset(start_cluster);
-            let header = self.device.read_bytes(offset, 512)?;
+            let header = self.device.read_at(offset, 512)?;
 
-            let (mime_type, extension) = self.detect_file_type(header);
+            let (mime_type, extension) = self
+                .options
+                .signatures
+                .detect_file_type(header)
+                .unwrap_or_else(|| ("application/octet-stream".to_string(), "bin".to_string()));
 
             // Estimate file size from chain length
             let estimated_size = chain.len() as u64 * self.fat_table.cluster_size() as u64;
 
             // Convert chain to block ranges
             let data_blocks = self.chain_to_block_ranges(&chain);
+            let created_time = self.content_created_time(offset, &mime_type);
 
             // Create basic exFAT metadata for orphaned clusters
             let exfat_meta = crate::ExFatFileMetadata {
                 first_cluster: start_cluster,
                 cluster_chain: chain.clone(),
-                chain_valid: chain.iter().all(|&c| c >= 2 && c != 0xFFFFFFF7),
+                chain_valid: self.fat_table.validate_chain(start_cluster).is_valid(),
                 checksum: 0,        // No checksum available for orphans
                 entry_count: 0,     // No directory entry
                 utf16_valid: false, // No filename
@@ -312,7 +450,7 @@ impl<'a> ExFatRecoveryEngine<'a> {
                 size: estimated_size,
                 deletion_time: None,
                 confidence_score: 0.5, // Medium confidence
-                file_type: FileType::RegularFile,
+                file_type: FileType::from_exfat_attributes(exfat_meta.attributes),
                 data_blocks,
                 is_recoverable: true,
                 metadata: FileMetadata {
@@ -321,12 +459,15 @@ impl<'a> ExFatRecoveryEngine<'a> {
                     permissions: None,
                     owner_uid: None,
                     owner_gid: None,
-                    created_time: None,
+                    owner_name: None,
+                    group_name: None,
+                    created_time,
                     modified_time: None,
                     accessed_time: None,
                     extended_attributes: std::collections::HashMap::new(),
                 },
                 fs_metadata: Some(crate::FsSpecificMetadata::ExFat(exfat_meta)),
+                skip_reason: None,
             };
 
             *file_id += 1;
@@ -337,22 +478,44 @@ impl<'a> ExFatRecoveryEngine<'a> {
     }
 
     /// Signature-based file scanning
+    ///
+    /// Examines the whole cluster heap unless `max_signature_clusters` is set.
+    /// (It used to stop after 50000 clusters, so on large SD cards nothing
+    /// past the first ~200MB was ever found.)
     fn scan_file_signatures(&self, file_id: &mut u64) -> Result<Vec<DeletedFile>> {
         let mut deleted_files = Vec::new();
 
         let cluster_size = self.fat_table.cluster_size() as u64;
-        let max_clusters = std::cmp::min(self.boot_sector.cluster_count, 50000);
+        let max_clusters = match self.options.max_signature_clusters {
+            Some(limit) => self.boot_sector.cluster_count.min(limit),
+            None => self.boot_sector.cluster_count,
+        };
+        if max_clusters < self.boot_sector.cluster_count {
+            tracing::info!(
+                "exFAT signature scan limited to {} of {} clusters",
+                max_clusters,
+                self.boot_sector.cluster_count
+            );
+        }
 
         for cluster in 2..max_clusters + 2 {
+            let examined = cluster - 2;
+            if examined % PROGRESS_INTERVAL == 0 {
+                if let Some(progress) = &self.options.progress {
+                    progress(examined, max_clusters);
+                }
+            }
+
             // Skip allocated clusters
             if self.fat_table.is_allocated(cluster) {
                 continue;
             }
 
             let offset = self.fat_table.cluster_offset(cluster);
-            if let Ok(header) = self.device.read_bytes(offset, 512) {
+            if let Ok(header) = self.device.read_at(offset, 512) {
                 if let Some((mime, ext, est_size)) = self.detect_file_with_size(header, offset) {
                     let block_count = est_size.div_ceil(cluster_size);
+                    let created_time = self.content_created_time(offset, &mime);
 
                     // Create minimal exFAT metadata for signature-based recovery
                     let exfat_meta = crate::ExFatFileMetadata {
@@ -375,12 +538,16 @@ impl<'a> ExFatRecoveryEngine<'a> {
                         size: est_size,
                         deletion_time: None,
                         confidence_score: 0.5,
-                        file_type: FileType::RegularFile,
-                        data_blocks: vec![BlockRange {
+                        file_type: FileType::from_exfat_attributes(exfat_meta.attributes),
+                        // Only the first cluster is known free; later ones may be in use
+                        data_blocks: BlockRange {
                             start_block: cluster as u64,
                             block_count,
                             is_allocated: false,
-                        }],
+                        }
+                        .split_by_allocation(1, |c| {
+                            u32::try_from(c).is_ok_and(|c| self.is_cluster_in_use(c))
+                        }),
                         is_recoverable: est_size > 0,
                         metadata: FileMetadata {
                             mime_type: Some(mime),
@@ -388,12 +555,15 @@ impl<'a> ExFatRecoveryEngine<'a> {
                             permissions: None,
                             owner_uid: None,
                             owner_gid: None,
-                            created_time: None,
+                            owner_name: None,
+                            group_name: None,
+                            created_time,
                             modified_time: None,
                             accessed_time: None,
                             extended_attributes: std::collections::HashMap::new(),
                         },
                         fs_metadata: Some(crate::FsSpecificMetadata::ExFat(exfat_meta)),
+                        skip_reason: None,
                     };
 
                     *file_id += 1;
@@ -402,6 +572,9 @@ impl<'a> ExFatRecoveryEngine<'a> {
             }
         }
 
+        if let Some(progress) = &self.options.progress {
+            progress(max_clusters, max_clusters);
+        }
         Ok(deleted_files)
     }
 
@@ -414,9 +587,8 @@ impl<'a> ExFatRecoveryEngine<'a> {
         let first_cluster = file_set.stream_extension.first_cluster;
 
         // Validate cluster chain for integrity
-        let chain_valid = chain.iter().all(|&c| {
-            c >= 2 && c != 0xFFFFFFF7 // Not bad cluster marker
-        });
+        let chain_valid =
+            first_cluster >= 2 && self.fat_table.validate_chain(first_cluster).is_valid();
 
         // Validate UTF-16 filename (check if it was properly decoded)
         let utf16_valid = !file_set.filename.is_empty()
@@ -443,7 +615,11 @@ impl<'a> ExFatRecoveryEngine<'a> {
     /// Convert a file entry set to DeletedFile
     fn file_set_to_deleted_file(&self, id: u64, file_set: &FileEntrySet) -> DeletedFile {
         let first_cluster = file_set.stream_extension.first_cluster;
-        let chain = self.fat_table.get_chain(first_cluster);
+        let chain = if self.options.list_only {
+            Vec::new()
+        } else {
+            self.fat_table.get_chain(first_cluster)
+        };
 
         tracing::debug!(
             "file_set_to_deleted_file: '{}' cluster={}, chain_len={}, data_len={}",
@@ -453,23 +629,32 @@ impl<'a> ExFatRecoveryEngine<'a> {
             file_set.stream_extension.data_length
         );
 
-        let data_blocks = self.chain_to_block_ranges(&chain);
+        // Clusters of a deleted file that are in use again belong to a live file now
+        let data_blocks = self
+            .chain_to_block_ranges(&chain)
+            .iter()
+            .flat_map(|range| {
+                range.split_by_allocation(self.fat_table.cluster_size() as u64, |offset| {
+                    self.fat_table
+                        .offset_to_cluster(offset)
+                        .is_some_and(|cluster| self.is_cluster_in_use(cluster))
+                })
+            })
+            .collect();
 
         // Extract exFAT-specific metadata
         let exfat_meta = self.extract_exfat_metadata(file_set, &chain);
 
-        let file_type = if file_set.file_entry.is_directory() {
-            FileType::Directory
-        } else {
-            FileType::RegularFile
-        };
+        let file_type = FileType::from_exfat_attributes(file_set.file_entry.file_attributes);
 
         DeletedFile {
             id,
             inode_or_cluster: first_cluster as u64,
             original_path: Some(PathBuf::from(&file_set.filename)),
             size: file_set.stream_extension.data_length,
-            deletion_time: None,   // TODO: Parse timestamp
+            // exFAT records no deletion time, so the last modified time is used
+            // as a heuristic lower bound for when the file was deleted
+            deletion_time: file_set.file_entry.modified_time(),
             confidence_score: 0.7, // Higher confidence for directory entries
             file_type,
             data_blocks,
@@ -483,12 +668,15 @@ impl<'a> ExFatRecoveryEngine<'a> {
                 permissions: None,
                 owner_uid: None,
                 owner_gid: None,
-                created_time: None,
-                modified_time: None,
-                accessed_time: None,
+                owner_name: None,
+                group_name: None,
+                created_time: file_set.file_entry.created_time(),
+                modified_time: file_set.file_entry.modified_time(),
+                accessed_time: file_set.file_entry.accessed_time(),
                 extended_attributes: std::collections::HashMap::new(),
             },
             fs_metadata: Some(crate::FsSpecificMetadata::ExFat(exfat_meta)),
+            skip_reason: None,
         }
     }
 
@@ -544,145 +732,203 @@ impl<'a> ExFatRecoveryEngine<'a> {
         ranges
     }
 
-    /// Detect file type from magic bytes
-    fn detect_file_type(&self, header: &[u8]) -> (String, String) {
-        if header.len() < 8 {
-            return ("application/octet-stream".to_string(), "bin".to_string());
-        }
-
-        // JPEG
-        if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
-            return ("image/jpeg".to_string(), "jpg".to_string());
-        }
-
-        // PNG
-        if header.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
-            return ("image/png".to_string(), "png".to_string());
-        }
-
-        // PDF
-        if header.starts_with(b"%PDF") {
-            return ("application/pdf".to_string(), "pdf".to_string());
-        }
-
-        // ZIP
-        if header.starts_with(&[0x50, 0x4B, 0x03, 0x04]) {
-            return ("application/zip".to_string(), "zip".to_string());
-        }
-
-        ("application/octet-stream".to_string(), "bin".to_string())
-    }
-
     /// Detect file type and estimate size
     fn detect_file_with_size(
         &self,
         header: &[u8],
         start_offset: u64,
     ) -> Option<(String, String, u64)> {
-        if header.len() < 8 {
-            return None;
-        }
+        let (mime, ext) = self.options.signatures.detect_file_type(header)?;
+        let size = self.find_file_end(start_offset, &mime);
+        Some((mime, ext, size))
+    }
 
-        // JPEG
-        if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
-            let size = self.find_jpeg_end(start_offset);
-            return Some(("image/jpeg".to_string(), "jpg".to_string(), size));
-        }
+    /// Size of the file at `start_offset`, from its end marker if one is found
+    ///
+    /// Types without an end marker get a conservative 1 MiB.
+    fn find_file_end(&self, start_offset: u64, mime: &str) -> u64 {
+        let limit = self
+            .options
+            .signatures
+            .end_marker_search_limit(mime)
+            .unwrap_or(1024 * 1024);
+        let available = self.device.size().saturating_sub(start_offset).min(limit);
+        self.device
+            .read_at(start_offset, available as usize)
+            .ok()
+            .and_then(|data| self.end_markers.find_end(data, start_offset, mime))
+            .map_or(available, |end| end.size)
+    }
 
-        // PNG
-        if header.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
-            let size = self.find_png_end(start_offset);
-            return Some(("image/png".to_string(), "png".to_string(), size));
+    /// When the photo at `offset` was taken, from its EXIF data
+    ///
+    /// Files found without a directory entry have no timestamps of their own.
+    fn content_created_time(&self, offset: u64, mime: &str) -> Option<DateTime<Utc>> {
+        if !matches!(mime, "image/jpeg" | "image/tiff") {
+            return None;
         }
+        let available = self
+            .device
+            .size()
+            .saturating_sub(offset)
+            .min(signatures::EXIF_SEARCH_LEN as u64);
+        let data = self.device.read_at(offset, available as usize).ok()?;
+        signatures::extract_image_date(data)
+    }
 
-        // PDF
-        if header.starts_with(b"%PDF") {
-            let size = self.find_pdf_end(start_offset);
-            return Some(("application/pdf".to_string(), "pdf".to_string(), size));
+    /// Whether a cluster is currently in use, per the allocation bitmap or the FAT
+    fn is_cluster_in_use(&self, cluster: u32) -> bool {
+        match &self.bitmap {
+            Some(bitmap) => bitmap.is_allocated(cluster),
+            None => self.fat_table.is_allocated(cluster),
         }
+    }
 
-        // ZIP
-        if header.starts_with(&[0x50, 0x4B, 0x03, 0x04]) {
-            let size = std::cmp::min(
-                self.boot_sector.volume_length * self.boot_sector.bytes_per_sector() as u64
-                    - start_offset,
-                1024 * 1024,
-            );
-            return Some(("application/zip".to_string(), "zip".to_string(), size));
-        }
+    /// Get cluster size for recovery
+    pub fn cluster_size(&self) -> u32 {
+        self.fat_table.cluster_size()
+    }
 
-        None
+    /// Get cluster offset for recovery
+    pub fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.fat_table.cluster_offset(cluster)
     }
+}
 
-    /// Find JPEG end marker
-    fn find_jpeg_end(&self, start_offset: u64) -> u64 {
-        let max_size = 10 * 1024 * 1024; // 10MB max
-        let chunk_size = 64 * 1024;
-        let mut offset = start_offset;
-
-        while offset < start_offset + max_size {
-            if let Ok(data) = self.device.read_bytes(offset, chunk_size) {
-                for i in 0..data.len().saturating_sub(1) {
-                    if data[i] == 0xFF && data[i + 1] == 0xD9 {
-                        return offset - start_offset + i as u64 + 2;
-                    }
-                }
-            }
-            offset += chunk_size as u64 - 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::exfat::directory::{ENTRY_TYPE_FILE, ENTRY_TYPE_FILENAME, ENTRY_TYPE_STREAM};
+
+    /// An exFAT volume of 512-byte sectors with the root directory at cluster 2
+    fn exfat_volume(
+        sectors_per_cluster_shift: u8,
+        cluster_count: u32,
+        fat: &[(u32, u32)],
+        clusters: &[(u32, Vec<u8>)],
+    ) -> BlockDevice {
+        let cluster_size = 512usize << sectors_per_cluster_shift;
+        let fat_sectors = ((cluster_count as usize + 2) * 4).div_ceil(512);
+        let fat_offset = 8 * 512;
+        let heap_offset = fat_offset + fat_sectors * 512;
+
+        let mut image = vec![0u8; heap_offset + cluster_count as usize * cluster_size];
+        image[3..11].copy_from_slice(b"EXFAT   ");
+        image[80..84].copy_from_slice(&8u32.to_le_bytes()); // FAT offset (sectors)
+        image[84..88].copy_from_slice(&(fat_sectors as u32).to_le_bytes());
+        image[88..92].copy_from_slice(&((heap_offset / 512) as u32).to_le_bytes());
+        image[92..96].copy_from_slice(&cluster_count.to_le_bytes());
+        image[96..100].copy_from_slice(&2u32.to_le_bytes()); // root directory
+        image[108] = 9; // 512-byte sectors
+        image[109] = sectors_per_cluster_shift;
+
+        for &(cluster, next) in fat {
+            let at = fat_offset + cluster as usize * 4;
+            image[at..at + 4].copy_from_slice(&next.to_le_bytes());
         }
-
-        max_size
+        for (cluster, data) in clusters {
+            let at = heap_offset + (*cluster as usize - 2) * cluster_size;
+            image[at..at + data.len()].copy_from_slice(data);
+        }
+        BlockDevice::from_bytes(image)
     }
 
-    /// Find PNG end (IEND chunk)
-    fn find_png_end(&self, start_offset: u64) -> u64 {
-        let max_size = 10 * 1024 * 1024;
-        let chunk_size = 64 * 1024;
-        let mut offset = start_offset;
+    /// A small volume of 64 4KiB clusters
+    fn exfat_image(fat: &[(u32, u32)], clusters: &[(u32, Vec<u8>)]) -> BlockDevice {
+        exfat_volume(3, 64, fat, clusters)
+    }
 
-        while offset < start_offset + max_size {
-            if let Ok(data) = self.device.read_bytes(offset, chunk_size) {
-                if let Some(pos) = data.windows(4).position(|w| w == b"IEND") {
-                    return offset - start_offset + pos as u64 + 8;
-                }
-            }
-            offset += chunk_size as u64 - 4;
+    /// File, stream extension and name entries for a subdirectory
+    fn subdirectory_entry(name: &str, first_cluster: u32) -> Vec<u8> {
+        let mut set = vec![0u8; ENTRY_SIZE * 3];
+        set[0] = ENTRY_TYPE_FILE;
+        set[1] = 2;
+        set[4] = 0x10; // directory attribute
+        set[32] = ENTRY_TYPE_STREAM;
+        set[35] = name.len() as u8;
+        set[52..56].copy_from_slice(&first_cluster.to_le_bytes());
+        set[64] = ENTRY_TYPE_FILENAME;
+        for (i, unit) in name.encode_utf16().enumerate() {
+            set[66 + i * 2..68 + i * 2].copy_from_slice(&unit.to_le_bytes());
         }
-
-        max_size
+        set
     }
 
-    /// Find PDF end (%%EOF)
-    fn find_pdf_end(&self, start_offset: u64) -> u64 {
-        let max_size = 50 * 1024 * 1024;
-        let chunk_size = 64 * 1024;
-        let mut offset = start_offset;
+    #[test]
+    fn test_signature_scan_reaches_past_old_cap() {
+        // 512-byte clusters put the old 50000-cluster cap at ~25MB
+        let eoc = crate::fs::exfat::fat::FAT_ENTRY_EOC_MAX;
+        let jpeg_cluster = 50_100;
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(b"JFIF\0");
+        jpeg.extend_from_slice(&[0u8; 100]);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        let device = exfat_volume(0, 50_200, &[(2, eoc)], &[(jpeg_cluster, jpeg)]);
+
+        let scan = |max_signature_clusters| {
+            let boot_sector = ExFatBootSector::parse(device.read_sector(0).unwrap()).unwrap();
+            let reports = std::cell::RefCell::new(Vec::new());
+            let files = ExFatRecoveryEngine::new(&device, boot_sector)
+                .unwrap()
+                .with_options(ExFatScanOptions {
+                    max_signature_clusters,
+                    progress: Some(Box::new(|done, total| {
+                        reports.borrow_mut().push((done, total))
+                    })),
+                    ..Default::default()
+                })
+                .scan_deleted_files()
+                .unwrap();
+            let found = files
+                .iter()
+                .any(|f| f.inode_or_cluster == jpeg_cluster as u64 && f.size == 113);
+            (found, reports.into_inner())
+        };
 
-        while offset < start_offset + max_size {
-            if let Ok(data) = self.device.read_bytes(offset, chunk_size) {
-                if let Some(pos) = data.windows(5).position(|w| w == b"%%EOF") {
-                    return offset - start_offset + pos as u64 + 5;
-                }
-            }
-            offset += chunk_size as u64 - 5;
-        }
+        let (found, reports) = scan(Some(50_000));
+        assert!(!found);
+        assert_eq!(reports.last(), Some(&(50_000, 50_000)));
 
-        max_size
+        let (found, reports) = scan(None);
+        assert!(found);
+        assert_eq!(reports.first(), Some(&(0, 50_200)));
+        assert_eq!(reports.last(), Some(&(50_200, 50_200)));
     }
 
-    /// Get cluster size for recovery
-    pub fn cluster_size(&self) -> u32 {
-        self.fat_table.cluster_size()
+    fn scan_stats(device: &BlockDevice) -> ExFatScanStats {
+        let boot_sector = ExFatBootSector::parse(device.read_sector(0).unwrap()).unwrap();
+        let engine = ExFatRecoveryEngine::new(device, boot_sector).unwrap();
+        engine.scan_deleted_files_with_stats().unwrap().1
     }
 
-    /// Get cluster offset for recovery
-    pub fn cluster_offset(&self, cluster: u32) -> u64 {
-        self.fat_table.cluster_offset(cluster)
+    #[test]
+    fn test_self_referential_chain_is_detected() {
+        // The root chain 2 -> 3 -> 2 loops, and "up" points back at the root
+        let device = exfat_image(&[(2, 3), (3, 2)], &[(2, subdirectory_entry("up", 2))]);
+        let stats = scan_stats(&device);
+
+        assert_eq!(stats.fat_chain_loops, 1);
+        assert_eq!(stats.directory_loops, 1);
+        assert!(!stats.is_complete());
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn test_deep_nesting_is_reported() {
+        let eoc = crate::fs::exfat::fat::FAT_ENTRY_EOC_MAX;
+        let mut fat = vec![(2, eoc)];
+        let mut clusters = vec![(2, subdirectory_entry("d", 4))];
+        for cluster in 4..4 + MAX_DIRECTORY_DEPTH as u32 + 2 {
+            fat.push((cluster, eoc));
+            clusters.push((cluster, subdirectory_entry("d", cluster + 1)));
+        }
+        let stats = scan_stats(&exfat_image(&fat, &clusters));
+
+        assert_eq!(stats.truncated_subtrees, 1);
+        assert_eq!(stats.directory_loops, 0);
+
+        let shallow = exfat_image(&[(2, eoc), (4, eoc)], &[(2, subdirectory_entry("d", 4))]);
+        assert!(scan_stats(&shallow).is_complete());
+    }
 
     #[test]
     fn test_file_type_detection() {
@@ -0,0 +1,257 @@
+/// Per-filesystem health summary: space usage, fragmentation and recovery prospects
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{DeletedFile, FileSystemType};
+
+/// Integrity of a file system whose superblock is consistent and cleanly unmounted
+const CLEAN_INTEGRITY: f32 = 0.9;
+
+/// Integrity of a dirty volume, or one with unreplayed log writes
+const UNCLEAN_INTEGRITY: f32 = 0.75;
+
+/// Integrity when the superblock could not be read at all
+const UNREADABLE_INTEGRITY: f32 = 0.7;
+
+/// Free space assumed when the superblock does not record usage
+const UNKNOWN_FREE_FRACTION: f32 = 0.5;
+
+/// Weight of free space in the recovery prospects; fragmentation gets the rest
+const FREE_SPACE_WEIGHT: f32 = 0.6;
+
+/// Health of a scanned file system, from its superblock and the files found on it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FilesystemHealth {
+    pub fs_type: FileSystemType,
+    /// Size of the file system in bytes, if the superblock was readable
+    pub total_bytes: Option<u64>,
+    /// Fraction of the file system in use (0.0 - 1.0), if the superblock records it
+    pub used_fraction: Option<f32>,
+    /// Fraction of files with metadata whose data is split over several extents or cluster runs
+    pub fragmentation: f32,
+    /// Consistency of the file system metadata (0.0 - 1.0)
+    pub integrity: f32,
+    /// Overall likelihood that deleted data survived intact (0.0 - 1.0)
+    pub recovery_prospects: f32,
+}
+
+impl FilesystemHealth {
+    /// Health from superblock values; `clean` is false for a dirty volume or pending log
+    pub fn from_superblock(
+        fs_type: FileSystemType,
+        total_bytes: u64,
+        used_fraction: Option<f32>,
+        clean: bool,
+    ) -> Self {
+        let integrity = if clean {
+            CLEAN_INTEGRITY
+        } else {
+            UNCLEAN_INTEGRITY
+        };
+        Self::new(fs_type, Some(total_bytes), used_fraction, integrity)
+    }
+
+    /// Health of a file system whose superblock could not be read
+    pub fn unreadable(fs_type: FileSystemType) -> Self {
+        Self::new(fs_type, None, None, UNREADABLE_INTEGRITY)
+    }
+
+    fn new(
+        fs_type: FileSystemType,
+        total_bytes: Option<u64>,
+        used_fraction: Option<f32>,
+        integrity: f32,
+    ) -> Self {
+        let mut health = Self {
+            fs_type,
+            total_bytes,
+            used_fraction: used_fraction.map(|used| used.clamp(0.0, 1.0)),
+            fragmentation: 0.0,
+            integrity,
+            recovery_prospects: 0.0,
+        };
+        health.recovery_prospects = health.compute_prospects();
+        health
+    }
+
+    /// Estimate fragmentation from the extents of files found through file system metadata
+    ///
+    /// Carved files are skipped, as signature scanning only ever yields a
+    /// single contiguous run.
+    pub fn with_files(mut self, files: &[DeletedFile]) -> Self {
+        let mapped: Vec<_> = files
+            .iter()
+            .filter(|file| file.fs_metadata.is_some() && !file.data_blocks.is_empty())
+            .collect();
+        self.fragmentation = if mapped.is_empty() {
+            0.0
+        } else {
+            let fragmented = mapped.iter().filter(|f| f.data_blocks.len() > 1).count();
+            fragmented as f32 / mapped.len() as f32
+        };
+        self.recovery_prospects = self.compute_prospects();
+        self
+    }
+
+    /// Fraction of the file system that is free, if known
+    pub fn free_fraction(&self) -> Option<f32> {
+        self.used_fraction.map(|used| 1.0 - used)
+    }
+
+    /// Free blocks are less likely to have been overwritten and contiguous
+    /// files are easier to reassemble, scaled by how far the metadata can be trusted
+    fn compute_prospects(&self) -> f32 {
+        let free = self.free_fraction().unwrap_or(UNKNOWN_FREE_FRACTION);
+        let contiguity = 1.0 - self.fragmentation;
+        self.integrity * (FREE_SPACE_WEIGHT * free + (1.0 - FREE_SPACE_WEIGHT) * contiguity)
+    }
+
+    /// Short rating of the recovery prospects
+    pub fn rating(&self) -> &'static str {
+        match self.recovery_prospects {
+            p if p >= 0.7 => "Good",
+            p if p >= 0.4 => "Fair",
+            _ => "Poor",
+        }
+    }
+}
+
+impl fmt::Display for FilesystemHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} Filesystem Health", self.fs_type)?;
+        match self.total_bytes {
+            Some(bytes) => writeln!(f, "- Size: {} MB", bytes / (1024 * 1024))?,
+            None => writeln!(f, "- Size: unknown")?,
+        }
+        match self.used_fraction {
+            Some(used) => writeln!(
+                f,
+                "- Used Space: {:.1}% ({:.1}% free)",
+                used * 100.0,
+                (1.0 - used) * 100.0
+            )?,
+            None => writeln!(f, "- Used Space: unknown")?,
+        }
+        writeln!(
+            f,
+            "- Fragmentation: {:.1}% of files",
+            self.fragmentation * 100.0
+        )?;
+        writeln!(f, "- Integrity: {:.0}%", self.integrity * 100.0)?;
+        write!(
+            f,
+            "- Recovery Prospects: {:.0}% ({})",
+            self.recovery_prospects * 100.0,
+            self.rating()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockRange, ExFatFileMetadata, FileMetadata, FileType, FsSpecificMetadata};
+    use std::collections::HashMap;
+
+    fn file_with_extents(extents: u64) -> DeletedFile {
+        DeletedFile {
+            id: extents,
+            inode_or_cluster: 0,
+            original_path: None,
+            size: extents * 4096,
+            deletion_time: None,
+            confidence_score: 0.5,
+            file_type: FileType::RegularFile,
+            data_blocks: (0..extents)
+                .map(|i| BlockRange {
+                    start_block: i * 100,
+                    block_count: 1,
+                    is_allocated: false,
+                })
+                .collect(),
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: Some(FsSpecificMetadata::ExFat(ExFatFileMetadata {
+                first_cluster: 2,
+                cluster_chain: Vec::new(),
+                chain_valid: true,
+                utf16_valid: true,
+                entry_count: 3,
+                checksum: 0,
+                attributes: 0,
+            })),
+            skip_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_health_from_superblock_values() {
+        // 1 GiB Btrfs volume with 256 MiB used and no tree log
+        let total_bytes: u64 = 1 << 30;
+        let bytes_used: u64 = 1 << 28;
+        let health = FilesystemHealth::from_superblock(
+            FileSystemType::Btrfs,
+            total_bytes,
+            Some(bytes_used as f32 / total_bytes as f32),
+            true,
+        );
+
+        assert_eq!(health.total_bytes, Some(1 << 30));
+        assert_eq!(health.used_fraction, Some(0.25));
+        assert_eq!(health.integrity, CLEAN_INTEGRITY);
+        // 0.9 * (0.6 * 0.75 + 0.4 * 1.0)
+        assert!((health.recovery_prospects - 0.765).abs() < 1e-6);
+        assert_eq!(health.rating(), "Good");
+    }
+
+    #[test]
+    fn test_dirty_full_volume_has_poor_prospects() {
+        // exFAT reports usage as a whole percentage
+        let health =
+            FilesystemHealth::from_superblock(FileSystemType::ExFat, 64 << 20, Some(0.95), false);
+        assert_eq!(health.integrity, UNCLEAN_INTEGRITY);
+        assert!(health.recovery_prospects < 0.4);
+        assert_eq!(health.rating(), "Poor");
+    }
+
+    #[test]
+    fn test_fragmentation_from_extents() {
+        let files = vec![
+            file_with_extents(1),
+            file_with_extents(1),
+            file_with_extents(3),
+            file_with_extents(2),
+        ];
+        let health =
+            FilesystemHealth::from_superblock(FileSystemType::ExFat, 64 << 20, Some(0.5), true)
+                .with_files(&files);
+
+        assert_eq!(health.fragmentation, 0.5);
+        // 0.9 * (0.6 * 0.5 + 0.4 * 0.5)
+        assert!((health.recovery_prospects - 0.45).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unreadable_superblock() {
+        let health = FilesystemHealth::unreadable(FileSystemType::Xfs);
+        assert_eq!(health.total_bytes, None);
+        assert_eq!(health.free_fraction(), None);
+        assert_eq!(health.integrity, UNREADABLE_INTEGRITY);
+
+        let text = health.to_string();
+        assert!(text.contains("Used Space: unknown"));
+        assert!(text.contains("Recovery Prospects: 49% (Fair)"));
+    }
+}
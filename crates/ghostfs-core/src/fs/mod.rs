@@ -1,43 +1,333 @@
 /// File system detection and module organization
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use std::path::Path;
 
 pub mod btrfs;
 pub mod common;
 pub mod exfat;
+pub mod health;
 pub mod xfs;
 
 use crate::FileSystemType;
 use common::BlockDevice;
+pub use health::FilesystemHealth;
+
+/// Offset of the Btrfs primary superblock
+const BTRFS_SUPERBLOCK_OFFSET: u64 = 64 * 1024;
+
+/// Bytes read for the Btrfs superblock check
+const BTRFS_SUPERBLOCK_SIZE: usize = 4096;
+
+/// Boot signature at the end of an exFAT boot sector
+const BOOT_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+/// Offset of the ext2/3/4 superblock magic (0xEF53)
+const EXT_MAGIC_OFFSET: u64 = 1024 + 0x38;
+
+/// Block size assumed when the superblock cannot be read
+pub const DEFAULT_BLOCK_SIZE: u32 = 4096;
+
+/// A recognized file system, which GhostFS may or may not be able to recover from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedFileSystem {
+    /// Display name, e.g. "XFS" or "NTFS"
+    pub name: String,
+    /// Whether GhostFS can scan it
+    pub supported: bool,
+    /// The type to scan it as, for supported file systems
+    pub fs_type: Option<FileSystemType>,
+}
+
+impl DetectedFileSystem {
+    fn supported(fs_type: FileSystemType) -> Self {
+        Self {
+            name: fs_type.to_string(),
+            supported: true,
+            fs_type: Some(fs_type),
+        }
+    }
+
+    fn unsupported(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            supported: false,
+            fs_type: None,
+        }
+    }
+}
+
+impl std::fmt::Display for DetectedFileSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.supported {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{} (not yet supported)", self.name)
+        }
+    }
+}
 
 /// Detect file system type from device/image
+///
+/// Returns `Ok(None)` if no known superblock is present and an error only if
+/// the device could not be read. If several superblocks are present the
+/// first in Btrfs, exFAT, XFS order wins; use [`detect_filesystems`] to see
+/// all of them.
 pub fn detect_filesystem(device_path: &Path) -> Result<Option<FileSystemType>> {
-    let device = BlockDevice::open(device_path)?;
+    Ok(detect_filesystems(device_path)?.into_iter().next())
+}
 
-    // Try to detect file system by reading superblocks at known locations
+/// Every file system whose superblock is present on the device/image
+///
+/// More than one match usually means a disk was reformatted without wiping
+/// the old superblock.
+pub fn detect_filesystems(device_path: &Path) -> Result<Vec<FileSystemType>> {
+    let device = BlockDevice::open(device_path)?;
+    let mut found = Vec::new();
 
-    // Check for XFS (superblock at sector 0)
-    if let Ok(sector0) = device.read_sector(0) {
-        if xfs::is_xfs_superblock(sector0) {
-            return Ok(Some(FileSystemType::Xfs));
+    // Check for Btrfs (superblock at 64KB), which needs a device large enough to hold it
+    if device.size() >= BTRFS_SUPERBLOCK_OFFSET + BTRFS_SUPERBLOCK_SIZE as u64 {
+        let btrfs_sb = device
+            .read_at(BTRFS_SUPERBLOCK_OFFSET, BTRFS_SUPERBLOCK_SIZE)
+            .with_context(|| format!("Failed to read {}", device_path.display()))?;
+        if btrfs::is_btrfs_superblock(btrfs_sb) {
+            found.push(FileSystemType::Btrfs);
         }
     }
 
-    // Check for Btrfs (superblock at 64KB)
-    if let Ok(btrfs_sb) = device.read_bytes(65536, 4096) {
-        if btrfs::is_btrfs_superblock(btrfs_sb) {
-            return Ok(Some(FileSystemType::Btrfs));
+    // XFS and exFAT both start at sector 0, and only one of them can own it
+    let sector0 = device
+        .read_sector(0)
+        .with_context(|| format!("Failed to read {}", device_path.display()))?;
+    if let Some(fs_type) = classify_sector0(sector0) {
+        found.push(fs_type);
+    }
+
+    Ok(found)
+}
+
+/// The file system whose superblock or boot sector is in `sector0`, if any
+///
+/// exFAT is identified by its OEM name and boot signature, XFS by its magic
+/// and a sane block size. Each check rules out the other, so a sector can
+/// never match both.
+fn classify_sector0(sector0: &[u8]) -> Option<FileSystemType> {
+    if exfat::is_exfat_boot_sector(sector0) && sector0.get(510..512) == Some(&BOOT_SIGNATURE[..]) {
+        return Some(FileSystemType::ExFat);
+    }
+
+    if xfs::is_xfs_superblock(sector0) {
+        let block_size = u32::from_be_bytes([sector0[4], sector0[5], sector0[6], sector0[7]]);
+        if block_size.is_power_of_two() && (512..=65536).contains(&block_size) {
+            return Some(FileSystemType::Xfs);
         }
+        tracing::debug!("XFS magic with invalid block size {}", block_size);
     }
 
-    // Check for exFAT (boot sector at sector 0)
-    if let Ok(sector0) = device.read_sector(0) {
-        if exfat::is_exfat_boot_sector(sector0) {
-            return Ok(Some(FileSystemType::ExFat));
+    None
+}
+
+/// Block or cluster size recorded in the file system's superblock
+///
+/// XFS `sb_blocksize`, Btrfs `sectorsize` or exFAT bytes per cluster; `None`
+/// when the superblock is missing or holds an implausible size.
+pub fn detect_block_size(device: &BlockDevice, fs_type: FileSystemType) -> Option<u32> {
+    let block_size = match fs_type {
+        FileSystemType::Xfs => {
+            let sector0 = device.read_sector(0).ok()?;
+            if !xfs::is_xfs_superblock(sector0) {
+                return None;
+            }
+            u32::from_be_bytes(sector0.get(4..8)?.try_into().ok()?)
         }
+        FileSystemType::Btrfs => {
+            device
+                .read_at(BTRFS_SUPERBLOCK_OFFSET, BTRFS_SUPERBLOCK_SIZE)
+                .and_then(btrfs::BtrfsSuperblock::parse)
+                .ok()?
+                .sectorsize
+        }
+        FileSystemType::ExFat => {
+            let boot = device
+                .read_sector(0)
+                .and_then(exfat::ExFatBootSector::parse)
+                .ok()?;
+            // Shifts straight from disk, so garbage must not overflow
+            let shift = boot.bytes_per_sector_shift as u32 + boot.sectors_per_cluster_shift as u32;
+            1u32.checked_shl(shift)?
+        }
+    };
+
+    // exFAT allows clusters of up to 32MB
+    if block_size.is_power_of_two() && (512..=32 * 1024 * 1024).contains(&block_size) {
+        Some(block_size)
+    } else {
+        tracing::debug!("Ignoring implausible {} block size {}", fs_type, block_size);
+        None
+    }
+}
+
+/// Boundaries file data starts on: every `block_size` bytes from `origin`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataAlignment {
+    pub fs_type: FileSystemType,
+    pub block_size: u64,
+    /// Device offset of the first block; the cluster heap for exFAT
+    pub origin: u64,
+}
+
+/// Where file data can start on the device, from the first superblock found
+///
+/// Superblocks are tried in Btrfs, exFAT, XFS order as in
+/// [`detect_filesystem`]. XFS and Btrfs blocks count from the start of the
+/// device, exFAT clusters from the cluster heap.
+pub fn detect_data_alignment(device: &BlockDevice) -> Option<DataAlignment> {
+    [
+        FileSystemType::Btrfs,
+        FileSystemType::ExFat,
+        FileSystemType::Xfs,
+    ]
+    .into_iter()
+    .find_map(|fs_type| {
+        let block_size = detect_block_size(device, fs_type)?;
+        let origin = match fs_type {
+            FileSystemType::ExFat => {
+                let boot = device
+                    .read_sector(0)
+                    .and_then(exfat::ExFatBootSector::parse)
+                    .ok()?;
+                (boot.cluster_heap_offset as u64).checked_shl(boot.bytes_per_sector_shift as u32)?
+            }
+            FileSystemType::Xfs | FileSystemType::Btrfs => 0,
+        };
+        Some(DataAlignment {
+            fs_type,
+            block_size: block_size as u64,
+            origin,
+        })
+    })
+}
+
+/// Identify the file system on a device/image, including ones GhostFS cannot scan
+///
+/// Supported file systems are detected as in [`detect_filesystem`]; failing
+/// that, NTFS, FAT and ext2/3/4 are recognized so callers can say what the
+/// device holds instead of reporting it as unknown.
+pub fn identify_filesystem(device_path: &Path) -> Result<Option<DetectedFileSystem>> {
+    if let Some(fs_type) = detect_filesystem(device_path)? {
+        return Ok(Some(DetectedFileSystem::supported(fs_type)));
     }
 
-    Ok(None)
+    let device = BlockDevice::open(device_path)?;
+    let sector0 = device
+        .read_sector(0)
+        .with_context(|| format!("Failed to read {}", device_path.display()))?;
+    let name = recognize_unsupported_sector0(sector0).or_else(|| {
+        // The ext superblock is past sector 0, so very small images cannot hold one
+        let superblock = device.read_at(1024, 1024).ok()?;
+        recognize_ext(superblock)
+    });
+    Ok(name.map(DetectedFileSystem::unsupported))
+}
+
+/// Name of an unsupported file system whose boot sector is `sector0`
+fn recognize_unsupported_sector0(sector0: &[u8]) -> Option<&'static str> {
+    if sector0.get(510..512) != Some(&BOOT_SIGNATURE[..]) {
+        return None;
+    }
+    if &sector0[3..11] == b"NTFS    " {
+        return Some("NTFS");
+    }
+    // FAT32 keeps its type string further into the boot sector than FAT12/16
+    if &sector0[82..90] == b"FAT32   " {
+        return Some("FAT32");
+    }
+    match &sector0[54..62] {
+        b"FAT12   " => Some("FAT12"),
+        b"FAT16   " => Some("FAT16"),
+        _ => None,
+    }
+}
+
+/// Name of the ext2/3/4 file system whose superblock is `superblock`
+fn recognize_ext(superblock: &[u8]) -> Option<&'static str> {
+    let at = (EXT_MAGIC_OFFSET - 1024) as usize;
+    if superblock.get(at..at + 2)? != 0xEF53u16.to_le_bytes() {
+        return None;
+    }
+    let le32 = |at: usize| {
+        u32::from_le_bytes([
+            superblock[at],
+            superblock[at + 1],
+            superblock[at + 2],
+            superblock[at + 3],
+        ])
+    };
+    const COMPAT_HAS_JOURNAL: u32 = 0x4;
+    const INCOMPAT_EXTENTS: u32 = 0x40;
+    const INCOMPAT_64BIT: u32 = 0x80;
+
+    if le32(0x60) & (INCOMPAT_EXTENTS | INCOMPAT_64BIT) != 0 {
+        Some("ext4")
+    } else if le32(0x5C) & COMPAT_HAS_JOURNAL != 0 {
+        Some("ext3")
+    } else {
+        Some("ext2")
+    }
+}
+
+/// Pick the file system to scan a device/image as
+///
+/// With `requested` unset the type is auto-detected, failing if there is no
+/// superblock or more than one. An explicit type is always honored, with a
+/// warning if detection disagrees.
+pub fn resolve_filesystem(
+    device_path: &Path,
+    requested: Option<FileSystemType>,
+) -> Result<FileSystemType> {
+    let Some(requested) = requested else {
+        return match detect_filesystems(device_path)?.as_slice() {
+            [fs_type] => {
+                tracing::info!("Auto-detected {} file system", fs_type);
+                Ok(*fs_type)
+            }
+            [] => match identify_filesystem(device_path)? {
+                Some(detected) => bail!(
+                    "{} holds {}, which GhostFS cannot recover from yet",
+                    device_path.display(),
+                    detected.name
+                ),
+                None => bail!(
+                    "Could not detect a supported file system on {}; specify the type explicitly",
+                    device_path.display()
+                ),
+            },
+            found => bail!(
+                "Found superblocks for {} on {}; specify the type explicitly",
+                found
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" and "),
+                device_path.display()
+            ),
+        };
+    };
+
+    // Detection only advises here, so its failures are not fatal
+    match detect_filesystems(device_path) {
+        Ok(found) if !found.is_empty() && !found.contains(&requested) => {
+            tracing::warn!(
+                "{} was requested but {} looks like {}; scanning as {}",
+                requested,
+                device_path.display(),
+                found[0],
+                requested
+            );
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("File system detection failed: {:#}", e),
+    }
+    Ok(requested)
 }
 
 /// Get human-readable file system information
@@ -50,3 +340,178 @@ pub fn get_filesystem_info(device_path: &Path, fs_type: FileSystemType) -> Resul
         FileSystemType::ExFat => exfat::get_filesystem_info(&device),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Bytes to write at an offset of a test image
+    type Patch<'a> = (usize, &'a [u8]);
+
+    fn image_with(patches: &[Patch]) -> tempfile::NamedTempFile {
+        let mut image = vec![0u8; 128 * 1024];
+        for (offset, bytes) in patches {
+            image[*offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&image).unwrap();
+        file
+    }
+
+    const BTRFS_SB: Patch = (65536 + 64, b"_BHRfS_M");
+    const EXFAT_BOOT: Patch = (3, b"EXFAT   ");
+    const EXFAT_BOOT_SIGNATURE: Patch = (510, &BOOT_SIGNATURE);
+    const XFS_SB: Patch = (0, &[b'X', b'F', b'S', b'B', 0, 0, 0x10, 0]);
+
+    fn detected(patches: &[Patch]) -> Option<FileSystemType> {
+        detect_filesystem(image_with(patches).path()).unwrap()
+    }
+
+    #[test]
+    fn test_detects_each_filesystem() {
+        assert_eq!(detected(&[XFS_SB]), Some(FileSystemType::Xfs));
+        assert_eq!(detected(&[BTRFS_SB]), Some(FileSystemType::Btrfs));
+        assert_eq!(
+            detected(&[EXFAT_BOOT, EXFAT_BOOT_SIGNATURE]),
+            Some(FileSystemType::ExFat)
+        );
+    }
+
+    #[test]
+    fn test_detects_block_size() {
+        let block_size = |patches: &[Patch], fs_type| {
+            let image = image_with(patches);
+            detect_block_size(&BlockDevice::open(image.path()).unwrap(), fs_type)
+        };
+
+        assert_eq!(block_size(&[XFS_SB], FileSystemType::Xfs), Some(4096));
+        // 512-byte sectors, 128 sectors per cluster
+        assert_eq!(
+            block_size(&[EXFAT_BOOT, (108, &[9, 7])], FileSystemType::ExFat),
+            Some(64 * 1024)
+        );
+        assert_eq!(
+            block_size(
+                &[BTRFS_SB, (65536 + 144, &16384u32.to_le_bytes())],
+                FileSystemType::Btrfs
+            ),
+            Some(16384)
+        );
+
+        // Missing superblocks and nonsense sizes are not guessed at
+        assert_eq!(block_size(&[], FileSystemType::Xfs), None);
+        assert_eq!(block_size(&[BTRFS_SB], FileSystemType::Btrfs), None);
+        assert_eq!(
+            block_size(&[EXFAT_BOOT, (108, &[200, 200])], FileSystemType::ExFat),
+            None
+        );
+    }
+
+    #[test]
+    fn test_garbage_is_unknown() {
+        assert_eq!(detected(&[]), None);
+        assert_eq!(detected(&[(0, &[0xA5; 4096])]), None);
+
+        // Magic alone is not enough
+        assert_eq!(detected(&[(0, b"XFSB")]), None);
+        assert_eq!(detected(&[EXFAT_BOOT]), None);
+    }
+
+    #[test]
+    fn test_read_failure_is_an_error() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"XFSB").unwrap();
+        assert!(detect_filesystem(file.path()).is_err());
+
+        // Too small for Btrfs but a whole sector is fine
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&[0u8; 512]).unwrap();
+        assert_eq!(detect_filesystem(file.path()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_auto_detects_single_filesystem() {
+        let image = image_with(&[BTRFS_SB]);
+        assert_eq!(
+            resolve_filesystem(image.path(), None).unwrap(),
+            FileSystemType::Btrfs
+        );
+
+        let image = image_with(&[EXFAT_BOOT, EXFAT_BOOT_SIGNATURE]);
+        assert_eq!(
+            resolve_filesystem(image.path(), None).unwrap(),
+            FileSystemType::ExFat
+        );
+    }
+
+    #[test]
+    fn test_auto_detect_rejects_unknown_and_ambiguous() {
+        let blank = image_with(&[]);
+        let err = resolve_filesystem(blank.path(), None).unwrap_err();
+        assert!(err.to_string().contains("Could not detect"));
+
+        let both = image_with(&[EXFAT_BOOT, EXFAT_BOOT_SIGNATURE, BTRFS_SB]);
+        assert_eq!(
+            detect_filesystems(both.path()).unwrap(),
+            vec![FileSystemType::Btrfs, FileSystemType::ExFat]
+        );
+        let err = resolve_filesystem(both.path(), None).unwrap_err();
+        assert!(err.to_string().contains("Btrfs and exFAT"));
+    }
+
+    fn identified(patches: &[Patch]) -> Option<DetectedFileSystem> {
+        identify_filesystem(image_with(patches).path()).unwrap()
+    }
+
+    #[test]
+    fn test_recognizes_unsupported_filesystems() {
+        const SIGNED: Patch = (510, &BOOT_SIGNATURE);
+        let cases: [(&[Patch], &str); 6] = [
+            (&[(3, b"NTFS    "), SIGNED], "NTFS"),
+            (&[(82, b"FAT32   "), SIGNED], "FAT32"),
+            (&[(54, b"FAT16   "), SIGNED], "FAT16"),
+            (&[(1080, &[0x53, 0xEF])], "ext2"),
+            (&[(1080, &[0x53, 0xEF]), (1024 + 0x5C, &[0x04])], "ext3"),
+            (&[(1080, &[0x53, 0xEF]), (1024 + 0x60, &[0x40])], "ext4"),
+        ];
+        for (patches, name) in cases {
+            let detected = identified(patches).unwrap();
+            assert_eq!(detected.name, name);
+            assert!(!detected.supported);
+            assert_eq!(detected.fs_type, None);
+        }
+
+        let ntfs = identified(&[(3, b"NTFS    "), SIGNED]).unwrap();
+        assert_eq!(ntfs.to_string(), "NTFS (not yet supported)");
+
+        // Without the boot signature the OEM ID is just bytes
+        assert_eq!(identified(&[(3, b"NTFS    ")]), None);
+        assert_eq!(identified(&[]), None);
+
+        let supported = identified(&[BTRFS_SB]).unwrap();
+        assert!(supported.supported);
+        assert_eq!(supported.fs_type, Some(FileSystemType::Btrfs));
+    }
+
+    #[test]
+    fn test_auto_detect_names_unsupported_filesystem() {
+        let ntfs = image_with(&[(3, b"NTFS    "), (510, &BOOT_SIGNATURE)]);
+        let err = resolve_filesystem(ntfs.path(), None).unwrap_err();
+        assert!(err.to_string().contains("holds NTFS"), "{}", err);
+    }
+
+    #[test]
+    fn test_explicit_type_is_honored() {
+        let image = image_with(&[BTRFS_SB]);
+        assert_eq!(
+            resolve_filesystem(image.path(), Some(FileSystemType::Xfs)).unwrap(),
+            FileSystemType::Xfs
+        );
+        let blank = image_with(&[]);
+        assert_eq!(
+            resolve_filesystem(blank.path(), Some(FileSystemType::ExFat)).unwrap(),
+            FileSystemType::ExFat
+        );
+    }
+}
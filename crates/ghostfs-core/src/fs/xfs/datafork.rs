@@ -0,0 +1,580 @@
+//! XFS data fork reader
+//!
+//! Where a file's data lives depends on the format of its inode's data fork:
+//! small files keep their bytes in the inode itself (local), most files list
+//! their extents in the inode (extents), and files with too many extents for
+//! that keep them in a B+tree (the bmbt) rooted in the inode (btree). This is
+//! the reader for each, chosen by the inode's [`XfsExtentFormat`].
+
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use super::log::fsblock_to_linear;
+use super::{
+    XFS_DINODE_CORE_SIZE, XFS_DINODE_FMT_BTREE, XFS_DINODE_FMT_EXTENTS, XFS_DINODE_FMT_LOCAL,
+    XFS_DINODE_FORKOFF_OFFSET, XFS_DINODE_SIZE_OFFSET, XFS_DINODE_V3_CORE_SIZE,
+};
+use crate::fs::common::BlockDevice;
+use crate::recovery::RecoveryError;
+use crate::XfsExtentFormat;
+
+/// Offset of `di_nextents`, the number of data fork extents, in the inode core
+const XFS_DINODE_NEXTENTS_OFFSET: usize = 76;
+
+/// bmbt block magic ("BMAP"), and with CRCs ("BMA3")
+const XFS_BMAP_MAGIC: u32 = 0x424D_4150;
+const XFS_BMAP_CRC_MAGIC: u32 = 0x424D_4133;
+
+/// Long-format btree block header, without and with CRCs
+const XFS_BTREE_LBLOCK_LEN: usize = 24;
+const XFS_BTREE_LBLOCK_CRC_LEN: usize = 72;
+
+/// Header of the bmbt root in the inode: level and record count
+const XFS_BMDR_HEADER_LEN: usize = 4;
+
+/// Packed extent record, and a key or pointer in a bmbt node
+const BMBT_REC_SIZE: usize = 16;
+const BMBT_KEY_SIZE: usize = 8;
+const BMBT_PTR_SIZE: usize = 8;
+
+/// Deepest bmbt XFS builds, root included
+const MAX_BMBT_LEVELS: u16 = 9;
+
+/// Geometry needed to follow data fork extents
+#[derive(Debug, Clone, Copy)]
+pub struct DataForkGeometry {
+    pub block_size: u32,
+    pub ag_blocks: u32,
+    /// log2 of the AG size rounded up, as AG numbers are encoded in block numbers
+    pub ag_block_log: u32,
+}
+
+/// One extent of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XfsExtent {
+    /// Block of the file the extent starts at
+    pub file_block: u64,
+    /// Linear device block the extent starts at
+    pub start_block: u64,
+    pub block_count: u64,
+    /// Preallocated but never written; reads as zeros
+    pub unwritten: bool,
+}
+
+/// Contents of a data fork
+#[derive(Debug, PartialEq, Eq)]
+pub enum DataFork<'a> {
+    /// The file's bytes, stored in the inode
+    Local(&'a [u8]),
+    /// The file's extents in file order, from the inode or its bmbt
+    Extents(Vec<XfsExtent>),
+}
+
+/// Extent format of an on-disk `di_format` value
+pub fn extent_format(di_format: u8) -> Option<XfsExtentFormat> {
+    match di_format {
+        XFS_DINODE_FMT_LOCAL => Some(XfsExtentFormat::Local),
+        XFS_DINODE_FMT_EXTENTS => Some(XfsExtentFormat::Extents),
+        XFS_DINODE_FMT_BTREE => Some(XfsExtentFormat::Btree),
+        _ => None,
+    }
+}
+
+/// Read the data fork of `inode_data` as stored in `format`
+pub fn read_data_fork<'a>(
+    device: &BlockDevice,
+    geometry: &DataForkGeometry,
+    inode_data: &'a [u8],
+    format: XfsExtentFormat,
+) -> Result<DataFork<'a>> {
+    let fork = data_fork_area(inode_data)?;
+    let mut extents = match format {
+        XfsExtentFormat::Local => {
+            let size = inode_size(inode_data);
+            let len = fork.len().min(size.min(usize::MAX as u64) as usize);
+            return Ok(DataFork::Local(&fork[..len]));
+        }
+        XfsExtentFormat::Extents => {
+            let count = be32(inode_data, XFS_DINODE_NEXTENTS_OFFSET) as usize;
+            let records = fork.len() / BMBT_REC_SIZE;
+            parse_extents(&fork[..count.min(records) * BMBT_REC_SIZE], geometry)
+        }
+        XfsExtentFormat::Btree => read_bmbt(device, geometry, fork)?,
+    };
+
+    extents.sort_by_key(|extent| extent.file_block);
+    Ok(DataFork::Extents(extents))
+}
+
+/// Write the contents of the file whose inode is `inode_data` to `out`
+///
+/// Holes and unwritten extents are written as zeros, as are unreadable
+/// blocks, which are logged. Data is copied a block at a time, so memory use
+/// does not grow with the file. Returns the bytes written, the inode's size.
+pub fn read_file_data<W: Write>(
+    device: &BlockDevice,
+    geometry: &DataForkGeometry,
+    inode_data: &[u8],
+    format: XfsExtentFormat,
+    out: &mut W,
+) -> Result<u64> {
+    let extents = match read_data_fork(device, geometry, inode_data, format)? {
+        DataFork::Local(data) => {
+            out.write_all(data)?;
+            return Ok(data.len() as u64);
+        }
+        DataFork::Extents(extents) => extents,
+    };
+
+    let size = inode_size(inode_data);
+    if size > device.size() {
+        bail!(
+            "Inode size {} is larger than the {}-byte device",
+            size,
+            device.size()
+        );
+    }
+
+    let block_size = geometry.block_size as u64;
+    let mut position = 0u64;
+    for extent in &extents {
+        let start = extent.file_block.saturating_mul(block_size).min(size);
+        if start < position {
+            continue; // Overlaps an earlier extent
+        }
+        write_zeros(out, start - position)?;
+        position = start;
+
+        for i in 0..extent.block_count {
+            if position >= size {
+                break;
+            }
+            let len = (size - position).min(block_size);
+            let block = extent.start_block.saturating_add(i);
+            if extent.unwritten {
+                write_zeros(out, len)?;
+            } else {
+                match device.read_at(block.saturating_mul(block_size), len as usize) {
+                    Ok(bytes) => out.write_all(bytes)?,
+                    Err(e) => {
+                        tracing::warn!("Zero-filling unreadable block {}: {}", block, e);
+                        write_zeros(out, len)?;
+                    }
+                }
+            }
+            position += len;
+        }
+    }
+    write_zeros(out, size - position)?;
+    Ok(size)
+}
+
+/// Write `len` zero bytes to `out`
+fn write_zeros<W: Write>(out: &mut W, len: u64) -> Result<()> {
+    std::io::copy(&mut std::io::repeat(0).take(len), out)?;
+    Ok(())
+}
+
+/// The data fork area of the inode: the literal area up to the attribute fork
+fn data_fork_area(inode_data: &[u8]) -> Result<&[u8]> {
+    if inode_data.len() < XFS_DINODE_CORE_SIZE {
+        bail!("Inode of {} bytes has no data fork", inode_data.len());
+    }
+    let start = if inode_data[4] == 3 {
+        XFS_DINODE_V3_CORE_SIZE
+    } else {
+        XFS_DINODE_CORE_SIZE
+    };
+    let end = match inode_data[XFS_DINODE_FORKOFF_OFFSET] as usize {
+        0 => inode_data.len(),
+        forkoff => (start + forkoff * 8).min(inode_data.len()),
+    };
+    match inode_data.get(start..end) {
+        Some(fork) => Ok(fork),
+        None => bail!("Inode of {} bytes has no data fork", inode_data.len()),
+    }
+}
+
+/// Walk the bmbt rooted in `root`, the data fork of a btree-format inode
+fn read_bmbt(
+    device: &BlockDevice,
+    geometry: &DataForkGeometry,
+    root: &[u8],
+) -> Result<Vec<XfsExtent>> {
+    if root.len() < XFS_BMDR_HEADER_LEN + BMBT_KEY_SIZE + BMBT_PTR_SIZE {
+        bail!(
+            "Data fork of {} bytes too small for a bmbt root",
+            root.len()
+        );
+    }
+    let level = u16::from_be_bytes([root[0], root[1]]);
+    let numrecs = u16::from_be_bytes([root[2], root[3]]) as usize;
+    if level == 0 || level >= MAX_BMBT_LEVELS {
+        bail!("Implausible bmbt root level {}", level);
+    }
+
+    // Keys first, then pointers, laid out for as many records as the fork holds
+    let max_records = (root.len() - XFS_BMDR_HEADER_LEN) / (BMBT_KEY_SIZE + BMBT_PTR_SIZE);
+    let pointers_offset = XFS_BMDR_HEADER_LEN + max_records * BMBT_KEY_SIZE;
+
+    let mut extents = Vec::new();
+    let mut visited = HashSet::new();
+    for i in 0..numrecs.min(max_records) {
+        let child = be64(root, pointers_offset + i * BMBT_PTR_SIZE);
+        walk_bmbt(
+            device,
+            geometry,
+            child,
+            level - 1,
+            &mut visited,
+            &mut extents,
+        )?;
+    }
+    Ok(extents)
+}
+
+/// Add the extents under bmbt block `fsblock` to `extents`
+///
+/// `visited` holds the blocks walked so far: in a damaged tree pointers can
+/// lead back to a block already read, and following them could take
+/// exponentially many reads.
+fn walk_bmbt(
+    device: &BlockDevice,
+    geometry: &DataForkGeometry,
+    fsblock: u64,
+    expected_level: u16,
+    visited: &mut HashSet<u64>,
+    extents: &mut Vec<XfsExtent>,
+) -> Result<()> {
+    let block_size = geometry.block_size as usize;
+    if block_size < XFS_BTREE_LBLOCK_CRC_LEN + BMBT_KEY_SIZE + BMBT_PTR_SIZE {
+        bail!("Block size {} too small for a bmbt block", block_size);
+    }
+    let location = fsblock_to_linear(fsblock, geometry.ag_blocks as u64, geometry.ag_block_log)
+        .and_then(|block| Some((block, block.checked_mul(geometry.block_size as u64)?)));
+    let Some((block_number, block_offset)) = location else {
+        return Err(RecoveryError::corrupted(
+            0,
+            format!("bmbt pointer to block {} is out of range", fsblock),
+        )
+        .into());
+    };
+    if !visited.insert(block_number) {
+        return Err(RecoveryError::corrupted(
+            block_offset,
+            format!("bmbt block {} is referenced more than once", block_number),
+        )
+        .into());
+    }
+    let block = device.read_block(block_number, geometry.block_size)?;
+
+    let header_len = match be32(block, 0) {
+        XFS_BMAP_MAGIC => XFS_BTREE_LBLOCK_LEN,
+        XFS_BMAP_CRC_MAGIC => XFS_BTREE_LBLOCK_CRC_LEN,
+        magic => {
+            return Err(RecoveryError::corrupted(
+                block_offset,
+                format!(
+                    "Bad bmbt block magic 0x{:08x} at block {}",
+                    magic, block_number
+                ),
+            )
+            .into())
+        }
+    };
+
+    let level = u16::from_be_bytes([block[4], block[5]]);
+    let numrecs = u16::from_be_bytes([block[6], block[7]]) as usize;
+    if level != expected_level {
+        return Err(RecoveryError::corrupted(
+            block_offset + 4,
+            format!(
+                "bmbt block {} has level {}, expected {}",
+                block_number, level, expected_level
+            ),
+        )
+        .into());
+    }
+
+    if level == 0 {
+        let max_records = (block_size - header_len) / BMBT_REC_SIZE;
+        let records = &block[header_len..header_len + numrecs.min(max_records) * BMBT_REC_SIZE];
+        extents.extend(parse_extents(records, geometry));
+        return Ok(());
+    }
+
+    let max_records = (block_size - header_len) / (BMBT_KEY_SIZE + BMBT_PTR_SIZE);
+    let pointers_offset = header_len + max_records * BMBT_KEY_SIZE;
+    for i in 0..numrecs.min(max_records) {
+        let child = be64(block, pointers_offset + i * BMBT_PTR_SIZE);
+        walk_bmbt(device, geometry, child, level - 1, visited, extents)?;
+    }
+
+    Ok(())
+}
+
+/// Decode packed extent records (big-endian, 16 bytes each)
+///
+/// Bit 127 flags an unwritten extent, bits 73-126 hold the file block,
+/// bits 21-72 the filesystem block and bits 0-20 the length.
+fn parse_extents(records: &[u8], geometry: &DataForkGeometry) -> Vec<XfsExtent> {
+    records
+        .chunks_exact(BMBT_REC_SIZE)
+        .filter_map(|record| {
+            let high = be64(record, 0);
+            let low = be64(record, 8);
+            let block_count = low & 0x1F_FFFF;
+            let fsblock = ((high & 0x1FF) << 43) | (low >> 21);
+            let start_block =
+                fsblock_to_linear(fsblock, geometry.ag_blocks as u64, geometry.ag_block_log)?;
+            (block_count > 0).then_some(XfsExtent {
+                file_block: (high & !(1 << 63)) >> 9,
+                start_block,
+                block_count,
+                unwritten: high >> 63 == 1,
+            })
+        })
+        .collect()
+}
+
+fn inode_size(inode_data: &[u8]) -> u64 {
+    be64(inode_data, XFS_DINODE_SIZE_OFFSET)
+}
+
+fn be32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn be64(data: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: usize = 4096;
+
+    /// One 16-block AG, or two 8-block AGs addressed with 3 bits
+    const ONE_AG: DataForkGeometry = DataForkGeometry {
+        block_size: BLOCK as u32,
+        ag_blocks: 16,
+        ag_block_log: 4,
+    };
+
+    /// A packed extent record
+    fn record(file_block: u64, fsblock: u64, count: u64, unwritten: bool) -> [u8; 16] {
+        let high = (unwritten as u64) << 63 | file_block << 9 | fsblock >> 43;
+        let low = (fsblock & ((1 << 43) - 1)) << 21 | count;
+        let mut record = [0u8; 16];
+        record[..8].copy_from_slice(&high.to_be_bytes());
+        record[8..].copy_from_slice(&low.to_be_bytes());
+        record
+    }
+
+    /// 512-byte v3 inode of `size` bytes in `di_format` `format`
+    fn inode(format: u8, size: u64, fork: &[u8]) -> Vec<u8> {
+        let mut inode = vec![0u8; 512];
+        inode[0..2].copy_from_slice(&0x494Eu16.to_be_bytes());
+        inode[2..4].copy_from_slice(&0x81A4u16.to_be_bytes());
+        inode[4] = 3;
+        inode[5] = format;
+        inode[XFS_DINODE_SIZE_OFFSET..XFS_DINODE_SIZE_OFFSET + 8]
+            .copy_from_slice(&size.to_be_bytes());
+        let start = XFS_DINODE_V3_CORE_SIZE;
+        inode[start..start + fork.len()].copy_from_slice(fork);
+        inode
+    }
+
+    /// 16-block device whose block `n` is filled with byte `n`
+    fn device() -> BlockDevice {
+        BlockDevice::from_bytes((0..16 * BLOCK).map(|i| (i / BLOCK) as u8).collect())
+    }
+
+    /// What [`read_file_data`] writes for the inode
+    fn contents(
+        device: &BlockDevice,
+        geometry: &DataForkGeometry,
+        inode_data: &[u8],
+        format: XfsExtentFormat,
+    ) -> Vec<u8> {
+        let mut contents = Vec::new();
+        let written = read_file_data(device, geometry, inode_data, format, &mut contents).unwrap();
+        assert_eq!(written, contents.len() as u64);
+        contents
+    }
+
+    #[test]
+    fn test_local_format_reads_inline_bytes() {
+        let mut data = inode(XFS_DINODE_FMT_LOCAL, 11, b"hello world, and more");
+        // Attribute fork right behind the data
+        data[XFS_DINODE_FORKOFF_OFFSET] = 2;
+
+        let fork = read_data_fork(&device(), &ONE_AG, &data, XfsExtentFormat::Local).unwrap();
+        assert_eq!(fork, DataFork::Local(b"hello world"));
+        assert_eq!(
+            contents(&device(), &ONE_AG, &data, XfsExtentFormat::Local),
+            b"hello world"
+        );
+    }
+
+    #[test]
+    fn test_extents_format_walks_extent_list() {
+        // Blocks 0-1 at block 5, a hole, then block 3 at block 9 (unwritten
+        // block 4 behind it); listed out of order, with a stale fourth record
+        let mut fork = Vec::new();
+        fork.extend_from_slice(&record(3, 9, 1, false));
+        fork.extend_from_slice(&record(0, 5, 2, false));
+        fork.extend_from_slice(&record(4, 12, 1, true));
+        fork.extend_from_slice(&record(8, 14, 1, false));
+        let size = 4 * BLOCK as u64 + 100;
+        let mut data = inode(XFS_DINODE_FMT_EXTENTS, size, &fork);
+        data[XFS_DINODE_NEXTENTS_OFFSET..XFS_DINODE_NEXTENTS_OFFSET + 4]
+            .copy_from_slice(&3u32.to_be_bytes());
+
+        let fork = read_data_fork(&device(), &ONE_AG, &data, XfsExtentFormat::Extents).unwrap();
+        let DataFork::Extents(extents) = fork else {
+            panic!("expected extents, got {:?}", fork);
+        };
+        let layout: Vec<(u64, u64, u64, bool)> = extents
+            .iter()
+            .map(|e| (e.file_block, e.start_block, e.block_count, e.unwritten))
+            .collect();
+        assert_eq!(
+            layout,
+            vec![(0, 5, 2, false), (3, 9, 1, false), (4, 12, 1, true)]
+        );
+
+        let contents = contents(&device(), &ONE_AG, &data, XfsExtentFormat::Extents);
+        assert_eq!(contents.len() as u64, size);
+        assert!(contents[..BLOCK].iter().all(|&b| b == 5));
+        assert!(contents[BLOCK..2 * BLOCK].iter().all(|&b| b == 6));
+        assert!(contents[2 * BLOCK..3 * BLOCK].iter().all(|&b| b == 0));
+        assert!(contents[3 * BLOCK..4 * BLOCK].iter().all(|&b| b == 9));
+        assert!(contents[4 * BLOCK..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_extent_blocks_are_decoded_per_ag() {
+        let two_ags = DataForkGeometry {
+            ag_blocks: 8,
+            ag_block_log: 3,
+            ..ONE_AG
+        };
+        // AG 1, block 2 is linear block 10
+        let mut data = inode(XFS_DINODE_FMT_EXTENTS, 10, &record(0, 1 << 3 | 2, 1, false));
+        data[XFS_DINODE_NEXTENTS_OFFSET + 3] = 1;
+
+        let contents = contents(&device(), &two_ags, &data, XfsExtentFormat::Extents);
+        assert_eq!(contents, vec![10; 10]);
+    }
+
+    /// Long-format bmbt block header at the start of `block`
+    fn put_bmbt_header(block: &mut [u8], magic: u32, level: u16, numrecs: u16) {
+        block[0..4].copy_from_slice(&magic.to_be_bytes());
+        block[4..6].copy_from_slice(&level.to_be_bytes());
+        block[6..8].copy_from_slice(&numrecs.to_be_bytes());
+    }
+
+    /// Inode whose bmbt root points at a node in block 1, over a v4 leaf in
+    /// block 2 and a v5 leaf in block 3, mapping file blocks 0-2 to blocks
+    /// 10, 11 and 13
+    fn btree_image() -> (Vec<u8>, Vec<u8>) {
+        let mut image: Vec<u8> = (0..16 * BLOCK).map(|i| (i / BLOCK) as u8).collect();
+
+        let node = &mut image[BLOCK..2 * BLOCK];
+        node.fill(0);
+        put_bmbt_header(node, XFS_BMAP_CRC_MAGIC, 1, 2);
+        let max_records = (BLOCK - XFS_BTREE_LBLOCK_CRC_LEN) / 16;
+        let pointers = XFS_BTREE_LBLOCK_CRC_LEN + max_records * 8;
+        node[pointers..pointers + 8].copy_from_slice(&2u64.to_be_bytes());
+        node[pointers + 8..pointers + 16].copy_from_slice(&3u64.to_be_bytes());
+
+        let leaf = &mut image[2 * BLOCK..3 * BLOCK];
+        leaf.fill(0);
+        put_bmbt_header(leaf, XFS_BMAP_MAGIC, 0, 1);
+        leaf[XFS_BTREE_LBLOCK_LEN..XFS_BTREE_LBLOCK_LEN + 16]
+            .copy_from_slice(&record(0, 10, 2, false));
+
+        let leaf = &mut image[3 * BLOCK..4 * BLOCK];
+        leaf.fill(0);
+        put_bmbt_header(leaf, XFS_BMAP_CRC_MAGIC, 0, 1);
+        leaf[XFS_BTREE_LBLOCK_CRC_LEN..XFS_BTREE_LBLOCK_CRC_LEN + 16]
+            .copy_from_slice(&record(2, 13, 1, false));
+
+        // Root: level 2, one pointer; the fork is the whole literal area
+        let mut root = vec![0u8; 512 - XFS_DINODE_V3_CORE_SIZE];
+        root[0..2].copy_from_slice(&2u16.to_be_bytes());
+        root[2..4].copy_from_slice(&1u16.to_be_bytes());
+        let max_records = (root.len() - XFS_BMDR_HEADER_LEN) / 16;
+        let pointers = XFS_BMDR_HEADER_LEN + max_records * 8;
+        root[pointers..pointers + 8].copy_from_slice(&1u64.to_be_bytes());
+
+        let size = 3 * BLOCK as u64 - 1;
+        (image, inode(XFS_DINODE_FMT_BTREE, size, &root))
+    }
+
+    #[test]
+    fn test_btree_format_traverses_bmbt() {
+        let (image, data) = btree_image();
+        let device = BlockDevice::from_bytes(image);
+
+        let fork = read_data_fork(&device, &ONE_AG, &data, XfsExtentFormat::Btree).unwrap();
+        let DataFork::Extents(extents) = fork else {
+            panic!("expected extents, got {:?}", fork);
+        };
+        let layout: Vec<(u64, u64, u64)> = extents
+            .iter()
+            .map(|e| (e.file_block, e.start_block, e.block_count))
+            .collect();
+        assert_eq!(layout, vec![(0, 10, 2), (2, 13, 1)]);
+
+        let contents = contents(&device, &ONE_AG, &data, XfsExtentFormat::Btree);
+        assert_eq!(contents.len(), 3 * BLOCK - 1);
+        assert!(contents[..BLOCK].iter().all(|&b| b == 10));
+        assert!(contents[BLOCK..2 * BLOCK].iter().all(|&b| b == 11));
+        assert!(contents[2 * BLOCK..].iter().all(|&b| b == 13));
+    }
+
+    #[test]
+    fn test_corrupt_bmbt_is_an_error() {
+        let (mut image, data) = btree_image();
+        // The second leaf overwritten
+        image[3 * BLOCK..3 * BLOCK + 4].copy_from_slice(b"XXXX");
+        let device = BlockDevice::from_bytes(image.clone());
+        let err = read_data_fork(&device, &ONE_AG, &data, XfsExtentFormat::Btree).unwrap_err();
+        assert!(err.to_string().contains("Bad bmbt block magic"), "{}", err);
+
+        // A node pointing back at itself, which the level check catches
+        let pointers = XFS_BTREE_LBLOCK_CRC_LEN + (BLOCK - XFS_BTREE_LBLOCK_CRC_LEN) / 16 * 8;
+        image[BLOCK + pointers..BLOCK + pointers + 8].copy_from_slice(&1u64.to_be_bytes());
+        image[BLOCK + 4..BLOCK + 6].copy_from_slice(&1u16.to_be_bytes());
+        let device = BlockDevice::from_bytes(image);
+        assert!(read_data_fork(&device, &ONE_AG, &data, XfsExtentFormat::Btree).is_err());
+    }
+
+    #[test]
+    fn test_bmbt_revisiting_a_block_is_an_error() {
+        let (mut image, data) = btree_image();
+        // Both pointers of the node lead to the first leaf
+        let pointers = XFS_BTREE_LBLOCK_CRC_LEN + (BLOCK - XFS_BTREE_LBLOCK_CRC_LEN) / 16 * 8;
+        image[BLOCK + pointers + 8..BLOCK + pointers + 16].copy_from_slice(&2u64.to_be_bytes());
+        let device = BlockDevice::from_bytes(image.clone());
+        let err = read_data_fork(&device, &ONE_AG, &data, XfsExtentFormat::Btree).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<RecoveryError>(),
+                Some(RecoveryError::Corrupted { offset, .. }) if *offset == 2 * BLOCK as u64
+            ),
+            "{}",
+            err
+        );
+
+        // A pointer whose AG number overflows the linear block number
+        image[BLOCK + pointers + 8..BLOCK + pointers + 16].copy_from_slice(&u64::MAX.to_be_bytes());
+        let device = BlockDevice::from_bytes(image);
+        let err = read_data_fork(&device, &ONE_AG, &data, XfsExtentFormat::Btree).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{}", err);
+    }
+}
@@ -0,0 +1,284 @@
+//! XFS free space btree reader
+//!
+//! Each allocation group header (AGF) roots a by-block-number btree (bnobt)
+//! whose leaf records list the free extents of that AG. Any block that is not
+//! in one of those extents is allocated, so a deleted inode whose extents
+//! overlap allocated space has had (some of) its data re-used.
+
+use anyhow::{bail, Result};
+
+use crate::fs::common::{BlockDevice, RangeSet};
+use crate::recovery::RecoveryError;
+
+/// AGF magic ("XAGF")
+const XFS_AGF_MAGIC: u32 = 0x5841_4746;
+/// bnobt block magic, v4 ("ABTB") and v5 with CRCs ("AB3B")
+const XFS_ABTB_MAGIC: u32 = 0x4142_5442;
+const XFS_ABTB_CRC_MAGIC: u32 = 0x4142_3342;
+
+/// Short-form btree block header sizes
+const XFS_BTREE_SBLOCK_LEN: usize = 16;
+const XFS_BTREE_SBLOCK_CRC_LEN: usize = 56;
+
+/// AGF field offsets
+const AGF_BNO_ROOT_OFFSET: usize = 16;
+const AGF_BNO_LEVEL_OFFSET: usize = 28;
+
+/// Bytes of the AGF header that are read (it always fits in the smallest sector)
+const AGF_READ_SIZE: usize = 512;
+
+/// Size of a bnobt record/key (startblock + blockcount) and of a node pointer
+const ALLOC_REC_SIZE: usize = 8;
+const ALLOC_PTR_SIZE: usize = 4;
+
+/// Deepest bnobt we are willing to follow
+const MAX_BTREE_LEVELS: u32 = 8;
+
+/// Geometry needed to locate the AG headers
+#[derive(Debug, Clone, Copy)]
+pub struct AgGeometry {
+    pub block_size: u32,
+    pub sector_size: u32,
+    pub ag_count: u32,
+    pub ag_blocks: u32,
+}
+
+/// Read the free extents of every AG as a set of linear block ranges
+///
+/// AGs whose AGF or btree cannot be read are skipped with a warning; their
+/// blocks are then reported as allocated, which errs on the side of caution.
+pub fn read_free_space(device: &BlockDevice, geometry: &AgGeometry) -> Result<RangeSet> {
+    let mut free_extents = Vec::new();
+    let mut readable_ags = 0;
+
+    for ag in 0..geometry.ag_count {
+        match read_ag_free_extents(device, geometry, ag) {
+            Ok(mut extents) => {
+                free_extents.append(&mut extents);
+                readable_ags += 1;
+            }
+            Err(e) => tracing::warn!("Could not read free space of AG {}: {}", ag, e),
+        }
+    }
+
+    if readable_ags == 0 {
+        bail!("No readable AGF headers");
+    }
+
+    Ok(RangeSet::from_ranges(free_extents))
+}
+
+fn read_ag_free_extents(
+    device: &BlockDevice,
+    geometry: &AgGeometry,
+    ag: u32,
+) -> Result<Vec<(u64, u64)>> {
+    let ag_start = ag as u64 * geometry.ag_blocks as u64;
+    let agf_offset = ag_start * geometry.block_size as u64 + geometry.sector_size as u64;
+    let agf = device.read_at(agf_offset, AGF_READ_SIZE)?;
+
+    if be32(agf, 0) != XFS_AGF_MAGIC {
+        return Err(RecoveryError::corrupted(agf_offset, "Bad AGF magic").into());
+    }
+
+    let root = be32(agf, AGF_BNO_ROOT_OFFSET);
+    let levels = be32(agf, AGF_BNO_LEVEL_OFFSET);
+    if levels == 0 || levels > MAX_BTREE_LEVELS || root >= geometry.ag_blocks {
+        return Err(RecoveryError::corrupted(
+            agf_offset + AGF_BNO_ROOT_OFFSET as u64,
+            format!("Implausible bnobt root {} with {} levels", root, levels),
+        )
+        .into());
+    }
+
+    let mut extents = Vec::new();
+    walk_bnobt(device, geometry, ag_start, root, levels - 1, &mut extents)?;
+    Ok(extents)
+}
+
+fn walk_bnobt(
+    device: &BlockDevice,
+    geometry: &AgGeometry,
+    ag_start: u64,
+    agbno: u32,
+    expected_level: u32,
+    extents: &mut Vec<(u64, u64)>,
+) -> Result<()> {
+    let block_size = geometry.block_size as usize;
+    if block_size < XFS_BTREE_SBLOCK_CRC_LEN + ALLOC_REC_SIZE + ALLOC_PTR_SIZE {
+        bail!("Block size {} too small for a bnobt block", block_size);
+    }
+    let block_number = ag_start + agbno as u64;
+    let block_offset = block_number * geometry.block_size as u64;
+    let block = device.read_block(block_number, geometry.block_size)?;
+
+    let header_len = match be32(block, 0) {
+        XFS_ABTB_MAGIC => XFS_BTREE_SBLOCK_LEN,
+        XFS_ABTB_CRC_MAGIC => XFS_BTREE_SBLOCK_CRC_LEN,
+        magic => {
+            return Err(RecoveryError::corrupted(
+                block_offset,
+                format!(
+                    "Bad bnobt block magic 0x{:08x} at AG block {}",
+                    magic, agbno
+                ),
+            )
+            .into())
+        }
+    };
+
+    let level = u16::from_be_bytes([block[4], block[5]]) as u32;
+    let numrecs = u16::from_be_bytes([block[6], block[7]]) as usize;
+    if level != expected_level {
+        return Err(RecoveryError::corrupted(
+            block_offset + 4,
+            format!(
+                "bnobt block {} has level {}, expected {}",
+                agbno, level, expected_level
+            ),
+        )
+        .into());
+    }
+
+    if level == 0 {
+        let max_records = (block_size - header_len) / ALLOC_REC_SIZE;
+        for i in 0..numrecs.min(max_records) {
+            let offset = header_len + i * ALLOC_REC_SIZE;
+            let start = be32(block, offset);
+            let count = be32(block, offset + 4);
+            if start < geometry.ag_blocks {
+                extents.push((ag_start + start as u64, count as u64));
+            }
+        }
+        return Ok(());
+    }
+
+    // Node: all keys first, then all pointers, laid out for the maximum record count
+    let max_records = (block_size - header_len) / (ALLOC_REC_SIZE + ALLOC_PTR_SIZE);
+    let pointers_offset = header_len + max_records * ALLOC_REC_SIZE;
+    for i in 0..numrecs.min(max_records) {
+        let pointer_offset = pointers_offset + i * ALLOC_PTR_SIZE;
+        let child = be32(block, pointer_offset);
+        if child >= geometry.ag_blocks {
+            return Err(RecoveryError::corrupted(
+                block_offset + pointer_offset as u64,
+                format!("bnobt pointer {} outside AG", child),
+            )
+            .into());
+        }
+        walk_bnobt(device, geometry, ag_start, child, level - 1, extents)?;
+    }
+
+    Ok(())
+}
+
+fn be32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: usize = 4096;
+
+    fn put_be32(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// One AG of 64 blocks: AGF in sector 1, bnobt root node at block 4 with
+    /// two leaves at blocks 5 and 6
+    fn build_image() -> Vec<u8> {
+        let mut image = vec![0u8; 64 * BLOCK];
+
+        let agf = &mut image[512..1024];
+        put_be32(agf, 0, XFS_AGF_MAGIC);
+        put_be32(agf, AGF_BNO_ROOT_OFFSET, 4);
+        put_be32(agf, AGF_BNO_LEVEL_OFFSET, 2);
+
+        // Root node (level 1, v5 header)
+        let node = &mut image[4 * BLOCK..5 * BLOCK];
+        put_be32(node, 0, XFS_ABTB_CRC_MAGIC);
+        node[4..6].copy_from_slice(&1u16.to_be_bytes());
+        node[6..8].copy_from_slice(&2u16.to_be_bytes());
+        let max_records = (BLOCK - XFS_BTREE_SBLOCK_CRC_LEN) / 12;
+        let pointers = XFS_BTREE_SBLOCK_CRC_LEN + max_records * 8;
+        put_be32(node, pointers, 5);
+        put_be32(node, pointers + 4, 6);
+
+        // Leaves: free extents [10, 14) and [40, 60)
+        for (block, start, count) in [(5, 10, 4), (6, 40, 20)] {
+            let leaf = &mut image[block * BLOCK..(block + 1) * BLOCK];
+            put_be32(leaf, 0, XFS_ABTB_CRC_MAGIC);
+            leaf[6..8].copy_from_slice(&1u16.to_be_bytes());
+            put_be32(leaf, XFS_BTREE_SBLOCK_CRC_LEN, start);
+            put_be32(leaf, XFS_BTREE_SBLOCK_CRC_LEN + 4, count);
+        }
+
+        image
+    }
+
+    #[test]
+    fn test_read_free_space() {
+        let device = BlockDevice::from_vec(build_image());
+        let geometry = AgGeometry {
+            block_size: BLOCK as u32,
+            sector_size: 512,
+            ag_count: 1,
+            ag_blocks: 64,
+        };
+
+        let free = read_free_space(&device, &geometry).unwrap();
+        assert_eq!(free.len(), 2);
+        assert!(!free.contains(9));
+        assert!(free.contains(10));
+        assert!(free.contains(13));
+        assert!(!free.contains(14));
+        assert!(free.contains(59));
+        assert!(!free.contains(60));
+    }
+
+    #[test]
+    fn test_missing_agf_is_an_error() {
+        let device = BlockDevice::from_vec(vec![0u8; 16 * BLOCK]);
+        let geometry = AgGeometry {
+            block_size: BLOCK as u32,
+            sector_size: 512,
+            ag_count: 1,
+            ag_blocks: 16,
+        };
+
+        assert!(read_free_space(&device, &geometry).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_bnobt_reports_offset() {
+        // The second leaf pointer of the root node leads outside the AG
+        let mut image = build_image();
+        let max_records = (BLOCK - XFS_BTREE_SBLOCK_CRC_LEN) / 12;
+        let pointer = 4 * BLOCK + XFS_BTREE_SBLOCK_CRC_LEN + max_records * 8 + 4;
+        put_be32(&mut image, pointer, 99);
+        let device = BlockDevice::from_vec(image);
+        let geometry = AgGeometry {
+            block_size: BLOCK as u32,
+            sector_size: 512,
+            ag_count: 1,
+            ag_blocks: 64,
+        };
+
+        let error = read_ag_free_extents(&device, &geometry, 0).unwrap_err();
+        match error.downcast_ref::<RecoveryError>() {
+            Some(RecoveryError::Corrupted { offset, detail }) => {
+                assert_eq!(*offset, pointer as u64);
+                assert_eq!(detail, "bnobt pointer 99 outside AG");
+            }
+            other => panic!("Expected a corruption error, got {:?}", other),
+        }
+        assert!(error.to_string().contains(&format!("0x{:x}", pointer)));
+    }
+}
@@ -0,0 +1,572 @@
+/// XFS log (journal) analysis
+///
+/// The XFS log is a circular buffer of log records. Each record starts with a
+/// header sector followed by a stream of log operations; inode changes are
+/// logged as an `XFS_LI_INODE` format region followed by a copy of the inode
+/// core. An unlink that drops the link count to zero leaves a logged inode
+/// core with `di_nlink == 0` whose ctime is the moment of deletion.
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use super::BlockDevice;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Log record header magic
+pub const XLOG_HEADER_MAGIC: u32 = 0xFEED_BABE;
+/// Log item type for inode changes
+pub const XFS_LI_INODE: u16 = 0x123b;
+
+/// Log basic block size
+const XLOG_BBSIZE: usize = 512;
+/// Bytes of record data covered by one header's cycle data array
+const XLOG_HEADER_CYCLE_SIZE: usize = 32 * 1024;
+/// Offset of `h_cycle_data` in the record header
+const XLOG_CYCLE_DATA_OFFSET: usize = 44;
+/// Offset of `h_size` (v2 logs) in the record header
+const XLOG_H_SIZE_OFFSET: usize = 320;
+/// Size of a log operation header
+const XLOG_OP_HEADER_SIZE: usize = 12;
+
+/// Log operation flags that carry no item data
+const XLOG_START_TRANS: u8 = 0x01;
+const XLOG_COMMIT_TRANS: u8 = 0x02;
+const XLOG_UNMOUNT_TRANS: u8 = 0x20;
+
+/// Data fork extents were logged after the inode core
+const XFS_ILOG_DEXT: u32 = 0x0004;
+
+/// Inode core magic ("IN")
+const XFS_DINODE_MAGIC: u16 = 0x494e;
+/// Minimum logged inode core needed to read nlink, timestamps and size
+const XFS_LOG_DINODE_MIN_SIZE: usize = 64;
+
+// ============================================================================
+// Structures
+// ============================================================================
+
+/// Inode state captured from a logged inode core
+#[derive(Debug, Clone, PartialEq)]
+pub struct XfsLoggedInode {
+    pub inode: u64,
+    pub mode: u16,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub atime: Option<DateTime<Utc>>,
+    pub mtime: Option<DateTime<Utc>>,
+    pub ctime: Option<DateTime<Utc>>,
+    /// Data fork extents as (filesystem block, block count)
+    pub extents: Vec<(u64, u64)>,
+    /// Log sequence number of the record the inode was logged in
+    pub lsn: u64,
+}
+
+impl XfsLoggedInode {
+    /// The last logged state has no links left
+    pub fn is_unlinked(&self) -> bool {
+        self.nlink == 0
+    }
+
+    /// Unlink updates ctime, so it records when the last link was removed
+    pub fn deletion_time(&self) -> Option<DateTime<Utc>> {
+        if self.is_unlinked() {
+            self.ctime
+        } else {
+            None
+        }
+    }
+}
+
+/// An inode log item whose regions are still being read
+struct PendingInodeItem {
+    inode: u64,
+    fields: u32,
+    little_endian: bool,
+    regions_seen: u16,
+    regions_total: u16,
+    logged: Option<usize>,
+}
+
+// ============================================================================
+// Log scanning
+// ============================================================================
+
+/// Locate and read the internal log described by the superblock
+pub fn read_internal_log(device: &BlockDevice) -> Result<&[u8]> {
+    let sb = device.read_at(0, 128)?;
+
+    let block_size = u32::from_be_bytes([sb[4], sb[5], sb[6], sb[7]]) as u64;
+    let log_start = u64::from_be_bytes([
+        sb[48], sb[49], sb[50], sb[51], sb[52], sb[53], sb[54], sb[55],
+    ]);
+    let ag_blocks = u32::from_be_bytes([sb[84], sb[85], sb[86], sb[87]]) as u64;
+    let log_blocks = u32::from_be_bytes([sb[96], sb[97], sb[98], sb[99]]) as u64;
+    let ag_block_log = sb[124] as u32;
+
+    if log_start == 0 {
+        bail!("XFS log is external; scan the log device with scan_log instead");
+    }
+    if block_size == 0 || log_blocks == 0 {
+        bail!("XFS superblock does not describe an internal log");
+    }
+
+    let Some(offset) = fsblock_to_linear(log_start, ag_blocks, ag_block_log)
+        .and_then(|block| block.checked_mul(block_size))
+    else {
+        bail!("XFS log start block {} is out of range", log_start);
+    };
+    let length = std::cmp::min(
+        log_blocks * block_size,
+        device.size().saturating_sub(offset),
+    );
+    device.read_at(offset, length as usize)
+}
+
+/// Convert an AG-encoded filesystem block number to a linear block number
+///
+/// Returns `None` if the result overflows, as garbage block numbers can.
+pub fn fsblock_to_linear(fsblock: u64, ag_blocks: u64, ag_block_log: u32) -> Option<u64> {
+    if ag_block_log == 0 || ag_block_log >= 64 {
+        return Some(fsblock);
+    }
+    let ag_no = fsblock >> ag_block_log;
+    let ag_block = fsblock & ((1u64 << ag_block_log) - 1);
+    ag_no.checked_mul(ag_blocks)?.checked_add(ag_block)
+}
+
+/// Scan the internal log and return inodes whose final logged state is unlinked,
+/// with data fork extents converted to linear blocks
+pub fn scan_journal(device: &BlockDevice) -> Result<Vec<XfsLoggedInode>> {
+    let sb = device.read_at(0, 128)?;
+    let ag_blocks = u32::from_be_bytes([sb[84], sb[85], sb[86], sb[87]]) as u64;
+    let ag_block_log = sb[124] as u32;
+
+    let mut unlinked = find_unlinked_inodes(read_internal_log(device)?);
+    for entry in &mut unlinked {
+        entry.extents.retain_mut(|extent| {
+            match fsblock_to_linear(extent.0, ag_blocks, ag_block_log) {
+                Some(block) => {
+                    extent.0 = block;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    tracing::info!(
+        "📓 XFS log analysis found {} unlinked inodes",
+        unlinked.len()
+    );
+    Ok(unlinked)
+}
+
+/// Inodes whose most recent logged state has `nlink == 0`, ordered by inode number
+pub fn find_unlinked_inodes(log: &[u8]) -> Vec<XfsLoggedInode> {
+    let mut latest: HashMap<u64, XfsLoggedInode> = HashMap::new();
+    for entry in scan_log(log) {
+        match latest.get(&entry.inode) {
+            Some(existing) if existing.lsn > entry.lsn => {}
+            _ => {
+                latest.insert(entry.inode, entry);
+            }
+        }
+    }
+
+    let mut unlinked: Vec<_> = latest
+        .into_values()
+        .filter(XfsLoggedInode::is_unlinked)
+        .collect();
+    unlinked.sort_by_key(|entry| entry.inode);
+    unlinked
+}
+
+/// Walk every log record in `log` and collect all logged inode cores in log order
+pub fn scan_log(log: &[u8]) -> Vec<XfsLoggedInode> {
+    let mut logged = Vec::new();
+    let mut pending: HashMap<u32, PendingInodeItem> = HashMap::new();
+    let mut pos = 0;
+
+    while pos + XLOG_BBSIZE <= log.len() {
+        if read_u32_be(log, pos) != XLOG_HEADER_MAGIC {
+            pos += XLOG_BBSIZE;
+            continue;
+        }
+
+        match read_record(log, pos) {
+            Some((data, lsn, num_ops, record_len)) => {
+                parse_operations(&data, lsn, num_ops, &mut pending, &mut logged);
+                pos += record_len;
+            }
+            None => pos += XLOG_BBSIZE,
+        }
+    }
+
+    logged
+}
+
+/// Read one log record at `pos`, restoring the words overwritten by cycle stamps.
+/// Returns the record data, its LSN, operation count and total on-disk length.
+fn read_record(log: &[u8], pos: usize) -> Option<(Vec<u8>, u64, u32, usize)> {
+    let version = read_u32_be(log, pos + 8);
+    let data_len = read_u32_be(log, pos + 12) as usize;
+    let lsn = read_u64_be(log, pos + 16);
+    let num_ops = read_u32_be(log, pos + 40);
+
+    // v2 logs with records larger than 32KB carry extended header sectors
+    let header_sectors = if version & 2 != 0 {
+        let h_size = read_u32_be(log, pos + XLOG_H_SIZE_OFFSET) as usize;
+        std::cmp::max(1, h_size.div_ceil(XLOG_HEADER_CYCLE_SIZE))
+    } else {
+        1
+    };
+
+    let data_sectors = data_len.div_ceil(XLOG_BBSIZE);
+    let data_start = pos + header_sectors * XLOG_BBSIZE;
+    let data_end = data_start + data_sectors * XLOG_BBSIZE;
+    if data_len == 0 || data_end > log.len() {
+        return None;
+    }
+
+    let mut data = log[data_start..data_end].to_vec();
+    let per_header = XLOG_HEADER_CYCLE_SIZE / XLOG_BBSIZE;
+    for sector in 0..data_sectors {
+        let (header, slot) = (sector / per_header, sector % per_header);
+        if header >= header_sectors {
+            break;
+        }
+        let saved = if header == 0 {
+            pos + XLOG_CYCLE_DATA_OFFSET + slot * 4
+        } else {
+            // xlog_rec_ext_header: xh_cycle followed by xh_cycle_data
+            pos + header * XLOG_BBSIZE + 4 + slot * 4
+        };
+        data[sector * XLOG_BBSIZE..sector * XLOG_BBSIZE + 4]
+            .copy_from_slice(&log[saved..saved + 4]);
+    }
+    data.truncate(data_len);
+
+    Some((data, lsn, num_ops, data_end - pos))
+}
+
+/// Decode the operations of one record, tracking inode items by transaction id
+fn parse_operations(
+    data: &[u8],
+    lsn: u64,
+    num_ops: u32,
+    pending: &mut HashMap<u32, PendingInodeItem>,
+    logged: &mut Vec<XfsLoggedInode>,
+) {
+    let mut offset = 0;
+
+    for _ in 0..num_ops {
+        if offset + XLOG_OP_HEADER_SIZE > data.len() {
+            break;
+        }
+        let tid = read_u32_be(data, offset);
+        let len = read_u32_be(data, offset + 4) as usize;
+        let flags = data[offset + 9];
+        let region_start = offset + XLOG_OP_HEADER_SIZE;
+        let region_end = region_start + len;
+        if region_end > data.len() {
+            break;
+        }
+        let region = &data[region_start..region_end];
+        offset = region_end;
+
+        if flags & (XLOG_START_TRANS | XLOG_UNMOUNT_TRANS) != 0 {
+            continue;
+        }
+        if flags & XLOG_COMMIT_TRANS != 0 {
+            pending.remove(&tid);
+            continue;
+        }
+
+        if let Some(item) = pending.get_mut(&tid) {
+            item.regions_seen += 1;
+            match item.regions_seen {
+                2 => {
+                    if let Some(mut entry) = parse_log_dinode(region, item.little_endian) {
+                        entry.inode = item.inode;
+                        entry.lsn = lsn;
+                        item.logged = Some(logged.len());
+                        logged.push(entry);
+                    }
+                }
+                3 if item.fields & XFS_ILOG_DEXT != 0 => {
+                    if let Some(index) = item.logged {
+                        logged[index].extents = parse_extent_records(region);
+                    }
+                }
+                _ => {}
+            }
+            if item.regions_seen >= item.regions_total {
+                pending.remove(&tid);
+            }
+            continue;
+        }
+
+        if let Some(item) = parse_inode_log_format(region) {
+            pending.insert(tid, item);
+        }
+    }
+}
+
+/// Parse an `xfs_inode_log_format` region (host byte order)
+fn parse_inode_log_format(region: &[u8]) -> Option<PendingInodeItem> {
+    if region.len() < 20 {
+        return None;
+    }
+
+    let little_endian = if u16::from_le_bytes([region[0], region[1]]) == XFS_LI_INODE {
+        true
+    } else if u16::from_be_bytes([region[0], region[1]]) == XFS_LI_INODE {
+        false
+    } else {
+        return None;
+    };
+
+    let regions_total = read_u16(region, 2, little_endian);
+    let fields = read_u32(region, 4, little_endian);
+    // The 32-bit layout packs ilf_ino directly after ilf_dsize
+    let inode = if region.len() == 52 {
+        read_u64(region, 12, little_endian)
+    } else if region.len() >= 24 {
+        read_u64(region, 16, little_endian)
+    } else {
+        return None;
+    };
+
+    Some(PendingInodeItem {
+        inode,
+        fields,
+        little_endian,
+        regions_seen: 1,
+        regions_total,
+        logged: None,
+    })
+}
+
+/// Parse an `xfs_log_dinode` region (host byte order)
+fn parse_log_dinode(region: &[u8], little_endian: bool) -> Option<XfsLoggedInode> {
+    if region.len() < XFS_LOG_DINODE_MIN_SIZE
+        || read_u16(region, 0, little_endian) != XFS_DINODE_MAGIC
+    {
+        return None;
+    }
+
+    let timestamp = |offset: usize| {
+        let seconds = read_u32(region, offset, little_endian) as i32;
+        let nanos = read_u32(region, offset + 4, little_endian);
+        if seconds > 0 {
+            DateTime::from_timestamp(seconds as i64, nanos.min(999_999_999))
+        } else {
+            None
+        }
+    };
+
+    Some(XfsLoggedInode {
+        inode: 0,
+        mode: read_u16(region, 2, little_endian),
+        uid: read_u32(region, 8, little_endian),
+        gid: read_u32(region, 12, little_endian),
+        nlink: read_u32(region, 16, little_endian),
+        atime: timestamp(32),
+        mtime: timestamp(40),
+        ctime: timestamp(48),
+        size: read_u64(region, 56, little_endian),
+        extents: Vec::new(),
+        lsn: 0,
+    })
+}
+
+/// Decode packed on-disk BMBT extent records (big-endian, 16 bytes each)
+pub(super) fn parse_extent_records(region: &[u8]) -> Vec<(u64, u64)> {
+    region
+        .chunks_exact(16)
+        .filter_map(|record| {
+            let high = read_u64_be(record, 0);
+            let low = read_u64_be(record, 8);
+            let start_block = ((high & 0x1FF) << 43) | (low >> 21);
+            let block_count = low & 0x1F_FFFF;
+            (block_count > 0).then_some((start_block, block_count))
+        })
+        .collect()
+}
+
+fn read_u16(data: &[u8], offset: usize, little_endian: bool) -> u16 {
+    let bytes = [data[offset], data[offset + 1]];
+    if little_endian {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize, little_endian: bool) -> u32 {
+    let bytes = [
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ];
+    if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize, little_endian: bool) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    if little_endian {
+        u64::from_le_bytes(bytes)
+    } else {
+        u64::from_be_bytes(bytes)
+    }
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    read_u32(data, offset, false)
+}
+
+fn read_u64_be(data: &[u8], offset: usize) -> u64 {
+    read_u64(data, offset, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TID: u32 = 0x0BAD_CAFE;
+
+    fn op(tid: u32, flags: u8, region: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&tid.to_be_bytes());
+        out.extend_from_slice(&(region.len() as u32).to_be_bytes());
+        out.push(0x69); // XFS_TRANSACTION client id
+        out.push(flags);
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(region);
+        out
+    }
+
+    fn inode_format(inode: u64, fields: u32, regions: u16) -> Vec<u8> {
+        let mut f = vec![0u8; 56];
+        f[0..2].copy_from_slice(&XFS_LI_INODE.to_le_bytes());
+        f[2..4].copy_from_slice(&regions.to_le_bytes());
+        f[4..8].copy_from_slice(&fields.to_le_bytes());
+        f[16..24].copy_from_slice(&inode.to_le_bytes());
+        f
+    }
+
+    fn log_dinode(nlink: u32, ctime: u32, size: u64) -> Vec<u8> {
+        let mut d = vec![0u8; 176];
+        d[0..2].copy_from_slice(&XFS_DINODE_MAGIC.to_le_bytes());
+        d[2..4].copy_from_slice(&0o100644u16.to_le_bytes());
+        d[16..20].copy_from_slice(&nlink.to_le_bytes());
+        d[40..44].copy_from_slice(&(ctime - 60).to_le_bytes());
+        d[48..52].copy_from_slice(&ctime.to_le_bytes());
+        d[56..64].copy_from_slice(&size.to_le_bytes());
+        d
+    }
+
+    fn extent_record(start_block: u64, block_count: u64) -> Vec<u8> {
+        let high = start_block >> 43;
+        let low = (start_block << 21) | block_count;
+        let mut r = high.to_be_bytes().to_vec();
+        r.extend_from_slice(&low.to_be_bytes());
+        r
+    }
+
+    /// Build a log record the way the kernel writes it: the first word of
+    /// every data sector is saved in the header and replaced by the cycle
+    fn log_record(lsn: u64, ops: &[Vec<u8>]) -> Vec<u8> {
+        let data: Vec<u8> = ops.concat();
+        let sectors = data.len().div_ceil(XLOG_BBSIZE);
+        let mut padded = data.clone();
+        padded.resize(sectors * XLOG_BBSIZE, 0);
+
+        let mut header = vec![0u8; XLOG_BBSIZE];
+        header[0..4].copy_from_slice(&XLOG_HEADER_MAGIC.to_be_bytes());
+        header[4..8].copy_from_slice(&1u32.to_be_bytes());
+        header[8..12].copy_from_slice(&2u32.to_be_bytes());
+        header[12..16].copy_from_slice(&(data.len() as u32).to_be_bytes());
+        header[16..24].copy_from_slice(&lsn.to_be_bytes());
+        header[40..44].copy_from_slice(&(ops.len() as u32).to_be_bytes());
+        header[320..324].copy_from_slice(&(XLOG_HEADER_CYCLE_SIZE as u32).to_be_bytes());
+
+        for sector in 0..sectors {
+            let word = sector * XLOG_BBSIZE;
+            let slot = XLOG_CYCLE_DATA_OFFSET + sector * 4;
+            header[slot..slot + 4].copy_from_slice(&padded[word..word + 4]);
+            padded[word..word + 4].copy_from_slice(&1u32.to_be_bytes());
+        }
+
+        header.extend_from_slice(&padded);
+        header
+    }
+
+    fn unlink_transaction(inode: u64, nlink: u32, ctime: u32) -> Vec<Vec<u8>> {
+        vec![
+            op(TID, XLOG_START_TRANS, &[]),
+            op(TID, 0, b"NART"),
+            op(TID, 0, &inode_format(inode, 0x1 | XFS_ILOG_DEXT, 3)),
+            op(TID, 0, &log_dinode(nlink, ctime, 8192)),
+            op(TID, 0, &extent_record(300, 2)),
+            op(TID, XLOG_COMMIT_TRANS, &[]),
+        ]
+    }
+
+    #[test]
+    fn test_unlinked_inode_from_log_segment() {
+        let log = log_record(0x1_0000_0002, &unlink_transaction(133, 0, 1_700_000_000));
+
+        let unlinked = find_unlinked_inodes(&log);
+        assert_eq!(unlinked.len(), 1);
+
+        let entry = &unlinked[0];
+        assert_eq!(entry.inode, 133);
+        assert_eq!(entry.size, 8192);
+        assert_eq!(entry.mode, 0o100644);
+        assert_eq!(entry.extents, vec![(300, 2)]);
+        assert_eq!(
+            entry.deletion_time(),
+            DateTime::from_timestamp(1_700_000_000, 0)
+        );
+        assert_eq!(entry.mtime, DateTime::from_timestamp(1_699_999_940, 0));
+    }
+
+    #[test]
+    fn test_relinked_inode_is_not_reported() {
+        let mut log = log_record(0x1_0000_0002, &unlink_transaction(133, 0, 1_700_000_000));
+        log.extend(log_record(
+            0x1_0000_0010,
+            &unlink_transaction(133, 1, 1_700_000_100),
+        ));
+        log.extend(log_record(
+            0x1_0000_0020,
+            &unlink_transaction(200, 0, 1_700_000_200),
+        ));
+
+        assert_eq!(scan_log(&log).len(), 3);
+
+        let unlinked = find_unlinked_inodes(&log);
+        assert_eq!(unlinked.len(), 1);
+        assert_eq!(unlinked[0].inode, 200);
+    }
+
+    #[test]
+    fn test_fsblock_to_linear() {
+        // AG 2, block 5 with 1000-block AGs addressed by 10 bits
+        assert_eq!(fsblock_to_linear((2 << 10) | 5, 1000, 10), Some(2005));
+        assert_eq!(fsblock_to_linear(42, 1000, 0), Some(42));
+        assert_eq!(fsblock_to_linear(u64::MAX, 1 << 20, 10), None);
+    }
+}
@@ -1,7 +1,16 @@
-use super::common::BlockDevice;
+use super::common::{BlockDevice, RangeSet};
+use crate::recovery::directory::{DirectoryReconstructor, XfsDirReconstructor};
+use crate::recovery::{signatures, CancellationToken, RecoveryError};
 use anyhow::Result;
 use chrono::DateTime;
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+pub mod datafork;
+pub mod freespace;
+pub mod log;
+pub mod xattr;
 
 const XFS_MAGIC: u32 = 0x58465342; // "XFSB" in big-endian
 const XFS_INODE_MAGIC: u16 = 0x494E; // "IN" in big-endian
@@ -9,6 +18,22 @@ const XFS_DINODE_FMT_EXTENTS: u8 = 1;
 const XFS_DINODE_FMT_BTREE: u8 = 2;
 const XFS_DINODE_FMT_LOCAL: u8 = 3;
 
+// Inode core field offsets
+const XFS_DINODE_SIZE_OFFSET: usize = 56;
+const XFS_DINODE_FORKOFF_OFFSET: usize = 82;
+const XFS_DINODE_INO_OFFSET: usize = 152;
+/// Data fork starts after the inode core: v1/v2 and v3 (with CRC) inodes
+const XFS_DINODE_CORE_SIZE: usize = 100;
+const XFS_DINODE_V3_CORE_SIZE: usize = 176;
+
+/// Blocks read per attribute fork extent, bounding the cost of a stale extent
+const MAX_ATTR_EXTENT_BLOCKS: u64 = 64;
+
+// Superblock version and feature bits
+const XFS_SB_VERSION_NUMBITS: u16 = 0x000F;
+const XFS_SB_VERSION_5: u16 = 5;
+const XFS_SB_VERSION2_FTYPE: u32 = 0x0000_0200;
+
 // XFS inode states (reserved for future use)
 const _XFS_INODE_GOOD: u16 = 0;
 const _XFS_INODE_FREE: u16 = 1;
@@ -31,6 +56,23 @@ pub struct XfsRecoveryConfig {
 
     /// Sample size for text detection
     pub text_sample_size: usize,
+
+    /// Also look for inodes at every inode-sized slot of the device, for
+    /// inode chunks the inode table walk does not reach
+    pub brute_force_inodes: bool,
+
+    /// Scan the inode tables of the allocation groups in parallel
+    pub parallel_ags: bool,
+
+    /// Only name and size the deleted inodes: their extents are not read,
+    /// their content is not examined and no signature scan runs
+    pub list_only: bool,
+
+    /// Carve files without a readable inode from their content
+    pub signature_scan: bool,
+
+    /// Signatures the signature scan identifies files by
+    pub signatures: signatures::SignatureSet,
 }
 
 impl Default for XfsRecoveryConfig {
@@ -40,6 +82,11 @@ impl Default for XfsRecoveryConfig {
             max_file_search_bytes: 10 * 1024 * 1024, // 10MB
             text_detection_threshold: 0.75, // 75% printable chars
             text_sample_size: 4096, // 4KB sample
+            brute_force_inodes: false,
+            parallel_ags: true,
+            list_only: false,
+            signature_scan: true,
+            signatures: signatures::SignatureSet::default(),
         }
     }
 }
@@ -82,6 +129,7 @@ pub struct XfsSuperblock {
     pub realtime_bitmap_blocks: u32,
     pub log_blocks: u32,
     pub version_num: u16,
+    pub features2: u32,
     pub sector_size: u16,
     pub inode_size: u16,
     pub inodes_per_block: u16,
@@ -96,12 +144,20 @@ pub struct XfsSuperblock {
     pub max_inode_percent: u8,
     pub inode_count: u64,
     pub inode_free_count: u64,
-    pub free_data_extents: u64,
+    pub free_data_blocks: u64,
     pub free_realtime_extents: u64,
 }
 
+impl XfsSuperblock {
+    /// Whether directory entries record the file type (always on v5)
+    pub fn has_ftype(&self) -> bool {
+        self.version_num & XFS_SB_VERSION_NUMBITS == XFS_SB_VERSION_5
+            || self.features2 & XFS_SB_VERSION2_FTYPE != 0
+    }
+}
+
 pub struct XfsRecoveryEngine {
-    device: BlockDevice,
+    device: Arc<BlockDevice>,
     superblock: Option<XfsSuperblock>,
     ag_count: u32,
     ag_blocks: u32,
@@ -110,19 +166,28 @@ pub struct XfsRecoveryEngine {
     inode_size: u16,
     inodes_per_block: u16,
     ag_inode_table_blocks: Vec<u64>, // Starting block of inode table for each AG
+    free_space: Option<RangeSet>,    // Free extents from the AGF btrees, if readable
     config: XfsRecoveryConfig,
+    cancellation: CancellationToken,
+    end_markers: signatures::EndMarkerScanner,
 }
 
 impl XfsRecoveryEngine {
-    pub fn new(device: BlockDevice) -> Result<Self> {
+    pub fn new(device: impl Into<Arc<BlockDevice>>) -> Result<Self> {
         Self::new_with_config(device, XfsRecoveryConfig::default())
     }
 
-    pub fn new_with_config(device: BlockDevice, config: XfsRecoveryConfig) -> Result<Self> {
+    /// Scan `device`, which may be shared with other readers through an `Arc`
+    pub fn new_with_config(
+        device: impl Into<Arc<BlockDevice>>,
+        config: XfsRecoveryConfig,
+    ) -> Result<Self> {
         tracing::info!("🔧 Initializing XFS Recovery Engine");
 
+        let end_markers =
+            signatures::EndMarkerScanner::default().with_signatures(config.signatures.clone());
         let mut engine = XfsRecoveryEngine {
-            device,
+            device: device.into(),
             superblock: None,
             ag_count: 4,
             ag_blocks: 1000,
@@ -131,7 +196,10 @@ impl XfsRecoveryEngine {
             inode_size: 256,
             inodes_per_block: 16,
             ag_inode_table_blocks: Vec::new(),
+            free_space: None,
             config,
+            cancellation: CancellationToken::new(),
+            end_markers,
         };
 
         // Parse the XFS superblock
@@ -155,6 +223,17 @@ impl XfsRecoveryEngine {
 
                 // Calculate inode table locations for each AG
                 engine.calculate_ag_inode_tables()?;
+
+                let geometry = freespace::AgGeometry {
+                    block_size: engine.block_size,
+                    sector_size: engine.sector_size,
+                    ag_count: engine.ag_count,
+                    ag_blocks: engine.ag_blocks,
+                };
+                match freespace::read_free_space(&engine.device, &geometry) {
+                    Ok(free_space) => engine.free_space = Some(free_space),
+                    Err(e) => tracing::warn!("Could not read XFS free space btrees: {}", e),
+                }
             }
             Err(e) => {
                 tracing::warn!("Failed to parse XFS superblock: {}", e);
@@ -180,11 +259,14 @@ impl XfsRecoveryEngine {
 
         let magic = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
         if magic != XFS_MAGIC {
-            anyhow::bail!(
-                "Invalid XFS magic: 0x{:08x}, expected 0x{:08x}",
-                magic,
-                XFS_MAGIC
-            );
+            return Err(RecoveryError::corrupted(
+                0,
+                format!(
+                    "Invalid XFS magic: 0x{:08x}, expected 0x{:08x}",
+                    magic, XFS_MAGIC
+                ),
+            )
+            .into());
         }
 
         let block_size = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
@@ -217,10 +299,8 @@ impl XfsRecoveryEngine {
 
         let ag_count = if data.len() >= 92 {
             u32::from_be_bytes([data[88], data[89], data[90], data[91]])
-        } else if ag_blocks > 0 {
-            data_blocks as u32 / ag_blocks
         } else {
-            4
+            (data_blocks as u32).checked_div(ag_blocks).unwrap_or(4)
         };
 
         let inode_size = if data.len() >= 106 {
@@ -229,11 +309,7 @@ impl XfsRecoveryEngine {
             256 // Default XFS inode size
         };
 
-        let inodes_per_block = if inode_size > 0 {
-            block_size as u16 / inode_size
-        } else {
-            16 // Safe default
-        };
+        let inodes_per_block = (block_size as u16).checked_div(inode_size).unwrap_or(16); // Safe default
 
         let sector_size = if data.len() >= 104 {
             u16::from_be_bytes([data[102], data[103]])
@@ -241,6 +317,27 @@ impl XfsRecoveryEngine {
             512 // Standard sector size
         };
 
+        let version_num = match u16::from_be_bytes([data[100], data[101]]) {
+            0 => 4, // Default version
+            version => version,
+        };
+        let features2 = u32::from_be_bytes([data[200], data[201], data[202], data[203]]);
+        // sb_fdblocks: free data blocks (may lag behind the AGFs with lazy counters)
+        let free_data_blocks = u64::from_be_bytes([
+            data[144], data[145], data[146], data[147], data[148], data[149], data[150], data[151],
+        ]);
+
+        // Log2 geometry, falling back to values derived from the sizes above
+        let log_or = |offset: usize, derived: u32| match data[offset] {
+            0 => derived as u8,
+            log => log,
+        };
+        let block_log = log_or(120, block_size.max(1).ilog2());
+        let sector_log = log_or(121, sector_size.max(1).ilog2());
+        let inode_log = log_or(122, inode_size.max(1).ilog2());
+        let inopb_log = log_or(123, inodes_per_block.max(1).ilog2());
+        let agblklog = log_or(124, ag_blocks.max(2).next_power_of_two().ilog2());
+
         tracing::debug!(
             "Parsed superblock: {} data blocks, {} AGs of {} blocks each",
             data_blocks,
@@ -264,22 +361,23 @@ impl XfsRecoveryEngine {
             ag_count,
             realtime_bitmap_blocks: 0,
             log_blocks: 0,
-            version_num: 4, // Default version
+            version_num,
+            features2,
             sector_size,
             inode_size,
             inodes_per_block,
             filesystem_name: [0u8; 12], // Will be filled with defaults
-            block_log: 12,              // 4096 = 2^12
-            sector_log: 9,              // 512 = 2^9
-            inode_log: 8,               // 256 = 2^8
-            inopb_log: 4,               // 16 = 2^4
-            agblklog: 20,               // Will be calculated
+            block_log,
+            sector_log,
+            inode_log,
+            inopb_log,
+            agblklog,
             rextslog: 0,
             in_progress: 0,
             max_inode_percent: 25,
             inode_count: data_blocks / 32, // Estimate
             inode_free_count: 0,
-            free_data_extents: 0,
+            free_data_blocks,
             free_realtime_extents: 0,
         })
     }
@@ -312,48 +410,117 @@ impl XfsRecoveryEngine {
         Ok(())
     }
 
-    /// Comprehensive scan for deleted files across all allocation groups
+    /// Stop scanning inode tables once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// The primary superblock, if it was readable
+    pub fn superblock(&self) -> Option<&XfsSuperblock> {
+        self.superblock.as_ref()
+    }
+
+    /// Fraction of data blocks in use (0.0 - 1.0), if the superblock was readable
+    ///
+    /// Prefers the free space btrees, whose counts are exact, and falls back to
+    /// the superblock's free block counter.
+    pub fn used_fraction(&self) -> Option<f32> {
+        let sb = self.superblock.as_ref()?;
+        if sb.data_blocks == 0 {
+            return None;
+        }
+        let free_blocks = match &self.free_space {
+            Some(free_space) => free_space.total_len(),
+            None => sb.free_data_blocks,
+        };
+        Some(1.0 - free_blocks.min(sb.data_blocks) as f32 / sb.data_blocks as f32)
+    }
+
     pub fn scan_deleted_files(&self) -> Result<Vec<crate::DeletedFile>> {
         tracing::info!("Starting comprehensive XFS deleted file scan");
-        let mut deleted_files = Vec::new();
-        let mut file_id_counter = 1u64;
 
-        // Scan each allocation group for deleted inodes
-        for (ag_no, &inode_table_start) in self.ag_inode_table_blocks.iter().enumerate() {
+        // Scan each allocation group for deleted inodes; the AGs are
+        // independent, so they can be scanned at the same time
+        let scan_ag = |(ag_no, &inode_table_start): (usize, &u64)| {
             tracing::debug!(
                 "Scanning AG {} inode table starting at block {}",
                 ag_no,
                 inode_table_start
             );
 
-            match self.scan_allocation_group_inodes(
-                ag_no as u32,
-                inode_table_start,
-                &mut file_id_counter,
-            ) {
+            match self.scan_allocation_group_inodes(ag_no as u32, inode_table_start) {
                 Ok(mut ag_files) => {
                     tracing::info!("Found {} deleted files in AG {}", ag_files.len(), ag_no);
-                    deleted_files.append(&mut ag_files);
+                    ag_files.sort_by_key(|file| file.inode_or_cluster);
+                    ag_files
                 }
                 Err(e) => {
                     tracing::warn!("Failed to scan AG {}: {}", ag_no, e);
                     // Continue with other AGs
+                    Vec::new()
                 }
             }
+        };
+        let tables = &self.ag_inode_table_blocks;
+        let per_ag: Vec<Vec<crate::DeletedFile>> = if self.config.parallel_ags {
+            tables.par_iter().enumerate().map(scan_ag).collect()
+        } else {
+            tables.iter().enumerate().map(scan_ag).collect()
+        };
+
+        // Number the files in AG then inode order, however the AGs were scanned
+        let mut deleted_files: Vec<_> = per_ag.into_iter().flatten().collect();
+        for (index, file) in deleted_files.iter_mut().enumerate() {
+            file.id = index as u64 + 1;
+        }
+        let mut file_id_counter = deleted_files.len() as u64 + 1;
+
+        if self.config.brute_force_inodes {
+            let known: HashSet<u64> = deleted_files.iter().map(|f| f.inode_or_cluster).collect();
+            match self.brute_force_inode_scan() {
+                Ok(found) => {
+                    let missed: Vec<_> = found
+                        .into_iter()
+                        .filter(|file| !known.contains(&file.inode_or_cluster))
+                        .collect();
+                    tracing::info!("Found {} inodes outside the inode tables", missed.len());
+                    for mut file in missed {
+                        file.id = file_id_counter;
+                        file_id_counter += 1;
+                        deleted_files.push(file);
+                    }
+                }
+                Err(e) => tracing::warn!("Brute-force inode scan failed: {}", e),
+            }
         }
 
-        // Additional signature-based scanning for files without readable inodes
-        tracing::info!("Performing signature-based scan for additional files");
-        match self.signature_based_scan(&mut file_id_counter) {
-            Ok(mut sig_files) => {
-                tracing::info!("Found {} files via signature scanning", sig_files.len());
-                deleted_files.append(&mut sig_files);
+        // Name the inodes from the directory entries that still reference them
+        let mut directories = self.reconstruct_directories();
+        for file in &mut deleted_files {
+            if let Some(path) = directories.reconstruct_path(file.inode_or_cluster) {
+                file.original_path = Some(path);
             }
-            Err(e) => {
-                tracing::warn!("Signature scan failed: {}", e);
+        }
+
+        // Additional signature-based scanning for files without readable inodes
+        if self.config.signature_scan && !self.config.list_only {
+            tracing::info!("Performing signature-based scan for additional files");
+            match self.signature_based_scan(&mut file_id_counter) {
+                Ok(mut sig_files) => {
+                    tracing::info!("Found {} files via signature scanning", sig_files.len());
+                    deleted_files.append(&mut sig_files);
+                }
+                Err(e) => {
+                    tracing::warn!("Signature scan failed: {}", e);
+                }
             }
         }
 
+        if let Some(free_space) = &self.free_space {
+            mark_reallocated_blocks(&mut deleted_files, free_space);
+        }
+
         tracing::info!(
             "XFS scan complete: {} total deleted files found",
             deleted_files.len()
@@ -362,22 +529,24 @@ impl XfsRecoveryEngine {
     }
 
     /// Scan inodes in a specific allocation group
+    ///
+    /// Files are returned unnumbered; `scan_deleted_files` numbers them once
+    /// every AG is done.
     fn scan_allocation_group_inodes(
         &self,
         ag_no: u32,
         inode_table_start: u64,
-        file_id_counter: &mut u64,
     ) -> Result<Vec<crate::DeletedFile>> {
         let mut deleted_files = Vec::new();
 
-        // Calculate how many inode blocks to scan
-        // Typically scan first several blocks of inode table
-        let max_inode_blocks_to_scan = std::cmp::min(64, self.ag_blocks / 8);
-
-        for block_offset in 0..max_inode_blocks_to_scan {
+        for block_offset in 0..self.inode_blocks_to_scan() {
+            if self.cancellation.is_cancelled() {
+                tracing::info!("XFS scan of AG {} cancelled", ag_no);
+                break;
+            }
             let inode_block = inode_table_start + block_offset as u64;
 
-            match self.scan_inode_block(ag_no, inode_block, file_id_counter) {
+            match self.scan_inode_block(ag_no, inode_block) {
                 Ok(mut block_files) => {
                     deleted_files.append(&mut block_files);
                 }
@@ -391,13 +560,137 @@ impl XfsRecoveryEngine {
         Ok(deleted_files)
     }
 
-    /// Scan a single inode block for deleted files
-    fn scan_inode_block(
+    /// How many blocks of each AG's inode table to scan
+    fn inode_blocks_to_scan(&self) -> u32 {
+        // Typically scan first several blocks of inode table
+        std::cmp::min(64, self.ag_blocks / 8)
+    }
+
+    /// Inode number of the inode in slot `inode_idx` of `block_number`
+    fn inode_number(
         &self,
+        inode_data: &[u8],
         ag_no: u32,
         block_number: u64,
-        file_id_counter: &mut u64,
-    ) -> Result<Vec<crate::DeletedFile>> {
+        inode_idx: usize,
+    ) -> u64 {
+        // v3 inodes record their own number
+        if inode_data.get(4) == Some(&3) && inode_data.len() >= XFS_DINODE_V3_CORE_SIZE {
+            let ino = u64::from_be_bytes(
+                inode_data[XFS_DINODE_INO_OFFSET..XFS_DINODE_INO_OFFSET + 8]
+                    .try_into()
+                    .unwrap(),
+            );
+            if ino != 0 {
+                return ino;
+            }
+        }
+
+        // Otherwise: AG number, block within the AG and slot within the block
+        let agbno = block_number - ag_no as u64 * self.ag_blocks as u64;
+        match &self.superblock {
+            Some(sb) => {
+                ((ag_no as u64) << (sb.agblklog as u32 + sb.inopb_log as u32))
+                    | (agbno << sb.inopb_log)
+                    | inode_idx as u64
+            }
+            None => {
+                (ag_no as u64 * self.ag_blocks as u64 + agbno) * self.inodes_per_block as u64
+                    + inode_idx as u64
+            }
+        }
+    }
+
+    /// Deleted files from inodes found anywhere on the device
+    ///
+    /// Walks the device at inode-size strides and parses every slot holding
+    /// an inode core, so inode chunks the inode btrees no longer lead to
+    /// (a corrupted AGI, chunks beyond the scanned part of the table) are
+    /// still found. Reads the whole device; the tree walk is much cheaper.
+    pub fn brute_force_inode_scan(&self) -> Result<Vec<crate::DeletedFile>> {
+        let inode_size = self.inode_size as usize;
+        if inode_size < XFS_DINODE_CORE_SIZE
+            || !(self.block_size as usize).is_multiple_of(inode_size)
+        {
+            anyhow::bail!(
+                "Inode size {} does not divide block size {}",
+                inode_size,
+                self.block_size
+            );
+        }
+
+        let mut deleted_files = Vec::new();
+        let total_blocks = self.device.size() / self.block_size as u64;
+        for block_number in 0..total_blocks {
+            let Ok(block_data) = self.device.read_block(block_number, self.block_size) else {
+                continue;
+            };
+            let ag_no = block_number.checked_div(self.ag_blocks as u64).unwrap_or(0) as u32;
+
+            for (inode_idx, inode_data) in block_data.chunks_exact(inode_size).enumerate() {
+                if !is_inode_core(inode_data) {
+                    continue;
+                }
+                match self.parse_inode(inode_data, ag_no, block_number, inode_idx) {
+                    Ok(Some(mut file)) => {
+                        file.id = deleted_files.len() as u64 + 1;
+                        deleted_files.push(file);
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::debug!(
+                        "Failed to parse inode at block {} slot {}: {}",
+                        block_number,
+                        inode_idx,
+                        e
+                    ),
+                }
+            }
+        }
+
+        Ok(deleted_files)
+    }
+
+    /// Collect directory entries for path reconstruction
+    ///
+    /// Short-form directories live inside their inode, so the inode tables
+    /// are walked for them first; block and data directories are then found
+    /// by scanning the device for directory block magics.
+    pub fn reconstruct_directories(&self) -> XfsDirReconstructor {
+        let mut reconstructor = XfsDirReconstructor::new(self.block_size);
+        let has_ftype = self.superblock.as_ref().is_some_and(|sb| sb.has_ftype());
+        if let Some(sb) = self.superblock.as_ref().filter(|sb| sb.root_inode != 0) {
+            reconstructor.set_root_inode(sb.root_inode);
+        }
+
+        let inode_size = self.inode_size as usize;
+        for (ag_no, &inode_table_start) in self.ag_inode_table_blocks.iter().enumerate() {
+            for block_offset in 0..self.inode_blocks_to_scan() {
+                let block_number = inode_table_start + block_offset as u64;
+                let Ok(block_data) = self.device.read_block(block_number, self.block_size) else {
+                    continue;
+                };
+
+                for (inode_idx, inode_data) in block_data.chunks_exact(inode_size).enumerate() {
+                    let Some(fork) = shortform_dir_fork(inode_data) else {
+                        continue;
+                    };
+                    let inode =
+                        self.inode_number(inode_data, ag_no as u32, block_number, inode_idx);
+                    if let Err(e) = reconstructor.add_shortform_dir(inode, fork, has_ftype) {
+                        tracing::debug!("Skipping short-form directory {}: {}", inode, e);
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = reconstructor.scan_directories(&self.device) {
+            tracing::warn!("XFS directory block scan failed: {}", e);
+        }
+        reconstructor
+    }
+
+    /// Scan a single inode block for deleted files
+    fn scan_inode_block(&self, ag_no: u32, block_number: u64) -> Result<Vec<crate::DeletedFile>> {
         let mut deleted_files = Vec::new();
 
         // Read the inode block
@@ -417,12 +710,7 @@ impl XfsRecoveryEngine {
 
             // Try to parse this inode
             match self.parse_inode(inode_data, ag_no, block_number, inode_idx) {
-                Ok(Some(deleted_file)) => {
-                    let mut file = deleted_file;
-                    file.id = *file_id_counter;
-                    *file_id_counter += 1;
-                    deleted_files.push(file);
-                }
+                Ok(Some(deleted_file)) => deleted_files.push(deleted_file),
                 Ok(None) => {
                     // Not a deleted file or not recoverable
                 }
@@ -535,11 +823,7 @@ impl XfsRecoveryEngine {
             return Ok(None);
         }
 
-        // Calculate inode number
-        let inode_number = (ag_no as u64 * self.ag_blocks as u64 * self.inodes_per_block as u64)
-            + (block_number - self.ag_inode_table_blocks[ag_no as usize])
-                * self.inodes_per_block as u64
-            + inode_idx as u64;
+        let inode_number = self.inode_number(inode_data, ag_no, block_number, inode_idx);
 
         tracing::debug!(
             "🔍 Found deleted inode {} in AG {}: size={}, blocks={}",
@@ -550,15 +834,14 @@ impl XfsRecoveryEngine {
         );
 
         // Extract data block references
-        let data_blocks = self.extract_data_blocks(inode_data, format, size)?;
+        let (data_blocks, inline_data, sparse_ranges) = if self.config.list_only {
+            (Vec::new(), None, Vec::new())
+        } else {
+            self.extract_data_blocks(inode_data, format, size)
+        };
 
         // Determine file type from mode
-        let file_type = match mode & 0xF000 {
-            0x8000 => crate::FileType::RegularFile,
-            0x4000 => crate::FileType::Directory,
-            0xA000 => crate::FileType::SymbolicLink,
-            _ => crate::FileType::Unknown,
-        };
+        let file_type = crate::FileType::from_mode(mode);
 
         // Try to determine file extension and MIME type from content
         let (mime_type, extension) = if !data_blocks.is_empty() {
@@ -590,8 +873,10 @@ impl XfsRecoveryEngine {
         let original_path = self.generate_filename(inode_number, &extension, &file_type);
 
         // Extract XFS-specific metadata for confidence scoring
-        let xfs_metadata =
+        let mut xfs_metadata =
             self.extract_xfs_metadata(ag_no, inode_number, format, onlink, gen, &data_blocks, size);
+        xfs_metadata.inline_data = inline_data;
+        xfs_metadata.sparse_ranges = sparse_ranges;
 
         let deleted_file = crate::DeletedFile {
             id: 0, // Will be set by caller
@@ -609,18 +894,46 @@ impl XfsRecoveryEngine {
                 permissions: Some(mode as u32 & 0o777),
                 owner_uid: Some(uid),
                 owner_gid: Some(gid),
+                owner_name: None,
+                group_name: None,
                 created_time: deletion_time, // Use ctime as creation time
                 modified_time,
                 accessed_time,
-                extended_attributes: HashMap::new(),
+                extended_attributes: self.read_extended_attributes(inode_data),
             },
             fs_metadata: Some(crate::FsSpecificMetadata::Xfs(xfs_metadata)),
+            skip_reason: None,
         };
 
         Ok(Some(deleted_file))
     }
 
+    /// Extended attributes from the inode's attribute fork
+    fn read_extended_attributes(&self, inode_data: &[u8]) -> HashMap<String, Vec<u8>> {
+        let extents = match xattr::attr_fork(inode_data) {
+            Some(xattr::AttrFork::Local(fork)) => return xattr::parse_shortform(fork),
+            Some(xattr::AttrFork::Extents(extents)) => extents,
+            None => return HashMap::new(),
+        };
+
+        let ag_block_log = self.superblock.as_ref().map_or(0, |sb| sb.agblklog as u32);
+        let mut attributes = HashMap::new();
+        for (fsblock, count) in extents {
+            let Some(start) = log::fsblock_to_linear(fsblock, self.ag_blocks as u64, ag_block_log)
+            else {
+                continue;
+            };
+            for block in start..start.saturating_add(count.min(MAX_ATTR_EXTENT_BLOCKS)) {
+                if let Ok(data) = self.device.read_block(block, self.block_size) {
+                    attributes.extend(xattr::parse_leaf_block(data));
+                }
+            }
+        }
+        attributes
+    }
+
     /// Extract XFS-specific metadata for confidence scoring
+    #[allow(clippy::too_many_arguments)]
     fn extract_xfs_metadata(
         &self,
         ag_no: u32,
@@ -636,12 +949,8 @@ impl XfsRecoveryEngine {
         let ag_inode_number = (inode_number % inodes_per_ag) as u32;
 
         // Determine extent format
-        let extent_format = match format {
-            XFS_DINODE_FMT_LOCAL => crate::XfsExtentFormat::Local,
-            XFS_DINODE_FMT_EXTENTS => crate::XfsExtentFormat::Extents,
-            XFS_DINODE_FMT_BTREE => crate::XfsExtentFormat::Btree,
-            _ => crate::XfsExtentFormat::Extents, // Default
-        };
+        let extent_format =
+            datafork::extent_format(format).unwrap_or(crate::XfsExtentFormat::Extents);
 
         // Count extents
         let extent_count = data_blocks.len() as u32;
@@ -665,11 +974,14 @@ impl XfsRecoveryEngine {
             is_aligned,
             last_link_count: onlink as u32,
             inode_generation: generation,
+            inline_data: None,
+            sparse_ranges: Vec::new(),
         }
     }
 
     /// Enhanced deleted file detection using multiple heuristics
     /// This reduces false positives and catches more edge cases
+    #[allow(clippy::too_many_arguments)]
     fn is_likely_deleted_file(
         &self,
         mode: u16,
@@ -772,104 +1084,126 @@ impl XfsRecoveryEngine {
         true
     }
 
-    /// Extract data block references from inode based on its format
+    /// Data block references, inline contents and sparse ranges from the
+    /// inode's data fork
+    ///
+    /// Local data is returned as is, with a pseudo block range at block 0
+    /// standing for it. A data fork that cannot be read yields none of them.
     fn extract_data_blocks(
         &self,
         inode_data: &[u8],
         format: u8,
         file_size: u64,
-    ) -> Result<Vec<crate::BlockRange>> {
-        let mut data_blocks = Vec::new();
+    ) -> (
+        Vec<crate::BlockRange>,
+        Option<Vec<u8>>,
+        Vec<crate::SparseRange>,
+    ) {
+        let Some(extent_format) = datafork::extent_format(format) else {
+            tracing::debug!("Unknown inode format: {}", format);
+            return (Vec::new(), None, Vec::new());
+        };
 
-        match format {
-            XFS_DINODE_FMT_EXTENTS => {
-                // Parse extent list from inode
-                data_blocks = self.parse_extent_list(inode_data)?;
-            }
-            XFS_DINODE_FMT_BTREE => {
-                // Parse B-tree root and follow to extent leaves
-                data_blocks = self.parse_btree_extents(inode_data)?;
-            }
-            XFS_DINODE_FMT_LOCAL => {
-                // Data is stored locally in the inode - create a pseudo block range
-                if file_size > 0 {
-                    data_blocks.push(crate::BlockRange {
-                        start_block: 0, // Special marker for local data
-                        block_count: 1,
-                        is_allocated: false,
-                    });
-                }
+        let fork = match datafork::read_data_fork(
+            &self.device,
+            &self.data_fork_geometry(),
+            inode_data,
+            extent_format,
+        ) {
+            Ok(fork) => fork,
+            Err(e) => {
+                tracing::debug!("Unreadable {:?} data fork: {}", extent_format, e);
+                return (Vec::new(), None, Vec::new());
             }
-            _ => {
-                tracing::debug!("Unknown inode format: {}", format);
+        };
+
+        match fork {
+            datafork::DataFork::Local(data) if file_size > 0 && !data.is_empty() => (
+                vec![crate::BlockRange {
+                    start_block: 0, // Special marker for local data
+                    block_count: 1,
+                    is_allocated: false,
+                }],
+                Some(data.to_vec()),
+                Vec::new(),
+            ),
+            datafork::DataFork::Local(_) => (Vec::new(), None, Vec::new()),
+            datafork::DataFork::Extents(extents) => {
+                let (ranges, sparse_ranges) = self.extent_layout(&extents, file_size);
+                (ranges, None, sparse_ranges)
             }
         }
-
-        Ok(data_blocks)
     }
 
-    /// Parse extent list format (simplified)
-    fn parse_extent_list(&self, inode_data: &[u8]) -> Result<Vec<crate::BlockRange>> {
-        let mut extents = Vec::new();
-
-        if inode_data.len() < 100 {
-            return Ok(extents);
-        }
-
-        // XFS extent format: each extent is 16 bytes
-        // We'll look for extent data starting after the inode core (around offset 96)
-        let extent_area_start = 96;
-        let max_extents = (inode_data.len() - extent_area_start) / 16;
+    /// Data block ranges of a file's extents, and the sparse ranges around them
+    ///
+    /// Holes, unwritten extents and extents outside the device have nothing
+    /// to read, so they become sparse ranges and the data after them keeps
+    /// its offset in the file. A file with no data extents gets no sparse
+    /// ranges either, rather than being recovered as all zeros.
+    fn extent_layout(
+        &self,
+        extents: &[datafork::XfsExtent],
+        file_size: u64,
+    ) -> (Vec<crate::BlockRange>, Vec<crate::SparseRange>) {
+        let block_size = self.block_size as u64;
+        let total_blocks = self.device.size() / block_size;
+        let mut ranges = Vec::new();
+        let mut sparse_ranges: Vec<crate::SparseRange> = Vec::new();
+        let mut add_sparse = |start: u64, end: u64| {
+            let end = end.min(file_size);
+            if start >= end {
+                return;
+            }
+            match sparse_ranges.last_mut() {
+                Some(last) if last.offset + last.length == start => last.length = end - last.offset,
+                _ => sparse_ranges.push(crate::SparseRange {
+                    offset: start,
+                    length: end - start,
+                }),
+            }
+        };
 
-        for i in 0..std::cmp::min(max_extents, 8) {
-            // Limit to reasonable number
-            let offset = extent_area_start + i * 16;
-            if offset + 16 > inode_data.len() {
+        // File block following the extents so far
+        let mut next_block = 0u64;
+        for extent in extents {
+            let start = extent.file_block.saturating_mul(block_size);
+            if start >= file_size {
                 break;
             }
+            if extent.file_block < next_block {
+                tracing::debug!("Skipping extent overlapping file block {}", next_block);
+                continue;
+            }
+            let extent_end = extent.file_block.saturating_add(extent.block_count);
 
-            // Parse extent (simplified - real XFS extents are more complex)
-            let extent_data = &inode_data[offset..offset + 16];
-            let start_block = u64::from_be_bytes([
-                extent_data[0],
-                extent_data[1],
-                extent_data[2],
-                extent_data[3],
-                extent_data[4],
-                extent_data[5],
-                extent_data[6],
-                extent_data[7],
-            ]);
-            let block_count = u32::from_be_bytes([
-                extent_data[8],
-                extent_data[9],
-                extent_data[10],
-                extent_data[11],
-            ]) as u64;
-
-            // Simple validation - blocks should be reasonable
-            if start_block > 0
-                && start_block < self.device.size() / self.block_size as u64
-                && block_count > 0
-                && block_count < 1024
-            {
-                extents.push(crate::BlockRange {
-                    start_block,
-                    block_count,
+            add_sparse(next_block.saturating_mul(block_size), start);
+            if extent.unwritten || extent.start_block == 0 || extent.start_block >= total_blocks {
+                add_sparse(start, extent_end.saturating_mul(block_size));
+            } else {
+                ranges.push(crate::BlockRange {
+                    start_block: extent.start_block,
+                    block_count: extent.block_count,
                     is_allocated: false,
                 });
             }
+            next_block = extent_end;
         }
+        add_sparse(next_block.saturating_mul(block_size), file_size);
 
-        Ok(extents)
+        if ranges.is_empty() {
+            sparse_ranges.clear();
+        }
+        (ranges, sparse_ranges)
     }
 
-    /// Parse B-tree extent format (simplified)
-    fn parse_btree_extents(&self, _inode_data: &[u8]) -> Result<Vec<crate::BlockRange>> {
-        // B-tree parsing is complex - for now return empty
-        // In a full implementation, this would traverse the B-tree structure
-        tracing::debug!("B-tree extent parsing not yet implemented");
-        Ok(Vec::new())
+    /// Geometry for following this file system's data fork extents
+    fn data_fork_geometry(&self) -> datafork::DataForkGeometry {
+        datafork::DataForkGeometry {
+            block_size: self.block_size,
+            ag_blocks: self.ag_blocks,
+            ag_block_log: self.superblock.as_ref().map_or(0, |sb| sb.agblklog as u32),
+        }
     }
 
     /// Signature-based scanning for files that may not have readable inodes
@@ -877,18 +1211,6 @@ impl XfsRecoveryEngine {
         let mut files = Vec::new();
         tracing::debug!("🔍 Starting signature-based scan");
 
-        // Common file signatures to look for
-        let signatures: Vec<(&[u8], &str, &str)> = vec![
-            (b"\xFF\xD8\xFF", "image/jpeg", "jpg"),
-            (b"\x89PNG\r\n\x1a\n", "image/png", "png"),
-            (b"GIF8", "image/gif", "gif"),
-            (b"%PDF", "application/pdf", "pdf"),
-            (b"PK\x03\x04", "application/zip", "zip"),
-            (b"\x7FELF", "application/x-executable", "bin"),
-            (b"{\n", "application/json", "json"),
-            (b"[", "text/plain", "txt"),
-        ];
-
         // Scan through the device looking for file signatures
         let total_blocks = self.device.size() / self.block_size as u64;
         let scan_blocks = self.config.adaptive_scan_blocks(total_blocks);
@@ -913,76 +1235,96 @@ impl XfsRecoveryEngine {
         }
 
         for block_num in 0..scan_blocks {
-            if let Ok(block_data) = self.device.read_block(block_num, self.block_size) {
-                for (signature, mime_type, extension) in &signatures {
-                    if block_data.starts_with(signature) {
-                        // Found a potential file
-                        let file_size =
-                            self.estimate_file_size_from_signature(block_data, signature);
-                        if file_size > 0 {
-                            let block_count = file_size.div_ceil(self.block_size as u64);
-
-                            let deleted_file = crate::DeletedFile {
-                                id: *file_id_counter,
-                                inode_or_cluster: *file_id_counter + 10000, // Use high numbers for sig-based
-                                original_path: Some(std::path::PathBuf::from(format!(
-                                    "recovered_file_{}.{}",
-                                    *file_id_counter, extension
-                                ))),
-                                size: file_size,
-                                deletion_time: None,
-                                confidence_score: 0.7, // Medium confidence for signature-based
-                                file_type: crate::FileType::RegularFile,
-                                data_blocks: vec![crate::BlockRange {
-                                    start_block: block_num,
-                                    block_count,
-                                    is_allocated: false,
-                                }],
-                                is_recoverable: true,
-                                metadata: crate::FileMetadata {
-                                    mime_type: Some(mime_type.to_string()),
-                                    file_extension: Some(extension.to_string()),
-                                    permissions: Some(0o644),
-                                    owner_uid: None,
-                                    owner_gid: None,
-                                    created_time: None,
-                                    modified_time: None,
-                                    accessed_time: None,
-                                    extended_attributes: HashMap::new(),
-                                },
-                                fs_metadata: None, // Signature-based recovery has no inode metadata
-                            };
-
-                            files.push(deleted_file);
-                            *file_id_counter += 1;
-
-                            tracing::debug!(
-                                "� Found {} file via signature at block {}",
-                                extension,
-                                block_num
-                            );
-                        }
-                        break; // Found one signature in this block
-                    }
-                }
+            let Ok(block_data) = self.device.read_block(block_num, self.block_size) else {
+                continue;
+            };
+            let Some((mime_type, extension)) = self
+                .config
+                .signatures
+                .detect_file_type(block_data)
+                .or_else(|| detect_text_file_start(block_data))
+            else {
+                continue;
+            };
+
+            // Found a potential file
+            let file_size =
+                self.estimate_file_size_from_signature(block_num, block_data, &mime_type);
+            if file_size == 0 {
+                continue;
             }
+            let block_count = file_size.div_ceil(self.block_size as u64);
+
+            let deleted_file = crate::DeletedFile {
+                id: *file_id_counter,
+                inode_or_cluster: *file_id_counter + 10000, // Use high numbers for sig-based
+                original_path: Some(std::path::PathBuf::from(format!(
+                    "recovered_file_{}.{}",
+                    *file_id_counter, extension
+                ))),
+                size: file_size,
+                deletion_time: None,
+                confidence_score: 0.7, // Medium confidence for signature-based
+                file_type: crate::FileType::RegularFile,
+                data_blocks: vec![crate::BlockRange {
+                    start_block: block_num,
+                    block_count,
+                    is_allocated: false,
+                }],
+                is_recoverable: true,
+                metadata: crate::FileMetadata {
+                    mime_type: Some(mime_type),
+                    file_extension: Some(extension.clone()),
+                    permissions: Some(0o644),
+                    owner_uid: None,
+                    owner_gid: None,
+                    owner_name: None,
+                    group_name: None,
+                    created_time: None,
+                    modified_time: None,
+                    accessed_time: None,
+                    extended_attributes: HashMap::new(),
+                },
+                fs_metadata: None, // Signature-based recovery has no inode metadata
+                skip_reason: None,
+            };
+
+            files.push(deleted_file);
+            *file_id_counter += 1;
+
+            tracing::debug!(
+                "🔍 Found {} file via signature at block {}",
+                extension,
+                block_num
+            );
         }
 
         Ok(files)
     }
 
     /// Estimate file size based on content analysis
-    fn estimate_file_size_from_signature(&self, block_data: &[u8], signature: &[u8]) -> u64 {
-        match signature {
-            b"%PDF" => {
-                // Look for PDF trailer
-                if let Some(pos) = block_data.windows(9).position(|w| w == b"%%EOF\n") {
-                    (pos + 9) as u64
-                } else {
-                    4096 // Default size
-                }
+    fn estimate_file_size_from_signature(
+        &self,
+        block_num: u64,
+        block_data: &[u8],
+        mime_type: &str,
+    ) -> u64 {
+        // Formats with an end marker are searched for it past the first block
+        if let Some(limit) = self.config.signatures.end_marker_search_limit(mime_type) {
+            let offset = block_num * self.block_size as u64;
+            let available = self.device.size().saturating_sub(offset).min(limit);
+            if let Some(end) = self
+                .device
+                .read_at(offset, available as usize)
+                .ok()
+                .and_then(|data| self.end_markers.find_end(data, offset, mime_type))
+            {
+                return end.size;
             }
-            b"{\n" | b"[" => {
+        }
+
+        match mime_type {
+            "application/json" | "text/plain" => {
                 // JSON - look for matching braces
                 let mut brace_count = 0;
                 let mut in_string = false;
@@ -1037,16 +1379,8 @@ impl XfsRecoveryEngine {
             .read_block(block_range.start_block, self.block_size)
         {
             // Check for common file signatures
-            if data.starts_with(b"\xFF\xD8\xFF") {
-                return (Some("image/jpeg".to_string()), Some("jpg".to_string()));
-            } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
-                return (Some("image/png".to_string()), Some("png".to_string()));
-            } else if data.starts_with(b"GIF8") {
-                return (Some("image/gif".to_string()), Some("gif".to_string()));
-            } else if data.starts_with(b"%PDF") {
-                return (Some("application/pdf".to_string()), Some("pdf".to_string()));
-            } else if data.starts_with(b"PK\x03\x04") {
-                return (Some("application/zip".to_string()), Some("zip".to_string()));
+            if let Some((mime_type, extension)) = self.config.signatures.detect_file_type(data) {
+                return (Some(mime_type), Some(extension));
             } else if data.starts_with(b"{\n") || data.starts_with(b"{ ") || data.starts_with(b"[")
             {
                 return (
@@ -1145,50 +1479,22 @@ impl XfsRecoveryEngine {
             .find(|f| f.inode_or_cluster == inode)
             .ok_or_else(|| anyhow::anyhow!("Inode {} not found in deleted files", inode))?;
 
-        let mut recovered_data = Vec::new();
-
-        // Recover data from each block range
-        for block_range in &target_file.data_blocks {
-            if block_range.start_block == 0 {
-                // Special case: local data stored in inode
-                tracing::debug!("📄 Recovering local inode data for inode {}", inode);
-                // Would need to re-read the inode and extract local data
-                // For now, add placeholder data
-                recovered_data
-                    .extend_from_slice(b"[Local inode data - recovery not yet implemented]");
-            } else {
-                // Read data from blocks
-                for block_offset in 0..block_range.block_count {
-                    let block_num = block_range.start_block + block_offset;
-                    match self.device.read_block(block_num, self.block_size) {
-                        Ok(block_data) => {
-                            let bytes_to_copy = if recovered_data.len() as u64
-                                + self.block_size as u64
-                                > target_file.size
-                            {
-                                (target_file.size - recovered_data.len() as u64) as usize
-                            } else {
-                                block_data.len()
-                            };
-
-                            recovered_data.extend_from_slice(&block_data[..bytes_to_copy]);
-
-                            if recovered_data.len() >= target_file.size as usize {
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to read block {}: {}", block_num, e);
-                        }
-                    }
-                }
-            }
+        // Local data was read from the inode during the scan
+        if let Some(inline) = target_file.inline_data() {
+            tracing::debug!("📄 Recovering local inode data for inode {}", inode);
+            return Ok(inline.to_vec());
         }
 
-        // Truncate to expected file size
-        if recovered_data.len() > target_file.size as usize {
-            recovered_data.truncate(target_file.size as usize);
-        }
+        // Holes and unreadable blocks are zero-filled as by `recover_files`
+        let mut recovered_data = Vec::new();
+        crate::recover_file_streaming(
+            target_file,
+            &self.device,
+            crate::FileSystemType::Xfs,
+            self.block_size,
+            &mut recovered_data,
+            &mut Vec::new(),
+        )?;
 
         tracing::info!(
             "Recovered {} bytes for inode {}",
@@ -1245,6 +1551,68 @@ impl XfsRecoveryEngine {
     }
 }
 
+/// Whether `inode_data` starts with an inode core: magic, version and data fork format
+fn is_inode_core(inode_data: &[u8]) -> bool {
+    inode_data.len() >= XFS_DINODE_CORE_SIZE
+        && u16::from_be_bytes([inode_data[0], inode_data[1]]) == XFS_INODE_MAGIC
+        && matches!(inode_data[4], 1..=3)
+        && inode_data[5] <= XFS_DINODE_FMT_LOCAL
+}
+
+/// Data fork of a directory inode stored in short-form (local) format
+fn shortform_dir_fork(inode_data: &[u8]) -> Option<&[u8]> {
+    if inode_data.len() < XFS_DINODE_CORE_SIZE
+        || u16::from_be_bytes([inode_data[0], inode_data[1]]) != XFS_INODE_MAGIC
+        || u16::from_be_bytes([inode_data[2], inode_data[3]]) & 0xF000 != 0x4000
+        || inode_data[5] != XFS_DINODE_FMT_LOCAL
+    {
+        return None;
+    }
+
+    let fork_start = if inode_data[4] == 3 {
+        XFS_DINODE_V3_CORE_SIZE
+    } else {
+        XFS_DINODE_CORE_SIZE
+    };
+    // The attribute fork, if any, begins forkoff * 8 bytes into the literal area
+    let fork_end = match inode_data[XFS_DINODE_FORKOFF_OFFSET] as usize {
+        0 => inode_data.len(),
+        forkoff => fork_start + forkoff * 8,
+    };
+    let size = u64::from_be_bytes(
+        inode_data[XFS_DINODE_SIZE_OFFSET..XFS_DINODE_SIZE_OFFSET + 8]
+            .try_into()
+            .unwrap(),
+    );
+
+    let end = fork_end
+        .min(inode_data.len())
+        .min(fork_start.saturating_add(size as usize));
+    inode_data
+        .get(fork_start..end)
+        .filter(|fork| !fork.is_empty())
+}
+
+/// Flag the parts of each file's extents that are no longer free space
+///
+/// Block 0 holds the superblock and is used as the marker for inode-local
+/// data, so those pseudo ranges are left alone.
+fn mark_reallocated_blocks(files: &mut [crate::DeletedFile], free_space: &RangeSet) {
+    for file in files {
+        file.data_blocks = file
+            .data_blocks
+            .iter()
+            .flat_map(|range| {
+                if range.start_block == 0 {
+                    vec![range.clone()]
+                } else {
+                    range.split_by_allocation(1, |block| !free_space.contains(block))
+                }
+            })
+            .collect();
+    }
+}
+
 /// Get comprehensive XFS file system information
 pub fn get_filesystem_info(device: &BlockDevice) -> Result<String> {
     tracing::info!("Analyzing XFS filesystem information");
@@ -1341,6 +1709,17 @@ pub fn is_xfs_superblock(data: &[u8]) -> bool {
     is_xfs
 }
 
+/// Recognize the start of a JSON or bracketed text file, which have no magic bytes
+fn detect_text_file_start(block_data: &[u8]) -> Option<(String, String)> {
+    if block_data.starts_with(b"{\n") {
+        Some(("application/json".to_string(), "json".to_string()))
+    } else if block_data.starts_with(b"[") {
+        Some(("text/plain".to_string(), "txt".to_string()))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1617,4 +1996,444 @@ mod tests {
             engine.extract_xfs_metadata(0, 200, XFS_DINODE_FMT_BTREE, 1, 200, &[], 10_000_000);
         assert_eq!(meta_btree.extent_format, crate::XfsExtentFormat::Btree);
     }
+
+    /// v3 inode `ino` in slot `idx` of inode table block 4
+    fn put_inode(image: &mut [u8], idx: usize, ino: u64, mode: u16, nlink: u16, size: u64) {
+        put_inode_at(image, 4, idx, ino, mode, nlink, size);
+    }
+
+    /// v3 inode `ino` in slot `idx` of `block`
+    fn put_inode_at(
+        image: &mut [u8],
+        block: usize,
+        idx: usize,
+        ino: u64,
+        mode: u16,
+        nlink: u16,
+        size: u64,
+    ) {
+        let start = block * 4096 + idx * 512;
+        let inode = &mut image[start..start + 512];
+        inode[0..2].copy_from_slice(&XFS_INODE_MAGIC.to_be_bytes());
+        inode[2..4].copy_from_slice(&mode.to_be_bytes());
+        inode[4] = 3;
+        inode[5] = XFS_DINODE_FMT_LOCAL;
+        inode[6..8].copy_from_slice(&nlink.to_be_bytes());
+        inode[8..12].copy_from_slice(&1u32.to_be_bytes());
+        inode[56..64].copy_from_slice(&size.to_be_bytes());
+        inode[64..72].copy_from_slice(&1u64.to_be_bytes());
+        inode[80] = 0x60; // mtime
+        inode[XFS_DINODE_INO_OFFSET..XFS_DINODE_INO_OFFSET + 8].copy_from_slice(&ino.to_be_bytes());
+    }
+
+    /// Short-form directory body: parent, then (name, ftype, inode) entries
+    fn shortform(parent: u32, entries: &[(&str, u8, u32)]) -> Vec<u8> {
+        let mut sf = vec![entries.len() as u8, 0];
+        sf.extend_from_slice(&parent.to_be_bytes());
+        for (name, ftype, ino) in entries {
+            sf.push(name.len() as u8);
+            sf.extend_from_slice(&0u16.to_be_bytes());
+            sf.extend_from_slice(name.as_bytes());
+            sf.push(*ftype);
+            sf.extend_from_slice(&ino.to_be_bytes());
+        }
+        sf
+    }
+
+    /// Image of one 64-block AG with 512-byte inodes, inode table at block 4
+    fn single_ag_image() -> Vec<u8> {
+        let mut image = vec![0u8; 64 * 4096];
+        image[0..4].copy_from_slice(&XFS_MAGIC.to_be_bytes());
+        image[4..8].copy_from_slice(&4096u32.to_be_bytes());
+        image[8..16].copy_from_slice(&64u64.to_be_bytes());
+        image[56..64].copy_from_slice(&32u64.to_be_bytes());
+        image[84..88].copy_from_slice(&64u32.to_be_bytes());
+        image[88..92].copy_from_slice(&1u32.to_be_bytes());
+        image[100..102].copy_from_slice(&0xB4A5u16.to_be_bytes());
+        image[102..104].copy_from_slice(&512u16.to_be_bytes());
+        image[104..106].copy_from_slice(&512u16.to_be_bytes());
+        image[123] = 3;
+        image[124] = 6;
+        image
+    }
+
+    #[test]
+    fn test_deleted_inode_gets_path_from_directories() {
+        // Inode table holds "/" (32, short-form) and "/docs" (33,
+        // short-form). "draft.txt" (34) was unlinked from "/docs", whose old
+        // single-block form at block 20 still holds the entry in its free space.
+        let mut image = single_ag_image();
+
+        let root = shortform(32, &[("docs", 2, 33)]);
+        put_inode(&mut image, 0, 32, 0x41ED, 3, root.len() as u64);
+        let start = 4 * 4096 + XFS_DINODE_V3_CORE_SIZE;
+        image[start..start + root.len()].copy_from_slice(&root);
+
+        let docs = shortform(32, &[]);
+        put_inode(&mut image, 1, 33, 0x41ED, 2, docs.len() as u64);
+        let start = 4 * 4096 + 512 + XFS_DINODE_V3_CORE_SIZE;
+        image[start..start + docs.len()].copy_from_slice(&docs);
+
+        put_inode(&mut image, 2, 34, 0x81A4, 0, 100);
+
+        // "XDB3" block owned by 33: free region holding the unlinked entry
+        let block = &mut image[20 * 4096..21 * 4096];
+        block[0..4].copy_from_slice(&0x58444233u32.to_be_bytes());
+        block[40..48].copy_from_slice(&33u64.to_be_bytes());
+        block[64..66].copy_from_slice(&0xFFFFu16.to_be_bytes());
+        block[66..68].copy_from_slice(&24u16.to_be_bytes());
+        block[68..72].copy_from_slice(&34u32.to_be_bytes());
+        block[72] = 9;
+        block[73..82].copy_from_slice(b"draft.txt");
+        block[82] = 1;
+        let free_end = 4096 - 8; // empty hash index and tail
+        block[88..90].copy_from_slice(&0xFFFFu16.to_be_bytes());
+        block[90..92].copy_from_slice(&((free_end - 88) as u16).to_be_bytes());
+
+        let engine = XfsRecoveryEngine::new(BlockDevice::from_vec(image)).unwrap();
+        let files = engine.scan_deleted_files().unwrap();
+        let draft = files.iter().find(|f| f.inode_or_cluster == 34).unwrap();
+        assert_eq!(
+            draft.original_path.as_deref(),
+            Some(std::path::Path::new("/docs/draft.txt"))
+        );
+    }
+
+    #[test]
+    fn test_deleted_inode_keeps_extended_attributes() {
+        let mut image = single_ag_image();
+        put_inode(&mut image, 0, 32, 0x81A4, 0, 100);
+
+        // Short-form attribute fork 64 bytes into the literal area
+        let fork = xattr::tests::shortform_attrs(&[
+            (0, "mime_type", b"application/pdf"),
+            (0x04, "selinux", b"system_u:object_r:user_home_t:s0"),
+        ]);
+        let inode = 4 * 4096;
+        image[inode + XFS_DINODE_FORKOFF_OFFSET] = 8;
+        image[inode + 83] = 1;
+        let start = inode + XFS_DINODE_V3_CORE_SIZE + 64;
+        image[start..start + fork.len()].copy_from_slice(&fork);
+
+        let engine = XfsRecoveryEngine::new(BlockDevice::from_vec(image)).unwrap();
+        let files = engine.scan_deleted_files().unwrap();
+        let file = files.iter().find(|f| f.inode_or_cluster == 32).unwrap();
+        let attributes = &file.metadata.extended_attributes;
+        assert_eq!(attributes["user.mime_type"], b"application/pdf");
+        assert_eq!(
+            attributes["security.selinux"],
+            b"system_u:object_r:user_home_t:s0"
+        );
+    }
+
+    #[test]
+    fn test_deleted_inode_data_read_by_extent_format() {
+        let mut image = single_ag_image();
+        // Local: the contents follow the inode core
+        let text = b"short note\n";
+        put_inode(&mut image, 0, 32, 0x81A4, 0, text.len() as u64);
+        let fork = 4 * 4096 + XFS_DINODE_V3_CORE_SIZE;
+        image[fork..fork + text.len()].copy_from_slice(text);
+
+        // Extents: the second block first on disk
+        let size = 4096 + 904;
+        put_inode(&mut image, 1, 33, 0x81A4, 0, size);
+        let inode = 4 * 4096 + 512;
+        image[inode + 5] = XFS_DINODE_FMT_EXTENTS;
+        image[inode + 79] = 2;
+        for (i, (file_block, block)) in [(0u64, 30u64), (1, 20)].into_iter().enumerate() {
+            let high = file_block << 9;
+            let low = block << 21 | 1;
+            let record = inode + XFS_DINODE_V3_CORE_SIZE + i * 16;
+            image[record..record + 8].copy_from_slice(&high.to_be_bytes());
+            image[record + 8..record + 16].copy_from_slice(&low.to_be_bytes());
+            image[block as usize * 4096..(block as usize + 1) * 4096].fill(b'a' + i as u8);
+        }
+
+        let engine = XfsRecoveryEngine::new(BlockDevice::from_vec(image)).unwrap();
+        let files = engine.scan_deleted_files().unwrap();
+
+        let local = files.iter().find(|f| f.inode_or_cluster == 32).unwrap();
+        assert_eq!(local.inline_data(), Some(&text[..]));
+        assert_eq!(engine.recover_file(32).unwrap(), text);
+
+        let extents = files.iter().find(|f| f.inode_or_cluster == 33).unwrap();
+        let blocks: Vec<(u64, u64)> = extents
+            .data_blocks
+            .iter()
+            .map(|r| (r.start_block, r.block_count))
+            .collect();
+        assert_eq!(blocks, vec![(30, 1), (20, 1)]);
+        assert!(extents.inline_data().is_none());
+        let contents = engine.recover_file(33).unwrap();
+        assert_eq!(contents.len() as u64, size);
+        assert!(contents[..4096].iter().all(|&b| b == b'a'));
+        assert!(contents[4096..].iter().all(|&b| b == b'b'));
+    }
+
+    #[test]
+    fn test_holes_and_unwritten_extents_recover_as_zeros() {
+        let mut image = single_ag_image();
+        // Block 30, a hole, block 20, then unwritten block 21 and a hole to the end
+        let size = 5 * 4096 + 100;
+        put_inode(&mut image, 0, 32, 0x81A4, 0, size);
+        let inode = 4 * 4096;
+        image[inode + 5] = XFS_DINODE_FMT_EXTENTS;
+        image[inode + 79] = 3;
+        let extents = [(0u64, 30u64, false), (2, 20, false), (3, 21, true)];
+        for (i, (file_block, block, unwritten)) in extents.into_iter().enumerate() {
+            let high = (unwritten as u64) << 63 | file_block << 9;
+            let low = block << 21 | 1;
+            let record = inode + XFS_DINODE_V3_CORE_SIZE + i * 16;
+            image[record..record + 8].copy_from_slice(&high.to_be_bytes());
+            image[record + 8..record + 16].copy_from_slice(&low.to_be_bytes());
+            image[block as usize * 4096..(block as usize + 1) * 4096].fill(b'a' + i as u8);
+        }
+
+        let engine = XfsRecoveryEngine::new(BlockDevice::from_vec(image)).unwrap();
+        let files = engine.scan_deleted_files().unwrap();
+        let file = files.iter().find(|f| f.inode_or_cluster == 32).unwrap();
+        let blocks: Vec<(u64, u64)> = file
+            .data_blocks
+            .iter()
+            .map(|r| (r.start_block, r.block_count))
+            .collect();
+        assert_eq!(blocks, vec![(30, 1), (20, 1)]);
+        let sparse: Vec<(u64, u64)> = file
+            .sparse_ranges()
+            .iter()
+            .map(|r| (r.offset, r.length))
+            .collect();
+        assert_eq!(sparse, vec![(4096, 4096), (3 * 4096, 2 * 4096 + 100)]);
+
+        let contents = engine.recover_file(32).unwrap();
+        assert_eq!(contents.len() as u64, size);
+        assert!(contents[..4096].iter().all(|&b| b == b'a'));
+        assert!(contents[4096..2 * 4096].iter().all(|&b| b == 0));
+        assert!(contents[2 * 4096..3 * 4096].iter().all(|&b| b == b'b'));
+        assert!(contents[3 * 4096..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_list_only_scan_leaves_extents_unread() {
+        let mut image = single_ag_image();
+        put_inode(&mut image, 0, 32, 0x81A4, 0, 4096);
+        let inode = 4 * 4096;
+        image[inode + 5] = XFS_DINODE_FMT_EXTENTS;
+        image[inode + 79] = 1;
+        let record = inode + XFS_DINODE_V3_CORE_SIZE;
+        image[record + 8..record + 16].copy_from_slice(&(20u64 << 21 | 1).to_be_bytes());
+
+        let config = XfsRecoveryConfig {
+            list_only: true,
+            ..Default::default()
+        };
+        let engine =
+            XfsRecoveryEngine::new_with_config(BlockDevice::from_vec(image), config).unwrap();
+        let files = engine.scan_deleted_files().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!((files[0].inode_or_cluster, files[0].size), (32, 4096));
+        assert!(files[0].original_path.is_some());
+        assert!(files[0].data_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_signature_scan_can_be_skipped() {
+        let mut image = single_ag_image();
+        put_inode(&mut image, 0, 32, 0x81A4, 0, 4096);
+        // A JPEG no inode references
+        image[50 * 4096..50 * 4096 + 4].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+
+        let carved = |signature_scan: bool| {
+            let config = XfsRecoveryConfig {
+                signature_scan,
+                ..Default::default()
+            };
+            let engine =
+                XfsRecoveryEngine::new_with_config(BlockDevice::from_vec(image.clone()), config)
+                    .unwrap();
+            let files = engine.scan_deleted_files().unwrap();
+            assert!(files.iter().any(|f| f.inode_or_cluster == 32));
+            files
+                .iter()
+                .filter(|f| f.data_blocks.first().is_some_and(|r| r.start_block == 50))
+                .count()
+        };
+
+        assert_eq!(carved(true), 1);
+        assert_eq!(carved(false), 0);
+    }
+
+    #[test]
+    fn test_brute_force_scan_finds_inodes_outside_the_table() {
+        // Only blocks 4..12 of the 64-block AG are walked as inode table;
+        // move a deleted inode out to block 40, where nothing indexes it
+        let mut image = single_ag_image();
+        put_inode(&mut image, 0, 320, 0x81A4, 0, 100);
+        image.copy_within(4 * 4096..4 * 4096 + 512, 40 * 4096 + 1024);
+        image[4 * 4096..4 * 4096 + 512].fill(0);
+
+        // Looks like an inode, but no inode has version 7
+        image[50 * 4096..50 * 4096 + 2].copy_from_slice(&XFS_INODE_MAGIC.to_be_bytes());
+        image[50 * 4096 + 4] = 7;
+
+        let engine = XfsRecoveryEngine::new(BlockDevice::from_vec(image.clone())).unwrap();
+        let files = engine.scan_deleted_files().unwrap();
+        assert!(files.iter().all(|f| f.inode_or_cluster != 320));
+
+        let found = engine.brute_force_inode_scan().unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].inode_or_cluster, 320);
+        assert_eq!(found[0].size, 100);
+
+        let config = XfsRecoveryConfig {
+            brute_force_inodes: true,
+            ..Default::default()
+        };
+        let engine =
+            XfsRecoveryEngine::new_with_config(BlockDevice::from_vec(image), config).unwrap();
+        let files = engine.scan_deleted_files().unwrap();
+        assert_eq!(
+            files.iter().filter(|f| f.inode_or_cluster == 320).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parallel_ag_scan_matches_sequential() {
+        // Four 64-block AGs, each with deleted inodes in its inode table
+        let mut image = single_ag_image();
+        image.resize(4 * 64 * 4096, 0);
+        image[8..16].copy_from_slice(&256u64.to_be_bytes());
+        image[88..92].copy_from_slice(&4u32.to_be_bytes());
+        for ag in 0..4 {
+            for idx in 0..3 {
+                // AG number, block 4 of the AG, slot: agblklog 6, inopblog 3
+                let ino = (ag << 9) | (4 << 3) | idx;
+                let size = 100 + ino * 10;
+                put_inode_at(
+                    &mut image,
+                    ag * 64 + 4,
+                    idx,
+                    ino as u64,
+                    0x81A4,
+                    0,
+                    size as u64,
+                );
+            }
+        }
+
+        let scan = |parallel_ags| {
+            let config = XfsRecoveryConfig {
+                parallel_ags,
+                ..Default::default()
+            };
+            let engine =
+                XfsRecoveryEngine::new_with_config(BlockDevice::from_vec(image.clone()), config)
+                    .unwrap();
+            engine
+                .scan_deleted_files()
+                .unwrap()
+                .into_iter()
+                .map(|file| (file.id, file.inode_or_cluster, file.size))
+                .collect::<Vec<_>>()
+        };
+
+        let sequential = scan(false);
+        let parallel = scan(true);
+        assert_eq!(parallel, sequential);
+
+        // AG order, then inode order, numbered from 1
+        let inodes: Vec<u64> = parallel.iter().map(|&(_, ino, _)| ino).take(12).collect();
+        let mut sorted = inodes.clone();
+        sorted.sort_unstable();
+        assert_eq!(inodes, sorted);
+        assert_eq!(inodes.first(), Some(&32));
+        assert_eq!(inodes.last(), Some(&(3 * 512 + 34)));
+        assert!(parallel
+            .iter()
+            .enumerate()
+            .all(|(i, &(id, _, _))| id == i as u64 + 1));
+    }
+
+    #[test]
+    fn test_cancelled_scan_reads_no_inode_tables() {
+        let mut image = single_ag_image();
+        put_inode(&mut image, 0, 32, 0x81A4, 0, 100);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let engine = XfsRecoveryEngine::new(BlockDevice::from_vec(image))
+            .unwrap()
+            .with_cancellation(token);
+        let files = engine.scan_deleted_files().unwrap();
+        assert!(files.iter().all(|f| f.inode_or_cluster != 32));
+    }
+
+    #[test]
+    fn test_reallocated_blocks_lower_confidence() {
+        use crate::recovery::{calculate_confidence_score, ActivityLevel, ConfidenceContext};
+
+        let mut files = vec![crate::DeletedFile {
+            id: 1,
+            inode_or_cluster: 131,
+            original_path: Some("/home/user/report.pdf".into()),
+            size: 10 * 4096,
+            deletion_time: Some(chrono::Utc::now() - chrono::Duration::hours(1)),
+            confidence_score: 0.0,
+            file_type: crate::FileType::RegularFile,
+            data_blocks: vec![crate::BlockRange {
+                start_block: 10,
+                block_count: 10,
+                is_allocated: false,
+            }],
+            is_recoverable: true,
+            metadata: crate::FileMetadata {
+                mime_type: Some("application/pdf".to_string()),
+                file_extension: Some("pdf".to_string()),
+                permissions: Some(0o644),
+                owner_uid: Some(1000),
+                owner_gid: Some(1000),
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        }];
+        let context = ConfidenceContext {
+            fs_type: crate::FileSystemType::Xfs,
+            scan_time: chrono::Utc::now(),
+            filesystem_integrity: 1.0,
+            total_files_found: 1,
+            device_activity_level: ActivityLevel::Low,
+            weights: Default::default(),
+            block_size: 4096,
+        };
+
+        // Everything still free: nothing is flagged
+        mark_reallocated_blocks(&mut files, &RangeSet::from_ranges([(0, 100)]));
+        assert!(files[0].data_blocks.iter().all(|r| !r.is_allocated));
+        let intact = calculate_confidence_score(&files[0], &context);
+
+        // Blocks 14..20 were handed to another file
+        mark_reallocated_blocks(&mut files, &RangeSet::from_ranges([(0, 14), (20, 80)]));
+        let blocks = &files[0].data_blocks;
+        assert_eq!(blocks.len(), 2);
+        assert_eq!((blocks[0].start_block, blocks[0].block_count), (10, 4));
+        assert!(!blocks[0].is_allocated);
+        assert_eq!((blocks[1].start_block, blocks[1].block_count), (14, 6));
+        assert!(blocks[1].is_allocated);
+
+        let reused = calculate_confidence_score(&files[0], &context);
+        assert!(
+            reused < intact,
+            "confidence should drop when blocks are reused ({} >= {})",
+            reused,
+            intact
+        );
+    }
 }
@@ -0,0 +1,268 @@
+/// XFS extended attributes from an inode's attribute fork
+///
+/// Small attribute sets are kept in the inode itself in short form; larger
+/// ones in leaf blocks that the fork maps with extent records. Values too
+/// large for a leaf block live in blocks of their own and are not read.
+use std::collections::HashMap;
+
+use super::log::parse_extent_records;
+use super::{XFS_DINODE_CORE_SIZE, XFS_DINODE_FORKOFF_OFFSET, XFS_DINODE_V3_CORE_SIZE};
+
+/// Offset of `di_aformat`, the attribute fork format, in the inode core
+const XFS_DINODE_AFORMAT_OFFSET: usize = 83;
+
+/// On-disk `di_aformat` values
+const XFS_ATTR_FORK_LOCAL: u8 = 1;
+const XFS_ATTR_FORK_EXTENTS: u8 = 2;
+
+/// Attribute leaf block magic, without and with CRCs
+const XFS_ATTR_LEAF_MAGIC: u16 = 0xFBEE;
+const XFS_ATTR3_LEAF_MAGIC: u16 = 0x3BEE;
+
+/// Start of the entry table in v4 and v5 leaf blocks
+const XFS_ATTR_LEAF_ENTRIES_OFFSET: usize = 32;
+const XFS_ATTR3_LEAF_ENTRIES_OFFSET: usize = 80;
+
+/// Attribute entry flags
+const XFS_ATTR_LOCAL: u8 = 0x01;
+const XFS_ATTR_ROOT: u8 = 0x02;
+const XFS_ATTR_SECURE: u8 = 0x04;
+const XFS_ATTR_PARENT: u8 = 0x08;
+const XFS_ATTR_INCOMPLETE: u8 = 0x80;
+
+/// Where an inode keeps its attributes
+#[derive(Debug, PartialEq, Eq)]
+pub enum AttrFork<'a> {
+    /// Short-form attributes inside the inode
+    Local(&'a [u8]),
+    /// Leaf blocks, as (filesystem block, block count) extents
+    Extents(Vec<(u64, u64)>),
+}
+
+/// The attribute fork of an inode, if it has one
+pub fn attr_fork(inode_data: &[u8]) -> Option<AttrFork<'_>> {
+    if inode_data.len() < XFS_DINODE_CORE_SIZE {
+        return None;
+    }
+    let literal_start = if inode_data[4] == 3 {
+        XFS_DINODE_V3_CORE_SIZE
+    } else {
+        XFS_DINODE_CORE_SIZE
+    };
+    // The fork begins forkoff * 8 bytes into the literal area; zero means none
+    let fork = match inode_data[XFS_DINODE_FORKOFF_OFFSET] as usize {
+        0 => return None,
+        forkoff => inode_data.get(literal_start + forkoff * 8..)?,
+    };
+
+    match inode_data[XFS_DINODE_AFORMAT_OFFSET] {
+        XFS_ATTR_FORK_LOCAL => Some(AttrFork::Local(fork)),
+        XFS_ATTR_FORK_EXTENTS => Some(AttrFork::Extents(parse_extent_records(fork))),
+        _ => None,
+    }
+}
+
+/// Attributes of a short-form attribute fork
+pub fn parse_shortform(fork: &[u8]) -> HashMap<String, Vec<u8>> {
+    let mut attributes = HashMap::new();
+    if fork.len() < 4 {
+        return attributes;
+    }
+    let total_size = (u16::from_be_bytes([fork[0], fork[1]]) as usize).min(fork.len());
+    let count = fork[2];
+
+    let mut offset = 4;
+    for _ in 0..count {
+        let Some(&[name_len, value_len, flags]) = fork.get(offset..offset + 3) else {
+            break;
+        };
+        let name_start = offset + 3;
+        let value_start = name_start + name_len as usize;
+        let end = value_start + value_len as usize;
+        if end > total_size {
+            break;
+        }
+        if let Some(name) = qualified_name(flags, &fork[name_start..value_start]) {
+            attributes.insert(name, fork[value_start..end].to_vec());
+        }
+        offset = end;
+    }
+    attributes
+}
+
+/// Attributes stored in an attribute leaf block
+///
+/// Blocks that are not leaves (interior nodes of a larger attribute tree,
+/// remote values) yield nothing.
+pub fn parse_leaf_block(block: &[u8]) -> HashMap<String, Vec<u8>> {
+    let mut attributes = HashMap::new();
+    let Some(magic) = block.get(8..10).map(|m| u16::from_be_bytes([m[0], m[1]])) else {
+        return attributes;
+    };
+    let (count_offset, entries_offset) = match magic {
+        XFS_ATTR_LEAF_MAGIC => (12, XFS_ATTR_LEAF_ENTRIES_OFFSET),
+        XFS_ATTR3_LEAF_MAGIC => (56, XFS_ATTR3_LEAF_ENTRIES_OFFSET),
+        _ => return attributes,
+    };
+    let count = u16::from_be_bytes([block[count_offset], block[count_offset + 1]]) as usize;
+
+    for index in 0..count {
+        let entry_offset = entries_offset + index * 8;
+        let Some(entry) = block.get(entry_offset..entry_offset + 8) else {
+            break;
+        };
+        let name_index = u16::from_be_bytes([entry[4], entry[5]]) as usize;
+        let flags = entry[6];
+
+        if flags & XFS_ATTR_LOCAL == 0 {
+            // Remote value: block number, value length, name length, name
+            if let Some(name_len) = block.get(name_index + 8) {
+                let name = block.get(name_index + 9..name_index + 9 + *name_len as usize);
+                tracing::debug!(
+                    "Skipping remote XFS attribute value of {:?}",
+                    name.map(String::from_utf8_lossy)
+                );
+            }
+            continue;
+        }
+
+        // Local value: value length, name length, name, value
+        let Some(header) = block.get(name_index..name_index + 3) else {
+            continue;
+        };
+        let value_len = u16::from_be_bytes([header[0], header[1]]) as usize;
+        let name_start = name_index + 3;
+        let value_start = name_start + header[2] as usize;
+        let Some(value) = block.get(value_start..value_start + value_len) else {
+            continue;
+        };
+        if let Some(name) = qualified_name(flags, &block[name_start..value_start]) {
+            attributes.insert(name, value.to_vec());
+        }
+    }
+    attributes
+}
+
+/// Attribute name with its namespace prefix, as `getfattr` shows it
+///
+/// Parent pointers and attributes left half-written are skipped.
+fn qualified_name(flags: u8, name: &[u8]) -> Option<String> {
+    if flags & (XFS_ATTR_PARENT | XFS_ATTR_INCOMPLETE) != 0 || name.is_empty() {
+        return None;
+    }
+    let namespace = if flags & XFS_ATTR_SECURE != 0 {
+        "security"
+    } else if flags & XFS_ATTR_ROOT != 0 {
+        "trusted"
+    } else {
+        "user"
+    };
+    Some(format!("{}.{}", namespace, String::from_utf8_lossy(name)))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Short-form attribute fork holding (flags, name, value) entries
+    pub(crate) fn shortform_attrs(entries: &[(u8, &str, &[u8])]) -> Vec<u8> {
+        let mut fork = vec![0, 0, entries.len() as u8, 0];
+        for (flags, name, value) in entries {
+            fork.extend_from_slice(&[name.len() as u8, value.len() as u8, *flags]);
+            fork.extend_from_slice(name.as_bytes());
+            fork.extend_from_slice(value);
+        }
+        let total_size = fork.len() as u16;
+        fork[0..2].copy_from_slice(&total_size.to_be_bytes());
+        fork
+    }
+
+    #[test]
+    fn test_parse_shortform_namespaces() {
+        let fork = shortform_attrs(&[
+            (0, "mime_type", b"image/png"),
+            (
+                XFS_ATTR_SECURE,
+                "selinux",
+                b"system_u:object_r:user_home_t:s0\0",
+            ),
+            (XFS_ATTR_ROOT, "SGI_ACL_FILE", &[0, 0, 0, 4]),
+            (XFS_ATTR_PARENT, "parent", b"ignored"),
+        ]);
+
+        let attributes = parse_shortform(&fork);
+        assert_eq!(attributes.len(), 3);
+        assert_eq!(attributes["user.mime_type"], b"image/png");
+        assert_eq!(
+            attributes["security.selinux"],
+            b"system_u:object_r:user_home_t:s0\0"
+        );
+        assert_eq!(attributes["trusted.SGI_ACL_FILE"], [0, 0, 0, 4]);
+    }
+
+    #[test]
+    fn test_attr_fork_location() {
+        let mut inode = vec![0u8; 512];
+        inode[4] = 3;
+        assert_eq!(attr_fork(&inode), None);
+
+        let fork = shortform_attrs(&[(0, "origin", b"camera")]);
+        inode[XFS_DINODE_FORKOFF_OFFSET] = 15;
+        inode[XFS_DINODE_AFORMAT_OFFSET] = XFS_ATTR_FORK_LOCAL;
+        let start = XFS_DINODE_V3_CORE_SIZE + 15 * 8;
+        inode[start..start + fork.len()].copy_from_slice(&fork);
+
+        let Some(AttrFork::Local(found)) = attr_fork(&inode) else {
+            panic!("expected a local attribute fork");
+        };
+        assert_eq!(parse_shortform(found)["user.origin"], b"camera");
+
+        // One extent record: block 300, 1 block
+        inode[XFS_DINODE_AFORMAT_OFFSET] = XFS_ATTR_FORK_EXTENTS;
+        inode[start..start + 16].fill(0);
+        inode[start + 8..start + 16].copy_from_slice(&((300u64 << 21) | 1).to_be_bytes());
+        assert_eq!(attr_fork(&inode), Some(AttrFork::Extents(vec![(300, 1)])));
+    }
+
+    #[test]
+    fn test_parse_v5_leaf_block() {
+        let mut block = vec![0u8; 4096];
+        block[8..10].copy_from_slice(&XFS_ATTR3_LEAF_MAGIC.to_be_bytes());
+        block[56..58].copy_from_slice(&3u16.to_be_bytes());
+
+        let mut put_entry = |index: usize, name_index: u16, flags: u8| {
+            let offset = XFS_ATTR3_LEAF_ENTRIES_OFFSET + index * 8;
+            block[offset + 4..offset + 6].copy_from_slice(&name_index.to_be_bytes());
+            block[offset + 6] = flags;
+        };
+        put_entry(0, 4000, XFS_ATTR_LOCAL);
+        put_entry(1, 3900, XFS_ATTR_LOCAL | XFS_ATTR_SECURE);
+        put_entry(2, 3800, 0);
+
+        let local = |name: &str, value: &[u8]| {
+            let mut bytes = (value.len() as u16).to_be_bytes().to_vec();
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(value);
+            bytes
+        };
+        let user = local("mime_type", b"text/plain");
+        block[4000..4000 + user.len()].copy_from_slice(&user);
+        let selinux = local("selinux", b"unconfined_u:object_r:tmp_t:s0\0");
+        block[3900..3900 + selinux.len()].copy_from_slice(&selinux);
+        // Remote: value block, value length, name length, name
+        block[3808] = 5;
+        block[3809..3814].copy_from_slice(b"large");
+
+        let attributes = parse_leaf_block(&block);
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes["user.mime_type"], b"text/plain");
+        assert_eq!(
+            attributes["security.selinux"],
+            b"unconfined_u:object_r:tmp_t:s0\0"
+        );
+
+        // Not a leaf block
+        assert!(parse_leaf_block(&[0u8; 4096]).is_empty());
+    }
+}
@@ -1,32 +1,39 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 pub mod forensics;
 pub mod fs;
 pub mod recovery;
+pub mod session;
 pub mod timeline;
 
 // Re-export key recovery types
 pub use recovery::{
-    ActivityLevel, ConfidenceReport, ExtentReconstructor, FileSignature, FragmentCatalog,
-    GapInfo, PartialRecovery, PartialRecoveryResult, ReconstructionResult,
-    ReconstructionStrategy, RecoveryConfig, RecoveryEngine, RecoveryError, RecoveryProgress,
-    RecoveryResult, RecoveryStage, SignatureAnalysisResult,
+    carve_device, ActivityLevel, CancellationToken, CarveConfig, ConfidenceReport,
+    ConfidenceWeights, ExtentReconstructor, FileSignature, FragmentCatalog, GapInfo,
+    PartialRecovery, PartialRecoveryResult, ReconstructionResult, ReconstructionStrategy,
+    RecoveryConfig, RecoveryEngine, RecoveryError, RecoveryProgress, RecoveryResult, RecoveryStage,
+    RecoveryStrategy, SignatureAnalysisResult,
 };
 
 // Re-export timeline types
-pub use timeline::{DeletionPattern, PatternType, RecoveryTimeline, TimelineStatistics};
+pub use timeline::{
+    DeletionPattern, DeletionWindow, PatternConfig, PatternType, RecoveryTimeline,
+    TimelineStatistics,
+};
 
 // Re-export forensics types
 pub use forensics::{
-    calculate_file_hash, calculate_hash, recover_files_with_forensics, verify_file_integrity,
-    AuditEntry, AuditEvent, AuditEventType, AuditLog, AuditLogger, FileHash, ForensicsConfig,
+    calculate_device_hash, calculate_file_hash, calculate_hash, calculate_image_hash,
+    generate_session_manifest, recover_files_with_forensics, verify_device_integrity,
+    verify_file_integrity, verify_image_integrity, AuditEntry, AuditEvent, AuditEventType,
+    AuditLog, AuditLogger, CaseMetadata, FileHash, ForensicsConfig, ForensicsFileRecord,
     ForensicsRecoveryReport, HashAlgorithm, HashManifest, HashVerification, VerificationStatus,
 };
 
@@ -60,6 +67,9 @@ pub struct RecoverySession {
     pub total_scanned: u64,
     pub confidence_threshold: f32,
     pub metadata: SessionMetadata,
+    /// Case the session belongs to; absent for sessions saved before it was recorded
+    #[serde(default)]
+    pub case_metadata: Option<forensics::CaseMetadata>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,6 +80,9 @@ pub struct SessionMetadata {
     pub scan_duration_ms: u64,
     pub files_found: u32,
     pub recoverable_files: u32,
+    /// Health of the scanned file system; absent for sessions saved before it was recorded
+    #[serde(default)]
+    pub filesystem_health: Option<fs::FilesystemHealth>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,6 +101,32 @@ pub struct DeletedFile {
     /// Filesystem-specific metadata for confidence scoring
     /// Serialized to preserve full recovery session fidelity when saving/loading sessions
     pub fs_metadata: Option<FsSpecificMetadata>,
+
+    /// Why the scan marked the file unrecoverable, if it did; set by GhostFS,
+    /// unlike `metadata`, which only holds what was found on the media
+    #[serde(default)]
+    pub skip_reason: Option<String>,
+}
+
+impl DeletedFile {
+    /// Contents carried in the file's metadata rather than in `data_blocks`,
+    /// such as a Btrfs inline extent or the data of a local-format XFS inode
+    pub fn inline_data(&self) -> Option<&[u8]> {
+        match self.fs_metadata {
+            Some(FsSpecificMetadata::Btrfs(ref meta)) => meta.inline_data.as_deref(),
+            Some(FsSpecificMetadata::Xfs(ref meta)) => meta.inline_data.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Parts of the file that have no data on the device and read as zeros,
+    /// such as XFS holes and unwritten extents, in file order
+    pub fn sparse_ranges(&self) -> &[SparseRange] {
+        match self.fs_metadata {
+            Some(FsSpecificMetadata::Xfs(ref meta)) => &meta.sparse_ranges,
+            _ => &[],
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,13 +136,19 @@ pub struct FileMetadata {
     pub permissions: Option<u32>,
     pub owner_uid: Option<u32>,
     pub owner_gid: Option<u32>,
+    /// User name of `owner_uid`, see [`recovery::ownership::resolve_ownership`]
+    #[serde(default)]
+    pub owner_name: Option<String>,
+    /// Group name of `owner_gid`
+    #[serde(default)]
+    pub group_name: Option<String>,
     pub created_time: Option<DateTime<Utc>>,
     pub modified_time: Option<DateTime<Utc>>,
     pub accessed_time: Option<DateTime<Utc>>,
     pub extended_attributes: HashMap<String, Vec<u8>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileType {
     RegularFile,
     Directory,
@@ -115,6 +160,34 @@ pub enum FileType {
     Unknown,
 }
 
+/// File type bits of a POSIX mode
+const S_IFMT: u16 = 0o170000;
+
+impl FileType {
+    /// Type from the `S_IFMT` bits of a POSIX mode, as stored in XFS and Btrfs inodes
+    pub fn from_mode(mode: u16) -> Self {
+        match mode & S_IFMT {
+            0o100000 => Self::RegularFile,
+            0o040000 => Self::Directory,
+            0o120000 => Self::SymbolicLink,
+            0o060000 => Self::BlockDevice,
+            0o020000 => Self::CharacterDevice,
+            0o010000 => Self::Fifo,
+            0o140000 => Self::Socket,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Type from exFAT file attributes, which only distinguish directories from files
+    pub fn from_exfat_attributes(attributes: u16) -> Self {
+        if attributes & fs::exfat::directory::ATTR_DIRECTORY != 0 {
+            Self::Directory
+        } else {
+            Self::RegularFile
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockRange {
     pub start_block: u64,
@@ -122,6 +195,53 @@ pub struct BlockRange {
     pub is_allocated: bool,
 }
 
+impl BlockRange {
+    /// Split the range into runs that are uniformly allocated or free
+    ///
+    /// `is_allocated` is asked about the range position at every `unit`
+    /// (e.g. once per cluster when the range is measured in bytes), and
+    /// consecutive positions with the same answer are merged into one run.
+    pub fn split_by_allocation<F>(&self, unit: u64, is_allocated: F) -> Vec<BlockRange>
+    where
+        F: Fn(u64) -> bool,
+    {
+        let unit = unit.max(1);
+        let end = self.start_block + self.block_count;
+        let mut runs: Vec<BlockRange> = Vec::new();
+        let mut position = self.start_block;
+
+        while position < end {
+            let step_end = std::cmp::min(end, (position / unit + 1) * unit);
+            let allocated = is_allocated(position);
+
+            match runs.last_mut() {
+                Some(run) if run.is_allocated == allocated => {
+                    run.block_count += step_end - position;
+                }
+                _ => runs.push(BlockRange {
+                    start_block: position,
+                    block_count: step_end - position,
+                    is_allocated: allocated,
+                }),
+            }
+            position = step_end;
+        }
+
+        runs
+    }
+}
+
+/// Bytes of a file with no data blocks, which read as zeros
+///
+/// The file's `data_blocks` hold the rest of it, so its contents are the
+/// data blocks with these spliced in at their offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparseRange {
+    /// Offset in the file
+    pub offset: u64,
+    pub length: u64,
+}
+
 /// Filesystem-specific metadata for confidence scoring
 /// This metadata is crucial for accurate confidence calculations and is now fully serializable
 /// to support session persistence and recovery result caching.
@@ -149,6 +269,12 @@ pub struct XfsFileMetadata {
     pub last_link_count: u32,
     /// XFS generation counter
     pub inode_generation: u32,
+    /// Contents of a local-format inode, stored in the inode itself
+    #[serde(default)]
+    pub inline_data: Option<Vec<u8>>,
+    /// Holes and unwritten extents, which are left out of `data_blocks`
+    #[serde(default)]
+    pub sparse_ranges: Vec<SparseRange>,
 }
 
 /// XFS extent storage format
@@ -179,6 +305,32 @@ pub struct BtrfsFileMetadata {
     pub extent_refs: Vec<u64>,
     /// Level in B-tree (0 = leaf)
     pub tree_level: u8,
+    /// Compressed extents that must be decompressed during recovery
+    #[serde(default)]
+    pub compressed_extents: Vec<BtrfsCompressedExtent>,
+    /// Generation of the backup root the file was found through, `None` for the current tree
+    #[serde(default)]
+    pub backup_generation: Option<u64>,
+    /// Contents of a small file stored inline in its extent item, decompressed
+    #[serde(default)]
+    pub inline_data: Option<Vec<u8>>,
+}
+
+/// A compressed Btrfs file extent, mapped onto one of the file's `data_blocks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BtrfsCompressedExtent {
+    /// Index into `DeletedFile.data_blocks` holding the compressed bytes
+    pub block_index: usize,
+    /// Compression type (1=zlib, 2=lz4, 3=zstd)
+    pub compression: u8,
+    /// Size of the compressed data on disk
+    pub disk_num_bytes: u64,
+    /// Uncompressed size of the extent
+    pub ram_bytes: u64,
+    /// Offset of the file data within the uncompressed extent
+    pub extent_offset: u64,
+    /// Number of file bytes referenced from the uncompressed extent
+    pub num_bytes: u64,
 }
 
 /// exFAT-specific file metadata for confidence scoring
@@ -222,6 +374,7 @@ pub fn scan_image(image_path: &Path, fs: FileSystemType) -> Result<RecoverySessi
 
     // For now, create a basic session with placeholder data
     // This will be replaced with actual file system scanning logic
+    let device = fs::common::BlockDevice::open(image_path)?;
     let session = RecoverySession {
         id: Uuid::new_v4(),
         fs_type: fs,
@@ -231,13 +384,15 @@ pub fn scan_image(image_path: &Path, fs: FileSystemType) -> Result<RecoverySessi
         total_scanned: 0,
         confidence_threshold: 0.5,
         metadata: SessionMetadata {
-            device_size: std::fs::metadata(image_path)?.len(),
+            device_size: device.size(),
             filesystem_size: 0,
-            block_size: 4096,
+            block_size: fs::detect_block_size(&device, fs).unwrap_or(fs::DEFAULT_BLOCK_SIZE),
             scan_duration_ms: 0,
             files_found: 0,
             recoverable_files: 0,
+            filesystem_health: None,
         },
+        case_metadata: None,
     };
 
     tracing::info!(
@@ -249,6 +404,48 @@ pub fn scan_image(image_path: &Path, fs: FileSystemType) -> Result<RecoverySessi
     Ok(session)
 }
 
+/// Strategies a scan runs unless told otherwise
+pub const DEFAULT_SCAN_STRATEGIES: [recovery::RecoveryStrategy; 4] = [
+    recovery::RecoveryStrategy::DirectoryTableScan,
+    recovery::RecoveryStrategy::FileSignatureScan,
+    recovery::RecoveryStrategy::JournalAnalysis,
+    recovery::RecoveryStrategy::MetadataReconstruction,
+];
+
+/// Options for `scan_and_analyze_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Custom XFS configuration, `None` for adaptive defaults
+    pub xfs_config: Option<fs::xfs::XfsRecoveryConfig>,
+    /// Strategies to run, `None` for `DEFAULT_SCAN_STRATEGIES`
+    pub strategies: Option<Vec<recovery::RecoveryStrategy>>,
+    /// Session database to checkpoint progress to
+    pub checkpoint_db: Option<PathBuf>,
+    /// Continue an interrupted scan from its checkpoint in `checkpoint_db`
+    pub resume: bool,
+    /// Device byte ranges left out of content carving, `None` to carve everything
+    pub skip_regions: Option<fs::common::RangeSet>,
+    /// Also search the trees of the Btrfs superblock's backup roots
+    pub btrfs_backup_roots: bool,
+    /// Hex SHA-256 recorded when the image was acquired; the image is hashed
+    /// before scanning and the scan refused if it does not match
+    pub expected_image_hash: Option<String>,
+    /// Directory to write an audit trail of the scan to
+    pub audit_dir: Option<PathBuf>,
+    /// Case the session belongs to, saved with it and recorded in its audit trail
+    pub case_metadata: Option<forensics::CaseMetadata>,
+    /// Only list deleted files from the file system metadata, for triage;
+    /// see `RecoveryConfig::list_only`
+    pub list_only: bool,
+    /// Directory for temporary files, see `RecoveryConfig::temp_dir`
+    pub temp_dir: Option<PathBuf>,
+    /// Weights of the confidence factors, `None` for the defaults
+    pub confidence_weights: Option<recovery::ConfidenceWeights>,
+    /// Signatures the carving scans identify files by, e.g. the built-in
+    /// ones plus those from [`recovery::load_definitions`]
+    pub signatures: recovery::SignatureSet,
+}
+
 /// Scan and analyze using the advanced recovery engine
 pub fn scan_and_analyze(image_path: &Path, fs: FileSystemType) -> Result<RecoverySession> {
     scan_and_analyze_with_config(image_path, fs, None)
@@ -260,52 +457,239 @@ pub fn scan_and_analyze_with_config(
     fs: FileSystemType,
     xfs_config: Option<fs::xfs::XfsRecoveryConfig>,
 ) -> Result<RecoverySession> {
-    use memmap2::MmapOptions;
-    use recovery::{RecoveryConfig, RecoveryEngine, RecoveryStrategy, ScanDepth};
-    use std::fs::File;
+    let options = ScanOptions {
+        xfs_config,
+        ..Default::default()
+    };
+    run_scan(image_path, fs, options, None)
+}
+
+/// Scan and analyze, passing every engine progress update to `progress`
+///
+/// The callback runs on the scanning thread; callers that need a responsive
+/// UI should run the scan on a worker thread and forward the updates.
+pub fn scan_and_analyze_with_progress<F>(
+    image_path: &Path,
+    fs: FileSystemType,
+    progress: F,
+) -> Result<RecoverySession>
+where
+    F: Fn(RecoveryProgress) + Send + Sync + 'static,
+{
+    run_scan(
+        image_path,
+        fs,
+        ScanOptions::default(),
+        Some(Box::new(progress)),
+    )
+}
+
+/// Scan and analyze, checkpointing progress to the session database at `db_path`
+///
+/// With `resume`, an interrupted scan of the same image continues from its
+/// last checkpoint; if there is none a new scan is started.
+pub fn scan_and_analyze_with_checkpoints(
+    image_path: &Path,
+    fs: FileSystemType,
+    xfs_config: Option<fs::xfs::XfsRecoveryConfig>,
+    db_path: &Path,
+    resume: bool,
+) -> Result<RecoverySession> {
+    let options = ScanOptions {
+        xfs_config,
+        checkpoint_db: Some(db_path.to_path_buf()),
+        resume,
+        ..Default::default()
+    };
+    run_scan(image_path, fs, options, None)
+}
+
+/// Scan and analyze with the given options
+pub fn scan_and_analyze_with_options(
+    image_path: &Path,
+    fs: FileSystemType,
+    options: ScanOptions,
+) -> Result<RecoverySession> {
+    run_scan(image_path, fs, options, None)
+}
+
+/// Scan and analyze with the given options, passing every engine progress update to `progress`
+pub fn scan_and_analyze_with_options_and_progress<F>(
+    image_path: &Path,
+    fs: FileSystemType,
+    options: ScanOptions,
+    progress: F,
+) -> Result<RecoverySession>
+where
+    F: Fn(RecoveryProgress) + Send + Sync + 'static,
+{
+    run_scan(image_path, fs, options, Some(Box::new(progress)))
+}
+
+fn run_scan(
+    image_path: &Path,
+    fs: FileSystemType,
+    options: ScanOptions,
+    progress: Option<Box<dyn Fn(RecoveryProgress) + Send + Sync>>,
+) -> Result<RecoverySession> {
+    use recovery::{CancellationToken, RecoveryConfig, RecoveryEngine, ScanDepth};
+
+    let recovery_strategies = options
+        .strategies
+        .unwrap_or_else(|| DEFAULT_SCAN_STRATEGIES.to_vec());
+    if recovery_strategies.is_empty() {
+        anyhow::bail!("At least one recovery strategy is required");
+    }
 
     // Software auto-determines recoverability based on confidence scoring
     // Files with >= 40% confidence are marked as recoverable
     const AUTO_CONFIDENCE_THRESHOLD: f32 = 0.4;
 
-    let file = File::open(image_path)?;
-    let mmap = unsafe { MmapOptions::new().map(&file)? };
+    let session_id = Uuid::new_v4().to_string();
+    let mut case_metadata = options.case_metadata;
+
+    // The image is mapped once, for hashing, block size detection and the
+    // scan: mapping an EWF image decompresses it
+    let (_file, mmap, image_size) =
+        fs::common::map_read_only_in(image_path, options.temp_dir.as_deref())?;
+    let mmap = std::sync::Arc::new(mmap);
+    let mut device = fs::common::BlockDevice::from_mmap(std::sync::Arc::clone(&mmap));
+    if let Some(reads) = fs::common::FileReads::open(image_path)? {
+        device = device.with_fault_source(reads);
+    }
+
+    // The image is checked before anything is read from it for the scan
+    let verification = match &options.expected_image_hash {
+        Some(expected) => {
+            let verification = forensics::verify_device_integrity(
+                &device,
+                image_path,
+                Some(expected.trim()),
+                HashAlgorithm::SHA256,
+            )?;
+            if verification.status == VerificationStatus::Verified {
+                case_metadata
+                    .get_or_insert_with(Default::default)
+                    .acquisition_hash = Some(verification.actual_hash.clone());
+            }
+            Some(verification)
+        }
+        None => None,
+    };
+
+    let audit_logger = match &options.audit_dir {
+        Some(dir) => {
+            let log =
+                AuditLog::new(session_id.clone(), dir)?.with_case_metadata(case_metadata.clone());
+            let logger = AuditLogger::new(std::sync::Arc::new(log));
+            logger.session_start(&image_path.display().to_string())?;
+            Some(logger)
+        }
+        None => None,
+    };
+
+    if let Some(verification) = &verification {
+        if let Some(logger) = &audit_logger {
+            logger.acquisition_verified(verification)?;
+        }
+        if verification.status != VerificationStatus::Verified {
+            if let Some(logger) = &audit_logger {
+                logger.session_end("aborted: acquisition hash mismatch")?;
+            }
+            anyhow::bail!(
+                "{} hashes to {}, not the expected acquisition hash {}; refusing to scan",
+                image_path.display(),
+                verification.actual_hash,
+                verification.expected_hash.as_deref().unwrap_or_default()
+            );
+        }
+        tracing::info!("Acquisition hash of {} verified", image_path.display());
+    }
 
     // Create recovery configuration
-    let config = RecoveryConfig {
+    let mut config = RecoveryConfig {
         min_confidence_threshold: AUTO_CONFIDENCE_THRESHOLD,
         scan_depth: ScanDepth::Standard,
-        recovery_strategies: vec![
-            RecoveryStrategy::DirectoryTableScan,
-            RecoveryStrategy::FileSignatureScan,
-            RecoveryStrategy::MetadataReconstruction,
-        ],
-        xfs_config,
+        recovery_strategies,
+        xfs_config: options.xfs_config,
+        btrfs_backup_roots: options.btrfs_backup_roots,
+        list_only: options.list_only,
+        temp_dir: options.temp_dir,
+        confidence_weights: options.confidence_weights.unwrap_or_default(),
+        signatures: options.signatures,
         ..Default::default()
     };
 
+    let block_size = fs::detect_block_size(&device, fs).unwrap_or(fs::DEFAULT_BLOCK_SIZE);
+    config.block_size = Some(block_size);
+
     // Initialize recovery engine
-    let session_id = Uuid::new_v4().to_string();
-    let mut engine = RecoveryEngine::new(
-        fs,
-        mmap,
-        4096, // Default block size
-        session_id.clone(),
-        config,
-    );
+    let mut engine = RecoveryEngine::new(fs, mmap, block_size as usize, session_id.clone(), config);
+    if let Some(reads) = fs::common::FileReads::open(image_path)? {
+        engine.set_fault_source(reads);
+    }
+    if let Some(regions) = options.skip_regions {
+        engine.set_skip_regions(regions);
+    }
+    if let Some(logger) = &audit_logger {
+        engine.set_audit_logger(logger.clone());
+    }
 
     // Set up progress callback
-    engine.set_progress_callback(|progress| {
+    engine.set_progress_callback(move |update| {
         tracing::info!(
             "Recovery progress: {:.1}% - {} ({} files found)",
-            progress.progress_percent,
-            progress.current_operation,
-            progress.files_found
+            update.progress_percent,
+            update.current_operation,
+            update.files_found
         );
+        if let Some(ref progress) = progress {
+            progress(update);
+        }
+    });
+
+    // Checkpoints are keyed by the canonical image path so a resume finds them.
+    // They only make the scan resumable, so a database that cannot be opened
+    // does not stop the scan.
+    let mut checkpoint = None;
+    let checkpoint_db = options.checkpoint_db.as_ref().and_then(|db_path| {
+        session::SessionDatabase::open(db_path)
+            .map_err(|e| {
+                tracing::warn!(
+                    "Scanning without checkpoints, {} could not be opened: {:#}",
+                    db_path.display(),
+                    e
+                )
+            })
+            .ok()
     });
+    if let Some(db) = checkpoint_db {
+        let device_path =
+            std::fs::canonicalize(image_path).unwrap_or_else(|_| image_path.to_path_buf());
+        if options.resume {
+            checkpoint = db.load_checkpoint(&device_path)?;
+            if checkpoint.is_none() {
+                tracing::warn!(
+                    "No checkpoint found for {}, starting a new scan",
+                    device_path.display()
+                );
+            }
+        }
+        engine.enable_checkpoints(db, &device_path);
+    }
 
     // Execute recovery
-    let recovery_result = engine.execute_recovery()?;
+    let recovery_result = match checkpoint {
+        Some(checkpoint) => engine.resume_from_checkpoint(checkpoint, &CancellationToken::new())?,
+        None => engine.execute_recovery(&CancellationToken::new())?,
+    };
+    if !recovery_result.read_errors.bad_sectors.is_empty() {
+        tracing::warn!(
+            "{} sectors of {} could not be read",
+            recovery_result.read_errors.bad_sectors.len(),
+            image_path.display()
+        );
+    }
 
     // Convert to legacy session format
     let session = RecoverySession {
@@ -317,15 +701,24 @@ pub fn scan_and_analyze_with_config(
         total_scanned: recovery_result.total_files_found as u64,
         confidence_threshold: AUTO_CONFIDENCE_THRESHOLD,
         metadata: SessionMetadata {
-            device_size: std::fs::metadata(image_path)?.len(),
-            filesystem_size: std::fs::metadata(image_path)?.len(),
-            block_size: 4096,
-            scan_duration_ms: 0, // TODO: Track actual duration
+            device_size: image_size,
+            filesystem_size: image_size,
+            block_size,
+            scan_duration_ms: recovery_result.scan_duration_ms,
             files_found: recovery_result.total_files_found as u32,
             recoverable_files: recovery_result.recoverable_files as u32,
+            filesystem_health: Some(recovery_result.filesystem_health),
         },
+        case_metadata,
     };
 
+    if let Some(logger) = &audit_logger {
+        logger.session_end(&format!(
+            "{} files found, {} recoverable",
+            recovery_result.total_files_found, recovery_result.recoverable_files
+        ))?;
+    }
+
     tracing::info!(
         "Recovery complete: {} files found, {} recoverable (auto-threshold: {})",
         recovery_result.total_files_found,
@@ -337,41 +730,47 @@ pub fn scan_and_analyze_with_config(
 }
 
 /// Recover files from a session to the specified output directory
+///
+/// `layout` decides the subdirectories files are written to, see
+/// [`OutputLayout`].
 pub fn recover_files(
     image_path: &Path,
     session: &RecoverySession,
     output_dir: &Path,
     file_ids: Option<Vec<u64>>,
+    layout: OutputLayout,
+) -> Result<RecoveryReport> {
+    // Open the source image for reading
+    let device = fs::common::BlockDevice::open(image_path)?;
+
+    recover_files_from_device(&device, session, output_dir, file_ids, layout)
+}
+
+/// [`recover_files`] reading from an already open device
+///
+/// Unreadable parts of a file do not abort it: they are zero-filled, so the
+/// rest keeps its offsets, and the file is reported as
+/// [`RecoveryStatus::Partial`] with the damaged ranges.
+pub fn recover_files_from_device(
+    device: &fs::common::BlockDevice,
+    session: &RecoverySession,
+    output_dir: &Path,
+    file_ids: Option<Vec<u64>>,
+    layout: OutputLayout,
 ) -> Result<RecoveryReport> {
-    use memmap2::MmapOptions;
     use std::fs::create_dir_all;
 
     // Create output directory if it doesn't exist
     create_dir_all(output_dir)?;
 
-    // Open the source image for reading
-    let source_file = std::fs::File::open(image_path)?;
-    let mmap = unsafe { MmapOptions::new().map(&source_file)? };
-
     let mut recovered_count = 0;
+    let mut partial_count = 0;
     let mut failed_count = 0;
     let mut total_bytes_recovered = 0u64;
     let mut recovery_details = Vec::new();
 
-    // Filter files to recover
-    let files_to_recover: Vec<&DeletedFile> = if let Some(ids) = file_ids {
-        session
-            .scan_results
-            .iter()
-            .filter(|f| ids.contains(&f.id))
-            .collect()
-    } else {
-        session
-            .scan_results
-            .iter()
-            .filter(|f| f.is_recoverable)
-            .collect()
-    };
+    let files_to_recover = select_files_to_recover(session, file_ids);
+    let output_paths = assign_recovery_paths(output_dir, &session.scan_results, layout);
 
     tracing::info!(
         "Starting recovery of {} files to {}",
@@ -380,31 +779,53 @@ pub fn recover_files(
     );
 
     for deleted_file in &files_to_recover {
-        match recover_single_file(&mmap, deleted_file, output_dir, session.fs_type) {
-            Ok(bytes_recovered) => {
+        let output_path = &output_paths[&deleted_file.id];
+        match recover_single_file(
+            device,
+            deleted_file,
+            output_path,
+            session.fs_type,
+            session.metadata.block_size,
+        ) {
+            Ok((bytes_written, damaged)) => {
+                let unreadable: u64 = damaged.iter().map(|range| range.length).sum();
+                let bytes_recovered = bytes_written - unreadable;
                 recovered_count += 1;
                 total_bytes_recovered += bytes_recovered;
+
+                let status = if damaged.is_empty() {
+                    tracing::info!(
+                        "✅ Recovered file ID {} ({} bytes)",
+                        deleted_file.id,
+                        bytes_recovered
+                    );
+                    RecoveryStatus::Success
+                } else {
+                    partial_count += 1;
+                    tracing::warn!(
+                        "⚠️ Partially recovered file ID {} ({} bytes unreadable, first at offset {})",
+                        deleted_file.id,
+                        unreadable,
+                        damaged[0].offset
+                    );
+                    RecoveryStatus::Partial(damaged)
+                };
                 recovery_details.push(FileRecoveryResult {
                     file_id: deleted_file.id,
                     original_path: deleted_file.original_path.clone(),
-                    recovered_path: generate_recovery_path(output_dir, deleted_file),
+                    recovered_path: output_path.clone(),
                     size: deleted_file.size,
                     bytes_recovered,
-                    status: RecoveryStatus::Success,
+                    status,
                     confidence_score: deleted_file.confidence_score,
                 });
-                tracing::info!(
-                    "✅ Recovered file ID {} ({} bytes)",
-                    deleted_file.id,
-                    bytes_recovered
-                );
             }
             Err(e) => {
                 failed_count += 1;
                 recovery_details.push(FileRecoveryResult {
                     file_id: deleted_file.id,
                     original_path: deleted_file.original_path.clone(),
-                    recovered_path: generate_recovery_path(output_dir, deleted_file),
+                    recovered_path: output_path.clone(),
                     size: deleted_file.size,
                     bytes_recovered: 0,
                     status: RecoveryStatus::Failed(e.to_string()),
@@ -418,10 +839,12 @@ pub fn recover_files(
     let report = RecoveryReport {
         total_files: files_to_recover.len(),
         recovered_files: recovered_count,
+        partial_files: partial_count,
         failed_files: failed_count,
         total_bytes_recovered,
         output_directory: output_dir.to_path_buf(),
         recovery_details,
+        read_errors: device.bad_sector_report(),
     };
 
     tracing::info!(
@@ -434,39 +857,248 @@ pub fn recover_files(
     Ok(report)
 }
 
-/// Recover a single file from the memory-mapped source
-fn recover_single_file(
-    mmap: &memmap2::Mmap,
-    deleted_file: &DeletedFile,
+/// Work out what [`recover_files`] would write, without writing anything
+///
+/// Selects the same files and output paths, flags output paths that already
+/// exist and would be overwritten, and checks the free space of the file
+/// system `output_dir` is on. `output_dir` is not created.
+pub fn plan_recovery(
+    session: &RecoverySession,
     output_dir: &Path,
-    fs_type: FileSystemType,
+    file_ids: Option<Vec<u64>>,
+    layout: OutputLayout,
+) -> Result<RecoveryPlan> {
+    let files_to_recover = select_files_to_recover(session, file_ids);
+    let mut output_paths = assign_recovery_paths(output_dir, &session.scan_results, layout);
+
+    let entries: Vec<PlannedFile> = files_to_recover
+        .iter()
+        .map(|deleted_file| {
+            let output_path = output_paths.remove(&deleted_file.id).unwrap();
+            PlannedFile {
+                file_id: deleted_file.id,
+                original_path: deleted_file.original_path.clone(),
+                collides: output_path.exists(),
+                output_path,
+                bytes: deleted_file.size,
+            }
+        })
+        .collect();
+
+    let total_bytes = entries.iter().map(|entry| entry.bytes).sum();
+    let available_bytes = fs::common::available_space(output_dir)?;
+
+    Ok(RecoveryPlan {
+        collisions: entries.iter().filter(|entry| entry.collides).count(),
+        space_ok: available_bytes.is_none_or(|available| total_bytes <= available),
+        entries,
+        total_bytes,
+        available_bytes,
+    })
+}
+
+/// The session files `recover_files` works on: the given IDs, or every
+/// recoverable file
+fn select_files_to_recover(
+    session: &RecoverySession,
+    file_ids: Option<Vec<u64>>,
+) -> Vec<&DeletedFile> {
+    if let Some(ids) = file_ids {
+        session
+            .scan_results
+            .iter()
+            .filter(|f| ids.contains(&f.id))
+            .collect()
+    } else {
+        session
+            .scan_results
+            .iter()
+            .filter(|f| f.is_recoverable)
+            .collect()
+    }
+}
+
+/// Files larger than this are written with [`recover_file_streaming`]
+pub const STREAMING_RECOVERY_THRESHOLD: u64 = 64 * 1024 * 1024; // 64MB
+
+/// Maximum bytes read from the device per write when streaming a recovery
+pub const STREAMING_WINDOW_SIZE: u64 = 4 * 1024 * 1024; // 4MB
+
+/// Granularity at which a failed read is retried piece by piece
+const SALVAGE_READ_SIZE: u64 = 512;
+
+/// Copy `length` device bytes from `offset` to `out`, salvaging what is readable
+///
+/// If the read fails, the range is re-read a sector at a time and sectors
+/// that still fail are written as zeros. Those are added to `damaged` as
+/// ranges of the recovered file, which is at `file_offset` when the copy starts.
+fn copy_device_range<W: Write>(
+    device: &fs::common::BlockDevice,
+    offset: u64,
+    length: u64,
+    file_offset: u64,
+    out: &mut W,
+    damaged: &mut Vec<DamagedRange>,
+) -> Result<()> {
+    if let Ok(data) = device.read_at(offset, length as usize) {
+        out.write_all(data)?;
+        return Ok(());
+    }
+
+    let mut done = 0;
+    while done < length {
+        let piece = std::cmp::min(SALVAGE_READ_SIZE, length - done);
+        match device.read_at(offset + done, piece as usize) {
+            Ok(data) => out.write_all(data)?,
+            Err(e) => {
+                tracing::debug!("Unreadable bytes at device offset {}: {}", offset + done, e);
+                out.write_all(&[0u8; SALVAGE_READ_SIZE as usize][..piece as usize])?;
+
+                let damaged_offset = file_offset + done;
+                match damaged.last_mut() {
+                    Some(last) if last.offset + last.length == damaged_offset => {
+                        last.length += piece;
+                    }
+                    _ => damaged.push(DamagedRange {
+                        offset: damaged_offset,
+                        length: piece,
+                    }),
+                }
+            }
+        }
+        done += piece;
+    }
+    Ok(())
+}
+
+/// Write zeros for the sparse ranges of a file starting `position` bytes into it
+///
+/// Ranges that follow each other are written together, up to `len` bytes of
+/// the file. Returns the number of bytes written.
+pub(crate) fn write_sparse_ranges<W: Write>(
+    deleted_file: &DeletedFile,
+    position: u64,
+    len: u64,
+    out: &mut W,
 ) -> Result<u64> {
-    let output_path = generate_recovery_path(output_dir, deleted_file);
-    let mut output_file = File::create(&output_path)?;
-    let mut bytes_written = 0u64;
+    let mut end = position;
+    for range in deleted_file.sparse_ranges() {
+        if range.offset < end {
+            continue;
+        }
+        if range.offset > end || end >= len {
+            break;
+        }
+        let zeros = std::cmp::min(range.length, len - end);
+        std::io::copy(&mut std::io::repeat(0).take(zeros), out)?;
+        end += zeros;
+    }
+    Ok(end - position)
+}
 
-    // Determine block-to-byte conversion multiplier based on filesystem type
-    // XFS/Btrfs: block numbers need to be multiplied by block size (4096)
+/// Block-to-byte conversion multiplier for a file's data blocks
+///
+/// `block_size` is the file system's block size, see [`fs::detect_block_size`].
+pub(crate) fn block_offset_multiplier(fs_type: FileSystemType, block_size: u32) -> u64 {
+    // XFS/Btrfs: block numbers are in file system blocks (sectors on Btrfs)
     // exFAT: data_blocks already store byte offsets, so multiplier is 1
-    let offset_multiplier = match fs_type {
-        FileSystemType::Xfs => 4096,
-        FileSystemType::Btrfs => 4096,
+    match fs_type {
+        FileSystemType::Xfs | FileSystemType::Btrfs => block_size as u64,
         FileSystemType::ExFat => 1, // exFAT data_blocks use byte offsets
+    }
+}
+
+/// Recover a single file from the source device
+///
+/// Returns the bytes written and the ranges of the file that could not be
+/// read and were zero-filled.
+fn recover_single_file(
+    device: &fs::common::BlockDevice,
+    deleted_file: &DeletedFile,
+    output_path: &Path,
+    fs_type: FileSystemType,
+    block_size: u32,
+) -> Result<(u64, Vec<DamagedRange>)> {
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut output_file = File::create(output_path)?;
+    let mut bytes_written = 0u64;
+    let mut damaged = Vec::new();
+
+    let offset_multiplier = block_offset_multiplier(fs_type, block_size);
+
+    // Btrfs extents may be compressed and need decompressing before writing
+    let compressed_extents: &[BtrfsCompressedExtent] = match deleted_file.fs_metadata {
+        Some(FsSpecificMetadata::Btrfs(ref meta)) => &meta.compressed_extents,
+        _ => &[],
     };
 
-    // Recover data from each block range
-    for block_range in &deleted_file.data_blocks {
-        let start_offset = block_range.start_block * offset_multiplier as u64;
-        let total_bytes = block_range.block_count * offset_multiplier as u64;
+    if let Some(inline) = deleted_file.inline_data() {
+        // Inline files have no data blocks, their bytes came from the metadata
+        let len = std::cmp::min(inline.len() as u64, deleted_file.size);
+        output_file.write_all(&inline[..len as usize])?;
+        bytes_written = len;
+    } else if deleted_file.size > STREAMING_RECOVERY_THRESHOLD && compressed_extents.is_empty() {
+        bytes_written = recover_file_streaming(
+            deleted_file,
+            device,
+            fs_type,
+            block_size,
+            &mut output_file,
+            &mut damaged,
+        )?;
+    }
+
+    // Recover data from each block range, with the zeros of any holes before it
+    for (block_index, block_range) in deleted_file.data_blocks.iter().enumerate() {
+        bytes_written += write_sparse_ranges(
+            deleted_file,
+            bytes_written,
+            deleted_file.size,
+            &mut output_file,
+        )?;
+        if bytes_written >= deleted_file.size {
+            break;
+        }
+
+        let start_offset = block_range.start_block * offset_multiplier;
+        let total_bytes = block_range.block_count * offset_multiplier;
         let end_offset = start_offset + total_bytes;
 
         // Make sure we don't read past the end of the image
-        if start_offset >= mmap.len() as u64 {
+        if start_offset >= device.size() {
             tracing::warn!("Block range starts beyond image bounds: {}", start_offset);
             continue;
         }
 
-        let actual_end = std::cmp::min(end_offset, mmap.len() as u64);
+        if let Some(extent) = compressed_extents
+            .iter()
+            .find(|e| e.block_index == block_index)
+        {
+            let compressed_end = std::cmp::min(start_offset + extent.disk_num_bytes, device.size());
+            let decompressed = fs::btrfs::recovery::decompress_extent(
+                device.read_at(start_offset, (compressed_end - start_offset) as usize)?,
+                extent.compression,
+                extent.ram_bytes,
+            )?;
+
+            // Only the referenced part of the uncompressed extent belongs to the file
+            let data_start = std::cmp::min(extent.extent_offset as usize, decompressed.len());
+            let data_end = std::cmp::min(
+                data_start.saturating_add(extent.num_bytes as usize),
+                decompressed.len(),
+            );
+            let remaining_file_bytes = deleted_file.size.saturating_sub(bytes_written);
+            let bytes_to_copy = std::cmp::min((data_end - data_start) as u64, remaining_file_bytes);
+
+            output_file
+                .write_all(&decompressed[data_start..data_start + bytes_to_copy as usize])?;
+            bytes_written += bytes_to_copy;
+            continue;
+        }
+
+        let actual_end = std::cmp::min(end_offset, device.size());
         let actual_bytes = actual_end - start_offset;
 
         // Also limit by the file's expected size
@@ -474,8 +1106,14 @@ fn recover_single_file(
         let bytes_to_copy = std::cmp::min(actual_bytes, remaining_file_bytes);
 
         if bytes_to_copy > 0 {
-            let data_slice = &mmap[start_offset as usize..(start_offset + bytes_to_copy) as usize];
-            output_file.write_all(data_slice)?;
+            copy_device_range(
+                device,
+                start_offset,
+                bytes_to_copy,
+                bytes_written,
+                &mut output_file,
+                &mut damaged,
+            )?;
             bytes_written += bytes_to_copy;
 
             tracing::debug!(
@@ -485,12 +1123,13 @@ fn recover_single_file(
                 block_range.start_block + block_range.block_count
             );
         }
-
-        // Stop if we've recovered the expected file size
-        if bytes_written >= deleted_file.size {
-            break;
-        }
     }
+    bytes_written += write_sparse_ranges(
+        deleted_file,
+        bytes_written,
+        deleted_file.size,
+        &mut output_file,
+    )?;
 
     output_file.flush()?;
 
@@ -500,50 +1139,350 @@ fn recover_single_file(
         {
             use std::os::unix::fs::PermissionsExt;
             let perms = std::fs::Permissions::from_mode(permissions);
-            std::fs::set_permissions(&output_path, perms)?;
+            std::fs::set_permissions(output_path, perms)?;
         }
     }
 
+    Ok((bytes_written, damaged))
+}
+
+/// Write a deleted file's data blocks to `out` in bounded windows.
+///
+/// Each block range is copied at most [`STREAMING_WINDOW_SIZE`] bytes at a time,
+/// so memory use stays flat regardless of file size. Compressed Btrfs extents
+/// are copied as stored, inline data is written without touching the device.
+/// Unreadable bytes are written as zeros and their ranges in the file appended
+/// to `damaged`. Returns the number of bytes written.
+///
+/// `block_size` is the file system's block size, as recorded in
+/// [`SessionMetadata::block_size`].
+pub fn recover_file_streaming<W: Write>(
+    deleted_file: &DeletedFile,
+    device: &fs::common::BlockDevice,
+    fs_type: FileSystemType,
+    block_size: u32,
+    out: W,
+    damaged: &mut Vec<DamagedRange>,
+) -> Result<u64> {
+    copy_file_data(
+        deleted_file,
+        device,
+        fs_type,
+        block_size,
+        deleted_file.size,
+        out,
+        damaged,
+    )
+}
+
+/// Most bytes [`preview_file`] returns
+pub const MAX_PREVIEW_BYTES: usize = 1024 * 1024;
+
+/// The first `max_bytes` of a deleted file, e.g. for a hex view or thumbnail
+///
+/// The bytes are read as recovery would write them, with unreadable ones as
+/// zeros. At most [`MAX_PREVIEW_BYTES`] are returned, whatever `max_bytes` is.
+/// Data blocks are read in `block_size` units, as by [`recover_file_streaming`].
+pub fn preview_file(
+    deleted_file: &DeletedFile,
+    device: &fs::common::BlockDevice,
+    fs_type: FileSystemType,
+    block_size: u32,
+    max_bytes: usize,
+) -> Result<Vec<u8>> {
+    let len = deleted_file
+        .size
+        .min(max_bytes.min(MAX_PREVIEW_BYTES) as u64);
+    let mut preview = Vec::with_capacity(len as usize);
+    copy_file_data(
+        deleted_file,
+        device,
+        fs_type,
+        block_size,
+        len,
+        &mut preview,
+        &mut Vec::new(),
+    )?;
+    Ok(preview)
+}
+
+/// Write the first `len` bytes of a deleted file to `out`, see [`recover_file_streaming`]
+pub(crate) fn copy_file_data<W: Write>(
+    deleted_file: &DeletedFile,
+    device: &fs::common::BlockDevice,
+    fs_type: FileSystemType,
+    block_size: u32,
+    len: u64,
+    mut out: W,
+    damaged: &mut Vec<DamagedRange>,
+) -> Result<u64> {
+    if let Some(inline) = deleted_file.inline_data() {
+        let len = std::cmp::min(inline.len() as u64, len);
+        out.write_all(&inline[..len as usize])?;
+        out.flush()?;
+        return Ok(len);
+    }
+
+    let offset_multiplier = block_offset_multiplier(fs_type, block_size);
+    let mut bytes_written = 0u64;
+
+    for block_range in &deleted_file.data_blocks {
+        bytes_written += write_sparse_ranges(deleted_file, bytes_written, len, &mut out)?;
+        if bytes_written >= len {
+            break;
+        }
+
+        let start_offset = block_range.start_block * offset_multiplier;
+        if start_offset >= device.size() {
+            tracing::warn!("Block range starts beyond image bounds: {}", start_offset);
+            continue;
+        }
+
+        let range_end = std::cmp::min(
+            start_offset + block_range.block_count * offset_multiplier,
+            device.size(),
+        );
+        let remaining_file_bytes = len.saturating_sub(bytes_written);
+        let copy_end = std::cmp::min(range_end, start_offset + remaining_file_bytes);
+
+        let mut offset = start_offset;
+        while offset < copy_end {
+            let window = std::cmp::min(STREAMING_WINDOW_SIZE, copy_end - offset);
+            copy_device_range(device, offset, window, bytes_written, &mut out, damaged)?;
+            offset += window;
+            bytes_written += window;
+        }
+
+        if bytes_written >= len {
+            break;
+        }
+    }
+    bytes_written += write_sparse_ranges(deleted_file, bytes_written, len, &mut out)?;
+
+    out.flush()?;
     Ok(bytes_written)
 }
 
-/// Generate a recovery path for a deleted file
-fn generate_recovery_path(output_dir: &Path, deleted_file: &DeletedFile) -> PathBuf {
-    let filename = if let Some(ref original_path) = deleted_file.original_path {
-        // Use the original filename if available
-        original_path
-            .file_name()
-            .and_then(|name| name.to_str())
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| format!("recovered_file_{}", deleted_file.id))
-    } else {
-        // Generate filename based on file type and metadata
-        let extension = deleted_file
-            .metadata
-            .file_extension
-            .as_ref()
-            .map(|ext| format!(".{}", ext))
-            .unwrap_or_else(|| match deleted_file.file_type {
-                FileType::RegularFile => ".dat".to_string(),
-                FileType::Directory => "".to_string(),
-                _ => ".unknown".to_string(),
-            });
-
-        format!("recovered_file_{}{}", deleted_file.id, extension)
-    };
+/// How recovered files are arranged under the output directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputLayout {
+    /// Every file directly in the output directory
+    Flat,
+    /// One subdirectory per MIME category, e.g. `image/` or `application/`
+    ByType,
+    /// One `YYYY-MM-DD` subdirectory per deletion (or else modification) day
+    ByDate,
+    /// The directory tree of each file's `original_path`
+    #[default]
+    Original,
+}
+
+impl OutputLayout {
+    /// Every layout
+    pub fn all() -> [Self; 4] {
+        [Self::Flat, Self::ByType, Self::ByDate, Self::Original]
+    }
+
+    /// Short name, as accepted by `from_str`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Flat => "flat",
+            Self::ByType => "by-type",
+            Self::ByDate => "by-date",
+            Self::Original => "original",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputLayout {
+    type Err = String;
+
+    /// Parse a layout's short name, case-insensitively
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .into_iter()
+            .find(|layout| layout.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::all().iter().map(|l| l.name()).collect();
+                format!(
+                    "unknown output layout '{}' (expected one of: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Subdirectory of files whose MIME type is unknown (by-type layout)
+const UNKNOWN_TYPE_DIR: &str = "unknown";
+
+/// Subdirectory of files without deletion or modification time (by-date layout)
+const UNDATED_DIR: &str = "undated";
 
-    output_dir.join(filename)
+/// Where each of `files` is written under `output_dir`
+///
+/// `layout` picks the subdirectory; files whose original name is unknown get
+/// a generated one. When several files map to the same path, the one with
+/// the lowest ID keeps it and the rest get a `_001`, `_002`, ... suffix before
+/// the extension. Pass all files of a session so that a file's path does not
+/// depend on which others are recovered alongside it.
+pub fn assign_recovery_paths(
+    output_dir: &Path,
+    files: &[DeletedFile],
+    layout: OutputLayout,
+) -> HashMap<u64, PathBuf> {
+    let mut ordered: Vec<&DeletedFile> = files.iter().collect();
+    ordered.sort_by_key(|file| file.id);
+
+    let mut taken = HashSet::new();
+    let mut paths = HashMap::new();
+    for file in ordered {
+        let base = layout_relative_path(file, layout);
+        let mut path = base.clone();
+        let mut suffix = 1;
+        while !taken.insert(path.clone()) {
+            path = with_collision_suffix(&base, suffix);
+            suffix += 1;
+        }
+        paths.insert(file.id, output_dir.join(path));
+    }
+    paths
+}
+
+/// Output path of a file relative to the output directory, before collisions
+fn layout_relative_path(deleted_file: &DeletedFile, layout: OutputLayout) -> PathBuf {
+    let original = deleted_file
+        .original_path
+        .as_deref()
+        .and_then(sanitize_original_path);
+    if layout == OutputLayout::Original {
+        if let Some(original) = original {
+            return original;
+        }
+    }
+
+    let name = original
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| generated_file_name(deleted_file));
+
+    match layout {
+        OutputLayout::Flat | OutputLayout::Original => name,
+        OutputLayout::ByType => {
+            let category = deleted_file
+                .metadata
+                .mime_type
+                .as_deref()
+                .and_then(|mime| mime.split_once('/'))
+                .and_then(|(category, _)| sanitize_name(category))
+                .unwrap_or_else(|| UNKNOWN_TYPE_DIR.to_string());
+            PathBuf::from(category).join(name)
+        }
+        OutputLayout::ByDate => {
+            let day = deleted_file
+                .deletion_time
+                .or(deleted_file.metadata.modified_time)
+                .map(|time| time.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| UNDATED_DIR.to_string());
+            PathBuf::from(day).join(name)
+        }
+    }
+}
+
+/// Name for a file whose original name is unknown
+fn generated_file_name(deleted_file: &DeletedFile) -> PathBuf {
+    // Generate filename based on file type and metadata
+    let extension = deleted_file
+        .metadata
+        .file_extension
+        .as_ref()
+        .map(|ext| format!(".{}", ext))
+        .unwrap_or_else(|| match deleted_file.file_type {
+            FileType::RegularFile => ".dat".to_string(),
+            FileType::Directory => "".to_string(),
+            _ => ".unknown".to_string(),
+        });
+
+    PathBuf::from(format!("recovered_file_{}{}", deleted_file.id, extension))
+}
+
+/// Longest file name component most file systems accept, in bytes
+const MAX_NAME_LEN: usize = 255;
+
+/// Turn an `original_path` read from the scanned media into a relative path
+/// that stays inside the output directory
+///
+/// The path comes from untrusted on-disk data. Leading `/` and drive
+/// prefixes are stripped, `.` and `..` components dropped, and NULs, control
+/// characters, path separators and characters Windows rejects replaced with
+/// `_`. Returns `None` if no file name is left.
+pub fn sanitize_original_path(path: &Path) -> Option<PathBuf> {
+    let sanitized: PathBuf = path
+        .components()
+        .filter_map(|component| match component {
+            std::path::Component::Normal(name) => sanitize_name(&name.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+    sanitized.file_name().is_some().then_some(sanitized)
+}
+
+/// A single path component made safe to create, or `None` to drop it
+fn sanitize_name(name: &str) -> Option<String> {
+    let mut clean: String = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_control() => '_',
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+
+    if clean.len() > MAX_NAME_LEN {
+        let mut end = MAX_NAME_LEN;
+        while !clean.is_char_boundary(end) {
+            end -= 1;
+        }
+        clean.truncate(end);
+    }
+
+    // Some systems strip trailing dots and spaces, so "..." could act as ".."
+    match clean.trim_end_matches(['.', ' ']) {
+        "" => None,
+        _ => Some(clean),
+    }
+}
+
+/// `dir/name.ext` with `_NNN` appended to the name
+fn with_collision_suffix(path: &Path, suffix: u32) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{:03}.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}_{:03}", stem, suffix),
+    };
+    path.with_file_name(name)
 }
 
 /// Recovery report with detailed results
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecoveryReport {
     pub total_files: usize,
+    /// Files written, including partially recovered ones
     pub recovered_files: usize,
+    /// Recovered files with unreadable, zero-filled ranges
+    #[serde(default)]
+    pub partial_files: usize,
     pub failed_files: usize,
     pub total_bytes_recovered: u64,
     pub output_directory: PathBuf,
     pub recovery_details: Vec<FileRecoveryResult>,
+    /// Sectors of the source that could not be read, and how retries fared
+    #[serde(default)]
+    pub read_errors: fs::common::BadSectorReport,
 }
 
 /// Individual file recovery result
@@ -558,9 +1497,148 @@ pub struct FileRecoveryResult {
     pub confidence_score: f32,
 }
 
+/// What `recover_files` would write, as computed by [`plan_recovery`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryPlan {
+    pub entries: Vec<PlannedFile>,
+    pub total_bytes: u64,
+    /// Entries whose output path is already taken by an existing file
+    pub collisions: usize,
+    /// Free space where the files would be written, if it could be determined
+    pub available_bytes: Option<u64>,
+    /// Whether `total_bytes` fits in the free space (assumed when unknown)
+    pub space_ok: bool,
+}
+
+/// A single file in a [`RecoveryPlan`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub file_id: u64,
+    pub original_path: Option<PathBuf>,
+    pub output_path: PathBuf,
+    pub bytes: u64,
+    /// A file already exists at the output path
+    pub collides: bool,
+}
+
 /// Recovery status for individual files
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RecoveryStatus {
     Success,
+    /// Written, but these ranges of the file were unreadable and zero-filled
+    Partial(Vec<DamagedRange>),
     Failed(String),
 }
+
+/// Bytes of a recovered file that could not be read from the device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DamagedRange {
+    /// Offset in the recovered file
+    pub offset: u64,
+    pub length: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_type_from_mode() {
+        let cases = [
+            (0o100644, FileType::RegularFile),
+            (0o040755, FileType::Directory),
+            (0o120777, FileType::SymbolicLink),
+            (0o060660, FileType::BlockDevice),
+            (0o020620, FileType::CharacterDevice),
+            (0o010644, FileType::Fifo),
+            (0o140755, FileType::Socket),
+            // Permission bits alone, and an undefined type
+            (0o000644, FileType::Unknown),
+            (0o170000, FileType::Unknown),
+        ];
+        for (mode, expected) in cases {
+            assert_eq!(FileType::from_mode(mode), expected, "mode {:o}", mode);
+        }
+    }
+
+    #[test]
+    fn test_file_type_from_exfat_attributes() {
+        // Archive, read-only and hidden files
+        assert_eq!(FileType::from_exfat_attributes(0x20), FileType::RegularFile);
+        assert_eq!(FileType::from_exfat_attributes(0x03), FileType::RegularFile);
+        assert_eq!(FileType::from_exfat_attributes(0), FileType::RegularFile);
+        assert_eq!(FileType::from_exfat_attributes(0x10), FileType::Directory);
+        assert_eq!(FileType::from_exfat_attributes(0x12), FileType::Directory);
+    }
+
+    #[test]
+    fn test_preview_returns_file_header() {
+        // A PNG spread over blocks 3 and 1 of an XFS image
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR";
+        let mut image = vec![0u8; 4 * 4096];
+        image[3 * 4096..3 * 4096 + png.len()].copy_from_slice(png);
+        image[4096..2 * 4096].fill(0xAA);
+        let device = fs::common::BlockDevice::from_vec(image);
+
+        let range = |start_block| BlockRange {
+            start_block,
+            block_count: 1,
+            is_allocated: false,
+        };
+        let file = DeletedFile {
+            id: 1,
+            inode_or_cluster: 131,
+            original_path: None,
+            size: 6000,
+            deletion_time: None,
+            confidence_score: 0.9,
+            file_type: FileType::RegularFile,
+            data_blocks: vec![range(3), range(1)],
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: Some("image/png".to_string()),
+                file_extension: Some("png".to_string()),
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        };
+
+        let preview =
+            |max_bytes| preview_file(&file, &device, FileSystemType::Xfs, 4096, max_bytes);
+        assert_eq!(preview(png.len()).unwrap(), png);
+        assert!(preview(0).unwrap().is_empty());
+
+        // Capped at the file size, reading on into its second extent
+        let whole = preview(usize::MAX).unwrap();
+        assert_eq!(whole.len(), 6000);
+        assert!(whole[4096..].iter().all(|&b| b == 0xAA));
+    }
+
+    #[cfg(feature = "ewf")]
+    #[test]
+    fn test_scan_of_ewf_image_leaves_no_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut image = vec![0u8; 1024 * 1024];
+        image[0..4].copy_from_slice(b"XFSB");
+        image[4..8].copy_from_slice(&4096u32.to_be_bytes());
+        let first = fs::common::ewf::tests::write_ewf(dir.path(), &image, 64);
+
+        let options = ScanOptions {
+            temp_dir: Some(temp_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let session = scan_and_analyze_with_options(&first, FileSystemType::Xfs, options).unwrap();
+        assert_eq!(session.metadata.device_size, image.len() as u64);
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+}
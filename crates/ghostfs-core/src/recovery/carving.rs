@@ -0,0 +1,255 @@
+/// Raw file carving, independent of any file system
+///
+/// When the file system is too damaged to parse, or the media was
+/// reformatted, file contents usually survive. Carving finds them by their
+/// headers and trailers alone, so it works on any device.
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::Result;
+use rayon::prelude::*;
+
+use crate::fs::common::BlockDevice;
+use crate::recovery::engine::SIZE_ESTIMATED_ATTRIBUTE;
+use crate::recovery::signatures::{
+    carve_by_trailers, EndMarkerScanner, SignatureSet, DEFAULT_END_MARKER_BUDGET,
+};
+use crate::{BlockRange, DeletedFile, FileMetadata, FileType};
+
+/// Bytes examined for a file header at each carving offset
+const HEADER_LEN: usize = 4096;
+
+/// Size given to a carved file of a type without an end marker
+const DEFAULT_CARVED_SIZE: u64 = 1024 * 1024;
+
+/// Bytes copied at a time when writing out a carved file
+const COPY_WINDOW: usize = 1024 * 1024;
+
+/// Confidence of a carved file whose end marker was found
+const BOUNDED_CONFIDENCE: f32 = 0.6;
+
+/// Confidence of a carved file whose size is a guess
+const UNBOUNDED_CONFIDENCE: f32 = 0.3;
+
+/// Raw carving configuration
+#[derive(Debug, Clone)]
+pub struct CarveConfig {
+    /// Alignment of the offsets a carved file may start at; files start on
+    /// a sector boundary on every common file system, and on a cluster
+    /// boundary on most
+    pub alignment: u64,
+    /// Device offset the alignment counts from, e.g. the exFAT cluster heap
+    pub alignment_origin: u64,
+    /// Bytes searched for one file's end marker before its size is estimated
+    pub end_marker_budget: u64,
+    /// Also carve ZIP and PDF files back from their trailers
+    pub trailer_carving: bool,
+    /// Signatures files are identified by
+    pub signatures: SignatureSet,
+}
+
+impl Default for CarveConfig {
+    fn default() -> Self {
+        Self {
+            alignment: 512,
+            alignment_origin: 0,
+            end_marker_budget: DEFAULT_END_MARKER_BUDGET,
+            trailer_carving: true,
+            signatures: SignatureSet::default(),
+        }
+    }
+}
+
+impl CarveConfig {
+    /// Default configuration aligned to the blocks or clusters of the file
+    /// system on `device`, or to sectors when none is detected
+    pub fn for_device(device: &BlockDevice) -> Self {
+        match crate::fs::detect_data_alignment(device) {
+            Some(detected) => Self {
+                alignment: detected.block_size,
+                alignment_origin: detected.origin,
+                ..Default::default()
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Whether a file may start at `offset`
+    pub fn is_aligned(&self, offset: u64) -> bool {
+        let alignment = self.alignment.max(1);
+        offset % alignment == self.alignment_origin % alignment
+    }
+}
+
+/// Carve files from `device` by their signatures, ignoring any file system
+///
+/// Every aligned offset is checked for a known file header, and each file
+/// runs to its end marker. Files found by their trailers are kept only if
+/// they start on an aligned offset too, which rules out most matches in
+/// the middle of other data. Headers inside a file whose end marker was
+/// found are part of that file and are not carved again. Files are numbered
+/// from 1 in device order; unreadable parts of the device are skipped.
+///
+/// As for exFAT, the carved files' `data_blocks` are byte offsets and lengths.
+pub fn carve_device(device: &BlockDevice, config: &CarveConfig) -> Vec<DeletedFile> {
+    let alignment = config.alignment.max(1);
+    let first = config.alignment_origin % alignment;
+    let signatures = &config.signatures;
+    let end_markers =
+        EndMarkerScanner::new(config.end_marker_budget).with_signatures(signatures.clone());
+
+    let slots = device.size().saturating_sub(first).div_ceil(alignment);
+    let headers: Vec<(u64, String, String)> = (0..slots)
+        .into_par_iter()
+        .filter_map(|slot| {
+            let offset = first + slot * alignment;
+            let len = (device.size() - offset).min(HEADER_LEN as u64) as usize;
+            let header = device.read_at(offset, len).ok()?;
+            signatures
+                .detect_file_type(header)
+                .map(|(mime, ext)| (offset, mime, ext))
+        })
+        .collect();
+
+    let mut files: Vec<DeletedFile> = Vec::new();
+    let mut claimed_until = 0;
+    for (offset, mime, ext) in headers {
+        if offset < claimed_until {
+            continue;
+        }
+
+        let limit = signatures
+            .end_marker_search_limit(&mime)
+            .unwrap_or(DEFAULT_CARVED_SIZE);
+        let available = (device.size() - offset).min(limit);
+        let end = device
+            .read_at(offset, available as usize)
+            .ok()
+            .and_then(|data| end_markers.find_end(data, offset, &mime));
+        let size = end.map_or(available, |end| end.size);
+
+        let mut file = carved_file(offset, size, mime, ext);
+        match end {
+            Some(end) if !end.estimated => {
+                claimed_until = offset + size;
+                file.confidence_score = BOUNDED_CONFIDENCE;
+            }
+            Some(_) => {
+                file.metadata
+                    .extended_attributes
+                    .insert(SIZE_ESTIMATED_ATTRIBUTE.to_string(), b"true".to_vec());
+            }
+            None => {}
+        }
+        files.push(file);
+    }
+
+    if config.trailer_carving {
+        match device.read_at(0, device.size() as usize) {
+            Ok(data) => {
+                for carved in carve_by_trailers(data) {
+                    if !config.is_aligned(carved.offset) {
+                        tracing::debug!(
+                            "Rejecting {} carved at unaligned offset {}",
+                            carved.mime_type,
+                            carved.offset
+                        );
+                        continue;
+                    }
+                    let existing = files.iter_mut().find(|file| {
+                        carved_offset(file) == carved.offset
+                            && file.metadata.mime_type.as_deref() == Some(carved.mime_type.as_str())
+                    });
+                    let mut file = carved_file(
+                        carved.offset,
+                        carved.size,
+                        carved.mime_type,
+                        carved.extension,
+                    );
+                    file.confidence_score = BOUNDED_CONFIDENCE;
+                    match existing {
+                        Some(existing) => *existing = file,
+                        None => files.push(file),
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Skipping trailer carving: {}", e),
+        }
+    }
+
+    files.sort_by_key(carved_offset);
+    for (index, file) in files.iter_mut().enumerate() {
+        file.id = index as u64 + 1;
+    }
+    files
+}
+
+/// Write the bytes of a file found by [`carve_device`] to `out`
+///
+/// Unreadable windows are written as zeros so the rest keeps its offsets.
+/// Returns the number of bytes written.
+pub fn write_carved_file<W: Write>(
+    device: &BlockDevice,
+    file: &DeletedFile,
+    mut out: W,
+) -> Result<u64> {
+    let start = carved_offset(file);
+    let end = start.saturating_add(file.size).min(device.size());
+
+    let mut offset = start;
+    while offset < end {
+        let len = (end - offset).min(COPY_WINDOW as u64) as usize;
+        match device.read_at(offset, len) {
+            Ok(data) => out.write_all(data)?,
+            Err(e) => {
+                tracing::warn!("Zero-filling carved file {}: {}", file.id, e);
+                out.write_all(&vec![0u8; len])?;
+            }
+        }
+        offset += len as u64;
+    }
+
+    out.flush()?;
+    Ok(end.saturating_sub(start))
+}
+
+/// Device offset a carved file starts at
+fn carved_offset(file: &DeletedFile) -> u64 {
+    file.data_blocks
+        .first()
+        .map_or(0, |range| range.start_block)
+}
+
+/// A carved file of `size` bytes at `offset`
+fn carved_file(offset: u64, size: u64, mime: String, ext: String) -> DeletedFile {
+    DeletedFile {
+        id: 0,
+        inode_or_cluster: 0,
+        original_path: None,
+        size,
+        deletion_time: None,
+        confidence_score: UNBOUNDED_CONFIDENCE,
+        file_type: FileType::RegularFile,
+        data_blocks: vec![BlockRange {
+            start_block: offset,
+            block_count: size,
+            is_allocated: false,
+        }],
+        is_recoverable: size > 0,
+        metadata: FileMetadata {
+            mime_type: Some(mime),
+            file_extension: Some(ext),
+            permissions: None,
+            owner_uid: None,
+            owner_gid: None,
+            owner_name: None,
+            group_name: None,
+            created_time: None,
+            modified_time: None,
+            accessed_time: None,
+            extended_attributes: HashMap::new(),
+        },
+        fs_metadata: None,
+        skip_reason: None,
+    }
+}
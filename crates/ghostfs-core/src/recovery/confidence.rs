@@ -1,8 +1,12 @@
 /// Confidence scoring algorithm for recovery reliability
 use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 
-use crate::{BlockRange, DeletedFile, FileMetadata, FileSystemType};
+use crate::forensics::StructuralValidity;
+use crate::recovery::signatures::entropy_confidence_factor;
+use crate::{BlockRange, DeletedFile, FileMetadata, FileSystemType, FsSpecificMetadata};
 
 // =============================================================================
 // CONFIDENCE SCORING THRESHOLDS AND CONSTANTS
@@ -27,8 +31,8 @@ const XFS_MIN_REASONABLE_EXTENT_SIZE: u64 = 4096; // 4 KB
 const XFS_MAX_REASONABLE_EXTENT_SIZE: u64 = 10_485_760; // 10 MB
 const XFS_MAX_REASONABLE_FILE_SIZE: u64 = 10_995_116_277_760; // 10 TB
 
-// Block size assumption (used when FS-specific info unavailable)
-const ASSUMED_BLOCK_SIZE: u64 = 4096;
+// Multiplier applied to extent scores when a file's block ranges overlap
+const EXTENT_OVERLAP_PENALTY: f32 = 0.5;
 
 /// Context for confidence scoring calculations
 #[derive(Debug, Clone)]
@@ -38,51 +42,157 @@ pub struct ConfidenceContext {
     pub filesystem_integrity: f32, // 0.0-1.0
     pub total_files_found: u32,
     pub device_activity_level: ActivityLevel,
+    pub weights: ConfidenceWeights,
+    /// File system block (or exFAT cluster) size in bytes
+    pub block_size: u64,
 }
 
-#[derive(Debug, Clone)]
+/// Relative weight of each factor in the overall confidence score
+///
+/// Weights are normalized by their sum, but are expected to add up to 1.0 so
+/// each one reads as a share of the final score.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfidenceWeights {
+    pub time_recency: f32,
+    pub metadata_completeness: f32,
+    pub data_block_integrity: f32,
+    pub file_signature_match: f32,
+    pub size_consistency: f32,
+    pub fs_specific: f32,
+}
+
+impl Default for ConfidenceWeights {
+    fn default() -> Self {
+        Self {
+            time_recency: 0.25,
+            metadata_completeness: 0.15,
+            data_block_integrity: 0.20,
+            file_signature_match: 0.15,
+            size_consistency: 0.10,
+            fs_specific: 0.15,
+        }
+    }
+}
+
+impl ConfidenceWeights {
+    /// Allowed deviation of the weight sum from 1.0
+    const SUM_TOLERANCE: f32 = 0.01;
+
+    /// Sum of all weights
+    pub fn total(&self) -> f32 {
+        self.time_recency
+            + self.metadata_completeness
+            + self.data_block_integrity
+            + self.file_signature_match
+            + self.size_consistency
+            + self.fs_specific
+    }
+
+    /// Check the weights, logging a warning if they do not sum to ~1.0
+    ///
+    /// Returns false if any weight is negative or they do not sum to ~1.0.
+    pub fn validate(&self) -> bool {
+        let weights = [
+            self.time_recency,
+            self.metadata_completeness,
+            self.data_block_integrity,
+            self.file_signature_match,
+            self.size_consistency,
+            self.fs_specific,
+        ];
+        if weights.iter().any(|w| *w < 0.0 || !w.is_finite()) {
+            tracing::warn!("Confidence weights must be non-negative: {:?}", self);
+            return false;
+        }
+
+        let total = self.total();
+        if (total - 1.0).abs() > Self::SUM_TOLERANCE {
+            tracing::warn!(
+                "Confidence weights sum to {:.3} instead of 1.0; scores will be normalized",
+                total
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Load weights from a JSON file; missing fields keep their default
+    pub fn from_json_file(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        let weights: Self = serde_json::from_str(&data)?;
+        weights.validate();
+        Ok(weights)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ActivityLevel {
     Low,    // Minimal writes since deletion
     Medium, // Some writes, moderate risk
     High,   // Heavy activity, high overwrite risk
 }
 
+impl ActivityLevel {
+    /// Estimate activity from how full the filesystem is (0.0 - 1.0)
+    ///
+    /// A fuller filesystem has fewer free blocks for new writes to land in, so
+    /// freed space is reused sooner. A filesystem that was not cleanly unmounted
+    /// (or has pending log writes) has been written to recently, which raises
+    /// the estimate by one level.
+    pub fn from_fullness(used_fraction: f32, recently_mounted: bool) -> Self {
+        let level = if used_fraction < 0.5 {
+            ActivityLevel::Low
+        } else if used_fraction < 0.85 {
+            ActivityLevel::Medium
+        } else {
+            ActivityLevel::High
+        };
+
+        match (level, recently_mounted) {
+            (ActivityLevel::Low, true) => ActivityLevel::Medium,
+            (_, true) => ActivityLevel::High,
+            (level, false) => level,
+        }
+    }
+}
+
 /// Calculate confidence score for a deleted file
 pub fn calculate_confidence_score(file: &DeletedFile, context: &ConfidenceContext) -> f32 {
     let factors = [
-        // Time-based factors (25% weight)
+        // Time-based factors (25% weight by default)
         ConfidenceFactor {
             name: "time_recency",
             score: calculate_time_recency_factor(file.deletion_time, context.scan_time),
-            weight: 0.25,
+            weight: context.weights.time_recency,
         },
-        // Structural integrity factors (35% weight)
+        // Structural integrity factors (35% weight by default)
         ConfidenceFactor {
             name: "metadata_completeness",
             score: calculate_metadata_completeness_factor(&file.metadata),
-            weight: 0.15,
+            weight: context.weights.metadata_completeness,
         },
         ConfidenceFactor {
             name: "data_block_integrity",
-            score: calculate_data_block_integrity_factor(&file.data_blocks),
-            weight: 0.20,
+            score: calculate_file_data_integrity_factor(file),
+            weight: context.weights.data_block_integrity,
         },
-        // Content validation factors (25% weight)
+        // Content validation factors (25% weight by default)
         ConfidenceFactor {
             name: "file_signature_match",
             score: calculate_file_signature_factor(file),
-            weight: 0.15,
+            weight: context.weights.file_signature_match,
         },
         ConfidenceFactor {
             name: "size_consistency",
-            score: calculate_size_consistency_factor(file),
-            weight: 0.10,
+            score: calculate_size_consistency_factor(file, context),
+            weight: context.weights.size_consistency,
         },
-        // File system specific factors (15% weight)
+        // File system specific factors (15% weight by default)
         ConfidenceFactor {
             name: "fs_specific",
             score: calculate_fs_specific_factor(file, context),
-            weight: 0.15,
+            weight: context.weights.fs_specific,
         },
     ];
 
@@ -179,6 +289,14 @@ fn calculate_metadata_completeness_factor(metadata: &FileMetadata) -> f32 {
     }
 }
 
+/// Data block integrity of a file, inline data needs no blocks to survive
+fn calculate_file_data_integrity_factor(file: &DeletedFile) -> f32 {
+    if file.inline_data().is_some() {
+        return 1.0;
+    }
+    calculate_data_block_integrity_factor(&file.data_blocks)
+}
+
 /// Calculate data block integrity factor
 fn calculate_data_block_integrity_factor(data_blocks: &[BlockRange]) -> f32 {
     if data_blocks.is_empty() {
@@ -200,13 +318,30 @@ fn calculate_data_block_integrity_factor(data_blocks: &[BlockRange]) -> f32 {
 
     // Higher allocation ratio = lower confidence (data may be overwritten)
     // Lower allocation ratio = higher confidence (data likely intact)
-    1.0 - allocation_ratio
+    let score = 1.0 - allocation_ratio;
+
+    // A file never maps the same block twice, so overlaps mean corrupt metadata
+    if check_extent_overlaps(data_blocks) {
+        score * EXTENT_OVERLAP_PENALTY
+    } else {
+        score
+    }
 }
 
 /// Calculate file signature matching factor
 fn calculate_file_signature_factor(file: &DeletedFile) -> f32 {
+    // Content that looks encrypted or wiped recovers poorly whatever its type
+    let entropy_factor = file
+        .metadata
+        .extended_attributes
+        .get("entropy")
+        .and_then(|value| std::str::from_utf8(value).ok()?.parse::<f32>().ok())
+        .map_or(1.0, |entropy| {
+            entropy_confidence_factor(entropy, file.metadata.mime_type.as_deref())
+        });
+
     // If we have MIME type from content analysis, check consistency
-    match (&file.metadata.mime_type, &file.metadata.file_extension) {
+    let type_factor = match (&file.metadata.mime_type, &file.metadata.file_extension) {
         (Some(mime), Some(ext)) => {
             if mime_extension_match(mime, ext) {
                 0.9
@@ -217,17 +352,42 @@ fn calculate_file_signature_factor(file: &DeletedFile) -> f32 {
         (Some(_), None) => 0.6, // Have mime but no extension
         (None, Some(_)) => 0.5, // Have extension but no mime
         (None, None) => 0.2,    // No type information
+    };
+
+    type_factor * entropy_factor
+}
+
+/// Bytes in one block of a file's `data_blocks`
+///
+/// exFAT signature hits are counted in clusters from their first cluster and
+/// exFAT chains are recorded as byte ranges; everything else uses the units
+/// recovery reads them in, see [`crate::block_offset_multiplier`].
+fn data_block_unit(file: &DeletedFile, context: &ConfidenceContext) -> u64 {
+    match &file.fs_metadata {
+        Some(FsSpecificMetadata::ExFat(exfat)) => {
+            let first = file.data_blocks.first().map(|range| range.start_block);
+            if first == Some(exfat.first_cluster as u64) {
+                context.block_size
+            } else {
+                1
+            }
+        }
+        _ => crate::block_offset_multiplier(context.fs_type, context.block_size as u32),
     }
 }
 
 /// Calculate size consistency factor
-fn calculate_size_consistency_factor(file: &DeletedFile) -> f32 {
+fn calculate_size_consistency_factor(file: &DeletedFile, context: &ConfidenceContext) -> f32 {
     let declared_size = file.size;
+    let unit = data_block_unit(file, context);
+    // Holes count towards the size without taking up blocks
+    let sparse: u64 = file.sparse_ranges().iter().map(|range| range.length).sum();
     let block_size: u64 = file
         .data_blocks
         .iter()
-        .map(|range| range.block_count * ASSUMED_BLOCK_SIZE)
-        .sum();
+        .map(|range| range.block_count * unit)
+        .sum::<u64>()
+        + sparse;
 
     if declared_size == 0 && block_size == 0 {
         return 0.5; // Empty file
@@ -336,6 +496,10 @@ fn calculate_xfs_extent_integrity(file: &DeletedFile, meta: &crate::XfsFileMetad
         score += 0.3;
     }
 
+    if check_extent_overlaps(&file.data_blocks) {
+        score *= EXTENT_OVERLAP_PENALTY;
+    }
+
     score
 }
 
@@ -365,21 +529,27 @@ fn calculate_xfs_inode_consistency(file: &DeletedFile, meta: &crate::XfsFileMeta
 }
 
 /// Helper: Check if extents overlap (indicates corruption)
-#[allow(dead_code)]
 fn check_extent_overlaps(blocks: &[crate::BlockRange]) -> bool {
-    for i in 0..blocks.len() {
-        for j in (i + 1)..blocks.len() {
-            let a = &blocks[i];
-            let b = &blocks[j];
-
-            let a_end = a.start_block + a.block_count;
-            let b_end = b.start_block + b.block_count;
-
-            // Check for overlap
-            if (a.start_block < b_end) && (b.start_block < a_end) {
-                return true;
-            }
+    // Sorted by start, a range overlaps something iff it starts before the
+    // furthest end seen so far
+    let mut ranges: Vec<(u64, u64)> = blocks
+        .iter()
+        .filter(|range| range.block_count > 0)
+        .map(|range| {
+            (
+                range.start_block,
+                range.start_block.saturating_add(range.block_count),
+            )
+        })
+        .collect();
+    ranges.sort_unstable();
+
+    let mut furthest_end = 0;
+    for (i, (start, end)) in ranges.into_iter().enumerate() {
+        if i > 0 && start < furthest_end {
+            return true;
         }
+        furthest_end = furthest_end.max(end);
     }
     false
 }
@@ -613,13 +783,16 @@ pub fn generate_confidence_report(
         ),
         (
             "Data Block Integrity",
-            calculate_data_block_integrity_factor(&file.data_blocks),
+            calculate_file_data_integrity_factor(file),
         ),
         (
             "File Signature Match",
             calculate_file_signature_factor(file),
         ),
-        ("Size Consistency", calculate_size_consistency_factor(file)),
+        (
+            "Size Consistency",
+            calculate_size_consistency_factor(file, context),
+        ),
         ("FS Specific", calculate_fs_specific_factor(file, context)),
     ];
 
@@ -664,6 +837,129 @@ fn get_recovery_recommendation(confidence: f32) -> RecoveryRecommendation {
     }
 }
 
+/// Number of equal-width confidence buckets in histograms and calibration reports
+pub const CONFIDENCE_BUCKETS: usize = 10;
+
+/// Bucket of a confidence score; a score of exactly 1.0 goes in the top bucket
+fn confidence_bucket(confidence: f32) -> usize {
+    ((confidence.clamp(0.0, 1.0) * CONFIDENCE_BUCKETS as f32) as usize).min(CONFIDENCE_BUCKETS - 1)
+}
+
+/// Number of files in each 0.1-wide confidence range, from 0.0-0.1 up
+pub fn confidence_histogram(files: &[DeletedFile]) -> [usize; CONFIDENCE_BUCKETS] {
+    let mut histogram = [0; CONFIDENCE_BUCKETS];
+    for file in files {
+        histogram[confidence_bucket(file.confidence_score)] += 1;
+    }
+    histogram
+}
+
+/// Files whose predicted confidence fell into one range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationBucket {
+    /// Lowest confidence in the bucket (inclusive)
+    pub lower: f32,
+    /// Highest confidence in the bucket (exclusive, except for the last)
+    pub upper: f32,
+    pub count: usize,
+    /// Mean predicted confidence, or 0.0 for an empty bucket
+    pub mean_confidence: f32,
+    /// Fraction of the files that recovered structurally intact, or 0.0 for an empty bucket
+    pub success_rate: f32,
+}
+
+/// How well confidence scores predicted recovery on a session with known outcomes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    /// Files with a structural check result
+    pub sample_count: usize,
+    /// Files without a result, or whose format has no structural check
+    pub skipped: usize,
+    /// Mean squared difference between confidence and outcome (0.0 is perfect)
+    pub brier_score: f32,
+    /// Reliability curve, from the lowest confidence range up
+    pub buckets: Vec<CalibrationBucket>,
+}
+
+impl CalibrationReport {
+    /// Count-weighted mean gap between predicted confidence and actual success rate
+    pub fn expected_calibration_error(&self) -> f32 {
+        if self.sample_count == 0 {
+            return 0.0;
+        }
+        self.buckets
+            .iter()
+            .map(|bucket| {
+                bucket.count as f32 * (bucket.mean_confidence - bucket.success_rate).abs()
+            })
+            .sum::<f32>()
+            / self.sample_count as f32
+    }
+}
+
+/// Compare confidence scores with the structural checks of the recovered files
+///
+/// `verification_results` maps file IDs to the outcome of checking the
+/// recovered file, from a session on an image where the deleted files are
+/// known. A valid structure counts as a successful recovery and a truncated
+/// or corrupt one as a failure; unchecked formats carry no ground truth and
+/// are skipped.
+pub fn calibration_report(
+    files: &[DeletedFile],
+    verification_results: &HashMap<u64, StructuralValidity>,
+) -> CalibrationReport {
+    let mut sums = vec![(0usize, 0.0f32, 0usize); CONFIDENCE_BUCKETS];
+    let mut squared_error = 0.0;
+    let mut sample_count = 0;
+
+    for file in files {
+        let recovered = match verification_results.get(&file.id) {
+            Some(StructuralValidity::Valid) => true,
+            Some(result) if result.is_damaged() => false,
+            _ => continue,
+        };
+        let confidence = file.confidence_score.clamp(0.0, 1.0);
+        let outcome = if recovered { 1.0 } else { 0.0 };
+        squared_error += (confidence - outcome).powi(2);
+        sample_count += 1;
+
+        let (count, confidence_sum, successes) = &mut sums[confidence_bucket(confidence)];
+        *count += 1;
+        *confidence_sum += confidence;
+        *successes += recovered as usize;
+    }
+
+    let width = 1.0 / CONFIDENCE_BUCKETS as f32;
+    let buckets = sums
+        .into_iter()
+        .enumerate()
+        .map(
+            |(i, (count, confidence_sum, successes))| CalibrationBucket {
+                lower: i as f32 * width,
+                upper: (i + 1) as f32 * width,
+                count,
+                mean_confidence: mean(confidence_sum, count),
+                success_rate: mean(successes as f32, count),
+            },
+        )
+        .collect();
+
+    CalibrationReport {
+        sample_count,
+        skipped: files.len() - sample_count,
+        brier_score: mean(squared_error, sample_count),
+        buckets,
+    }
+}
+
+fn mean(sum: f32, count: usize) -> f32 {
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f32
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -678,6 +974,8 @@ mod tests {
             filesystem_integrity: 0.9,
             total_files_found: 100,
             device_activity_level: ActivityLevel::Low,
+            weights: ConfidenceWeights::default(),
+            block_size: 4096,
         };
 
         let metadata = FileMetadata {
@@ -686,6 +984,8 @@ mod tests {
             permissions: Some(0o644),
             owner_uid: Some(1000),
             owner_gid: Some(1000),
+            owner_name: None,
+            group_name: None,
             created_time: Some(Utc::now() - Duration::days(1)),
             modified_time: Some(Utc::now() - Duration::days(1)),
             accessed_time: Some(Utc::now()),
@@ -708,6 +1008,7 @@ mod tests {
             is_recoverable: true,
             metadata,
             fs_metadata: None,
+            skip_reason: None,
         };
 
         let confidence = calculate_confidence_score(&file, &context);
@@ -718,6 +1019,128 @@ mod tests {
         assert!(confidence <= 1.0, "Confidence should not exceed 1.0");
     }
 
+    #[test]
+    fn test_high_entropy_lowers_signature_factor() {
+        let mut file = xfs_file_with_blocks(vec![range(100, 8)]);
+        file.metadata.mime_type = Some("image/jpeg".to_string());
+        file.metadata.file_extension = Some("jpg".to_string());
+        let plain = calculate_file_signature_factor(&file);
+
+        file.metadata
+            .extended_attributes
+            .insert("entropy".to_string(), b"4.200".to_vec());
+        assert_eq!(calculate_file_signature_factor(&file), plain);
+
+        file.metadata
+            .extended_attributes
+            .insert("entropy".to_string(), b"7.990".to_vec());
+        assert!(calculate_file_signature_factor(&file) < plain);
+
+        // Archives are near-random when intact
+        file.metadata.mime_type = Some("application/zip".to_string());
+        file.metadata.file_extension = Some("zip".to_string());
+        assert_eq!(calculate_file_signature_factor(&file), plain);
+    }
+
+    fn xfs_file_with_blocks(data_blocks: Vec<BlockRange>) -> DeletedFile {
+        DeletedFile {
+            id: 1,
+            inode_or_cluster: 131,
+            original_path: None,
+            size: 8 * 4096,
+            deletion_time: Some(Utc::now() - Duration::hours(2)),
+            confidence_score: 0.0,
+            file_type: FileType::RegularFile,
+            data_blocks,
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: Some("image/jpeg".to_string()),
+                file_extension: Some("jpg".to_string()),
+                permissions: Some(0o644),
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: Some(crate::FsSpecificMetadata::Xfs(crate::XfsFileMetadata {
+                ag_number: 0,
+                ag_inode_number: 131,
+                extent_count: 2,
+                extent_format: crate::XfsExtentFormat::Extents,
+                is_aligned: true,
+                last_link_count: 1,
+                inode_generation: 1,
+                inline_data: None,
+                sparse_ranges: Vec::new(),
+            })),
+            skip_reason: None,
+        }
+    }
+
+    fn range(start_block: u64, block_count: u64) -> BlockRange {
+        BlockRange {
+            start_block,
+            block_count,
+            is_allocated: false,
+        }
+    }
+
+    #[test]
+    fn test_overlapping_extents_lower_confidence() {
+        let context = ConfidenceContext {
+            fs_type: FileSystemType::Xfs,
+            scan_time: Utc::now(),
+            filesystem_integrity: 0.9,
+            total_files_found: 100,
+            device_activity_level: ActivityLevel::Low,
+            weights: ConfidenceWeights::default(),
+            block_size: 4096,
+        };
+
+        let disjoint = xfs_file_with_blocks(vec![range(100, 4), range(200, 4)]);
+        let overlapping = xfs_file_with_blocks(vec![range(100, 4), range(102, 4)]);
+        assert!(!check_extent_overlaps(&disjoint.data_blocks));
+        assert!(check_extent_overlaps(&overlapping.data_blocks));
+
+        let Some(crate::FsSpecificMetadata::Xfs(ref meta)) = disjoint.fs_metadata else {
+            unreachable!()
+        };
+        assert!(
+            calculate_xfs_extent_integrity(&overlapping, meta)
+                < calculate_xfs_extent_integrity(&disjoint, meta)
+        );
+        assert!(
+            calculate_data_block_integrity_factor(&overlapping.data_blocks)
+                < calculate_data_block_integrity_factor(&disjoint.data_blocks)
+        );
+
+        // The base factor applies to every filesystem
+        let btrfs_context = ConfidenceContext {
+            fs_type: FileSystemType::Btrfs,
+            ..context.clone()
+        };
+        for context in [&context, &btrfs_context] {
+            assert!(
+                calculate_confidence_score(&overlapping, context)
+                    < calculate_confidence_score(&disjoint, context)
+            );
+        }
+    }
+
+    #[test]
+    fn test_extent_overlap_detection() {
+        // Touching ranges do not overlap, empty ranges are ignored
+        assert!(!check_extent_overlaps(&[range(0, 4), range(4, 4)]));
+        assert!(!check_extent_overlaps(&[range(10, 0), range(8, 4)]));
+        // Containment is found even when the ranges are not adjacent after sorting
+        assert!(check_extent_overlaps(&[range(0, 100), range(50, 2), range(20, 2)]));
+        assert!(check_extent_overlaps(&[range(7, 1), range(7, 1)]));
+    }
+
     #[test]
     fn test_mime_extension_matching() {
         assert!(mime_extension_match("image/jpeg", "jpg"));
@@ -734,6 +1157,8 @@ mod tests {
             filesystem_integrity: 0.9,
             total_files_found: 100,
             device_activity_level: ActivityLevel::Low,
+            weights: ConfidenceWeights::default(),
+            block_size: 4096,
         };
 
         let btrfs_meta = crate::BtrfsFileMetadata {
@@ -744,6 +1169,9 @@ mod tests {
             in_snapshot: true,
             cow_extent_count: 5,
             tree_level: 0,
+            compressed_extents: vec![],
+            backup_generation: None,
+            inline_data: None,
         };
 
         let file = DeletedFile {
@@ -766,12 +1194,15 @@ mod tests {
                 permissions: None,
                 owner_uid: None,
                 owner_gid: None,
+                owner_name: None,
+                group_name: None,
                 created_time: Some(Utc::now() - Duration::days(10)),
                 modified_time: Some(Utc::now() - Duration::days(2)),
                 accessed_time: Some(Utc::now() - Duration::hours(1)),
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: Some(crate::FsSpecificMetadata::Btrfs(btrfs_meta)),
+            skip_reason: None,
         };
 
         let confidence = calculate_confidence_score(&file, &context);
@@ -793,6 +1224,8 @@ mod tests {
             filesystem_integrity: 0.7,
             total_files_found: 100,
             device_activity_level: ActivityLevel::Medium,
+            weights: ConfidenceWeights::default(),
+            block_size: 4096,
         };
 
         let btrfs_meta = crate::BtrfsFileMetadata {
@@ -803,6 +1236,9 @@ mod tests {
             in_snapshot: false,
             cow_extent_count: 3,
             tree_level: 0,
+            compressed_extents: vec![],
+            backup_generation: None,
+            inline_data: None,
         };
 
         let file = DeletedFile {
@@ -825,12 +1261,15 @@ mod tests {
                 permissions: None,
                 owner_uid: None,
                 owner_gid: None,
+                owner_name: None,
+                group_name: None,
                 created_time: None,
                 modified_time: None,
                 accessed_time: None,
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: Some(crate::FsSpecificMetadata::Btrfs(btrfs_meta)),
+            skip_reason: None,
         };
 
         let confidence = calculate_confidence_score(&file, &context);
@@ -851,6 +1290,8 @@ mod tests {
             filesystem_integrity: 0.85,
             total_files_found: 50,
             device_activity_level: ActivityLevel::Low,
+            weights: ConfidenceWeights::default(),
+            block_size: 4096,
         };
 
         let exfat_meta = crate::ExFatFileMetadata {
@@ -883,12 +1324,15 @@ mod tests {
                 permissions: None,
                 owner_uid: None,
                 owner_gid: None,
+                owner_name: None,
+                group_name: None,
                 created_time: Some(Utc::now() - Duration::days(5)),
                 modified_time: Some(Utc::now() - Duration::days(1)),
                 accessed_time: Some(Utc::now() - Duration::hours(3)),
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: Some(crate::FsSpecificMetadata::ExFat(exfat_meta)),
+            skip_reason: None,
         };
 
         let confidence = calculate_confidence_score(&file, &context);
@@ -911,6 +1355,8 @@ mod tests {
             filesystem_integrity: 0.6,
             total_files_found: 50,
             device_activity_level: ActivityLevel::High,
+            weights: ConfidenceWeights::default(),
+            block_size: 4096,
         };
 
         let exfat_meta = crate::ExFatFileMetadata {
@@ -943,12 +1389,15 @@ mod tests {
                 permissions: None,
                 owner_uid: None,
                 owner_gid: None,
+                owner_name: None,
+                group_name: None,
                 created_time: None,
                 modified_time: None,
                 accessed_time: None,
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: Some(crate::FsSpecificMetadata::ExFat(exfat_meta)),
+            skip_reason: None,
         };
 
         let confidence = calculate_confidence_score(&file, &context);
@@ -964,4 +1413,288 @@ mod tests {
             "Should still have some confidence from valid cluster number"
         );
     }
+
+    #[test]
+    fn test_size_consistency_uses_cluster_size() {
+        // A 128KB photo in two 64KB clusters of an SD card
+        let context_with = |block_size: u64| ConfidenceContext {
+            fs_type: FileSystemType::ExFat,
+            scan_time: Utc::now(),
+            filesystem_integrity: 0.75,
+            total_files_found: 1,
+            device_activity_level: ActivityLevel::Low,
+            weights: ConfidenceWeights::default(),
+            block_size,
+        };
+        let mut file = DeletedFile {
+            id: 1,
+            inode_or_cluster: 200,
+            original_path: None,
+            size: 128 * 1024,
+            deletion_time: None,
+            confidence_score: 0.0,
+            file_type: FileType::RegularFile,
+            data_blocks: vec![BlockRange {
+                start_block: 200,
+                block_count: 2,
+                is_allocated: false,
+            }],
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: Some("image/jpeg".to_string()),
+                file_extension: Some("jpg".to_string()),
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: Some(crate::FsSpecificMetadata::ExFat(crate::ExFatFileMetadata {
+                first_cluster: 200,
+                cluster_chain: vec![200, 201],
+                chain_valid: true,
+                checksum: 0,
+                entry_count: 0,
+                utf16_valid: false,
+                attributes: 0,
+            })),
+            skip_reason: None,
+        };
+
+        let sd_card = context_with(64 * 1024);
+        assert_eq!(calculate_size_consistency_factor(&file, &sd_card), 1.0);
+        // Assuming 4KB clusters makes the file look sixteen times its size
+        assert_eq!(
+            calculate_size_consistency_factor(&file, &context_with(4096)),
+            0.0625
+        );
+
+        // Cluster chains are recorded in bytes whatever the cluster size
+        file.data_blocks[0].start_block = 12 * 1024 * 1024;
+        file.data_blocks[0].block_count = 128 * 1024;
+        assert_eq!(calculate_size_consistency_factor(&file, &sd_card), 1.0);
+
+        // Carved files are recorded in bytes on exFAT, in blocks elsewhere
+        file.fs_metadata = None;
+        assert_eq!(calculate_size_consistency_factor(&file, &sd_card), 1.0);
+        file.data_blocks[0].block_count = 128;
+        let xfs = ConfidenceContext {
+            fs_type: FileSystemType::Xfs,
+            ..context_with(1024)
+        };
+        assert_eq!(calculate_size_consistency_factor(&file, &xfs), 1.0);
+    }
+
+    #[test]
+    fn test_confidence_weights_change_score() {
+        let file = DeletedFile {
+            id: 1,
+            inode_or_cluster: 4242,
+            original_path: None,
+            size: 8192,
+            deletion_time: Some(Utc::now() - Duration::minutes(10)),
+            confidence_score: 0.0,
+            file_type: FileType::RegularFile,
+            data_blocks: vec![],
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        };
+
+        let zero = ConfidenceWeights {
+            time_recency: 0.0,
+            metadata_completeness: 0.0,
+            data_block_integrity: 0.0,
+            file_signature_match: 0.0,
+            size_consistency: 0.0,
+            fs_specific: 0.0,
+        };
+        let context_with = |weights: ConfidenceWeights| ConfidenceContext {
+            fs_type: FileSystemType::Xfs,
+            scan_time: Utc::now(),
+            filesystem_integrity: 1.0,
+            total_files_found: 1,
+            device_activity_level: ActivityLevel::Low,
+            weights,
+            block_size: 4096,
+        };
+
+        // All weight on one factor yields exactly that factor's score
+        let recency_only = context_with(ConfidenceWeights {
+            time_recency: 1.0,
+            ..zero
+        });
+        let signature_only = context_with(ConfidenceWeights {
+            file_signature_match: 1.0,
+            ..zero
+        });
+        let recency_score = calculate_confidence_score(&file, &recency_only);
+        let signature_score = calculate_confidence_score(&file, &signature_only);
+
+        assert_eq!(
+            recency_score,
+            calculate_time_recency_factor(file.deletion_time, recency_only.scan_time)
+        );
+        assert_eq!(signature_score, calculate_file_signature_factor(&file));
+        assert!(recency_score > signature_score);
+
+        // Weights are normalized, so scaling them all leaves the score unchanged
+        let defaults = ConfidenceWeights::default();
+        let doubled = ConfidenceWeights {
+            time_recency: defaults.time_recency * 2.0,
+            metadata_completeness: defaults.metadata_completeness * 2.0,
+            data_block_integrity: defaults.data_block_integrity * 2.0,
+            file_signature_match: defaults.file_signature_match * 2.0,
+            size_consistency: defaults.size_consistency * 2.0,
+            fs_specific: defaults.fs_specific * 2.0,
+        };
+        let default_score = calculate_confidence_score(&file, &context_with(defaults));
+        let doubled_score = calculate_confidence_score(&file, &context_with(doubled));
+        assert!((default_score - doubled_score).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_confidence_weights_validation() {
+        assert!(ConfidenceWeights::default().validate());
+        assert!((ConfidenceWeights::default().total() - 1.0).abs() < 1e-6);
+
+        let unbalanced = ConfidenceWeights {
+            time_recency: 0.9,
+            ..Default::default()
+        };
+        assert!(!unbalanced.validate());
+
+        let negative = ConfidenceWeights {
+            time_recency: 0.5,
+            fs_specific: -0.1,
+            ..Default::default()
+        };
+        assert!(!negative.validate());
+
+        // Partial JSON keeps defaults for missing fields
+        let weights: ConfidenceWeights =
+            serde_json::from_str(r#"{"time_recency": 0.10, "file_signature_match": 0.30}"#)
+                .unwrap();
+        assert_eq!(weights.file_signature_match, 0.30);
+        assert_eq!(weights.size_consistency, 0.10);
+        assert!(weights.validate());
+    }
+
+    #[test]
+    fn test_calibration_report_buckets() {
+        // (confidence, structural check result)
+        let dataset = [
+            (0.05, StructuralValidity::Corrupt("bad header".to_string())),
+            (0.15, StructuralValidity::Corrupt("bad header".to_string())),
+            (0.15, StructuralValidity::Valid),
+            (0.85, StructuralValidity::Valid),
+            (0.85, StructuralValidity::Valid),
+            (
+                0.95,
+                StructuralValidity::Truncated("no trailer".to_string()),
+            ),
+            (1.0, StructuralValidity::Valid),
+            (0.5, StructuralValidity::Unchecked),
+        ];
+        let mut files = Vec::new();
+        let mut results = HashMap::new();
+        for (id, (confidence, result)) in dataset.into_iter().enumerate() {
+            let mut file = xfs_file_with_blocks(Vec::new());
+            file.id = id as u64;
+            file.confidence_score = confidence;
+            files.push(file);
+            results.insert(id as u64, result);
+        }
+        // A file with no result at all
+        let mut unverified = xfs_file_with_blocks(Vec::new());
+        unverified.id = 99;
+        files.push(unverified);
+
+        let report = calibration_report(&files, &results);
+        assert_eq!(report.sample_count, 7);
+        assert_eq!(report.skipped, 2);
+        assert_eq!(report.buckets.len(), CONFIDENCE_BUCKETS);
+
+        let bucket = |i: usize| &report.buckets[i];
+        assert_eq!(bucket(0).count, 1);
+        assert_eq!(bucket(0).success_rate, 0.0);
+        assert_eq!(bucket(1).count, 2);
+        assert!((bucket(1).mean_confidence - 0.15).abs() < 1e-6);
+        assert_eq!(bucket(1).success_rate, 0.5);
+        assert_eq!(bucket(5).count, 0);
+        assert_eq!(bucket(8).count, 2);
+        assert_eq!(bucket(8).success_rate, 1.0);
+        // A confidence of exactly 1.0 lands in the top bucket
+        assert_eq!(bucket(9).count, 2);
+        assert!((bucket(9).mean_confidence - 0.975).abs() < 1e-6);
+        assert_eq!(bucket(9).success_rate, 0.5);
+
+        let squared_errors: f32 = [0.05f32, 0.15, 0.85, 0.15, 0.15, 0.95, 0.0]
+            .iter()
+            .map(|e| e * e)
+            .sum();
+        assert!((report.brier_score - squared_errors / 7.0).abs() < 1e-6);
+
+        // |0.05-0| + 2*|0.15-0.5| + 2*|0.85-1| + 2*|0.975-0.5|, over 7
+        let expected = (0.05 + 2.0 * 0.35 + 2.0 * 0.15 + 2.0 * 0.475) / 7.0;
+        assert!((report.expected_calibration_error() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_confidence_histogram_buckets() {
+        let scores = [0.0, 0.05, 0.1, 0.39, 0.4, 0.45, 0.6, 0.99, 1.0, 1.2];
+        let files: Vec<DeletedFile> = scores
+            .iter()
+            .map(|&score| {
+                let mut file = xfs_file_with_blocks(Vec::new());
+                file.confidence_score = score;
+                file
+            })
+            .collect();
+
+        // Bucket edges are inclusive below; 1.0 and stray scores above it land in the top bucket
+        assert_eq!(confidence_histogram(&files), [2, 1, 0, 1, 2, 0, 1, 0, 0, 3]);
+        assert_eq!(confidence_histogram(&[]), [0; CONFIDENCE_BUCKETS]);
+    }
+
+    #[test]
+    fn test_calibration_report_without_results() {
+        let report = calibration_report(&[xfs_file_with_blocks(Vec::new())], &HashMap::new());
+        assert_eq!(report.sample_count, 0);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.brier_score, 0.0);
+        assert_eq!(report.expected_calibration_error(), 0.0);
+    }
+
+    #[test]
+    fn test_activity_level_from_fullness() {
+        assert_eq!(ActivityLevel::from_fullness(0.0, false), ActivityLevel::Low);
+        assert_eq!(ActivityLevel::from_fullness(0.3, false), ActivityLevel::Low);
+        assert_eq!(ActivityLevel::from_fullness(0.5, false), ActivityLevel::Medium);
+        assert_eq!(ActivityLevel::from_fullness(0.7, false), ActivityLevel::Medium);
+        assert_eq!(ActivityLevel::from_fullness(0.85, false), ActivityLevel::High);
+        assert_eq!(ActivityLevel::from_fullness(1.0, false), ActivityLevel::High);
+
+        // A recent mount raises the estimate by one level
+        assert_eq!(ActivityLevel::from_fullness(0.3, true), ActivityLevel::Medium);
+        assert_eq!(ActivityLevel::from_fullness(0.7, true), ActivityLevel::High);
+        assert_eq!(ActivityLevel::from_fullness(0.95, true), ActivityLevel::High);
+    }
 }
@@ -7,12 +7,11 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-use super::{DirectoryReconstructor, ReconstructionStats};
+use super::{DirectoryReconstructor, ReconstructionStats, TreeEntry};
 use crate::fs::common::BlockDevice;
 
 // Btrfs file types
 const BTRFS_FT_REG_FILE: u8 = 1;
-#[cfg(test)]
 const BTRFS_FT_DIR: u8 = 2;
 
 /// Btrfs directory entry information
@@ -194,6 +193,19 @@ impl DirectoryReconstructor for BtrfsDirReconstructor {
             root_id: Some(self.root_inode),
         }
     }
+
+    fn tree_entries(&self) -> Vec<TreeEntry> {
+        self.entries
+            .values()
+            .map(|e| TreeEntry {
+                id: e.inode,
+                parent_id: e.parent_inode,
+                name: e.name.clone(),
+                is_directory: e.file_type == BTRFS_FT_DIR,
+                size: None,
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -10,7 +10,7 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-use super::{DirectoryReconstructor, ReconstructionStats};
+use super::{DirectoryReconstructor, ReconstructionStats, TreeEntry};
 use crate::fs::common::BlockDevice;
 
 // exFAT directory entry type codes
@@ -202,7 +202,7 @@ impl ExFatDirReconstructor {
 
         // Read the directory cluster
         let offset = self.cluster_offset(start_cluster);
-        let cluster_data = device.read_bytes(offset, self.cluster_size as usize)?;
+        let cluster_data = device.read_at(offset, self.cluster_size as usize)?;
 
         // Parse entries in this cluster
         let entries = self.parse_directory_cluster(cluster_data.as_ref(), start_cluster);
@@ -290,6 +290,19 @@ impl DirectoryReconstructor for ExFatDirReconstructor {
             root_id: Some(self.root_cluster),
         }
     }
+
+    fn tree_entries(&self) -> Vec<TreeEntry> {
+        self.entries
+            .values()
+            .map(|e| TreeEntry {
+                id: e.first_cluster,
+                parent_id: e.parent_cluster,
+                name: e.name.clone(),
+                is_directory: e.is_directory,
+                size: Some(e.size),
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -303,6 +316,67 @@ mod tests {
         assert_eq!(EXFAT_TYPE_NAME, 0xC1);
     }
 
+    /// File + Stream + one Name entry (names up to 15 characters)
+    fn file_entry_set(name: &str, first_cluster: u32, size: u64, is_directory: bool) -> Vec<u8> {
+        let mut set = vec![0u8; 96];
+        set[0] = EXFAT_TYPE_FILE;
+        set[1] = 2;
+        if is_directory {
+            set[4..6].copy_from_slice(&ATTR_DIRECTORY.to_le_bytes());
+        }
+        set[32] = EXFAT_TYPE_STREAM;
+        set[35] = name.len() as u8;
+        set[52..56].copy_from_slice(&first_cluster.to_le_bytes());
+        set[56..64].copy_from_slice(&size.to_le_bytes());
+        set[64] = EXFAT_TYPE_NAME;
+        for (i, unit) in name.encode_utf16().enumerate() {
+            set[66 + i * 2..68 + i * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        set
+    }
+
+    #[test]
+    fn test_build_tree_from_scanned_directories() {
+        use std::path::Path;
+
+        // Cluster heap at offset 0: root dir in cluster 2, DCIM in cluster 3
+        let mut image = vec![0u8; 8 * 4096];
+        let root = [
+            file_entry_set("DCIM", 3, 4096, true),
+            file_entry_set("notes.txt", 4, 120, false),
+        ]
+        .concat();
+        image[..root.len()].copy_from_slice(&root);
+
+        let mut deleted = file_entry_set("old.jpg", 6, 2048, false);
+        deleted[0] = EXFAT_TYPE_FILE_DELETED;
+        let dcim = [file_entry_set("IMG_0001.JPG", 5, 5000, false), deleted].concat();
+        image[4096..4096 + dcim.len()].copy_from_slice(&dcim);
+
+        let device = BlockDevice::from_vec(image);
+        let mut reconstructor = ExFatDirReconstructor::new(4096, 2, 0);
+        assert_eq!(reconstructor.scan_directories(&device).unwrap(), 4);
+
+        let tree = reconstructor.build_tree();
+        assert_eq!(tree.total_entries, 4);
+        assert_eq!(tree.orphaned_entries, 0);
+
+        let names: Vec<_> = tree.root.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["DCIM", "notes.txt"]);
+
+        let dcim = tree.get(Path::new("/DCIM")).unwrap();
+        assert!(dcim.is_directory);
+        assert_eq!(dcim.children.len(), 2);
+
+        let photo = tree.get(Path::new("/DCIM/IMG_0001.JPG")).unwrap();
+        assert_eq!(photo.id, Some(5));
+        assert_eq!(photo.size, Some(5000));
+        assert!(tree.get(Path::new("/DCIM/old.jpg")).is_some());
+
+        let json = tree.to_json().unwrap();
+        assert!(json.contains("\"IMG_0001.JPG\""));
+    }
+
     #[test]
     fn test_cluster_offset_calculation() {
         let reconstructor = ExFatDirReconstructor::new(4096, 5, 1048576);
@@ -5,10 +5,12 @@
 
 mod btrfs;
 mod exfat;
+mod tree;
 mod xfs;
 
 pub use btrfs::{BtrfsDirEntry, BtrfsDirReconstructor};
 pub use exfat::{ExFatDirEntry, ExFatDirReconstructor};
+pub use tree::{RecoveredTree, TreeEntry, TreeNode, LOST_AND_FOUND};
 pub use xfs::{XfsDirEntry, XfsDirReconstructor};
 
 use crate::fs::common::BlockDevice;
@@ -39,4 +41,12 @@ pub trait DirectoryReconstructor {
 
     /// Get reconstruction statistics
     fn stats(&self) -> ReconstructionStats;
+
+    /// All entries found so far, as flat (id, parent) records
+    fn tree_entries(&self) -> Vec<TreeEntry>;
+
+    /// Assemble all entries into a nested tree rooted at the detected root
+    fn build_tree(&self) -> RecoveredTree {
+        RecoveredTree::from_entries(&self.tree_entries(), self.stats().root_id)
+    }
 }
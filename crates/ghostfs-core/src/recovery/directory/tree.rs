@@ -0,0 +1,267 @@
+//! Recovered namespace as a tree
+//!
+//! Assembles the flat (id -> parent) entries found by a reconstructor into a
+//! nested directory tree. Entries whose parent chain never reaches the root
+//! are collected under a synthetic `/lost+found`, like fsck does.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path};
+
+/// Name of the directory holding entries that could not be attached to the root
+pub const LOST_AND_FOUND: &str = "lost+found";
+
+/// Parent chains deeper than this are cut and the rest goes to lost+found
+const MAX_TREE_DEPTH: usize = 256;
+
+/// A flat directory entry as reported by a reconstructor
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    /// Inode or first cluster of the entry
+    pub id: u64,
+    /// Inode or cluster of the directory containing the entry
+    pub parent_id: u64,
+    /// Filename (without path)
+    pub name: String,
+    /// Whether the entry is a directory
+    pub is_directory: bool,
+    /// File size, if the directory entry records it
+    pub size: Option<u64>,
+}
+
+/// A file or directory in the recovered tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeNode {
+    /// Filename, or "/" for the root
+    pub name: String,
+    /// Inode or cluster; `None` for the root and synthetic directories
+    pub id: Option<u64>,
+    pub is_directory: bool,
+    pub size: Option<u64>,
+    /// Directories first, then files, each sorted by name
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn directory(name: &str, id: Option<u64>) -> Self {
+        Self {
+            name: name.to_string(),
+            id,
+            is_directory: true,
+            size: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Number of nodes below this one
+    pub fn descendant_count(&self) -> usize {
+        self.children
+            .iter()
+            .map(|child| 1 + child.descendant_count())
+            .sum()
+    }
+}
+
+/// The whole recovered namespace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveredTree {
+    pub root: TreeNode,
+    /// Entries placed in the tree (excluding the root itself)
+    pub total_entries: usize,
+    /// Entries that ended up under `/lost+found`
+    pub orphaned_entries: usize,
+}
+
+impl RecoveredTree {
+    /// Build a tree from flat entries, rooted at `root_id`
+    ///
+    /// Every entry appears exactly once: a visited set breaks cycles in
+    /// corrupted parent links, and anything unreachable from the root is
+    /// attached under `/lost+found` at its topmost known ancestor.
+    pub fn from_entries(entries: &[TreeEntry], root_id: Option<u64>) -> Self {
+        let by_id: HashMap<u64, &TreeEntry> = entries.iter().map(|e| (e.id, e)).collect();
+        let mut children: HashMap<u64, Vec<&TreeEntry>> = HashMap::new();
+        for entry in by_id.values() {
+            if Some(entry.id) != root_id {
+                children.entry(entry.parent_id).or_default().push(entry);
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut root = TreeNode::directory("/", root_id);
+        if let Some(root_id) = root_id {
+            visited.insert(root_id);
+            root.children = build_children(root_id, &children, &mut visited, 0);
+        }
+
+        let mut ids: Vec<u64> = by_id.keys().copied().collect();
+        ids.sort_unstable();
+
+        let mut lost = Vec::new();
+        for id in ids {
+            if visited.contains(&id) {
+                continue;
+            }
+
+            // Climb to the topmost unplaced ancestor, stopping if the chain loops
+            let mut top = id;
+            let mut chain = HashSet::from([id]);
+            while let Some(parent) = by_id
+                .get(&by_id[&top].parent_id)
+                .map(|parent| parent.id)
+                .filter(|parent| !visited.contains(parent) && chain.insert(*parent))
+            {
+                top = parent;
+            }
+
+            visited.insert(top);
+            lost.push(build_node(by_id[&top], &children, &mut visited, 0));
+        }
+
+        let orphaned_entries = lost.iter().map(|node| 1 + node.descendant_count()).sum();
+        if !lost.is_empty() {
+            let existing = root
+                .children
+                .iter_mut()
+                .find(|child| child.is_directory && child.name == LOST_AND_FOUND);
+            match existing {
+                Some(lost_and_found) => {
+                    lost_and_found.children.extend(lost);
+                    sort_nodes(&mut lost_and_found.children);
+                }
+                None => {
+                    let mut lost_and_found = TreeNode::directory(LOST_AND_FOUND, None);
+                    lost_and_found.children = lost;
+                    sort_nodes(&mut lost_and_found.children);
+                    root.children.push(lost_and_found);
+                    sort_nodes(&mut root.children);
+                }
+            }
+        }
+
+        Self {
+            total_entries: by_id.len()
+                - usize::from(root_id.is_some_and(|r| by_id.contains_key(&r))),
+            orphaned_entries,
+            root,
+        }
+    }
+
+    /// Look up a node by absolute path, e.g. `/home/user/file.txt`
+    pub fn get(&self, path: &Path) -> Option<&TreeNode> {
+        let mut node = &self.root;
+        for component in path.components() {
+            match component {
+                Component::RootDir | Component::CurDir => {}
+                Component::Normal(name) => {
+                    node = node
+                        .children
+                        .iter()
+                        .find(|child| child.name.as_str() == name)?;
+                }
+                _ => return None,
+            }
+        }
+        Some(node)
+    }
+
+    /// Export the tree as JSON (for the GUI)
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+fn build_node(
+    entry: &TreeEntry,
+    children: &HashMap<u64, Vec<&TreeEntry>>,
+    visited: &mut HashSet<u64>,
+    depth: usize,
+) -> TreeNode {
+    let mut node = TreeNode {
+        name: entry.name.clone(),
+        id: Some(entry.id),
+        is_directory: entry.is_directory,
+        size: entry.size,
+        children: Vec::new(),
+    };
+    if depth < MAX_TREE_DEPTH {
+        node.children = build_children(entry.id, children, visited, depth + 1);
+    }
+    // Anything with children is a directory, even if its type was unknown
+    node.is_directory |= !node.children.is_empty();
+    node
+}
+
+fn build_children(
+    parent_id: u64,
+    children: &HashMap<u64, Vec<&TreeEntry>>,
+    visited: &mut HashSet<u64>,
+    depth: usize,
+) -> Vec<TreeNode> {
+    let mut nodes = Vec::new();
+    for child in children.get(&parent_id).into_iter().flatten() {
+        if visited.insert(child.id) {
+            nodes.push(build_node(child, children, visited, depth));
+        }
+    }
+    sort_nodes(&mut nodes);
+    nodes
+}
+
+fn sort_nodes(nodes: &mut [TreeNode]) {
+    nodes.sort_by(|a, b| {
+        b.is_directory
+            .cmp(&a.is_directory)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, parent_id: u64, name: &str, is_directory: bool) -> TreeEntry {
+        TreeEntry {
+            id,
+            parent_id,
+            name: name.to_string(),
+            is_directory,
+            size: None,
+        }
+    }
+
+    #[test]
+    fn test_cycle_is_broken() {
+        // 10 and 11 point at each other; neither reaches the root
+        let entries = vec![
+            entry(2, 1, "etc", true),
+            entry(10, 11, "a", true),
+            entry(11, 10, "b", true),
+            entry(12, 11, "c.txt", false),
+        ];
+        let tree = RecoveredTree::from_entries(&entries, Some(1));
+
+        assert_eq!(tree.total_entries, 4);
+        assert_eq!(tree.orphaned_entries, 3);
+        assert_eq!(tree.root.descendant_count(), 5); // 4 entries + lost+found
+        assert!(tree.get(Path::new("/etc")).is_some());
+        assert!(tree.get(Path::new("/lost+found/b/a")).is_some());
+        assert!(tree.get(Path::new("/lost+found/b/c.txt")).is_some());
+    }
+
+    #[test]
+    fn test_self_parent_and_existing_lost_and_found() {
+        let entries = vec![
+            entry(3, 1, LOST_AND_FOUND, true),
+            entry(4, 3, "#4", false),
+            entry(5, 5, "self", false),
+        ];
+        let tree = RecoveredTree::from_entries(&entries, Some(1));
+
+        // Orphans are merged into the real lost+found
+        assert_eq!(tree.root.children.len(), 1);
+        let lost_and_found = tree.get(Path::new("/lost+found")).unwrap();
+        assert_eq!(lost_and_found.children.len(), 2);
+        assert_eq!(tree.orphaned_entries, 1);
+    }
+}
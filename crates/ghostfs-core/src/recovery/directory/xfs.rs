@@ -10,18 +10,34 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-use super::{DirectoryReconstructor, ReconstructionStats};
+use super::{DirectoryReconstructor, ReconstructionStats, TreeEntry};
 use crate::fs::common::BlockDevice;
 
 // XFS directory block magic numbers
-const XFS_DIR2_BLOCK_MAGIC: u32 = 0x58443244; // "XD2D"
-const XFS_DIR3_BLOCK_MAGIC: u32 = 0x58443344; // "XD3D"
+/// Single-block directory, v4 ("XD2B") and v5 ("XDB3")
+const XFS_DIR2_BLOCK_MAGIC: u32 = 0x58443242;
+const XFS_DIR3_BLOCK_MAGIC: u32 = 0x58444233;
+/// Data block of a leaf/node directory, v4 ("XD2D") and v5 ("XDD3")
+const XFS_DIR2_DATA_MAGIC: u32 = 0x58443244;
+const XFS_DIR3_DATA_MAGIC: u32 = 0x58444433;
+
+// Directory data block layout
+const XFS_DIR2_DATA_HDR_SIZE: usize = 16;
+const XFS_DIR3_DATA_HDR_SIZE: usize = 64;
+const XFS_DIR3_OWNER_OFFSET: usize = 40;
+/// Marks an unused region in a data block, followed by its 16-bit length
+const XFS_DIR2_DATA_FREE_TAG: u16 = 0xFFFF;
+/// Block directories end with a hash index of 8-byte leaf entries and an
+/// 8-byte tail holding the leaf count
+const XFS_DIR2_BLOCK_TAIL_SIZE: usize = 8;
+const XFS_DIR2_LEAF_ENTRY_SIZE: usize = 8;
 
 // XFS file types (v5 filesystems)
 pub const XFS_DIR3_FT_UNKNOWN: u8 = 0;
 #[cfg(test)]
 const XFS_DIR3_FT_REG_FILE: u8 = 1;
 pub const XFS_DIR3_FT_DIR: u8 = 2;
+const XFS_DIR3_FT_MAX: u8 = 8;
 
 /// XFS directory entry information
 #[derive(Debug, Clone)]
@@ -79,117 +95,221 @@ impl XfsDirReconstructor {
         tracing::debug!("🌳 Root inode set to: {}", inode);
     }
 
-    /// Parse a single directory block
+    /// Parse a single directory block, returning its live entries
+    ///
+    /// Handles both single-block directories and the data blocks of
+    /// leaf/node directories. Blocks without a directory magic yield no entries.
     pub fn parse_dir_block(
         &self,
         block_data: &[u8],
         block_number: u64,
     ) -> Result<Vec<XfsDirEntry>> {
-        if block_data.len() < 16 {
+        if block_data.len() < XFS_DIR2_DATA_HDR_SIZE {
             anyhow::bail!("Block too small for directory header");
         }
 
-        // Check magic number
-        let magic =
-            u32::from_be_bytes([block_data[0], block_data[1], block_data[2], block_data[3]]);
+        Ok(self
+            .parse_data_block(block_data, block_number)
+            .map(|block| block.entries)
+            .unwrap_or_default())
+    }
+
+    /// Parse a directory block and add its entries, including deleted
+    /// entries that can still be salvaged
+    pub fn add_dir_block(&mut self, block_data: &[u8], block_number: u64) -> usize {
+        let Some(block) = self.parse_data_block(block_data, block_number) else {
+            return 0;
+        };
+
+        // Only the root directory is its own parent
+        if let (Some(dir), Some(parent)) = (block.dir_inode, block.parent_inode) {
+            if dir == parent && self.root_inode.is_none() {
+                self.set_root_inode(dir);
+            }
+        }
+
+        let count = block.entries.len() + block.salvaged.len();
+        self.add_entries(block.entries);
+        self.add_salvaged_entries(block.salvaged);
+        count
+    }
 
-        let is_v5 = match magic {
-            XFS_DIR3_BLOCK_MAGIC => true,
-            XFS_DIR2_BLOCK_MAGIC => false,
-            _ => return Ok(Vec::new()), // Not a directory block
+    /// Add entries recovered from unused directory space
+    ///
+    /// A renamed or re-linked inode leaves a stale entry behind, so a live
+    /// entry for the same inode always wins.
+    fn add_salvaged_entries(&mut self, entries: Vec<XfsDirEntry>) {
+        for entry in entries {
+            self.entries.entry(entry.inode).or_insert(entry);
+        }
+    }
+
+    fn parse_data_block(&self, block_data: &[u8], block_number: u64) -> Option<ParsedDirBlock> {
+        let (is_v5, is_block_dir) = match be32(block_data, 0)? {
+            XFS_DIR2_BLOCK_MAGIC => (false, true),
+            XFS_DIR3_BLOCK_MAGIC => (true, true),
+            XFS_DIR2_DATA_MAGIC => (false, false),
+            XFS_DIR3_DATA_MAGIC => (true, false),
+            _ => return None, // Not a directory block
         };
 
-        // Extract owner inode (parent directory)
-        let owner_inode = if is_v5 && block_data.len() >= 16 {
-            u64::from_be_bytes([
-                block_data[8],
-                block_data[9],
-                block_data[10],
-                block_data[11],
-                block_data[12],
-                block_data[13],
-                block_data[14],
-                block_data[15],
-            ])
+        let header_size = if is_v5 {
+            XFS_DIR3_DATA_HDR_SIZE
+        } else {
+            XFS_DIR2_DATA_HDR_SIZE
+        };
+        // v5 blocks record the directory that owns them; v4 only via "."
+        let owner = if is_v5 {
+            be64(block_data, XFS_DIR3_OWNER_OFFSET).filter(|&inode| inode != 0)
         } else {
-            0
+            None
         };
 
-        // Start parsing entries after header
-        let mut entries = Vec::new();
-        let header_size = if is_v5 { 64 } else { 16 };
-        let mut offset = header_size;
+        // Entries stop where a single-block directory's hash index begins
+        let mut end = block_data.len();
+        if is_block_dir {
+            let leaf_count = be32(block_data, end.checked_sub(XFS_DIR2_BLOCK_TAIL_SIZE)?)?;
+            end = (leaf_count as usize)
+                .checked_mul(XFS_DIR2_LEAF_ENTRY_SIZE)
+                .and_then(|leaf_bytes| end.checked_sub(XFS_DIR2_BLOCK_TAIL_SIZE + leaf_bytes))
+                .filter(|&end| end >= header_size)?;
+        }
 
-        while offset + 11 < block_data.len() {
-            // Read inode number (8 bytes, big-endian)
-            if offset + 8 > block_data.len() {
-                break;
-            }
+        // v5 always records the file type in each entry
+        let has_ftype = is_v5;
+        let mut block = ParsedDirBlock {
+            dir_inode: owner,
+            parent_inode: None,
+            entries: Vec::new(),
+            salvaged: Vec::new(),
+        };
 
-            let inode = u64::from_be_bytes([
-                block_data[offset],
-                block_data[offset + 1],
-                block_data[offset + 2],
-                block_data[offset + 3],
-                block_data[offset + 4],
-                block_data[offset + 5],
-                block_data[offset + 6],
-                block_data[offset + 7],
-            ]);
-            offset += 8;
-
-            // Check for invalid inode (end of entries or free space)
-            if inode == 0 || inode == 0xFFFFFFFFFFFFFFFF {
-                break;
+        let mut offset = header_size;
+        while offset + 8 <= end {
+            if be16(block_data, offset)? == XFS_DIR2_DATA_FREE_TAG {
+                let length = be16(block_data, offset + 2)? as usize;
+                if length < 8 || !length.is_multiple_of(8) || offset + length > end {
+                    break;
+                }
+                if let Some(entry) = salvage_entry(
+                    &block_data[offset..offset + length],
+                    has_ftype,
+                    block_number,
+                ) {
+                    block.salvaged.push(entry);
+                }
+                offset += length;
+                continue;
             }
 
-            // Read name length (1 byte)
-            if offset >= block_data.len() {
+            let inode = be64(block_data, offset)?;
+            let namelen = *block_data.get(offset + 8)? as usize;
+            let entry_size = data_entry_size(namelen, has_ftype);
+            if inode == 0 || namelen == 0 || offset + entry_size > end {
                 break;
             }
-            let namelen = block_data[offset] as usize;
-            offset += 1;
 
-            if namelen == 0 || namelen > 255 {
-                break;
+            let name =
+                String::from_utf8_lossy(&block_data[offset + 9..offset + 9 + namelen]).to_string();
+            let file_type = if has_ftype {
+                block_data[offset + 9 + namelen]
+            } else {
+                XFS_DIR3_FT_UNKNOWN
+            };
+            offset += entry_size;
+
+            match name.as_str() {
+                "." => block.dir_inode = block.dir_inode.or(Some(inode)),
+                ".." => block.parent_inode = Some(inode),
+                _ => block.entries.push(XfsDirEntry {
+                    inode,
+                    name,
+                    file_type,
+                    parent_inode: 0,
+                    source_block: block_number,
+                }),
             }
+        }
 
-            // Read filename
-            if offset + namelen > block_data.len() {
-                break;
-            }
+        let parent = block.dir_inode.unwrap_or(0);
+        for entry in block.entries.iter_mut().chain(block.salvaged.iter_mut()) {
+            entry.parent_inode = parent;
+        }
 
-            let name_bytes = &block_data[offset..offset + namelen];
-            let name = String::from_utf8_lossy(name_bytes).to_string();
-            offset += namelen;
+        Some(block)
+    }
 
-            // Read file type (v5 only)
-            let ftype = if is_v5 && offset < block_data.len() {
-                let ft = block_data[offset];
-                offset += 1;
-                ft
+    /// Parse a short-form directory stored inline in its inode's data fork
+    ///
+    /// Returns the parent directory inode and the entries.
+    pub fn parse_shortform_dir(
+        &self,
+        dir_inode: u64,
+        data: &[u8],
+        has_ftype: bool,
+    ) -> Result<(u64, Vec<XfsDirEntry>)> {
+        let (Some(&count), Some(&i8count)) = (data.first(), data.get(1)) else {
+            anyhow::bail!("Short-form directory header truncated");
+        };
+        // Inode numbers are 8 bytes wide if any of them needs it
+        let ino_size = if i8count > 0 { 8 } else { 4 };
+        let parent = read_sf_inode(data, 2, ino_size)
+            .ok_or_else(|| anyhow::anyhow!("Short-form directory header truncated"))?;
+
+        let mut entries = Vec::with_capacity(count as usize);
+        let mut offset = 2 + ino_size;
+        for _ in 0..count {
+            // namelen(1) + offset tag(2) + name + [ftype(1)] + inumber
+            let namelen = *data
+                .get(offset)
+                .ok_or_else(|| anyhow::anyhow!("Short-form entry truncated"))?
+                as usize;
+            let name_start = offset + 3;
+            let name_bytes = data
+                .get(name_start..name_start + namelen)
+                .filter(|_| namelen > 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid short-form entry name"))?;
+
+            let mut pos = name_start + namelen;
+            let file_type = if has_ftype {
+                pos += 1;
+                *data
+                    .get(pos - 1)
+                    .ok_or_else(|| anyhow::anyhow!("Short-form entry truncated"))?
             } else {
                 XFS_DIR3_FT_UNKNOWN
             };
-
-            // Align to 8-byte boundary
-            offset = (offset + 7) & !7;
-
-            // Skip . and ..
-            if name == "." || name == ".." {
-                continue;
-            }
+            let inode = read_sf_inode(data, pos, ino_size)
+                .ok_or_else(|| anyhow::anyhow!("Short-form entry truncated"))?;
+            offset = pos + ino_size;
 
             entries.push(XfsDirEntry {
                 inode,
-                name,
-                file_type: ftype,
-                parent_inode: owner_inode,
-                source_block: block_number,
+                name: String::from_utf8_lossy(name_bytes).to_string(),
+                file_type,
+                parent_inode: dir_inode,
+                source_block: 0,
             });
         }
 
-        Ok(entries)
+        Ok((parent, entries))
+    }
+
+    /// Parse a short-form directory and add its entries
+    pub fn add_shortform_dir(
+        &mut self,
+        dir_inode: u64,
+        data: &[u8],
+        has_ftype: bool,
+    ) -> Result<usize> {
+        let (parent, entries) = self.parse_shortform_dir(dir_inode, data, has_ftype)?;
+        if parent == dir_inode && self.root_inode.is_none() {
+            self.set_root_inode(dir_inode);
+        }
+
+        let count = entries.len();
+        self.add_entries(entries);
+        Ok(count)
     }
 
     /// Detect root inode by finding lowest directory inode
@@ -273,6 +393,86 @@ impl XfsDirReconstructor {
     }
 }
 
+/// Entries and self/parent links found in one directory data block
+struct ParsedDirBlock {
+    /// Directory owning the block (v5 header, or the "." entry)
+    dir_inode: Option<u64>,
+    /// Target of the ".." entry
+    parent_inode: Option<u64>,
+    entries: Vec<XfsDirEntry>,
+    /// Deleted entries recovered from unused space
+    salvaged: Vec<XfsDirEntry>,
+}
+
+/// On-disk size of a data entry: inumber, namelen, name, [ftype], tag, padded to 8
+fn data_entry_size(namelen: usize, has_ftype: bool) -> usize {
+    (8 + 1 + namelen + usize::from(has_ftype) + 2 + 7) & !7
+}
+
+/// Recover a deleted entry from an unused region of a data block
+///
+/// Removing an entry only overwrites the first 4 bytes of its 8-byte inode
+/// number with the free tag and length, so the low 32 bits of the inode
+/// number, the name length and the name itself survive.
+fn salvage_entry(region: &[u8], has_ftype: bool, block_number: u64) -> Option<XfsDirEntry> {
+    let inode = be32(region, 4)? as u64;
+    let namelen = *region.get(8)? as usize;
+    if inode == 0 || namelen == 0 || data_entry_size(namelen, has_ftype) > region.len() {
+        return None;
+    }
+
+    let name_bytes = &region[9..9 + namelen];
+    if name_bytes.iter().any(|&b| b == 0 || b == b'/') {
+        return None;
+    }
+    let name = std::str::from_utf8(name_bytes).ok()?;
+    if name == "." || name == ".." {
+        return None;
+    }
+
+    let file_type = if has_ftype {
+        region[9 + namelen]
+    } else {
+        XFS_DIR3_FT_UNKNOWN
+    };
+    if file_type >= XFS_DIR3_FT_MAX {
+        return None;
+    }
+
+    Some(XfsDirEntry {
+        inode,
+        name: name.to_string(),
+        file_type,
+        parent_inode: 0,
+        source_block: block_number,
+    })
+}
+
+fn read_sf_inode(data: &[u8], offset: usize, size: usize) -> Option<u64> {
+    match size {
+        4 => be32(data, offset).map(u64::from),
+        _ => be64(data, offset),
+    }
+}
+
+fn be16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn be64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
 impl DirectoryReconstructor for XfsDirReconstructor {
     fn scan_directories(&mut self, device: &BlockDevice) -> Result<usize> {
         tracing::info!("🔍 Scanning XFS blocks for directory entries");
@@ -282,22 +482,18 @@ impl DirectoryReconstructor for XfsDirReconstructor {
 
         for block_num in 0..max_blocks {
             let offset = block_num * self.block_size as u64;
-            if let Ok(block_data) = device.read_bytes(offset, self.block_size as usize) {
-                if let Ok(entries) = self.parse_dir_block(block_data.as_ref(), block_num) {
-                    if !entries.is_empty() {
-                        tracing::debug!(
-                            "📂 Found {} entries in block {}",
-                            entries.len(),
-                            block_num
-                        );
-                        self.add_entries(entries);
-                    }
+            if let Ok(block_data) = device.read_at(offset, self.block_size as usize) {
+                let found = self.add_dir_block(block_data, block_num);
+                if found > 0 {
+                    tracing::debug!("📂 Found {} entries in block {}", found, block_num);
                 }
             }
         }
 
-        // Detect root inode
-        self.detect_root_inode();
+        // Detect root inode unless the caller or a directory block already knew it
+        if self.root_inode.is_none() {
+            self.detect_root_inode();
+        }
 
         tracing::info!("✅ Found {} XFS directory entries", self.entries.len());
         Ok(self.entries.len())
@@ -321,6 +517,19 @@ impl DirectoryReconstructor for XfsDirReconstructor {
             root_id: self.root_inode,
         }
     }
+
+    fn tree_entries(&self) -> Vec<TreeEntry> {
+        self.entries
+            .values()
+            .map(|e| TreeEntry {
+                id: e.inode,
+                parent_id: e.parent_inode,
+                name: e.name.clone(),
+                is_directory: e.file_type == XFS_DIR3_FT_DIR,
+                size: None,
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -329,8 +538,161 @@ mod tests {
 
     #[test]
     fn test_xfs_magic_constants() {
-        assert_eq!(XFS_DIR3_BLOCK_MAGIC, 0x58443344);
-        assert_eq!(XFS_DIR2_BLOCK_MAGIC, 0x58443244);
+        assert_eq!(&XFS_DIR2_BLOCK_MAGIC.to_be_bytes(), b"XD2B");
+        assert_eq!(&XFS_DIR3_BLOCK_MAGIC.to_be_bytes(), b"XDB3");
+        assert_eq!(&XFS_DIR2_DATA_MAGIC.to_be_bytes(), b"XD2D");
+        assert_eq!(&XFS_DIR3_DATA_MAGIC.to_be_bytes(), b"XDD3");
+    }
+
+    /// Append a data entry (inumber, namelen, name, [ftype], tag) at `offset`
+    fn put_entry(
+        block: &mut [u8],
+        offset: usize,
+        inode: u64,
+        name: &str,
+        file_type: Option<u8>,
+    ) -> usize {
+        block[offset..offset + 8].copy_from_slice(&inode.to_be_bytes());
+        block[offset + 8] = name.len() as u8;
+        block[offset + 9..offset + 9 + name.len()].copy_from_slice(name.as_bytes());
+        if let Some(file_type) = file_type {
+            block[offset + 9 + name.len()] = file_type;
+        }
+        let size = data_entry_size(name.len(), file_type.is_some());
+        block[offset + size - 2..offset + size].copy_from_slice(&(offset as u16).to_be_bytes());
+        offset + size
+    }
+
+    /// Mark `[offset, offset + length)` unused, as the kernel does on unlink
+    fn put_free(block: &mut [u8], offset: usize, length: usize) {
+        block[offset..offset + 2].copy_from_slice(&XFS_DIR2_DATA_FREE_TAG.to_be_bytes());
+        block[offset + 2..offset + 4].copy_from_slice(&(length as u16).to_be_bytes());
+        block[offset + length - 2..offset + length].copy_from_slice(&(offset as u16).to_be_bytes());
+    }
+
+    /// Single-block v5 directory (inode 131) inside the root directory (128):
+    /// ".", "..", "photos/", "report.pdf", a deleted "secret.doc" (inode
+    /// 140) and a hash index of 5 leaf entries before the tail
+    fn v5_block_dir() -> Vec<u8> {
+        let mut block = vec![0u8; 4096];
+        block[0..4].copy_from_slice(&XFS_DIR3_BLOCK_MAGIC.to_be_bytes());
+        block[XFS_DIR3_OWNER_OFFSET..XFS_DIR3_OWNER_OFFSET + 8]
+            .copy_from_slice(&131u64.to_be_bytes());
+
+        let mut offset = XFS_DIR3_DATA_HDR_SIZE;
+        offset = put_entry(&mut block, offset, 131, ".", Some(XFS_DIR3_FT_DIR));
+        offset = put_entry(&mut block, offset, 128, "..", Some(XFS_DIR3_FT_DIR));
+        offset = put_entry(&mut block, offset, 132, "photos", Some(XFS_DIR3_FT_DIR));
+        let deleted = offset;
+        offset = put_entry(
+            &mut block,
+            offset,
+            140,
+            "secret.doc",
+            Some(XFS_DIR3_FT_REG_FILE),
+        );
+        put_free(&mut block, deleted, offset - deleted);
+        offset = put_entry(
+            &mut block,
+            offset,
+            133,
+            "report.pdf",
+            Some(XFS_DIR3_FT_REG_FILE),
+        );
+
+        // Hash index and tail; everything up to it is one free region
+        let leaf_start = 4096 - XFS_DIR2_BLOCK_TAIL_SIZE - 5 * XFS_DIR2_LEAF_ENTRY_SIZE;
+        put_free(&mut block, offset, leaf_start - offset);
+        block[leaf_start..4096 - 8].fill(0xAB);
+        block[4096 - 8..4096 - 4].copy_from_slice(&5u32.to_be_bytes());
+        block
+    }
+
+    #[test]
+    fn test_block_dir_entries_and_salvage() {
+        let block = v5_block_dir();
+
+        let reconstructor = XfsDirReconstructor::new(4096);
+        let live = reconstructor.parse_dir_block(&block, 20).unwrap();
+        let names: Vec<&str> = live.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["photos", "report.pdf"]);
+        assert!(live.iter().all(|e| e.parent_inode == 131));
+
+        let mut reconstructor = XfsDirReconstructor::new(4096);
+        reconstructor.set_root_inode(128);
+        assert_eq!(reconstructor.add_dir_block(&block, 20), 3);
+        assert_eq!(
+            reconstructor.get_filename(140).as_deref(),
+            Some("secret.doc")
+        );
+        assert_eq!(reconstructor.entries[&140].source_block, 20);
+    }
+
+    #[test]
+    fn test_shortform_and_block_dirs_give_full_paths() {
+        // Root (128) is short-form: "docs" -> 131, then inode 131 is a block
+        // directory. Short-form parent equal to self marks the root.
+        let mut sf = vec![1u8, 0];
+        sf.extend_from_slice(&128u32.to_be_bytes());
+        sf.push(4);
+        sf.extend_from_slice(&0x60u16.to_be_bytes());
+        sf.extend_from_slice(b"docs");
+        sf.push(XFS_DIR3_FT_DIR);
+        sf.extend_from_slice(&131u32.to_be_bytes());
+
+        let mut reconstructor = XfsDirReconstructor::new(4096);
+        assert_eq!(reconstructor.add_shortform_dir(128, &sf, true).unwrap(), 1);
+        assert_eq!(reconstructor.root_inode, Some(128));
+        reconstructor.add_dir_block(&v5_block_dir(), 20);
+
+        assert_eq!(
+            reconstructor.reconstruct_path(133),
+            Some(PathBuf::from("/docs/report.pdf"))
+        );
+        assert_eq!(
+            reconstructor.reconstruct_path(140),
+            Some(PathBuf::from("/docs/secret.doc"))
+        );
+    }
+
+    #[test]
+    fn test_shortform_with_8_byte_inodes() {
+        let big = 1u64 << 40;
+        let mut sf = vec![1u8, 1];
+        sf.extend_from_slice(&128u64.to_be_bytes());
+        sf.push(5);
+        sf.extend_from_slice(&0x60u16.to_be_bytes());
+        sf.extend_from_slice(b"a.txt");
+        sf.extend_from_slice(&big.to_be_bytes());
+
+        let reconstructor = XfsDirReconstructor::new(4096);
+        let (parent, entries) = reconstructor.parse_shortform_dir(200, &sf, false).unwrap();
+        assert_eq!(parent, 128);
+        assert_eq!(entries[0].inode, big);
+        assert_eq!(entries[0].name, "a.txt");
+        assert_eq!(entries[0].parent_inode, 200);
+
+        // Truncated entry
+        assert!(reconstructor
+            .parse_shortform_dir(200, &sf[..sf.len() - 2], false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_v4_data_block_uses_dot_entry() {
+        let mut block = vec![0u8; 4096];
+        block[0..4].copy_from_slice(&XFS_DIR2_DATA_MAGIC.to_be_bytes());
+        let mut offset = XFS_DIR2_DATA_HDR_SIZE;
+        offset = put_entry(&mut block, offset, 300, ".", None);
+        offset = put_entry(&mut block, offset, 128, "..", None);
+        offset = put_entry(&mut block, offset, 301, "notes.txt", None);
+        put_free(&mut block, offset, 4096 - offset);
+
+        let reconstructor = XfsDirReconstructor::new(4096);
+        let entries = reconstructor.parse_dir_block(&block, 7).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].parent_inode, 300);
+        assert_eq!(entries[0].file_type, XFS_DIR3_FT_UNKNOWN);
     }
 
     #[test]
@@ -376,8 +738,8 @@ mod tests {
 
         // Header: magic
         block[0..4].copy_from_slice(&XFS_DIR3_BLOCK_MAGIC.to_be_bytes());
-        // Owner inode at offset 8
-        block[8..16].copy_from_slice(&123u64.to_be_bytes());
+        // Owner inode
+        block[40..48].copy_from_slice(&123u64.to_be_bytes());
 
         // Entry at offset 64: inode=456, namelen=8, name="test.txt", ftype=1
         let offset = 64;
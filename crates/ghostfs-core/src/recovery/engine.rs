@@ -1,24 +1,48 @@
 use chrono::{DateTime, Utc};
 use memmap2::Mmap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 /// Advanced file recovery algorithms and strategies
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::{
+    forensics::AuditLogger,
+    fs::{
+        common::{BadSectorReport, BlockDevice, FaultSource, RangeSet},
+        FilesystemHealth,
+    },
     recovery::{
-        confidence::{calculate_confidence_score, ActivityLevel, ConfidenceContext},
+        confidence::{
+            calculate_confidence_score, confidence_histogram, ActivityLevel, ConfidenceContext,
+            ConfidenceWeights, CONFIDENCE_BUCKETS,
+        },
         directory::{
             BtrfsDirReconstructor, DirectoryReconstructor, ExFatDirReconstructor,
             XfsDirReconstructor,
         },
-        signatures::{analyze_file_signature, extract_content_metadata, SignatureMatch},
+        signatures::{
+            carve_by_trailers, extract_content_metadata, is_random_match, refine_file_type,
+            CarvedFile, EndMarkerScanner, SignatureMatch, SignatureSet, DEFAULT_END_MARKER_BUDGET,
+            MIN_SIGNATURE_CONFIDENCE,
+        },
     },
+    session::SessionDatabase,
     BlockRange, DeletedFile, FileMetadata, FileSystemType, FileType,
 };
 
+/// Extended attribute set when a carved file's end marker was not found
+/// within the search budget, so its size is only the searched length
+pub const SIZE_ESTIMATED_ATTRIBUTE: &str = "ghostfs.size_estimated";
+
 /// Recovery engine configuration
 #[derive(Debug, Clone)]
 pub struct RecoveryConfig {
     pub min_confidence_threshold: f32,
+    /// Files claiming to be larger than this are flagged unrecoverable
     pub max_file_size: u64,
     pub scan_depth: ScanDepth,
     pub recovery_strategies: Vec<RecoveryStrategy>,
@@ -26,6 +50,26 @@ pub struct RecoveryConfig {
     pub metadata_reconstruction: bool,
     pub parallel_processing: bool,
     pub xfs_config: Option<crate::fs::xfs::XfsRecoveryConfig>,
+    /// Also search the trees of the Btrfs superblock's backup roots for
+    /// files deleted in the last few transactions
+    pub btrfs_backup_roots: bool,
+    /// Clusters the exFAT signature scan examines, overriding the scan depth's limit
+    pub exfat_max_signature_clusters: Option<u32>,
+    pub confidence_weights: ConfidenceWeights,
+    /// File system block (or exFAT cluster) size, `None` to read it from the superblock
+    pub block_size: Option<u32>,
+    /// Bytes searched for one carved file's end marker before its size is estimated
+    pub end_marker_budget: u64,
+    /// Only enumerate deleted files from the file system metadata: just the
+    /// strategies a quick scan runs, without resolving extents, carving or
+    /// confidence scoring. The files found have no `data_blocks`.
+    pub list_only: bool,
+    /// Directory for temporary files made while opening the image, such as
+    /// a decompressed EWF image; `None` for the system temporary directory
+    /// (`TMPDIR`). The files are removed before the scan starts.
+    pub temp_dir: Option<PathBuf>,
+    /// Signatures the carving scans identify files by
+    pub signatures: SignatureSet,
 }
 
 impl Default for RecoveryConfig {
@@ -38,17 +82,26 @@ impl Default for RecoveryConfig {
                 RecoveryStrategy::DirectoryTableScan,
                 RecoveryStrategy::InodeTableScan,
                 RecoveryStrategy::FileSignatureScan,
+                RecoveryStrategy::JournalAnalysis,
                 RecoveryStrategy::MetadataReconstruction,
             ],
             signature_validation: true,
             metadata_reconstruction: true,
             parallel_processing: true,
             xfs_config: None, // Use adaptive defaults
+            btrfs_backup_roots: false,
+            exfat_max_signature_clusters: None,
+            confidence_weights: ConfidenceWeights::default(),
+            block_size: None,
+            end_marker_budget: DEFAULT_END_MARKER_BUDGET,
+            list_only: false,
+            temp_dir: None,
+            signatures: SignatureSet::default(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScanDepth {
     Quick,      // Fast scan, high-confidence files only
     Standard,   // Balanced scan with moderate depth
@@ -56,7 +109,54 @@ pub enum ScanDepth {
     Exhaustive, // Maximum depth, very slow but thorough
 }
 
-#[derive(Debug, Clone)]
+/// Device bytes a `Standard` signature scan covers, also on exFAT
+const STANDARD_SIGNATURE_SCAN_LIMIT: usize = 8 * 1024 * 1024 * 1024;
+
+impl ScanDepth {
+    /// Whether `strategy` runs at this depth
+    ///
+    /// A quick scan only reads filesystem metadata and skips everything
+    /// that walks the whole device.
+    pub fn includes(&self, strategy: &RecoveryStrategy) -> bool {
+        match self {
+            ScanDepth::Quick => matches!(
+                strategy,
+                RecoveryStrategy::DirectoryTableScan | RecoveryStrategy::InodeTableScan
+            ),
+            _ => true,
+        }
+    }
+
+    /// Device bytes the signature scan covers, `None` for the whole device
+    fn signature_scan_limit(&self) -> Option<usize> {
+        match self {
+            ScanDepth::Quick => Some(0),
+            ScanDepth::Standard => Some(STANDARD_SIGNATURE_SCAN_LIMIT),
+            ScanDepth::Deep | ScanDepth::Exhaustive => None,
+        }
+    }
+
+    /// exFAT clusters of `cluster_size` bytes the signature scan examines,
+    /// `None` for all of them
+    fn exfat_signature_cluster_limit(&self, cluster_size: u32) -> Option<u32> {
+        self.signature_scan_limit().map(|bytes| {
+            let clusters = bytes as u64 / cluster_size.max(1) as u64;
+            clusters.min(u32::MAX as u64) as u32
+        })
+    }
+
+    /// Distance between signature scan windows; smaller strides overlap
+    /// windows so headers between chunk boundaries are also found
+    fn signature_stride(&self) -> usize {
+        match self {
+            ScanDepth::Quick | ScanDepth::Standard => SIGNATURE_CHUNK_SIZE,
+            ScanDepth::Deep => SIGNATURE_CHUNK_SIZE / 2,
+            ScanDepth::Exhaustive => SIGNATURE_CHUNK_SIZE / 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RecoveryStrategy {
     DirectoryTableScan,     // Scan directory structures
     InodeTableScan,         // Scan inode/cluster tables
@@ -64,20 +164,152 @@ pub enum RecoveryStrategy {
     MetadataReconstruction, // Rebuild file metadata from fragments
     JournalAnalysis,        // Analyze journal/log entries
     FragmentedFileRecovery, // Recover fragmented files
+    TrailerCarving,         // Carve files back from their trailers
+}
+
+impl RecoveryStrategy {
+    /// Get all strategies
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::DirectoryTableScan,
+            Self::InodeTableScan,
+            Self::FileSignatureScan,
+            Self::MetadataReconstruction,
+            Self::JournalAnalysis,
+            Self::FragmentedFileRecovery,
+            Self::TrailerCarving,
+        ]
+    }
+
+    /// Short name, as accepted by `from_str`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DirectoryTableScan => "directory",
+            Self::InodeTableScan => "inode",
+            Self::FileSignatureScan => "signature",
+            Self::MetadataReconstruction => "metadata",
+            Self::JournalAnalysis => "journal",
+            Self::FragmentedFileRecovery => "fragmented",
+            Self::TrailerCarving => "trailer",
+        }
+    }
+
+    /// Whether running the strategy does anything yet
+    ///
+    /// Metadata reconstruction and fragmented file recovery are accepted but
+    /// are currently no-ops.
+    pub fn is_implemented(&self) -> bool {
+        !matches!(
+            self,
+            Self::MetadataReconstruction | Self::FragmentedFileRecovery
+        )
+    }
+}
+
+impl std::str::FromStr for RecoveryStrategy {
+    type Err = String;
+
+    /// Parse a strategy's short name, case-insensitively
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .into_iter()
+            .find(|strategy| strategy.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::all().iter().map(|s| s.name()).collect();
+                format!(
+                    "unknown recovery strategy '{}' (expected one of: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
 }
 
 /// Advanced recovery engine
 pub struct RecoveryEngine {
     config: RecoveryConfig,
     fs_type: FileSystemType,
-    device_map: Mmap,
+    device_map: Arc<Mmap>,
+    /// The mapping as read by the file system modules, shared by every
+    /// strategy so a bad sector found by one is skipped by the next
+    device: Arc<BlockDevice>,
     block_size: usize,
     session_id: String,
     recovered_files: Vec<DeletedFile>,
     progress_callback: Option<Box<dyn Fn(RecoveryProgress) + Send + Sync>>,
+    checkpoint_store: Option<CheckpointStore>,
+    audit_logger: Option<AuditLogger>,
+    /// Token of the scan currently running
+    cancellation: CancellationToken,
+    /// Strategies from `config.recovery_strategies` that have finished
+    completed_strategies: usize,
+    /// Device offset up to which the signature scan has finished
+    signature_offset: usize,
+    /// Device byte ranges content carving does not look at
+    skip_regions: RangeSet,
+    /// Files already handed to a streaming consumer (or filtered out) and dropped
+    streamed_files: usize,
+    /// Statistics of the files delivered to a streaming consumer
+    delivered_statistics: RecoveryStatistics,
+    /// Trailer-carved files, found ahead of trailer carving while streaming
+    trailer_hits: Option<Vec<CarvedFile>>,
+    /// End-marker searches of the signature scan, remembering what was searched
+    end_markers: EndMarkerScanner,
 }
 
-#[derive(Debug, Clone)]
+/// Receives each file of a streaming scan once it is final
+type FileSink<'a> = &'a mut dyn FnMut(&DeletedFile);
+
+/// Shared flag for stopping a running scan from another thread
+///
+/// The engine checks it between strategies and between batches of the
+/// signature scan, so cancellation takes effect within one batch.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the scan using this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Where checkpoints of this scan are persisted
+struct CheckpointStore {
+    db: SessionDatabase,
+    device_path: PathBuf,
+}
+
+/// Persisted progress of an interrupted scan
+///
+/// Captures everything needed to continue with the first unfinished
+/// strategy: the files found so far, the filesystem analysis, and how far
+/// the signature scan got.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    pub session_id: String,
+    /// Stage the scan was in when the checkpoint was taken
+    pub stage: RecoveryStage,
+    /// Number of recovery strategies that have finished
+    pub completed_strategies: usize,
+    /// Device offset at which the signature scan continues
+    pub signature_offset: u64,
+    /// Size of the scanned device, to refuse resuming against a different one
+    pub device_size: u64,
+    pub recovered_files: Vec<DeletedFile>,
+    pub saved_at: DateTime<Utc>,
+    context: FileSystemContext,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecoveryProgress {
     pub stage: RecoveryStage,
     pub progress_percent: f32,
@@ -87,13 +319,68 @@ pub struct RecoveryProgress {
     pub current_operation: String,
 }
 
+/// Samples kept for the rolling throughput average
+const THROUGHPUT_WINDOW: usize = 8;
+
+/// Estimates the time left in a scan from its recent throughput
+///
+/// Only the last few samples count, so the estimate follows changes in read
+/// speed and ignores bytes skipped when resuming from a checkpoint. Callers
+/// pass the time of each sample, which keeps the estimator testable.
 #[derive(Debug, Clone)]
+pub struct ThroughputEstimator {
+    total_bytes: u64,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl ThroughputEstimator {
+    pub fn new(total_bytes: u64) -> Self {
+        Self {
+            total_bytes,
+            samples: VecDeque::with_capacity(THROUGHPUT_WINDOW),
+        }
+    }
+
+    /// Record that `bytes_processed` bytes were done at `now`
+    pub fn record(&mut self, now: Instant, bytes_processed: u64) {
+        if self.samples.len() == THROUGHPUT_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, bytes_processed));
+    }
+
+    /// Bytes per second over the sample window
+    pub fn throughput(&self) -> Option<f64> {
+        let (first_time, first_bytes) = *self.samples.front()?;
+        let (last_time, last_bytes) = *self.samples.back()?;
+        let elapsed = last_time.duration_since(first_time).as_secs_f64();
+        if elapsed <= 0.0 || last_bytes <= first_bytes {
+            return None;
+        }
+        Some((last_bytes - first_bytes) as f64 / elapsed)
+    }
+
+    /// Time left at the current throughput, once there are two samples
+    pub fn estimate_remaining(&self) -> Option<Duration> {
+        let (_, processed) = *self.samples.back()?;
+        let remaining = self.total_bytes.saturating_sub(processed);
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        Some(Duration::from_secs_f64(
+            remaining as f64 / self.throughput()?,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RecoveryStage {
     Initialization,
     FileSystemAnalysis,
     DirectoryScanning,
     InodeScanning,
     SignatureScanning,
+    JournalAnalysis,
     MetadataReconstruction,
     ConfidenceCalculation,
     FinalValidation,
@@ -103,22 +390,53 @@ pub enum RecoveryStage {
 impl RecoveryEngine {
     pub fn new(
         fs_type: FileSystemType,
-        device_map: Mmap,
+        device_map: impl Into<Arc<Mmap>>,
         block_size: usize,
         session_id: String,
         config: RecoveryConfig,
     ) -> Self {
+        let device_map = device_map.into();
         Self {
+            end_markers: EndMarkerScanner::new(config.end_marker_budget)
+                .with_signatures(config.signatures.clone()),
             config,
             fs_type,
+            device: Arc::new(BlockDevice::from_mmap(Arc::clone(&device_map))),
             device_map,
             block_size,
             session_id,
             recovered_files: Vec::new(),
             progress_callback: None,
+            checkpoint_store: None,
+            audit_logger: None,
+            cancellation: CancellationToken::new(),
+            completed_strategies: 0,
+            signature_offset: 0,
+            skip_regions: RangeSet::default(),
+            streamed_files: 0,
+            delivered_statistics: RecoveryStatistics::default(),
+            trailer_hits: None,
         }
     }
 
+    /// Leave device byte ranges out of signature scanning and trailer carving
+    ///
+    /// Used by rescans to avoid carving again what a previous scan of the
+    /// same device already recovered.
+    pub fn set_skip_regions(&mut self, regions: RangeSet) {
+        self.skip_regions = regions;
+    }
+
+    /// Let `faults` decide which reads of the device fail
+    ///
+    /// Pass the [`FileReads`](crate::fs::common::FileReads) of a block
+    /// device so media errors fail reads instead of crashing the scan.
+    pub fn set_fault_source(&mut self, faults: impl FaultSource + 'static) {
+        self.device = Arc::new(
+            BlockDevice::from_mmap(Arc::clone(&self.device_map)).with_fault_source(faults),
+        );
+    }
+
     pub fn set_progress_callback<F>(&mut self, callback: F)
     where
         F: Fn(RecoveryProgress) + Send + Sync + 'static,
@@ -126,8 +444,62 @@ impl RecoveryEngine {
         self.progress_callback = Some(Box::new(callback));
     }
 
+    /// Persist checkpoints of this scan of `device_path` to the session database
+    ///
+    /// A checkpoint is saved after filesystem analysis, after every strategy
+    /// and periodically during the signature scan. It is removed once the
+    /// scan completes.
+    pub fn enable_checkpoints(&mut self, db: SessionDatabase, device_path: &Path) {
+        self.checkpoint_store = Some(CheckpointStore {
+            db,
+            device_path: device_path.to_path_buf(),
+        });
+    }
+
+    /// Record scan start and completion in a forensic audit log
+    pub fn set_audit_logger(&mut self, logger: AuditLogger) {
+        self.audit_logger = Some(logger);
+    }
+
     /// Execute comprehensive file recovery
-    pub fn execute_recovery(&mut self) -> Result<RecoveryResult, RecoveryError> {
+    ///
+    /// If `cancellation` is triggered, the scan stops and returns
+    /// `RecoveryError::Cancelled` with the files found so far.
+    pub fn execute_recovery(
+        &mut self,
+        cancellation: &CancellationToken,
+    ) -> Result<RecoveryResult, RecoveryError> {
+        self.run_recovery(cancellation, None)
+    }
+
+    /// Execute file recovery, handing each file to `on_file` instead of collecting them
+    ///
+    /// Signature scan hits are scored and delivered after every batch, so
+    /// memory use does not grow with the number of hits; files found through
+    /// file system metadata are delivered once the scan completes. The
+    /// returned result carries the counts and statistics but no `files`.
+    ///
+    /// Checkpoints only hold the files not yet delivered, so a streaming scan
+    /// should not be resumed with [`RecoveryEngine::resume_from_checkpoint`].
+    pub fn execute_recovery_streaming(
+        &mut self,
+        cancellation: &CancellationToken,
+        mut on_file: impl FnMut(&DeletedFile),
+    ) -> Result<RecoveryResult, RecoveryError> {
+        self.run_recovery(cancellation, Some(&mut on_file))
+    }
+
+    fn run_recovery(
+        &mut self,
+        cancellation: &CancellationToken,
+        on_file: Option<FileSink>,
+    ) -> Result<RecoveryResult, RecoveryError> {
+        let started = Instant::now();
+        self.cancellation = cancellation.clone();
+        self.streamed_files = 0;
+        self.delivered_statistics = RecoveryStatistics::default();
+        self.trailer_hits = None;
+
         self.emit_progress(RecoveryProgress {
             stage: RecoveryStage::Initialization,
             progress_percent: 0.0,
@@ -137,71 +509,228 @@ impl RecoveryEngine {
             current_operation: "Initializing recovery engine...".to_string(),
         });
 
+        if let Some(logger) = &self.audit_logger {
+            logger.scan_started(&self.fs_type.to_string(), self.device_map.len() as u64)?;
+        }
+
         // Phase 1: File system analysis
+        self.completed_strategies = 0;
+        self.signature_offset = 0;
         let fs_context = self.analyze_filesystem()?;
+        self.save_checkpoint(RecoveryStage::FileSystemAnalysis, &fs_context);
+
+        self.complete_recovery(fs_context, started, on_file)
+    }
 
+    /// Continue an interrupted scan from its last checkpoint
+    ///
+    /// Strategies that had finished are skipped and the signature scan
+    /// continues from the saved offset. The engine must have been created
+    /// for the same device and with the same strategies as the original scan.
+    pub fn resume_from_checkpoint(
+        &mut self,
+        checkpoint: ScanCheckpoint,
+        cancellation: &CancellationToken,
+    ) -> Result<RecoveryResult, RecoveryError> {
+        let started = Instant::now();
+        self.cancellation = cancellation.clone();
+
+        if checkpoint.device_size != self.device_map.len() as u64 {
+            return Err(RecoveryError::InvalidFileSystem(format!(
+                "Checkpoint was taken on a {} byte device, this one has {} bytes",
+                checkpoint.device_size,
+                self.device_map.len()
+            )));
+        }
+        if checkpoint.context.fs_type != self.fs_type {
+            return Err(RecoveryError::InvalidFileSystem(format!(
+                "Checkpoint is for a {} scan, not {}",
+                checkpoint.context.fs_type, self.fs_type
+            )));
+        }
+        let strategy_count = self.active_strategies().len();
+        if checkpoint.completed_strategies > strategy_count {
+            return Err(RecoveryError::ParseError(format!(
+                "Checkpoint has {} completed strategies, only {} are configured",
+                checkpoint.completed_strategies, strategy_count
+            )));
+        }
+
+        tracing::info!(
+            "Resuming session {} after {} completed strategies ({} files so far)",
+            checkpoint.session_id,
+            checkpoint.completed_strategies,
+            checkpoint.recovered_files.len()
+        );
+
+        self.session_id = checkpoint.session_id;
+        self.recovered_files = checkpoint.recovered_files;
+        self.completed_strategies = checkpoint.completed_strategies;
+        self.signature_offset = checkpoint.signature_offset as usize;
+
+        self.complete_recovery(checkpoint.context, started, None)
+    }
+
+    /// Configured strategies that run at the configured scan depth
+    ///
+    /// A list-only scan runs those of a quick scan, whatever the depth.
+    fn active_strategies(&self) -> Vec<RecoveryStrategy> {
+        self.config
+            .recovery_strategies
+            .iter()
+            .filter(|strategy| self.config.scan_depth.includes(strategy))
+            .filter(|strategy| !self.config.list_only || ScanDepth::Quick.includes(strategy))
+            .copied()
+            .collect()
+    }
+
+    /// Whether the file system modules may carve files from their content
+    ///
+    /// Their own signature scans only run as part of the signature scan
+    /// strategy, so skipping it skips all carving.
+    fn signature_scan_enabled(&self) -> bool {
+        self.active_strategies()
+            .contains(&RecoveryStrategy::FileSignatureScan)
+    }
+
+    /// Device offset at which the signature scan stops
+    fn signature_scan_end(&self) -> usize {
+        match self.config.scan_depth.signature_scan_limit() {
+            Some(limit) => limit.min(self.device_map.len()),
+            None => self.device_map.len(),
+        }
+    }
+
+    /// Run the remaining strategies, then score and validate the results
+    fn complete_recovery(
+        &mut self,
+        fs_context: FileSystemContext,
+        started: Instant,
+        mut on_file: Option<FileSink>,
+    ) -> Result<RecoveryResult, RecoveryError> {
         // Phase 2: Execute recovery strategies
-        let strategies = self.config.recovery_strategies.clone();
-        for (i, strategy) in strategies.iter().enumerate() {
+        let strategies = self.active_strategies();
+        for (i, strategy) in strategies
+            .iter()
+            .enumerate()
+            .skip(self.completed_strategies)
+        {
+            if self.cancellation.is_cancelled() {
+                break;
+            }
+
             let stage_progress = (i as f32 / strategies.len() as f32) * 80.0;
-            self.execute_strategy(strategy, stage_progress, &fs_context)?;
+            let stage = self.execute_strategy(
+                strategy,
+                stage_progress,
+                &fs_context,
+                on_file.as_mut().map(|sink| &mut **sink as FileSink),
+            )?;
+
+            // Only the signature scan stops early; it is done once it reached the end
+            let finished = !matches!(strategy, RecoveryStrategy::FileSignatureScan)
+                || self.signature_offset >= self.signature_scan_end();
+            if finished {
+                self.completed_strategies = i + 1;
+                self.signature_offset = 0;
+            }
+            self.save_checkpoint(stage, &fs_context);
         }
 
-        // Phase 3: Calculate confidence scores
-        self.emit_progress(RecoveryProgress {
-            stage: RecoveryStage::ConfidenceCalculation,
-            progress_percent: 85.0,
-            files_found: self.recovered_files.len() as u32,
-            bytes_processed: 0,
-            estimated_time_remaining: None,
-            current_operation: "Calculating confidence scores...".to_string(),
-        });
-
-        self.calculate_confidence_scores(&fs_context)?;
+        if self.cancellation.is_cancelled() {
+            tracing::info!(
+                "Recovery cancelled with {} files found so far",
+                self.recovered_files.len()
+            );
+            if !self.config.list_only {
+                self.calculate_confidence_scores(&fs_context)?;
+                self.final_validation()?;
+            }
+            if let Some(on_file) = on_file {
+                self.deliver_remaining(on_file);
+            }
+            return Err(RecoveryError::Cancelled(Box::new(
+                self.build_result(&fs_context, started),
+            )));
+        }
 
-        // Phase 4: Final validation and filtering
-        self.emit_progress(RecoveryProgress {
-            stage: RecoveryStage::FinalValidation,
-            progress_percent: 95.0,
-            files_found: self.recovered_files.len() as u32,
-            bytes_processed: 0,
-            estimated_time_remaining: None,
-            current_operation: "Performing final validation...".to_string(),
-        });
+        // A listing keeps every file found, unscored
+        if !self.config.list_only {
+            // Phase 3: Calculate confidence scores
+            self.emit_progress(RecoveryProgress {
+                stage: RecoveryStage::ConfidenceCalculation,
+                progress_percent: 85.0,
+                files_found: self.files_found(),
+                bytes_processed: 0,
+                estimated_time_remaining: None,
+                current_operation: "Calculating confidence scores...".to_string(),
+            });
+
+            self.calculate_confidence_scores(&fs_context)?;
+
+            // Phase 4: Final validation and filtering
+            self.emit_progress(RecoveryProgress {
+                stage: RecoveryStage::FinalValidation,
+                progress_percent: 95.0,
+                files_found: self.files_found(),
+                bytes_processed: 0,
+                estimated_time_remaining: None,
+                current_operation: "Performing final validation...".to_string(),
+            });
+
+            self.final_validation()?;
+        }
+        if let Some(on_file) = on_file {
+            self.deliver_remaining(on_file);
+        }
 
-        self.final_validation()?;
+        if let Some(store) = &self.checkpoint_store {
+            if let Err(e) = store.db.delete_checkpoint(&self.session_id) {
+                tracing::warn!("Failed to remove scan checkpoint: {}", e);
+            }
+        }
 
         // Complete
         self.emit_progress(RecoveryProgress {
             stage: RecoveryStage::Complete,
             progress_percent: 100.0,
-            files_found: self.recovered_files.len() as u32,
+            files_found: self.files_found(),
             bytes_processed: 0,
             estimated_time_remaining: None,
             current_operation: "Recovery complete".to_string(),
         });
 
+        let result = self.build_result(&fs_context, started);
         tracing::info!(
             "🎯 Final recovery stats: {} total files, {} pass confidence threshold",
-            self.recovered_files.len(),
-            self.recovered_files
-                .iter()
-                .filter(|f| f.confidence_score >= self.config.min_confidence_threshold)
-                .count()
+            result.total_files_found,
+            result.recoverable_files
         );
 
-        Ok(RecoveryResult {
+        if let Some(logger) = &self.audit_logger {
+            logger.scan_completed(result.total_files_found, result.scan_duration_ms)?;
+        }
+
+        Ok(result)
+    }
+
+    fn build_result(&self, fs_context: &FileSystemContext, started: Instant) -> RecoveryResult {
+        let delivered = self.delivered_statistics.total_files as usize;
+        RecoveryResult {
             session_id: self.session_id.clone(),
-            total_files_found: self.recovered_files.len(),
-            recoverable_files: self
-                .recovered_files
-                .iter()
-                .filter(|f| f.confidence_score >= self.config.min_confidence_threshold)
-                .count(),
+            total_files_found: delivered + self.recovered_files.len(),
+            recoverable_files: delivered
+                + self
+                    .recovered_files
+                    .iter()
+                    .filter(|f| f.confidence_score >= self.config.min_confidence_threshold)
+                    .count(),
             files: self.recovered_files.clone(),
             filesystem_health: fs_context.filesystem_health,
             recovery_statistics: self.generate_statistics(),
-        })
+            scan_duration_ms: started.elapsed().as_millis() as u64,
+            read_errors: self.device.bad_sector_report(),
+        }
     }
 
     fn analyze_filesystem(&mut self) -> Result<FileSystemContext, RecoveryError> {
@@ -225,110 +754,139 @@ impl RecoveryEngine {
         tracing::info!("RecoveryEngine: Starting XFS filesystem analysis (using xfs module)");
 
         // Instantiate the XFS recovery engine and scan for deleted files
-        match self.create_block_device() {
-            Ok(device) => {
-                let xfs_engine = if let Some(ref xfs_config) = self.config.xfs_config {
-                    // Use custom XFS config
-                    tracing::info!("Using custom XFS recovery configuration");
-                    crate::fs::xfs::XfsRecoveryEngine::new_with_config(device, xfs_config.clone())
-                } else {
-                    // Use adaptive defaults
-                    crate::fs::xfs::XfsRecoveryEngine::new(device)
-                };
-
-                match xfs_engine {
-                    Ok(engine) => {
-                        match engine.scan_deleted_files() {
-                            Ok(mut files) => {
-                                tracing::info!("🔄 XFS engine returned {} files", files.len());
-                                // Merge scanned files into recovered_files
-                                self.recovered_files.append(&mut files);
-                                tracing::info!(
-                                    "🔄 Total recovered files after XFS merge: {}",
-                                    self.recovered_files.len()
-                                );
-                            }
-                            Err(e) => tracing::warn!("XFS scan_deleted_files failed: {:?}", e),
-                        }
+        let device = self.block_device();
+        let mut xfs_config = match self.config.xfs_config {
+            Some(ref xfs_config) => {
+                tracing::info!("Using custom XFS recovery configuration");
+                xfs_config.clone()
+            }
+            // Adaptive defaults
+            None => crate::fs::xfs::XfsRecoveryConfig::default(),
+        };
+        // An exhaustive scan also looks for inodes outside the inode tables
+        xfs_config.brute_force_inodes |= self.config.scan_depth == ScanDepth::Exhaustive;
+        xfs_config.list_only |= self.config.list_only;
+        xfs_config.signature_scan &= self.signature_scan_enabled();
+        xfs_config.signatures = self.config.signatures.clone();
+        let xfs_engine = crate::fs::xfs::XfsRecoveryEngine::new_with_config(device, xfs_config)
+            .map(|engine| engine.with_cancellation(self.cancellation.clone()));
+
+        let mut used_fraction = None;
+        let mut health = FilesystemHealth::unreadable(FileSystemType::Xfs);
+        match xfs_engine {
+            Ok(engine) => {
+                used_fraction = engine.used_fraction();
+                if let Some(sb) = engine.superblock() {
+                    health = FilesystemHealth::from_superblock(
+                        FileSystemType::Xfs,
+                        sb.data_blocks * sb.block_size as u64,
+                        used_fraction,
+                        sb.in_progress == 0,
+                    );
+                }
+                match engine.scan_deleted_files() {
+                    Ok(mut files) => {
+                        tracing::info!("🔄 XFS engine returned {} files", files.len());
+                        // Merge scanned files into recovered_files
+                        self.recovered_files.append(&mut files);
+                        tracing::info!(
+                            "🔄 Total recovered files after XFS merge: {}",
+                            self.recovered_files.len()
+                        );
                     }
-                    Err(e) => tracing::warn!("Failed to create XFS recovery engine: {:?}", e),
+                    Err(e) => tracing::warn!("XFS scan_deleted_files failed: {:?}", e),
                 }
             }
-            Err(e) => tracing::warn!("Failed to create block device for XFS engine: {}", e),
+            Err(e) => tracing::warn!("Failed to create XFS recovery engine: {:?}", e),
         }
 
-        // Return a generic FileSystemContext — real values should be derived from the XFS superblock
         Ok(FileSystemContext {
             fs_type: FileSystemType::Xfs,
-            filesystem_health: 0.8,
-            block_size: 4096,
+            filesystem_health: health.with_files(&self.recovered_files),
+            block_size: self.filesystem_block_size() as usize,
             total_blocks: 0,
             free_blocks: 0,
             inode_count: 0,
             allocation_groups: None,
             journal_location: None,
             last_mount_time: None,
-            activity_level: crate::recovery::ActivityLevel::Medium,
+            activity_level: estimate_activity(used_fraction, false),
         })
     }
 
-    /// Create a temporary BlockDevice by writing the in-memory mmap to a temp file
-    fn create_block_device(&self) -> Result<crate::fs::common::BlockDevice, RecoveryError> {
-        use std::io::Write;
-
-        let tmp_dir = std::env::temp_dir();
-        let tmp_path = tmp_dir.join(format!("ghostfs_recovery_{}.img", self.session_id));
-
-        // Write the memory-mapped data to a temporary file
-        let mut file = std::fs::File::create(&tmp_path)?;
-        file.write_all(&self.device_map[..])?;
-        file.sync_all()?;
+    /// Block or cluster size of the file system, from the config or its superblock
+    fn filesystem_block_size(&self) -> u32 {
+        self.config
+            .block_size
+            .or_else(|| crate::fs::detect_block_size(&self.block_device(), self.fs_type))
+            .unwrap_or(crate::fs::DEFAULT_BLOCK_SIZE)
+    }
 
-        // Open as BlockDevice
-        let bd = crate::fs::common::BlockDevice::open(&tmp_path).map_err(|e| {
-            RecoveryError::IoError(std::io::Error::other(format!(
-                "BlockDevice open failed: {}",
-                e
-            )))
-        })?;
+    /// Bytes per block of the data blocks of carved files
+    ///
+    /// Carved files use the same units as those found through the file
+    /// system, so recovery can read them alike.
+    fn data_block_unit(&self) -> u64 {
+        crate::block_offset_multiplier(self.fs_type, self.block_size as u32)
+    }
 
-        Ok(bd)
+    /// The mapped device as a BlockDevice for the filesystem modules (no copy)
+    fn block_device(&self) -> Arc<BlockDevice> {
+        Arc::clone(&self.device)
     }
 
     fn analyze_btrfs_filesystem(&mut self) -> Result<FileSystemContext, RecoveryError> {
         tracing::info!("RecoveryEngine: Starting Btrfs filesystem analysis");
 
         // Try to use the Btrfs recovery engine
-        match self.create_block_device() {
-            Ok(device) => {
-                // Use the Btrfs module to scan for deleted files
-                match crate::fs::btrfs::scan_for_deleted_files(&device) {
-                    Ok(mut files) => {
-                        tracing::info!("Btrfs engine returned {} files", files.len());
-                        self.recovered_files.append(&mut files);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Btrfs scan failed: {:?}", e);
-                    }
-                }
+        let device = self.block_device();
+        let superblock = device
+            .read_at(crate::fs::btrfs::BTRFS_SUPER_INFO_OFFSET, 4096)
+            .and_then(crate::fs::btrfs::BtrfsSuperblock::parse)
+            .inspect_err(|e| tracing::warn!("Unreadable Btrfs superblock: {}", e))
+            .ok();
+        // Use the Btrfs module to scan for deleted files
+        let options = crate::fs::btrfs::BtrfsScanOptions {
+            backup_roots: self.config.btrfs_backup_roots,
+            list_only: self.config.list_only,
+            signature_scan: self.signature_scan_enabled(),
+            signatures: self.config.signatures.clone(),
+        };
+        match crate::fs::btrfs::scan_for_deleted_files(&device, options) {
+            Ok(mut files) => {
+                tracing::info!("Btrfs engine returned {} files", files.len());
+                self.recovered_files.append(&mut files);
             }
             Err(e) => {
-                tracing::warn!("Failed to create block device for Btrfs: {:?}", e);
+                tracing::warn!("Btrfs scan failed: {:?}", e);
             }
         }
 
+        let health = match &superblock {
+            Some(sb) => FilesystemHealth::from_superblock(
+                FileSystemType::Btrfs,
+                sb.total_bytes,
+                sb.used_fraction(),
+                !sb.has_pending_log(),
+            ),
+            None => FilesystemHealth::unreadable(FileSystemType::Btrfs),
+        };
+
         // Return filesystem context
         Ok(FileSystemContext {
             fs_type: FileSystemType::Btrfs,
-            filesystem_health: 0.85,
-            block_size: 4096,
-            total_blocks: self.device_map.len() as u64 / 4096,
+            filesystem_health: health.with_files(&self.recovered_files),
+            block_size: self.filesystem_block_size() as usize,
+            total_blocks: self.device_map.len() as u64 / self.filesystem_block_size() as u64,
             free_blocks: 0,
             inode_count: 0,
             allocation_groups: None,
             journal_location: None,
             last_mount_time: None,
-            activity_level: ActivityLevel::Low,
+            activity_level: estimate_activity(
+                superblock.as_ref().and_then(|sb| sb.used_fraction()),
+                superblock.as_ref().is_some_and(|sb| sb.has_pending_log()),
+            ),
         })
     }
 
@@ -336,36 +894,75 @@ impl RecoveryEngine {
         tracing::info!("RecoveryEngine: Starting exFAT filesystem analysis");
 
         // Try to use the exFAT recovery engine
-        match self.create_block_device() {
-            Ok(device) => {
-                // Use the exFAT module to scan for deleted files
-                match crate::fs::exfat::scan_for_deleted_files(&device) {
-                    Ok(mut files) => {
-                        tracing::info!("exFAT engine returned {} files", files.len());
-                        self.recovered_files.append(&mut files);
-                    }
-                    Err(e) => {
-                        tracing::warn!("exFAT scan failed: {:?}", e);
-                    }
-                }
+        let device = self.block_device();
+        let boot_sector = device
+            .read_sector(0)
+            .and_then(crate::fs::exfat::ExFatBootSector::parse)
+            .inspect_err(|e| tracing::warn!("Unreadable exFAT boot sector: {}", e))
+            .ok();
+        let cluster_size = boot_sector
+            .as_ref()
+            .map_or(self.block_size as u32, |boot| boot.bytes_per_cluster());
+        let options = crate::fs::exfat::recovery::ExFatScanOptions {
+            max_signature_clusters: if self.signature_scan_enabled() {
+                self.config.exfat_max_signature_clusters.or_else(|| {
+                    self.config
+                        .scan_depth
+                        .exfat_signature_cluster_limit(cluster_size)
+                })
+            } else {
+                Some(0)
+            },
+            progress: Some(Box::new(|done, total| {
+                self.emit_progress(RecoveryProgress {
+                    stage: RecoveryStage::FileSystemAnalysis,
+                    progress_percent: 0.0,
+                    files_found: self.files_found(),
+                    bytes_processed: 0,
+                    estimated_time_remaining: None,
+                    current_operation: format!("Scanning exFAT clusters ({}/{})...", done, total),
+                })
+            })),
+            list_only: self.config.list_only,
+            signatures: self.config.signatures.clone(),
+        };
+
+        // Use the exFAT module to scan for deleted files
+        match crate::fs::exfat::scan_for_deleted_files_with_options(&device, options) {
+            Ok(mut files) => {
+                tracing::info!("exFAT engine returned {} files", files.len());
+                self.recovered_files.append(&mut files);
             }
             Err(e) => {
-                tracing::warn!("Failed to create block device for exFAT: {:?}", e);
+                tracing::warn!("exFAT scan failed: {:?}", e);
             }
         }
 
+        let health = match &boot_sector {
+            Some(boot) => FilesystemHealth::from_superblock(
+                FileSystemType::ExFat,
+                boot.cluster_count as u64 * boot.bytes_per_cluster() as u64,
+                boot.used_fraction(),
+                !boot.is_dirty(),
+            ),
+            None => FilesystemHealth::unreadable(FileSystemType::ExFat),
+        };
+
         // Return filesystem context
         Ok(FileSystemContext {
             fs_type: FileSystemType::ExFat,
-            filesystem_health: 0.75, // exFAT has less integrity checking
-            block_size: 4096,
-            total_blocks: self.device_map.len() as u64 / 4096,
+            filesystem_health: health.with_files(&self.recovered_files),
+            block_size: self.filesystem_block_size() as usize,
+            total_blocks: self.device_map.len() as u64 / self.filesystem_block_size() as u64,
             free_blocks: 0,
             inode_count: 0, // exFAT doesn't use inodes
             allocation_groups: None,
             journal_location: None,
             last_mount_time: None,
-            activity_level: ActivityLevel::Medium,
+            activity_level: estimate_activity(
+                boot_sector.as_ref().and_then(|boot| boot.used_fraction()),
+                boot_sector.as_ref().is_some_and(|boot| boot.is_dirty()),
+            ),
         })
     }
 
@@ -374,57 +971,91 @@ impl RecoveryEngine {
         strategy: &RecoveryStrategy,
         base_progress: f32,
         context: &FileSystemContext,
-    ) -> Result<(), RecoveryError> {
+        on_file: Option<FileSink>,
+    ) -> Result<RecoveryStage, RecoveryError> {
+        let stage = match strategy {
+            RecoveryStrategy::DirectoryTableScan => RecoveryStage::DirectoryScanning,
+            RecoveryStrategy::InodeTableScan => RecoveryStage::InodeScanning,
+            RecoveryStrategy::FileSignatureScan => RecoveryStage::SignatureScanning,
+            RecoveryStrategy::MetadataReconstruction => RecoveryStage::MetadataReconstruction,
+            RecoveryStrategy::JournalAnalysis => RecoveryStage::JournalAnalysis,
+            RecoveryStrategy::FragmentedFileRecovery => RecoveryStage::MetadataReconstruction,
+            RecoveryStrategy::TrailerCarving => RecoveryStage::SignatureScanning,
+        };
+
         match strategy {
             RecoveryStrategy::DirectoryTableScan => {
                 self.emit_progress(RecoveryProgress {
                     stage: RecoveryStage::DirectoryScanning,
                     progress_percent: base_progress,
-                    files_found: self.recovered_files.len() as u32,
+                    files_found: self.files_found(),
                     bytes_processed: 0,
                     estimated_time_remaining: None,
                     current_operation: "Scanning directory tables...".to_string(),
                 });
-                self.scan_directory_tables(context)
+                self.scan_directory_tables(context)?;
             }
             RecoveryStrategy::InodeTableScan => {
                 self.emit_progress(RecoveryProgress {
                     stage: RecoveryStage::InodeScanning,
                     progress_percent: base_progress,
-                    files_found: self.recovered_files.len() as u32,
+                    files_found: self.files_found(),
                     bytes_processed: 0,
                     estimated_time_remaining: None,
                     current_operation: "Scanning inode tables...".to_string(),
                 });
-                self.scan_inode_tables(context)
+                self.scan_inode_tables(context)?;
             }
             RecoveryStrategy::FileSignatureScan => {
                 self.emit_progress(RecoveryProgress {
                     stage: RecoveryStage::SignatureScanning,
                     progress_percent: base_progress,
-                    files_found: self.recovered_files.len() as u32,
+                    files_found: self.files_found(),
                     bytes_processed: 0,
                     estimated_time_remaining: None,
                     current_operation: "Scanning for file signatures...".to_string(),
                 });
-                self.scan_file_signatures(context)
+                self.scan_file_signatures(context, base_progress, on_file)?;
             }
             RecoveryStrategy::MetadataReconstruction => {
                 self.emit_progress(RecoveryProgress {
                     stage: RecoveryStage::MetadataReconstruction,
                     progress_percent: base_progress,
-                    files_found: self.recovered_files.len() as u32,
+                    files_found: self.files_found(),
                     bytes_processed: 0,
                     estimated_time_remaining: None,
                     current_operation: "Reconstructing metadata...".to_string(),
                 });
-                self.reconstruct_metadata(context)
+                self.reconstruct_metadata(context)?;
+            }
+            RecoveryStrategy::JournalAnalysis => {
+                self.emit_progress(RecoveryProgress {
+                    stage: RecoveryStage::JournalAnalysis,
+                    progress_percent: base_progress,
+                    files_found: self.files_found(),
+                    bytes_processed: 0,
+                    estimated_time_remaining: None,
+                    current_operation: "Analyzing journal entries...".to_string(),
+                });
+                self.analyze_journal(context)?;
+            }
+            RecoveryStrategy::TrailerCarving => {
+                self.emit_progress(RecoveryProgress {
+                    stage: RecoveryStage::SignatureScanning,
+                    progress_percent: base_progress,
+                    files_found: self.files_found(),
+                    bytes_processed: 0,
+                    estimated_time_remaining: None,
+                    current_operation: "Carving files from trailers...".to_string(),
+                });
+                self.carve_trailers();
             }
             _ => {
                 // TODO: Implement other strategies
-                Ok(())
             }
         }
+
+        Ok(stage)
     }
 
     fn scan_directory_tables(&mut self, context: &FileSystemContext) -> Result<(), RecoveryError> {
@@ -443,32 +1074,172 @@ impl RecoveryEngine {
         }
     }
 
-    fn scan_file_signatures(&mut self, _context: &FileSystemContext) -> Result<(), RecoveryError> {
-        // Scan entire device for file signatures
-        let chunk_size = 1024 * 1024; // 1MB chunks
-        let mut offset = 0;
+    fn scan_file_signatures(
+        &mut self,
+        context: &FileSystemContext,
+        base_progress: f32,
+        mut on_file: Option<FileSink>,
+    ) -> Result<(), RecoveryError> {
+        // Scan the device (or as much as the scan depth allows) for file
+        // signatures, in batches so the scan can be cancelled and checkpointed
+        // between them
+        let chunk_size = SIGNATURE_CHUNK_SIZE;
+        let stride = self.config.scan_depth.signature_stride();
+        let scan_end = self.signature_scan_end();
+        let stage_share = 80.0 / self.active_strategies().len().max(1) as f32;
+        let mut estimator = ThroughputEstimator::new(scan_end as u64);
+        estimator.record(Instant::now(), self.signature_offset as u64);
+
+        while self.signature_offset < scan_end {
+            if self.cancellation.is_cancelled() {
+                return Ok(());
+            }
 
-        while offset < self.device_map.len() {
-            let end = std::cmp::min(offset + chunk_size, self.device_map.len());
-            let chunk = &self.device_map[offset..end];
+            let batch_start = self.signature_offset;
+            let batch_end = std::cmp::min(batch_start + SIGNATURE_BATCH_SIZE, scan_end);
+            let device = &self.device_map[..];
+            let signatures = &self.config.signatures;
+            let skip_regions = &self.skip_regions;
+            let offsets = (batch_start..batch_end)
+                .step_by(stride)
+                .filter(|&offset| !skip_regions.contains(offset as u64));
+
+            let mut hits: Vec<(usize, SignatureMatch)> = if self.config.parallel_processing {
+                offsets
+                    .collect::<Vec<_>>()
+                    .into_par_iter()
+                    .flat_map_iter(|offset| {
+                        signature_hits_at(device, offset, chunk_size, signatures)
+                    })
+                    .collect()
+            } else {
+                offsets
+                    .flat_map(|offset| signature_hits_at(device, offset, chunk_size, signatures))
+                    .collect()
+            };
+
+            // Stable sort keeps per-chunk confidence ordering, so output is identical
+            // regardless of how the chunks were scheduled
+            hits.sort_by_key(|(offset, _)| *offset);
+            hits.dedup_by(|a, b| a.0 == b.0 && a.1.signature.signature == b.1.signature.signature);
+
+            let batch_first = self.recovered_files.len();
+            for (offset, signature_match) in hits {
+                let end = std::cmp::min(offset + chunk_size, self.device_map.len());
+                let data = &self.device_map[offset..end];
+                if is_random_match(data, &signature_match) {
+                    tracing::debug!(
+                        "Skipping {} signature at {} in random-looking data",
+                        signature_match.signature.mime_type,
+                        offset
+                    );
+                    continue;
+                }
+                let deleted_file =
+                    self.create_file_from_signature(offset, &signature_match, data)?;
+                self.recovered_files.push(deleted_file);
+            }
+            if let Some(on_file) = on_file.as_mut() {
+                self.deliver_batch(batch_first, context, on_file);
+            }
 
-            // Analyze chunk for file signatures
-            let signature_result = analyze_file_signature(chunk, 1024);
+            self.signature_offset = batch_end;
+            estimator.record(Instant::now(), batch_end as u64);
+            self.emit_progress(RecoveryProgress {
+                stage: RecoveryStage::SignatureScanning,
+                progress_percent: base_progress + stage_share * batch_end as f32 / scan_end as f32,
+                files_found: self.files_found(),
+                bytes_processed: batch_end as u64,
+                estimated_time_remaining: estimator.estimate_remaining(),
+                current_operation: "Scanning for file signatures...".to_string(),
+            });
+
+            let crossed_interval = batch_end / SIGNATURE_CHECKPOINT_INTERVAL
+                > batch_start / SIGNATURE_CHECKPOINT_INTERVAL;
+            if crossed_interval && batch_end < scan_end {
+                self.save_checkpoint(RecoveryStage::SignatureScanning, context);
+            }
+        }
 
-            for signature_match in signature_result.matches {
-                if signature_match.confidence > 0.7 {
-                    let deleted_file =
-                        self.create_file_from_signature(offset, &signature_match, chunk)?;
+        Ok(())
+    }
+
+    fn analyze_journal(&mut self, context: &FileSystemContext) -> Result<(), RecoveryError> {
+        match context.fs_type {
+            FileSystemType::Xfs => self.analyze_xfs_journal(),
+            // Btrfs has no journal and exFAT has no log of directory changes
+            FileSystemType::Btrfs | FileSystemType::ExFat => Ok(()),
+        }
+    }
+
+    fn analyze_xfs_journal(&mut self) -> Result<(), RecoveryError> {
+        tracing::info!("📓 Scanning XFS log for recently unlinked inodes");
+
+        let logged = match crate::fs::xfs::log::scan_journal(&self.block_device()) {
+            Ok(logged) => logged,
+            Err(e) => {
+                tracing::warn!("XFS log analysis failed: {}", e);
+                return Ok(());
+            }
+        };
+
+        for entry in logged {
+            let existing = self
+                .recovered_files
+                .iter_mut()
+                .find(|f| f.inode_or_cluster == entry.inode);
+
+            match existing {
+                Some(file) => {
+                    // The logged ctime is the unlink itself, unlike the on-disk ctime heuristic
+                    file.deletion_time = entry.deletion_time();
+                    file.metadata.modified_time = file.metadata.modified_time.or(entry.mtime);
+                    file.metadata.accessed_time = file.metadata.accessed_time.or(entry.atime);
+                    if file.data_blocks.is_empty() {
+                        file.data_blocks = journal_block_ranges(&entry);
+                    }
+                }
+                None => {
+                    let deleted_file = self.create_file_from_journal(&entry);
                     self.recovered_files.push(deleted_file);
                 }
             }
-
-            offset += chunk_size;
         }
 
         Ok(())
     }
 
+    fn create_file_from_journal(&self, entry: &crate::fs::xfs::log::XfsLoggedInode) -> DeletedFile {
+        let data_blocks = journal_block_ranges(entry);
+
+        DeletedFile {
+            id: self.next_file_id(),
+            inode_or_cluster: entry.inode,
+            original_path: None,
+            size: entry.size,
+            deletion_time: entry.deletion_time(),
+            confidence_score: 0.0, // Will be calculated later
+            file_type: FileType::from_mode(entry.mode),
+            is_recoverable: !data_blocks.is_empty(),
+            data_blocks,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: Some(entry.mode as u32 & 0o777),
+                owner_uid: Some(entry.uid),
+                owner_gid: Some(entry.gid),
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: entry.mtime,
+                accessed_time: entry.atime,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        }
+    }
+
     fn reconstruct_metadata(&mut self, _context: &FileSystemContext) -> Result<(), RecoveryError> {
         // Enhance metadata for recovered files
         if self.config.metadata_reconstruction {
@@ -482,32 +1253,98 @@ impl RecoveryEngine {
         &mut self,
         context: &FileSystemContext,
     ) -> Result<(), RecoveryError> {
-        let confidence_context = ConfidenceContext {
+        let confidence_context = self.confidence_context(context);
+        for file in &mut self.recovered_files {
+            score_file(file, &confidence_context);
+        }
+
+        Ok(())
+    }
+
+    fn confidence_context(&self, context: &FileSystemContext) -> ConfidenceContext {
+        ConfidenceContext {
             fs_type: context.fs_type,
             scan_time: Utc::now(),
-            filesystem_integrity: context.filesystem_health,
-            total_files_found: self.recovered_files.len() as u32,
-            device_activity_level: context.activity_level.clone(),
+            filesystem_integrity: context.filesystem_health.integrity,
+            total_files_found: self.files_found(),
+            device_activity_level: context.activity_level,
+            weights: self.config.confidence_weights,
+            block_size: context.block_size as u64,
+        }
+    }
+
+    /// Files found so far, including those already delivered to a streaming consumer
+    fn files_found(&self) -> u32 {
+        (self.streamed_files + self.recovered_files.len()) as u32
+    }
+
+    /// ID for the next file found
+    fn next_file_id(&self) -> u64 {
+        (self.streamed_files + self.recovered_files.len()) as u64 + 1
+    }
+
+    /// Score, validate and deliver the signature hits from `first` on, then drop them
+    ///
+    /// Hits that trailer carving will also find take the carved size now,
+    /// as they can no longer be corrected once delivered.
+    fn deliver_batch(&mut self, first: usize, context: &FileSystemContext, on_file: FileSink) {
+        if self
+            .active_strategies()
+            .contains(&RecoveryStrategy::TrailerCarving)
+            && self.trailer_hits.is_none()
+        {
+            self.trailer_hits = Some(carve_by_trailers(&self.device_map));
+        }
+
+        let confidence_context = self.confidence_context(context);
+        let batch: Vec<DeletedFile> = self.recovered_files.drain(first..).collect();
+        self.streamed_files += batch.len();
+        for mut file in batch {
+            self.claim_trailer_hit(&mut file);
+            score_file(&mut file, &confidence_context);
+            self.flag_if_oversized(&mut file);
+            if file.confidence_score >= self.config.min_confidence_threshold {
+                self.deliver(file, on_file);
+            }
+        }
+    }
+
+    /// Take the size of the trailer-carved file starting where `file` does
+    fn claim_trailer_hit(&mut self, file: &mut DeletedFile) {
+        let unit = self.data_block_unit();
+        let Some(hits) = &mut self.trailer_hits else {
+            return;
         };
+        let start_block = file.data_blocks.first().map(|range| range.start_block);
+        let position = hits.iter().position(|carved| {
+            Some(carved.offset / unit) == start_block
+                && file.metadata.mime_type.as_deref() == Some(carved.mime_type.as_str())
+        });
+        if let Some(position) = position {
+            let carved = hits.remove(position);
+            file.size = carved.size;
+            file.data_blocks = carved_block_ranges(&carved, unit);
+        }
+    }
 
-        for file in &mut self.recovered_files {
-            let original_confidence = file.confidence_score;
-            let calculated_confidence = calculate_confidence_score(file, &confidence_context);
-            // Take the maximum of original and calculated confidence to preserve high-quality filesystem-specific scores
-            file.confidence_score = original_confidence.max(calculated_confidence);
-            tracing::info!(
-                "🎯 File {} confidence: {} -> {} (calculated: {})",
-                file.id,
-                original_confidence,
-                file.confidence_score,
-                calculated_confidence
-            );
+    /// Deliver the validated files still held, highest confidence first
+    fn deliver_remaining(&mut self, on_file: FileSink) {
+        let files = std::mem::take(&mut self.recovered_files);
+        self.streamed_files += files.len();
+        for file in files {
+            self.deliver(file, on_file);
         }
+    }
 
-        Ok(())
+    fn deliver(&mut self, file: DeletedFile, on_file: FileSink) {
+        self.delivered_statistics
+            .record(&file, self.config.max_file_size);
+        on_file(&file);
     }
 
     fn final_validation(&mut self) -> Result<(), RecoveryError> {
+        self.flag_oversized_files();
+
         tracing::info!(
             "🔍 Final validation: {} files before filtering (threshold: {})",
             self.recovered_files.len(),
@@ -539,6 +1376,64 @@ impl RecoveryEngine {
         Ok(())
     }
 
+    /// Mark files larger than `max_file_size` as unrecoverable
+    ///
+    /// A corrupted inode or extent can claim terabytes, which recovery would
+    /// otherwise try to read and write out in full.
+    fn flag_oversized_files(&mut self) {
+        let mut files = std::mem::take(&mut self.recovered_files);
+        for file in &mut files {
+            self.flag_if_oversized(file);
+        }
+        self.recovered_files = files;
+    }
+
+    fn flag_if_oversized(&self, file: &mut DeletedFile) {
+        let limit = self.config.max_file_size;
+        if file.size <= limit {
+            return;
+        }
+        tracing::warn!(
+            "File {} claims {} bytes, over the {} byte limit; not recovering it",
+            file.id,
+            file.size,
+            limit
+        );
+        file.is_recoverable = false;
+        file.skip_reason = Some(format!(
+            "size {} exceeds max_file_size {}",
+            file.size, limit
+        ));
+    }
+
+    /// Persist the current progress, if checkpoints are enabled
+    ///
+    /// Failing to save is logged but does not abort the scan.
+    fn save_checkpoint(&self, stage: RecoveryStage, context: &FileSystemContext) {
+        let Some(store) = &self.checkpoint_store else {
+            return;
+        };
+
+        let checkpoint = ScanCheckpoint {
+            session_id: self.session_id.clone(),
+            stage,
+            completed_strategies: self.completed_strategies,
+            signature_offset: self.signature_offset as u64,
+            device_size: self.device_map.len() as u64,
+            recovered_files: self.recovered_files.clone(),
+            saved_at: Utc::now(),
+            context: context.clone(),
+        };
+        match store.db.save_checkpoint(&store.device_path, &checkpoint) {
+            Ok(()) => tracing::debug!(
+                "Saved checkpoint at {:?} ({} files)",
+                checkpoint.stage,
+                checkpoint.recovered_files.len()
+            ),
+            Err(e) => tracing::warn!("Failed to save scan checkpoint: {}", e),
+        }
+    }
+
     fn emit_progress(&self, progress: RecoveryProgress) {
         if let Some(ref callback) = self.progress_callback {
             callback(progress);
@@ -548,37 +1443,28 @@ impl RecoveryEngine {
     fn scan_xfs_directories(&mut self) -> Result<(), RecoveryError> {
         tracing::info!("🔍 Scanning XFS directories for file path reconstruction");
 
-        match self.create_block_device() {
-            Ok(device) => {
-                let mut reconstructor = XfsDirReconstructor::new(self.block_size as u32);
-
-                match reconstructor.scan_directories(&device) {
-                    Ok(entry_count) => {
-                        tracing::info!("✅ Found {} XFS directory entries", entry_count);
-
-                        // Enhance recovered files with reconstructed paths
-                        for file in &mut self.recovered_files {
-                            if file.original_path.is_none() {
-                                if let Some(path) =
-                                    reconstructor.reconstruct_path(file.inode_or_cluster)
-                                {
-                                    tracing::debug!(
-                                        "📂 Reconstructed path for inode {}: {}",
-                                        file.inode_or_cluster,
-                                        path.display()
-                                    );
-                                    file.original_path = Some(path);
-                                }
-                            }
+        let device = self.block_device();
+        let mut reconstructor = XfsDirReconstructor::new(self.block_size as u32);
+
+        match reconstructor.scan_directories(&device) {
+            Ok(entry_count) => {
+                tracing::info!("✅ Found {} XFS directory entries", entry_count);
+
+                // Enhance recovered files with reconstructed paths
+                for file in &mut self.recovered_files {
+                    if file.original_path.is_none() {
+                        if let Some(path) = reconstructor.reconstruct_path(file.inode_or_cluster) {
+                            tracing::debug!(
+                                "📂 Reconstructed path for inode {}: {}",
+                                file.inode_or_cluster,
+                                path.display()
+                            );
+                            file.original_path = Some(path);
                         }
                     }
-                    Err(e) => tracing::warn!("XFS directory scan failed: {:?}", e),
                 }
             }
-            Err(e) => tracing::warn!(
-                "Failed to create block device for XFS directory scan: {}",
-                e
-            ),
+            Err(e) => tracing::warn!("XFS directory scan failed: {:?}", e),
         }
 
         Ok(())
@@ -614,44 +1500,35 @@ impl RecoveryEngine {
     fn scan_exfat_directories(&mut self) -> Result<(), RecoveryError> {
         tracing::info!("🔍 Scanning exFAT directories for file path reconstruction");
 
-        match self.create_block_device() {
-            Ok(device) => {
-                // exFAT parameters: typically 4KB clusters, root at cluster 5, heap at 128KB
-                // These should ideally come from the VBR/boot sector
-                let cluster_size = 4096u32;
-                let root_cluster = 5u64;
-                let cluster_heap_offset = 131072u64; // 128KB typical offset
-
-                let mut reconstructor =
-                    ExFatDirReconstructor::new(cluster_size, root_cluster, cluster_heap_offset);
-
-                match reconstructor.scan_directories(&device) {
-                    Ok(entry_count) => {
-                        tracing::info!("✅ Found {} exFAT directory entries", entry_count);
-
-                        // Enhance recovered files with reconstructed paths
-                        for file in &mut self.recovered_files {
-                            if file.original_path.is_none() {
-                                if let Some(path) =
-                                    reconstructor.reconstruct_path(file.inode_or_cluster)
-                                {
-                                    tracing::debug!(
-                                        "📂 Reconstructed path for cluster {}: {}",
-                                        file.inode_or_cluster,
-                                        path.display()
-                                    );
-                                    file.original_path = Some(path);
-                                }
-                            }
+        let device = self.block_device();
+        // exFAT parameters: typically 4KB clusters, root at cluster 5, heap at 128KB
+        // These should ideally come from the VBR/boot sector
+        let cluster_size = 4096u32;
+        let root_cluster = 5u64;
+        let cluster_heap_offset = 131072u64; // 128KB typical offset
+
+        let mut reconstructor =
+            ExFatDirReconstructor::new(cluster_size, root_cluster, cluster_heap_offset);
+
+        match reconstructor.scan_directories(&device) {
+            Ok(entry_count) => {
+                tracing::info!("✅ Found {} exFAT directory entries", entry_count);
+
+                // Enhance recovered files with reconstructed paths
+                for file in &mut self.recovered_files {
+                    if file.original_path.is_none() {
+                        if let Some(path) = reconstructor.reconstruct_path(file.inode_or_cluster) {
+                            tracing::debug!(
+                                "📂 Reconstructed path for cluster {}: {}",
+                                file.inode_or_cluster,
+                                path.display()
+                            );
+                            file.original_path = Some(path);
                         }
                     }
-                    Err(e) => tracing::warn!("exFAT directory scan failed: {:?}", e),
                 }
             }
-            Err(e) => tracing::warn!(
-                "Failed to create block device for exFAT directory scan: {}",
-                e
-            ),
+            Err(e) => tracing::warn!("exFAT directory scan failed: {:?}", e),
         }
 
         Ok(())
@@ -674,41 +1551,120 @@ impl RecoveryEngine {
         signature_match: &SignatureMatch,
         data: &[u8],
     ) -> Result<DeletedFile, RecoveryError> {
-        let _metadata = if self.config.signature_validation {
+        let content_metadata = if self.config.signature_validation {
             extract_content_metadata(data, signature_match)
         } else {
             Default::default()
         };
+        let (mime_type, file_extension) = refine_file_type(data, signature_match);
+
+        // Without an end marker, assume the file runs to the end of the chunk
+        let end = self
+            .end_markers
+            .find_end(&self.device_map[offset..], offset as u64, &mime_type);
+        let size = end.map_or(data.len() as u64, |end| end.size);
+        let mut attributes = content_metadata.to_attributes();
+        if end.is_some_and(|end| end.estimated) {
+            attributes.insert(SIZE_ESTIMATED_ATTRIBUTE.to_string(), "true".to_string());
+        }
+        let unit = self.data_block_unit();
+        let start_block = offset as u64 / unit;
+        let end_block = (offset as u64 + size).div_ceil(unit);
 
         Ok(DeletedFile {
-            id: self.recovered_files.len() as u64 + 1,
+            id: self.next_file_id(),
             inode_or_cluster: 0, // Unknown from signature scan
             original_path: None,
-            size: 0, // TODO: Determine from signature analysis
+            size,
             deletion_time: None,
             confidence_score: 0.0, // Will be calculated later
             file_type: FileType::RegularFile,
             data_blocks: vec![BlockRange {
-                start_block: (offset / self.block_size) as u64,
-                block_count: 1,
+                start_block,
+                block_count: (end_block - start_block).max(1),
                 is_allocated: false,
             }],
             is_recoverable: true,
             metadata: FileMetadata {
-                mime_type: Some(signature_match.signature.mime_type.clone()),
-                file_extension: signature_match.signature.extensions.first().cloned(),
+                mime_type: Some(mime_type),
+                file_extension,
                 permissions: None,
                 owner_uid: None,
                 owner_gid: None,
-                created_time: None,
+                owner_name: None,
+                group_name: None,
+                created_time: content_metadata.created_time,
                 modified_time: None,
                 accessed_time: None,
-                extended_attributes: HashMap::new(),
+                extended_attributes: attributes
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into_bytes()))
+                    .collect(),
             },
             fs_metadata: None, // Signature-based recovery has no filesystem metadata
+            skip_reason: None,
         })
     }
 
+    /// Add files located by their trailers
+    ///
+    /// A carved file starting where a header-based hit already starts only
+    /// corrects that file's size; anything else is recovered as a new file.
+    fn carve_trailers(&mut self) {
+        let carved_files = self
+            .trailer_hits
+            .take()
+            .unwrap_or_else(|| carve_by_trailers(&self.device_map));
+        for carved in carved_files {
+            if self.cancellation.is_cancelled() {
+                return;
+            }
+            if self.skip_regions.contains(carved.offset) {
+                continue;
+            }
+
+            let data_blocks = carved_block_ranges(&carved, self.data_block_unit());
+            let start_block = data_blocks[0].start_block;
+
+            let existing = self.recovered_files.iter_mut().find(|file| {
+                file.data_blocks.first().map(|range| range.start_block) == Some(start_block)
+                    && file.metadata.mime_type.as_deref() == Some(carved.mime_type.as_str())
+            });
+            if let Some(file) = existing {
+                file.size = carved.size;
+                file.data_blocks = data_blocks;
+                continue;
+            }
+
+            self.recovered_files.push(DeletedFile {
+                id: self.next_file_id(),
+                inode_or_cluster: 0,
+                original_path: None,
+                size: carved.size,
+                deletion_time: None,
+                confidence_score: 0.0, // Will be calculated later
+                file_type: FileType::RegularFile,
+                data_blocks,
+                is_recoverable: true,
+                metadata: FileMetadata {
+                    mime_type: Some(carved.mime_type),
+                    file_extension: Some(carved.extension),
+                    permissions: None,
+                    owner_uid: None,
+                    owner_gid: None,
+                    owner_name: None,
+                    group_name: None,
+                    created_time: None,
+                    modified_time: None,
+                    accessed_time: None,
+                    extended_attributes: HashMap::new(),
+                },
+                fs_metadata: None,
+                skip_reason: None,
+            });
+        }
+    }
+
     #[allow(dead_code)]
     fn enhance_file_metadata(&self, _file: &mut DeletedFile) -> Result<(), RecoveryError> {
         // TODO: Implement metadata enhancement
@@ -717,44 +1673,94 @@ impl RecoveryEngine {
     }
 
     fn generate_statistics(&self) -> RecoveryStatistics {
-        let mut stats = RecoveryStatistics::default();
-
+        let mut stats = self.delivered_statistics.clone();
         for file in &self.recovered_files {
-            stats.total_files += 1;
-            stats.total_size += file.size;
-
-            match file.confidence_score {
-                s if s >= 0.8 => stats.high_confidence_files += 1,
-                s if s >= 0.6 => stats.medium_confidence_files += 1,
-                _ => stats.low_confidence_files += 1,
-            }
-
-            // Count by file type
-            if let Some(ref mime_type) = file.metadata.mime_type {
-                if mime_type.starts_with("image/") {
-                    stats.images += 1;
-                } else if mime_type.starts_with("video/") {
-                    stats.videos += 1;
-                } else if mime_type.starts_with("audio/") {
-                    stats.audio += 1;
-                } else if mime_type.starts_with("text/") || mime_type.contains("document") {
-                    stats.documents += 1;
-                } else {
-                    stats.other += 1;
-                }
-            }
+            stats.record(file, self.config.max_file_size);
         }
-
         stats
     }
 }
 
+/// Recompute a file's confidence, keeping a higher score set by the file system module
+fn score_file(file: &mut DeletedFile, context: &ConfidenceContext) {
+    let original_confidence = file.confidence_score;
+    let calculated_confidence = calculate_confidence_score(file, context);
+    // Take the maximum of original and calculated confidence to preserve high-quality filesystem-specific scores
+    file.confidence_score = original_confidence.max(calculated_confidence);
+    tracing::info!(
+        "🎯 File {} confidence: {} -> {} (calculated: {})",
+        file.id,
+        original_confidence,
+        file.confidence_score,
+        calculated_confidence
+    );
+}
+
+/// Blocks covered by a trailer-carved file
+fn carved_block_ranges(carved: &CarvedFile, block_size: u64) -> Vec<BlockRange> {
+    let start_block = carved.offset / block_size;
+    let end_block = (carved.offset + carved.size).div_ceil(block_size);
+    vec![BlockRange {
+        start_block,
+        block_count: (end_block - start_block).max(1),
+        is_allocated: false,
+    }]
+}
+
+/// Size of the device windows examined by the signature scan
+const SIGNATURE_CHUNK_SIZE: usize = 1024 * 1024; // 1MB chunks
+
+/// Bytes of signature scanning between cancellation checks
+const SIGNATURE_BATCH_SIZE: usize = 16 * SIGNATURE_CHUNK_SIZE;
+
+/// Bytes of signature scanning between checkpoints (a multiple of the batch size)
+const SIGNATURE_CHECKPOINT_INTERVAL: usize = 16 * SIGNATURE_BATCH_SIZE;
+
+/// Signature matches worth recovering at the start of the chunk at `offset`
+fn signature_hits_at(
+    device: &[u8],
+    offset: usize,
+    chunk_size: usize,
+    signatures: &SignatureSet,
+) -> Vec<(usize, SignatureMatch)> {
+    let end = std::cmp::min(offset + chunk_size, device.len());
+    let signature_result = signatures.analyze(&device[offset..end], 1024);
+
+    signature_result
+        .matches
+        .into_iter()
+        .filter(|signature_match| signature_match.confidence >= MIN_SIGNATURE_CONFIDENCE)
+        .map(|signature_match| (offset, signature_match))
+        .collect()
+}
+
+/// Activity estimate from filesystem fullness, or Medium when it could not be read
+fn estimate_activity(used_fraction: Option<f32>, recently_mounted: bool) -> ActivityLevel {
+    match used_fraction {
+        Some(used) => ActivityLevel::from_fullness(used, recently_mounted),
+        None if recently_mounted => ActivityLevel::High,
+        None => ActivityLevel::Medium,
+    }
+}
+
+/// Block ranges for the data fork extents captured in the log
+fn journal_block_ranges(entry: &crate::fs::xfs::log::XfsLoggedInode) -> Vec<BlockRange> {
+    entry
+        .extents
+        .iter()
+        .map(|&(start_block, block_count)| BlockRange {
+            start_block,
+            block_count,
+            is_allocated: false,
+        })
+        .collect()
+}
+
 // Supporting data structures
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct FileSystemContext {
     fs_type: FileSystemType,
-    filesystem_health: f32,
-    #[allow(dead_code)]
+    filesystem_health: FilesystemHealth,
     block_size: usize,
     #[allow(dead_code)]
     total_blocks: u64,
@@ -777,11 +1783,23 @@ pub struct RecoveryResult {
     pub total_files_found: usize,
     pub recoverable_files: usize,
     pub files: Vec<DeletedFile>,
-    pub filesystem_health: f32,
+    /// Space usage, fragmentation and recovery prospects of the scanned file system
+    pub filesystem_health: FilesystemHealth,
     pub recovery_statistics: RecoveryStatistics,
+    /// Wall-clock time spent in `execute_recovery`, in milliseconds
+    pub scan_duration_ms: u64,
+    /// Sectors of the device that could not be read during the scan
+    pub read_errors: BadSectorReport,
 }
 
-#[derive(Debug, Default)]
+impl RecoveryResult {
+    /// Number of found files in each 0.1-wide confidence range, lowest first
+    pub fn confidence_histogram(&self) -> [usize; CONFIDENCE_BUCKETS] {
+        confidence_histogram(&self.files)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RecoveryStatistics {
     pub total_files: u32,
     pub total_size: u64,
@@ -793,6 +1811,52 @@ pub struct RecoveryStatistics {
     pub audio: u32,
     pub documents: u32,
     pub other: u32,
+    /// Files over `RecoveryConfig::max_file_size`, flagged unrecoverable
+    pub oversized_files: u32,
+}
+
+impl RecoveryStatistics {
+    /// Statistics of `files`, e.g. the results of a saved session
+    pub fn from_files<'a>(
+        files: impl IntoIterator<Item = &'a DeletedFile>,
+        max_file_size: u64,
+    ) -> Self {
+        let mut stats = Self::default();
+        for file in files {
+            stats.record(file, max_file_size);
+        }
+        stats
+    }
+
+    /// Count one more file
+    fn record(&mut self, file: &DeletedFile, max_file_size: u64) {
+        self.total_files += 1;
+        self.total_size += file.size;
+        if file.size > max_file_size {
+            self.oversized_files += 1;
+        }
+
+        match file.confidence_score {
+            s if s >= 0.8 => self.high_confidence_files += 1,
+            s if s >= 0.6 => self.medium_confidence_files += 1,
+            _ => self.low_confidence_files += 1,
+        }
+
+        // Count by file type
+        if let Some(ref mime_type) = file.metadata.mime_type {
+            if mime_type.starts_with("image/") {
+                self.images += 1;
+            } else if mime_type.starts_with("video/") {
+                self.videos += 1;
+            } else if mime_type.starts_with("audio/") {
+                self.audio += 1;
+            } else if mime_type.starts_with("text/") || mime_type.contains("document") {
+                self.documents += 1;
+            } else {
+                self.other += 1;
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -802,6 +1866,25 @@ pub enum RecoveryError {
     NotImplemented(String),
     InvalidFileSystem(String),
     InsufficientSpace(String),
+    /// The scan was cancelled; carries the files found up to that point
+    Cancelled(Box<RecoveryResult>),
+    /// On-disk structures are damaged; `offset` is the device byte where it was detected
+    Corrupted {
+        offset: u64,
+        detail: String,
+    },
+    /// The image uses a format or feature this build cannot read
+    UnsupportedFeature(String),
+}
+
+impl RecoveryError {
+    /// Damaged on-disk data at device byte `offset`
+    pub fn corrupted(offset: u64, detail: impl Into<String>) -> Self {
+        RecoveryError::Corrupted {
+            offset,
+            detail: detail.into(),
+        }
+    }
 }
 
 impl std::fmt::Display for RecoveryError {
@@ -812,6 +1895,17 @@ impl std::fmt::Display for RecoveryError {
             RecoveryError::NotImplemented(e) => write!(f, "Not implemented: {}", e),
             RecoveryError::InvalidFileSystem(e) => write!(f, "Invalid file system: {}", e),
             RecoveryError::InsufficientSpace(e) => write!(f, "Insufficient space: {}", e),
+            RecoveryError::Cancelled(partial) => write!(
+                f,
+                "Recovery cancelled after finding {} files",
+                partial.total_files_found
+            ),
+            RecoveryError::Corrupted { offset, detail } => write!(
+                f,
+                "Corrupted data at byte {} (0x{:x}): {}",
+                offset, offset, detail
+            ),
+            RecoveryError::UnsupportedFeature(e) => write!(f, "Unsupported feature: {}", e),
         }
     }
 }
@@ -830,3 +1924,576 @@ impl From<std::io::Error> for RecoveryError {
         RecoveryError::IoError(error)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use memmap2::MmapMut;
+
+    fn engine_with_image(image: &[u8], parallel_processing: bool) -> RecoveryEngine {
+        let mut map = MmapMut::map_anon(image.len()).unwrap();
+        map.copy_from_slice(image);
+
+        let config = RecoveryConfig {
+            parallel_processing,
+            ..Default::default()
+        };
+        RecoveryEngine::new(
+            FileSystemType::ExFat,
+            map.make_read_only().unwrap(),
+            4096,
+            "test-session".to_string(),
+            config,
+        )
+    }
+
+    fn test_context() -> FileSystemContext {
+        FileSystemContext {
+            fs_type: FileSystemType::ExFat,
+            filesystem_health: FilesystemHealth::from_superblock(
+                FileSystemType::ExFat,
+                64 << 20,
+                None,
+                false,
+            ),
+            block_size: 4096,
+            total_blocks: 0,
+            free_blocks: 0,
+            inode_count: 0,
+            allocation_groups: None,
+            journal_location: None,
+            last_mount_time: None,
+            activity_level: ActivityLevel::Medium,
+        }
+    }
+
+    #[test]
+    fn test_parallel_signature_scan_matches_sequential() {
+        let chunk = SIGNATURE_CHUNK_SIZE;
+        let mut image = vec![0u8; chunk * 8];
+        image[0..4].copy_from_slice(b"%PDF");
+        image[chunk * 3..chunk * 3 + 8]
+            .copy_from_slice(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]);
+        image[chunk * 6..chunk * 6 + 4].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+
+        let scan = |parallel: bool| {
+            let mut engine = engine_with_image(&image, parallel);
+            engine
+                .scan_file_signatures(&test_context(), 0.0, None)
+                .unwrap();
+            engine
+                .recovered_files
+                .iter()
+                .map(|f| (f.data_blocks[0].start_block, f.metadata.mime_type.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        let sequential = scan(false);
+        let parallel = scan(true);
+
+        assert_eq!(sequential.len(), 3);
+        assert_eq!(parallel, sequential);
+        // Carved exFAT files are recorded in bytes, like cluster chains
+        assert_eq!(parallel[0], (0, Some("application/pdf".to_string())));
+        let png = (chunk * 3) as u64;
+        assert_eq!(parallel[1], (png, Some("image/png".to_string())));
+        let jpeg = (chunk * 6) as u64;
+        assert_eq!(parallel[2], (jpeg, Some("image/jpeg".to_string())));
+    }
+
+    #[test]
+    fn test_resume_after_interruption_matches_full_scan() {
+        let chunk = SIGNATURE_CHUNK_SIZE;
+        let mut image = vec![0u8; chunk * 4];
+        image[0..4].copy_from_slice(b"%PDF");
+        image[chunk * 2..chunk * 2 + 4].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("sessions.db");
+        let device_path = Path::new("/images/card.img");
+
+        let full = engine_with_image(&image, false)
+            .execute_recovery(&CancellationToken::new())
+            .unwrap();
+
+        // Interrupt the scan as soon as the directory stage is done
+        let mut engine = engine_with_image(&image, false);
+        engine.enable_checkpoints(SessionDatabase::open(&db_path).unwrap(), device_path);
+        engine.set_progress_callback(|progress| {
+            if progress.stage == RecoveryStage::InodeScanning {
+                panic!("interrupted");
+            }
+        });
+        let interrupted = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            engine.execute_recovery(&CancellationToken::new())
+        }));
+        assert!(interrupted.is_err());
+
+        let db = SessionDatabase::open(&db_path).unwrap();
+        let checkpoint = db.load_checkpoint(device_path).unwrap().unwrap();
+        assert_eq!(checkpoint.stage, RecoveryStage::DirectoryScanning);
+        assert_eq!(checkpoint.completed_strategies, 1);
+
+        let mut engine = engine_with_image(&image, false);
+        engine.enable_checkpoints(db, device_path);
+        let resumed = engine
+            .resume_from_checkpoint(checkpoint, &CancellationToken::new())
+            .unwrap();
+
+        assert_eq!(resumed.session_id, full.session_id);
+        assert_eq!(resumed.total_files_found, 2);
+        assert_eq!(
+            serde_json::to_value(&resumed.files).unwrap(),
+            serde_json::to_value(&full.files).unwrap()
+        );
+
+        // A finished scan leaves no checkpoint behind
+        let db = SessionDatabase::open(&db_path).unwrap();
+        assert!(db.load_checkpoint(device_path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cancel_during_signature_scan_returns_partial_result() {
+        let chunk = SIGNATURE_CHUNK_SIZE;
+        let mut image = vec![0u8; SIGNATURE_BATCH_SIZE * 2 + chunk];
+        image[0..4].copy_from_slice(b"%PDF");
+        let late = SIGNATURE_BATCH_SIZE + 4 * chunk;
+        image[late..late + 4].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+
+        let mut engine = engine_with_image(&image, true);
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        engine.set_progress_callback(move |progress| {
+            if progress.stage == RecoveryStage::SignatureScanning && progress.bytes_processed > 0 {
+                cancel.cancel();
+            }
+        });
+
+        let partial = match engine.execute_recovery(&token) {
+            Err(RecoveryError::Cancelled(partial)) => partial,
+            other => panic!(
+                "expected cancellation, got {:?}",
+                other.map(|r| r.files.len())
+            ),
+        };
+
+        // Only the first batch was scanned
+        assert_eq!(partial.total_files_found, 1);
+        assert_eq!(
+            partial.files[0].metadata.mime_type.as_deref(),
+            Some("application/pdf")
+        );
+        assert_eq!(engine.signature_offset, SIGNATURE_BATCH_SIZE);
+    }
+
+    #[test]
+    fn test_streaming_delivers_files_per_batch() {
+        let chunk = SIGNATURE_CHUNK_SIZE;
+        let mut image = vec![0u8; SIGNATURE_BATCH_SIZE * 3];
+        image[0..4].copy_from_slice(b"%PDF");
+        let second = SIGNATURE_BATCH_SIZE + 4 * chunk;
+        image[second..second + 4].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        let third = 2 * SIGNATURE_BATCH_SIZE + chunk;
+        image[third..third + 8].copy_from_slice(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]);
+
+        let collected = engine_with_image(&image, false)
+            .execute_recovery(&CancellationToken::new())
+            .unwrap();
+
+        let mut engine = engine_with_image(&image, false);
+        let scanned = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let progress = Arc::clone(&scanned);
+        engine.set_progress_callback(move |update| {
+            progress.store(update.bytes_processed, Ordering::SeqCst);
+        });
+        let mut delivered = Vec::new();
+        let result = engine
+            .execute_recovery_streaming(&CancellationToken::new(), |file| {
+                delivered.push((file.id, scanned.load(Ordering::SeqCst)));
+            })
+            .unwrap();
+
+        // One callback per file, each before the scan moved past the next batch
+        assert_eq!(delivered.len(), 3);
+        assert_eq!(delivered[0].1, 0);
+        assert_eq!(delivered[1].1, SIGNATURE_BATCH_SIZE as u64);
+        assert_eq!(delivered[2].1, 2 * SIGNATURE_BATCH_SIZE as u64);
+
+        // Nothing is held back once delivered
+        assert!(engine.recovered_files.is_empty());
+        assert!(result.files.is_empty());
+        assert_eq!(result.total_files_found, collected.total_files_found);
+        assert_eq!(
+            result.recovery_statistics.total_files,
+            collected.recovery_statistics.total_files
+        );
+        let mut ids: Vec<_> = delivered.iter().map(|(id, _)| *id).collect();
+        let mut collected_ids: Vec<_> = collected.files.iter().map(|f| f.id).collect();
+        ids.sort();
+        collected_ids.sort();
+        assert_eq!(ids, collected_ids);
+    }
+
+    #[test]
+    fn test_xfs_scan_creates_no_temp_files() {
+        let mut image = vec![0u8; 1024 * 1024];
+        image[0..4].copy_from_slice(b"XFSB");
+        image[4..8].copy_from_slice(&4096u32.to_be_bytes());
+
+        let mut engine = engine_with_image(&image, false);
+        engine.fs_type = FileSystemType::Xfs;
+        engine.session_id = "no-temp-files".to_string();
+        engine.execute_recovery(&CancellationToken::new()).unwrap();
+
+        assert_no_temp_files("no-temp-files");
+    }
+
+    #[test]
+    fn test_cancelled_xfs_scan_creates_no_temp_files() {
+        let mut image = vec![0u8; 1024 * 1024];
+        image[0..4].copy_from_slice(b"XFSB");
+        image[4..8].copy_from_slice(&4096u32.to_be_bytes());
+
+        let mut engine = engine_with_image(&image, false);
+        engine.fs_type = FileSystemType::Xfs;
+        engine.session_id = "cancelled-no-temp-files".to_string();
+        let token = CancellationToken::new();
+        token.cancel();
+        assert!(matches!(
+            engine.execute_recovery(&token),
+            Err(RecoveryError::Cancelled(_))
+        ));
+
+        assert_no_temp_files("cancelled-no-temp-files");
+    }
+
+    #[test]
+    fn test_strategies_share_the_device_and_its_bad_sectors() {
+        let mut image = vec![0u8; 1024 * 1024];
+        image[0..4].copy_from_slice(b"XFSB");
+
+        let mut engine = engine_with_image(&image, false);
+        engine.fs_type = FileSystemType::Xfs;
+        engine.set_fault_source(RangeSet::from_ranges([(0, 512)]));
+        let result = engine.execute_recovery(&CancellationToken::new()).unwrap();
+
+        assert_eq!(result.read_errors.bad_sectors, vec![0]);
+        // Only the first read of the superblock was retried; every later
+        // strategy found the sector already marked bad
+        let attempts = crate::fs::common::RetryPolicy::default().attempts as u64;
+        assert_eq!(result.read_errors.retries, attempts - 1);
+    }
+
+    fn assert_no_temp_files(session_id: &str) {
+        let leftovers: Vec<_> = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().contains(session_id))
+            .collect();
+        assert!(leftovers.is_empty(), "temp files left: {:?}", leftovers);
+    }
+
+    #[test]
+    fn test_scan_is_audited() {
+        use crate::forensics::{AuditEventType, AuditLog};
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = Arc::new(AuditLog::new("audit-scan", dir.path()).unwrap());
+
+        let mut engine = engine_with_image(&[0u8; 8192], false);
+        engine.set_audit_logger(AuditLogger::new(log.clone()));
+        engine.execute_recovery(&CancellationToken::new()).unwrap();
+
+        let events: Vec<_> = log
+            .get_entries()
+            .into_iter()
+            .map(|e| e.event_type)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                AuditEventType::DiskScanStart,
+                AuditEventType::DiskScanComplete
+            ]
+        );
+        log.verify_chain().unwrap();
+    }
+
+    #[test]
+    fn test_eta_decreases_at_steady_throughput() {
+        let start = Instant::now();
+        let mut estimator = ThroughputEstimator::new(100 * 1024 * 1024);
+        assert_eq!(estimator.estimate_remaining(), None);
+
+        estimator.record(start, 0);
+        assert_eq!(estimator.estimate_remaining(), None);
+
+        // A mock clock advancing one second per 10 MiB
+        let mut previous = Duration::MAX;
+        for second in 1..=10u64 {
+            estimator.record(
+                start + Duration::from_secs(second),
+                second * 10 * 1024 * 1024,
+            );
+            let eta = estimator.estimate_remaining().unwrap();
+            assert!(eta < previous, "ETA went up at {}s: {:?}", second, eta);
+            previous = eta;
+        }
+        assert_eq!(previous, Duration::ZERO);
+
+        // Halfway at 10 MiB/s leaves five seconds
+        let mut estimator = ThroughputEstimator::new(100 * 1024 * 1024);
+        estimator.record(start, 0);
+        estimator.record(start + Duration::from_secs(5), 50 * 1024 * 1024);
+        assert_eq!(estimator.estimate_remaining(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_signature_scan_reports_eta() {
+        let image = vec![0u8; SIGNATURE_BATCH_SIZE * 2];
+        let mut engine = engine_with_image(&image, false);
+        let etas = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = etas.clone();
+        engine.set_progress_callback(move |progress| {
+            if progress.bytes_processed > 0 {
+                sink.lock().unwrap().push(progress.estimated_time_remaining);
+            }
+        });
+        engine
+            .scan_file_signatures(&test_context(), 0.0, None)
+            .unwrap();
+
+        let etas = etas.lock().unwrap();
+        assert_eq!(etas.len(), 2);
+        assert_eq!(etas[1], Some(Duration::ZERO));
+    }
+
+    fn engine_with_depth(image: &[u8], scan_depth: ScanDepth) -> RecoveryEngine {
+        let mut engine = engine_with_image(image, true);
+        engine.config.scan_depth = scan_depth;
+        engine
+    }
+
+    #[test]
+    fn test_exfat_signature_cluster_limit_follows_cluster_size() {
+        let standard = ScanDepth::Standard;
+        let limit = STANDARD_SIGNATURE_SCAN_LIMIT as u64;
+        for cluster_size in [512u32, 4096, 128 * 1024] {
+            let clusters = standard
+                .exfat_signature_cluster_limit(cluster_size)
+                .unwrap();
+            assert_eq!(clusters as u64 * cluster_size as u64, limit);
+        }
+        assert_eq!(
+            ScanDepth::Quick.exfat_signature_cluster_limit(4096),
+            Some(0)
+        );
+        assert_eq!(ScanDepth::Deep.exfat_signature_cluster_limit(4096), None);
+    }
+
+    #[test]
+    fn test_quick_scan_skips_signature_carving() {
+        let chunk = SIGNATURE_CHUNK_SIZE;
+        let mut image = vec![0u8; SIGNATURE_BATCH_SIZE];
+        for offset in (0..image.len()).step_by(chunk) {
+            image[offset..offset + 4].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        }
+
+        let scan = |scan_depth| {
+            let mut engine = engine_with_depth(&image, scan_depth);
+            let signature_bytes = Arc::new(std::sync::atomic::AtomicU64::new(0));
+            let seen = Arc::clone(&signature_bytes);
+            engine.set_progress_callback(move |progress| {
+                if progress.stage == RecoveryStage::SignatureScanning {
+                    seen.fetch_max(progress.bytes_processed, Ordering::SeqCst);
+                }
+            });
+            let carves = engine.signature_scan_enabled();
+            let result = engine.execute_recovery(&CancellationToken::new()).unwrap();
+            (carves, signature_bytes.load(Ordering::SeqCst), result)
+        };
+        let (quick_carves, quick_bytes, quick) = scan(ScanDepth::Quick);
+        let (deep_carves, deep_bytes, deep) = scan(ScanDepth::Deep);
+
+        // The file system modules are told not to carve either
+        assert!(!quick_carves);
+        assert!(deep_carves);
+        assert_eq!(quick_bytes, 0);
+        assert_eq!(deep_bytes, image.len() as u64);
+        assert!(quick.files.iter().all(|f| f.inode_or_cluster != 0));
+        assert!(deep.total_files_found >= 16);
+    }
+
+    #[test]
+    fn test_deeper_scans_overlap_windows() {
+        let chunk = SIGNATURE_CHUNK_SIZE;
+        let mut image = vec![0u8; chunk * 4];
+        image[chunk / 2..chunk / 2 + 4].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        image[chunk / 4..chunk / 4 + 8]
+            .copy_from_slice(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]);
+
+        let found = |scan_depth| {
+            let mut engine = engine_with_depth(&image, scan_depth);
+            engine
+                .scan_file_signatures(&test_context(), 0.0, None)
+                .unwrap();
+            engine.recovered_files.len()
+        };
+        assert_eq!(found(ScanDepth::Standard), 0);
+        assert_eq!(found(ScanDepth::Deep), 1);
+        assert_eq!(found(ScanDepth::Exhaustive), 2);
+    }
+
+    #[test]
+    fn test_strategy_names_round_trip() {
+        for strategy in RecoveryStrategy::all() {
+            assert_eq!(strategy.name().parse(), Ok(strategy));
+        }
+        assert_eq!("Signature".parse(), Ok(RecoveryStrategy::FileSignatureScan));
+        assert!("bogus".parse::<RecoveryStrategy>().is_err());
+        assert!(!RecoveryStrategy::FragmentedFileRecovery.is_implemented());
+    }
+
+    #[test]
+    fn test_trailer_carving_recovers_damaged_zip() {
+        let archive = crate::recovery::signatures::tests::zip_archive(b"notes.txt");
+        let mut image = vec![0u8; 64 * 1024];
+        image[8192..8192 + archive.len()].copy_from_slice(&archive);
+        image[8192..8196].fill(0);
+
+        // Header-based scanning finds nothing
+        let mut engine = engine_with_image(&image, false);
+        engine
+            .scan_file_signatures(&test_context(), 0.0, None)
+            .unwrap();
+        assert!(engine.recovered_files.is_empty());
+
+        engine
+            .execute_strategy(
+                &RecoveryStrategy::TrailerCarving,
+                0.0,
+                &test_context(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(engine.recovered_files.len(), 1);
+        let file = &engine.recovered_files[0];
+        assert_eq!(file.size, archive.len() as u64);
+        assert_eq!(file.data_blocks[0].start_block, 8192);
+        assert_eq!(file.metadata.file_extension.as_deref(), Some("zip"));
+    }
+
+    #[test]
+    fn test_oversized_file_is_flagged_not_recovered() {
+        let mut image = vec![0u8; 64 * 1024];
+        image[0..4].copy_from_slice(b"%PDF");
+        let mut engine = engine_with_image(&image, false);
+        engine
+            .scan_file_signatures(&test_context(), 0.0, None)
+            .unwrap();
+        assert_eq!(engine.recovered_files.len(), 1);
+
+        // A corrupted inode claiming 5TB next to the plausible carved file
+        let mut corrupt = engine.recovered_files[0].clone();
+        corrupt.id = 2;
+        corrupt.inode_or_cluster = 1234;
+        corrupt.size = 5 << 40;
+        engine.recovered_files.push(corrupt);
+        for file in &mut engine.recovered_files {
+            file.confidence_score = 0.9;
+        }
+
+        engine.final_validation().unwrap();
+
+        let corrupt = engine.recovered_files.iter().find(|f| f.id == 2).unwrap();
+        assert!(!corrupt.is_recoverable);
+        assert!(corrupt.skip_reason.is_some());
+        let carved = engine.recovered_files.iter().find(|f| f.id == 1).unwrap();
+        assert!(carved.is_recoverable);
+        assert!(carved.skip_reason.is_none());
+        // The attributes found on the media are left alone
+        assert_eq!(
+            corrupt.metadata.extended_attributes,
+            carved.metadata.extended_attributes
+        );
+
+        let stats = engine.generate_statistics();
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.oversized_files, 1);
+    }
+
+    #[test]
+    fn test_session_statistics_serialize_by_field() {
+        let mut session = crate::session::database::tests::sample_session(3);
+        session.scan_results[2].confidence_score = 0.5;
+        session.scan_results[2].metadata.mime_type = Some("application/pdf".to_string());
+
+        let stats = RecoveryStatistics::from_files(&session.scan_results, 8192);
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "total_files": 3,
+                "total_size": 4096 * 6,
+                "high_confidence_files": 2,
+                "medium_confidence_files": 0,
+                "low_confidence_files": 1,
+                "images": 2,
+                "videos": 0,
+                "audio": 0,
+                "documents": 0,
+                "other": 1,
+                "oversized_files": 1,
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<RecoveryStatistics>(json).unwrap(),
+            stats
+        );
+    }
+
+    #[test]
+    fn test_resume_rejects_different_device() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("sessions.db");
+        let device_path = Path::new("/images/card.img");
+
+        let mut engine = engine_with_image(&[0u8; 8192], false);
+        engine.enable_checkpoints(SessionDatabase::open(&db_path).unwrap(), device_path);
+        engine.save_checkpoint(RecoveryStage::FileSystemAnalysis, &test_context());
+
+        let db = SessionDatabase::open(&db_path).unwrap();
+        let checkpoint = db.load_checkpoint(device_path).unwrap().unwrap();
+        let result = engine_with_image(&[0u8; 4096], false)
+            .resume_from_checkpoint(checkpoint, &CancellationToken::new());
+        assert!(matches!(result, Err(RecoveryError::InvalidFileSystem(_))));
+    }
+
+    #[test]
+    fn test_corrupted_error_reports_offset() {
+        let error = RecoveryError::corrupted(0x10040, "Invalid Btrfs magic");
+        assert_eq!(
+            error.to_string(),
+            "Corrupted data at byte 65600 (0x10040): Invalid Btrfs magic"
+        );
+        assert_eq!(
+            RecoveryError::UnsupportedFeature("EWF2 (Ex01) images".to_string()).to_string(),
+            "Unsupported feature: EWF2 (Ex01) images"
+        );
+
+        // Superblock parse failures carry it through anyhow
+        let device = crate::fs::common::BlockDevice::from_vec(vec![0u8; 128 * 1024]);
+        let error = device
+            .read_at(crate::fs::btrfs::BTRFS_SUPER_INFO_OFFSET, 4096)
+            .and_then(crate::fs::btrfs::BtrfsSuperblock::parse)
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<RecoveryError>(),
+            Some(RecoveryError::Corrupted {
+                offset: 0x10040,
+                ..
+            })
+        ));
+        assert!(error.to_string().contains("0x10040"));
+    }
+}
@@ -6,6 +6,16 @@ use std::collections::HashMap;
 
 use crate::recovery::fragments::{Fragment, FragmentId};
 
+/// Bytes compared at a fragment boundary when scoring content continuity
+const CONTINUITY_WINDOW: usize = 512;
+
+/// Share of the final confidence taken by content continuity when both
+/// fragments have data loaded
+const CONTINUITY_WEIGHT: f32 = 0.4;
+
+/// Run of identical 0x00/0xFF bytes at a boundary treated as padding
+const PADDING_RUN: usize = 64;
+
 /// Result of matching two fragments
 #[derive(Debug, Clone)]
 pub struct MatchScore {
@@ -23,6 +33,10 @@ pub struct MatchScore {
 
     /// Spatial proximity score (disk location)
     pub spatial_proximity: f32,
+
+    /// How plausibly the candidate's head continues the target's tail
+    /// (`None` when either fragment has no data loaded)
+    pub content_continuity: Option<f32>,
 }
 
 impl MatchScore {
@@ -43,8 +57,17 @@ impl MatchScore {
             structural_similarity: structural_sim,
             temporal_proximity: temporal_prox,
             spatial_proximity: spatial_prox,
+            content_continuity: None,
         }
     }
+
+    /// Blend a content continuity score into the overall confidence
+    pub fn with_continuity(mut self, continuity: f32) -> Self {
+        self.confidence =
+            (1.0 - CONTINUITY_WEIGHT) * self.confidence + CONTINUITY_WEIGHT * continuity;
+        self.content_continuity = Some(continuity);
+        self
+    }
 }
 
 /// Fragment matching engine
@@ -69,19 +92,73 @@ impl FragmentMatcher {
 
     /// Match two fragments
     pub fn match_fragments(&self, frag1: &Fragment, frag2: &Fragment) -> Option<MatchScore> {
+        let score = self.score_fragments(frag1, frag2);
+
+        if score.confidence >= self.min_confidence {
+            Some(score)
+        } else {
+            None
+        }
+    }
+
+    /// Match `next` as the fragment that directly follows `prev`
+    ///
+    /// Unlike [`match_fragments`](Self::match_fragments) this is directional:
+    /// when both fragments have data loaded, the score includes how well the
+    /// head of `next` continues the tail of `prev`.
+    pub fn match_continuation(&self, prev: &Fragment, next: &Fragment) -> Option<MatchScore> {
+        let mut score = self.score_fragments(prev, next);
+        if let Some(continuity) = self.content_continuity(prev, next) {
+            score = score.with_continuity(continuity);
+        }
+
+        if score.confidence >= self.min_confidence {
+            Some(score)
+        } else {
+            None
+        }
+    }
+
+    /// Score two fragments without applying the confidence threshold
+    fn score_fragments(&self, frag1: &Fragment, frag2: &Fragment) -> MatchScore {
         // Calculate individual scores
         let content_sim = self.content_similarity(frag1, frag2);
         let structural_sim = self.structural_similarity(frag1, frag2);
         let temporal_prox = self.temporal_proximity(frag1, frag2);
         let spatial_prox = self.spatial_proximity(frag1, frag2);
 
-        let score = MatchScore::calculate(content_sim, structural_sim, temporal_prox, spatial_prox);
+        MatchScore::calculate(content_sim, structural_sim, temporal_prox, spatial_prox)
+    }
 
-        if score.confidence >= self.min_confidence {
-            Some(score)
-        } else {
-            None
+    /// Score how plausibly `next` continues where `prev` ends
+    ///
+    /// Compares the byte entropy of the last bytes of `prev` with the first
+    /// bytes of `next`: compressed or encoded streams keep a steady entropy
+    /// across a fragment boundary, while a jump into zero/0xFF padding marks
+    /// the end of a file or unrelated slack space. Returns `None` when either
+    /// fragment has no data loaded.
+    pub fn content_continuity(&self, prev: &Fragment, next: &Fragment) -> Option<f32> {
+        let prev_data = prev.data.as_deref().filter(|d| !d.is_empty())?;
+        let next_data = next.data.as_deref().filter(|d| !d.is_empty())?;
+
+        let tail = &prev_data[prev_data.len().saturating_sub(CONTINUITY_WINDOW)..];
+        let head = &next_data[..next_data.len().min(CONTINUITY_WINDOW)];
+
+        let tail_padded = padding_run(tail.iter().rev()) >= PADDING_RUN.min(tail.len());
+        let head_padded = padding_run(head.iter()) >= PADDING_RUN.min(head.len());
+
+        if tail_padded && !head_padded {
+            // The previous fragment already ran into padding, so real data is
+            // unlikely to follow directly
+            return Some(0.2);
+        }
+        if head_padded && !tail_padded {
+            // Abrupt switch from file content to padding
+            return Some(0.1);
         }
+
+        let difference = (normalized_entropy(tail) - normalized_entropy(head)).abs();
+        Some(1.0 - difference.min(1.0))
     }
 
     /// Calculate content similarity between fragments
@@ -145,11 +222,7 @@ impl FragmentMatcher {
 
     /// Calculate spatial proximity (disk location)
     fn spatial_proximity(&self, frag1: &Fragment, frag2: &Fragment) -> f32 {
-        let distance = if frag1.start_offset > frag2.start_offset {
-            frag1.start_offset - frag2.start_offset
-        } else {
-            frag2.start_offset - frag1.start_offset
-        };
+        let distance = frag1.start_offset.abs_diff(frag2.start_offset);
 
         // Score based on distance
         // Adjacent blocks = 1.0, within 1MB = 0.7, within 10MB = 0.4, else = 0.0
@@ -180,7 +253,7 @@ impl FragmentMatcher {
                 continue; // Skip self
             }
 
-            if let Some(score) = self.match_fragments(target, candidate) {
+            if let Some(score) = self.match_continuation(target, candidate) {
                 matches.push((candidate.id, score));
             }
         }
@@ -255,6 +328,25 @@ pub fn calculate_entropy(data: &[u8]) -> f32 {
     entropy as f32
 }
 
+/// Entropy scaled to 0.0-1.0 by the maximum possible for the sample length
+fn normalized_entropy(data: &[u8]) -> f32 {
+    let max_entropy = (data.len().min(256) as f32).log2();
+    if max_entropy == 0.0 {
+        return 0.0;
+    }
+    calculate_entropy(data) / max_entropy
+}
+
+/// Length of the leading run of identical 0x00 or 0xFF bytes
+fn padding_run<'a>(mut bytes: impl Iterator<Item = &'a u8>) -> usize {
+    match bytes.next() {
+        Some(&first) if first == 0x00 || first == 0xFF => {
+            1 + bytes.take_while(|&&b| b == first).count()
+        }
+        _ => 0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -326,6 +418,73 @@ mod tests {
         assert_eq!(matches[0].0, 2); // frag2 should match best
     }
 
+    /// Deterministic high-entropy bytes
+    fn noise(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_content_continuity() {
+        let matcher = FragmentMatcher::new();
+
+        let mut head = Fragment::new(1, 0, 4096, 0);
+        head.set_data(noise(1, 4096));
+
+        let mut continuation = Fragment::new(2, 512 * 1024, 4096, 128);
+        continuation.set_data(noise(2, 4096));
+
+        let mut padding = Fragment::new(3, 4096, 4096, 1);
+        let mut padded = vec![0u8; 4096];
+        padded[2048..].copy_from_slice(&noise(3, 2048));
+        padding.set_data(padded);
+
+        let good = matcher.content_continuity(&head, &continuation).unwrap();
+        let bad = matcher.content_continuity(&head, &padding).unwrap();
+        assert!(good > 0.9, "noise continuing noise scored {good}");
+        assert!(bad < 0.2, "noise into padding scored {bad}");
+
+        // No data loaded, no opinion
+        let bare = Fragment::new(4, 8192, 4096, 2);
+        assert!(matcher.content_continuity(&head, &bare).is_none());
+    }
+
+    #[test]
+    fn test_continuity_overrides_proximity() {
+        let matcher = FragmentMatcher::new().with_min_confidence(0.0);
+
+        let mut head = Fragment::new(1, 0, 4096, 0);
+        head.set_data(noise(1, 4096));
+
+        // Physically adjacent, but starts with slack-space padding
+        let mut adjacent = Fragment::new(2, 4096, 4096, 1);
+        let mut padded = vec![0u8; 4096];
+        padded[2048..].copy_from_slice(&noise(3, 2048));
+        adjacent.set_data(padded);
+
+        // Further away, but its content carries straight on
+        let mut distant = Fragment::new(3, 512 * 1024, 4096, 128);
+        distant.set_data(noise(2, 4096));
+
+        let proximity_only = matcher.score_fragments(&head, &adjacent).confidence
+            > matcher.score_fragments(&head, &distant).confidence;
+        assert!(
+            proximity_only,
+            "proximity alone should prefer the adjacent fragment"
+        );
+
+        let matches = matcher.find_best_matches(&head, &[&adjacent, &distant]);
+        assert_eq!(matches[0].0, 3);
+        assert!(matches[0].1.content_continuity.unwrap() > 0.9);
+    }
+
     #[test]
     fn test_entropy_calculation() {
         // Uniform data (low entropy)
@@ -2,17 +2,35 @@
 ///
 /// This module handles detecting, storing, and organizing file fragments
 /// for later reassembly into complete files.
-
+///
+/// Cataloging a large device is expensive, so a catalog can be saved and
+/// loaded again to rerun reassembly with different settings. Saved catalogs
+/// keep everything but the fragment data, which is re-read from the device.
+///
+/// Zero-filled and repeated regions of a device yield many byte-identical
+/// fragments. A catalog can collapse those into one fragment that records
+/// every offset it was found at, so they do not swamp the matcher.
+use anyhow::{bail, Context, Result};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
 
+use crate::fs::common::BlockDevice;
 use crate::recovery::signatures::SignatureMatch;
 
 /// Unique identifier for a fragment
 pub type FragmentId = u64;
 
+/// Version of the format written by [`FragmentCatalog::save`]
+pub const FRAGMENT_CATALOG_FORMAT_VERSION: u32 = 1;
+
 /// Represents a detected file fragment
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fragment {
     /// Unique fragment identifier
     pub id: FragmentId,
@@ -41,7 +59,12 @@ pub struct Fragment {
     /// Filesystem block number
     pub block_number: u64,
     
-    /// Fragment data (optional, can be loaded on demand)
+    /// Offsets of byte-identical fragments collapsed into this one
+    #[serde(default)]
+    pub duplicate_offsets: Vec<u64>,
+    
+    /// Fragment data (optional, can be loaded on demand; never saved)
+    #[serde(skip)]
     pub data: Option<Vec<u8>>,
 }
 
@@ -63,6 +86,7 @@ impl Fragment {
             temporal_hint: None,
             confidence: 0.5, // Default medium confidence
             block_number,
+            duplicate_offsets: Vec::new(),
             data: None,
         }
     }
@@ -130,6 +154,12 @@ pub struct FragmentCatalog {
     /// Fragments organized by disk location
     by_location: BTreeMap<u64, FragmentId>,
     
+    /// Fragments with data organized by a hash of all of it
+    by_content: HashMap<u64, Vec<FragmentId>>,
+    
+    /// Whether byte-identical fragments are collapsed into one
+    deduplicate: bool,
+    
     /// Next fragment ID to assign
     next_id: FragmentId,
 }
@@ -142,38 +172,186 @@ impl FragmentCatalog {
             by_signature: HashMap::new(),
             by_size: BTreeMap::new(),
             by_location: BTreeMap::new(),
+            by_content: HashMap::new(),
+            deduplicate: false,
             next_id: 1,
         }
     }
     
+    /// Collapse fragments with the same data as one already in the catalog
+    ///
+    /// Only fragments with data are compared. Off by default, since a file
+    /// can legitimately hold identical blocks, e.g. runs of zeros.
+    pub fn with_deduplication(mut self, deduplicate: bool) -> Self {
+        self.deduplicate = deduplicate;
+        self
+    }
+    
     /// Add a fragment to the catalog
+    ///
+    /// With deduplication on, a fragment with the same data as a cataloged
+    /// one is not added; its offset is recorded on that fragment instead,
+    /// and that fragment's ID is returned.
     pub fn add_fragment(&mut self, mut fragment: Fragment) -> FragmentId {
+        if self.deduplicate {
+            if let Some(id) = self.find_duplicate(&fragment) {
+                let offset = fragment.start_offset;
+                if let Some(existing) = self.fragments.get_mut(&id) {
+                    existing.duplicate_offsets.push(offset);
+                }
+                self.by_location.insert(offset, id);
+                return id;
+            }
+        }
+        
         let id = self.next_id;
         self.next_id += 1;
         
         fragment.id = id;
+        self.insert(fragment);
+        
+        id
+    }
+    
+    /// Store a fragment under its own ID and index it
+    fn insert(&mut self, fragment: Fragment) {
+        let id = fragment.id;
         
         // Index by signature if present
         if let Some(ref sig) = fragment.signature {
             self.by_signature
                 .entry(sig.signature.mime_type.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(id);
         }
         
         // Index by size
         self.by_size
             .entry(fragment.size)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(id);
         
         // Index by location
         self.by_location.insert(fragment.start_offset, id);
+        for &offset in &fragment.duplicate_offsets {
+            self.by_location.insert(offset, id);
+        }
+        
+        // Index by content
+        if let Some(ref data) = fragment.data {
+            self.by_content
+                .entry(full_content_hash(data))
+                .or_default()
+                .push(id);
+        }
         
         // Store fragment
         self.fragments.insert(id, fragment);
+    }
+    
+    /// Cataloged fragment with exactly the data of `fragment`, if any
+    fn find_duplicate(&self, fragment: &Fragment) -> Option<FragmentId> {
+        let data = fragment.data.as_ref()?;
+        self.by_content
+            .get(&full_content_hash(data))?
+            .iter()
+            .copied()
+            .find(|id| {
+                self.fragments
+                    .get(id)
+                    .is_some_and(|other| other.data.as_ref() == Some(data))
+            })
+    }
+    
+    /// Number of fragments collapsed into others, and their total size
+    pub fn deduplication_savings(&self) -> (usize, u64) {
+        self.fragments
+            .values()
+            .fold((0, 0), |(count, bytes), fragment| {
+                let duplicates = fragment.duplicate_offsets.len();
+                (count + duplicates, bytes + duplicates as u64 * fragment.size)
+            })
+    }
+    
+    /// Save the catalog as JSON, without fragment data
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut fragments: Vec<&Fragment> = self.fragments.values().collect();
+        fragments.sort_by_key(|fragment| fragment.id);
         
-        id
+        let saved = SavedCatalog {
+            format_version: FRAGMENT_CATALOG_FORMAT_VERSION,
+            next_id: self.next_id,
+            fragments,
+        };
+        
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create fragment catalog {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, &saved)?;
+        writer.flush()?;
+        
+        tracing::info!("Saved {} fragments to {}", self.len(), path.display());
+        Ok(())
+    }
+    
+    /// Load a catalog written by [`FragmentCatalog::save`]
+    ///
+    /// Fragments keep their IDs, so reassembly results and relationships
+    /// found before saving still refer to the same fragments. Their data is
+    /// not loaded; see [`FragmentCatalog::reload_data`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open fragment catalog {}", path.display()))?;
+        let saved: LoadedCatalog = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("{} is not a valid fragment catalog", path.display()))?;
+        
+        if saved.format_version > FRAGMENT_CATALOG_FORMAT_VERSION {
+            bail!(
+                "Fragment catalog uses format version {}, this build supports up to {}",
+                saved.format_version,
+                FRAGMENT_CATALOG_FORMAT_VERSION
+            );
+        }
+        
+        let mut catalog = Self::new();
+        for fragment in saved.fragments {
+            if catalog.fragments.contains_key(&fragment.id) {
+                bail!("Fragment catalog lists fragment {} twice", fragment.id);
+            }
+            catalog.next_id = catalog.next_id.max(fragment.id + 1);
+            catalog.insert(fragment);
+        }
+        catalog.next_id = catalog.next_id.max(saved.next_id);
+        
+        Ok(catalog)
+    }
+    
+    /// Read the data of every fragment back from `device`
+    ///
+    /// Fragments that cannot be read keep no data. Returns the number of
+    /// fragments whose data was read.
+    pub fn reload_data(&mut self, device: &BlockDevice) -> usize {
+        let mut loaded = 0;
+        self.by_content.clear();
+        for fragment in self.fragments.values_mut() {
+            match device.read_at(fragment.start_offset, fragment.size as usize) {
+                Ok(data) => {
+                    self.by_content
+                        .entry(full_content_hash(data))
+                        .or_default()
+                        .push(fragment.id);
+                    fragment.data = Some(data.to_vec());
+                    loaded += 1;
+                }
+                Err(e) => {
+                    tracing::warn!("Could not re-read fragment {}: {}", fragment.id, e);
+                    fragment.data = None;
+                }
+            }
+        }
+        loaded
     }
     
     /// Get a fragment by ID
@@ -191,7 +369,7 @@ impl FragmentCatalog {
         self.by_signature
             .get(mime_type)
             .map(|ids| ids.iter().filter_map(|id| self.fragments.get(id).cloned()).collect())
-            .unwrap_or_else(Vec::new)
+            .unwrap_or_default()
     }
     
     /// Get fragments within a size range
@@ -273,6 +451,13 @@ impl FragmentCatalog {
             }
             
             self.by_location.remove(&fragment.start_offset);
+            for offset in &fragment.duplicate_offsets {
+                self.by_location.remove(offset);
+            }
+            
+            for ids in self.by_content.values_mut() {
+                ids.retain(|&x| x != id);
+            }
             
             Some(fragment)
         } else {
@@ -287,6 +472,29 @@ impl Default for FragmentCatalog {
     }
 }
 
+/// Hash of all of `data`, unlike the sampled `Fragment::content_hash`
+fn full_content_hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Catalog as written by [`FragmentCatalog::save`]
+#[derive(Serialize)]
+struct SavedCatalog<'a> {
+    format_version: u32,
+    next_id: FragmentId,
+    fragments: Vec<&'a Fragment>,
+}
+
+/// Catalog as read by [`FragmentCatalog::load`]
+#[derive(Deserialize)]
+struct LoadedCatalog {
+    format_version: u32,
+    next_id: FragmentId,
+    fragments: Vec<Fragment>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -353,4 +561,177 @@ mod tests {
         let results = catalog.fragments_by_size_range(1000, 2500);
         assert_eq!(results.len(), 2);
     }
+
+    
+    fn jpeg_match() -> SignatureMatch {
+        SignatureMatch {
+            category: "image".to_string(),
+            signature: crate::recovery::signatures::FileSignature {
+                signature: vec![0xFF, 0xD8],
+                offset: 0,
+                mime_type: "image/jpeg".to_string(),
+                extensions: vec!["jpg".to_string()],
+                description: "JPEG".to_string(),
+                trailer: Some(vec![0xFF, 0xD9]),
+            },
+            confidence: 0.9,
+        }
+    }
+    
+    /// Three JPEG fragments and one without a signature, with data
+    fn sample_catalog(device: &[u8]) -> FragmentCatalog {
+        let mut catalog = FragmentCatalog::new();
+        for (index, offset) in [0u64, 4096, 8192, 12288].into_iter().enumerate() {
+            let mut fragment = Fragment::new(0, offset, 4096, index as u64);
+            fragment.set_data(device[offset as usize..offset as usize + 4096].to_vec());
+            if index < 3 {
+                fragment.signature = Some(jpeg_match());
+            }
+            fragment.parent_file_hint = Some(42);
+            fragment.temporal_hint = Some(Utc::now());
+            catalog.add_fragment(fragment);
+        }
+        catalog
+    }
+    
+    fn sample_device() -> Vec<u8> {
+        (0..16384u32).map(|i| (i / 4096 * 7 + i % 13) as u8).collect()
+    }
+    
+    #[test]
+    fn test_deduplication_collapses_identical_fragments() {
+        let zeros = |offset: u64| {
+            let mut fragment = Fragment::new(0, offset, 4096, offset / 4096);
+            fragment.set_data(vec![0; 4096]);
+            fragment
+        };
+        let mut other = Fragment::new(0, 16384, 4096, 4);
+        // Same first kilobyte, so the same sampled content hash
+        let mut data = vec![0; 4096];
+        data[4095] = 1;
+        other.set_data(data);
+        
+        let mut catalog = FragmentCatalog::new().with_deduplication(true);
+        let id = catalog.add_fragment(zeros(0));
+        assert_eq!(catalog.add_fragment(zeros(4096)), id);
+        assert_eq!(catalog.add_fragment(zeros(12288)), id);
+        let other_id = catalog.add_fragment(other);
+        
+        assert_eq!(catalog.len(), 2);
+        assert_ne!(other_id, id);
+        let fragment = catalog.get(id).unwrap();
+        assert_eq!(fragment.start_offset, 0);
+        assert_eq!(fragment.duplicate_offsets, vec![4096, 12288]);
+        assert_eq!(catalog.fragments_near_location(12288, 0)[0].id, id);
+        assert_eq!(catalog.deduplication_savings(), (2, 8192));
+        
+        let stats = crate::recovery::ReassemblyEngine::new(catalog).get_statistics();
+        assert_eq!(stats.total_fragments, 2);
+        assert_eq!(stats.duplicate_fragments, 2);
+        assert_eq!(stats.deduplicated_bytes, 8192);
+        
+        // Off by default
+        let mut catalog = FragmentCatalog::new();
+        catalog.add_fragment(zeros(0));
+        catalog.add_fragment(zeros(4096));
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog.deduplication_savings(), (0, 0));
+    }
+    
+    #[test]
+    fn test_save_load_round_trip() {
+        let device = sample_device();
+        let catalog = sample_catalog(&device);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+        
+        catalog.save(&path).unwrap();
+        let loaded = FragmentCatalog::load(&path).unwrap();
+        
+        assert_eq!(loaded.len(), catalog.len());
+        for fragment in catalog.all_fragments() {
+            let copy = loaded.get(fragment.id).unwrap();
+            assert_eq!(copy.start_offset, fragment.start_offset);
+            assert_eq!(copy.size, fragment.size);
+            assert_eq!(copy.content_hash, fragment.content_hash);
+            assert_eq!(copy.parent_file_hint, fragment.parent_file_hint);
+            assert_eq!(copy.temporal_hint, fragment.temporal_hint);
+            assert_eq!(
+                copy.signature.as_ref().map(|s| &s.signature.mime_type),
+                fragment.signature.as_ref().map(|s| &s.signature.mime_type)
+            );
+            // Data is not saved
+            assert!(copy.data.is_none());
+        }
+        
+        // Indexes and relationships are rebuilt
+        let ids = |fragments: Vec<Fragment>| {
+            let mut ids: Vec<FragmentId> = fragments.iter().map(|f| f.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            ids(loaded.fragments_by_signature("image/jpeg")),
+            ids(catalog.fragments_by_signature("image/jpeg"))
+        );
+        for fragment in catalog.all_fragments() {
+            assert_eq!(
+                loaded.find_related_fragments(fragment.id),
+                catalog.find_related_fragments(fragment.id)
+            );
+        }
+        assert_eq!(loaded.fragments_near_location(4096, 0).len(), 1);
+        assert_eq!(loaded.fragments_by_size_range(4096, 4096).len(), 4);
+    }
+    
+    #[test]
+    fn test_loaded_catalog_continues_ids() {
+        let mut catalog = sample_catalog(&sample_device());
+        catalog.remove(4);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+        catalog.save(&path).unwrap();
+        
+        let mut loaded = FragmentCatalog::load(&path).unwrap();
+        // The removed fragment's ID is not handed out again
+        assert_eq!(loaded.add_fragment(Fragment::new(0, 20480, 512, 5)), 5);
+    }
+    
+    #[test]
+    fn test_reload_data_reads_fragments_from_device() {
+        let device = sample_device();
+        let catalog = sample_catalog(&device);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+        catalog.save(&path).unwrap();
+        
+        let mut loaded = FragmentCatalog::load(&path).unwrap();
+        let mut past_end = Fragment::new(0, 16000, 4096, 9);
+        past_end.signature = Some(jpeg_match());
+        let past_end = loaded.add_fragment(past_end);
+        
+        let block_device = BlockDevice::from_bytes(device);
+        assert_eq!(loaded.reload_data(&block_device), 4);
+        for fragment in catalog.all_fragments() {
+            assert_eq!(loaded.get(fragment.id).unwrap().data, fragment.data);
+        }
+        assert!(loaded.get(past_end).unwrap().data.is_none());
+    }
+    
+    #[test]
+    fn test_load_rejects_newer_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{"format_version":{},"next_id":1,"fragments":[]}}"#,
+                FRAGMENT_CATALOG_FORMAT_VERSION + 1
+            ),
+        )
+        .unwrap();
+        
+        let err = FragmentCatalog::load(&path).unwrap_err();
+        assert!(err.to_string().contains("format version"));
+    }
 }
@@ -1,8 +1,11 @@
 /// Recovery module containing advanced algorithms and strategies
+pub mod carving;
 pub mod confidence;
 pub mod directory;
 pub mod engine;
+pub mod ownership;
 pub mod signatures;
+pub mod thumbnail;
 
 // Fragment reassembly modules
 pub mod fragment_matcher;
@@ -11,28 +14,38 @@ pub mod partial;
 pub mod reassembly;
 pub mod reconstruction;
 
+pub use carving::{carve_device, write_carved_file, CarveConfig};
 pub use confidence::{
-    calculate_confidence_score, generate_confidence_report, ActivityLevel, ConfidenceContext,
-    ConfidenceReport, RecoveryRecommendation,
+    calculate_confidence_score, calibration_report, confidence_histogram,
+    generate_confidence_report, ActivityLevel, CalibrationBucket, CalibrationReport,
+    ConfidenceContext, ConfidenceReport, ConfidenceWeights, RecoveryRecommendation,
+    CONFIDENCE_BUCKETS,
 };
 
 pub use signatures::{
-    analyze_file_signature, extract_content_metadata, init_signature_database, ContentMetadata,
-    FileSignature, SignatureAnalysisResult, SignatureMatch,
+    analyze_file_signature, carve_by_trailers, extract_content_metadata, extract_image_date,
+    init_signature_database, load_definitions, shannon_entropy, CarvedFile, ContentMetadata,
+    EndEstimate, EndMarkerScanner, FileSignature, SignatureAnalysisResult, SignatureMatch,
+    SignatureSet, TrailerSignature, DEFAULT_END_MARKER_BUDGET, MIN_SIGNATURE_CONFIDENCE,
 };
 
+pub use thumbnail::{generate_thumbnail, MAX_THUMBNAIL_SOURCE_BYTES};
+
 pub use engine::{
-    RecoveryConfig, RecoveryEngine, RecoveryError, RecoveryProgress, RecoveryResult, RecoveryStage,
-    RecoveryStatistics, RecoveryStrategy, ScanDepth,
+    CancellationToken, RecoveryConfig, RecoveryEngine, RecoveryError, RecoveryProgress,
+    RecoveryResult, RecoveryStage, RecoveryStatistics, RecoveryStrategy, ScanCheckpoint, ScanDepth,
+    ThroughputEstimator, SIZE_ESTIMATED_ATTRIBUTE,
 };
 
 pub use directory::{
     BtrfsDirEntry, BtrfsDirReconstructor, DirectoryReconstructor, ExFatDirEntry,
-    ExFatDirReconstructor, ReconstructionStats, XfsDirEntry, XfsDirReconstructor,
+    ExFatDirReconstructor, ReconstructionStats, RecoveredTree, TreeEntry, TreeNode, XfsDirEntry,
+    XfsDirReconstructor,
 };
 
 pub use fragment_matcher::{calculate_entropy, FragmentMatcher, MatchScore};
 pub use fragments::{Fragment, FragmentCatalog, FragmentId};
+pub use ownership::resolve_ownership;
 pub use partial::{PartialRecovery, PartialRecoveryResult};
 pub use reassembly::{GapInfo, ReassemblyEngine, ReassemblyResult, ReassemblyStatistics};
 pub use reconstruction::{ExtentReconstructor, ReconstructionResult, ReconstructionStrategy};
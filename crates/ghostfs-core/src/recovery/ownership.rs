@@ -0,0 +1,166 @@
+/// Owner and group name resolution from passwd and group files
+///
+/// Names come from files supplied by the caller, typically `/etc/passwd` and
+/// `/etc/group` recovered from the image itself, never from the system
+/// GhostFS runs on, whose users have nothing to do with the evidence.
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::DeletedFile;
+
+/// Fill `owner_name` and `group_name` of `files` from a passwd and a group file
+///
+/// IDs missing from the files leave the names unset.
+pub fn resolve_ownership(files: &mut [DeletedFile], passwd: &Path, group: &Path) -> Result<()> {
+    let users = parse_passwd(&read_account_file(passwd)?);
+    let groups = parse_group(&read_account_file(group)?);
+
+    for file in files {
+        let metadata = &mut file.metadata;
+        if let Some(uid) = metadata.owner_uid {
+            metadata.owner_name = users.get(&uid).cloned();
+        }
+        if let Some(gid) = metadata.owner_gid {
+            metadata.group_name = groups.get(&gid).cloned();
+        }
+    }
+
+    Ok(())
+}
+
+/// User names by UID from the contents of a passwd file
+pub fn parse_passwd(content: &str) -> HashMap<u32, String> {
+    parse_id_names(content)
+}
+
+/// Group names by GID from the contents of a group file
+pub fn parse_group(content: &str) -> HashMap<u32, String> {
+    parse_id_names(content)
+}
+
+/// Recovered files may hold stray non-UTF-8 bytes, which are replaced
+fn read_account_file(path: &Path) -> Result<String> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Map the numeric ID in the third `:`-separated field of each line to the name in the first
+///
+/// Both files share this layout. Comments, blank and malformed lines are skipped. The first entry for an
+/// ID wins, as it does for `getpwuid`.
+fn parse_id_names(content: &str) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(':').collect();
+        let (Some(name), Some(id)) = (fields.first(), fields.get(2)) else {
+            continue;
+        };
+        let Ok(id) = id.parse::<u32>() else {
+            continue;
+        };
+        if !name.is_empty() {
+            names.entry(id).or_insert_with(|| name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileMetadata, FileType};
+    use std::io::Write;
+
+    const PASSWD: &str = "\
+root:x:0:0:root:/root:/bin/bash
+# service accounts
+daemon:x:1:1:daemon:/usr/sbin:/usr/sbin/nologin
+alice:x:1000:1000:Alice,,,:/home/alice:/bin/bash
+broken line without fields
+toor:x:0:0:duplicate root:/root:/bin/sh
+";
+
+    const GROUP: &str = "\
+root:x:0:
+adm:x:4:syslog,alice
+alice:x:1000:
+staff:x:50:alice
+";
+
+    fn owned_file(uid: Option<u32>, gid: Option<u32>) -> DeletedFile {
+        DeletedFile {
+            id: 1,
+            inode_or_cluster: 131,
+            original_path: None,
+            size: 0,
+            deletion_time: None,
+            confidence_score: 0.9,
+            file_type: FileType::RegularFile,
+            data_blocks: Vec::new(),
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: Some(0o644),
+                owner_uid: uid,
+                owner_gid: gid,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_passwd_and_group() {
+        let users = parse_passwd(PASSWD);
+        assert_eq!(users.len(), 3);
+        assert_eq!(users[&0], "root");
+        assert_eq!(users[&1000], "alice");
+
+        let groups = parse_group(GROUP);
+        assert_eq!(groups[&4], "adm");
+        assert_eq!(groups[&50], "staff");
+    }
+
+    #[test]
+    fn test_resolve_ownership_from_recovered_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let passwd = dir.path().join("passwd");
+        let group = dir.path().join("group");
+        std::fs::File::create(&passwd)
+            .unwrap()
+            .write_all(PASSWD.as_bytes())
+            .unwrap();
+        std::fs::File::create(&group)
+            .unwrap()
+            .write_all(GROUP.as_bytes())
+            .unwrap();
+
+        let mut files = vec![
+            owned_file(Some(1000), Some(50)),
+            owned_file(Some(4242), Some(4242)),
+            owned_file(None, None),
+        ];
+        resolve_ownership(&mut files, &passwd, &group).unwrap();
+
+        assert_eq!(files[0].metadata.owner_name.as_deref(), Some("alice"));
+        assert_eq!(files[0].metadata.group_name.as_deref(), Some("staff"));
+        assert_eq!(files[1].metadata.owner_name, None);
+        assert_eq!(files[1].metadata.group_name, None);
+        assert_eq!(files[2].metadata.owner_name, None);
+
+        assert!(resolve_ownership(&mut files, &dir.path().join("missing"), &group).is_err());
+    }
+}
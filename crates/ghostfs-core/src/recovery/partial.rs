@@ -1,4 +1,3 @@
-use super::fragment_matcher::FragmentMatcher;
 /// Partial file recovery - Phase 5B
 ///
 /// This module handles recovery of partially overwritten or fragmented files
@@ -43,9 +42,6 @@ pub struct GapInfo {
 
 /// Partial file recovery engine
 pub struct PartialRecovery {
-    /// Fragment matcher for finding related fragments
-    matcher: FragmentMatcher,
-
     /// Minimum completeness threshold
     min_completeness: f32,
 }
@@ -54,7 +50,6 @@ impl PartialRecovery {
     /// Create a new partial recovery engine
     pub fn new() -> Self {
         Self {
-            matcher: FragmentMatcher::new().with_min_confidence(0.4),
             min_completeness: 0.3, // Recover if at least 30% of file is available
         }
     }
@@ -39,6 +39,9 @@ pub struct GapInfo {
 
     /// Whether the gap was filled (interpolated)
     pub filled: bool,
+
+    /// Whether the gap is small enough (`<= max_gap_size`) to be filled
+    pub fillable: bool,
 }
 
 /// Fragment reassembly engine
@@ -54,6 +57,9 @@ pub struct ReassemblyEngine {
 
     /// Minimum confidence for reassembly
     min_confidence: f32,
+
+    /// Zero-fill fillable gaps so fragments keep their original offsets
+    fill_gaps: bool,
 }
 
 impl ReassemblyEngine {
@@ -64,6 +70,7 @@ impl ReassemblyEngine {
             matcher: FragmentMatcher::new(),
             max_gap_size: 64 * 1024, // 64KB default
             min_confidence: 0.6,
+            fill_gaps: false,
         }
     }
 
@@ -80,6 +87,12 @@ impl ReassemblyEngine {
         self
     }
 
+    /// Zero-fill gaps up to `max_gap_size` when writing reassembled files
+    pub fn with_fill_gaps(mut self, fill_gaps: bool) -> Self {
+        self.fill_gaps = fill_gaps;
+        self
+    }
+
     /// Reassemble fragments for a specific file type
     pub fn reassemble_by_type(&self, mime_type: &str) -> Vec<ReassemblyResult> {
         let fragments: Vec<Fragment> = self.catalog.fragments_by_signature(mime_type);
@@ -106,7 +119,7 @@ impl ReassemblyEngine {
 
     /// Reassemble all detected fragments
     pub fn reassemble_all(&self) -> Vec<ReassemblyResult> {
-        let all_fragments: Vec<_> = self.catalog.all_fragments().cloned().collect();
+        let all_fragments = self.fragments_in_cluster_order();
 
         if all_fragments.is_empty() {
             return Vec::new();
@@ -128,6 +141,16 @@ impl ReassemblyEngine {
         results
     }
 
+    /// All catalog fragments in a deterministic order for clustering.
+    ///
+    /// Clustering is greedy, so fragments carrying a file signature go first to
+    /// seed one cluster per file header, then the rest follow by disk location.
+    fn fragments_in_cluster_order(&self) -> Vec<Fragment> {
+        let mut fragments: Vec<_> = self.catalog.all_fragments().cloned().collect();
+        fragments.sort_by_key(|f| (f.signature.is_none(), f.start_offset, f.id));
+        fragments
+    }
+
     /// Reassemble a cluster of related fragments
     fn reassemble_cluster(&self, fragment_ids: &[FragmentId]) -> Option<ReassemblyResult> {
         if fragment_ids.is_empty() {
@@ -150,11 +173,16 @@ impl ReassemblyEngine {
         // Calculate gaps
         let gaps = self.detect_gaps(&ordered);
 
-        // Calculate total size
+        // Calculate total size, including any zero-filled gaps
         let total_size: u64 = ordered
             .iter()
             .map(|(_, frag_id, _)| self.catalog.get(*frag_id).map(|f| f.size).unwrap_or(0))
-            .sum();
+            .sum::<u64>()
+            + gaps
+                .iter()
+                .filter(|gap| gap.filled)
+                .map(|gap| gap.size)
+                .sum::<u64>();
 
         // Calculate overall confidence
         let confidence = self.calculate_reassembly_confidence(&ordered, &gaps);
@@ -346,8 +374,9 @@ impl ReassemblyEngine {
             let mut best_match_score = 0.0f32;
             for (_, ordered_id, _) in ordered {
                 if let Some(ordered_frag) = fragments.iter().find(|f| f.id == *ordered_id) {
-                    // Use public match_fragments API which combines temporal and spatial proximity
-                    if let Some(match_score) = self.matcher.match_fragments(frag, ordered_frag) {
+                    // Score the orphan as a continuation of the ordered fragment, so
+                    // content continuity counts alongside temporal and spatial proximity
+                    if let Some(match_score) = self.matcher.match_continuation(ordered_frag, frag) {
                         best_match_score = best_match_score.max(match_score.confidence);
                     } else {
                         // Even if below confidence threshold, consider spatial proximity
@@ -359,7 +388,7 @@ impl ReassemblyEngine {
 
             if best
                 .as_ref()
-                .map_or(true, |(_, _, score)| best_match_score > *score)
+                .is_none_or(|(_, _, score)| best_match_score > *score)
             {
                 best = Some((idx, frag.id, best_match_score));
             }
@@ -370,11 +399,7 @@ impl ReassemblyEngine {
 
     /// Calculate simple proximity score based on disk location
     fn calculate_simple_proximity(&self, frag1: &Fragment, frag2: &Fragment) -> f32 {
-        let distance = if frag1.start_offset > frag2.start_offset {
-            frag1.start_offset - frag2.start_offset
-        } else {
-            frag2.start_offset - frag1.start_offset
-        };
+        let distance = frag1.start_offset.abs_diff(frag2.start_offset);
 
         // Score based on distance - closer fragments more likely related
         const MB: u64 = 1024 * 1024;
@@ -390,31 +415,41 @@ impl ReassemblyEngine {
     }
 
     /// Detect gaps between ordered fragments
+    ///
+    /// A gap is the unread disk space between the end of one fragment and the
+    /// start of the next one in reassembly order. Its position is the offset in
+    /// the reassembled file where the missing bytes belong; with `fill_gaps`,
+    /// fillable gaps are marked filled and occupy that space in the output.
     fn detect_gaps(&self, ordered: &[(usize, FragmentId, MatchScore)]) -> Vec<GapInfo> {
         let mut gaps = Vec::new();
 
         let mut current_pos = 0u64;
+        let mut previous_end: Option<u64> = None;
 
         for (_, frag_id, _) in ordered {
             if let Some(fragment) = self.catalog.get(*frag_id) {
-                // Check for gap
-                let expected_start = current_pos;
-
-                // In reality, fragments might not have absolute positions
-                // This is a simplified gap detection
-                if fragment.size > 0 {
-                    let gap_size = 0; // Placeholder - would need more context
-
-                    if gap_size > 0 && gap_size <= self.max_gap_size {
+                // Only forward jumps on disk leave a measurable hole; a fragment
+                // located before its predecessor has no known distance
+                if let Some(end) = previous_end {
+                    let gap_size = fragment.start_offset.saturating_sub(end);
+
+                    if gap_size > 0 {
+                        let fillable = gap_size <= self.max_gap_size;
+                        let filled = fillable && self.fill_gaps;
                         gaps.push(GapInfo {
-                            position: expected_start,
+                            position: current_pos,
                             size: gap_size,
-                            filled: false,
+                            filled,
+                            fillable,
                         });
+                        if filled {
+                            current_pos += gap_size;
+                        }
                     }
                 }
 
                 current_pos += fragment.size;
+                previous_end = Some(fragment.start_offset + fragment.size);
             }
         }
 
@@ -439,7 +474,9 @@ impl ReassemblyEngine {
             / ordered.len() as f32;
 
         // Penalty for gaps
-        let gap_penalty = if gaps.is_empty() {
+        let gap_penalty = if gaps.iter().any(|gap| !gap.fillable) {
+            0.5 // Data beyond max_gap_size is missing, so the file is likely broken
+        } else if gaps.is_empty() {
             1.0
         } else {
             0.8 // 20% penalty for having gaps
@@ -460,19 +497,37 @@ impl ReassemblyEngine {
     }
 
     /// Write reassembled file to output
+    ///
+    /// Filled gaps are written as zeros at their position, and fragments whose
+    /// data is shorter than their size are zero-padded, so the output is exactly
+    /// `total_size` bytes. Without filled gaps the fragment data is concatenated.
     pub fn write_reassembled<W: Write>(
         &self,
         result: &ReassemblyResult,
         mut writer: W,
     ) -> io::Result<usize> {
         let mut total_written = 0;
+        let preserve_offsets = result.gaps.iter().any(|gap| gap.filled);
+        let mut filled_gaps = result.gaps.iter().filter(|gap| gap.filled).peekable();
 
         for fragment_id in &result.fragment_ids {
             if let Some(fragment) = self.catalog.get(*fragment_id) {
+                while let Some(gap) =
+                    filled_gaps.next_if(|gap| gap.position <= total_written as u64)
+                {
+                    total_written += write_zeros(&mut writer, gap.size)?;
+                }
+
                 if let Some(ref data) = fragment.data {
                     writer.write_all(data)?;
                     total_written += data.len();
                 }
+
+                if preserve_offsets {
+                    let written = fragment.data.as_ref().map_or(0, |d| d.len() as u64);
+                    total_written +=
+                        write_zeros(&mut writer, fragment.size.saturating_sub(written))?;
+                }
             }
         }
 
@@ -483,13 +538,16 @@ impl ReassemblyEngine {
     pub fn get_statistics(&self) -> ReassemblyStatistics {
         let total_fragments = self.catalog.len();
 
-        let fragments: Vec<_> = self.catalog.all_fragments().cloned().collect();
+        let fragments = self.fragments_in_cluster_order();
         let clusters = self.matcher.cluster_fragments(&fragments);
 
         let reassemblable = clusters.len();
+        let (duplicate_fragments, deduplicated_bytes) = self.catalog.deduplication_savings();
 
         ReassemblyStatistics {
             total_fragments,
+            duplicate_fragments,
+            deduplicated_bytes,
             reassemblable_files: reassemblable,
             average_fragments_per_file: if reassemblable > 0 {
                 total_fragments as f32 / reassemblable as f32
@@ -500,10 +558,28 @@ impl ReassemblyEngine {
     }
 }
 
+/// Write `count` zero bytes, returning how many were written
+fn write_zeros<W: Write>(writer: &mut W, count: u64) -> io::Result<usize> {
+    const ZEROS: [u8; 8192] = [0; 8192];
+
+    let mut remaining = count;
+    while remaining > 0 {
+        let chunk = std::cmp::min(remaining, ZEROS.len() as u64) as usize;
+        writer.write_all(&ZEROS[..chunk])?;
+        remaining -= chunk as u64;
+    }
+
+    Ok(count as usize)
+}
+
 /// Statistics about reassembly capabilities
 #[derive(Debug, Clone)]
 pub struct ReassemblyStatistics {
     pub total_fragments: usize,
+    /// Fragments the catalog collapsed into byte-identical ones
+    pub duplicate_fragments: usize,
+    /// Bytes those duplicates would have added
+    pub deduplicated_bytes: u64,
     pub reassemblable_files: usize,
     pub average_fragments_per_file: f32,
 }
@@ -537,6 +613,7 @@ mod tests {
                 mime_type: "image/jpeg".to_string(),
                 extensions: vec!["jpg".to_string()],
                 description: "JPEG".to_string(),
+                trailer: None,
             },
             confidence: 1.0,
         });
@@ -556,18 +633,94 @@ mod tests {
 
     #[test]
     fn test_gap_detection() {
-        let catalog = FragmentCatalog::new();
+        let mut catalog = FragmentCatalog::new();
+        let frag1 = catalog.add_fragment(Fragment::new(0, 0, 1024, 0));
+        let frag2 = catalog.add_fragment(Fragment::new(0, 8192, 1024, 2)); // Gap between
         let engine = ReassemblyEngine::new(catalog);
 
-        let frag1 = Fragment::new(1, 0, 1024, 0);
-        let frag2 = Fragment::new(2, 8192, 1024, 2); // Gap between
-
         let score = MatchScore::calculate(0.8, 0.8, 0.8, 0.8);
-        let ordered = vec![(0, frag1.id, score.clone()), (1, frag2.id, score)];
+        let ordered = vec![(0, frag1, score.clone()), (1, frag2, score)];
 
         let gaps = engine.detect_gaps(&ordered);
-        // Gap detection is simplified in current impl
-        assert!(gaps.len() <= 1);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].position, 1024);
+        assert_eq!(gaps[0].size, 8192 - 1024);
+        assert!(gaps[0].fillable);
+        assert!(!gaps[0].filled);
+    }
+
+    #[test]
+    fn test_oversized_gap_is_unfillable() {
+        let mut catalog = FragmentCatalog::new();
+        let frag1 = catalog.add_fragment(Fragment::new(0, 0, 1024, 0));
+        let frag2 = catalog.add_fragment(Fragment::new(0, 2048, 1024, 1));
+        let frag3 = catalog.add_fragment(Fragment::new(0, 1024 * 1024, 1024, 256));
+        let engine = ReassemblyEngine::new(catalog).with_max_gap_size(4096);
+
+        let score = MatchScore::calculate(0.8, 0.8, 0.8, 0.8);
+        let contiguous = vec![(0, frag1, score.clone())];
+        let small_gap = vec![(0, frag1, score.clone()), (1, frag2, score.clone())];
+        let large_gap = vec![(0, frag1, score.clone()), (2, frag3, score)];
+
+        let gaps = engine.detect_gaps(&large_gap);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].size, 1024 * 1024 - 1024);
+        assert!(!gaps[0].fillable);
+
+        let confidence = |ordered: &[(usize, FragmentId, MatchScore)]| {
+            engine.calculate_reassembly_confidence(ordered, &engine.detect_gaps(ordered))
+        };
+        assert!(confidence(&small_gap) < confidence(&contiguous));
+        assert!(confidence(&large_gap) < confidence(&small_gap));
+    }
+
+    #[test]
+    fn test_fill_gaps_preserves_offsets() {
+        let mut catalog = FragmentCatalog::new();
+        let mut head = Fragment::new(0, 0, 1024, 0);
+        head.set_data(vec![0xAA; 1024]);
+        let mut tail = Fragment::new(0, 4096, 1024, 1);
+        tail.set_data(vec![0xBB; 1024]);
+        let head = catalog.add_fragment(head);
+        let tail = catalog.add_fragment(tail);
+
+        let engine = ReassemblyEngine::new(catalog).with_fill_gaps(true);
+        let result = engine.reassemble_cluster(&[head, tail]).unwrap();
+
+        assert_eq!(result.fragment_ids, vec![head, tail]);
+        assert_eq!(result.gaps.len(), 1);
+        assert!(result.gaps[0].filled);
+        assert_eq!(result.total_size, 4096 + 1024);
+
+        let mut output = Vec::new();
+        let written = engine.write_reassembled(&result, &mut output).unwrap();
+
+        assert_eq!(written as u64, result.total_size);
+        assert_eq!(output.len() as u64, result.total_size);
+        assert!(output[..1024].iter().all(|&b| b == 0xAA));
+        assert!(output[1024..4096].iter().all(|&b| b == 0));
+        assert!(output[4096..].iter().all(|&b| b == 0xBB));
+    }
+
+    #[test]
+    fn test_gaps_not_filled_by_default() {
+        let mut catalog = FragmentCatalog::new();
+        let mut head = Fragment::new(0, 0, 1024, 0);
+        head.set_data(vec![0xAA; 1024]);
+        let mut tail = Fragment::new(0, 4096, 1024, 1);
+        tail.set_data(vec![0xBB; 1024]);
+        let head = catalog.add_fragment(head);
+        let tail = catalog.add_fragment(tail);
+
+        let engine = ReassemblyEngine::new(catalog);
+        let result = engine.reassemble_cluster(&[head, tail]).unwrap();
+
+        assert!(!result.gaps[0].filled);
+        assert_eq!(result.total_size, 2048);
+
+        let mut output = Vec::new();
+        engine.write_reassembled(&result, &mut output).unwrap();
+        assert_eq!(output.len(), 2048);
     }
 
     #[test]
@@ -663,6 +816,7 @@ mod tests {
                 mime_type: "application/pdf".to_string(),
                 extensions: vec!["pdf".to_string()],
                 description: "PDF".to_string(),
+                trailer: None,
             },
             confidence: 1.0,
         });
@@ -686,7 +840,7 @@ mod tests {
         assert!(ordered.is_some(), "Should successfully order fragments");
         let ordered = ordered.unwrap();
         assert!(
-            ordered.len() >= 1,
+            !ordered.is_empty(),
             "Should have at least the start fragment"
         );
 
@@ -696,4 +850,42 @@ mod tests {
             "First fragment should have the file signature"
         );
     }
+
+    #[test]
+    fn test_content_continuity_orders_fragments() {
+        // Deterministic high-entropy bytes standing in for compressed data
+        fn noise(seed: u64, len: usize) -> Vec<u8> {
+            let mut state = seed;
+            (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect()
+        }
+
+        let mut start = Fragment::new(1, 0, 4096, 0);
+        let mut start_data = vec![0xFF, 0xD8, 0xFF, 0xE0];
+        start_data.extend(noise(1, 4092));
+        start.set_data(start_data);
+
+        // Adjacent on disk, but its head is padding before unrelated data
+        let mut slack = Fragment::new(2, 4096, 4096, 1);
+        let mut slack_data = vec![0u8; 4096];
+        slack_data[2048..].copy_from_slice(&noise(3, 2048));
+        slack.set_data(slack_data);
+
+        // Relocated further out, but its content continues the stream
+        let mut middle = Fragment::new(3, 512 * 1024, 4096, 128);
+        middle.set_data(noise(2, 4096));
+
+        let engine = ReassemblyEngine::new(FragmentCatalog::new()).with_min_confidence(0.1);
+        let fragments = vec![start, slack, middle];
+        let ordered = engine.order_fragments(&fragments).unwrap();
+
+        let ids: Vec<_> = ordered.iter().map(|(_, id, _)| *id).collect();
+        assert_eq!(ids, vec![1, 3, 2]);
+    }
 }
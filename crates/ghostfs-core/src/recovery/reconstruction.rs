@@ -2,11 +2,9 @@
 ///
 /// This module implements intelligent extent reconstruction for files with
 /// complex or damaged extent maps, using pattern analysis and data validation.
-
 use anyhow::Result;
 
 use super::fragments::FragmentCatalog;
-use super::fragment_matcher::FragmentMatcher;
 use crate::{BlockRange, DeletedFile};
 
 /// Result of extent reconstruction
@@ -52,9 +50,6 @@ pub enum ReconstructionStrategy {
 
 /// Smart extent reconstructor
 pub struct ExtentReconstructor {
-    /// Fragment matcher for finding related data
-    matcher: FragmentMatcher,
-    
     /// Minimum confidence threshold
     min_confidence: f32,
 }
@@ -63,7 +58,6 @@ impl ExtentReconstructor {
     /// Create a new extent reconstructor
     pub fn new() -> Self {
         Self {
-            matcher: FragmentMatcher::new().with_min_confidence(0.5),
             min_confidence: 0.6,
         }
     }
@@ -285,7 +279,7 @@ impl ExtentReconstructor {
         // Convert fragments to block ranges
         for frag in &candidates {
             let start_block = frag.start_offset / block_size;
-            let block_count = (frag.size + block_size - 1) / block_size; // Round up
+            let block_count = frag.size.div_ceil(block_size); // Round up
             
             block_ranges.push(BlockRange {
                 start_block,
@@ -450,12 +444,15 @@ mod tests {
                 permissions: None,
                 owner_uid: None,
                 owner_gid: None,
+                owner_name: None,
+                group_name: None,
                 created_time: None,
                 modified_time: None,
                 accessed_time: None,
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: None,
+            skip_reason: None,
         };
         
         let strategy = reconstructor.choose_strategy(&small_file, &catalog);
@@ -1,14 +1,212 @@
 /// Advanced file signature analysis for recovery validation
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Furthest a JPEG or PNG end marker is searched for
+const MAX_IMAGE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Furthest a PDF end marker is searched for
+const MAX_PDF_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Bytes an [`EndMarkerScanner`] searches for one file's end marker by default
+pub const DEFAULT_END_MARKER_BUDGET: u64 = 16 * 1024 * 1024;
+
+/// Bytes from the start of an image searched for EXIF data
+pub const EXIF_SEARCH_LEN: usize = 64 * 1024;
+
+/// Confidence from which a signature match is taken to be a file
+pub const MIN_SIGNATURE_CONFIDENCE: f32 = 0.7;
+
+/// Bytes at the start of a region sampled for [`shannon_entropy`]
+pub const ENTROPY_SAMPLE_LEN: usize = 4096;
+
+/// Entropy (bits per byte) from which a sample is treated as random data
+///
+/// Uniformly random bytes measure about 7.95 over [`ENTROPY_SAMPLE_LEN`];
+/// file headers with tables, names or markers stay well below.
+pub const RANDOM_ENTROPY_THRESHOLD: f32 = 7.9;
+
+/// Entropy below which a sample is mostly padding or a repeated byte
+pub const LOW_ENTROPY_THRESHOLD: f32 = 1.0;
+
+/// Furthest a user-defined trailer is searched for
+const MAX_CUSTOM_SIZE: u64 = 50 * 1024 * 1024;
+
+/// End of the furthest magic a user definition may place
+///
+/// The carving scans identify files from as little as a sector.
+const MAX_CUSTOM_SIGNATURE_END: usize = 512;
+
+/// Category user-defined signatures are listed under
+const CUSTOM_CATEGORY: &str = "custom";
+
+/// The built-in signature database, built on first use
+static SIGNATURES: OnceLock<HashMap<String, Vec<FileSignature>>> = OnceLock::new();
 
 /// File signature database for validating recovered files
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileSignature {
     pub signature: Vec<u8>,
     pub offset: usize,
     pub mime_type: String,
     pub extensions: Vec<String>,
     pub description: String,
+    /// Footer that ends the file, for estimating its size
+    pub trailer: Option<Vec<u8>>,
+}
+
+/// A signature as written in a definitions file
+///
+/// Byte strings are hex, optionally separated by spaces.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureDefinition {
+    magic: String,
+    #[serde(default)]
+    offset: usize,
+    mime_type: String,
+    extensions: Vec<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    trailer: Option<String>,
+}
+
+impl SignatureDefinition {
+    fn into_signature(self) -> Result<FileSignature> {
+        let signature = parse_hex(&self.magic).context("Invalid magic")?;
+        ensure!(!signature.is_empty(), "Magic is empty");
+        ensure!(
+            self.offset + signature.len() <= MAX_CUSTOM_SIGNATURE_END,
+            "Magic at offset {} ends past byte {}",
+            self.offset,
+            MAX_CUSTOM_SIGNATURE_END
+        );
+        ensure!(!self.mime_type.is_empty(), "MIME type is empty");
+        let trailer = match &self.trailer {
+            Some(hex) => {
+                let trailer = parse_hex(hex).context("Invalid trailer")?;
+                ensure!(!trailer.is_empty(), "Trailer is empty");
+                Some(trailer)
+            }
+            None => None,
+        };
+
+        Ok(FileSignature {
+            signature,
+            offset: self.offset,
+            description: self.description.unwrap_or_else(|| self.mime_type.clone()),
+            mime_type: self.mime_type,
+            extensions: self.extensions,
+            trailer,
+        })
+    }
+}
+
+/// Bytes of a hex string such as `"47 48 53 54"` or `"47485354"`
+fn parse_hex(hex: &str) -> Result<Vec<u8>> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        bail!("Odd number of hex digits in {:?}", hex);
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).unwrap_or("");
+            u8::from_str_radix(pair, 16).with_context(|| format!("{:?} is not a hex byte", pair))
+        })
+        .collect()
+}
+
+/// Read extra signatures from a JSON file
+///
+/// The file holds an array of definitions:
+///
+/// ```json
+/// [{"magic": "47 48 53 54", "offset": 0, "mime_type": "application/x-ghost",
+///   "extensions": ["ghost"], "trailer": "454E44"}]
+/// ```
+///
+/// `offset` defaults to 0 and `description` to the MIME type; `trailer` is
+/// optional and lets carving find where the file ends.
+pub fn load_definitions(path: &Path) -> Result<Vec<FileSignature>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let definitions: Vec<SignatureDefinition> = serde_json::from_str(&text)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    definitions
+        .into_iter()
+        .enumerate()
+        .map(|(index, definition)| {
+            definition
+                .into_signature()
+                .with_context(|| format!("Signature {} in {}", index + 1, path.display()))
+        })
+        .collect()
+}
+
+/// The signatures a scan identifies files by: the built-in ones plus any
+/// loaded with [`load_definitions`]
+///
+/// Scans take one through their configuration; cloning it is cheap. The
+/// free functions such as [`detect_file_type`] use the built-in signatures
+/// alone.
+#[derive(Debug, Clone, Default)]
+pub struct SignatureSet {
+    custom: Arc<[FileSignature]>,
+}
+
+impl SignatureSet {
+    /// The built-in signatures plus `custom`
+    pub fn new(custom: Vec<FileSignature>) -> Self {
+        Self {
+            custom: custom.into(),
+        }
+    }
+
+    /// The signatures added to the built-in ones
+    pub fn custom(&self) -> &[FileSignature] {
+        &self.custom
+    }
+
+    /// Like [`analyze_file_signature`], also matching the custom signatures
+    pub fn analyze(&self, data: &[u8], max_bytes: usize) -> SignatureAnalysisResult {
+        analyze_with(data, max_bytes, &self.custom)
+    }
+
+    /// Like [`detect_file_type`], also matching the custom signatures
+    pub fn detect_file_type(&self, header: &[u8]) -> Option<(String, String)> {
+        detect_with(header, &self.custom)
+    }
+
+    /// Like [`end_marker_search_limit`], also for custom signatures with a trailer
+    pub fn end_marker_search_limit(&self, mime: &str) -> Option<u64> {
+        search_limit_with(mime, &self.custom)
+    }
+
+    /// Like [`estimate_file_size`], also ending files at custom trailers
+    pub fn estimate_file_size(&self, data: &[u8], offset: usize, mime: &str) -> Option<u64> {
+        estimate_size_with(data, offset, mime, &self.custom)
+    }
+}
+
+/// The built-in signature database
+fn signature_database() -> &'static HashMap<String, Vec<FileSignature>> {
+    SIGNATURES.get_or_init(init_signature_database)
+}
+
+/// A custom signature with a trailer for `mime`
+fn custom_trailer<'a>(mime: &str, custom: &'a [FileSignature]) -> Option<&'a [u8]> {
+    custom
+        .iter()
+        .find(|signature| signature.mime_type == mime)?
+        .trailer
+        .as_deref()
 }
 
 /// Initialize the comprehensive file signature database
@@ -23,6 +221,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "image/jpeg".to_string(),
             extensions: vec!["jpg".to_string(), "jpeg".to_string()],
             description: "JPEG Image".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A],
@@ -30,6 +229,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "image/png".to_string(),
             extensions: vec!["png".to_string()],
             description: "PNG Image".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x47, 0x49, 0x46, 0x38, 0x37, 0x61], // GIF87a
@@ -37,6 +237,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "image/gif".to_string(),
             extensions: vec!["gif".to_string()],
             description: "GIF Image (87a)".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x47, 0x49, 0x46, 0x38, 0x39, 0x61], // GIF89a
@@ -44,6 +245,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "image/gif".to_string(),
             extensions: vec!["gif".to_string()],
             description: "GIF Image (89a)".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x42, 0x4D], // BM
@@ -51,6 +253,23 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "image/bmp".to_string(),
             extensions: vec!["bmp".to_string()],
             description: "Windows Bitmap".to_string(),
+            trailer: None,
+        },
+        FileSignature {
+            signature: vec![0x49, 0x49, 0x2A, 0x00], // II*
+            offset: 0,
+            mime_type: "image/tiff".to_string(),
+            extensions: vec!["tif".to_string(), "tiff".to_string()],
+            description: "TIFF Image (little-endian)".to_string(),
+            trailer: None,
+        },
+        FileSignature {
+            signature: vec![0x4D, 0x4D, 0x00, 0x2A], // MM*
+            offset: 0,
+            mime_type: "image/tiff".to_string(),
+            extensions: vec!["tif".to_string(), "tiff".to_string()],
+            description: "TIFF Image (big-endian)".to_string(),
+            trailer: None,
         },
     ];
     signatures.insert("image".to_string(), image_sigs);
@@ -58,11 +277,12 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
     // Video formats
     let video_sigs = vec![
         FileSignature {
-            signature: vec![0x00, 0x00, 0x00, 0x18, 0x66, 0x74, 0x79, 0x70], // ftyp
+            signature: vec![0x66, 0x74, 0x79, 0x70], // ftyp box, after its 4-byte size
             offset: 4,
             mime_type: "video/mp4".to_string(),
             extensions: vec!["mp4".to_string(), "m4v".to_string()],
             description: "MPEG-4 Video".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x46, 0x4C, 0x56, 0x01], // FLV
@@ -70,6 +290,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "video/x-flv".to_string(),
             extensions: vec!["flv".to_string()],
             description: "Flash Video".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x1A, 0x45, 0xDF, 0xA3], // EBML
@@ -77,6 +298,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "video/webm".to_string(),
             extensions: vec!["webm".to_string(), "mkv".to_string()],
             description: "WebM/Matroska Video".to_string(),
+            trailer: None,
         },
     ];
     signatures.insert("video".to_string(), video_sigs);
@@ -89,6 +311,15 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "audio/mpeg".to_string(),
             extensions: vec!["mp3".to_string()],
             description: "MP3 Audio".to_string(),
+            trailer: None,
+        },
+        FileSignature {
+            signature: vec![0xFF, 0xF3], // MPEG-2 Layer III frame sync
+            offset: 0,
+            mime_type: "audio/mpeg".to_string(),
+            extensions: vec!["mp3".to_string()],
+            description: "MP3 Audio (MPEG-2)".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x49, 0x44, 0x33], // ID3
@@ -96,6 +327,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "audio/mpeg".to_string(),
             extensions: vec!["mp3".to_string()],
             description: "MP3 Audio with ID3".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x66, 0x4C, 0x61, 0x43], // fLaC
@@ -103,6 +335,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "audio/flac".to_string(),
             extensions: vec!["flac".to_string()],
             description: "FLAC Audio".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x4F, 0x67, 0x67, 0x53], // OggS
@@ -110,6 +343,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "audio/ogg".to_string(),
             extensions: vec!["ogg".to_string(), "oga".to_string()],
             description: "Ogg Audio".to_string(),
+            trailer: None,
         },
     ];
     signatures.insert("audio".to_string(), audio_sigs);
@@ -122,6 +356,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/pdf".to_string(),
             extensions: vec!["pdf".to_string()],
             description: "PDF Document".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x50, 0x4B, 0x03, 0x04], // PK (ZIP-based)
@@ -130,6 +365,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
                 .to_string(),
             extensions: vec!["docx".to_string(), "xlsx".to_string(), "pptx".to_string()],
             description: "Microsoft Office Document".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1], // OLE
@@ -137,6 +373,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/msword".to_string(),
             extensions: vec!["doc".to_string(), "xls".to_string(), "ppt".to_string()],
             description: "Microsoft Office Legacy Document".to_string(),
+            trailer: None,
         },
     ];
     signatures.insert("document".to_string(), document_sigs);
@@ -149,6 +386,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/zip".to_string(),
             extensions: vec!["zip".to_string()],
             description: "ZIP Archive".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x52, 0x61, 0x72, 0x21, 0x1A, 0x07, 0x00], // Rar!
@@ -156,6 +394,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/vnd.rar".to_string(),
             extensions: vec!["rar".to_string()],
             description: "RAR Archive".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C], // 7z
@@ -163,6 +402,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/x-7z-compressed".to_string(),
             extensions: vec!["7z".to_string()],
             description: "7-Zip Archive".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x1F, 0x8B, 0x08], // GZIP
@@ -170,6 +410,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/gzip".to_string(),
             extensions: vec!["gz".to_string(), "gzip".to_string()],
             description: "GZIP Archive".to_string(),
+            trailer: None,
         },
     ];
     signatures.insert("archive".to_string(), archive_sigs);
@@ -182,6 +423,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/vnd.microsoft.portable-executable".to_string(),
             extensions: vec!["exe".to_string(), "dll".to_string(), "sys".to_string()],
             description: "Windows Executable".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0x7F, 0x45, 0x4C, 0x46], // ELF
@@ -189,6 +431,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/x-executable".to_string(),
             extensions: vec!["elf".to_string(), "so".to_string()],
             description: "Linux Executable".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0xFE, 0xED, 0xFA, 0xCE], // Mach-O 32-bit
@@ -196,6 +439,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/x-mach-binary".to_string(),
             extensions: vec!["dylib".to_string()],
             description: "macOS Executable (32-bit)".to_string(),
+            trailer: None,
         },
         FileSignature {
             signature: vec![0xFE, 0xED, 0xFA, 0xCF], // Mach-O 64-bit
@@ -203,6 +447,7 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
             mime_type: "application/x-mach-binary".to_string(),
             extensions: vec!["dylib".to_string()],
             description: "macOS Executable (64-bit)".to_string(),
+            trailer: None,
         },
     ];
     signatures.insert("executable".to_string(), executable_sigs);
@@ -212,20 +457,34 @@ pub fn init_signature_database() -> HashMap<String, Vec<FileSignature>> {
 
 /// Analyze file content to determine file type and validate signature
 pub fn analyze_file_signature(data: &[u8], max_bytes: usize) -> SignatureAnalysisResult {
-    let signatures = init_signature_database();
+    analyze_with(data, max_bytes, &[])
+}
+
+/// [`analyze_file_signature`] against the built-in signatures plus `custom`
+fn analyze_with(
+    data: &[u8],
+    max_bytes: usize,
+    custom: &[FileSignature],
+) -> SignatureAnalysisResult {
+    // Built once; carving scans call this for every block
+    let signatures = signature_database();
     let analysis_data = &data[..std::cmp::min(data.len(), max_bytes)];
 
     let mut matches = Vec::new();
 
     // Check all signature categories
-    for (category, category_sigs) in &signatures {
+    let categories = signatures
+        .iter()
+        .map(|(category, sigs)| (category.as_str(), sigs.as_slice()))
+        .chain((!custom.is_empty()).then_some((CUSTOM_CATEGORY, custom)));
+    for (category, category_sigs) in categories {
         for signature in category_sigs {
             if signature.offset + signature.signature.len() <= analysis_data.len() {
                 let slice =
                     &analysis_data[signature.offset..signature.offset + signature.signature.len()];
                 if slice == signature.signature {
                     matches.push(SignatureMatch {
-                        category: category.clone(),
+                        category: category.to_string(),
                         signature: signature.clone(),
                         confidence: calculate_signature_confidence(signature, analysis_data),
                     });
@@ -241,11 +500,11 @@ pub fn analyze_file_signature(data: &[u8], max_bytes: usize) -> SignatureAnalysi
         matches,
         analyzed_bytes: analysis_data.len(),
         is_text_file: is_likely_text_file(analysis_data),
-        entropy: calculate_entropy(analysis_data),
+        entropy: shannon_entropy(analysis_data),
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignatureMatch {
     pub category: String,
     pub signature: FileSignature,
@@ -261,32 +520,65 @@ pub struct SignatureAnalysisResult {
 }
 
 /// Calculate confidence for a signature match
+///
+/// A magic is evidence in proportion to its length: two bytes turn up every
+/// 64KB of random data, four bytes every 4GB. Each of the first four bytes
+/// counts 0.15 and each further byte up to eight 0.05, a magic at the start
+/// of the data anchors the file there, and format structure found behind
+/// the magic adds the rest. Only structurally checked two-byte magics, and
+/// three-byte ones with some structure, reach [`MIN_SIGNATURE_CONFIDENCE`].
 fn calculate_signature_confidence(signature: &FileSignature, data: &[u8]) -> f32 {
-    let mut confidence = 0.8; // Base confidence for signature match
+    let len = signature.signature.len();
+    let mut confidence = 0.15 * len.min(4) as f32 + 0.05 * (len.clamp(4, 8) - 4) as f32;
 
-    // Longer signatures are more reliable
-    confidence += (signature.signature.len() as f32 / 20.0).min(0.2);
+    // The file starts where the magic is; a magic further in says less
+    confidence += if signature.offset == 0 { 0.15 } else { 0.1 };
 
-    // Signatures at offset 0 are more reliable
-    if signature.offset == 0 {
-        confidence += 0.1;
+    if len <= 2 && is_plausible_short_match(signature, data) {
+        confidence += 0.3;
     }
 
     // Check for additional validation patterns
-    confidence += validate_additional_patterns(signature, data);
+    confidence += validate_additional_patterns(signature, data).unwrap_or(0.0);
 
     confidence.min(1.0)
 }
 
 /// Validate additional file format patterns beyond the main signature
-fn validate_additional_patterns(signature: &FileSignature, data: &[u8]) -> f32 {
-    match signature.mime_type.as_str() {
+///
+/// `None` for formats without a structure check.
+fn validate_additional_patterns(signature: &FileSignature, data: &[u8]) -> Option<f32> {
+    let confidence = match signature.mime_type.as_str() {
         "image/jpeg" => validate_jpeg_structure(data),
         "image/png" => validate_png_structure(data),
         "application/pdf" => validate_pdf_structure(data),
         "video/mp4" => validate_mp4_structure(data),
-        _ => 0.0,
-    }
+        "application/gzip" => validate_gzip_structure(data),
+        "audio/mpeg" if signature.signature == b"ID3" => validate_id3_structure(data),
+        "application/zip"
+        | "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+            validate_zip_structure(data)
+        }
+        _ => return None,
+    };
+    Some(confidence)
+}
+
+/// Whether content of this type is compressed from just past its header
+///
+/// Archives and audio and video streams measure as near-random even when
+/// intact, so high entropy says nothing about their condition.
+pub fn is_compressed_format(mime_type: &str) -> bool {
+    mime_type.starts_with("audio/")
+        || mime_type.starts_with("video/")
+        || matches!(
+            mime_type,
+            "application/zip"
+                | "application/vnd.rar"
+                | "application/x-7z-compressed"
+                | "application/gzip"
+                | "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        )
 }
 
 fn validate_jpeg_structure(data: &[u8]) -> f32 {
@@ -296,24 +588,26 @@ fn validate_jpeg_structure(data: &[u8]) -> f32 {
 
     let mut confidence = 0.0;
 
+    // The start of image is followed by another marker: APPn, DQT, SOFn, DHT or COM
+    if matches!(data[3], 0xE0..=0xEF | 0xDB | 0xC0..=0xC4 | 0xFE) {
+        confidence += 0.1;
+    }
+
     // Look for JFIF or EXIF markers
-    for i in 0..data.len().saturating_sub(4) {
-        if &data[i..i + 4] == b"JFIF" {
+    for window in data.windows(4) {
+        if window == b"JFIF" {
             confidence += 0.15;
             break;
         }
-        if &data[i..i + 4] == b"Exif" {
+        if window == b"Exif" {
             confidence += 0.1;
             break;
         }
     }
 
     // Look for End of Image marker
-    for i in 0..data.len().saturating_sub(2) {
-        if data[i] == 0xFF && data[i + 1] == 0xD9 {
-            confidence += 0.1;
-            break;
-        }
+    if data.windows(2).any(|window| window == [0xFF, 0xD9]) {
+        confidence += 0.1;
     }
 
     confidence
@@ -392,6 +686,51 @@ fn validate_mp4_structure(data: &[u8]) -> f32 {
     confidence
 }
 
+fn validate_zip_structure(data: &[u8]) -> f32 {
+    if data.len() < 30 {
+        return 0.0;
+    }
+
+    let mut confidence = 0.0;
+
+    // The first local file header names its entry in plain text
+    let name_len = u16::from_le_bytes([data[26], data[27]]) as usize;
+    if let Some(name) = data.get(30..30 + name_len) {
+        if name_len > 0 && name.iter().all(|b| (0x20..0x7F).contains(b)) {
+            confidence += 0.1;
+        }
+    }
+
+    // A small archive has its central directory in view
+    if find_all(data, &ZIP_CENTRAL_HEADER).next().is_some() {
+        confidence += 0.1;
+    }
+
+    confidence
+}
+
+fn validate_gzip_structure(data: &[u8]) -> f32 {
+    // Reserved flag bits are zero and the OS byte is a defined one
+    match data.get(3..10) {
+        Some(header) if header[0] & 0xE0 == 0 && (header[6] <= 13 || header[6] == 255) => 0.15,
+        _ => 0.0,
+    }
+}
+
+fn validate_id3_structure(data: &[u8]) -> f32 {
+    // ID3v2.2 to v2.4, and a tag size of 7-bit bytes
+    match data.get(3..10) {
+        Some(header)
+            if (2..=4).contains(&header[0])
+                && header[1] == 0
+                && header[3..7].iter().all(|b| b & 0x80 == 0) =>
+        {
+            0.15
+        }
+        _ => 0.0,
+    }
+}
+
 /// Check if file content appears to be text
 fn is_likely_text_file(data: &[u8]) -> bool {
     if data.is_empty() {
@@ -415,8 +754,11 @@ fn is_likely_text_file(data: &[u8]) -> bool {
     printable_ratio > 0.9 || has_utf8_bom
 }
 
-/// Calculate Shannon entropy of data
-fn calculate_entropy(data: &[u8]) -> f32 {
+/// Shannon entropy of `data` in bits per byte
+///
+/// Ranges from 0.0 for a single repeated byte to 8.0 for uniformly
+/// distributed bytes. Encrypted and compressed data sit near the top.
+pub fn shannon_entropy(data: &[u8]) -> f32 {
     if data.is_empty() {
         return 0.0;
     }
@@ -439,13 +781,61 @@ fn calculate_entropy(data: &[u8]) -> f32 {
     entropy
 }
 
+/// Entropy of the first [`ENTROPY_SAMPLE_LEN`] bytes of `data`
+pub fn sample_entropy(data: &[u8]) -> f32 {
+    shannon_entropy(&data[..data.len().min(ENTROPY_SAMPLE_LEN)])
+}
+
+/// Whether a signature hit looks like a chance match inside random data
+///
+/// Real files follow their magic bytes with structure that the format
+/// checks pick up. A hit in a full sample that is indistinguishable from
+/// random bytes, with none of its format's markers present, is almost always
+/// part of an encrypted or already-compressed blob. Formats without a
+/// structure check are never judged, since an intact 7z or FLAC file is just
+/// as random past its header.
+pub fn is_random_match(data: &[u8], signature_match: &SignatureMatch) -> bool {
+    let sample = &data[..data.len().min(ENTROPY_SAMPLE_LEN)];
+    sample.len() == ENTROPY_SAMPLE_LEN
+        && shannon_entropy(sample) >= RANDOM_ENTROPY_THRESHOLD
+        && validate_additional_patterns(&signature_match.signature, sample) == Some(0.0)
+}
+
+/// Confidence multiplier for content of `mime_type` with the given [`sample_entropy`]
+///
+/// Near-random content is likely encrypted, unless its format is compressed,
+/// and near-constant content likely padding or wiped space; both recover
+/// poorly.
+pub fn entropy_confidence_factor(entropy: f32, mime_type: Option<&str>) -> f32 {
+    if entropy >= RANDOM_ENTROPY_THRESHOLD {
+        if mime_type.is_some_and(is_compressed_format) {
+            1.0
+        } else {
+            0.5
+        }
+    } else if entropy < LOW_ENTROPY_THRESHOLD {
+        0.6
+    } else {
+        1.0
+    }
+}
+
 /// Extract detailed metadata from file content
 pub fn extract_content_metadata(data: &[u8], signature_match: &SignatureMatch) -> ContentMetadata {
-    match signature_match.signature.mime_type.as_str() {
+    let metadata = match signature_match.signature.mime_type.as_str() {
         "image/jpeg" => extract_jpeg_metadata(data),
         "image/png" => extract_png_metadata(data),
+        "image/tiff" => ContentMetadata {
+            created_time: extract_image_date(data),
+            ..Default::default()
+        },
         "application/pdf" => extract_pdf_metadata(data),
         _ => ContentMetadata::default(),
+    };
+
+    ContentMetadata {
+        entropy: Some(sample_entropy(data)),
+        ..metadata
     }
 }
 
@@ -456,11 +846,629 @@ pub struct ContentMetadata {
     pub color_depth: Option<u8>,
     pub compression: Option<String>,
     pub creation_software: Option<String>,
+    /// When the content was made, from metadata embedded in it such as EXIF
+    pub created_time: Option<DateTime<Utc>>,
+    /// Shannon entropy of the start of the content, see [`sample_entropy`]
+    pub entropy: Option<f32>,
     pub additional_info: HashMap<String, String>,
 }
 
+impl ContentMetadata {
+    /// Flatten into key/value pairs suitable for `FileMetadata::extended_attributes`
+    pub fn to_attributes(&self) -> HashMap<String, String> {
+        let mut attributes = self.additional_info.clone();
+
+        if let Some(width) = self.width {
+            attributes.insert("width".to_string(), width.to_string());
+        }
+        if let Some(height) = self.height {
+            attributes.insert("height".to_string(), height.to_string());
+        }
+        if let Some(color_depth) = self.color_depth {
+            attributes.insert("color_depth".to_string(), color_depth.to_string());
+        }
+        if let Some(ref compression) = self.compression {
+            attributes.insert("compression".to_string(), compression.clone());
+        }
+        if let Some(ref software) = self.creation_software {
+            attributes.insert("creation_software".to_string(), software.clone());
+        }
+        if let Some(entropy) = self.entropy {
+            attributes.insert("entropy".to_string(), format!("{:.3}", entropy));
+        }
+
+        attributes
+    }
+}
+
+/// Largest size [`estimate_file_size`] will report for a MIME type
+///
+/// Also a sensible size to assume when the end marker was not found.
+pub fn end_marker_search_limit(mime: &str) -> Option<u64> {
+    search_limit_with(mime, &[])
+}
+
+/// [`end_marker_search_limit`], also for the trailers in `custom`
+fn search_limit_with(mime: &str, custom: &[FileSignature]) -> Option<u64> {
+    match mime {
+        "image/jpeg" | "image/png" => Some(MAX_IMAGE_SIZE),
+        "application/pdf" => Some(MAX_PDF_SIZE),
+        _ => custom_trailer(mime, custom).map(|_| MAX_CUSTOM_SIZE),
+    }
+}
+
+/// Estimate the size of a file starting at `offset` in `data` from its end marker
+///
+/// Finds the JPEG end-of-image marker, the PNG `IEND` chunk (plus its CRC) or
+/// the PDF `%%EOF` trailer; [`SignatureSet::estimate_file_size`] also
+/// knows the trailers of custom signatures. Returns `None` for other types,
+/// or if no end marker is found within [`end_marker_search_limit`] bytes.
+pub fn estimate_file_size(data: &[u8], offset: usize, mime: &str) -> Option<u64> {
+    estimate_size_with(data, offset, mime, &[])
+}
+
+/// [`estimate_file_size`], also ending files at the trailers in `custom`
+fn estimate_size_with(
+    data: &[u8],
+    offset: usize,
+    mime: &str,
+    custom: &[FileSignature],
+) -> Option<u64> {
+    let (marker, trailer_len) = end_marker(mime, custom)?;
+    let limit = search_limit_with(mime, custom)? as usize;
+
+    let file = data.get(offset..)?;
+    let file = &file[..file.len().min(limit)];
+    let position = file
+        .windows(marker.len())
+        .position(|window| window == marker)?;
+
+    // A CRC cut off by the end of the data is not worth losing the file over
+    Some((position + marker.len() + trailer_len).min(file.len()) as u64)
+}
+
+/// End marker of a MIME type, and how many bytes of trailer follow it
+fn end_marker<'a>(mime: &str, custom: &'a [FileSignature]) -> Option<(&'a [u8], usize)> {
+    match mime {
+        "image/jpeg" => Some((&[0xFF, 0xD9], 0)),
+        "image/png" => Some((b"IEND", 4)),
+        "application/pdf" => Some((b"%%EOF", 0)),
+        _ => custom_trailer(mime, custom).map(|trailer| (trailer, 0)),
+    }
+}
+
+/// Size of a file found by an [`EndMarkerScanner`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndEstimate {
+    pub size: u64,
+    /// The search budget ran out before an end marker was found, so `size`
+    /// only covers the bytes that were searched
+    pub estimated: bool,
+}
+
+/// Device range known to hold no end marker, possibly followed by one
+#[derive(Debug, Clone, Copy)]
+struct ScannedRegion {
+    start: u64,
+    /// No marker starts in `start..clean_end`
+    clean_end: u64,
+    /// A marker starts at `clean_end`
+    marker_at_end: bool,
+}
+
+/// End-marker search shared by the carving scans, bounded by a byte budget
+///
+/// [`estimate_file_size`] searches up to the type's full size limit from
+/// every header, so a device full of JPEG-like headers is read over and over.
+/// The scanner remembers which device ranges it has already searched and
+/// skips them, and stops searching for one file after `budget` new bytes.
+/// Offsets are device offsets, so one scanner can serve a whole scan.
+#[derive(Debug)]
+pub struct EndMarkerScanner {
+    budget: u64,
+    signatures: SignatureSet,
+    scanned: Mutex<HashMap<Vec<u8>, Vec<ScannedRegion>>>,
+}
+
+impl Default for EndMarkerScanner {
+    fn default() -> Self {
+        Self::new(DEFAULT_END_MARKER_BUDGET)
+    }
+}
+
+impl EndMarkerScanner {
+    /// Scanner searching at most `budget` bytes per file
+    pub fn new(budget: u64) -> Self {
+        Self {
+            budget,
+            signatures: SignatureSet::default(),
+            scanned: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Also find the ends of files of the custom types in `signatures`
+    pub fn with_signatures(mut self, signatures: SignatureSet) -> Self {
+        self.signatures = signatures;
+        self
+    }
+
+    /// Size of the file whose bytes start `data`, at `device_offset` on the device
+    ///
+    /// Like [`estimate_file_size`], `None` if the type has no end marker or
+    /// none is found within [`end_marker_search_limit`] bytes or the data.
+    /// If the budget runs out first, the searched length is returned as an
+    /// estimate.
+    pub fn find_end(&self, data: &[u8], device_offset: u64, mime: &str) -> Option<EndEstimate> {
+        let custom = self.signatures.custom();
+        let (marker, trailer_len) = end_marker(mime, custom)?;
+        let limit = data.len().min(search_limit_with(mime, custom)? as usize);
+        let found = |position: u64| {
+            let size = (position - device_offset) as usize + marker.len() + trailer_len;
+            (position - device_offset + marker.len() as u64 <= limit as u64).then_some(
+                EndEstimate {
+                    size: size.min(limit) as u64,
+                    estimated: false,
+                },
+            )
+        };
+
+        // Skip what earlier searches covered
+        let mut position = device_offset;
+        while let Some(region) = self.cached_region(marker, position) {
+            if region.marker_at_end {
+                return found(region.clean_end);
+            }
+            position = region.clean_end;
+        }
+
+        // Marker starts that fit in the data, and how many this search may check
+        let last_start = device_offset + (limit + 1).saturating_sub(marker.len()) as u64;
+        let stop = last_start.min(position.saturating_add(self.budget));
+        let window_end = (stop - device_offset) as usize + marker.len() - 1;
+        let searched = data
+            .get((position - device_offset) as usize..window_end.min(data.len()))
+            .unwrap_or_default();
+        let hit = searched
+            .windows(marker.len())
+            .position(|window| window == marker)
+            .map(|index| position + index as u64);
+
+        let clean_end = hit.unwrap_or(stop.max(position));
+        self.remember(
+            marker,
+            ScannedRegion {
+                start: device_offset,
+                clean_end,
+                marker_at_end: hit.is_some(),
+            },
+        );
+
+        match hit {
+            Some(position) => found(position),
+            None if stop < last_start => Some(EndEstimate {
+                size: stop - device_offset,
+                estimated: true,
+            }),
+            None => None,
+        }
+    }
+
+    /// The searched region reaching furthest past `position`, if one covers it
+    fn cached_region(&self, marker: &[u8], position: u64) -> Option<ScannedRegion> {
+        let scanned = self.scanned.lock().unwrap();
+        scanned
+            .get(marker)?
+            .iter()
+            .filter(|region| region.start <= position && position <= region.clean_end)
+            .filter(|region| region.marker_at_end || position < region.clean_end)
+            .max_by_key(|region| (region.clean_end, region.marker_at_end))
+            .copied()
+    }
+
+    /// Record a searched region, dropping those it covers
+    fn remember(&self, marker: &[u8], region: ScannedRegion) {
+        let mut scanned = self.scanned.lock().unwrap();
+        let regions = match scanned.get_mut(marker) {
+            Some(regions) => regions,
+            None => scanned.entry(marker.to_vec()).or_default(),
+        };
+        regions.retain(|old| {
+            old.start < region.start
+                || old.clean_end > region.clean_end
+                || (old.clean_end == region.clean_end && old.marker_at_end && !region.marker_at_end)
+        });
+        regions.push(region);
+    }
+}
+
+/// Identify a file from its first bytes
+///
+/// This is the type detection shared by the filesystem engines' carving
+/// scans. Returns the MIME type and extension of the most likely signature
+/// after [`refine_file_type`]. Two-byte signatures (BMP, MP3 frame sync,
+/// MZ) match plenty of random data, so they must also pass a structural check.
+pub fn detect_file_type(header: &[u8]) -> Option<(String, String)> {
+    detect_with(header, &[])
+}
+
+/// [`detect_file_type`] against the built-in signatures plus `custom`
+fn detect_with(header: &[u8], custom: &[FileSignature]) -> Option<(String, String)> {
+    analyze_with(header, header.len(), custom)
+        .matches
+        .iter()
+        .find(|m| m.signature.signature.len() > 2 || is_plausible_short_match(&m.signature, header))
+        .map(|m| {
+            let (mime_type, extension) = refine_file_type(header, m);
+            (mime_type, extension.unwrap_or_else(|| "bin".to_string()))
+        })
+}
+
+/// Structural check behind a two-byte signature match
+fn is_plausible_short_match(signature: &FileSignature, data: &[u8]) -> bool {
+    match signature.mime_type.as_str() {
+        // Reserved fields are zero and the DIB header has one of its known sizes
+        "image/bmp" => {
+            data.len() >= 18
+                && data[6..10] == [0, 0, 0, 0]
+                && matches!(
+                    u32::from_le_bytes([data[14], data[15], data[16], data[17]]),
+                    12 | 40 | 52 | 56 | 64 | 108 | 124
+                )
+        }
+        // Bitrate index 0 (free) and 15 (bad) and sample rate index 3 are invalid
+        "audio/mpeg" => {
+            data.len() >= 3 && {
+                let bitrate = data[2] >> 4;
+                let sample_rate = (data[2] >> 2) & 0x03;
+                bitrate != 0 && bitrate != 0x0F && sample_rate != 0x03
+            }
+        }
+        // The DOS header points at the PE header
+        "application/vnd.microsoft.portable-executable" => {
+            data.len() >= 0x40 && {
+                let pe_offset =
+                    u32::from_le_bytes([data[0x3C], data[0x3D], data[0x3E], data[0x3F]]) as usize;
+                data.get(pe_offset..pe_offset + 4) == Some(b"PE\0\0".as_slice())
+            }
+        }
+        _ => false,
+    }
+}
+
+/// A footer that ends a file, for carving backwards from it
+///
+/// Header-anchored scans miss files whose first block is damaged. Formats
+/// with a distinctive trailer can be found from the end instead, and the
+/// trailer (or a header found before it) bounds the file.
+#[derive(Debug, Clone)]
+pub struct TrailerSignature {
+    pub trailer: Vec<u8>,
+    pub mime_type: String,
+    pub extension: String,
+    /// Furthest before the trailer the start of the file is looked for
+    pub max_size: u64,
+}
+
+/// A file located by its trailer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CarvedFile {
+    /// Offset of the first byte of the file in the scanned data
+    pub offset: u64,
+    pub size: u64,
+    pub mime_type: String,
+    pub extension: String,
+}
+
+/// ZIP end of central directory record ("PK\x05\x06")
+const ZIP_EOCD: [u8; 4] = [0x50, 0x4B, 0x05, 0x06];
+/// Fixed part of the EOCD record, before the archive comment
+const ZIP_EOCD_LEN: usize = 22;
+/// ZIP central directory file header ("PK\x01\x02")
+const ZIP_CENTRAL_HEADER: [u8; 4] = [0x50, 0x4B, 0x01, 0x02];
+/// Largest ZIP archive trailer carving will bound
+const MAX_ZIP_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Trailers understood by [`carve_by_trailers`]
+pub fn trailer_signatures() -> Vec<TrailerSignature> {
+    vec![
+        TrailerSignature {
+            trailer: ZIP_EOCD.to_vec(),
+            mime_type: "application/zip".to_string(),
+            extension: "zip".to_string(),
+            max_size: MAX_ZIP_SIZE,
+        },
+        TrailerSignature {
+            trailer: b"%%EOF".to_vec(),
+            mime_type: "application/pdf".to_string(),
+            extension: "pdf".to_string(),
+            max_size: MAX_PDF_SIZE,
+        },
+    ]
+}
+
+/// Find files in `data` by their trailers and bound each one
+///
+/// A ZIP's EOCD record gives the size and offset of the central directory,
+/// which places the start of the archive even if its first local header is
+/// damaged. A PDF is bounded by the nearest `%PDF-` header before its
+/// `%%EOF`; for incrementally updated PDFs only the last `%%EOF` is kept.
+pub fn carve_by_trailers(data: &[u8]) -> Vec<CarvedFile> {
+    let mut carved: Vec<CarvedFile> = Vec::new();
+
+    for signature in trailer_signatures() {
+        for position in find_all(data, &signature.trailer) {
+            let bounds = if signature.trailer == ZIP_EOCD {
+                bound_zip(data, position)
+            } else {
+                bound_pdf(data, position, signature.max_size as usize)
+            };
+            let Some((start, end)) = bounds else {
+                continue;
+            };
+            if (end - start) as u64 > signature.max_size {
+                continue;
+            }
+
+            let file = CarvedFile {
+                offset: start as u64,
+                size: (end - start) as u64,
+                mime_type: signature.mime_type.clone(),
+                extension: signature.extension.clone(),
+            };
+            // A later trailer for the same start (PDF updates) supersedes the earlier one
+            match carved
+                .iter_mut()
+                .find(|c| c.offset == file.offset && c.mime_type == file.mime_type)
+            {
+                Some(existing) => existing.size = existing.size.max(file.size),
+                None => carved.push(file),
+            }
+        }
+    }
+
+    carved.sort_by_key(|c| c.offset);
+    carved
+}
+
+/// Start and end of the ZIP archive whose EOCD record is at `eocd`
+fn bound_zip(data: &[u8], eocd: usize) -> Option<(usize, usize)> {
+    let record = data.get(eocd..eocd + ZIP_EOCD_LEN)?;
+    let le32 = |at: usize| {
+        u32::from_le_bytes([record[at], record[at + 1], record[at + 2], record[at + 3]])
+    };
+    let directory_size = le32(12) as usize;
+    let directory_offset = le32(16) as usize;
+    let comment_len = u16::from_le_bytes([record[20], record[21]]) as usize;
+
+    // The central directory ends where the EOCD record starts
+    let directory_start = eocd.checked_sub(directory_size)?;
+    let start = directory_start.checked_sub(directory_offset)?;
+    let end = (eocd + ZIP_EOCD_LEN + comment_len).min(data.len());
+
+    // An empty archive has no directory to check, anything else must start with an entry
+    if directory_size > 0 && data.get(directory_start..directory_start + 4)? != ZIP_CENTRAL_HEADER {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+/// Start and end of the PDF whose `%%EOF` is at `trailer`
+fn bound_pdf(data: &[u8], trailer: usize, max_size: usize) -> Option<(usize, usize)> {
+    let mut end = trailer + 5;
+    // Include the end-of-line after the marker
+    if data.get(end) == Some(&b'\r') {
+        end += 1;
+    }
+    if data.get(end) == Some(&b'\n') {
+        end += 1;
+    }
+
+    let search_start = trailer.saturating_sub(max_size);
+    let start = data[search_start..trailer]
+        .windows(5)
+        .rposition(|window| window == b"%PDF-")?;
+    Some((search_start + start, end))
+}
+
+/// Offsets of every occurrence of `needle` in `data`
+fn find_all<'a>(data: &'a [u8], needle: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    data.windows(needle.len())
+        .enumerate()
+        .filter(move |(_, window)| *window == needle)
+        .map(|(position, _)| position)
+}
+
+/// Refine the MIME type and extension of a signature match using the content.
+///
+/// ZIP-based formats share the `PK\x03\x04` signature, so the member names of
+/// the archive decide between OOXML/OpenDocument/JAR containers and plain ZIP.
+/// ISO media files share the `ftyp` box, whose major brand tells MP4, M4A and
+/// QuickTime apart.
+pub fn refine_file_type(data: &[u8], signature_match: &SignatureMatch) -> (String, Option<String>) {
+    if signature_match.signature.signature == [0x50, 0x4B, 0x03, 0x04] {
+        let (mime_type, extension) = detect_zip_container(data);
+        return (mime_type.to_string(), Some(extension.to_string()));
+    }
+    if signature_match.signature.signature == *b"ftyp" {
+        let (mime_type, extension) = detect_ftyp_brand(data);
+        return (mime_type.to_string(), Some(extension.to_string()));
+    }
+
+    (
+        signature_match.signature.mime_type.clone(),
+        signature_match.signature.extensions.first().cloned(),
+    )
+}
+
+/// Classify an ISO base media file by the major brand of its `ftyp` box
+fn detect_ftyp_brand(data: &[u8]) -> (&'static str, &'static str) {
+    match data.get(8..12) {
+        Some(b"qt  ") => ("video/quicktime", "mov"),
+        Some(b"M4A ") | Some(b"M4B ") => ("audio/mp4", "m4a"),
+        Some(b"M4V ") | Some(b"M4VH") | Some(b"M4VP") => ("video/x-m4v", "m4v"),
+        Some(b"3gp4") | Some(b"3gp5") | Some(b"3gp6") => ("video/3gpp", "3gp"),
+        _ => ("video/mp4", "mp4"),
+    }
+}
+
+/// Classify a ZIP archive by the names of its local file headers
+fn detect_zip_container(data: &[u8]) -> (&'static str, &'static str) {
+    let mut offset = 0;
+    let mut has_content_types = false;
+
+    while offset + 30 <= data.len() && data[offset..offset + 4] == [0x50, 0x4B, 0x03, 0x04] {
+        let compressed_size = u32::from_le_bytes([
+            data[offset + 18],
+            data[offset + 19],
+            data[offset + 20],
+            data[offset + 21],
+        ]) as usize;
+        let name_len = u16::from_le_bytes([data[offset + 26], data[offset + 27]]) as usize;
+        let extra_len = u16::from_le_bytes([data[offset + 28], data[offset + 29]]) as usize;
+
+        let name_start = offset + 30;
+        let name_end = std::cmp::min(name_start + name_len, data.len());
+        let name = &data[name_start..name_end];
+
+        if name.starts_with(b"word/") {
+            return (
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+                "docx",
+            );
+        }
+        if name.starts_with(b"xl/") {
+            return (
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                "xlsx",
+            );
+        }
+        if name.starts_with(b"ppt/") {
+            return (
+                "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+                "pptx",
+            );
+        }
+        if name == b"[Content_Types].xml" {
+            // Every OOXML package has this; keep walking for the part that names the type
+            has_content_types = true;
+        }
+        if name == b"META-INF/MANIFEST.MF" {
+            return ("application/java-archive", "jar");
+        }
+        if name == b"mimetype" {
+            // OpenDocument stores its MIME type uncompressed as the first member
+            let content_start = name_end + extra_len;
+            let content_end = std::cmp::min(content_start + compressed_size, data.len());
+            let content = data.get(content_start..content_end).unwrap_or(&[]);
+            if content.ends_with(b"opendocument.text") {
+                return ("application/vnd.oasis.opendocument.text", "odt");
+            }
+            if content.ends_with(b"opendocument.spreadsheet") {
+                return ("application/vnd.oasis.opendocument.spreadsheet", "ods");
+            }
+            if content.ends_with(b"opendocument.presentation") {
+                return ("application/vnd.oasis.opendocument.presentation", "odp");
+            }
+        }
+
+        // Streamed entries (bit 3) put sizes after the data, so stop walking there
+        let flags = u16::from_le_bytes([data[offset + 6], data[offset + 7]]);
+        if flags & 0x0008 != 0 {
+            break;
+        }
+        offset = name_start + name_len + extra_len + compressed_size;
+    }
+
+    if has_content_types {
+        // An OOXML package whose main part was not in the data; documents are the most common
+        return (
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "docx",
+        );
+    }
+
+    ("application/zip", "zip")
+}
+
+/// When a photo was taken, from the EXIF data of a JPEG or TIFF
+///
+/// Reads `DateTimeOriginal`, falling back to `DateTimeDigitized` and then
+/// `DateTime`. Cameras record local time, which is taken as UTC unless the
+/// matching `OffsetTime*` tag gives the zone. Only the first
+/// [`EXIF_SEARCH_LEN`] bytes are looked at. Always `None` without the `exif`
+/// feature.
+#[cfg(feature = "exif")]
+pub fn extract_image_date(data: &[u8]) -> Option<DateTime<Utc>> {
+    use exif::{In, Tag, Value};
+
+    fn ascii(exif: &exif::Exif, tag: Tag) -> Option<&[u8]> {
+        match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(values) => values.first().map(Vec::as_slice),
+            _ => None,
+        }
+    }
+
+    let data = &data[..data.len().min(EXIF_SEARCH_LEN)];
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(data))
+        .ok()?;
+
+    [
+        (Tag::DateTimeOriginal, Tag::OffsetTimeOriginal),
+        (Tag::DateTimeDigitized, Tag::OffsetTimeDigitized),
+        (Tag::DateTime, Tag::OffsetTime),
+    ]
+    .into_iter()
+    .find_map(|(date_tag, offset_tag)| {
+        let mut date = exif::DateTime::from_ascii(ascii(&exif, date_tag)?).ok()?;
+        if let Some(offset) = ascii(&exif, offset_tag) {
+            // A malformed offset leaves the time as UTC
+            let _ = date.parse_offset(offset);
+        }
+
+        let local =
+            chrono::NaiveDate::from_ymd_opt(date.year as i32, date.month as u32, date.day as u32)?
+                .and_hms_opt(date.hour as u32, date.minute as u32, date.second as u32)?;
+        let utc = local - chrono::Duration::minutes(date.offset.unwrap_or(0) as i64);
+        Some(DateTime::from_naive_utc_and_offset(utc, Utc))
+    })
+}
+
+/// When a photo was taken; EXIF support is disabled in this build
+#[cfg(not(feature = "exif"))]
+pub fn extract_image_date(_data: &[u8]) -> Option<DateTime<Utc>> {
+    None
+}
+
 fn extract_jpeg_metadata(data: &[u8]) -> ContentMetadata {
-    let mut metadata = ContentMetadata::default();
+    let mut metadata = ContentMetadata {
+        created_time: extract_image_date(data),
+        ..Default::default()
+    };
+
+    // Start of Frame segments carry the image geometry. DHT (C4), JPG (C8)
+    // and DAC (CC) share the marker range but are not frames.
+    for i in 0..data.len().saturating_sub(9) {
+        let marker = data[i + 1];
+        if data[i] == 0xFF
+            && (0xC0..=0xCF).contains(&marker)
+            && !matches!(marker, 0xC4 | 0xC8 | 0xCC)
+        {
+            metadata.color_depth = Some(data[i + 4]);
+            metadata.height = Some(u16::from_be_bytes([data[i + 5], data[i + 6]]) as u32);
+            metadata.width = Some(u16::from_be_bytes([data[i + 7], data[i + 8]]) as u32);
+            metadata.compression = Some(
+                if marker == 0xC2 {
+                    "progressive"
+                } else {
+                    "baseline"
+                }
+                .to_string(),
+            );
+            break;
+        }
+    }
 
     // Look for APP0 segment (JFIF)
     for i in 0..data.len().saturating_sub(16) {
@@ -500,13 +1508,46 @@ fn extract_png_metadata(data: &[u8]) -> ContentMetadata {
     metadata
 }
 
-fn extract_pdf_metadata(_data: &[u8]) -> ContentMetadata {
-    // PDF metadata extraction would require more complex parsing
-    ContentMetadata::default()
+fn extract_pdf_metadata(data: &[u8]) -> ContentMetadata {
+    let mut metadata = ContentMetadata::default();
+    let text = String::from_utf8_lossy(data);
+
+    if let Some(version) = text
+        .strip_prefix("%PDF-")
+        .and_then(|rest| rest.get(..3))
+        .filter(|v| v.as_bytes()[0].is_ascii_digit())
+    {
+        metadata
+            .additional_info
+            .insert("pdf_version".to_string(), version.to_string());
+    }
+
+    // Page objects are "/Type /Page"; "/Type /Pages" is the page tree node
+    let page_count = text
+        .match_indices("/Type")
+        .filter(|(i, _)| {
+            let rest = text[i + 5..].trim_start();
+            rest.starts_with("/Page") && !rest.starts_with("/Pages")
+        })
+        .count();
+    if page_count > 0 {
+        metadata
+            .additional_info
+            .insert("page_count".to_string(), page_count.to_string());
+    }
+
+    if let Some(start) = text.find("/Producer") {
+        let rest = text[start + 9..].trim_start();
+        if let Some(value) = rest.strip_prefix('(').and_then(|v| v.split(')').next()) {
+            metadata.creation_software = Some(value.to_string());
+        }
+    }
+
+    metadata
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     #[test]
@@ -528,6 +1569,224 @@ mod tests {
         assert_eq!(result.matches[0].signature.mime_type, "image/png");
     }
 
+    /// Confidence of the best match for `mime` at the start of `data`
+    fn confidence_of(data: &[u8], mime: &str) -> f32 {
+        analyze_file_signature(data, 1024)
+            .matches
+            .iter()
+            .find(|m| m.signature.mime_type == mime)
+            .map(|m| m.confidence)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_short_magics_score_below_long_ones() {
+        let padded = |magic: &[u8]| {
+            let mut data = magic.to_vec();
+            data.resize(64, 0x5A);
+            data
+        };
+
+        let bmp = confidence_of(&padded(b"BM"), "image/bmp");
+        let gzip = confidence_of(&padded(&[0x1F, 0x8B, 0x08, 0xFF]), "application/gzip");
+        let elf = confidence_of(&padded(b"\x7fELF"), "application/x-executable");
+        let ole = confidence_of(
+            &padded(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]),
+            "application/msword",
+        );
+
+        assert!(
+            bmp < gzip && gzip < elf && elf < ole,
+            "{bmp} {gzip} {elf} {ole}"
+        );
+        // Bare two- and three-byte magics do not count as files
+        assert!(bmp < MIN_SIGNATURE_CONFIDENCE);
+        assert!(gzip < MIN_SIGNATURE_CONFIDENCE);
+        assert!(elf >= MIN_SIGNATURE_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_structure_lifts_short_magics() {
+        // BMP with zero reserved fields and a 40-byte DIB header
+        let mut bmp = vec![0u8; 64];
+        bmp[..2].copy_from_slice(b"BM");
+        bmp[2..6].copy_from_slice(&64u32.to_le_bytes());
+        bmp[14..18].copy_from_slice(&40u32.to_le_bytes());
+        assert!(confidence_of(&bmp, "image/bmp") >= MIN_SIGNATURE_CONFIDENCE);
+
+        // Deflate gzip with no flags, written on Unix
+        let mut gzip = vec![0x1F, 0x8B, 0x08, 0x00, 0, 0, 0, 0, 0x00, 0x03];
+        gzip.resize(64, 0x5A);
+        assert!(confidence_of(&gzip, "application/gzip") >= MIN_SIGNATURE_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_estimate_jpeg_size() {
+        let mut data = vec![0u8; 100];
+        data.extend_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10]);
+        data.extend_from_slice(&[0xAB; 500]);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        data.extend_from_slice(&[0x00; 300]);
+
+        assert_eq!(estimate_file_size(&data, 100, "image/jpeg"), Some(508));
+        // No end marker after the start of the file
+        assert_eq!(estimate_file_size(&data[..600], 100, "image/jpeg"), None);
+    }
+
+    #[test]
+    fn test_estimate_png_size() {
+        let mut data = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        data.extend_from_slice(&[0x11; 200]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"IEND");
+        data.extend_from_slice(&[0xAE, 0x42, 0x60, 0x82]);
+        data.extend_from_slice(&[0xFF; 64]);
+
+        assert_eq!(estimate_file_size(&data, 0, "image/png"), Some(220));
+        assert_eq!(estimate_file_size(&data, 0, "application/zip"), None);
+        assert_eq!(estimate_file_size(&data, data.len() + 1, "image/png"), None);
+    }
+
+    #[test]
+    fn test_end_marker_scan_stops_at_budget() {
+        // A JPEG header with no end marker anywhere in 1 MiB
+        let mut data = vec![0u8; 1024 * 1024];
+        data[..4].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+
+        let scanner = EndMarkerScanner::new(4096);
+        assert_eq!(
+            scanner.find_end(&data, 0, "image/jpeg"),
+            Some(EndEstimate {
+                size: 4096,
+                estimated: true
+            })
+        );
+
+        // A header inside the searched range skips what was searched and
+        // spends its own budget past it
+        assert_eq!(
+            scanner.find_end(&data[1000..], 1000, "image/jpeg"),
+            Some(EndEstimate {
+                size: 2 * 4096 - 1000,
+                estimated: true
+            })
+        );
+
+        // With budget to spare, reaching the end of the data finds nothing
+        let scanner = EndMarkerScanner::new(2 * 1024 * 1024);
+        assert_eq!(scanner.find_end(&data, 0, "image/jpeg"), None);
+    }
+
+    #[test]
+    fn test_end_marker_scan_reuses_found_markers() {
+        let mut data = vec![0u8; 8192];
+        data[5000..5002].copy_from_slice(&[0xFF, 0xD9]);
+
+        let scanner = EndMarkerScanner::new(8192);
+        let end = scanner.find_end(&data, 0, "image/jpeg").unwrap();
+        assert_eq!(end.size, 5002);
+        assert!(!end.estimated);
+        assert_eq!(scanner.find_end(&data, 0, "application/zip"), None);
+
+        // A later header before the marker is answered from what was
+        // searched, without reading its bytes again
+        let blank = vec![0u8; 8192 - 3000];
+        let end = scanner.find_end(&blank, 3000, "image/jpeg").unwrap();
+        assert_eq!(end.size, 2002);
+        assert!(!end.estimated);
+    }
+
+    fn detected(header: &[u8]) -> Option<(String, String)> {
+        // Pad like a sector read would be
+        let mut data = header.to_vec();
+        data.resize(512, 0);
+        detect_file_type(&data)
+    }
+
+    #[test]
+    fn test_detect_image_types() {
+        assert_eq!(
+            detected(b"GIF87a\x10\x00"),
+            Some(("image/gif".into(), "gif".into()))
+        );
+        assert_eq!(
+            detected(b"GIF89a\x10\x00"),
+            Some(("image/gif".into(), "gif".into()))
+        );
+        assert_eq!(
+            detected(b"II*\x00\x08\x00\x00\x00"),
+            Some(("image/tiff".into(), "tif".into()))
+        );
+        assert_eq!(
+            detected(b"MM\x00*\x00\x00\x00\x08"),
+            Some(("image/tiff".into(), "tif".into()))
+        );
+
+        let mut bmp = b"BM".to_vec();
+        bmp.extend_from_slice(&1078u32.to_le_bytes());
+        bmp.extend_from_slice(&[0, 0, 0, 0]);
+        bmp.extend_from_slice(&54u32.to_le_bytes());
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        assert_eq!(detected(&bmp), Some(("image/bmp".into(), "bmp".into())));
+        // "BM" followed by junk is not a bitmap
+        assert_eq!(detected(b"BMxxxxxxxxxxxxxxxxxx"), None);
+    }
+
+    #[test]
+    fn test_detect_media_types() {
+        let ftyp = |brand: &[u8]| {
+            let mut data = vec![0, 0, 0, 0x20];
+            data.extend_from_slice(b"ftyp");
+            data.extend_from_slice(brand);
+            data
+        };
+        assert_eq!(
+            detected(&ftyp(b"isom")),
+            Some(("video/mp4".into(), "mp4".into()))
+        );
+        assert_eq!(
+            detected(&ftyp(b"qt  ")),
+            Some(("video/quicktime".into(), "mov".into()))
+        );
+        assert_eq!(
+            detected(&ftyp(b"M4A ")),
+            Some(("audio/mp4".into(), "m4a".into()))
+        );
+
+        assert_eq!(
+            detected(b"ID3\x03\x00"),
+            Some(("audio/mpeg".into(), "mp3".into()))
+        );
+        // 128 kbit/s, 44.1 kHz
+        assert_eq!(
+            detected(&[0xFF, 0xFB, 0x90, 0x64]),
+            Some(("audio/mpeg".into(), "mp3".into()))
+        );
+        // Bitrate index 15 is invalid
+        assert_eq!(detected(&[0xFF, 0xFB, 0xF0, 0x64]), None);
+    }
+
+    #[test]
+    fn test_detect_zip_subtypes() {
+        let mut docx = zip_with_member(b"[Content_Types].xml");
+        docx.extend(zip_with_member(b"word/document.xml"));
+        assert_eq!(detected(&docx).unwrap().1, "docx");
+
+        let mut xlsx = zip_with_member(b"[Content_Types].xml");
+        xlsx.extend(zip_with_member(b"xl/workbook.xml"));
+        assert_eq!(detected(&xlsx).unwrap().1, "xlsx");
+
+        // Only the content types part made it into the header
+        let package = zip_with_member(b"[Content_Types].xml");
+        assert_eq!(detected(&package).unwrap().1, "docx");
+
+        let plain = zip_with_member(b"notes.txt");
+        assert_eq!(
+            detected(&plain),
+            Some(("application/zip".into(), "zip".into()))
+        );
+    }
+
     #[test]
     fn test_text_file_detection() {
         let text_content = b"Hello, this is a text file with normal characters.";
@@ -537,16 +1796,385 @@ mod tests {
         assert!(!is_likely_text_file(&binary_content));
     }
 
+    fn signature_match_for(data: &[u8], mime_type: &str) -> SignatureMatch {
+        analyze_file_signature(data, 1024)
+            .matches
+            .into_iter()
+            .find(|m| m.signature.mime_type == mime_type)
+            .expect("signature should match")
+    }
+
+    #[test]
+    fn test_jpeg_dimensions() {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(b"JFIF\0");
+        jpeg.extend_from_slice(&[0x01, 0x02, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00]);
+        // SOF0: length 17, precision 8, height 480, width 640, 3 components
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80, 0x03]);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+
+        let signature_match = signature_match_for(&jpeg, "image/jpeg");
+        let metadata = extract_content_metadata(&jpeg, &signature_match);
+
+        assert_eq!(metadata.width, Some(640));
+        assert_eq!(metadata.height, Some(480));
+        assert_eq!(metadata.color_depth, Some(8));
+
+        let attributes = metadata.to_attributes();
+        assert_eq!(attributes.get("width").map(String::as_str), Some("640"));
+        assert_eq!(attributes.get("height").map(String::as_str), Some("480"));
+        assert_eq!(
+            attributes.get("jfif_version").map(String::as_str),
+            Some("1.2")
+        );
+    }
+
+    /// Little-endian TIFF data with ASCII tags in IFD0 and the Exif IFD
+    fn exif_tiff(ifd0: &[(u16, &[u8])], exif: &[(u16, &[u8])]) -> Vec<u8> {
+        let ifd_len = |entries: usize| 2 + 12 * entries + 4;
+        let ifd0_entries = ifd0.len() + 1; // plus the Exif IFD pointer
+        let exif_offset = 8 + ifd_len(ifd0_entries);
+        let mut data_offset = exif_offset + ifd_len(exif.len());
+
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        let mut values = Vec::new();
+        let mut write_ifd =
+            |tiff: &mut Vec<u8>, entries: &[(u16, &[u8])], pointer: Option<usize>| {
+                tiff.extend_from_slice(
+                    &((entries.len() + pointer.is_some() as usize) as u16).to_le_bytes(),
+                );
+                for (tag, value) in entries {
+                    tiff.extend_from_slice(&tag.to_le_bytes());
+                    tiff.extend_from_slice(&2u16.to_le_bytes()); // ASCII
+                    tiff.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    tiff.extend_from_slice(&(data_offset as u32).to_le_bytes());
+                    values.extend_from_slice(value);
+                    data_offset += value.len();
+                }
+                if let Some(pointer) = pointer {
+                    tiff.extend_from_slice(&0x8769u16.to_le_bytes());
+                    tiff.extend_from_slice(&4u16.to_le_bytes()); // LONG
+                    tiff.extend_from_slice(&1u32.to_le_bytes());
+                    tiff.extend_from_slice(&(pointer as u32).to_le_bytes());
+                }
+                tiff.extend_from_slice(&0u32.to_le_bytes()); // no next IFD
+            };
+        write_ifd(&mut tiff, ifd0, Some(exif_offset));
+        write_ifd(&mut tiff, exif, None);
+        tiff.extend_from_slice(&values);
+        tiff
+    }
+
+    /// A JPEG whose APP1 segment holds `tiff`
+    fn exif_jpeg(tiff: &[u8]) -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(tiff);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[cfg(feature = "exif")]
+    #[test]
+    fn test_extract_image_date_from_exif() {
+        use chrono::TimeZone;
+
+        let tiff = exif_tiff(
+            &[(0x0132, b"2024:01:01 00:00:00\0")],
+            &[(0x9003, b"2023:07:14 09:30:00\0"), (0x9011, b"+02:00\0")],
+        );
+        let jpeg = exif_jpeg(&tiff);
+
+        // DateTimeOriginal wins over DateTime, shifted to UTC by its offset
+        let taken = Utc.with_ymd_and_hms(2023, 7, 14, 7, 30, 0).unwrap();
+        assert_eq!(extract_image_date(&jpeg), Some(taken));
+        assert_eq!(extract_image_date(&tiff), Some(taken));
+
+        let signature_match = signature_match_for(&jpeg, "image/jpeg");
+        let metadata = extract_content_metadata(&jpeg, &signature_match);
+        assert_eq!(metadata.created_time, Some(taken));
+
+        // Without DateTimeOriginal or an offset, DateTime is taken as UTC
+        let tiff = exif_tiff(&[(0x0132, b"2024:01:01 12:00:00\0")], &[]);
+        assert_eq!(
+            extract_image_date(&exif_jpeg(&tiff)),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_extract_image_date_without_exif() {
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0xFF, 0xD9,
+        ];
+        assert_eq!(extract_image_date(&jpeg), None);
+        assert_eq!(extract_image_date(b"not an image"), None);
+
+        // A blank date, as written by cameras with no clock set
+        let tiff = exif_tiff(&[], &[(0x9003, b"    :  :     :  :  \0")]);
+        assert_eq!(extract_image_date(&exif_jpeg(&tiff)), None);
+    }
+
+    fn zip_with_member(name: &[u8]) -> Vec<u8> {
+        let mut zip = vec![0x50, 0x4B, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00];
+        zip.extend_from_slice(&[0u8; 8]); // time, date, crc32
+        zip.extend_from_slice(&4u32.to_le_bytes()); // compressed size
+        zip.extend_from_slice(&4u32.to_le_bytes()); // uncompressed size
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip.extend_from_slice(name);
+        zip.extend_from_slice(b"data");
+        zip
+    }
+
+    /// A one-member archive with its central directory and EOCD record
+    pub(crate) fn zip_archive(name: &[u8]) -> Vec<u8> {
+        let mut zip = zip_with_member(name);
+        let directory_offset = zip.len();
+
+        zip.extend_from_slice(&ZIP_CENTRAL_HEADER);
+        zip.extend_from_slice(&[0x14, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        zip.extend_from_slice(&[0u8; 8]); // time, date, crc32
+        zip.extend_from_slice(&4u32.to_le_bytes());
+        zip.extend_from_slice(&4u32.to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0u8; 12]); // extra, comment, disk, attributes
+        zip.extend_from_slice(&0u32.to_le_bytes()); // local header offset
+        zip.extend_from_slice(name);
+        let directory_size = zip.len() - directory_offset;
+
+        zip.extend_from_slice(&ZIP_EOCD);
+        zip.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        zip.extend_from_slice(&(directory_size as u32).to_le_bytes());
+        zip.extend_from_slice(&(directory_offset as u32).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip
+    }
+
+    #[test]
+    fn test_carve_zip_with_damaged_header() {
+        let archive = zip_archive(b"report.txt");
+        let mut data = vec![0xAA; 3000];
+        data.extend_from_slice(&archive);
+        data.extend_from_slice(&[0xBB; 500]);
+        // Wipe the local file header
+        data[3000..3010].fill(0);
+
+        // The header scan no longer sees it
+        assert!(detect_file_type(&data[3000..]).is_none());
+
+        let carved = carve_by_trailers(&data);
+        assert_eq!(
+            carved,
+            vec![CarvedFile {
+                offset: 3000,
+                size: archive.len() as u64,
+                mime_type: "application/zip".to_string(),
+                extension: "zip".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_carve_pdf_keeps_last_update() {
+        let mut data = vec![0u8; 100];
+        data.extend_from_slice(b"%PDF-1.4\n1 0 obj\n%%EOF\n2 0 obj\n%%EOF\n");
+        data.extend_from_slice(&[0u8; 50]);
+        // A trailer with no header before it is not carved
+        let mut orphan = vec![0u8; 20];
+        orphan.extend_from_slice(b"%%EOF");
+
+        let carved = carve_by_trailers(&data);
+        assert_eq!(carved.len(), 1);
+        assert_eq!(carved[0].offset, 100);
+        assert_eq!(carved[0].size, 37);
+        assert!(carve_by_trailers(&orphan).is_empty());
+    }
+
+    #[test]
+    fn test_docx_detection() {
+        let mut docx = zip_with_member(b"[Content_Types].xml");
+        docx.extend(zip_with_member(b"word/document.xml"));
+        let signature_match = signature_match_for(&docx, "application/zip");
+
+        let (mime_type, extension) = refine_file_type(&docx, &signature_match);
+        assert_eq!(
+            mime_type,
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        );
+        assert_eq!(extension.as_deref(), Some("docx"));
+
+        let zip = zip_with_member(b"notes.txt");
+        let (mime_type, extension) = refine_file_type(&zip, &signature_match);
+        assert_eq!(mime_type, "application/zip");
+        assert_eq!(extension.as_deref(), Some("zip"));
+    }
+
+    #[test]
+    fn test_pdf_page_count() {
+        let pdf = b"%PDF-1.7\n1 0 obj << /Type /Pages /Kids [2 0 R 3 0 R] >> endobj\n\
+            2 0 obj << /Type /Page >> endobj\n3 0 obj << /Type/Page >> endobj\n\
+            4 0 obj << /Producer (GhostWriter 2.1) >> endobj";
+        let signature_match = signature_match_for(pdf, "application/pdf");
+
+        let attributes = extract_content_metadata(pdf, &signature_match).to_attributes();
+        assert_eq!(attributes.get("page_count").map(String::as_str), Some("2"));
+        assert_eq!(
+            attributes.get("pdf_version").map(String::as_str),
+            Some("1.7")
+        );
+        assert_eq!(
+            attributes.get("creation_software").map(String::as_str),
+            Some("GhostWriter 2.1")
+        );
+    }
+
     #[test]
     fn test_entropy_calculation() {
         // Uniform distribution should have high entropy
         let uniform = (0..=255u8).collect::<Vec<_>>();
-        let entropy = calculate_entropy(&uniform);
+        let entropy = shannon_entropy(&uniform);
         assert!(entropy > 7.5); // Close to 8.0 for uniform distribution
 
         // All same bytes should have zero entropy
         let same_bytes = vec![0x42; 1000];
-        let entropy = calculate_entropy(&same_bytes);
+        let entropy = shannon_entropy(&same_bytes);
         assert!(entropy < 0.1);
     }
+
+    /// Deterministic bytes that are statistically random
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_entropy_of_random_zeros_and_text() {
+        let random = sample_entropy(&random_bytes(ENTROPY_SAMPLE_LEN));
+        let zeros = sample_entropy(&[0u8; ENTROPY_SAMPLE_LEN]);
+        let text = sample_entropy(
+            "It was the best of times, it was the worst of times, it was the age of \
+             wisdom, it was the age of foolishness, it was the epoch of belief."
+                .repeat(40)
+                .as_bytes(),
+        );
+
+        assert!(random >= RANDOM_ENTROPY_THRESHOLD, "random: {random}");
+        assert_eq!(zeros, 0.0);
+        assert!((3.5..5.0).contains(&text), "text: {text}");
+
+        assert_eq!(entropy_confidence_factor(text, None), 1.0);
+        assert!(entropy_confidence_factor(random, None) < 1.0);
+        assert!(entropy_confidence_factor(zeros, None) < 1.0);
+
+        // Compressed formats are near-random when intact, but not empty
+        let archive = Some("application/x-7z-compressed");
+        assert_eq!(entropy_confidence_factor(random, archive), 1.0);
+        assert_eq!(entropy_confidence_factor(random, Some("audio/flac")), 1.0);
+        assert!(entropy_confidence_factor(random, Some("image/jpeg")) < 1.0);
+        assert!(entropy_confidence_factor(zeros, archive) < 1.0);
+    }
+
+    #[test]
+    fn test_random_match_detection() {
+        let mut data = random_bytes(ENTROPY_SAMPLE_LEN);
+        data[..4].copy_from_slice(&[0x50, 0x4B, 0x03, 0x04]);
+        let zip = analyze_file_signature(&data, 1024)
+            .matches
+            .into_iter()
+            .find(|m| m.signature.mime_type == "application/zip")
+            .unwrap();
+        assert!(is_random_match(&data, &zip));
+
+        // A real local file header names its entry
+        data[26..28].copy_from_slice(&9u16.to_le_bytes());
+        data[30..39].copy_from_slice(b"notes.txt");
+        assert!(!is_random_match(&data, &zip));
+
+        // Short samples are never judged
+        assert!(!is_random_match(&data[..512], &zip));
+
+        // Nor are formats without a structure check, such as 7z, whose
+        // compressed streams start right after a short header
+        data[..6].copy_from_slice(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]);
+        let seven_zip = analyze_file_signature(&data, 1024)
+            .matches
+            .into_iter()
+            .find(|m| m.signature.mime_type == "application/x-7z-compressed")
+            .unwrap();
+        assert!(sample_entropy(&data) >= RANDOM_ENTROPY_THRESHOLD);
+        assert!(!is_random_match(&data, &seven_zip));
+    }
+
+    fn write_definitions(json: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, json.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_load_signature_definitions() {
+        let file = write_definitions(
+            r#"[
+                {"magic": "47 48 53 54", "mime_type": "application/x-ghost",
+                 "extensions": ["ghost"], "trailer": "454e44"},
+                {"magic": "cafe", "offset": 8, "mime_type": "application/x-cafe",
+                 "extensions": ["cafe"], "description": "Cafe archive"}
+            ]"#,
+        );
+        let signatures = load_definitions(file.path()).unwrap();
+
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[0].signature, b"GHST");
+        assert_eq!(signatures[0].offset, 0);
+        assert_eq!(signatures[0].description, "application/x-ghost");
+        assert_eq!(signatures[0].trailer.as_deref(), Some(b"END".as_slice()));
+        assert_eq!(signatures[1].signature, [0xCA, 0xFE]);
+        assert_eq!(signatures[1].offset, 8);
+        assert_eq!(signatures[1].trailer, None);
+    }
+
+    #[test]
+    fn test_reject_invalid_definitions() {
+        for (json, error) in [
+            (
+                r#"[{"magic": "4G", "mime_type": "a/b", "extensions": []}]"#,
+                "Invalid magic",
+            ),
+            (
+                r#"[{"magic": "474", "mime_type": "a/b", "extensions": []}]"#,
+                "Invalid magic",
+            ),
+            (
+                r#"[{"magic": "", "mime_type": "a/b", "extensions": []}]"#,
+                "Magic is empty",
+            ),
+            (
+                r#"[{"magic": "4748", "offset": 511, "mime_type": "a/b", "extensions": []}]"#,
+                "ends past byte 512",
+            ),
+            (
+                r#"[{"magic": "47", "mime_type": "a/b", "extensions": [], "trailer": "x"}]"#,
+                "Invalid trailer",
+            ),
+        ] {
+            let file = write_definitions(json);
+            let message = format!("{:#}", load_definitions(file.path()).unwrap_err());
+            assert!(message.contains("Signature 1"), "{}", message);
+            assert!(message.contains(error), "{}", message);
+        }
+
+        // Not an array of definitions
+        let file = write_definitions(r#"{"magic": "47"}"#);
+        assert!(load_definitions(file.path()).is_err());
+    }
 }
@@ -0,0 +1,208 @@
+/// Thumbnails of deleted photos
+///
+/// A gallery of what a device held is easier to triage than a list of
+/// names. The photo is read as recovery would write it and scaled down to a
+/// small JPEG.
+use anyhow::Result;
+
+use crate::fs::common::BlockDevice;
+use crate::{DeletedFile, FileSystemType};
+
+/// Largest photo a thumbnail is made of; bigger files are not read
+pub const MAX_THUMBNAIL_SOURCE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// JPEG thumbnail of a deleted JPEG or PNG photo, at most `max_dim` pixels
+/// on either side; `block_size` is the file system's, as for
+/// [`preview_file`](crate::preview_file)
+///
+/// A photo that is truncated or too damaged to decode fails with
+/// [`RecoveryError::Corrupted`](crate::recovery::RecoveryError::Corrupted) at
+/// its first byte, and other file types with
+/// [`RecoveryError::UnsupportedFeature`](crate::recovery::RecoveryError::UnsupportedFeature),
+/// so callers can show a placeholder for either.
+#[cfg(feature = "thumbnails")]
+pub fn generate_thumbnail(
+    file: &DeletedFile,
+    device: &BlockDevice,
+    fs_type: FileSystemType,
+    block_size: u32,
+    max_dim: u32,
+) -> Result<Vec<u8>> {
+    use crate::recovery::RecoveryError;
+    use image::ImageFormat;
+
+    let format = match file.metadata.mime_type.as_deref() {
+        Some("image/jpeg") => ImageFormat::Jpeg,
+        Some("image/png") => ImageFormat::Png,
+        other => {
+            return Err(RecoveryError::UnsupportedFeature(format!(
+                "thumbnails of {} files",
+                other.unwrap_or("untyped")
+            ))
+            .into())
+        }
+    };
+    if file.size > MAX_THUMBNAIL_SOURCE_BYTES {
+        return Err(RecoveryError::UnsupportedFeature(format!(
+            "thumbnails of photos over {} bytes",
+            MAX_THUMBNAIL_SOURCE_BYTES
+        ))
+        .into());
+    }
+
+    let mut data = Vec::with_capacity(file.size as usize);
+    crate::copy_file_data(
+        file,
+        device,
+        fs_type,
+        block_size,
+        file.size,
+        &mut data,
+        &mut Vec::new(),
+    )?;
+
+    let photo = image::load_from_memory_with_format(&data, format).map_err(|e| {
+        let offset = match file.inline_data() {
+            Some(_) => 0,
+            None => file.data_blocks.first().map_or(0, |range| {
+                range.start_block * crate::block_offset_multiplier(fs_type, block_size)
+            }),
+        };
+        RecoveryError::corrupted(
+            offset,
+            format!("undecodable photo in file {}: {}", file.id, e),
+        )
+    })?;
+
+    let mut thumbnail = Vec::new();
+    photo
+        .thumbnail(max_dim, max_dim)
+        .to_rgb8()
+        .write_to(&mut std::io::Cursor::new(&mut thumbnail), ImageFormat::Jpeg)?;
+    Ok(thumbnail)
+}
+
+/// Thumbnail of a deleted photo; thumbnails are disabled in this build
+#[cfg(not(feature = "thumbnails"))]
+pub fn generate_thumbnail(
+    _file: &DeletedFile,
+    _device: &BlockDevice,
+    _fs_type: FileSystemType,
+    _block_size: u32,
+    _max_dim: u32,
+) -> Result<Vec<u8>> {
+    Err(crate::recovery::RecoveryError::UnsupportedFeature(
+        "thumbnails require the thumbnails feature".to_string(),
+    )
+    .into())
+}
+
+#[cfg(all(test, feature = "thumbnails"))]
+mod tests {
+    use super::*;
+    use crate::recovery::RecoveryError;
+    use crate::{BlockRange, FileMetadata, FileType};
+    use std::collections::HashMap;
+
+    /// A 64x32 gradient encoded as JPEG
+    fn jpeg() -> Vec<u8> {
+        let photo =
+            image::RgbImage::from_fn(64, 32, |x, y| image::Rgb([x as u8 * 4, y as u8 * 8, 128]));
+        let mut data = Vec::new();
+        photo
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        data
+    }
+
+    /// An exFAT file of `size` bytes at device byte 4096
+    fn photo_file(size: u64) -> DeletedFile {
+        DeletedFile {
+            id: 7,
+            inode_or_cluster: 3,
+            original_path: Some("/DCIM/IMG_0007.JPG".into()),
+            size,
+            deletion_time: None,
+            confidence_score: 0.9,
+            file_type: FileType::RegularFile,
+            data_blocks: vec![BlockRange {
+                start_block: 4096,
+                block_count: size,
+                is_allocated: false,
+            }],
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: Some("image/jpeg".to_string()),
+                file_extension: Some("jpg".to_string()),
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        }
+    }
+
+    fn device_with(data: &[u8]) -> BlockDevice {
+        let mut image = vec![0u8; 4096 + data.len()];
+        image[4096..].copy_from_slice(data);
+        BlockDevice::from_vec(image)
+    }
+
+    #[test]
+    fn test_thumbnail_of_small_jpeg() {
+        let jpeg = jpeg();
+        let device = device_with(&jpeg);
+
+        let thumbnail = generate_thumbnail(
+            &photo_file(jpeg.len() as u64),
+            &device,
+            FileSystemType::ExFat,
+            4096,
+            16,
+        )
+        .unwrap();
+
+        assert_eq!(&thumbnail[..3], &[0xFF, 0xD8, 0xFF]);
+        let decoded = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+    }
+
+    #[test]
+    fn test_truncated_photo_is_reported_corrupted() {
+        let jpeg = jpeg();
+        let truncated = &jpeg[..jpeg.len() / 3];
+        let device = device_with(truncated);
+
+        let error = generate_thumbnail(
+            &photo_file(truncated.len() as u64),
+            &device,
+            FileSystemType::ExFat,
+            4096,
+            16,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<RecoveryError>(),
+            Some(RecoveryError::Corrupted { offset: 4096, .. })
+        ));
+
+        let mut document = photo_file(jpeg.len() as u64);
+        document.metadata.mime_type = Some("application/pdf".to_string());
+        let error =
+            generate_thumbnail(&document, &device, FileSystemType::ExFat, 4096, 16).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<RecoveryError>(),
+            Some(RecoveryError::UnsupportedFeature(_))
+        ));
+    }
+}
@@ -0,0 +1,1045 @@
+/// SQLite persistence for recovery sessions
+///
+/// Each session is stored as one row in `sessions`: the fields needed for
+/// listing and filtering are kept in columns, while the session metadata and
+/// the full `scan_results` are kept as JSON so a loaded session is identical
+/// to the one that was saved.
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::{recovery::ScanCheckpoint, DeletedFile, FileSystemType, RecoverySession};
+
+/// File name of the session database inside the data directory
+const DEFAULT_DB_NAME: &str = "sessions.db";
+
+/// Devices listed in `AggregateStats::top_devices`
+const TOP_DEVICES: usize = 10;
+
+/// Current schema version, i.e. the number of entries in `MIGRATIONS`
+pub const SCHEMA_VERSION: u32 = 5;
+
+/// Ordered schema migrations; entry `n` upgrades the schema to version `n + 1`.
+/// Append new steps, never edit existing ones.
+const MIGRATIONS: &[&str] = &[
+    // v1: initial session table
+    "CREATE TABLE IF NOT EXISTS sessions (
+        id TEXT PRIMARY KEY,
+        fs_type TEXT NOT NULL,
+        device_path TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        confidence_threshold REAL NOT NULL,
+        total_scanned INTEGER NOT NULL,
+        files_found INTEGER NOT NULL,
+        recoverable_files INTEGER NOT NULL,
+        metadata_json TEXT NOT NULL,
+        scan_results_json TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_sessions_created_at ON sessions(created_at);",
+    // v2: scan timing and filesystem health score
+    "ALTER TABLE sessions ADD COLUMN scan_duration_ms INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE sessions ADD COLUMN health_score REAL;",
+    // v3: per-file index over scan results, backfilled from the JSON blob
+    "CREATE TABLE IF NOT EXISTS files (
+        session_id TEXT NOT NULL,
+        file_id INTEGER NOT NULL,
+        inode_or_cluster INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        confidence REAL NOT NULL,
+        mime_type TEXT,
+        is_recoverable INTEGER NOT NULL,
+        PRIMARY KEY (session_id, file_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_files_mime_type ON files(mime_type);
+    CREATE INDEX IF NOT EXISTS idx_files_confidence ON files(confidence);
+    CREATE INDEX IF NOT EXISTS idx_files_size ON files(size);
+    INSERT OR REPLACE INTO files
+        SELECT s.id,
+               json_extract(f.value, '$.id'),
+               json_extract(f.value, '$.inode_or_cluster'),
+               json_extract(f.value, '$.size'),
+               json_extract(f.value, '$.confidence_score'),
+               json_extract(f.value, '$.metadata.mime_type'),
+               json_extract(f.value, '$.is_recoverable')
+        FROM sessions s, json_each(s.scan_results_json) f;",
+    // v4: checkpoints of interrupted scans
+    "CREATE TABLE IF NOT EXISTS checkpoints (
+        session_id TEXT PRIMARY KEY,
+        device_path TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        checkpoint_json TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_checkpoints_device_path ON checkpoints(device_path);",
+    // v5: case identity of forensic sessions
+    "ALTER TABLE sessions ADD COLUMN case_metadata_json TEXT;",
+];
+
+/// Lightweight view of a stored session for listings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub id: Uuid,
+    pub fs_type: FileSystemType,
+    pub device_path: PathBuf,
+    pub created_at: DateTime<Utc>,
+    pub files_found: u32,
+    pub recoverable_files: u32,
+}
+
+/// Filter for querying indexed files across sessions; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Restrict to a single session
+    pub session_id: Option<Uuid>,
+    /// MIME type prefix, e.g. `"image/"` or `"image/jpeg"`
+    pub mime_prefix: Option<String>,
+    pub min_confidence: Option<f32>,
+    pub max_confidence: Option<f32>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub recoverable_only: bool,
+}
+
+impl FileFilter {
+    /// Whether `file` passes every predicate except `session_id`, which files don't carry
+    pub fn matches(&self, file: &DeletedFile) -> bool {
+        let mime_matches = match &self.mime_prefix {
+            Some(prefix) => file
+                .metadata
+                .mime_type
+                .as_deref()
+                .is_some_and(|mime| mime.starts_with(prefix.as_str())),
+            None => true,
+        };
+
+        mime_matches
+            && self
+                .min_confidence
+                .is_none_or(|min| file.confidence_score >= min)
+            && self
+                .max_confidence
+                .is_none_or(|max| file.confidence_score <= max)
+            && self.min_size.is_none_or(|min| file.size >= min)
+            && self.max_size.is_none_or(|max| file.size <= max)
+            && (!self.recoverable_only || file.is_recoverable)
+    }
+}
+
+/// Indexed fields of a deleted file, as returned by `SessionDatabase::query_files`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeletedFileSummary {
+    pub session_id: Uuid,
+    pub file_id: u64,
+    pub inode_or_cluster: u64,
+    pub size: u64,
+    pub confidence_score: f32,
+    pub mime_type: Option<String>,
+    pub is_recoverable: bool,
+}
+
+/// Totals across every stored session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AggregateStats {
+    pub sessions: u64,
+    pub files_found: u64,
+    pub recoverable_files: u64,
+    /// Per file system type, ordered by type name
+    pub by_fs_type: Vec<FsTypeStats>,
+    /// Indexed files per MIME top-level type (`"image"`, `"application"`, ...),
+    /// with files of unknown type under `"unknown"`
+    pub by_mime_category: BTreeMap<String, u64>,
+    /// Mean confidence of all indexed files, `None` if there are none
+    pub average_confidence: Option<f32>,
+    /// Most often scanned devices, most scans first
+    pub top_devices: Vec<DeviceScanCount>,
+}
+
+/// Session totals for one file system type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FsTypeStats {
+    pub fs_type: FileSystemType,
+    pub sessions: u64,
+    pub files_found: u64,
+    pub recoverable_files: u64,
+}
+
+/// How often a device was scanned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceScanCount {
+    pub device_path: PathBuf,
+    pub sessions: u64,
+    pub last_scanned: DateTime<Utc>,
+}
+
+/// SQLite-backed store of recovery sessions
+pub struct SessionDatabase {
+    conn: Connection,
+}
+
+impl SessionDatabase {
+    /// Open (or create) the session database at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open session database {}", path.display()))?;
+        let db = Self { conn };
+        db.initialize_schema()?;
+        Ok(db)
+    }
+
+    /// Open a throwaway in-memory database
+    pub fn open_in_memory() -> Result<Self> {
+        let db = Self {
+            conn: Connection::open_in_memory()?,
+        };
+        db.initialize_schema()?;
+        Ok(db)
+    }
+
+    /// Default database location: `$XDG_DATA_HOME/ghostfs` or `~/.local/share/ghostfs`,
+    /// falling back to the working directory when no home is known
+    pub fn default_path() -> PathBuf {
+        let data_dir = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            });
+
+        match data_dir {
+            Some(dir) => dir.join("ghostfs").join(DEFAULT_DB_NAME),
+            None => PathBuf::from(DEFAULT_DB_NAME),
+        }
+    }
+
+    /// Bring the schema up to date by applying every pending migration in order
+    fn initialize_schema(&self) -> Result<()> {
+        self.migrate_to(SCHEMA_VERSION)
+    }
+
+    fn migrate_to(&self, target: u32) -> Result<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);",
+        )?;
+
+        let current = self.schema_version()?;
+        if current > SCHEMA_VERSION {
+            bail!(
+                "Session database schema version {} is newer than supported version {}",
+                current,
+                SCHEMA_VERSION
+            );
+        }
+
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            let version = index as u32 + 1;
+            if version <= current || version > target {
+                continue;
+            }
+
+            let tx = self.conn.unchecked_transaction()?;
+            tx.execute_batch(migration)
+                .with_context(|| format!("Session database migration {} failed", version))?;
+            tx.execute("DELETE FROM schema_version", [])?;
+            tx.execute(
+                "INSERT INTO schema_version (version) VALUES (?1)",
+                params![version],
+            )?;
+            tx.commit()?;
+
+            tracing::debug!("Migrated session database to schema version {}", version);
+        }
+
+        Ok(())
+    }
+
+    /// Schema version recorded in the database (0 for a fresh database)
+    pub fn schema_version(&self) -> Result<u32> {
+        let version = self
+            .conn
+            .query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+                row.get::<_, Option<u32>>(0)
+            })
+            .optional()?
+            .flatten();
+        Ok(version.unwrap_or(0))
+    }
+
+    /// Insert or replace a session and its per-file index
+    pub fn save_session(&self, session: &RecoverySession) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO sessions (
+                id, fs_type, device_path, created_at, confidence_threshold, total_scanned,
+                files_found, recoverable_files, metadata_json, scan_results_json, scan_duration_ms,
+                health_score, case_metadata_json
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                session.id.to_string(),
+                fs_type_name(session.fs_type),
+                session.device_path.to_string_lossy(),
+                format_timestamp(&session.created_at),
+                session.confidence_threshold,
+                session.total_scanned as i64,
+                session.metadata.files_found,
+                session.metadata.recoverable_files,
+                serde_json::to_string(&session.metadata)?,
+                serde_json::to_string(&session.scan_results)?,
+                session.metadata.scan_duration_ms as i64,
+                session
+                    .metadata
+                    .filesystem_health
+                    .map(|health| health.recovery_prospects),
+                session
+                    .case_metadata
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+            ],
+        )?;
+
+        let session_id = session.id.to_string();
+        tx.execute(
+            "DELETE FROM files WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO files (
+                    session_id, file_id, inode_or_cluster, size, confidence, mime_type, is_recoverable
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for file in &session.scan_results {
+                insert.execute(params![
+                    session_id,
+                    file.id as i64,
+                    file.inode_or_cluster as i64,
+                    file.size as i64,
+                    file.confidence_score,
+                    file.metadata.mime_type,
+                    file.is_recoverable,
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        tracing::debug!("Saved session {} to database", session.id);
+        Ok(())
+    }
+
+    /// Insert or replace the checkpoint of an in-progress scan of `device_path`
+    pub fn save_checkpoint(&self, device_path: &Path, checkpoint: &ScanCheckpoint) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO checkpoints (session_id, device_path, updated_at, checkpoint_json)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                checkpoint.session_id,
+                device_path.to_string_lossy(),
+                format_timestamp(&checkpoint.saved_at),
+                serde_json::to_string(checkpoint)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent checkpoint of an interrupted scan of `device_path`, if any
+    pub fn load_checkpoint(&self, device_path: &Path) -> Result<Option<ScanCheckpoint>> {
+        let json = self
+            .conn
+            .query_row(
+                "SELECT checkpoint_json FROM checkpoints WHERE device_path = ?1
+                 ORDER BY updated_at DESC LIMIT 1",
+                params![device_path.to_string_lossy()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        json.map(|json| serde_json::from_str(&json).context("Corrupt scan checkpoint"))
+            .transpose()
+    }
+
+    /// Remove the checkpoint of a scan that has finished
+    pub fn delete_checkpoint(&self, session_id: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM checkpoints WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Load a session by full ID or unique ID prefix
+    pub fn load_session(&self, id: &str) -> Result<RecoverySession> {
+        let id = self.resolve_id(id)?;
+
+        let row = self.conn.query_row(
+            "SELECT id, fs_type, device_path, created_at, confidence_threshold, total_scanned,
+                    metadata_json, scan_results_json, case_metadata_json
+             FROM sessions WHERE id = ?1",
+            params![id.to_string()],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, f32>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, String>(7)?,
+                    row.get::<_, Option<String>>(8)?,
+                ))
+            },
+        )?;
+
+        Ok(RecoverySession {
+            id: Uuid::parse_str(&row.0)?,
+            fs_type: parse_fs_type(&row.1)?,
+            device_path: PathBuf::from(row.2),
+            created_at: parse_timestamp(&row.3)?,
+            confidence_threshold: row.4,
+            total_scanned: row.5 as u64,
+            metadata: serde_json::from_str(&row.6)?,
+            scan_results: serde_json::from_str(&row.7)?,
+            case_metadata: row
+                .8
+                .map(|json| serde_json::from_str(&json))
+                .transpose()
+                .context("Corrupt case metadata")?,
+        })
+    }
+
+    /// Whether a session with this exact ID is stored
+    pub fn session_exists(&self, id: &Uuid) -> Result<bool> {
+        let found = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM sessions WHERE id = ?1",
+                params![id.to_string()],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    /// Summaries of all stored sessions, newest first
+    pub fn list_sessions(&self) -> Result<Vec<SessionSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, fs_type, device_path, created_at, files_found, recoverable_files
+             FROM sessions ORDER BY created_at DESC",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, u32>(4)?,
+                row.get::<_, u32>(5)?,
+            ))
+        })?;
+
+        let mut summaries = Vec::new();
+        for row in rows {
+            let (id, fs_type, device_path, created_at, files_found, recoverable_files) = row?;
+            summaries.push(SessionSummary {
+                id: Uuid::parse_str(&id)?,
+                fs_type: parse_fs_type(&fs_type)?,
+                device_path: PathBuf::from(device_path),
+                created_at: parse_timestamp(&created_at)?,
+                files_found,
+                recoverable_files,
+            });
+        }
+
+        Ok(summaries)
+    }
+
+    /// Delete a session by full ID or unique ID prefix
+    pub fn delete_session(&self, id: &str) -> Result<Uuid> {
+        let id = self.resolve_id(id)?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM files WHERE session_id = ?1",
+            params![id.to_string()],
+        )?;
+        tx.execute(
+            "DELETE FROM sessions WHERE id = ?1",
+            params![id.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Delete sessions created more than `days` days ago, returning how many were removed
+    pub fn cleanup_old_sessions(&self, days: u32) -> Result<usize> {
+        // A cutoff before the earliest representable time matches no session
+        let Some(cutoff) =
+            Duration::try_days(days as i64).and_then(|age| Utc::now().checked_sub_signed(age))
+        else {
+            return Ok(0);
+        };
+        let cutoff = format_timestamp(&cutoff);
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM files WHERE session_id IN (SELECT id FROM sessions WHERE created_at < ?1)",
+            params![cutoff],
+        )?;
+        let removed = tx.execute(
+            "DELETE FROM sessions WHERE created_at < ?1",
+            params![cutoff],
+        )?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// Query indexed files across all stored sessions
+    pub fn query_files(&self, filter: &FileFilter) -> Result<Vec<DeletedFileSummary>> {
+        let mut sql = String::from(
+            "SELECT session_id, file_id, inode_or_cluster, size, confidence, mime_type, is_recoverable
+             FROM files WHERE 1 = 1",
+        );
+        let mut values: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        if let Some(session_id) = filter.session_id {
+            values.push(Box::new(session_id.to_string()));
+            sql.push_str(&format!(" AND session_id = ?{}", values.len()));
+        }
+        if let Some(prefix) = &filter.mime_prefix {
+            values.push(Box::new(prefix.clone()));
+            let n = values.len();
+            sql.push_str(&format!(" AND substr(mime_type, 1, length(?{n})) = ?{n}"));
+        }
+        if let Some(min_confidence) = filter.min_confidence {
+            values.push(Box::new(min_confidence));
+            sql.push_str(&format!(" AND confidence >= ?{}", values.len()));
+        }
+        if let Some(max_confidence) = filter.max_confidence {
+            values.push(Box::new(max_confidence));
+            sql.push_str(&format!(" AND confidence <= ?{}", values.len()));
+        }
+        if let Some(min_size) = filter.min_size {
+            values.push(Box::new(min_size as i64));
+            sql.push_str(&format!(" AND size >= ?{}", values.len()));
+        }
+        if let Some(max_size) = filter.max_size {
+            values.push(Box::new(max_size.min(i64::MAX as u64) as i64));
+            sql.push_str(&format!(" AND size <= ?{}", values.len()));
+        }
+        if filter.recoverable_only {
+            sql.push_str(" AND is_recoverable = 1");
+        }
+        sql.push_str(" ORDER BY session_id, file_id");
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                DeletedFileSummary {
+                    session_id: Uuid::nil(),
+                    file_id: row.get::<_, i64>(1)? as u64,
+                    inode_or_cluster: row.get::<_, i64>(2)? as u64,
+                    size: row.get::<_, i64>(3)? as u64,
+                    confidence_score: row.get(4)?,
+                    mime_type: row.get(5)?,
+                    is_recoverable: row.get(6)?,
+                },
+            ))
+        })?;
+
+        let mut files = Vec::new();
+        for row in rows {
+            let (session_id, mut summary) = row?;
+            summary.session_id = Uuid::parse_str(&session_id)?;
+            files.push(summary);
+        }
+        Ok(files)
+    }
+
+    /// Totals across all stored sessions
+    ///
+    /// Computed in SQL from the session columns and the `files` index, so no
+    /// session is deserialized.
+    pub fn aggregate_statistics(&self) -> Result<AggregateStats> {
+        let mut stats = self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(files_found), 0), COALESCE(SUM(recoverable_files), 0)
+             FROM sessions",
+            [],
+            |row| {
+                Ok(AggregateStats {
+                    sessions: row.get::<_, i64>(0)? as u64,
+                    files_found: row.get::<_, i64>(1)? as u64,
+                    recoverable_files: row.get::<_, i64>(2)? as u64,
+                    ..Default::default()
+                })
+            },
+        )?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT fs_type, COUNT(*), SUM(files_found), SUM(recoverable_files)
+             FROM sessions GROUP BY fs_type ORDER BY fs_type",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (fs_type, sessions, files_found, recoverable_files) = row?;
+            stats.by_fs_type.push(FsTypeStats {
+                fs_type: parse_fs_type(&fs_type)?,
+                sessions: sessions as u64,
+                files_found: files_found as u64,
+                recoverable_files: recoverable_files as u64,
+            });
+        }
+
+        // "image/jpeg" -> "image"; NULL and slash-less types are unknown
+        let mut stmt = self.conn.prepare(
+            "SELECT COALESCE(NULLIF(substr(mime_type, 1, instr(mime_type, '/') - 1), ''), 'unknown'),
+                    COUNT(*)
+             FROM files GROUP BY 1",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (category, count) = row?;
+            *stats.by_mime_category.entry(category).or_default() += count as u64;
+        }
+
+        stats.average_confidence = self
+            .conn
+            .query_row("SELECT AVG(confidence) FROM files", [], |row| {
+                row.get::<_, Option<f64>>(0)
+            })?
+            .map(|average| average as f32);
+
+        let mut stmt = self.conn.prepare(
+            "SELECT device_path, COUNT(*), MAX(created_at) FROM sessions
+             GROUP BY device_path ORDER BY COUNT(*) DESC, device_path LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![TOP_DEVICES as i64], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (device_path, sessions, last_scanned) = row?;
+            stats.top_devices.push(DeviceScanCount {
+                device_path: PathBuf::from(device_path),
+                sessions: sessions as u64,
+                last_scanned: parse_timestamp(&last_scanned)?,
+            });
+        }
+
+        Ok(stats)
+    }
+
+    /// Resolve a full session ID or a unique prefix of one
+    pub fn resolve_id(&self, id_or_prefix: &str) -> Result<Uuid> {
+        if let Ok(id) = Uuid::parse_str(id_or_prefix) {
+            if self.session_exists(&id)? {
+                return Ok(id);
+            }
+            bail!("Session {} not found", id);
+        }
+
+        let prefix = id_or_prefix.to_lowercase();
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            bail!("Invalid session ID: {}", id_or_prefix);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM sessions WHERE id LIKE ?1 || '%' LIMIT 2")?;
+        let matches: Vec<String> = stmt
+            .query_map(params![prefix], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        match matches.as_slice() {
+            [id] => Ok(Uuid::parse_str(id)?),
+            [] => bail!("Session {} not found", id_or_prefix),
+            _ => bail!("Session ID prefix {} is ambiguous", id_or_prefix),
+        }
+    }
+}
+
+fn fs_type_name(fs_type: FileSystemType) -> &'static str {
+    match fs_type {
+        FileSystemType::Xfs => "xfs",
+        FileSystemType::Btrfs => "btrfs",
+        FileSystemType::ExFat => "exfat",
+    }
+}
+
+fn parse_fs_type(name: &str) -> Result<FileSystemType> {
+    match name {
+        "xfs" => Ok(FileSystemType::Xfs),
+        "btrfs" => Ok(FileSystemType::Btrfs),
+        "exfat" => Ok(FileSystemType::ExFat),
+        other => bail!("Unknown filesystem type in session database: {}", other),
+    }
+}
+
+/// Fixed-width UTC timestamps sort lexicographically, so SQL comparisons work
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{FileMetadata, FileType, SessionMetadata};
+    use std::collections::HashMap;
+
+    pub(crate) fn sample_session(files: usize) -> RecoverySession {
+        let scan_results = (0..files as u64)
+            .map(|i| DeletedFile {
+                id: i + 1,
+                inode_or_cluster: 100 + i,
+                original_path: Some(PathBuf::from(format!("/home/user/photo_{}.jpg", i))),
+                size: 4096 * (i + 1),
+                deletion_time: Some(Utc::now()),
+                confidence_score: 0.85,
+                file_type: FileType::RegularFile,
+                data_blocks: vec![],
+                is_recoverable: true,
+                metadata: FileMetadata {
+                    mime_type: Some("image/jpeg".to_string()),
+                    file_extension: Some("jpg".to_string()),
+                    permissions: Some(0o644),
+                    owner_uid: Some(1000),
+                    owner_gid: Some(1000),
+                    owner_name: None,
+                    group_name: None,
+                    created_time: None,
+                    modified_time: None,
+                    accessed_time: None,
+                    extended_attributes: HashMap::new(),
+                },
+                fs_metadata: None,
+                skip_reason: None,
+            })
+            .collect();
+
+        RecoverySession {
+            id: Uuid::new_v4(),
+            fs_type: FileSystemType::ExFat,
+            device_path: PathBuf::from("/images/card.img"),
+            created_at: Utc::now(),
+            scan_results,
+            total_scanned: files as u64,
+            confidence_threshold: 0.4,
+            metadata: SessionMetadata {
+                device_size: 64 * 1024 * 1024,
+                filesystem_size: 64 * 1024 * 1024,
+                block_size: 4096,
+                scan_duration_ms: 1234,
+                files_found: files as u32,
+                recoverable_files: files as u32,
+                filesystem_health: Some(crate::fs::FilesystemHealth::from_superblock(
+                    FileSystemType::ExFat,
+                    64 * 1024 * 1024,
+                    Some(0.5),
+                    true,
+                )),
+            },
+            case_metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let db = SessionDatabase::open_in_memory().unwrap();
+        let session = sample_session(3);
+        db.save_session(&session).unwrap();
+
+        let loaded = db.load_session(&session.id.to_string()).unwrap();
+        assert_eq!(loaded.id, session.id);
+        assert_eq!(loaded.fs_type, session.fs_type);
+        assert_eq!(loaded.created_at, session.created_at);
+        assert_eq!(loaded.metadata.scan_duration_ms, 1234);
+        assert_eq!(
+            loaded.metadata.filesystem_health,
+            session.metadata.filesystem_health
+        );
+        assert_eq!(loaded.scan_results.len(), 3);
+        assert_eq!(
+            loaded.scan_results[2].original_path,
+            session.scan_results[2].original_path
+        );
+    }
+
+    #[test]
+    fn test_case_metadata_round_trip() {
+        let db = SessionDatabase::open_in_memory().unwrap();
+        let mut session = sample_session(1);
+        let case = crate::forensics::CaseMetadata {
+            examiner: Some("J. Doe".to_string()),
+            case_number: Some("2024-0117".to_string()),
+            evidence_id: Some("EV-3".to_string()),
+            acquisition_hash: Some("ab".repeat(32)),
+        };
+        session.case_metadata = Some(case.clone());
+        db.save_session(&session).unwrap();
+
+        let loaded = db.load_session(&session.id.to_string()).unwrap();
+        assert_eq!(loaded.case_metadata, Some(case));
+
+        let plain = sample_session(1);
+        db.save_session(&plain).unwrap();
+        let loaded = db.load_session(&plain.id.to_string()).unwrap();
+        assert_eq!(loaded.case_metadata, None);
+    }
+
+    #[test]
+    fn test_short_id_prefix_resolution() {
+        let db = SessionDatabase::open_in_memory().unwrap();
+        let session = sample_session(1);
+        db.save_session(&session).unwrap();
+
+        let prefix = &session.id.to_string()[..8];
+        assert_eq!(db.load_session(prefix).unwrap().id, session.id);
+        assert!(db.load_session("ffffffff-0000").is_err());
+        assert!(db.load_session("not-hex!").is_err());
+    }
+
+    #[test]
+    fn test_list_delete_and_cleanup() {
+        let db = SessionDatabase::open_in_memory().unwrap();
+        let recent = sample_session(1);
+        let mut old = sample_session(2);
+        old.created_at = Utc::now() - Duration::days(90);
+        db.save_session(&recent).unwrap();
+        db.save_session(&old).unwrap();
+
+        let summaries = db.list_sessions().unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].id, recent.id); // Newest first
+        assert_eq!(summaries[1].files_found, 2);
+
+        assert_eq!(db.cleanup_old_sessions(u32::MAX).unwrap(), 0);
+        assert_eq!(db.cleanup_old_sessions(30).unwrap(), 1);
+        assert!(!db.session_exists(&old.id).unwrap());
+
+        assert_eq!(
+            db.delete_session(&recent.id.to_string()).unwrap(),
+            recent.id
+        );
+        assert!(db.list_sessions().unwrap().is_empty());
+    }
+
+    fn column_names(db: &SessionDatabase, table: &str) -> Vec<String> {
+        let mut stmt = db
+            .conn
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap();
+        stmt.query_map([], |row| row.get::<_, String>(1))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migrates_v1_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions.db");
+        let session = sample_session(2);
+
+        // Build a v1 database with one session written by the v1 schema
+        {
+            let db = SessionDatabase {
+                conn: Connection::open(&path).unwrap(),
+            };
+            db.migrate_to(1).unwrap();
+            assert_eq!(db.schema_version().unwrap(), 1);
+            assert!(!column_names(&db, "sessions").contains(&"scan_duration_ms".to_string()));
+
+            db.conn
+                .execute(
+                    "INSERT INTO sessions (
+                        id, fs_type, device_path, created_at, confidence_threshold, total_scanned,
+                        files_found, recoverable_files, metadata_json, scan_results_json
+                    ) VALUES (?1, 'exfat', '/images/card.img', ?2, 0.4, 2, 2, 2, ?3, ?4)",
+                    params![
+                        session.id.to_string(),
+                        format_timestamp(&session.created_at),
+                        serde_json::to_string(&session.metadata).unwrap(),
+                        serde_json::to_string(&session.scan_results).unwrap(),
+                    ],
+                )
+                .unwrap();
+        }
+
+        let db = SessionDatabase::open(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+
+        let columns = column_names(&db, "sessions");
+        assert!(columns.contains(&"scan_duration_ms".to_string()));
+        assert!(columns.contains(&"health_score".to_string()));
+        assert!(columns.contains(&"case_metadata_json".to_string()));
+
+        let loaded = db.load_session(&session.id.to_string()).unwrap();
+        assert_eq!(loaded.scan_results.len(), 2);
+        assert_eq!(loaded.case_metadata, None);
+
+        // v1 sessions are backfilled into the per-file index
+        let indexed = db.query_files(&FileFilter::default()).unwrap();
+        assert_eq!(indexed.len(), 2);
+        assert_eq!(indexed[1].size, session.scan_results[1].size);
+        assert_eq!(indexed[1].mime_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(loaded.created_at, session.created_at);
+
+        // Reopening an up-to-date database is a no-op
+        drop(db);
+        let db = SessionDatabase::open(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        db.save_session(&sample_session(1)).unwrap();
+        assert_eq!(db.list_sessions().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_newer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions.db");
+        drop(SessionDatabase::open(&path).unwrap());
+
+        let conn = Connection::open(&path).unwrap();
+        conn.execute(
+            "UPDATE schema_version SET version = ?1",
+            params![SCHEMA_VERSION + 1],
+        )
+        .unwrap();
+        drop(conn);
+
+        assert!(SessionDatabase::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_query_files_across_sessions() {
+        let db = SessionDatabase::open_in_memory().unwrap();
+
+        let mut photos = sample_session(3); // JPEGs of 4K, 8K, 12K
+        photos.scan_results[2].confidence_score = 0.3;
+        photos.scan_results[2].is_recoverable = false;
+
+        let mut documents = sample_session(2);
+        documents.scan_results[0].metadata.mime_type = Some("application/pdf".to_string());
+        documents.scan_results[1].size = 1024 * 1024;
+
+        db.save_session(&photos).unwrap();
+        db.save_session(&documents).unwrap();
+
+        let jpegs = db
+            .query_files(&FileFilter {
+                mime_prefix: Some("image/".to_string()),
+                recoverable_only: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(jpegs.len(), 3);
+        assert!(jpegs
+            .iter()
+            .all(|f| f.mime_type.as_deref() == Some("image/jpeg") && f.is_recoverable));
+        assert_eq!(
+            jpegs
+                .iter()
+                .filter(|f| f.session_id == documents.id)
+                .count(),
+            1
+        );
+
+        let confident = db
+            .query_files(&FileFilter {
+                min_confidence: Some(0.5),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(confident.len(), 4);
+
+        let sized = db
+            .query_files(&FileFilter {
+                min_size: Some(8192),
+                max_size: Some(64 * 1024),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(sized.len(), 2);
+        assert!(sized.iter().all(|f| f.session_id == photos.id));
+
+        // Re-saving replaces the index rather than duplicating it
+        db.save_session(&photos).unwrap();
+        assert_eq!(db.query_files(&FileFilter::default()).unwrap().len(), 5);
+
+        db.delete_session(&photos.id.to_string()).unwrap();
+        let remaining = db.query_files(&FileFilter::default()).unwrap();
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.iter().all(|f| f.session_id == documents.id));
+    }
+
+    #[test]
+    fn test_file_filter_matches_mixed_session() {
+        let mut session = sample_session(4); // JPEGs of 4K, 8K, 12K, 16K
+        session.scan_results[1].metadata.mime_type = Some("image/png".to_string());
+        session.scan_results[2].metadata.mime_type = Some("application/pdf".to_string());
+        session.scan_results[3].metadata.mime_type = None;
+        session.scan_results[0].confidence_score = 0.5;
+
+        let matching = |filter: FileFilter| -> Vec<u64> {
+            session
+                .scan_results
+                .iter()
+                .filter(|f| filter.matches(f))
+                .map(|f| f.id)
+                .collect()
+        };
+
+        let images = FileFilter {
+            mime_prefix: Some("image/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(matching(images), vec![1, 2]);
+
+        let jpegs = FileFilter {
+            mime_prefix: Some("image/jpeg".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(matching(jpegs), vec![1]);
+
+        let confident_images = FileFilter {
+            mime_prefix: Some("image/".to_string()),
+            min_confidence: Some(0.7),
+            ..Default::default()
+        };
+        assert_eq!(matching(confident_images), vec![2]);
+
+        let doubtful = FileFilter {
+            max_confidence: Some(0.6),
+            ..Default::default()
+        };
+        assert_eq!(matching(doubtful), vec![1]);
+
+        let sized = FileFilter {
+            min_size: Some(8192),
+            max_size: Some(12288),
+            ..Default::default()
+        };
+        assert_eq!(matching(sized), vec![2, 3]);
+        assert_eq!(matching(FileFilter::default()), vec![1, 2, 3, 4]);
+    }
+}
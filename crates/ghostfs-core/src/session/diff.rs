@@ -0,0 +1,246 @@
+/// Comparing two scans of a device taken at different times
+///
+/// Media that stays in use keeps losing data: more files are deleted, and
+/// the blocks of deleted files are reused. Diffing an older scan against a
+/// newer one shows both. Files are matched by inode (or exFAT cluster)
+/// number; carved files, which have none, by the block they start at.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::{DeletedFile, RecoverySession};
+
+/// Smallest confidence change reported by `RecoverySession::diff`
+const CONFIDENCE_TOLERANCE: f32 = 0.001;
+
+/// Outcome of `RecoverySession::diff`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionDiff {
+    /// Files only the newer scan found, i.e. deleted in between
+    pub newly_deleted: Vec<FileChange>,
+    /// Files recoverable in the older scan that the newer one found
+    /// unrecoverable or no longer found, usually because they were overwritten
+    pub became_unrecoverable: Vec<FileChange>,
+    /// Other files found by both scans whose confidence changed, or that
+    /// became recoverable
+    pub confidence_changed: Vec<FileChange>,
+    /// Files found by both scans with the same recoverability and confidence
+    pub unchanged: usize,
+}
+
+impl SessionDiff {
+    /// Whether the scans found the same files in the same state
+    pub fn is_empty(&self) -> bool {
+        self.newly_deleted.is_empty()
+            && self.became_unrecoverable.is_empty()
+            && self.confidence_changed.is_empty()
+    }
+}
+
+/// A file as the two scans saw it; `None` where a scan did not find it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileChange {
+    pub inode_or_cluster: u64,
+    pub original_path: Option<PathBuf>,
+    /// ID of the file in the older session
+    pub old_id: Option<u64>,
+    /// ID of the file in the newer session
+    pub new_id: Option<u64>,
+    pub old_confidence: Option<f32>,
+    pub new_confidence: Option<f32>,
+}
+
+impl FileChange {
+    fn new(old: Option<&DeletedFile>, new: Option<&DeletedFile>) -> Self {
+        let either = new.or(old).expect("a change involves at least one file");
+        Self {
+            inode_or_cluster: either.inode_or_cluster,
+            original_path: new
+                .and_then(|f| f.original_path.clone())
+                .or_else(|| old.and_then(|f| f.original_path.clone())),
+            old_id: old.map(|f| f.id),
+            new_id: new.map(|f| f.id),
+            old_confidence: old.map(|f| f.confidence_score),
+            new_confidence: new.map(|f| f.confidence_score),
+        }
+    }
+}
+
+impl RecoverySession {
+    /// Compare this session with `other`, a later scan of the same device
+    pub fn diff(&self, other: &RecoverySession) -> SessionDiff {
+        let older: HashMap<FileKey, &DeletedFile> = self
+            .scan_results
+            .iter()
+            .map(|file| (FileKey::of(file), file))
+            .collect();
+        let newer: HashMap<FileKey, &DeletedFile> = other
+            .scan_results
+            .iter()
+            .map(|file| (FileKey::of(file), file))
+            .collect();
+
+        let mut diff = SessionDiff::default();
+        for new in &other.scan_results {
+            let Some(&old) = older.get(&FileKey::of(new)) else {
+                diff.newly_deleted.push(FileChange::new(None, Some(new)));
+                continue;
+            };
+
+            if old.is_recoverable && !new.is_recoverable {
+                diff.became_unrecoverable
+                    .push(FileChange::new(Some(old), Some(new)));
+            } else if old.is_recoverable == new.is_recoverable
+                && (old.confidence_score - new.confidence_score).abs() < CONFIDENCE_TOLERANCE
+            {
+                diff.unchanged += 1;
+            } else {
+                diff.confidence_changed
+                    .push(FileChange::new(Some(old), Some(new)));
+            }
+        }
+
+        for old in &self.scan_results {
+            if old.is_recoverable && !newer.contains_key(&FileKey::of(old)) {
+                diff.became_unrecoverable
+                    .push(FileChange::new(Some(old), None));
+            }
+        }
+        diff.became_unrecoverable
+            .sort_by_key(|change| change.old_id);
+
+        diff
+    }
+}
+
+/// What identifies a file across scans of one device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FileKey {
+    Inode(u64),
+    /// Carved files have no inode and are told apart by their first block
+    StartBlock(u64),
+}
+
+impl FileKey {
+    fn of(file: &DeletedFile) -> Self {
+        match file.inode_or_cluster {
+            0 => Self::StartBlock(file.data_blocks.first().map_or(0, |r| r.start_block)),
+            inode => Self::Inode(inode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::database::tests::sample_session;
+
+    /// An older scan of three files and a newer copy of it to modify
+    fn scans() -> (RecoverySession, RecoverySession) {
+        let older = sample_session(3);
+        let mut newer = older.clone();
+        newer.id = uuid::Uuid::new_v4();
+        (older, newer)
+    }
+
+    #[test]
+    fn test_identical_scans_have_empty_diff() {
+        let (older, newer) = scans();
+
+        let diff = older.diff(&newer);
+        assert!(diff.is_empty());
+        assert_eq!(diff.unchanged, 3);
+    }
+
+    #[test]
+    fn test_diff_finds_newly_deleted_files() {
+        let (older, mut newer) = scans();
+        let mut added = newer.scan_results[0].clone();
+        added.id = 4;
+        added.inode_or_cluster = 999;
+        added.original_path = Some(PathBuf::from("/home/user/report.pdf"));
+        newer.scan_results.push(added);
+
+        let diff = older.diff(&newer);
+        assert_eq!(diff.newly_deleted.len(), 1);
+        let change = &diff.newly_deleted[0];
+        assert_eq!(change.inode_or_cluster, 999);
+        assert_eq!((change.old_id, change.new_id), (None, Some(4)));
+        assert_eq!(
+            change.original_path,
+            Some(PathBuf::from("/home/user/report.pdf"))
+        );
+        assert_eq!(diff.unchanged, 3);
+    }
+
+    #[test]
+    fn test_diff_finds_overwritten_files() {
+        let (older, mut newer) = scans();
+        // One file no longer recoverable, another no longer found at all
+        newer.scan_results[0].is_recoverable = false;
+        newer.scan_results[0].confidence_score = 0.1;
+        newer.scan_results.remove(2);
+
+        let diff = older.diff(&newer);
+        let lost: Vec<(Option<u64>, Option<u64>)> = diff
+            .became_unrecoverable
+            .iter()
+            .map(|change| (change.old_id, change.new_id))
+            .collect();
+        assert_eq!(lost, vec![(Some(1), Some(1)), (Some(3), None)]);
+        assert_eq!(diff.became_unrecoverable[0].new_confidence, Some(0.1));
+        assert!(diff.newly_deleted.is_empty());
+        assert!(diff.confidence_changed.is_empty());
+        assert_eq!(diff.unchanged, 1);
+    }
+
+    #[test]
+    fn test_diff_finds_confidence_changes() {
+        let (mut older, mut newer) = scans();
+        newer.scan_results[1].confidence_score = 0.55;
+        // A file that was unrecoverable and now is counts as a change too
+        older.scan_results[2].is_recoverable = false;
+
+        let diff = older.diff(&newer);
+        let changed: Vec<(u64, Option<f32>, Option<f32>)> = diff
+            .confidence_changed
+            .iter()
+            .map(|change| {
+                (
+                    change.new_id.unwrap(),
+                    change.old_confidence,
+                    change.new_confidence,
+                )
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            vec![(2, Some(0.85), Some(0.55)), (3, Some(0.85), Some(0.85))]
+        );
+        assert!(diff.became_unrecoverable.is_empty());
+        assert_eq!(diff.unchanged, 1);
+    }
+
+    #[test]
+    fn test_diff_matches_carved_files_by_start_block() {
+        let (mut older, mut newer) = scans();
+        for session in [&mut older, &mut newer] {
+            for (file, start) in session.scan_results.iter_mut().zip([10, 20, 30]) {
+                file.inode_or_cluster = 0;
+                file.data_blocks = vec![crate::BlockRange {
+                    start_block: start,
+                    block_count: 4,
+                    is_allocated: false,
+                }];
+            }
+        }
+        newer.scan_results[2].data_blocks[0].start_block = 40;
+
+        let diff = older.diff(&newer);
+        assert_eq!(diff.newly_deleted.len(), 1);
+        assert_eq!(diff.newly_deleted[0].new_id, Some(3));
+        assert_eq!(diff.became_unrecoverable.len(), 1);
+        assert_eq!(diff.became_unrecoverable[0].old_id, Some(3));
+        assert_eq!(diff.unchanged, 2);
+    }
+}
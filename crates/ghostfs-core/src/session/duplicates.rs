@@ -0,0 +1,160 @@
+/// Grouping deleted files with identical content
+///
+/// Media cards often hold many byte-identical copies of the same photo.
+/// Files are first bucketed by size, and only files sharing a size are read
+/// from the device and hashed, so unique sizes cost nothing.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::fs::common::BlockDevice;
+use crate::{recover_file_streaming, DeletedFile, RecoverySession};
+
+/// Deleted files of a session whose recovered content is identical
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// SHA-256 of the content, as `calculate_file_hash` reports it for a recovered copy
+    pub hash: String,
+    /// Size of each copy in bytes
+    pub size: u64,
+    /// IDs of the files sharing this content, lowest first
+    pub file_ids: Vec<u64>,
+}
+
+impl DuplicateGroup {
+    /// Bytes saved by recovering only one copy
+    pub fn redundant_bytes(&self) -> u64 {
+        self.size * (self.file_ids.len() as u64).saturating_sub(1)
+    }
+}
+
+/// Find the recoverable files of `session` that share content, reading them from its device
+///
+/// Files with unreadable ranges are left out, as their zero-filled gaps
+/// could make different files look identical. Groups are ordered by the
+/// space they waste, largest first.
+pub(super) fn find_duplicates(session: &RecoverySession) -> Result<Vec<DuplicateGroup>> {
+    let mut by_size: HashMap<u64, Vec<&DeletedFile>> = HashMap::new();
+    for file in &session.scan_results {
+        if file.is_recoverable && file.size > 0 {
+            by_size.entry(file.size).or_default().push(file);
+        }
+    }
+    by_size.retain(|_, files| files.len() > 1);
+    if by_size.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let device = BlockDevice::open(&session.device_path)?;
+    let mut groups = Vec::new();
+    for (size, files) in by_size {
+        let mut by_hash: HashMap<String, Vec<u64>> = HashMap::new();
+        for file in files {
+            match content_hash(&device, file, session) {
+                Ok(Some(hash)) => by_hash.entry(hash).or_default().push(file.id),
+                Ok(None) => tracing::debug!("File {} has unreadable ranges, not hashed", file.id),
+                Err(e) => tracing::warn!("Failed to hash file {}: {:#}", file.id, e),
+            }
+        }
+        groups.extend(by_hash.into_iter().filter(|(_, ids)| ids.len() > 1).map(
+            |(hash, mut file_ids)| {
+                file_ids.sort_unstable();
+                DuplicateGroup {
+                    hash,
+                    size,
+                    file_ids,
+                }
+            },
+        ));
+    }
+
+    groups.sort_by(|a, b| {
+        b.redundant_bytes()
+            .cmp(&a.redundant_bytes())
+            .then_with(|| a.file_ids.cmp(&b.file_ids))
+    });
+    Ok(groups)
+}
+
+/// SHA-256 of the bytes recovery would write for `file`, or `None` if some were unreadable
+fn content_hash(
+    device: &BlockDevice,
+    file: &DeletedFile,
+    session: &RecoverySession,
+) -> Result<Option<String>> {
+    let mut hasher = Sha256::new();
+    let mut damaged = Vec::new();
+    recover_file_streaming(
+        file,
+        device,
+        session.fs_type,
+        session.metadata.block_size,
+        &mut hasher,
+        &mut damaged,
+    )?;
+    if !damaged.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forensics::{calculate_file_hash, HashAlgorithm};
+    use crate::session::database::tests::sample_session;
+    use crate::session::{SessionDatabase, SessionManager};
+    use crate::BlockRange;
+    use std::io::Write;
+
+    const FILE_SIZE: u64 = 2048;
+
+    /// Session over an exFAT image holding `contents` back to back
+    fn session_with_contents(contents: &[u8]) -> (tempfile::NamedTempFile, RecoverySession) {
+        let mut image = Vec::new();
+        for &fill in contents {
+            image.extend((0..FILE_SIZE).map(|i| fill ^ (i % 251) as u8));
+        }
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&image).unwrap();
+
+        let mut session = sample_session(contents.len());
+        session.device_path = file.path().to_path_buf();
+        for (index, deleted) in session.scan_results.iter_mut().enumerate() {
+            deleted.size = FILE_SIZE;
+            // exFAT data blocks are byte offsets
+            deleted.data_blocks = vec![BlockRange {
+                start_block: index as u64 * FILE_SIZE,
+                block_count: FILE_SIZE,
+                is_allocated: false,
+            }];
+        }
+        (file, session)
+    }
+
+    #[test]
+    fn test_identical_files_are_grouped() {
+        let (image, session) = session_with_contents(&[7, 1, 7, 2, 7]);
+        let manager = SessionManager::with_database(SessionDatabase::open_in_memory().unwrap());
+        let groups = manager.find_duplicates(&session).unwrap();
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].file_ids, vec![1, 3, 5]);
+        assert_eq!(groups[0].size, FILE_SIZE);
+        assert_eq!(groups[0].redundant_bytes(), 2 * FILE_SIZE);
+
+        // The hash is the one a recovered copy would have
+        let copy = tempfile::NamedTempFile::new().unwrap();
+        let data = std::fs::read(image.path()).unwrap();
+        std::fs::write(copy.path(), &data[..FILE_SIZE as usize]).unwrap();
+        let expected = calculate_file_hash(copy.path(), HashAlgorithm::SHA256).unwrap();
+        assert_eq!(groups[0].hash, expected.hash);
+    }
+
+    #[test]
+    fn test_distinct_files_are_not_grouped() {
+        let (_image, session) = session_with_contents(&[1, 2, 3]);
+        assert!(find_duplicates(&session).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+/// Combining several scans of the same device into one session
+///
+/// A quick scan followed by a deep scan finds many of the same files twice.
+/// Two results describe the same file when they share an inode (or exFAT
+/// cluster) number or their data blocks overlap; the copy with the higher
+/// confidence wins.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{BlockRange, DeletedFile, RecoverySession};
+
+/// Outcome of `RecoverySession::merge`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// Files only the other session had, appended with new IDs
+    pub added: usize,
+    /// Files both sessions had
+    pub duplicates: usize,
+    /// Duplicates replaced by the other session's higher-confidence copy
+    pub upgraded: usize,
+}
+
+impl RecoverySession {
+    /// Merge the files of `other`, a scan of the same device, into this session
+    ///
+    /// Refuses sessions of a different device unless `force` is set, and
+    /// sessions of a different file system type always, since their block
+    /// numbers are not comparable.
+    pub fn merge(&mut self, other: &RecoverySession, force: bool) -> Result<MergeReport> {
+        if other.fs_type != self.fs_type {
+            bail!(
+                "Cannot merge a {} session into a {} session",
+                other.fs_type,
+                self.fs_type
+            );
+        }
+        if other.device_path != self.device_path && !force {
+            bail!(
+                "Session {} scanned {} but session {} scanned {} (use force to merge anyway)",
+                other.id,
+                other.device_path.display(),
+                self.id,
+                self.device_path.display()
+            );
+        }
+
+        let mut report = MergeReport::default();
+        let mut next_id = self.scan_results.iter().map(|f| f.id).max().unwrap_or(0) + 1;
+
+        for file in &other.scan_results {
+            match self.scan_results.iter_mut().find(|f| is_same_file(f, file)) {
+                Some(existing) => {
+                    report.duplicates += 1;
+                    if file.confidence_score > existing.confidence_score {
+                        report.upgraded += 1;
+                        let id = existing.id;
+                        let original_path = existing.original_path.take();
+                        let deletion_time = existing.deletion_time;
+
+                        *existing = file.clone();
+                        existing.id = id;
+                        existing.original_path = existing.original_path.take().or(original_path);
+                        existing.deletion_time = existing.deletion_time.or(deletion_time);
+                    }
+                }
+                None => {
+                    let mut file = file.clone();
+                    file.id = next_id;
+                    next_id += 1;
+                    self.scan_results.push(file);
+                    report.added += 1;
+                }
+            }
+        }
+
+        self.total_scanned = self.total_scanned.max(other.total_scanned);
+        self.metadata.scan_duration_ms += other.metadata.scan_duration_ms;
+        self.metadata.files_found = self.scan_results.len() as u32;
+        self.metadata.recoverable_files = self
+            .scan_results
+            .iter()
+            .filter(|f| f.is_recoverable)
+            .count() as u32;
+
+        Ok(report)
+    }
+}
+
+/// Whether two results from scans of one device describe the same file
+pub(super) fn is_same_file(a: &DeletedFile, b: &DeletedFile) -> bool {
+    // Carved files have no inode and are told apart by their blocks alone
+    if a.inode_or_cluster != 0 && a.inode_or_cluster == b.inode_or_cluster {
+        return true;
+    }
+    // Every local-format XFS inode claims block 0 as a marker, not as data
+    if a.inline_data().is_some() || b.inline_data().is_some() {
+        return false;
+    }
+    a.data_blocks
+        .iter()
+        .any(|x| b.data_blocks.iter().any(|y| ranges_overlap(x, y)))
+}
+
+fn ranges_overlap(a: &BlockRange, b: &BlockRange) -> bool {
+    a.block_count > 0
+        && b.block_count > 0
+        && a.start_block < b.start_block + b.block_count
+        && b.start_block < a.start_block + a.block_count
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::session::database::tests::sample_session;
+    use std::path::PathBuf;
+
+    fn blocks(start_block: u64, block_count: u64) -> Vec<BlockRange> {
+        vec![BlockRange {
+            start_block,
+            block_count,
+            is_allocated: false,
+        }]
+    }
+
+    /// Two scans of one device whose files sit at `starts`
+    fn scans(first: &[u64], second: &[u64]) -> (RecoverySession, RecoverySession) {
+        let scan = |starts: &[u64]| {
+            let mut session = sample_session(starts.len());
+            for (file, &start) in session.scan_results.iter_mut().zip(starts) {
+                file.inode_or_cluster = 0;
+                file.data_blocks = blocks(start, 4);
+            }
+            session
+        };
+        (scan(first), scan(second))
+    }
+
+    #[test]
+    fn test_merge_disjoint_sessions() {
+        let (mut quick, deep) = scans(&[0, 10], &[20, 30, 40]);
+
+        let report = quick.merge(&deep, false).unwrap();
+
+        assert_eq!(
+            report,
+            MergeReport {
+                added: 3,
+                duplicates: 0,
+                upgraded: 0,
+            }
+        );
+        assert_eq!(quick.scan_results.len(), 5);
+        assert_eq!(quick.metadata.files_found, 5);
+        assert_eq!(quick.metadata.recoverable_files, 5);
+        let ids: Vec<u64> = quick.scan_results.iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_merge_overlapping_sessions() {
+        // The deep scan finds the quick scan's second file again, shifted by a block
+        let (mut quick, mut deep) = scans(&[0, 10], &[11, 30]);
+        quick.scan_results[1].confidence_score = 0.5;
+        deep.scan_results[0].confidence_score = 0.9;
+        deep.scan_results[0].original_path = None;
+
+        let report = quick.merge(&deep, false).unwrap();
+
+        assert_eq!(report.added, 1);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.upgraded, 1);
+        assert_eq!(quick.scan_results.len(), 3);
+
+        let upgraded = &quick.scan_results[1];
+        assert_eq!(upgraded.id, 2);
+        assert_eq!(upgraded.confidence_score, 0.9);
+        assert_eq!(upgraded.data_blocks[0].start_block, 11);
+        // The name from the first scan is kept when the better copy has none
+        assert_eq!(
+            upgraded.original_path,
+            Some(PathBuf::from("/home/user/photo_1.jpg"))
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_higher_confidence_copy() {
+        let mut first = sample_session(2);
+        let mut second = first.clone();
+        second.id = uuid::Uuid::new_v4();
+        first.scan_results[0].confidence_score = 0.9;
+        second.scan_results[0].confidence_score = 0.6;
+
+        let report = first.merge(&second, false).unwrap();
+
+        // Matched by inode number; the lower-confidence duplicate is dropped
+        assert_eq!(report.duplicates, 2);
+        assert_eq!(report.upgraded, 0);
+        assert_eq!(first.scan_results.len(), 2);
+        assert_eq!(first.scan_results[0].confidence_score, 0.9);
+    }
+
+    /// Make `file` a local-format XFS inode holding `data`
+    pub(crate) fn make_inline(file: &mut DeletedFile, inode: u64, data: &[u8]) {
+        file.inode_or_cluster = inode;
+        file.data_blocks = blocks(0, 1);
+        file.fs_metadata = Some(crate::FsSpecificMetadata::Xfs(crate::XfsFileMetadata {
+            ag_number: 0,
+            ag_inode_number: inode as u32,
+            extent_count: 0,
+            extent_format: crate::XfsExtentFormat::Local,
+            is_aligned: true,
+            last_link_count: 1,
+            inode_generation: 1,
+            inline_data: Some(data.to_vec()),
+            sparse_ranges: Vec::new(),
+        }));
+    }
+
+    #[test]
+    fn test_inline_files_match_by_inode_only() {
+        let (mut first, mut second) = scans(&[0, 10], &[0, 10]);
+        for session in [&mut first, &mut second] {
+            session.fs_type = crate::FileSystemType::Xfs;
+        }
+        make_inline(&mut first.scan_results[0], 131, b"first");
+        make_inline(&mut second.scan_results[0], 132, b"second");
+        // The same inline file, found again by the second scan
+        make_inline(&mut second.scan_results[1], 131, b"first");
+        // A carved file at block 0 is not an inline file either
+        second.scan_results.push(first.scan_results[1].clone());
+        second.scan_results[2].data_blocks = blocks(0, 4);
+
+        let report = first.merge(&second, false).unwrap();
+
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.added, 2);
+        let inodes: Vec<u64> = first
+            .scan_results
+            .iter()
+            .map(|f| f.inode_or_cluster)
+            .collect();
+        assert_eq!(inodes, vec![131, 0, 132, 0]);
+    }
+
+    #[test]
+    fn test_merge_refuses_other_device_unless_forced() {
+        let (mut first, mut second) = scans(&[0], &[10]);
+        second.device_path = PathBuf::from("/images/other.img");
+
+        assert!(first.merge(&second, false).is_err());
+        assert_eq!(first.scan_results.len(), 1);
+        assert_eq!(first.merge(&second, true).unwrap().added, 1);
+
+        second.fs_type = crate::FileSystemType::Xfs;
+        assert!(first.merge(&second, true).is_err());
+    }
+}
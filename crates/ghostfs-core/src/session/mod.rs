@@ -0,0 +1,456 @@
+/// Persistent recovery sessions
+///
+/// Sessions produced by a scan are stored in a local SQLite database so they
+/// can be listed, reloaded and recovered from later, and can be exported to
+/// portable files for moving between machines.
+pub mod database;
+mod diff;
+mod duplicates;
+mod merge;
+mod page;
+mod rescan;
+
+pub use database::{
+    AggregateStats, DeletedFileSummary, DeviceScanCount, FileFilter, FsTypeStats, SessionDatabase,
+    SessionSummary,
+};
+pub use diff::{FileChange, SessionDiff};
+pub use duplicates::DuplicateGroup;
+pub use merge::MergeReport;
+pub use page::{FilePage, FileSortKey, SortDirection};
+pub use rescan::{rescan, RescanReport};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::forensics::AuditLogger;
+use crate::RecoverySession;
+
+/// Version of the portable session export format
+pub const SESSION_EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Magic bytes at the start of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Self-contained, portable representation of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionExport {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub ghostfs_version: String,
+    pub session: RecoverySession,
+}
+
+/// High-level session operations on top of the session database
+pub struct SessionManager {
+    db: SessionDatabase,
+    audit_logger: Option<AuditLogger>,
+}
+
+impl SessionManager {
+    /// Open a session manager backed by the database at `db_path`
+    pub fn new(db_path: &Path) -> Result<Self> {
+        Ok(Self::with_database(SessionDatabase::open(db_path)?))
+    }
+
+    /// Open a session manager backed by the default database location
+    pub fn open_default() -> Result<Self> {
+        Self::new(&SessionDatabase::default_path())
+    }
+
+    /// Wrap an already open database
+    pub fn with_database(db: SessionDatabase) -> Self {
+        Self {
+            db,
+            audit_logger: None,
+        }
+    }
+
+    /// Record every session saved through this manager in an audit log
+    pub fn with_audit_logger(mut self, logger: AuditLogger) -> Self {
+        self.audit_logger = Some(logger);
+        self
+    }
+
+    /// Underlying session database
+    pub fn database(&self) -> &SessionDatabase {
+        &self.db
+    }
+
+    /// Store a session, replacing any previous version with the same ID
+    pub fn save_session(&self, session: &RecoverySession) -> Result<()> {
+        self.db.save_session(session)?;
+        if let Some(logger) = &self.audit_logger {
+            logger.session_saved(&session.id.to_string(), session.scan_results.len())?;
+        }
+        Ok(())
+    }
+
+    /// Merge the session `source` into `target` and store the result as `target`
+    ///
+    /// Both IDs may be unique prefixes; `source` is left in the database.
+    pub fn merge_sessions(&self, target: &str, source: &str, force: bool) -> Result<MergeReport> {
+        let mut merged = self.db.load_session(target)?;
+        let other = self.db.load_session(source)?;
+        if merged.id == other.id {
+            bail!("Cannot merge session {} with itself", merged.id);
+        }
+
+        let report = merged.merge(&other, force)?;
+        self.save_session(&merged)?;
+        tracing::info!(
+            "Merged session {} into {}: {} added, {} duplicates",
+            other.id,
+            merged.id,
+            report.added,
+            report.duplicates
+        );
+        Ok(report)
+    }
+
+    /// Groups of files in `session` with byte-identical content
+    ///
+    /// Reads candidate files from the session's device and hashes them, so
+    /// only one copy of each group needs recovering.
+    pub fn find_duplicates(&self, session: &RecoverySession) -> Result<Vec<DuplicateGroup>> {
+        duplicates::find_duplicates(session)
+    }
+
+    /// Totals across all stored sessions, for dashboards
+    pub fn aggregate_statistics(&self) -> Result<AggregateStats> {
+        self.db.aggregate_statistics()
+    }
+
+    /// Load every stored session, oldest first
+    ///
+    /// Used to repopulate in-memory state on startup; a session that fails
+    /// to load is skipped with a warning rather than hiding all the others.
+    pub fn load_all_sessions(&self) -> Result<Vec<RecoverySession>> {
+        let mut summaries = self.db.list_sessions()?;
+        summaries.sort_by_key(|summary| summary.created_at);
+
+        let mut sessions = Vec::with_capacity(summaries.len());
+        for summary in summaries {
+            match self.db.load_session(&summary.id.to_string()) {
+                Ok(session) => sessions.push(session),
+                Err(e) => tracing::warn!("Skipping session {}: {:#}", summary.id, e),
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Write a session to a portable JSON file (gzip-compressed if `path` ends in `.gz`)
+    pub fn export_session(&self, id: &str, path: &Path) -> Result<()> {
+        let session = self.db.load_session(id)?;
+        let export = SessionExport {
+            format_version: SESSION_EXPORT_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            ghostfs_version: env!("CARGO_PKG_VERSION").to_string(),
+            session,
+        };
+
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create export file {}", path.display()))?;
+        let writer = BufWriter::new(file);
+
+        if path.extension().is_some_and(|ext| ext == "gz") {
+            write_gzip(writer, &export)?;
+        } else {
+            let mut writer = writer;
+            serde_json::to_writer_pretty(&mut writer, &export)?;
+            writer.flush()?;
+        }
+
+        tracing::info!(
+            "Exported session {} to {}",
+            export.session.id,
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// Read a portable session file, validate it and insert it into the database
+    ///
+    /// Fails if a session with the same ID is already stored.
+    pub fn import_session(&self, path: &Path) -> Result<Uuid> {
+        let mut data = Vec::new();
+        File::open(path)
+            .with_context(|| format!("Failed to open session file {}", path.display()))?
+            .read_to_end(&mut data)?;
+
+        if data.starts_with(&GZIP_MAGIC) {
+            data = read_gzip(&data)?;
+        }
+
+        let export: SessionExport =
+            serde_json::from_slice(&data).context("Session file is not a valid GhostFS export")?;
+        validate_export(&export)?;
+
+        let session = export.session;
+        if self.db.session_exists(&session.id)? {
+            bail!(
+                "Session {} already exists in the local database",
+                session.id
+            );
+        }
+
+        self.save_session(&session)?;
+        tracing::info!("Imported session {} from {}", session.id, path.display());
+        Ok(session.id)
+    }
+}
+
+fn validate_export(export: &SessionExport) -> Result<()> {
+    if export.format_version > SESSION_EXPORT_FORMAT_VERSION {
+        bail!(
+            "Session file uses format version {}, this build supports up to {}",
+            export.format_version,
+            SESSION_EXPORT_FORMAT_VERSION
+        );
+    }
+
+    let session = &export.session;
+    if !(0.0..=1.0).contains(&session.confidence_threshold) {
+        bail!(
+            "Invalid confidence threshold {} in session file",
+            session.confidence_threshold
+        );
+    }
+
+    let mut ids = HashSet::new();
+    for file in &session.scan_results {
+        if !ids.insert(file.id) {
+            bail!("Duplicate file ID {} in session file", file.id);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "session-gzip")]
+fn write_gzip<W: Write>(writer: W, export: &SessionExport) -> Result<()> {
+    let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+    serde_json::to_writer(&mut encoder, export)?;
+    encoder.finish()?.flush()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "session-gzip"))]
+fn write_gzip<W: Write>(_writer: W, _export: &SessionExport) -> Result<()> {
+    bail!("Compressed session export requires the session-gzip feature")
+}
+
+#[cfg(feature = "session-gzip")]
+fn read_gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(data).read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+#[cfg(not(feature = "session-gzip"))]
+fn read_gzip(_data: &[u8]) -> Result<Vec<u8>> {
+    bail!("Compressed session import requires the session-gzip feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::tests::sample_session;
+
+    fn manager() -> SessionManager {
+        SessionManager::with_database(SessionDatabase::open_in_memory().unwrap())
+    }
+
+    #[test]
+    fn test_import_rejects_existing_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+
+        let manager = manager();
+        let session = sample_session(2);
+        manager.database().save_session(&session).unwrap();
+        manager
+            .export_session(&session.id.to_string(), &path)
+            .unwrap();
+
+        let err = manager.import_session(&path).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+    }
+
+    #[test]
+    fn test_saved_sessions_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("sessions.db");
+
+        let first = sample_session(2);
+        let second = sample_session(3);
+        {
+            let manager = SessionManager::new(&db_path).unwrap();
+            manager.save_session(&first).unwrap();
+            manager.save_session(&second).unwrap();
+        }
+
+        let manager = SessionManager::new(&db_path).unwrap();
+        let mut loaded: Vec<_> = manager
+            .load_all_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.id, s.scan_results.len()))
+            .collect();
+        loaded.sort();
+        let mut expected = vec![(first.id, 2), (second.id, 3)];
+        expected.sort();
+        assert_eq!(loaded, expected);
+    }
+
+    #[test]
+    fn test_save_is_audited() {
+        use crate::forensics::{AuditEventType, AuditLog};
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = Arc::new(AuditLog::new("audit-save", dir.path()).unwrap());
+        let manager = manager().with_audit_logger(AuditLogger::new(log.clone()));
+
+        manager.save_session(&sample_session(2)).unwrap();
+
+        let saved = log.get_entries_by_type(AuditEventType::SessionSaved);
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].metadata["files"], "2");
+        log.verify_chain().unwrap();
+    }
+
+    #[test]
+    fn test_import_rejects_newer_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+
+        let export = SessionExport {
+            format_version: SESSION_EXPORT_FORMAT_VERSION + 1,
+            exported_at: Utc::now(),
+            ghostfs_version: "99.0.0".to_string(),
+            session: sample_session(1),
+        };
+        std::fs::write(&path, serde_json::to_vec(&export).unwrap()).unwrap();
+
+        assert!(manager().import_session(&path).is_err());
+    }
+
+    #[test]
+    fn test_import_rejects_duplicate_file_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+
+        let mut session = sample_session(2);
+        session.scan_results[1].id = session.scan_results[0].id;
+        let export = SessionExport {
+            format_version: SESSION_EXPORT_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            ghostfs_version: env!("CARGO_PKG_VERSION").to_string(),
+            session,
+        };
+        std::fs::write(&path, serde_json::to_vec(&export).unwrap()).unwrap();
+
+        assert!(manager().import_session(&path).is_err());
+    }
+
+    #[test]
+    fn test_merge_sessions_saves_result() {
+        let manager = manager();
+        let first = sample_session(2);
+        let mut second = sample_session(3);
+        for file in &mut second.scan_results {
+            file.inode_or_cluster += 1; // overlaps the first scan's second file
+        }
+        manager.save_session(&first).unwrap();
+        manager.save_session(&second).unwrap();
+
+        let report = manager
+            .merge_sessions(&first.id.to_string(), &second.id.to_string(), false)
+            .unwrap();
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.added, 2);
+
+        let merged = manager
+            .database()
+            .load_session(&first.id.to_string())
+            .unwrap();
+        assert_eq!(merged.scan_results.len(), 4);
+        assert_eq!(merged.metadata.files_found, 4);
+        assert!(manager
+            .merge_sessions(&first.id.to_string(), &first.id.to_string(), false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_aggregate_statistics_across_sessions() {
+        let manager = manager();
+        let empty = manager.aggregate_statistics().unwrap();
+        assert_eq!(empty.sessions, 0);
+        assert_eq!(empty.average_confidence, None);
+
+        // Two scans of a camera card and one of a disk
+        let first_card = sample_session(3);
+        let mut second_card = sample_session(1);
+        second_card.created_at = first_card.created_at + chrono::Duration::hours(1);
+        let mut disk = sample_session(2);
+        disk.fs_type = crate::FileSystemType::Xfs;
+        disk.device_path = std::path::PathBuf::from("/images/disk.img");
+        disk.scan_results[0].metadata.mime_type = Some("application/pdf".to_string());
+        disk.scan_results[0].confidence_score = 0.45;
+        disk.scan_results[1].metadata.mime_type = None;
+        disk.scan_results[1].confidence_score = 0.25;
+        disk.scan_results[1].is_recoverable = false;
+        disk.metadata.recoverable_files = 1;
+        for session in [&first_card, &second_card, &disk] {
+            manager.save_session(session).unwrap();
+        }
+
+        let stats = manager.aggregate_statistics().unwrap();
+
+        assert_eq!(stats.sessions, 3);
+        assert_eq!(stats.files_found, 6);
+        assert_eq!(stats.recoverable_files, 5);
+        assert_eq!(
+            stats.by_fs_type,
+            vec![
+                FsTypeStats {
+                    fs_type: crate::FileSystemType::ExFat,
+                    sessions: 2,
+                    files_found: 4,
+                    recoverable_files: 4,
+                },
+                FsTypeStats {
+                    fs_type: crate::FileSystemType::Xfs,
+                    sessions: 1,
+                    files_found: 2,
+                    recoverable_files: 1,
+                },
+            ]
+        );
+        let categories: Vec<(&str, u64)> = stats
+            .by_mime_category
+            .iter()
+            .map(|(category, count)| (category.as_str(), *count))
+            .collect();
+        assert_eq!(
+            categories,
+            vec![("application", 1), ("image", 4), ("unknown", 1)]
+        );
+        let average = stats.average_confidence.unwrap();
+        assert!((average - (0.85 * 4.0 + 0.45 + 0.25) / 6.0).abs() < 1e-4);
+
+        assert_eq!(stats.top_devices.len(), 2);
+        assert_eq!(stats.top_devices[0].device_path, first_card.device_path);
+        assert_eq!(stats.top_devices[0].sessions, 2);
+        assert_eq!(stats.top_devices[0].last_scanned, second_card.created_at);
+        assert_eq!(stats.top_devices[1].device_path, disk.device_path);
+        assert_eq!(stats.top_devices[1].sessions, 1);
+    }
+}
@@ -0,0 +1,176 @@
+/// Sorting and paging the files of a session for display
+///
+/// A session can hold tens of thousands of files. A UI asks for the page it
+/// shows instead of the whole list, and the sorting happens here.
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::{DeletedFile, RecoverySession};
+
+/// What `RecoverySession::files_page` sorts by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileSortKey {
+    Size,
+    Confidence,
+    /// Original path; files without one sort first
+    Name,
+    /// MIME type, or the extension when the type is unknown
+    Type,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// One page of a session's files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilePage {
+    /// Files in the whole session
+    pub total: usize,
+    /// Position of the first file of the page in the sorted list
+    pub offset: usize,
+    pub files: Vec<DeletedFile>,
+}
+
+impl RecoverySession {
+    /// Up to `limit` files from position `offset` of the files sorted by `key`
+    ///
+    /// Files that compare equal stay in ID order in either direction, so
+    /// pages do not overlap or skip files.
+    pub fn files_page(
+        &self,
+        key: FileSortKey,
+        direction: SortDirection,
+        offset: usize,
+        limit: usize,
+    ) -> FilePage {
+        let mut files: Vec<&DeletedFile> = self.scan_results.iter().collect();
+        files.sort_by(|a, b| {
+            let order = compare(a, b, key);
+            let order = match direction {
+                SortDirection::Ascending => order,
+                SortDirection::Descending => order.reverse(),
+            };
+            order.then(a.id.cmp(&b.id))
+        });
+
+        FilePage {
+            total: files.len(),
+            offset,
+            files: files
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+fn compare(a: &DeletedFile, b: &DeletedFile, key: FileSortKey) -> Ordering {
+    match key {
+        FileSortKey::Size => a.size.cmp(&b.size),
+        FileSortKey::Confidence => a.confidence_score.total_cmp(&b.confidence_score),
+        FileSortKey::Name => a.original_path.cmp(&b.original_path),
+        FileSortKey::Type => type_of(a).cmp(type_of(b)),
+    }
+}
+
+fn type_of(file: &DeletedFile) -> &str {
+    file.metadata
+        .mime_type
+        .as_deref()
+        .or(file.metadata.file_extension.as_deref())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::database::tests::sample_session;
+    use std::path::PathBuf;
+
+    /// Five files with distinct sizes, confidences, names and types
+    fn session() -> RecoverySession {
+        let mut session = sample_session(5);
+        let details = [
+            (300, 0.5, "/b.pdf", Some("application/pdf")),
+            (100, 0.9, "/e.png", Some("image/png")),
+            (500, 0.7, "/a.txt", None),
+            (200, 0.6, "/d.jpg", Some("image/jpeg")),
+            (400, 0.8, "/c.zip", Some("application/zip")),
+        ];
+        for (file, (size, confidence, path, mime)) in session.scan_results.iter_mut().zip(details) {
+            file.size = size;
+            file.confidence_score = confidence;
+            file.original_path = Some(PathBuf::from(path));
+            file.metadata.mime_type = mime.map(str::to_string);
+            file.metadata.file_extension = Some(path.rsplit('.').next().unwrap().to_string());
+        }
+        session
+    }
+
+    fn ids(page: &FilePage) -> Vec<u64> {
+        page.files.iter().map(|f| f.id).collect()
+    }
+
+    #[test]
+    fn test_files_sort_by_each_key() {
+        let session = session();
+        let sorted = |key| ids(&session.files_page(key, SortDirection::Ascending, 0, 10));
+
+        assert_eq!(sorted(FileSortKey::Size), vec![2, 4, 1, 5, 3]);
+        assert_eq!(sorted(FileSortKey::Confidence), vec![1, 4, 3, 5, 2]);
+        assert_eq!(sorted(FileSortKey::Name), vec![3, 1, 5, 4, 2]);
+        // "txt" has no MIME type and sorts by its extension
+        assert_eq!(sorted(FileSortKey::Type), vec![1, 5, 4, 2, 3]);
+
+        let page = session.files_page(FileSortKey::Size, SortDirection::Descending, 0, 10);
+        assert_eq!(ids(&page), vec![3, 5, 1, 4, 2]);
+    }
+
+    #[test]
+    fn test_equal_files_keep_id_order_both_ways() {
+        let mut session = session();
+        for file in &mut session.scan_results {
+            file.size = 4096;
+        }
+
+        for direction in [SortDirection::Ascending, SortDirection::Descending] {
+            let page = session.files_page(FileSortKey::Size, direction, 0, 10);
+            assert_eq!(ids(&page), vec![1, 2, 3, 4, 5]);
+        }
+    }
+
+    #[test]
+    fn test_pages_cover_files_once() {
+        let session = session();
+        let page = |offset, limit| {
+            session.files_page(FileSortKey::Size, SortDirection::Ascending, offset, limit)
+        };
+
+        assert_eq!(ids(&page(0, 2)), vec![2, 4]);
+        assert_eq!(ids(&page(2, 2)), vec![1, 5]);
+        // The last page is short, and past the end there is nothing
+        assert_eq!(ids(&page(4, 2)), vec![3]);
+        assert!(page(5, 2).files.is_empty());
+        assert!(page(100, 2).files.is_empty());
+        assert!(page(0, 0).files.is_empty());
+
+        let last = page(4, 2);
+        assert_eq!((last.total, last.offset), (5, 4));
+    }
+
+    #[test]
+    fn test_sort_options_deserialize_lowercase() {
+        let key: FileSortKey = serde_json::from_str("\"confidence\"").unwrap();
+        assert_eq!(key, FileSortKey::Confidence);
+        let direction: SortDirection = serde_json::from_str("\"descending\"").unwrap();
+        assert_eq!(direction, SortDirection::Descending);
+    }
+}
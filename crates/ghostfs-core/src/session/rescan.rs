@@ -0,0 +1,199 @@
+/// Rescanning a device against a stored session
+///
+/// A device imaged again later mostly holds what it held before. Regions
+/// where the previous scan recovered files are not carved again; those files
+/// are carried over when their data still starts with the same kind of
+/// content, and everything else is scanned as usual.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::merge::is_same_file;
+use crate::fs::common::{BlockDevice, RangeSet};
+use crate::recovery::SignatureSet;
+use crate::{DeletedFile, FileSystemType, RecoverySession, ScanOptions};
+
+/// Bytes read from the start of a carried-over file to check it is still there
+const PRESENCE_CHECK_LEN: u64 = 512;
+
+/// How a rescan compares with the session it started from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RescanReport {
+    /// IDs in the new session of files the previous scan did not have
+    pub new_files: Vec<u64>,
+    /// IDs in the new session of files the previous scan also found
+    pub unchanged: Vec<u64>,
+    /// IDs in the previous session of files no longer found
+    pub disappeared: Vec<u64>,
+    /// Device bytes not carved again because the previous scan recovered them
+    pub skipped_bytes: u64,
+}
+
+/// Scan `image_path` again, skipping what `previous` already recovered there
+///
+/// Files `previous` considered recoverable have their data regions left out
+/// of signature scanning and trailer carving. Filesystem metadata is always
+/// read again, since it is cheap and is where deletions show up.
+pub fn rescan(
+    image_path: &Path,
+    previous: &RecoverySession,
+    mut options: ScanOptions,
+) -> Result<(RecoverySession, RescanReport)> {
+    let fs_type = previous.fs_type;
+    let block_size = previous.metadata.block_size;
+    let known: Vec<&DeletedFile> = previous
+        .scan_results
+        .iter()
+        .filter(|f| f.is_recoverable && f.confidence_score >= previous.confidence_threshold)
+        .collect();
+    let regions = RangeSet::from_ranges(
+        known
+            .iter()
+            .flat_map(|f| byte_ranges(f, fs_type, block_size)),
+    );
+    let skipped_bytes = regions.total_len();
+    options.skip_regions = Some(regions);
+    let signatures = options.signatures.clone();
+
+    let mut session = crate::run_scan(image_path, fs_type, options, None)?;
+
+    // Files in skipped regions were not carved again; keep those still there
+    let device = BlockDevice::open(image_path)?;
+    let mut next_id = session.scan_results.iter().map(|f| f.id).max().unwrap_or(0) + 1;
+    for file in known {
+        let found = session.scan_results.iter().any(|f| is_same_file(f, file));
+        if !found && still_present(&device, file, fs_type, block_size, &signatures) {
+            let mut file = file.clone();
+            file.id = next_id;
+            next_id += 1;
+            session.scan_results.push(file);
+        }
+    }
+    session.metadata.files_found = session.scan_results.len() as u32;
+    session.metadata.recoverable_files = session
+        .scan_results
+        .iter()
+        .filter(|f| f.is_recoverable)
+        .count() as u32;
+
+    let report = RescanReport {
+        skipped_bytes,
+        ..compare_with_previous(previous, &session)
+    };
+    Ok((session, report))
+}
+
+/// Which files of `session` the `previous` scan also found, and which of its
+/// files are gone
+fn compare_with_previous(previous: &RecoverySession, session: &RecoverySession) -> RescanReport {
+    let mut report = RescanReport::default();
+    for file in &session.scan_results {
+        if previous.scan_results.iter().any(|f| is_same_file(f, file)) {
+            report.unchanged.push(file.id);
+        } else {
+            report.new_files.push(file.id);
+        }
+    }
+    report.disappeared = previous
+        .scan_results
+        .iter()
+        .filter(|old| !session.scan_results.iter().any(|f| is_same_file(f, old)))
+        .map(|old| old.id)
+        .collect();
+    report
+}
+
+/// Device byte ranges `(start, length)` holding a file's data
+///
+/// Empty for files found through exFAT metadata, whose blocks may be cluster
+/// numbers or byte offsets, and for files whose data is inline in their
+/// metadata; those are found again by the metadata scan anyway.
+fn byte_ranges(file: &DeletedFile, fs_type: FileSystemType, block_size: u32) -> Vec<(u64, u64)> {
+    if (fs_type == FileSystemType::ExFat && file.fs_metadata.is_some())
+        || file.inline_data().is_some()
+    {
+        return Vec::new();
+    }
+    let unit = crate::block_offset_multiplier(fs_type, block_size);
+    file.data_blocks
+        .iter()
+        .map(|range| (range.start_block * unit, range.block_count * unit))
+        .collect()
+}
+
+/// Whether the start of a file's data still holds the content type it had
+fn still_present(
+    device: &BlockDevice,
+    file: &DeletedFile,
+    fs_type: FileSystemType,
+    block_size: u32,
+    signatures: &SignatureSet,
+) -> bool {
+    let (Some(mime_type), Some(&(start, length))) = (
+        file.metadata.mime_type.as_deref(),
+        byte_ranges(file, fs_type, block_size).first(),
+    ) else {
+        return false;
+    };
+
+    let len = length
+        .min(PRESENCE_CHECK_LEN)
+        .min(device.size().saturating_sub(start));
+    device
+        .read_at(start, len as usize)
+        .ok()
+        .and_then(|data| signatures.detect_file_type(data))
+        .is_some_and(|(detected, _)| detected == mime_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::database::tests::sample_session;
+    use crate::session::merge::tests::make_inline;
+    use crate::BlockRange;
+
+    #[test]
+    fn test_rescan_tells_inline_files_apart() {
+        let mut previous = sample_session(2);
+        previous.fs_type = FileSystemType::Xfs;
+        make_inline(&mut previous.scan_results[0], 131, b"kept");
+        make_inline(&mut previous.scan_results[1], 132, b"overwritten");
+        // Inline data is not on the device to skip or check again
+        assert!(byte_ranges(&previous.scan_results[0], FileSystemType::Xfs, 4096).is_empty());
+
+        let mut session = sample_session(2);
+        session.fs_type = FileSystemType::Xfs;
+        make_inline(&mut session.scan_results[0], 131, b"kept");
+        make_inline(&mut session.scan_results[1], 133, b"deleted since");
+
+        let report = compare_with_previous(&previous, &session);
+        assert_eq!(report.unchanged, vec![1]);
+        assert_eq!(report.new_files, vec![2]);
+        assert_eq!(report.disappeared, vec![2]);
+    }
+
+    #[test]
+    fn test_byte_ranges_use_the_filesystem_block_size() {
+        let mut file = sample_session(1).scan_results.remove(0);
+        file.data_blocks = vec![BlockRange {
+            start_block: 10,
+            block_count: 3,
+            is_allocated: false,
+        }];
+
+        assert_eq!(
+            byte_ranges(&file, FileSystemType::Xfs, 1024),
+            vec![(10 * 1024, 3 * 1024)]
+        );
+        assert_eq!(
+            byte_ranges(&file, FileSystemType::Btrfs, 16384),
+            vec![(10 * 16384, 3 * 16384)]
+        );
+        // exFAT blocks found by carving are byte offsets already
+        assert_eq!(
+            byte_ranges(&file, FileSystemType::ExFat, 4096),
+            vec![(10, 3)]
+        );
+    }
+}
@@ -47,6 +47,67 @@ pub enum PatternType {
     SuspiciousActivity,
 }
 
+/// Thresholds used by pattern detection
+#[derive(Debug, Clone)]
+pub struct PatternConfig {
+    /// Maximum time span of a bulk deletion burst
+    pub bulk_window: Duration,
+    /// Minimum number of deletions within the window to flag a bulk deletion
+    pub bulk_threshold: usize,
+}
+
+impl Default for PatternConfig {
+    fn default() -> Self {
+        Self {
+            bulk_window: Duration::minutes(5),
+            bulk_threshold: 5,
+        }
+    }
+}
+
+/// Range of deletion times to keep; unset bounds are open
+///
+/// `after` is inclusive and `before` exclusive, so consecutive windows do not
+/// overlap. Files with no known deletion time are kept unless `exclude_unknown`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeletionWindow {
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+    pub exclude_unknown: bool,
+}
+
+impl DeletionWindow {
+    /// Whether the window keeps every file
+    pub fn is_unbounded(&self) -> bool {
+        self.after.is_none() && self.before.is_none() && !self.exclude_unknown
+    }
+
+    /// Whether `file` was deleted inside the window
+    pub fn contains(&self, file: &DeletedFile) -> bool {
+        match file.deletion_time {
+            Some(deleted) => {
+                self.after.is_none_or(|after| deleted >= after)
+                    && self.before.is_none_or(|before| deleted < before)
+            }
+            None => !self.exclude_unknown,
+        }
+    }
+
+    /// Drop the session's files deleted outside the window and recount it
+    pub fn apply(&self, session: &mut RecoverySession) {
+        if self.is_unbounded() {
+            return;
+        }
+        session.scan_results.retain(|file| self.contains(file));
+        session.metadata.files_found = session.scan_results.len() as u32;
+        session.metadata.recoverable_files = session
+            .scan_results
+            .iter()
+            .filter(|f| f.is_recoverable)
+            .count() as u32;
+    }
+}
+
 /// Statistical analysis of the timeline
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimelineStatistics {
@@ -68,10 +129,36 @@ impl RecoveryTimeline {
     /// Extracts all timestamp-based events from deleted files, sorts them
     /// chronologically, detects patterns, and calculates statistics.
     pub fn from_session(session: &RecoverySession) -> Self {
+        Self::from_session_with_config(session, &PatternConfig::default())
+    }
+
+    /// Build a timeline using custom pattern detection thresholds
+    pub fn from_session_with_config(session: &RecoverySession, config: &PatternConfig) -> Self {
+        Self::from_session_in_window(session, config, &DeletionWindow::default())
+    }
+
+    /// Build a timeline from only the files deleted inside `window`
+    pub fn from_session_in_window(
+        session: &RecoverySession,
+        config: &PatternConfig,
+        window: &DeletionWindow,
+    ) -> Self {
+        let in_window: Vec<DeletedFile>;
+        let files: &[DeletedFile] = if window.is_unbounded() {
+            &session.scan_results
+        } else {
+            in_window = session
+                .scan_results
+                .iter()
+                .filter(|file| window.contains(file))
+                .cloned()
+                .collect();
+            &in_window
+        };
         let mut events = Vec::new();
 
         // Extract all timestamp events from deleted files
-        for file in &session.scan_results {
+        for file in files {
             // Creation events
             if let Some(created) = file.metadata.created_time {
                 events.push(TimelineEntry {
@@ -131,10 +218,10 @@ impl RecoveryTimeline {
         events.sort_by_key(|e| e.timestamp);
 
         // Detect patterns
-        let patterns = Self::detect_patterns(&events, &session.scan_results);
+        let patterns = Self::detect_patterns(&events, files, session.created_at, config);
 
         // Generate statistics
-        let statistics = Self::calculate_statistics(&events, &session.scan_results);
+        let statistics = Self::calculate_statistics(&events, files);
 
         RecoveryTimeline {
             events,
@@ -144,7 +231,12 @@ impl RecoveryTimeline {
     }
 
     /// Detect suspicious deletion patterns
-    fn detect_patterns(events: &[TimelineEntry], files: &[DeletedFile]) -> Vec<DeletionPattern> {
+    fn detect_patterns(
+        events: &[TimelineEntry],
+        files: &[DeletedFile],
+        scan_time: DateTime<Utc>,
+        config: &PatternConfig,
+    ) -> Vec<DeletionPattern> {
         let mut patterns = Vec::new();
 
         // Pattern 1: Bulk deletion detection
@@ -154,54 +246,51 @@ impl RecoveryTimeline {
             .filter(|e| matches!(e.event_type, TimelineEventType::FileDeleted))
             .collect();
 
-        let mut processed_windows = Vec::new();
-
-        for window_start in 0..deletion_events.len() {
-            let start_time = deletion_events[window_start].timestamp;
-            let mut files_in_window = vec![deletion_events[window_start].file_id];
-
-            // Check if this window overlaps with already processed windows
-            if processed_windows
-                .iter()
-                .any(|&(start, _): &(usize, usize)| {
-                    window_start >= start && window_start < start + 5
-                })
+        // Sliding window: grow the window from each start while events stay
+        // within range, and skip past a flagged burst so no file is counted twice
+        let mut left = 0;
+        while left < deletion_events.len() {
+            let start_time = deletion_events[left].timestamp;
+            let mut right = left + 1;
+            while right < deletion_events.len()
+                && deletion_events[right].timestamp - start_time <= config.bulk_window
             {
-                continue;
-            }
-
-            for event in deletion_events.iter().skip(window_start + 1) {
-                if event.timestamp - start_time <= Duration::minutes(5) {
-                    files_in_window.push(event.file_id);
-                } else {
-                    break;
-                }
+                right += 1;
             }
 
-            // If 5+ files deleted within 5 minutes, flag as bulk deletion
-            if files_in_window.len() >= 5 {
-                let end_time = deletion_events
-                    .get(window_start + files_in_window.len() - 1)
-                    .map(|e| e.timestamp)
-                    .unwrap_or(start_time + Duration::minutes(5));
-
+            if right - left >= config.bulk_threshold {
+                let burst = &deletion_events[left..right];
                 patterns.push(DeletionPattern {
                     pattern_type: PatternType::BulkDeletion,
                     confidence: 0.9,
-                    affected_files: files_in_window.clone(),
-                    timeframe: (start_time, end_time),
+                    affected_files: burst.iter().map(|e| e.file_id).collect(),
+                    timeframe: (start_time, burst[burst.len() - 1].timestamp),
                     description: format!(
-                        "{} files deleted within 5 minutes starting at {}",
-                        files_in_window.len(),
+                        "{} files deleted within {} starting at {}",
+                        burst.len(),
+                        format_period(config.bulk_window.num_seconds() as f64),
                         start_time.format("%Y-%m-%d %H:%M:%S")
                     ),
                 });
-
-                processed_windows.push((window_start, files_in_window.len()));
+                left = right;
+            } else {
+                left += 1;
             }
         }
 
-        // Pattern 2: Selective deletion by file type
+        // Pattern 2: Periodic deletion (e.g. cleanup scripts, log rotation)
+        if let Some(pattern) = Self::detect_periodic_deletion(&deletion_events) {
+            patterns.push(pattern);
+        }
+
+        // Pattern 3: Suspicious activity (anti-forensic signals combined)
+        if let Some(pattern) =
+            Self::detect_suspicious_activity(&deletion_events, files, scan_time, &patterns)
+        {
+            patterns.push(pattern);
+        }
+
+        // Pattern 4: Selective deletion by file type
         let mut type_deletions: HashMap<String, Vec<u64>> = HashMap::new();
 
         for event in deletion_events {
@@ -243,6 +332,221 @@ impl RecoveryTimeline {
         patterns
     }
 
+    /// Detect deletions that recur at a regular interval
+    ///
+    /// Deletions close together are first grouped into bursts, so one run of a
+    /// cleanup script counts once. The intervals between burst starts must then
+    /// be long enough and regular enough (low coefficient of variation).
+    fn detect_periodic_deletion(deletion_events: &[&TimelineEntry]) -> Option<DeletionPattern> {
+        // Deletions this close together belong to the same run
+        let burst_gap = Duration::minutes(5);
+        // At least this many runs (so 3 intervals) before calling it periodic
+        const MIN_BURSTS: usize = 4;
+        // Intervals shorter than this are bulk activity, not a schedule
+        const MIN_PERIOD_SECS: f64 = 3600.0;
+        // Maximum standard deviation of the intervals relative to their mean
+        const MAX_VARIATION: f64 = 0.2;
+
+        let mut bursts: Vec<(DateTime<Utc>, Vec<u64>)> = Vec::new();
+        let mut last_time: Option<DateTime<Utc>> = None;
+        for event in deletion_events {
+            match bursts.last_mut() {
+                Some((_, ids)) if last_time.is_some_and(|t| event.timestamp - t <= burst_gap) => {
+                    ids.push(event.file_id)
+                }
+                _ => bursts.push((event.timestamp, vec![event.file_id])),
+            }
+            last_time = Some(event.timestamp);
+        }
+
+        if bursts.len() < MIN_BURSTS {
+            return None;
+        }
+
+        let intervals: Vec<f64> = bursts
+            .windows(2)
+            .map(|pair| (pair[1].0 - pair[0].0).num_seconds() as f64)
+            .collect();
+        let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+        if mean < MIN_PERIOD_SECS {
+            return None;
+        }
+
+        let variance =
+            intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
+        let variation = variance.sqrt() / mean;
+        if variation > MAX_VARIATION {
+            return None;
+        }
+
+        let start_time = bursts.first()?.0;
+        let end_time = deletion_events.last()?.timestamp;
+        let affected_files: Vec<u64> = bursts.into_iter().flat_map(|(_, ids)| ids).collect();
+
+        Some(DeletionPattern {
+            pattern_type: PatternType::PeriodicDeletion,
+            confidence: (1.0 - variation) as f32,
+            description: format!(
+                "{} files deleted in {} runs roughly every {} starting at {}",
+                affected_files.len(),
+                intervals.len() + 1,
+                format_period(mean),
+                start_time.format("%Y-%m-%d %H:%M:%S")
+            ),
+            affected_files,
+            timeframe: (start_time, end_time),
+        })
+    }
+
+    /// Combine anti-forensic signals into a single suspicious activity pattern
+    ///
+    /// Each signal contributes a weight and the weights are combined as
+    /// independent evidence, so several weak signals together score higher
+    /// than any one of them alone:
+    /// - metadata-less (signature-only) files carved from the blocks of a bulk
+    ///   deletion, suggesting the metadata was wiped after deleting
+    /// - a burst of deletions shortly before the scan
+    /// - images and documents deleted together within a tight window
+    fn detect_suspicious_activity(
+        deletion_events: &[&TimelineEntry],
+        files: &[DeletedFile],
+        scan_time: DateTime<Utc>,
+        patterns: &[DeletionPattern],
+    ) -> Option<DeletionPattern> {
+        const WIPED_METADATA_WEIGHT: f32 = 0.6;
+        const PRE_SCAN_WEIGHT: f32 = 0.5;
+        const SENSITIVE_TYPES_WEIGHT: f32 = 0.4;
+        const MIN_WIPED_FILES: usize = 3;
+        const MIN_PRE_SCAN_DELETIONS: usize = 5;
+        const MIN_SENSITIVE_PER_KIND: usize = 2;
+
+        let by_id: HashMap<u64, &DeletedFile> = files.iter().map(|f| (f.id, f)).collect();
+        let mut evidence: Vec<String> = Vec::new();
+        let mut weights: Vec<f32> = Vec::new();
+        let mut affected: Vec<u64> = Vec::new();
+        let mut times: Vec<DateTime<Utc>> = Vec::new();
+
+        // Signal 1: signature-only files living in the blocks a bulk deletion freed
+        for bulk in patterns
+            .iter()
+            .filter(|p| p.pattern_type == PatternType::BulkDeletion)
+        {
+            let blocks = bulk
+                .affected_files
+                .iter()
+                .filter_map(|id| by_id.get(id))
+                .flat_map(|f| &f.data_blocks);
+            let region = blocks.fold(None, |region: Option<(u64, u64)>, r| {
+                let end = r.start_block + r.block_count;
+                Some(match region {
+                    Some((start, stop)) => (start.min(r.start_block), stop.max(end)),
+                    None => (r.start_block, end),
+                })
+            });
+            let Some((region_start, region_end)) = region else {
+                continue;
+            };
+
+            let wiped: Vec<u64> = files
+                .iter()
+                .filter(|f| f.deletion_time.is_none() && f.original_path.is_none())
+                .filter(|f| {
+                    f.data_blocks.iter().any(|r| {
+                        r.start_block < region_end && r.start_block + r.block_count > region_start
+                    })
+                })
+                .map(|f| f.id)
+                .collect();
+
+            if wiped.len() >= MIN_WIPED_FILES {
+                evidence.push(format!(
+                    "{} metadata-less files found in blocks {}-{} freed by a bulk deletion at {}",
+                    wiped.len(),
+                    region_start,
+                    region_end - 1,
+                    bulk.timeframe.0.format("%Y-%m-%d %H:%M:%S")
+                ));
+                weights.push(WIPED_METADATA_WEIGHT);
+                affected.extend(&bulk.affected_files);
+                affected.extend(wiped);
+                times.extend([bulk.timeframe.0, bulk.timeframe.1]);
+                break;
+            }
+        }
+
+        // Signal 2: deletions clustered right before the scan
+        let pre_scan: Vec<&&TimelineEntry> = deletion_events
+            .iter()
+            .filter(|e| e.timestamp <= scan_time && scan_time - e.timestamp <= Duration::hours(1))
+            .collect();
+        if pre_scan.len() >= MIN_PRE_SCAN_DELETIONS {
+            evidence.push(format!(
+                "{} files deleted within the hour before the scan",
+                pre_scan.len()
+            ));
+            weights.push(PRE_SCAN_WEIGHT);
+            affected.extend(pre_scan.iter().map(|e| e.file_id));
+            times.extend(pre_scan.iter().map(|e| e.timestamp));
+        }
+
+        // Signal 3: images and documents deleted together within a tight window
+        let sensitive: Vec<(&TimelineEntry, bool)> = deletion_events
+            .iter()
+            .filter_map(|e| {
+                let mime = by_id.get(&e.file_id)?.metadata.mime_type.as_deref()?;
+                if mime.starts_with("image/") {
+                    Some((*e, true))
+                } else if is_document_mime(mime) {
+                    Some((*e, false))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for start in 0..sensitive.len() {
+            let window_end = sensitive[start].0.timestamp + Duration::minutes(10);
+            let window: Vec<_> = sensitive[start..]
+                .iter()
+                .take_while(|(e, _)| e.timestamp <= window_end)
+                .collect();
+            let images = window.iter().filter(|(_, is_image)| *is_image).count();
+            let documents = window.len() - images;
+            if images >= MIN_SENSITIVE_PER_KIND && documents >= MIN_SENSITIVE_PER_KIND {
+                evidence.push(format!(
+                    "{} images and {} documents deleted within 10 minutes of {}",
+                    images,
+                    documents,
+                    sensitive[start].0.timestamp.format("%Y-%m-%d %H:%M:%S")
+                ));
+                weights.push(SENSITIVE_TYPES_WEIGHT);
+                affected.extend(window.iter().map(|(e, _)| e.file_id));
+                times.extend(window.iter().map(|(e, _)| e.timestamp));
+                break;
+            }
+        }
+
+        if evidence.is_empty() {
+            return None;
+        }
+
+        affected.sort_unstable();
+        affected.dedup();
+        let confidence = 1.0 - weights.iter().map(|w| 1.0 - w).product::<f32>();
+        let timeframe = (*times.iter().min()?, *times.iter().max()?);
+
+        Some(DeletionPattern {
+            pattern_type: PatternType::SuspiciousActivity,
+            confidence,
+            affected_files: affected,
+            timeframe,
+            description: format!(
+                "{} anti-forensic signal(s): {}",
+                evidence.len(),
+                evidence.join("; ")
+            ),
+        })
+    }
+
     /// Calculate timeline statistics
     fn calculate_statistics(events: &[TimelineEntry], files: &[DeletedFile]) -> TimelineStatistics {
         let deletion_events: Vec<_> = events
@@ -299,6 +603,12 @@ impl RecoveryTimeline {
         serde_json::to_string_pretty(self)
     }
 
+    /// The timeline of `session` as a JSON value with its `events`,
+    /// `patterns` and `statistics`, for a frontend to render
+    pub fn json_for_session(session: &RecoverySession) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(Self::from_session(session))
+    }
+
     /// Export timeline as CSV
     pub fn to_csv(&self) -> String {
         let mut csv = String::from("Timestamp,Event Type,File ID,Description\n");
@@ -402,6 +712,174 @@ impl RecoveryTimeline {
 
         report
     }
+
+    /// Export timeline as a standalone HTML report
+    ///
+    /// The page embeds its own stylesheet so it can be shared as a single
+    /// file. Filenames and descriptions may come from an attacker-controlled
+    /// filesystem, so all of them are escaped.
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>GhostFS Recovery Timeline</title>\n",
+        );
+        html.push_str(HTML_STYLE);
+        html.push_str("</head>\n<body>\n<h1>Recovery Timeline Analysis</h1>\n");
+
+        // Statistics
+        html.push_str("<h2>Statistics</h2>\n<div class=\"stats\">\n");
+        let mut stats = vec![
+            ("Total events", self.statistics.total_events.to_string()),
+            (
+                "Deletion events",
+                self.statistics.deletion_events.to_string(),
+            ),
+            (
+                "Avg deletions/day",
+                format!("{:.1}", self.statistics.average_deletions_per_day),
+            ),
+        ];
+        if let Some(peak) = self.statistics.peak_deletion_time {
+            stats.push((
+                "Peak deletion time",
+                peak.format("%Y-%m-%d %H:%M:%S").to_string(),
+            ));
+        }
+        for (label, value) in stats {
+            html.push_str(&format!(
+                "<div class=\"stat\"><span class=\"value\">{}</span>\
+                 <span class=\"label\">{}</span></div>\n",
+                escape_html(&value),
+                label
+            ));
+        }
+        html.push_str("</div>\n");
+
+        if !self.statistics.file_types_affected.is_empty() {
+            html.push_str(
+                "<h2>File Types Affected</h2>\n<table>\n\
+                 <tr><th>Type</th><th>Files</th></tr>\n",
+            );
+            let mut types: Vec<_> = self.statistics.file_types_affected.iter().collect();
+            types.sort_by_key(|(mime_type, count)| (std::cmp::Reverse(*count), *mime_type));
+            for (mime_type, count) in types {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(mime_type),
+                    count
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        // Patterns
+        if !self.patterns.is_empty() {
+            html.push_str(
+                "<h2>Suspicious Patterns Detected</h2>\n<table class=\"patterns\">\n\
+                 <tr><th>#</th><th>Pattern</th><th>Confidence</th><th>Description</th>\
+                 <th>Timeframe</th><th>Affected files</th></tr>\n",
+            );
+            for (i, pattern) in self.patterns.iter().enumerate() {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{:?}</td><td>{:.0}%</td><td>{}</td>\
+                     <td>{} to {}</td><td>{}</td></tr>\n",
+                    i + 1,
+                    pattern.pattern_type,
+                    pattern.confidence * 100.0,
+                    escape_html(&pattern.description),
+                    pattern.timeframe.0.format("%Y-%m-%d %H:%M:%S"),
+                    pattern.timeframe.1.format("%Y-%m-%d %H:%M:%S"),
+                    pattern.affected_files.len()
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        // Event timeline
+        html.push_str("<h2>Event Timeline</h2>\n<ol class=\"events\">\n");
+        for event in &self.events {
+            let (icon, class) = match event.event_type {
+                TimelineEventType::FileCreated => ("📝", "created"),
+                TimelineEventType::FileModified => ("✏️", "modified"),
+                TimelineEventType::FileDeleted => ("🗑️", "deleted"),
+                TimelineEventType::FileRecovered => ("✅", "recovered"),
+            };
+            html.push_str(&format!(
+                "<li class=\"{}\"><time datetime=\"{}\">{}</time>\
+                 <span class=\"icon\">{}</span>{}</li>\n",
+                class,
+                event.timestamp.to_rfc3339(),
+                event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                icon,
+                escape_html(&event.description)
+            ));
+        }
+        html.push_str("</ol>\n</body>\n</html>\n");
+
+        html
+    }
+}
+
+/// Inline stylesheet for the HTML report
+const HTML_STYLE: &str = "<style>
+body { font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; margin: 2em auto; max-width: 1100px; color: #222; }
+h1 { border-bottom: 2px solid #444; padding-bottom: 0.3em; }
+h2 { margin-top: 1.5em; color: #444; }
+.stats { display: flex; flex-wrap: wrap; gap: 1em; }
+.stat { background: #f4f6f8; border-radius: 6px; padding: 0.8em 1.2em; min-width: 10em; }
+.stat .value { display: block; font-size: 1.4em; font-weight: bold; }
+.stat .label { color: #666; font-size: 0.9em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #ddd; padding: 0.4em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f4f6f8; }
+.patterns td:nth-child(3) { font-weight: bold; }
+.events { list-style: none; padding: 0; }
+.events li { padding: 0.3em 0; border-bottom: 1px solid #eee; word-break: break-all; }
+.events time { font-family: monospace; color: #666; margin-right: 0.8em; }
+.events .icon { margin-right: 0.5em; }
+.events .deleted { color: #a40000; }
+</style>
+";
+
+/// Escape text for inclusion in HTML element content or attribute values
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Office and PDF documents, the usual targets alongside images
+fn is_document_mime(mime: &str) -> bool {
+    mime == "application/pdf"
+        || mime == "application/msword"
+        || mime == "application/rtf"
+        || mime.starts_with("application/vnd.openxmlformats-officedocument")
+        || mime.starts_with("application/vnd.oasis.opendocument")
+        || mime.starts_with("application/vnd.ms-")
+}
+
+/// Format an interval in seconds as the nearest whole days, hours or minutes
+fn format_period(seconds: f64) -> String {
+    const DAY: f64 = 86_400.0;
+    const HOUR: f64 = 3_600.0;
+
+    let days = (seconds / DAY).round();
+    if days >= 1.0 && (seconds - days * DAY).abs() < 0.1 * DAY {
+        format!("{:.0}d", days)
+    } else if seconds >= HOUR {
+        format!("{:.0}h", seconds / HOUR)
+    } else {
+        format!("{:.0}m", seconds / 60.0)
+    }
 }
 
 #[cfg(test)]
@@ -427,7 +905,9 @@ mod tests {
                 scan_duration_ms: 0,
                 files_found: 0,
                 recoverable_files: 0,
+                filesystem_health: None,
             },
+            case_metadata: None,
         };
 
         let timeline = RecoveryTimeline::from_session(&session);
@@ -456,12 +936,15 @@ mod tests {
                     permissions: None,
                     owner_uid: None,
                     owner_gid: None,
+                    owner_name: None,
+                    group_name: None,
                     created_time: Some(now - Duration::days(1)),
                     modified_time: Some(now - Duration::hours(1)),
                     accessed_time: None,
                     extended_attributes: HashMap::new(),
                 },
                 fs_metadata: None,
+                skip_reason: None,
             },
             DeletedFile {
                 id: 2,
@@ -479,12 +962,15 @@ mod tests {
                     permissions: None,
                     owner_uid: None,
                     owner_gid: None,
+                    owner_name: None,
+                    group_name: None,
                     created_time: Some(now - Duration::days(2)),
                     modified_time: Some(now - Duration::hours(2)),
                     accessed_time: None,
                     extended_attributes: HashMap::new(),
                 },
                 fs_metadata: None,
+                skip_reason: None,
             },
         ];
 
@@ -503,7 +989,9 @@ mod tests {
                 scan_duration_ms: 100,
                 files_found: 2,
                 recoverable_files: 2,
+                filesystem_health: None,
             },
+            case_metadata: None,
         };
 
         let timeline = RecoveryTimeline::from_session(&session);
@@ -517,6 +1005,394 @@ mod tests {
             .contains_key("text/plain"));
     }
 
+    fn deleted_at(id: u64, deletion_time: DateTime<Utc>) -> DeletedFile {
+        DeletedFile {
+            id,
+            inode_or_cluster: 100 + id,
+            original_path: Some(PathBuf::from(format!("/var/log/app.log.{}", id))),
+            size: 4096,
+            deletion_time: Some(deletion_time),
+            confidence_score: 0.8,
+            file_type: FileType::RegularFile,
+            data_blocks: vec![],
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        }
+    }
+
+    fn timeline_of(files: Vec<DeletedFile>) -> RecoveryTimeline {
+        RecoveryTimeline::from_session(&session_of(files))
+    }
+
+    fn session_of(files: Vec<DeletedFile>) -> RecoverySession {
+        RecoverySession {
+            id: uuid::Uuid::new_v4(),
+            device_path: PathBuf::from("/dev/test"),
+            fs_type: crate::FileSystemType::Xfs,
+            created_at: Utc::now(),
+            total_scanned: files.len() as u64,
+            scan_results: files,
+            confidence_threshold: 0.5,
+            metadata: crate::SessionMetadata {
+                device_size: 1024 * 1024 * 1024,
+                filesystem_size: 1024 * 1024 * 1024,
+                block_size: 4096,
+                scan_duration_ms: 0,
+                files_found: 0,
+                recoverable_files: 0,
+                filesystem_health: None,
+            },
+            case_metadata: None,
+        }
+    }
+
+    /// Files 0-4 deleted a day apart from 2024-03-01, and file 5 at an unknown time
+    fn daily_deletions() -> (DateTime<Utc>, Vec<DeletedFile>) {
+        let start = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut files: Vec<_> = (0..5)
+            .map(|id| deleted_at(id, start + Duration::days(id as i64)))
+            .collect();
+        let mut unknown = deleted_at(5, start);
+        unknown.deletion_time = None;
+        files.push(unknown);
+        (start, files)
+    }
+
+    #[test]
+    fn test_deletion_window_filters_by_time() {
+        let (start, files) = daily_deletions();
+        let kept = |window: DeletionWindow| -> Vec<u64> {
+            files
+                .iter()
+                .filter(|f| window.contains(f))
+                .map(|f| f.id)
+                .collect()
+        };
+
+        let window = DeletionWindow {
+            after: Some(start + Duration::days(1)),
+            before: Some(start + Duration::days(3)),
+            exclude_unknown: false,
+        };
+        // After is inclusive, before exclusive
+        assert_eq!(kept(window), vec![1, 2, 5]);
+        assert_eq!(
+            kept(DeletionWindow {
+                exclude_unknown: true,
+                ..window
+            }),
+            vec![1, 2]
+        );
+        assert_eq!(
+            kept(DeletionWindow {
+                after: Some(start + Duration::days(3)),
+                ..Default::default()
+            }),
+            vec![3, 4, 5]
+        );
+        assert!(DeletionWindow::default().is_unbounded());
+        assert_eq!(kept(DeletionWindow::default()), vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_deletion_window_applies_to_session_and_timeline() {
+        let (start, files) = daily_deletions();
+        let window = DeletionWindow {
+            before: Some(start + Duration::days(2)),
+            exclude_unknown: true,
+            ..Default::default()
+        };
+
+        let session = session_of(files);
+        let timeline =
+            RecoveryTimeline::from_session_in_window(&session, &PatternConfig::default(), &window);
+        let mut ids: Vec<u64> = timeline.events.iter().map(|e| e.file_id).collect();
+        ids.dedup();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(timeline.statistics.deletion_events, 2);
+
+        let mut filtered = session.clone();
+        window.apply(&mut filtered);
+        let ids: Vec<u64> = filtered.scan_results.iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(filtered.metadata.files_found, 2);
+        assert_eq!(filtered.metadata.recoverable_files, 2);
+    }
+
+    #[test]
+    fn test_periodic_deletion_detected() {
+        let start = DateTime::parse_from_rfc3339("2024-03-01T03:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        // A nightly job removing two files a few seconds apart, with some jitter
+        let jitter = [0, 40, -25, 10, 55, -30];
+        let files: Vec<_> = jitter
+            .iter()
+            .enumerate()
+            .flat_map(|(day, &jitter)| {
+                let run = start + Duration::days(day as i64) + Duration::seconds(jitter);
+                let id = day as u64 * 2;
+                [
+                    deleted_at(id, run),
+                    deleted_at(id + 1, run + Duration::seconds(3)),
+                ]
+            })
+            .collect();
+
+        let timeline = timeline_of(files);
+        let periodic: Vec<_> = timeline
+            .patterns
+            .iter()
+            .filter(|p| p.pattern_type == PatternType::PeriodicDeletion)
+            .collect();
+
+        assert_eq!(periodic.len(), 1);
+        assert_eq!(periodic[0].affected_files.len(), 12);
+        assert!(periodic[0].confidence > 0.9);
+        assert!(periodic[0].description.contains("every 1d"));
+    }
+
+    #[test]
+    fn test_weekly_deletion_period_reported() {
+        let start = Utc::now() - Duration::days(60);
+        let files = (0..5)
+            .map(|week| deleted_at(week, start + Duration::weeks(week as i64)))
+            .collect();
+
+        let timeline = timeline_of(files);
+        let pattern = timeline
+            .patterns
+            .iter()
+            .find(|p| p.pattern_type == PatternType::PeriodicDeletion)
+            .unwrap();
+        assert!(pattern.description.contains("every 7d"));
+    }
+
+    #[test]
+    fn test_irregular_deletions_not_periodic() {
+        let start = Utc::now() - Duration::days(30);
+        let offsets_hours = [0, 3, 50, 52, 140, 400];
+        let files = offsets_hours
+            .iter()
+            .enumerate()
+            .map(|(id, &hours)| deleted_at(id as u64, start + Duration::hours(hours)))
+            .collect();
+
+        let timeline = timeline_of(files);
+        assert!(!timeline
+            .patterns
+            .iter()
+            .any(|p| p.pattern_type == PatternType::PeriodicDeletion));
+    }
+
+    #[test]
+    fn test_suspicious_activity_combines_signals() {
+        let burst = Utc::now() - Duration::minutes(20);
+        let mimes = ["image/jpeg", "application/pdf"];
+
+        // Six photos and PDFs wiped in one burst just before the scan
+        let mut files: Vec<_> = (0..6)
+            .map(|id| {
+                let mut file = deleted_at(id, burst + Duration::seconds(id as i64 * 20));
+                file.metadata.mime_type = Some(mimes[id as usize % 2].to_string());
+                file.data_blocks = vec![crate::BlockRange {
+                    start_block: 1000 + id * 10,
+                    block_count: 10,
+                    is_allocated: false,
+                }];
+                file
+            })
+            .collect();
+
+        // Signature-only hits in the same region: their metadata is gone
+        files.extend((0..4).map(|i| {
+            let mut file = deleted_at(100 + i, burst);
+            file.deletion_time = None;
+            file.original_path = None;
+            file.data_blocks = vec![crate::BlockRange {
+                start_block: 1005 + i * 10,
+                block_count: 4,
+                is_allocated: false,
+            }];
+            file
+        }));
+
+        let timeline = timeline_of(files);
+        let suspicious: Vec<_> = timeline
+            .patterns
+            .iter()
+            .filter(|p| p.pattern_type == PatternType::SuspiciousActivity)
+            .collect();
+
+        assert_eq!(suspicious.len(), 1);
+        let pattern = suspicious[0];
+        assert!(pattern.description.starts_with("3 anti-forensic signal(s)"));
+        assert!(pattern.description.contains("4 metadata-less files"));
+        assert!(pattern.description.contains("before the scan"));
+        assert!(pattern.description.contains("3 images and 3 documents"));
+        assert!(pattern.confidence > 0.85);
+        assert_eq!(pattern.affected_files.len(), 10);
+    }
+
+    #[test]
+    fn test_routine_deletions_not_suspicious() {
+        let start = Utc::now() - Duration::days(10);
+        let files = (0..6)
+            .map(|id| {
+                let mut file = deleted_at(id, start + Duration::hours(id as i64 * 7));
+                file.metadata.mime_type = Some("text/plain".to_string());
+                file
+            })
+            .collect();
+
+        let timeline = timeline_of(files);
+        assert!(!timeline
+            .patterns
+            .iter()
+            .any(|p| p.pattern_type == PatternType::SuspiciousActivity));
+    }
+
+    fn bulk_patterns(timeline: &RecoveryTimeline) -> Vec<&DeletionPattern> {
+        timeline
+            .patterns
+            .iter()
+            .filter(|p| p.pattern_type == PatternType::BulkDeletion)
+            .collect()
+    }
+
+    #[test]
+    fn test_back_to_back_bursts() {
+        let start = Utc::now() - Duration::days(3);
+
+        // Two bursts of six, the second starting six minutes after the first
+        let files = (0..12)
+            .map(|id| {
+                let burst = Duration::minutes(6 * (id as i64 / 6));
+                deleted_at(id, start + burst + Duration::seconds(id as i64 % 6 * 10))
+            })
+            .collect();
+
+        let timeline = timeline_of(files);
+        let bulk = bulk_patterns(&timeline);
+
+        assert_eq!(bulk.len(), 2);
+        assert_eq!(bulk[0].affected_files, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(bulk[1].affected_files, vec![6, 7, 8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn test_long_burst_not_double_counted() {
+        let start = Utc::now() - Duration::days(3);
+
+        // One deletion every 30 seconds for 10 minutes
+        let files = (0..20)
+            .map(|id| deleted_at(id, start + Duration::seconds(id as i64 * 30)))
+            .collect();
+
+        let timeline = timeline_of(files);
+        let bulk = bulk_patterns(&timeline);
+
+        let mut counted: Vec<u64> = bulk
+            .iter()
+            .flat_map(|p| p.affected_files.iter().copied())
+            .collect();
+        let total = counted.len();
+        counted.sort_unstable();
+        counted.dedup();
+        assert_eq!(counted.len(), total, "a file was counted in two bursts");
+        assert_eq!(total, 20);
+        assert_eq!(bulk.len(), 2);
+    }
+
+    #[test]
+    fn test_pattern_config_thresholds() {
+        let start = Utc::now() - Duration::days(3);
+        let files: Vec<_> = (0..3)
+            .map(|id| deleted_at(id, start + Duration::seconds(id as i64 * 20)))
+            .collect();
+
+        // Three deletions are below the default threshold of five
+        assert!(bulk_patterns(&timeline_of(files.clone())).is_empty());
+
+        let mut session = RecoverySession {
+            id: uuid::Uuid::new_v4(),
+            device_path: PathBuf::from("/dev/test"),
+            fs_type: crate::FileSystemType::Xfs,
+            created_at: Utc::now(),
+            scan_results: files,
+            total_scanned: 3,
+            confidence_threshold: 0.5,
+            metadata: crate::SessionMetadata {
+                device_size: 1024 * 1024 * 1024,
+                filesystem_size: 1024 * 1024 * 1024,
+                block_size: 4096,
+                scan_duration_ms: 0,
+                files_found: 3,
+                recoverable_files: 3,
+                filesystem_health: None,
+            },
+            case_metadata: None,
+        };
+        let config = PatternConfig {
+            bulk_window: Duration::minutes(1),
+            bulk_threshold: 3,
+        };
+        let timeline = RecoveryTimeline::from_session_with_config(&session, &config);
+        let bulk = bulk_patterns(&timeline);
+        assert_eq!(bulk.len(), 1);
+        assert!(bulk[0].description.contains("within 1m"));
+
+        // Spread beyond the window: not a burst any more
+        session.scan_results[2].deletion_time = Some(start + Duration::minutes(2));
+        let timeline = RecoveryTimeline::from_session_with_config(&session, &config);
+        assert!(bulk_patterns(&timeline).is_empty());
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("📸 vacation🌴.jpg"), "📸 vacation🌴.jpg");
+    }
+
+    #[test]
+    fn test_json_for_session() {
+        let (start, files) = daily_deletions();
+        let session = session_of(files);
+
+        let json = RecoveryTimeline::json_for_session(&session).unwrap();
+        assert_eq!(json["statistics"]["deletion_events"], 5);
+        assert_eq!(
+            json["events"][0]["timestamp"],
+            serde_json::to_value(start).unwrap()
+        );
+        assert!(json["patterns"].is_array());
+
+        let timeline: RecoveryTimeline = serde_json::from_value(json).unwrap();
+        let expected = RecoveryTimeline::from_session(&session);
+        assert_eq!(timeline.events.len(), expected.events.len());
+        assert_eq!(timeline.patterns.len(), expected.patterns.len());
+    }
+
     #[test]
     fn test_csv_export() {
         let session = RecoverySession {
@@ -534,7 +1410,9 @@ mod tests {
                 scan_duration_ms: 0,
                 files_found: 0,
                 recoverable_files: 0,
+                filesystem_health: None,
             },
+            case_metadata: None,
         };
 
         let timeline = RecoveryTimeline::from_session(&session);
@@ -0,0 +1,138 @@
+//! Best-effort recovery from devices with unreadable regions
+
+mod common;
+
+use ghostfs_core::fs::common::{map_read_only, BlockDevice, FileReads, RangeSet, RetryPolicy};
+use ghostfs_core::{
+    recover_files_from_device, DamagedRange, DeletedFile, FileSystemType, OutputLayout,
+    RecoverySession, RecoveryStatus,
+};
+use std::time::Duration;
+
+const BLOCK_SIZE: usize = common::BLOCK_SIZE as usize;
+
+/// A file of `blocks` blocks starting at `start_block`
+fn deleted_file(id: u64, name: &str, start_block: u64, blocks: u64) -> DeletedFile {
+    common::file(id)
+        .path(name)
+        .blocks(start_block, blocks)
+        .build()
+}
+
+fn session(scan_results: Vec<DeletedFile>, blocks: usize) -> RecoverySession {
+    common::session(
+        FileSystemType::Xfs,
+        "/dev/failing",
+        (blocks * BLOCK_SIZE) as u64,
+        scan_results,
+    )
+}
+
+#[test]
+fn test_unreadable_blocks_are_zero_filled() {
+    // Eight blocks, each filled with 1 + its block number
+    let image: Vec<u8> = (0..8u8).flat_map(|block| [block + 1; BLOCK_SIZE]).collect();
+    // Half of block 2 and the first sector of block 3 are unreadable
+    let bad_start = 2 * BLOCK_SIZE as u64 + 2048;
+    let device = BlockDevice::from_bytes(image)
+        .with_unreadable_regions(RangeSet::from_ranges([(bad_start, 2048 + 512)]));
+    let session = session(
+        vec![
+            deleted_file(1, "damaged.bin", 1, 4),
+            deleted_file(2, "intact.bin", 5, 2),
+        ],
+        8,
+    );
+    let out = tempfile::tempdir().unwrap();
+
+    let report =
+        recover_files_from_device(&device, &session, out.path(), None, OutputLayout::Flat).unwrap();
+    assert_eq!(report.recovered_files, 2);
+    assert_eq!(report.partial_files, 1);
+    assert_eq!(report.failed_files, 0);
+
+    let damaged = &report.recovery_details[0];
+    let expected = vec![DamagedRange {
+        offset: BLOCK_SIZE as u64 + 2048,
+        length: 2048 + 512,
+    }];
+    match &damaged.status {
+        RecoveryStatus::Partial(ranges) => assert_eq!(ranges, &expected),
+        status => panic!("expected a partial recovery, got {:?}", status),
+    }
+    assert_eq!(damaged.bytes_recovered, 4 * BLOCK_SIZE as u64 - 2560);
+
+    // The readable data is all there, at its original offsets
+    let data = std::fs::read(out.path().join("damaged.bin")).unwrap();
+    assert_eq!(data.len(), 4 * BLOCK_SIZE);
+    assert!(data[..BLOCK_SIZE + 2048]
+        .iter()
+        .enumerate()
+        .all(|(i, &b)| { b == if i < BLOCK_SIZE { 2 } else { 3 } }));
+    assert!(data[BLOCK_SIZE + 2048..2 * BLOCK_SIZE + 512]
+        .iter()
+        .all(|&b| b == 0));
+    assert!(data[2 * BLOCK_SIZE + 512..3 * BLOCK_SIZE]
+        .iter()
+        .all(|&b| b == 4));
+    assert!(data[3 * BLOCK_SIZE..].iter().all(|&b| b == 5));
+
+    assert!(matches!(
+        report.recovery_details[1].status,
+        RecoveryStatus::Success
+    ));
+    // Block 2's second half and block 3's first sector
+    assert_eq!(report.read_errors.bad_sectors, vec![20, 21, 22, 23, 24]);
+    let intact = std::fs::read(out.path().join("intact.bin")).unwrap();
+    assert_eq!(intact, [[6; BLOCK_SIZE], [7; BLOCK_SIZE]].concat());
+}
+
+#[test]
+fn test_read_errors_of_the_image_are_zero_filled() {
+    let image: Vec<u8> = (0..8u8).flat_map(|block| [block + 1; BLOCK_SIZE]).collect();
+    let image_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(image_file.path(), &image).unwrap();
+    // Read through the file the way a block device is read
+    let (_file, mmap, _) = map_read_only(image_file.path()).unwrap();
+    let reads = FileReads::from_paths(&[image_file.path().to_path_buf()]).unwrap();
+    let device = BlockDevice::from_mmap(mmap)
+        .with_fault_source(reads)
+        .with_retry_policy(RetryPolicy {
+            attempts: 1,
+            backoff: Duration::ZERO,
+        });
+    // The image loses its last two blocks while mapped, so reading them
+    // fails the way a dying disk does rather than through a simulated fault
+    image_file.as_file().set_len(6 * BLOCK_SIZE as u64).unwrap();
+
+    let session = session(vec![deleted_file(1, "tail.bin", 4, 4)], 8);
+    let out = tempfile::tempdir().unwrap();
+
+    let report =
+        recover_files_from_device(&device, &session, out.path(), None, OutputLayout::Flat).unwrap();
+    assert_eq!(report.partial_files, 1);
+    let expected = vec![DamagedRange {
+        offset: 2 * BLOCK_SIZE as u64,
+        length: 2 * BLOCK_SIZE as u64,
+    }];
+    match &report.recovery_details[0].status {
+        RecoveryStatus::Partial(ranges) => assert_eq!(ranges, &expected),
+        status => panic!("expected a partial recovery, got {:?}", status),
+    }
+
+    let data = std::fs::read(out.path().join("tail.bin")).unwrap();
+    assert_eq!(
+        data,
+        [
+            [5; BLOCK_SIZE],
+            [6; BLOCK_SIZE],
+            [0; BLOCK_SIZE],
+            [0; BLOCK_SIZE]
+        ]
+        .concat()
+    );
+    assert_eq!(
+        report.read_errors.bad_sectors,
+        (48..64).collect::<Vec<u64>>()
+    );
+}
@@ -0,0 +1,137 @@
+//! Fixtures shared by the integration tests of ghostfs-core
+
+// Each test binary uses a different part of these fixtures
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use ghostfs_core::{
+    BlockRange, DeletedFile, FileMetadata, FileSystemType, FileType, RecoverySession,
+    SessionMetadata,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Block size of the sessions built here
+pub const BLOCK_SIZE: u64 = 4096;
+
+/// Builds a [`DeletedFile`], starting from a recoverable one-block file
+pub struct FileBuilder {
+    file: DeletedFile,
+}
+
+/// A recoverable regular file `id` of one block at block `id`
+pub fn file(id: u64) -> FileBuilder {
+    FileBuilder {
+        file: DeletedFile {
+            id,
+            inode_or_cluster: 100 + id,
+            original_path: None,
+            size: BLOCK_SIZE,
+            deletion_time: None,
+            confidence_score: 0.9,
+            file_type: FileType::RegularFile,
+            data_blocks: vec![BlockRange {
+                start_block: id,
+                block_count: 1,
+                is_allocated: false,
+            }],
+            is_recoverable: true,
+            metadata: FileMetadata {
+                mime_type: None,
+                file_extension: None,
+                permissions: None,
+                owner_uid: None,
+                owner_gid: None,
+                owner_name: None,
+                group_name: None,
+                created_time: None,
+                modified_time: None,
+                accessed_time: None,
+                extended_attributes: HashMap::new(),
+            },
+            fs_metadata: None,
+            skip_reason: None,
+        },
+    }
+}
+
+impl FileBuilder {
+    pub fn path(mut self, path: &str) -> Self {
+        self.file.original_path = Some(PathBuf::from(path));
+        self
+    }
+
+    /// `count` blocks from `start_block`, which the file fills
+    pub fn blocks(mut self, start_block: u64, count: u64) -> Self {
+        self.file.data_blocks = vec![BlockRange {
+            start_block,
+            block_count: count,
+            is_allocated: false,
+        }];
+        self.file.size = count * BLOCK_SIZE;
+        self
+    }
+
+    /// `size` bytes, keeping the blocks
+    pub fn size(mut self, size: u64) -> Self {
+        self.file.size = size;
+        self
+    }
+
+    pub fn mime(mut self, mime_type: &str) -> Self {
+        self.file.metadata.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    pub fn extension(mut self, extension: &str) -> Self {
+        self.file.metadata.file_extension = Some(extension.to_string());
+        self
+    }
+
+    pub fn confidence(mut self, confidence_score: f32) -> Self {
+        self.file.confidence_score = confidence_score;
+        self
+    }
+
+    pub fn recoverable(mut self, is_recoverable: bool) -> Self {
+        self.file.is_recoverable = is_recoverable;
+        self
+    }
+
+    pub fn deleted_at(mut self, deletion_time: DateTime<Utc>) -> Self {
+        self.file.deletion_time = Some(deletion_time);
+        self
+    }
+
+    pub fn build(self) -> DeletedFile {
+        self.file
+    }
+}
+
+/// A session of `scan_results`, created now, on a device of `device_size` bytes
+pub fn session(
+    fs_type: FileSystemType,
+    device_path: impl Into<PathBuf>,
+    device_size: u64,
+    scan_results: Vec<DeletedFile>,
+) -> RecoverySession {
+    RecoverySession {
+        id: uuid::Uuid::new_v4(),
+        fs_type,
+        device_path: device_path.into(),
+        created_at: Utc::now(),
+        total_scanned: scan_results.len() as u64,
+        confidence_threshold: 0.4,
+        metadata: SessionMetadata {
+            device_size,
+            filesystem_size: device_size,
+            block_size: BLOCK_SIZE as u32,
+            scan_duration_ms: 10,
+            files_found: scan_results.len() as u32,
+            recoverable_files: scan_results.len() as u32,
+            filesystem_health: None,
+        },
+        scan_results,
+        case_metadata: None,
+    }
+}
@@ -0,0 +1,59 @@
+//! User-defined signatures loaded from a definitions file
+
+use ghostfs_core::fs::common::BlockDevice;
+use ghostfs_core::recovery::signatures::{detect_file_type, estimate_file_size};
+use ghostfs_core::recovery::{load_definitions, SignatureSet};
+use ghostfs_core::{carve_device, CarveConfig};
+use std::io::Write;
+
+const MIME: &str = "application/x-ghostfs-test";
+
+fn test_signatures() -> SignatureSet {
+    let mut definitions = tempfile::NamedTempFile::new().unwrap();
+    definitions
+        .write_all(
+            br#"[{"magic": "47 48 53 54 46 53", "mime_type": "application/x-ghostfs-test",
+                  "extensions": ["gfst"], "description": "GhostFS test file",
+                  "trailer": "47 45 4E 44"}]"#,
+        )
+        .unwrap();
+    SignatureSet::new(load_definitions(definitions.path()).unwrap())
+}
+
+#[test]
+fn test_custom_signature_matches_crafted_buffer() {
+    let signatures = test_signatures();
+    let mut data = vec![0u8; 4096];
+    data[..6].copy_from_slice(b"GHSTFS");
+    data[2000..2004].copy_from_slice(b"GEND");
+
+    assert_eq!(
+        signatures.detect_file_type(&data[..512]),
+        Some((MIME.to_string(), "gfst".to_string()))
+    );
+    assert_eq!(signatures.estimate_file_size(&data, 0, MIME), Some(2004));
+
+    // The built-in signatures alone do not know the type
+    assert_eq!(detect_file_type(&data[..512]), None);
+    assert_eq!(estimate_file_size(&data, 0, MIME), None);
+    assert_eq!(SignatureSet::default().detect_file_type(&data[..512]), None);
+}
+
+#[test]
+fn test_carving_uses_the_configured_signatures() {
+    let mut image = vec![0u8; 64 * 1024];
+    image[8192..8198].copy_from_slice(b"GHSTFS");
+    image[8192 + 3000..8192 + 3004].copy_from_slice(b"GEND");
+    let device = BlockDevice::from_bytes(image);
+
+    assert!(carve_device(&device, &CarveConfig::default()).is_empty());
+
+    let config = CarveConfig {
+        signatures: test_signatures(),
+        ..Default::default()
+    };
+    let files = carve_device(&device, &config);
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].metadata.mime_type.as_deref(), Some(MIME));
+    assert_eq!(files[0].size, 3004);
+}
@@ -4,7 +4,7 @@ use ghostfs_core::recovery::{
     BtrfsDirEntry, BtrfsDirReconstructor, DirectoryReconstructor, ExFatDirReconstructor,
     XfsDirEntry, XfsDirReconstructor,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[test]
 fn test_xfs_path_reconstruction_integration() {
@@ -162,3 +162,56 @@ fn test_directory_reconstructor_trait() {
     assert_eq!(exfat_reconstructor.stats().total_entries, 0);
     assert_eq!(exfat_reconstructor.stats().root_id, Some(5));
 }
+
+#[test]
+fn test_xfs_build_tree() {
+    let mut reconstructor = XfsDirReconstructor::new(4096);
+    reconstructor.set_root_inode(64);
+
+    // / (64)
+    //   ├── etc/ (70)
+    //   │   └── fstab (71)
+    //   └── home/ (100)
+    //       └── user/ (200)
+    //           └── file.txt (250)
+    // plus a file whose directory was never found (parent 999)
+    // and two directories whose parent links point at each other
+    let entry = |inode, name: &str, file_type, parent_inode| XfsDirEntry {
+        inode,
+        name: name.to_string(),
+        file_type,
+        parent_inode,
+        source_block: 0,
+    };
+    reconstructor.add_entries(vec![
+        entry(70, "etc", 2, 64),
+        entry(71, "fstab", 1, 70),
+        entry(100, "home", 2, 64),
+        entry(200, "user", 2, 100),
+        entry(250, "file.txt", 1, 200),
+        entry(500, "stray.log", 1, 999),
+        entry(600, "loop_a", 2, 601),
+        entry(601, "loop_b", 2, 600),
+    ]);
+
+    let tree = reconstructor.build_tree();
+    assert_eq!(tree.total_entries, 8);
+    assert_eq!(tree.orphaned_entries, 3);
+
+    let top: Vec<_> = tree.root.children.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(top, vec!["etc", "home", "lost+found"]);
+    assert_eq!(
+        tree.get(Path::new("/home/user/file.txt")).unwrap().id,
+        Some(250)
+    );
+    assert!(tree.get(Path::new("/etc/fstab")).is_some());
+
+    let lost_and_found = tree.get(Path::new("/lost+found")).unwrap();
+    assert_eq!(lost_and_found.id, None);
+    assert!(tree.get(Path::new("/lost+found/stray.log")).is_some());
+    // The cycle is cut: each looping directory appears exactly once
+    assert_eq!(lost_and_found.descendant_count(), 3);
+
+    let json = tree.to_json().unwrap();
+    assert!(json.contains("\"lost+found\""));
+}
@@ -0,0 +1,241 @@
+//! Output paths of recovered files
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+use ghostfs_core::{
+    recover_files, sanitize_original_path, DeletedFile, FileSystemType, OutputLayout,
+    RecoverySession,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const BLOCK_SIZE: usize = common::BLOCK_SIZE as usize;
+
+/// A one-block file at `block`, whose image block is filled with `block`
+fn deleted_file(id: u64, original_path: Option<&str>, block: u64) -> DeletedFile {
+    let file = common::file(id)
+        .blocks(block, 1)
+        .mime("image/jpeg")
+        .extension("jpg");
+    match original_path {
+        Some(path) => file.path(path),
+        None => file,
+    }
+    .build()
+}
+
+/// An image with one block per file, each filled with its block number
+fn image_and_session(scan_results: Vec<DeletedFile>) -> (tempfile::NamedTempFile, RecoverySession) {
+    let blocks = scan_results.len();
+    let mut image = tempfile::NamedTempFile::new().unwrap();
+    for block in 0..blocks as u8 {
+        image.write_all(&[block; BLOCK_SIZE]).unwrap();
+    }
+    image.flush().unwrap();
+
+    let session = common::session(
+        FileSystemType::Xfs,
+        image.path(),
+        (blocks * BLOCK_SIZE) as u64,
+        scan_results,
+    );
+    (image, session)
+}
+
+/// Every file below `dir`, relative to it
+fn files_below(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(current).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path.strip_prefix(dir).unwrap().to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn test_colliding_names_are_written_distinctly() {
+    let (image, session) = image_and_session(vec![
+        deleted_file(1, Some("/DCIM/photo.jpg"), 0),
+        // Same name and directory, e.g. a file deleted and recreated
+        deleted_file(2, Some("/DCIM/photo.jpg"), 1),
+        deleted_file(3, Some("/Backup/photo.jpg"), 2),
+        deleted_file(4, None, 3),
+    ]);
+    let out = tempfile::tempdir().unwrap();
+
+    let report = recover_files(
+        image.path(),
+        &session,
+        out.path(),
+        None,
+        OutputLayout::Original,
+    )
+    .unwrap();
+    assert_eq!(report.recovered_files, 4);
+
+    for (path, block) in [
+        ("DCIM/photo.jpg", 0u8),
+        ("DCIM/photo_001.jpg", 1),
+        ("Backup/photo.jpg", 2),
+        ("recovered_file_4.jpg", 3),
+    ] {
+        let data = std::fs::read(out.path().join(path)).unwrap();
+        assert_eq!(data, vec![block; BLOCK_SIZE], "{}", path);
+    }
+
+    // Recovering only the second file still gives it the suffixed name
+    let out = tempfile::tempdir().unwrap();
+    recover_files(
+        image.path(),
+        &session,
+        out.path(),
+        Some(vec![2]),
+        OutputLayout::Original,
+    )
+    .unwrap();
+    assert!(out.path().join("DCIM/photo_001.jpg").is_file());
+    assert!(!out.path().join("DCIM/photo.jpg").exists());
+}
+
+#[test]
+fn test_sanitize_original_path() {
+    let sanitize = |path: &str| sanitize_original_path(Path::new(path));
+
+    assert_eq!(
+        sanitize("../../etc/passwd"),
+        Some(PathBuf::from("etc/passwd"))
+    );
+    assert_eq!(sanitize("/abs/path"), Some(PathBuf::from("abs/path")));
+    assert_eq!(sanitize("a/./b/../c"), Some(PathBuf::from("a/b/c")));
+    assert_eq!(
+        sanitize("..\\..\\Windows\\win.ini"),
+        Some(PathBuf::from(".._.._Windows_win.ini"))
+    );
+    assert_eq!(sanitize("C:x|y?.txt"), Some(PathBuf::from("C_x_y_.txt")));
+    assert_eq!(
+        sanitize("bad\0name\n.jpg"),
+        Some(PathBuf::from("bad_name_.jpg"))
+    );
+    assert_eq!(
+        sanitize("docs/.../notes.txt"),
+        Some(PathBuf::from("docs/notes.txt"))
+    );
+    assert_eq!(sanitize(&"x".repeat(300)).unwrap().as_os_str().len(), 255);
+    assert_eq!(sanitize("/../.."), None);
+}
+
+#[test]
+fn test_malicious_original_paths_stay_inside_output() {
+    let (image, session) = image_and_session(vec![
+        deleted_file(1, Some("../../etc/passwd"), 0),
+        deleted_file(2, Some("/abs/path"), 1),
+        deleted_file(3, Some("sub/../../../escape.txt"), 2),
+        deleted_file(4, Some("/.."), 3),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("out");
+
+    let report = recover_files(image.path(), &session, &out, None, OutputLayout::Original).unwrap();
+    assert_eq!(report.recovered_files, 4);
+
+    // Nothing was written next to the output directory
+    assert_eq!(files_below(dir.path()).len(), 4);
+    assert_eq!(
+        files_below(&out),
+        vec![
+            PathBuf::from("abs/path"),
+            PathBuf::from("etc/passwd"),
+            PathBuf::from("recovered_file_4.jpg"),
+            PathBuf::from("sub/escape.txt"),
+        ]
+    );
+    for detail in &report.recovery_details {
+        assert!(detail.recovered_path.starts_with(&out));
+    }
+}
+
+#[test]
+fn test_output_layouts() {
+    let mut files = vec![
+        deleted_file(1, Some("/DCIM/photo.jpg"), 0),
+        deleted_file(2, Some("/Backup/photo.jpg"), 1),
+        deleted_file(3, Some("/docs/report.pdf"), 2),
+        deleted_file(4, None, 3),
+    ];
+    files[0].deletion_time = Some(Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap());
+    files[1].metadata.modified_time = Some(Utc.with_ymd_and_hms(2024, 2, 29, 8, 0, 0).unwrap());
+    files[2].metadata.mime_type = Some("application/pdf".to_string());
+    files[2].metadata.file_extension = Some("pdf".to_string());
+    files[3].metadata.mime_type = None;
+    let (image, session) = image_and_session(files);
+
+    for (layout, expected) in [
+        (
+            OutputLayout::Flat,
+            vec![
+                "photo.jpg",
+                "photo_001.jpg",
+                "recovered_file_4.jpg",
+                "report.pdf",
+            ],
+        ),
+        (
+            OutputLayout::ByType,
+            vec![
+                "application/report.pdf",
+                "image/photo.jpg",
+                "image/photo_001.jpg",
+                "unknown/recovered_file_4.jpg",
+            ],
+        ),
+        (
+            OutputLayout::ByDate,
+            vec![
+                "2024-02-29/photo.jpg",
+                "2024-03-01/photo.jpg",
+                "undated/recovered_file_4.jpg",
+                "undated/report.pdf",
+            ],
+        ),
+        (
+            OutputLayout::Original,
+            vec![
+                "Backup/photo.jpg",
+                "DCIM/photo.jpg",
+                "docs/report.pdf",
+                "recovered_file_4.jpg",
+            ],
+        ),
+    ] {
+        let out = tempfile::tempdir().unwrap();
+        let report = recover_files(image.path(), &session, out.path(), None, layout).unwrap();
+        assert_eq!(report.recovered_files, 4, "{:?}", layout);
+
+        let expected: Vec<PathBuf> = expected.into_iter().map(PathBuf::from).collect();
+        assert_eq!(files_below(out.path()), expected, "{:?}", layout);
+    }
+
+    // Each file keeps its own content whatever the layout
+    let out = tempfile::tempdir().unwrap();
+    recover_files(image.path(), &session, out.path(), None, OutputLayout::Flat).unwrap();
+    let data = std::fs::read(out.path().join("photo_001.jpg")).unwrap();
+    assert_eq!(data, vec![1u8; BLOCK_SIZE]);
+}
+
+#[test]
+fn test_output_layout_names() {
+    for layout in OutputLayout::all() {
+        assert_eq!(layout.name().parse::<OutputLayout>(), Ok(layout));
+    }
+    assert_eq!("BY-TYPE".parse::<OutputLayout>(), Ok(OutputLayout::ByType));
+    assert!("tree".parse::<OutputLayout>().is_err());
+}
@@ -0,0 +1,150 @@
+//! Carving files from a device with no file system metadata
+
+use ghostfs_core::fs::common::BlockDevice;
+use ghostfs_core::recovery::{carve_device, write_carved_file, CarveConfig};
+
+/// A small baseline JPEG: SOI, a JFIF APP0 segment, scan data and EOI
+fn jpeg() -> Vec<u8> {
+    let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+    data.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+    data.extend((0..3000u32).map(|i| (i % 200) as u8 + 1));
+    data.extend_from_slice(&[0xFF, 0xD9]);
+    data
+}
+
+/// A PNG with an IHDR chunk, some IDAT bytes and IEND with its CRC
+fn png() -> Vec<u8> {
+    let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+    data.extend_from_slice(b"\0\0\0\x0dIHDR\0\0\0\x10\0\0\0\x10\x08\x02\0\0\0\x90\x91\x68\x36");
+    data.extend_from_slice(b"\0\0\x04\0IDAT");
+    data.extend((0..1024u32).map(|i| (i % 97) as u8 + 3));
+    data.extend_from_slice(b"\x12\x34\x56\x78");
+    data.extend_from_slice(b"\0\0\0\0IEND\xae\x42\x60\x82");
+    data
+}
+
+fn pdf() -> Vec<u8> {
+    let mut data = b"%PDF-1.4\n1 0 obj\n<< /Type /Catalog >>\nendobj\n".to_vec();
+    data.extend_from_slice(b"trailer\n<< /Root 1 0 R >>\n%%EOF");
+    data
+}
+
+/// Zeroed image holding the test files at the given offsets
+fn image_with(files: &[(usize, &[u8])], size: usize) -> Vec<u8> {
+    let mut image = vec![0u8; size];
+    for (offset, data) in files {
+        image[*offset..*offset + data.len()].copy_from_slice(data);
+    }
+    image
+}
+
+#[test]
+fn test_carve_known_files_from_metadata_free_buffer() {
+    let (jpeg, png, pdf) = (jpeg(), png(), pdf());
+    let image = image_with(
+        &[(4096, &jpeg), (3 * 65536 + 512, &png), (300_032, &pdf)],
+        512 * 1024,
+    );
+    let device = BlockDevice::from_bytes(image);
+
+    let files = carve_device(&device, &CarveConfig::default());
+    let found: Vec<(u64, u64, &str)> = files
+        .iter()
+        .map(|f| {
+            (
+                f.data_blocks[0].start_block,
+                f.size,
+                f.metadata.mime_type.as_deref().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (4096, jpeg.len() as u64, "image/jpeg"),
+            (3 * 65536 + 512, png.len() as u64, "image/png"),
+            (300_032, pdf.len() as u64, "application/pdf"),
+        ]
+    );
+    assert_eq!(
+        files.iter().map(|f| f.id).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+
+    for (file, expected) in files.iter().zip([&jpeg, &png, &pdf]) {
+        let mut out = Vec::new();
+        let written = write_carved_file(&device, file, &mut out).unwrap();
+        assert_eq!(written, expected.len() as u64);
+        assert_eq!(&out, expected);
+    }
+}
+
+#[test]
+fn test_carving_honours_alignment() {
+    let jpeg = jpeg();
+    // Not on a 512-byte boundary
+    let image = image_with(&[(4096 + 100, &jpeg)], 64 * 1024);
+    let device = BlockDevice::from_bytes(image);
+
+    assert!(carve_device(&device, &CarveConfig::default()).is_empty());
+
+    let config = CarveConfig {
+        alignment: 4,
+        ..Default::default()
+    };
+    let files = carve_device(&device, &config);
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].data_blocks[0].start_block, 4196);
+    assert_eq!(files[0].size, jpeg.len() as u64);
+}
+
+#[test]
+fn test_unaligned_trailer_matches_rejected() {
+    let pdf = pdf();
+    // Found by its trailer, but starting off any sector boundary
+    let image = image_with(&[(1000, &pdf)], 64 * 1024);
+    let device = BlockDevice::from_bytes(image);
+
+    assert!(carve_device(&device, &CarveConfig::default()).is_empty());
+
+    let config = CarveConfig {
+        alignment: 8,
+        ..Default::default()
+    };
+    let files = carve_device(&device, &config);
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].data_blocks[0].start_block, 1000);
+}
+
+#[test]
+fn test_exfat_carving_aligns_to_cluster_heap() {
+    // exFAT boot sector: 512-byte sectors, 4KiB clusters, heap at sector 65
+    let mut image = vec![0u8; 256 * 1024];
+    image[3..11].copy_from_slice(b"EXFAT   ");
+    image[88..92].copy_from_slice(&65u32.to_le_bytes());
+    image[108] = 9;
+    image[109] = 3;
+    image[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    let heap = 65 * 512;
+    let jpeg = jpeg();
+    // One JPEG on a cluster boundary, one on a sector boundary inside a cluster
+    image[heap + 4096..heap + 4096 + jpeg.len()].copy_from_slice(&jpeg);
+    image[heap + 16384 + 512..heap + 16384 + 512 + jpeg.len()].copy_from_slice(&jpeg);
+    let device = BlockDevice::from_bytes(image);
+
+    let config = CarveConfig::for_device(&device);
+    assert_eq!(
+        (config.alignment, config.alignment_origin),
+        (4096, heap as u64)
+    );
+
+    let starts: Vec<u64> = carve_device(&device, &config)
+        .iter()
+        .map(|f| f.data_blocks[0].start_block)
+        .collect();
+    assert_eq!(starts, vec![heap as u64 + 4096]);
+
+    // Sector alignment finds both
+    assert_eq!(carve_device(&device, &CarveConfig::default()).len(), 2);
+}
@@ -0,0 +1,67 @@
+//! Raw block device access through a loopback device
+//!
+//! Needs root and `losetup`, so it only runs with
+//! `cargo test --features loop-device-tests` on Linux.
+#![cfg(all(target_os = "linux", feature = "loop-device-tests"))]
+
+use ghostfs_core::fs::common::{
+    ensure_not_mounted_writable, find_writable_mount, is_block_device, BlockDevice,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Read-only loop device that is detached on drop
+struct LoopDevice {
+    path: PathBuf,
+}
+
+impl LoopDevice {
+    fn attach(image: &Path) -> Self {
+        let output = Command::new("losetup")
+            .args(["--find", "--show", "--read-only"])
+            .arg(image)
+            .output()
+            .expect("failed to run losetup");
+        assert!(
+            output.status.success(),
+            "losetup failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Self {
+            path: PathBuf::from(String::from_utf8(output.stdout).unwrap().trim()),
+        }
+    }
+}
+
+impl Drop for LoopDevice {
+    fn drop(&mut self) {
+        let _ = Command::new("losetup").arg("-d").arg(&self.path).status();
+    }
+}
+
+#[test]
+fn test_open_loop_device_read_only() {
+    const IMAGE_SIZE: usize = 8 * 1024 * 1024;
+
+    let mut image = tempfile::NamedTempFile::new().unwrap();
+    let mut data = vec![0u8; IMAGE_SIZE];
+    data[..4].copy_from_slice(b"XFSB");
+    data[IMAGE_SIZE - 4..].copy_from_slice(b"TAIL");
+    image.write_all(&data).unwrap();
+    image.flush().unwrap();
+
+    let loop_device = LoopDevice::attach(image.path());
+    assert!(is_block_device(&loop_device.path).unwrap());
+
+    // The metadata length of a block device is 0; the size must come from the kernel
+    let device = BlockDevice::open(&loop_device.path).unwrap();
+    assert_eq!(device.size(), IMAGE_SIZE as u64);
+    assert_eq!(device.read_at(0, 4).unwrap(), b"XFSB");
+    assert_eq!(device.read_at(IMAGE_SIZE as u64 - 4, 4).unwrap(), b"TAIL");
+
+    // An unmounted device is safe to scan without --force
+    assert!(find_writable_mount(&loop_device.path).unwrap().is_none());
+    ensure_not_mounted_writable(&loop_device.path, false).unwrap();
+}
@@ -0,0 +1,59 @@
+//! Dry-run recovery planning
+
+mod common;
+
+use ghostfs_core::{plan_recovery, DeletedFile, FileSystemType, OutputLayout, RecoverySession};
+
+fn deleted_file(id: u64, name: &str, size: u64, is_recoverable: bool) -> DeletedFile {
+    common::file(id)
+        .path(&format!("/home/user/{}", name))
+        .blocks(id * 16, size.div_ceil(common::BLOCK_SIZE))
+        .size(size)
+        .mime("image/jpeg")
+        .extension("jpg")
+        .recoverable(is_recoverable)
+        .build()
+}
+
+fn session(scan_results: Vec<DeletedFile>) -> RecoverySession {
+    common::session(
+        FileSystemType::Xfs,
+        "/images/disk.img",
+        1024 * 1024,
+        scan_results,
+    )
+}
+
+#[test]
+fn test_dry_run_reports_totals_without_writing() {
+    let session = session(vec![
+        deleted_file(1, "a.jpg", 1000, true),
+        deleted_file(2, "b.jpg", 5000, true),
+        // Same name from another directory
+        deleted_file(3, "a.jpg", 3000, true),
+        deleted_file(4, "skipped.jpg", 7000, false),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("recovered");
+
+    let plan = plan_recovery(&session, &out, None, OutputLayout::Original).unwrap();
+
+    let ids: Vec<u64> = plan.entries.iter().map(|e| e.file_id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(plan.total_bytes, 9000);
+    assert_eq!(plan.collisions, 0);
+    assert_eq!(plan.entries[1].output_path, out.join("home/user/b.jpg"));
+    assert_eq!(plan.entries[2].output_path, out.join("home/user/a_001.jpg"));
+    assert!(plan.space_ok);
+    assert!(!out.exists());
+
+    // An explicit selection includes unrecoverable files, and existing files collide
+    let user_dir = out.join("home/user");
+    std::fs::create_dir_all(&user_dir).unwrap();
+    std::fs::write(user_dir.join("skipped.jpg"), b"already here").unwrap();
+    let plan = plan_recovery(&session, &out, Some(vec![2, 4]), OutputLayout::Original).unwrap();
+    assert_eq!(plan.total_bytes, 12000);
+    assert_eq!(plan.collisions, 1);
+    assert!(plan.entries[1].collides);
+    assert_eq!(std::fs::read_dir(&user_dir).unwrap().count(), 1);
+}
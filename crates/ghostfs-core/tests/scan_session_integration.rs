@@ -0,0 +1,299 @@
+//! Integration tests for scanning an image into a RecoverySession
+
+use ghostfs_core::session::rescan;
+use ghostfs_core::{
+    scan_and_analyze, scan_and_analyze_with_options, scan_and_analyze_with_options_and_progress,
+    scan_and_analyze_with_progress, scan_image, FileSystemType, RecoveryProgress, RecoverySession,
+    RecoveryStage, RecoveryStrategy, ScanOptions,
+};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Build an image with a JPEG header at the start of every 1MB chunk
+fn write_fixture_image(megabytes: usize) -> tempfile::NamedTempFile {
+    let mut image = vec![0u8; megabytes * 1024 * 1024];
+    for chunk in image.chunks_mut(1024 * 1024) {
+        chunk[..10].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F']);
+    }
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&image).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// Build an empty exFAT volume with a JPEG header at the start of every 1MB chunk after the first
+fn write_exfat_fixture_image(megabytes: usize) -> tempfile::NamedTempFile {
+    let size = megabytes * 1024 * 1024;
+    let mut image = vec![0u8; size];
+    image[3..11].copy_from_slice(b"EXFAT   ");
+    image[72..80].copy_from_slice(&(size as u64 / 512).to_le_bytes()); // volume length
+    image[80..84].copy_from_slice(&8u32.to_le_bytes()); // FAT offset (sectors)
+    image[84..88].copy_from_slice(&32u32.to_le_bytes()); // FAT length (sectors)
+    image[88..92].copy_from_slice(&64u32.to_le_bytes()); // cluster heap offset (sectors)
+    image[92..96].copy_from_slice(&(size as u32 / 4096 - 1).to_le_bytes());
+    image[96..100].copy_from_slice(&2u32.to_le_bytes()); // root directory
+    image[108] = 9; // 512-byte sectors
+    image[109] = 3; // 4KiB clusters
+    image[110] = 1; // one FAT
+    image[112] = 10; // percent in use
+    image[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    for chunk in image.chunks_mut(1024 * 1024).skip(1) {
+        chunk[..10].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F']);
+    }
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&image).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+#[test]
+fn test_scan_records_cluster_size() {
+    let image = write_exfat_fixture_image(4);
+    let mut data = std::fs::read(image.path()).unwrap();
+    data[109] = 7; // 64KiB clusters, as on most SD cards
+    std::fs::write(image.path(), &data).unwrap();
+
+    let session = scan_and_analyze(image.path(), FileSystemType::ExFat).unwrap();
+
+    assert_eq!(session.metadata.block_size, 64 * 1024);
+    assert_eq!(
+        scan_image(image.path(), FileSystemType::ExFat)
+            .unwrap()
+            .metadata
+            .block_size,
+        64 * 1024
+    );
+}
+
+#[test]
+fn test_scan_records_duration() {
+    let image = write_fixture_image(16);
+
+    let started = std::time::Instant::now();
+    let session = scan_and_analyze(image.path(), FileSystemType::ExFat).unwrap();
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+
+    // A small image may scan in under a millisecond, but never take longer
+    // than the call did
+    assert!(
+        session.metadata.scan_duration_ms <= elapsed_ms,
+        "scan took {}ms of a {}ms call",
+        session.metadata.scan_duration_ms,
+        elapsed_ms
+    );
+}
+
+#[test]
+fn test_scan_duration_survives_session_round_trip() {
+    let image = write_fixture_image(16);
+    let session = scan_and_analyze(image.path(), FileSystemType::ExFat).unwrap();
+
+    let json = serde_json::to_string(&session).unwrap();
+    let loaded: RecoverySession = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(loaded.id, session.id);
+    assert_eq!(
+        loaded.metadata.scan_duration_ms,
+        session.metadata.scan_duration_ms
+    );
+}
+
+#[test]
+fn test_scan_forwards_engine_progress() {
+    let image = write_fixture_image(33);
+    let updates: Arc<Mutex<Vec<RecoveryProgress>>> = Arc::default();
+    let sink = updates.clone();
+
+    let session = scan_and_analyze_with_progress(image.path(), FileSystemType::ExFat, move |p| {
+        sink.lock().unwrap().push(p)
+    })
+    .unwrap();
+
+    let updates = updates.lock().unwrap();
+    let last = updates.last().unwrap();
+    assert_eq!(last.stage, RecoveryStage::Complete);
+    assert_eq!(last.files_found as usize, session.scan_results.len());
+
+    // The signature scan reports real byte counts and an ETA
+    let scanning: Vec<_> = updates
+        .iter()
+        .filter(|p| p.stage == RecoveryStage::SignatureScanning && p.bytes_processed > 0)
+        .collect();
+    assert!(scanning.len() >= 2);
+    assert!(scanning
+        .iter()
+        .all(|p| p.estimated_time_remaining.is_some()));
+}
+
+#[test]
+fn test_disabling_signature_scan_skips_signature_only_files() {
+    let image = write_exfat_fixture_image(4);
+    let is_jpeg = |session: &RecoverySession| {
+        session
+            .scan_results
+            .iter()
+            .any(|f| f.metadata.mime_type.as_deref() == Some("image/jpeg"))
+    };
+
+    let default_scan =
+        scan_and_analyze_with_options(image.path(), FileSystemType::ExFat, ScanOptions::default())
+            .unwrap();
+    assert!(is_jpeg(&default_scan));
+
+    let options = ScanOptions {
+        strategies: Some(vec![
+            RecoveryStrategy::DirectoryTableScan,
+            RecoveryStrategy::JournalAnalysis,
+        ]),
+        ..Default::default()
+    };
+    let session =
+        scan_and_analyze_with_options(image.path(), FileSystemType::ExFat, options).unwrap();
+    assert!(!is_jpeg(&session));
+    assert!(session.scan_results.is_empty());
+}
+
+#[test]
+fn test_scan_requires_a_strategy() {
+    let image = write_exfat_fixture_image(1);
+    let options = ScanOptions {
+        strategies: Some(Vec::new()),
+        ..Default::default()
+    };
+
+    assert!(scan_and_analyze_with_options(image.path(), FileSystemType::ExFat, options).is_err());
+}
+
+/// The exFAT fixture with each JPEG ending after 64KB
+fn write_bounded_exfat_fixture_image(megabytes: usize) -> tempfile::NamedTempFile {
+    let image = write_exfat_fixture_image(megabytes);
+    let mut data = std::fs::read(image.path()).unwrap();
+    for chunk in data.chunks_mut(1024 * 1024).skip(1) {
+        chunk[64 * 1024 - 2..64 * 1024].copy_from_slice(&[0xFF, 0xD9]);
+    }
+    std::fs::write(image.path(), &data).unwrap();
+    image
+}
+
+#[test]
+fn test_rescan_of_unchanged_image_finds_nothing_new() {
+    let image = write_bounded_exfat_fixture_image(4);
+    let first =
+        scan_and_analyze_with_options(image.path(), FileSystemType::ExFat, ScanOptions::default())
+            .unwrap();
+
+    let (second, report) = rescan(image.path(), &first, ScanOptions::default()).unwrap();
+
+    assert!(report.new_files.is_empty(), "{:?}", report);
+    assert!(report.disappeared.is_empty(), "{:?}", report);
+    assert_eq!(report.unchanged.len(), second.scan_results.len());
+    // The carved files were not carved a second time
+    assert!(report.skipped_bytes > 0);
+}
+
+#[test]
+fn test_rescan_reports_new_and_disappeared_files() {
+    let image = write_bounded_exfat_fixture_image(4);
+    let first =
+        scan_and_analyze_with_options(image.path(), FileSystemType::ExFat, ScanOptions::default())
+            .unwrap();
+
+    // The photo at 2MB is overwritten and a PNG appears in a previously empty area
+    let mut data = std::fs::read(image.path()).unwrap();
+    data[2 * 1024 * 1024..2 * 1024 * 1024 + 10].fill(0);
+    data[3 * 1024 * 1024 + 512 * 1024..3 * 1024 * 1024 + 512 * 1024 + 8]
+        .copy_from_slice(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]);
+    std::fs::write(image.path(), &data).unwrap();
+
+    let (second, report) = rescan(image.path(), &first, ScanOptions::default()).unwrap();
+
+    // The photo at 2MB was found by both the exFAT scan and the carver
+    assert_eq!(report.disappeared, vec![2, 5], "{:?}", report);
+    // The photos at 1MB and 3MB, each found by both as well
+    assert_eq!(report.unchanged.len(), 4, "{:?}", report);
+    assert_eq!(report.new_files.len(), 1, "{:?}", report);
+    let new_file = second
+        .scan_results
+        .iter()
+        .find(|f| f.id == report.new_files[0])
+        .unwrap();
+    assert_eq!(new_file.metadata.mime_type.as_deref(), Some("image/png"));
+}
+
+/// The exFAT fixture with the deleted entry of "holiday.jpg", the photo at 1MB,
+/// in its root directory
+fn write_exfat_fixture_with_deleted_entry(megabytes: usize) -> tempfile::NamedTempFile {
+    let image = write_exfat_fixture_image(megabytes);
+    let mut data = std::fs::read(image.path()).unwrap();
+    data[8 * 512 + 8..8 * 512 + 12].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes()); // root: one cluster
+
+    let name = "holiday.jpg";
+    let root = &mut data[64 * 512..64 * 512 + 96];
+    root[0] = 0x05; // deleted file entry
+    root[1] = 2; // secondary entries
+    root[32] = 0x40; // deleted stream extension
+    root[35] = name.len() as u8;
+    root[52..56].copy_from_slice(&250u32.to_le_bytes()); // the cluster at 1MB
+    root[56..64].copy_from_slice(&(64u64 * 1024).to_le_bytes());
+    root[64] = 0x41; // deleted file name
+    for (i, unit) in name.encode_utf16().enumerate() {
+        root[66 + i * 2..68 + i * 2].copy_from_slice(&unit.to_le_bytes());
+    }
+
+    std::fs::write(image.path(), &data).unwrap();
+    image
+}
+
+#[test]
+fn test_list_only_scan_names_files_from_metadata_alone() {
+    let image = write_exfat_fixture_with_deleted_entry(4);
+
+    let full =
+        scan_and_analyze_with_options(image.path(), FileSystemType::ExFat, ScanOptions::default())
+            .unwrap();
+    let holiday = |session: &RecoverySession| {
+        session
+            .scan_results
+            .iter()
+            .find(|f| f.original_path.as_deref() == Some(std::path::Path::new("holiday.jpg")))
+            .cloned()
+            .unwrap()
+    };
+    assert!(!holiday(&full).data_blocks.is_empty());
+
+    let stages: Arc<Mutex<Vec<RecoveryStage>>> = Arc::default();
+    let sink = stages.clone();
+    let options = ScanOptions {
+        list_only: true,
+        ..Default::default()
+    };
+    let listing = scan_and_analyze_with_options_and_progress(
+        image.path(),
+        FileSystemType::ExFat,
+        options,
+        move |p| sink.lock().unwrap().push(p.stage),
+    )
+    .unwrap();
+
+    // Only the deleted entry is listed, with its size but no extents
+    assert_eq!(listing.scan_results.len(), 1);
+    let file = holiday(&listing);
+    assert_eq!(file.size, 64 * 1024);
+    assert!(file.data_blocks.is_empty());
+
+    let stages = stages.lock().unwrap();
+    assert!(stages.contains(&RecoveryStage::DirectoryScanning));
+    for skipped in [
+        RecoveryStage::SignatureScanning,
+        RecoveryStage::JournalAnalysis,
+        RecoveryStage::MetadataReconstruction,
+        RecoveryStage::ConfidenceCalculation,
+        RecoveryStage::FinalValidation,
+    ] {
+        assert!(!stages.contains(&skipped), "{:?} ran", skipped);
+    }
+    assert_eq!(stages.last(), Some(&RecoveryStage::Complete));
+}
@@ -0,0 +1,62 @@
+//! Round-trip tests for portable session export/import
+
+use ghostfs_core::session::SessionManager;
+use ghostfs_core::{scan_and_analyze, FileSystemType};
+use std::io::Write;
+
+fn write_fixture_image() -> tempfile::NamedTempFile {
+    let mut image = vec![0u8; 4 * 1024 * 1024];
+    for chunk in image.chunks_mut(1024 * 1024) {
+        chunk[..10].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F']);
+    }
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&image).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+fn round_trip(export_name: &str) {
+    let dir = tempfile::tempdir().unwrap();
+    let image = write_fixture_image();
+    let session = scan_and_analyze(image.path(), FileSystemType::ExFat).unwrap();
+
+    let source = SessionManager::new(&dir.path().join("source.db")).unwrap();
+    source.database().save_session(&session).unwrap();
+
+    let export_path = dir.path().join(export_name);
+    source
+        .export_session(&session.id.to_string(), &export_path)
+        .unwrap();
+
+    let target = SessionManager::new(&dir.path().join("target.db")).unwrap();
+    let imported_id = target.import_session(&export_path).unwrap();
+    assert_eq!(imported_id, session.id);
+
+    let loaded = target
+        .database()
+        .load_session(&imported_id.to_string())
+        .unwrap();
+    assert_eq!(loaded.fs_type, session.fs_type);
+    assert_eq!(loaded.device_path, session.device_path);
+    assert_eq!(loaded.created_at, session.created_at);
+    assert_eq!(loaded.metadata.files_found, session.metadata.files_found);
+    assert_eq!(loaded.scan_results.len(), session.scan_results.len());
+    assert_eq!(
+        serde_json::to_value(&loaded.scan_results).unwrap(),
+        serde_json::to_value(&session.scan_results).unwrap()
+    );
+
+    // Importing the same file twice is a UUID conflict
+    assert!(target.import_session(&export_path).is_err());
+}
+
+#[test]
+fn test_export_import_round_trip() {
+    round_trip("session.json");
+}
+
+#[test]
+fn test_export_import_round_trip_gzip() {
+    round_trip("session.json.gz");
+}
@@ -0,0 +1,99 @@
+//! Memory bound test for streaming file recovery
+
+use ghostfs_core::fs::common::BlockDevice;
+use ghostfs_core::{
+    recover_file_streaming, BlockRange, DeletedFile, FileMetadata, FileSystemType, FileType,
+    STREAMING_WINDOW_SIZE,
+};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Discards what is written, remembering the largest single write
+#[derive(Default)]
+struct LargestWrite {
+    largest: usize,
+    total: u64,
+}
+
+impl Write for LargestWrite {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.largest = self.largest.max(buf.len());
+        self.total += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_streaming_recovery_writes_in_bounded_windows() {
+    const FILE_SIZE: u64 = 500 * 1024 * 1024;
+
+    // Sparse image: the blocks read back as zeros without using disk space
+    let image = tempfile::NamedTempFile::new().unwrap();
+    image.as_file().set_len(FILE_SIZE + 4096).unwrap();
+    let device = BlockDevice::open(image.path()).unwrap();
+
+    let deleted_file = DeletedFile {
+        id: 1,
+        inode_or_cluster: 131,
+        original_path: None,
+        size: FILE_SIZE,
+        deletion_time: None,
+        confidence_score: 0.9,
+        file_type: FileType::RegularFile,
+        data_blocks: vec![
+            BlockRange {
+                start_block: 1,
+                block_count: FILE_SIZE / 4096 / 2,
+                is_allocated: false,
+            },
+            BlockRange {
+                start_block: 1 + FILE_SIZE / 4096 / 2,
+                block_count: FILE_SIZE / 4096 / 2,
+                is_allocated: false,
+            },
+        ],
+        is_recoverable: true,
+        metadata: FileMetadata {
+            mime_type: Some("video/mp4".to_string()),
+            file_extension: Some("mp4".to_string()),
+            permissions: None,
+            owner_uid: None,
+            owner_gid: None,
+            owner_name: None,
+            group_name: None,
+            created_time: None,
+            modified_time: None,
+            accessed_time: None,
+            extended_attributes: HashMap::new(),
+        },
+        fs_metadata: None,
+        skip_reason: None,
+    };
+
+    // The device is a memory mapping, so what the windows bound is how much
+    // of the file is handed to the writer at once
+    let mut out = LargestWrite::default();
+    let mut damaged = Vec::new();
+    let written = recover_file_streaming(
+        &deleted_file,
+        &device,
+        FileSystemType::Xfs,
+        4096,
+        &mut out,
+        &mut damaged,
+    )
+    .unwrap();
+
+    assert_eq!(written, FILE_SIZE);
+    assert_eq!(out.total, FILE_SIZE);
+    assert!(damaged.is_empty());
+    assert!(
+        out.largest as u64 <= STREAMING_WINDOW_SIZE,
+        "streaming recovery wrote {} bytes at once",
+        out.largest
+    );
+}
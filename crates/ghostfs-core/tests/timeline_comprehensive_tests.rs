@@ -26,7 +26,9 @@ fn create_test_session(fs_type: FileSystemType, files: Vec<DeletedFile>) -> Reco
             scan_duration_ms: 100,
             files_found: files_count as u32,
             recoverable_files: files_count as u32,
+            filesystem_health: None,
         },
+        case_metadata: None,
     }
 }
 
@@ -55,12 +57,15 @@ fn create_file_with_timestamps(
             permissions: Some(0o644),
             owner_uid: Some(1000),
             owner_gid: Some(1000),
+            owner_name: None,
+            group_name: None,
             created_time: Some(created),
             modified_time: Some(modified),
             accessed_time: Some(deleted - Duration::hours(1)),
             extended_attributes: HashMap::new(),
         },
         fs_metadata: None,
+        skip_reason: None,
     }
 }
 
@@ -122,12 +127,15 @@ fn test_files_with_missing_timestamps() {
             permissions: None,
             owner_uid: None,
             owner_gid: None,
+            owner_name: None,
+            group_name: None,
             created_time: None,  // Missing
             modified_time: None, // Missing
             accessed_time: None,
             extended_attributes: HashMap::new(),
         },
         fs_metadata: None,
+        skip_reason: None,
     };
 
     // File with no timestamps at all
@@ -147,12 +155,15 @@ fn test_files_with_missing_timestamps() {
             permissions: None,
             owner_uid: None,
             owner_gid: None,
+            owner_name: None,
+            group_name: None,
             created_time: None,
             modified_time: None,
             accessed_time: None,
             extended_attributes: HashMap::new(),
         },
         fs_metadata: None,
+        skip_reason: None,
     };
 
     let session = create_test_session(FileSystemType::Xfs, vec![file1, file2]);
@@ -632,6 +643,55 @@ fn test_csv_export_formatting() {
     assert_eq!(lines.len(), 4); // Header + 3 events
 }
 
+#[test]
+fn test_html_export_escapes_filenames() {
+    let now = Utc::now();
+    let files = vec![
+        create_file_with_timestamps(
+            1,
+            "/tmp/<script>alert(\"pwned\")</script>.txt",
+            "text/plain",
+            now - Duration::days(1),
+            now - Duration::hours(1),
+            now,
+        ),
+        create_file_with_timestamps(
+            2,
+            "/home/user/📸Photos/Tom & Jerry's <b>.jpg",
+            "image/jpeg",
+            now - Duration::days(1),
+            now - Duration::hours(1),
+            now,
+        ),
+    ];
+
+    let session = create_test_session(FileSystemType::ExFat, files);
+    let html = RecoveryTimeline::from_session(&session).to_html();
+
+    // Attacker-controlled names never reach the page as markup
+    assert!(!html.contains("<script>"));
+    assert!(!html.contains("<b>"));
+    assert!(html.contains("&lt;script&gt;alert(&quot;pwned&quot;)&lt;/script&gt;"));
+    assert!(html.contains("Tom &amp; Jerry&#39;s &lt;b&gt;"));
+    assert!(html.contains("📸Photos"));
+
+    // Standalone document with balanced structure
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.trim_end().ends_with("</html>"));
+    assert!(html.contains("<style>"));
+    for tag in ["html", "head", "body", "table", "ol", "style"] {
+        assert_eq!(
+            html.matches(&format!("<{}", tag)).count(),
+            html.matches(&format!("</{}>", tag)).count(),
+            "unbalanced <{}>",
+            tag
+        );
+    }
+    assert_eq!(html.matches("<li ").count(), 6); // 2 files x 3 events
+    assert_eq!(html.matches("</li>").count(), 6);
+    assert!(html.contains("🗑️"));
+}
+
 #[test]
 fn test_json_export_structure() {
     let now = Utc::now();
@@ -54,12 +54,15 @@ fn create_ransomware_scenario(fs_type: FileSystemType, fs_name: &str) -> Recover
                 permissions: Some(0o644),
                 owner_uid: Some(1000),
                 owner_gid: Some(1000),
+                owner_name: None,
+                group_name: None,
                 created_time: Some(now - Duration::days(30) - Duration::hours(i as i64)),
                 modified_time: Some(now - Duration::days(1)),
                 accessed_time: Some(now - Duration::hours(3)),
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: None,
+            skip_reason: None,
         });
     }
 
@@ -78,7 +81,9 @@ fn create_ransomware_scenario(fs_type: FileSystemType, fs_name: &str) -> Recover
             scan_duration_ms: 8500,
             files_found: 50,
             recoverable_files: 50,
+            filesystem_health: None,
         },
+        case_metadata: None,
     }
 }
 
@@ -33,12 +33,15 @@ fn main() {
                 permissions: Some(0o644),
                 owner_uid: Some(1000),
                 owner_gid: Some(1000),
+                owner_name: None,
+                group_name: None,
                 created_time: Some(now - Duration::days(30)),
                 modified_time: Some(now - Duration::days(10)),
                 accessed_time: Some(now - Duration::days(3)),
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: None,
+            skip_reason: None,
         });
     }
 
@@ -63,12 +66,15 @@ fn main() {
                 permissions: Some(0o644),
                 owner_uid: Some(1000),
                 owner_gid: Some(1000),
+                owner_name: None,
+                group_name: None,
                 created_time: Some(now - Duration::days(7)),
                 modified_time: Some(now - Duration::days(1)),
                 accessed_time: Some(now - Duration::hours(8)),
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: None,
+            skip_reason: None,
         });
     }
 
@@ -90,12 +96,15 @@ fn main() {
                 permissions: Some(0o644),
                 owner_uid: Some(1000),
                 owner_gid: Some(1000),
+                owner_name: None,
+                group_name: None,
                 created_time: Some(now - Duration::days(20)),
                 modified_time: Some(now - Duration::days(6)),
                 accessed_time: Some(now - Duration::days(5)),
                 extended_attributes: HashMap::new(),
             },
             fs_metadata: None,
+            skip_reason: None,
         });
     }
 
@@ -115,7 +124,9 @@ fn main() {
             scan_duration_ms: 12500,
             files_found: 10,
             recoverable_files: 10,
+            filesystem_health: None,
         },
+        case_metadata: None,
     };
 
     // Generate timeline analysis